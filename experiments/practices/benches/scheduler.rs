@@ -0,0 +1,51 @@
+//! Benchmark: Scheduling throughput of the priority graph and greedy
+//! schedulers
+//!
+//! Analogy: Two hosts seat the same stream of parties, once when nobody
+//! cares where they sit, once when half of them want the window table and
+//! once when all of them do. Timing how many parties each host seats per
+//! second shows which one copes better as the window table gets popular.
+
+use {
+    criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput},
+    priority_graph_practice::{
+        scheduler::{GreedyScheduler, PriorityGraphScheduler, Scheduler},
+        workload::{Workload, WorkloadConfig},
+    },
+    std::hint::black_box,
+};
+
+const NUM_TRANSACTIONS: usize = 1_000;
+
+fn bench_schedulers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("schedule");
+    group.throughput(Throughput::Elements(NUM_TRANSACTIONS as u64));
+    for hot_account_percentage in [0, 50, 100] {
+        let workload = Workload::generate(&WorkloadConfig {
+            num_transactions: NUM_TRANSACTIONS,
+            // Enough payers that fee payers rarely conflict, so contention
+            // comes from the hot account.
+            num_payers: NUM_TRANSACTIONS * 10,
+            hot_account_ratio: f64::from(hot_account_percentage) / 100.0,
+            ..WorkloadConfig::default()
+        });
+        let transactions = &workload.transactions;
+
+        group.bench_with_input(
+            BenchmarkId::new("prio_graph", format!("{hot_account_percentage}% hot")),
+            transactions,
+            |b, transactions| {
+                b.iter(|| PriorityGraphScheduler::new().schedule(black_box(transactions)))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("greedy", format!("{hot_account_percentage}% hot")),
+            transactions,
+            |b, transactions| b.iter(|| GreedyScheduler::new().schedule(black_box(transactions))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_schedulers);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+//! Benchmark: Parallel commits into one locked map versus a sharded one
+//!
+//! Analogy: Sixteen waiters write their orders into the ledger. With one
+//! ledger they queue for the pen; with a ledger per group of tables each
+//! waiter mostly finds their page free, so adding waiters adds orders
+//! written per second instead of length to the queue.
+
+use {
+    criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput},
+    priority_graph_practice::accounts_storage::ShardedStorage,
+    solana_account::AccountSharedData,
+    solana_pubkey::Pubkey,
+    std::thread,
+};
+
+/// Commits per thread, each of the accounts one transfer writes.
+const COMMITS_PER_THREAD: usize = 2_000;
+const ACCOUNTS_PER_COMMIT: usize = 2;
+
+fn bench_parallel_commits(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel_commit");
+    for num_threads in [1, 2, 4, 8, 16] {
+        // Every thread writes accounts of its own, so only the locks make
+        // them wait.
+        let accounts: Vec<Vec<Pubkey>> = (0..num_threads)
+            .map(|_| {
+                (0..COMMITS_PER_THREAD * ACCOUNTS_PER_COMMIT)
+                    .map(|_| Pubkey::new_unique())
+                    .collect()
+            })
+            .collect();
+        group.throughput(Throughput::Elements(
+            (num_threads * COMMITS_PER_THREAD) as u64,
+        ));
+        // One shard is every committer behind a single lock.
+        for num_shards in [1, ShardedStorage::DEFAULT_NUM_SHARDS] {
+            let storage = ShardedStorage::new(num_shards);
+            group.bench_with_input(
+                BenchmarkId::new(format!("{num_shards} shards"), num_threads),
+                &accounts,
+                |b, accounts| {
+                    b.iter(|| {
+                        thread::scope(|scope| {
+                            for pubkeys in accounts {
+                                let storage = &storage;
+                                scope.spawn(move || {
+                                    for commit in pubkeys.chunks(ACCOUNTS_PER_COMMIT) {
+                                        storage.commit(commit.iter().map(|pubkey| {
+                                            (*pubkey, AccountSharedData::new(1, 0, pubkey))
+                                        }));
+                                    }
+                                });
+                            }
+                        })
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel_commits);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Benchmark: Signature verification throughput of the verifiers
+//!
+//! Analogy: A queue of guests shows its invitations at the door. One
+//! doorman checks a stack of cards at a glance, another uses the scanner
+//! that reads several cards in one pass; counting guests let in per second
+//! shows whether the scanner is worth plugging in.
+
+use {
+    criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput},
+    priority_graph_practice::{
+        sigverify::{verify_transactions_with, CpuVerifier, SigVerifyConfig, SignatureVerifier},
+        system_program,
+    },
+    solana_hash::Hash,
+    solana_keypair::Keypair,
+    solana_message::Message,
+    solana_pubkey::Pubkey,
+    solana_signer::Signer,
+    solana_transaction::{sanitized::SanitizedTransaction, Transaction},
+    std::hint::black_box,
+};
+
+const NUM_TRANSACTIONS: usize = 1_024;
+
+fn bench_sigverify(c: &mut Criterion) {
+    let transactions: Vec<_> = (0..NUM_TRANSACTIONS)
+        .map(|_| {
+            let payer = Keypair::new();
+            let transfer = system_program::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+            let message = Message::new(&[transfer], Some(&payer.pubkey()));
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+                &[&payer],
+                message,
+                Hash::new_unique(),
+            ))
+        })
+        .collect();
+    let config = SigVerifyConfig::default();
+    let verifiers: &[(&str, &dyn SignatureVerifier)] = &[
+        ("cpu", &CpuVerifier),
+        #[cfg(feature = "sigverify-simd")]
+        ("simd", &priority_graph_practice::sigverify::SimdVerifier),
+    ];
+
+    let mut group = c.benchmark_group("sigverify");
+    group.throughput(Throughput::Elements(NUM_TRANSACTIONS as u64));
+    for &(name, verifier) in verifiers {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| verify_transactions_with(black_box(&transactions), &config, verifier))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sigverify);
+criterion_main!(benches);
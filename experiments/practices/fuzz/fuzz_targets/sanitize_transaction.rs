@@ -0,0 +1,27 @@
+//! Arbitrary bytes as a wire transaction, the way the RPC server receives
+//! them.
+//!
+//! Decoding and sanitizing have to turn away whatever they don't accept
+//! with a typed error rather than panic, and a transaction that decodes has
+//! to encode back to the same bytes, so its signatures cover exactly what
+//! was received.
+
+#![no_main]
+
+use {
+    libfuzzer_sys::fuzz_target,
+    priority_graph_practice::sanitize::{deserialize_transaction, sanitize_transaction},
+    solana_message::SimpleAddressLoader,
+    solana_transaction_error::TransactionError,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(transaction) = deserialize_transaction(data) else {
+        return;
+    };
+    assert_eq!(bincode::serialize(&transaction).unwrap(), data);
+    if let Err(err) = sanitize_transaction(transaction, SimpleAddressLoader::Disabled) {
+        // The bank reports every rejection as a transaction error.
+        let _ = TransactionError::from(err);
+    }
+});
@@ -0,0 +1,30 @@
+//! Arbitrary bytes as a versioned message, signed with as many signatures
+//! as its header asks for.
+//!
+//! Most random wire transactions already fail the signature count, so this
+//! target gets to the header, account key and instruction checks far more
+//! often. Whatever the message, sanitizing must not panic.
+
+#![no_main]
+
+use {
+    libfuzzer_sys::fuzz_target,
+    priority_graph_practice::sanitize::{sanitize_transaction, MAX_TRANSACTION_LEN},
+    solana_message::{SimpleAddressLoader, VersionedMessage},
+    solana_signature::Signature,
+    solana_transaction::versioned::VersionedTransaction,
+};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() > MAX_TRANSACTION_LEN {
+        return;
+    }
+    let Ok(message) = bincode::deserialize::<VersionedMessage>(data) else {
+        return;
+    };
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures.into()],
+        message,
+    };
+    let _ = sanitize_transaction(transaction, SimpleAddressLoader::Disabled);
+});
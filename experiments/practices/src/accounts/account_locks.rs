@@ -0,0 +1,116 @@
+use {
+    super::{LockSet, LockSetConfig},
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+    std::collections::{hash_map::Entry, HashMap, HashSet},
+};
+
+/// The account locks held by transactions that are executing.
+///
+/// Any number of transactions can hold a read lock on an account, but a
+/// write lock is exclusive. Unlike a scheduler, the table does not order
+/// anything: a lock set that conflicts with a held lock is simply turned
+/// away with [`TransactionError::AccountInUse`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountLocks {
+    write_locks: HashSet<Pubkey>,
+    /// Number of read locks held on every read-locked account.
+    readonly_locks: HashMap<Pubkey, u64>,
+}
+
+impl AccountLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes every lock of `lock_set`, or none of them if one conflicts
+    /// with a lock already held.
+    pub fn try_lock(&mut self, lock_set: &LockSet) -> Result<(), TransactionError> {
+        let is_in_use = lock_set.writable().iter().any(|key| self.is_locked(key))
+            || lock_set
+                .readonly()
+                .iter()
+                .any(|key| self.is_write_locked(key));
+        if is_in_use {
+            return Err(TransactionError::AccountInUse);
+        }
+        self.write_locks.extend(lock_set.writable().iter().copied());
+        for key in lock_set.readonly() {
+            *self.readonly_locks.entry(*key).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    /// Releases the locks of `lock_set`, which must have been taken with
+    /// [`try_lock`](Self::try_lock).
+    ///
+    /// # Panics
+    ///
+    /// If one of the locks is not held.
+    pub fn unlock(&mut self, lock_set: &LockSet) {
+        for key in lock_set.writable() {
+            assert!(self.write_locks.remove(key), "{key} is not write locked");
+        }
+        for key in lock_set.readonly() {
+            let Entry::Occupied(mut entry) = self.readonly_locks.entry(*key) else {
+                panic!("{key} is not read locked");
+            };
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Locks the accounts of every transaction in order, the way a batch
+    /// about to execute does.
+    ///
+    /// A transaction that conflicts with a lock held already, including
+    /// one taken by a transaction ahead of it, fails with
+    /// [`TransactionError::AccountInUse`] and holds no locks.
+    pub fn lock_accounts(
+        &mut self,
+        transactions: &[SanitizedTransaction],
+        config: LockSetConfig,
+    ) -> Vec<Result<(), TransactionError>> {
+        transactions
+            .iter()
+            .map(|transaction| self.try_lock(&LockSet::from_transaction(transaction, config)))
+            .collect()
+    }
+
+    /// Releases the locks of the transactions that
+    /// [`lock_accounts`](Self::lock_accounts) locked. `results` line up with
+    /// `transactions`.
+    pub fn unlock_accounts(
+        &mut self,
+        transactions: &[SanitizedTransaction],
+        results: &[Result<(), TransactionError>],
+        config: LockSetConfig,
+    ) {
+        for (transaction, result) in transactions.iter().zip(results) {
+            if result.is_ok() {
+                self.unlock(&LockSet::from_transaction(transaction, config));
+            }
+        }
+    }
+
+    pub fn is_write_locked(&self, pubkey: &Pubkey) -> bool {
+        self.write_locks.contains(pubkey)
+    }
+
+    pub fn is_read_locked(&self, pubkey: &Pubkey) -> bool {
+        self.readonly_locks.contains_key(pubkey)
+    }
+
+    /// Whether `pubkey` is locked at all.
+    pub fn is_locked(&self, pubkey: &Pubkey) -> bool {
+        self.is_write_locked(pubkey) || self.is_read_locked(pubkey)
+    }
+
+    /// Whether no locks are held.
+    pub fn is_empty(&self) -> bool {
+        self.write_locks.is_empty() && self.readonly_locks.is_empty()
+    }
+}
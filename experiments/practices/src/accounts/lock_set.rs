@@ -0,0 +1,110 @@
+use {
+    super::is_reserved_account_key, prio_graph::AccessKind, solana_message::SanitizedMessage,
+    solana_pubkey::Pubkey, solana_transaction::sanitized::SanitizedTransaction,
+};
+
+/// Controls how a [`LockSet`] is derived from a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockSetConfig {
+    /// Demote write locks on invoked programs and on reserved accounts
+    /// (sysvars and builtin programs) to read locks, as mainnet does.
+    /// Invoked programs keep their write lock when the upgradeable loader
+    /// is part of the transaction, since it may be upgrading them.
+    pub demote_program_write_locks: bool,
+}
+
+impl Default for LockSetConfig {
+    fn default() -> Self {
+        Self {
+            demote_program_write_locks: true,
+        }
+    }
+}
+
+/// The accounts a transaction needs to lock, split by access kind.
+///
+/// For v0 transactions the lock set covers both the static account keys and
+/// the keys resolved from address lookup tables, because a
+/// [`SanitizedTransaction`] carries its loaded addresses in the message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LockSet {
+    writable: Vec<Pubkey>,
+    readonly: Vec<Pubkey>,
+}
+
+impl LockSet {
+    /// Creates a lock set from explicit account lists, for callers that do
+    /// not have a transaction at hand.
+    pub fn new(writable: Vec<Pubkey>, readonly: Vec<Pubkey>) -> Self {
+        Self { writable, readonly }
+    }
+
+    /// Computes the lock set from the transaction's message.
+    ///
+    /// With [`LockSetConfig::demote_program_write_locks`] unset, every
+    /// account the message asks to write is write locked.
+    pub fn from_transaction(transaction: &SanitizedTransaction, config: LockSetConfig) -> Self {
+        let message = transaction.message();
+        let demote_invoked_programs = !message.is_upgradeable_loader_present();
+        let mut lock_set = Self::default();
+        for (index, key) in message.account_keys().iter().enumerate() {
+            let is_demoted = config.demote_program_write_locks
+                && (is_reserved_account_key(key)
+                    || (demote_invoked_programs && message.is_invoked(index)));
+            if is_writable_index(message, index) && !is_demoted {
+                lock_set.writable.push(*key);
+            } else {
+                lock_set.readonly.push(*key);
+            }
+        }
+        lock_set
+    }
+
+    pub fn writable(&self) -> &[Pubkey] {
+        &self.writable
+    }
+
+    pub fn readonly(&self) -> &[Pubkey] {
+        &self.readonly
+    }
+
+    /// Returns true if both lock sets cannot be held at the same time, i.e.
+    /// one of them writes an account the other reads or writes.
+    pub fn conflicts_with(&self, other: &LockSet) -> bool {
+        self.writable
+            .iter()
+            .any(|key| other.writable.contains(key) || other.readonly.contains(key))
+            || other.writable.iter().any(|key| self.readonly.contains(key))
+    }
+
+    /// Iterates over every locked account with its access kind, in the shape
+    /// expected by `PrioGraph::insert_transaction`.
+    pub fn iter(&self) -> impl Iterator<Item = (Pubkey, AccessKind)> + '_ {
+        self.writable
+            .iter()
+            .map(|key| (*key, AccessKind::Write))
+            .chain(self.readonly.iter().map(|key| (*key, AccessKind::Read)))
+    }
+}
+
+/// Returns true if the message header (or the lookup tables, for loaded
+/// addresses) requests a write lock on the account at `index`.
+fn is_writable_index(message: &SanitizedMessage, index: usize) -> bool {
+    let header = message.header();
+    let num_static_keys = message.static_account_keys().len();
+    let num_signed = usize::from(header.num_required_signatures);
+    if index >= num_static_keys {
+        let num_loaded_writable = match message {
+            SanitizedMessage::V0(message) => message.loaded_addresses.writable.len(),
+            SanitizedMessage::Legacy(_) | SanitizedMessage::V1(_) => 0,
+        };
+        index.saturating_sub(num_static_keys) < num_loaded_writable
+    } else if index >= num_signed {
+        let num_writable_unsigned = num_static_keys
+            .saturating_sub(num_signed)
+            .saturating_sub(usize::from(header.num_readonly_unsigned_accounts));
+        index.saturating_sub(num_signed) < num_writable_unsigned
+    } else {
+        index < num_signed.saturating_sub(usize::from(header.num_readonly_signed_accounts))
+    }
+}
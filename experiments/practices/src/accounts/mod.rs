@@ -0,0 +1,20 @@
+//! Account access bookkeeping shared by the scheduler and the executor.
+//!
+//! [`PrefetchedAccounts`] holds the accounts of a batch loaded ahead of
+//! its execution: the bank prefetches the next batch on the rayon pool
+//! while the current one executes, so loading overlaps with compute.
+//! [`AccountLocks`] holds the locks of the transactions executing right
+//! now, as a check that no scheduler let two conflicting transactions run
+//! together.
+
+mod account_locks;
+mod lock_set;
+mod prefetch;
+mod reserved_account_keys;
+
+pub use {
+    account_locks::AccountLocks,
+    lock_set::{LockSet, LockSetConfig},
+    prefetch::{AccountLoadMetrics, PrefetchedAccounts, PrefetchingLoader},
+    reserved_account_keys::{is_reserved_account_key, reserved_account_keys},
+};
@@ -0,0 +1,145 @@
+use {
+    crate::svm::AccountLoader,
+    rayon::prelude::*,
+    solana_account::AccountSharedData,
+    solana_pubkey::Pubkey,
+    std::{
+        collections::HashMap,
+        sync::atomic::{AtomicU64, Ordering},
+        time::{Duration, Instant},
+    },
+};
+
+/// How well prefetching kept account loads off the execution path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccountLoadMetrics {
+    /// Accounts loaded ahead of the batch that needed them.
+    pub accounts_prefetched: u64,
+    /// Account loads during execution served by a prefetch.
+    pub prefetch_hits: u64,
+    /// Account loads during execution that went to the store, because
+    /// the account could change before its batch or was not referenced by
+    /// the message, like a programdata account.
+    pub prefetch_misses: u64,
+    /// Time spent prefetching, in microseconds.
+    pub prefetch_us: u64,
+    /// Part of `prefetch_us` that did not overlap with execution.
+    pub blocking_prefetch_us: u64,
+}
+
+impl AccountLoadMetrics {
+    /// Percentage of account loads served by a prefetch; 0 before the
+    /// first load.
+    pub fn hit_percentage(&self) -> f64 {
+        let loads = self.prefetch_hits + self.prefetch_misses;
+        if loads == 0 {
+            return 0.0;
+        }
+        100.0 * self.prefetch_hits as f64 / loads as f64
+    }
+
+    /// Adds the counts of `other` to these.
+    pub fn accumulate(&mut self, other: &AccountLoadMetrics) {
+        self.accounts_prefetched += other.accounts_prefetched;
+        self.prefetch_hits += other.prefetch_hits;
+        self.prefetch_misses += other.prefetch_misses;
+        self.prefetch_us += other.prefetch_us;
+        self.blocking_prefetch_us += other.blocking_prefetch_us;
+    }
+}
+
+/// Accounts loaded ahead of the batch that executes on them.
+///
+/// The executor reads them through [`loader`](Self::loader), which counts
+/// how many of its loads the prefetch served.
+#[derive(Debug, Default)]
+pub struct PrefetchedAccounts {
+    /// `None` for addresses the store has no account for.
+    accounts: HashMap<Pubkey, Option<AccountSharedData>>,
+    load_time: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl PrefetchedAccounts {
+    /// Loads the accounts at `keys` from `loader` in parallel.
+    pub fn load(loader: &(impl AccountLoader + Sync), keys: &[Pubkey]) -> Self {
+        let start = Instant::now();
+        let accounts = keys
+            .par_iter()
+            .map(|key| (*key, loader.load_account(key)))
+            .collect();
+        Self {
+            accounts,
+            load_time: start.elapsed(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds the accounts of `other`, loaded for the same batch.
+    pub fn extend(&mut self, other: PrefetchedAccounts) {
+        self.accounts.extend(other.accounts);
+        self.load_time += other.load_time;
+        *self.hits.get_mut() += other.hits.into_inner();
+        *self.misses.get_mut() += other.misses.into_inner();
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    pub fn contains(&self, pubkey: &Pubkey) -> bool {
+        self.accounts.contains_key(pubkey)
+    }
+
+    /// Time the loads took altogether.
+    pub fn load_time(&self) -> Duration {
+        self.load_time
+    }
+
+    /// Serves the prefetched accounts and every other account from
+    /// `fallback`.
+    pub fn loader<'a, L: AccountLoader>(&'a self, fallback: &'a L) -> PrefetchingLoader<'a, L> {
+        PrefetchingLoader {
+            prefetched: self,
+            fallback,
+        }
+    }
+
+    /// The accounts prefetched and the loads served so far.
+    pub fn metrics(&self) -> AccountLoadMetrics {
+        AccountLoadMetrics {
+            accounts_prefetched: self.accounts.len() as u64,
+            prefetch_hits: self.hits.load(Ordering::Relaxed),
+            prefetch_misses: self.misses.load(Ordering::Relaxed),
+            prefetch_us: self.load_time.as_micros() as u64,
+            blocking_prefetch_us: 0,
+        }
+    }
+}
+
+/// An [`AccountLoader`] that prefers prefetched accounts, returned by
+/// [`PrefetchedAccounts::loader`].
+pub struct PrefetchingLoader<'a, L> {
+    prefetched: &'a PrefetchedAccounts,
+    fallback: &'a L,
+}
+
+impl<L: AccountLoader> AccountLoader for PrefetchingLoader<'_, L> {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        match self.prefetched.accounts.get(pubkey) {
+            Some(account) => {
+                self.prefetched.hits.fetch_add(1, Ordering::Relaxed);
+                account.clone()
+            }
+            None => {
+                self.prefetched.misses.fetch_add(1, Ordering::Relaxed);
+                self.fallback.load_account(pubkey)
+            }
+        }
+    }
+}
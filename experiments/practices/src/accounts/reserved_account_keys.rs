@@ -0,0 +1,59 @@
+use {
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{
+        address_lookup_table, bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable,
+        compute_budget, config, ed25519_program, feature, loader_v4, native_loader,
+        secp256k1_program, secp256r1_program, stake, system_program, sysvar, vote,
+    },
+    std::{collections::HashSet, sync::LazyLock},
+};
+
+static RESERVED_ACCOUNT_KEYS: LazyLock<HashSet<Pubkey>> = LazyLock::new(|| {
+    [
+        // Builtin and precompile programs.
+        address_lookup_table::id(),
+        bpf_loader::id(),
+        bpf_loader_deprecated::id(),
+        bpf_loader_upgradeable::id(),
+        compute_budget::id(),
+        config::id(),
+        ed25519_program::id(),
+        feature::id(),
+        loader_v4::id(),
+        native_loader::id(),
+        secp256k1_program::id(),
+        secp256r1_program::id(),
+        stake::id(),
+        system_program::id(),
+        vote::id(),
+        // Sysvars.
+        sysvar::id(),
+        sysvar::clock::id(),
+        sysvar::epoch_rewards::id(),
+        sysvar::epoch_schedule::id(),
+        sysvar::fees::id(),
+        sysvar::instructions::id(),
+        sysvar::last_restart_slot::id(),
+        sysvar::recent_blockhashes::id(),
+        sysvar::rent::id(),
+        sysvar::rewards::id(),
+        sysvar::slot_hashes::id(),
+        sysvar::slot_history::id(),
+        sysvar::stake_history::id(),
+    ]
+    .into_iter()
+    .collect()
+});
+
+/// Returns true if `key` belongs to a builtin program or a sysvar.
+///
+/// The runtime never grants write locks on these accounts, even when a
+/// transaction asks for one.
+pub fn is_reserved_account_key(key: &Pubkey) -> bool {
+    RESERVED_ACCOUNT_KEYS.contains(key)
+}
+
+/// Every reserved key, in the form message sanitization expects.
+pub fn reserved_account_keys() -> &'static HashSet<Pubkey> {
+    &RESERVED_ACCOUNT_KEYS
+}
@@ -0,0 +1,623 @@
+//! In-memory account storage.
+//!
+//! [`AccountsDb`] holds account state across executed transactions. Writes
+//! can be grouped under a snapshot and discarded with a rollback, which is
+//! how a failed transaction's writes are thrown away. The accounts
+//! themselves live in an [`AccountsStorage`], in memory unless the store
+//! is built [with another one](AccountsDb::with_storage).
+//!
+//! The accounts can also be saved to a file with
+//! [`AccountsDb::snapshot_to_file`] and restored with
+//! [`AccountsDb::load_from_snapshot`], so a large experiment state is built
+//! once and reused across runs. The file starts with a magic and a format
+//! version, followed by the number of accounts and every account sorted by
+//! pubkey, all encoded with bincode.
+//!
+//! Next to the accounts the store keeps secondary indexes, updated with
+//! every write and rollback: accounts by owning program, and token accounts
+//! by mint and by token owner. Scans like
+//! [`AccountsDb::get_program_accounts`] then touch only the accounts they
+//! return instead of every stored one.
+//!
+//! [`AccountsVersions`] keeps the state of every account as of each slot
+//! published to it, for scans that run on other threads while later slots
+//! are committed: [`AccountsVersions::scan_at_slot`] sees exactly the
+//! accounts as that slot left them, however many slots are published while
+//! it runs.
+
+use {
+    crate::{
+        accounts_storage::{AccountsStorage, InMemoryStorage},
+        svm::AccountLoader,
+        token_program::{self, TokenAccount},
+    },
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap},
+        fs::File,
+        io::{self, BufReader, BufWriter, Read, Write},
+        path::Path,
+        sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    },
+};
+
+const SNAPSHOT_MAGIC: &[u8; 7] = b"SVMACCT";
+const SNAPSHOT_VERSION: u8 = 1;
+
+/// An account as a snapshot file stores it: pubkey, lamports, data, owner,
+/// executable and rent epoch.
+pub(crate) type SnapshotAccount = ([u8; 32], u64, Vec<u8>, [u8; 32], bool, u64);
+
+pub(crate) fn to_snapshot_account(pubkey: &Pubkey, account: &AccountSharedData) -> SnapshotAccount {
+    (
+        pubkey.to_bytes(),
+        account.lamports(),
+        account.data().to_vec(),
+        account.owner().to_bytes(),
+        account.executable(),
+        account.rent_epoch(),
+    )
+}
+
+pub(crate) fn from_snapshot_account(
+    (pubkey, lamports, data, owner, executable, rent_epoch): SnapshotAccount,
+) -> (Pubkey, AccountSharedData) {
+    let mut account = AccountSharedData::new(lamports, 0, &Pubkey::from(owner));
+    account.set_data_from_slice(&data);
+    account.set_executable(executable);
+    account.set_rent_epoch(rent_epoch);
+    (Pubkey::from(pubkey), account)
+}
+
+/// Why a snapshot file could not be written or loaded.
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("snapshot I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("file is not an accounts snapshot")]
+    InvalidHeader,
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedVersion(u8),
+    #[error("snapshot is corrupted")]
+    Corrupted,
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(err: bincode::Error) -> Self {
+        match *err {
+            bincode::ErrorKind::Io(err) if err.kind() != io::ErrorKind::UnexpectedEof => {
+                Self::Io(err)
+            }
+            _ => Self::Corrupted,
+        }
+    }
+}
+
+/// Handle to a snapshot taken with [`AccountsDb::snapshot`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct SnapshotId(usize);
+
+/// Account store with copy-on-write snapshots.
+///
+/// Taking a snapshot copies nothing. Instead, the first write to an account
+/// after a snapshot records the account's previous state in that
+/// snapshot's undo log, so rolling back only touches the accounts that
+/// changed. Snapshots nest: rolling back an outer snapshot also undoes
+/// everything written under the snapshots taken after it.
+///
+/// Cloning the store [forks](AccountsStorage::fork) its storage.
+#[derive(Debug)]
+pub struct AccountsDb {
+    accounts: Box<dyn AccountsStorage>,
+    /// One undo log per open snapshot, oldest first. Each entry holds the
+    /// account's state when the snapshot was taken; `None` means the
+    /// account did not exist yet.
+    undo_logs: Vec<HashMap<Pubkey, Option<AccountSharedData>>>,
+    /// State of every account written since the last checkpoint, as of
+    /// that checkpoint.
+    written: HashMap<Pubkey, Option<AccountSharedData>>,
+    secondary_indexes: SecondaryIndexes,
+}
+
+impl Default for AccountsDb {
+    fn default() -> Self {
+        Self::with_storage(Box::new(InMemoryStorage::new()))
+    }
+}
+
+impl Clone for AccountsDb {
+    fn clone(&self) -> Self {
+        Self {
+            accounts: self.accounts.fork(),
+            undo_logs: self.undo_logs.clone(),
+            written: self.written.clone(),
+            secondary_indexes: self.secondary_indexes.clone(),
+        }
+    }
+}
+
+/// Keys of the stored accounts by what they hold, each set sorted so
+/// scans return accounts in a stable order.
+#[derive(Clone, Debug, Default)]
+struct SecondaryIndexes {
+    /// Accounts by the program owning them.
+    program_id: HashMap<Pubkey, BTreeSet<Pubkey>>,
+    /// Token accounts by mint.
+    spl_token_mint: HashMap<Pubkey, BTreeSet<Pubkey>>,
+    /// Token accounts by the wallet that owns their tokens.
+    spl_token_owner: HashMap<Pubkey, BTreeSet<Pubkey>>,
+}
+
+/// What an account is indexed by: its owning program, and its mint and
+/// token owner if it is a token account.
+type IndexKeys = (Pubkey, Option<(Pubkey, Pubkey)>);
+
+fn index_keys(account: &AccountSharedData) -> IndexKeys {
+    (*account.owner(), token_account_keys(account))
+}
+
+impl SecondaryIndexes {
+    fn insert(&mut self, pubkey: Pubkey, (program_id, token_keys): IndexKeys) {
+        self.program_id
+            .entry(program_id)
+            .or_default()
+            .insert(pubkey);
+        if let Some((mint, owner)) = token_keys {
+            self.spl_token_mint.entry(mint).or_default().insert(pubkey);
+            self.spl_token_owner
+                .entry(owner)
+                .or_default()
+                .insert(pubkey);
+        }
+    }
+
+    fn remove(&mut self, pubkey: &Pubkey, account: &AccountSharedData) {
+        remove_key(&mut self.program_id, account.owner(), pubkey);
+        if let Some((mint, owner)) = token_account_keys(account) {
+            remove_key(&mut self.spl_token_mint, &mint, pubkey);
+            remove_key(&mut self.spl_token_owner, &owner, pubkey);
+        }
+    }
+}
+
+fn remove_key(index: &mut HashMap<Pubkey, BTreeSet<Pubkey>>, key: &Pubkey, pubkey: &Pubkey) {
+    if let Some(pubkeys) = index.get_mut(key) {
+        pubkeys.remove(pubkey);
+        if pubkeys.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+/// The mint and token owner of a token account, read straight from the
+/// head of its data.
+fn token_account_keys(account: &AccountSharedData) -> Option<(Pubkey, Pubkey)> {
+    let data = account.data();
+    if *account.owner() != token_program::ID || data.len() != TokenAccount::LEN {
+        return None;
+    }
+    let mint = Pubkey::try_from(&data[..32]).ok()?;
+    let owner = Pubkey::try_from(&data[32..64]).ok()?;
+    Some((mint, owner))
+}
+
+impl AccountsDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a store keeping its accounts in `storage`, indexing the
+    /// ones it already holds.
+    pub fn with_storage(storage: Box<dyn AccountsStorage>) -> Self {
+        let mut secondary_indexes = SecondaryIndexes::default();
+        for (pubkey, account) in storage.iter() {
+            secondary_indexes.insert(pubkey, index_keys(&account));
+        }
+        Self {
+            accounts: storage,
+            undo_logs: Vec::new(),
+            written: HashMap::new(),
+            secondary_indexes,
+        }
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.accounts.get(pubkey)
+    }
+
+    /// Stores `account` under `pubkey`.
+    ///
+    /// An account left with zero lamports was closed, so it is removed
+    /// rather than stored, the same way the runtime purges dead accounts.
+    pub fn store_account(&mut self, pubkey: Pubkey, account: AccountSharedData) {
+        let previous = if account.lamports() == 0 {
+            self.remove_account(&pubkey)
+        } else {
+            self.insert_account(pubkey, account)
+        };
+        self.written
+            .entry(pubkey)
+            .or_insert_with(|| previous.clone());
+        if let Some(undo_log) = self.undo_logs.last_mut() {
+            undo_log.entry(pubkey).or_insert(previous);
+        }
+    }
+
+    /// Iterates over the accounts written since the last
+    /// [`checkpoint`](Self::checkpoint), each with its state as of the
+    /// checkpoint; `None` means the account did not exist then. Accounts
+    /// written and then rolled back are included.
+    pub fn written_accounts(&self) -> impl Iterator<Item = (&Pubkey, Option<&AccountSharedData>)> {
+        self.written
+            .iter()
+            .map(|(pubkey, previous)| (pubkey, previous.as_ref()))
+    }
+
+    /// Makes the current state the baseline later writes are tracked
+    /// against.
+    pub fn checkpoint(&mut self) {
+        self.written.clear();
+    }
+
+    /// Every account `program_id` owns, sorted by pubkey.
+    pub fn get_program_accounts(&self, program_id: &Pubkey) -> Vec<(Pubkey, AccountSharedData)> {
+        self.indexed_accounts(&self.secondary_indexes.program_id, program_id)
+    }
+
+    /// Every token account holding tokens of `mint`, sorted by pubkey.
+    pub fn get_token_accounts_by_mint(&self, mint: &Pubkey) -> Vec<(Pubkey, AccountSharedData)> {
+        self.indexed_accounts(&self.secondary_indexes.spl_token_mint, mint)
+    }
+
+    /// Every token account whose tokens `owner` owns, sorted by pubkey.
+    pub fn get_token_accounts_by_owner(&self, owner: &Pubkey) -> Vec<(Pubkey, AccountSharedData)> {
+        self.indexed_accounts(&self.secondary_indexes.spl_token_owner, owner)
+    }
+
+    fn indexed_accounts(
+        &self,
+        index: &HashMap<Pubkey, BTreeSet<Pubkey>>,
+        key: &Pubkey,
+    ) -> Vec<(Pubkey, AccountSharedData)> {
+        index.get(key).map_or_else(Vec::new, |pubkeys| {
+            pubkeys
+                .iter()
+                .map(|pubkey| {
+                    let account = self
+                        .accounts
+                        .get(pubkey)
+                        .expect("indexed accounts are stored");
+                    (*pubkey, account)
+                })
+                .collect()
+        })
+    }
+
+    /// Stores `account`, keeping the secondary indexes up to date, and
+    /// returns what was stored before.
+    fn insert_account(
+        &mut self,
+        pubkey: Pubkey,
+        account: AccountSharedData,
+    ) -> Option<AccountSharedData> {
+        let index_keys = index_keys(&account);
+        let previous = self.accounts.insert(pubkey, account);
+        if let Some(previous) = &previous {
+            self.secondary_indexes.remove(&pubkey, previous);
+        }
+        self.secondary_indexes.insert(pubkey, index_keys);
+        previous
+    }
+
+    fn remove_account(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        let previous = self.accounts.remove(pubkey);
+        if let Some(previous) = &previous {
+            self.secondary_indexes.remove(pubkey, previous);
+        }
+        previous
+    }
+
+    /// Iterates over every stored account, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Pubkey, AccountSharedData)> + '_ {
+        self.accounts.iter()
+    }
+
+    pub fn num_accounts(&self) -> usize {
+        self.accounts.len()
+    }
+
+    /// Number of snapshots that are still open.
+    pub fn num_snapshots(&self) -> usize {
+        self.undo_logs.len()
+    }
+
+    /// Opens a snapshot that later writes can be rolled back to.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        self.undo_logs.push(HashMap::new());
+        SnapshotId(self.undo_logs.len() - 1)
+    }
+
+    /// Restores the state at the time `snapshot` was taken and closes it,
+    /// along with every snapshot taken after it.
+    pub fn rollback(&mut self, snapshot: SnapshotId) {
+        self.assert_open(&snapshot);
+        while self.undo_logs.len() > snapshot.0 {
+            let undo_log = self.undo_logs.pop().unwrap();
+            for (pubkey, previous) in undo_log {
+                match previous {
+                    Some(account) => self.insert_account(pubkey, account),
+                    None => self.remove_account(&pubkey),
+                };
+            }
+        }
+    }
+
+    /// Closes `snapshot` and keeps everything written under it.
+    ///
+    /// Snapshots taken after `snapshot` are closed as well. If an older
+    /// snapshot is still open, the writes stay revertible through it.
+    pub fn release(&mut self, snapshot: SnapshotId) {
+        self.assert_open(&snapshot);
+        let released: Vec<_> = self.undo_logs.drain(snapshot.0..).collect();
+        if let Some(parent) = self.undo_logs.last_mut() {
+            // The oldest released log holds the state the parent has to
+            // restore, so merge from oldest to newest and keep the first
+            // entry seen for each account.
+            for undo_log in released {
+                for (pubkey, previous) in undo_log {
+                    parent.entry(pubkey).or_insert(previous);
+                }
+            }
+        }
+    }
+
+    /// Writes every stored account to `path`, replacing the file if it
+    /// exists.
+    ///
+    /// Only the current state is saved: open snapshots and their undo logs
+    /// are not part of the file.
+    pub fn snapshot_to_file(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        // Only the keys are sorted in memory, for storages that do not
+        // hold their accounts there.
+        let mut pubkeys: Vec<Pubkey> = self.accounts.iter().map(|(pubkey, _)| pubkey).collect();
+        pubkeys.sort_unstable();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&[SNAPSHOT_VERSION])?;
+        bincode::serialize_into(&mut writer, &(pubkeys.len() as u64))?;
+        for pubkey in pubkeys {
+            let account = self
+                .accounts
+                .get(&pubkey)
+                .expect("listed accounts are stored");
+            bincode::serialize_into(&mut writer, &to_snapshot_account(&pubkey, &account))?;
+        }
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        Ok(())
+    }
+
+    /// Loads the accounts saved to `path` by
+    /// [`snapshot_to_file`](Self::snapshot_to_file), with no snapshots open.
+    pub fn load_from_snapshot(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0; SNAPSHOT_MAGIC.len() + 1];
+        reader.read_exact(&mut header).map_err(|err| {
+            if err.kind() == io::ErrorKind::UnexpectedEof {
+                SnapshotError::InvalidHeader
+            } else {
+                SnapshotError::Io(err)
+            }
+        })?;
+        if &header[..SNAPSHOT_MAGIC.len()] != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::InvalidHeader);
+        }
+        let version = header[SNAPSHOT_MAGIC.len()];
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let num_accounts: u64 = bincode::deserialize_from(&mut reader)?;
+        let mut accounts_db = Self::new();
+        for _ in 0..num_accounts {
+            let (pubkey, account) = from_snapshot_account(bincode::deserialize_from(&mut reader)?);
+            accounts_db.insert_account(pubkey, account);
+        }
+        if reader.read(&mut [0])? != 0 {
+            return Err(SnapshotError::Corrupted);
+        }
+        Ok(accounts_db)
+    }
+
+    fn assert_open(&self, snapshot: &SnapshotId) {
+        assert!(
+            snapshot.0 < self.undo_logs.len(),
+            "snapshot {} is no longer open",
+            snapshot.0
+        );
+    }
+}
+
+/// Why [`AccountsVersions::scan_at_slot`] cannot scan a slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ScanError {
+    #[error("slot {0} is not published yet")]
+    SlotNotPublished(Slot),
+    #[error("slot {0} was purged")]
+    SlotPurged(Slot),
+}
+
+/// Account states by the slot that wrote them, shared between the thread
+/// publishing slots and the threads scanning them.
+///
+/// A slot is published all at once, so a scan sees either all of its
+/// writes or none, and the versions a published slot left are never
+/// changed afterwards: scanning one only ever reads versions no later
+/// publish touches. Old versions are kept until
+/// [`purge_older_than`](Self::purge_older_than) drops them, which leaves
+/// alone the slots of the scans still running.
+#[derive(Debug, Default)]
+pub struct AccountsVersions {
+    inner: RwLock<VersionsInner>,
+}
+
+#[derive(Debug, Default)]
+struct VersionsInner {
+    /// Every kept version of every account, oldest first; `None` means
+    /// the slot closed the account.
+    versions: HashMap<Pubkey, Vec<(Slot, Option<AccountSharedData>)>>,
+    last_slot: Option<Slot>,
+    /// Oldest slot that can still be scanned.
+    oldest_slot: Slot,
+    /// Slots of the scans running, with how many scan each.
+    scanned_slots: BTreeMap<Slot, usize>,
+}
+
+impl AccountsVersions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Newest published slot, if any was.
+    pub fn last_slot(&self) -> Option<Slot> {
+        self.read().last_slot
+    }
+
+    /// Records the state `slot` left `accounts` in; `None` means the
+    /// account was closed. Accounts not listed keep their state from the
+    /// slots before.
+    ///
+    /// # Panics
+    ///
+    /// If `slot` is not after the last published slot.
+    pub fn publish(
+        &self,
+        slot: Slot,
+        accounts: impl IntoIterator<Item = (Pubkey, Option<AccountSharedData>)>,
+    ) {
+        let mut inner = self.write();
+        assert!(
+            inner.last_slot.is_none_or(|last_slot| slot > last_slot),
+            "slot {slot} is published after a later slot"
+        );
+        for (pubkey, account) in accounts {
+            inner
+                .versions
+                .entry(pubkey)
+                .or_default()
+                .push((slot, account));
+        }
+        inner.last_slot = Some(slot);
+    }
+
+    /// Scans every account as of `slot`, in pubkey order.
+    ///
+    /// The scan reads one account at a time, so publishing goes on while
+    /// it runs, and keeps `slot` from being purged until it is dropped.
+    pub fn scan_at_slot(&self, slot: Slot) -> Result<ScanAtSlot<'_>, ScanError> {
+        let mut inner = self.write();
+        if inner.last_slot.is_none_or(|last_slot| slot > last_slot) {
+            return Err(ScanError::SlotNotPublished(slot));
+        }
+        if slot < inner.oldest_slot {
+            return Err(ScanError::SlotPurged(slot));
+        }
+        *inner.scanned_slots.entry(slot).or_default() += 1;
+        let mut pubkeys: Vec<Pubkey> = inner
+            .versions
+            .iter()
+            .filter(|(_, versions)| versions[0].0 <= slot)
+            .map(|(pubkey, _)| *pubkey)
+            .collect();
+        pubkeys.sort_unstable();
+        Ok(ScanAtSlot {
+            versions: self,
+            slot,
+            pubkeys: pubkeys.into_iter(),
+        })
+    }
+
+    /// Drops the versions only slots before `slot` can see, and with them
+    /// the accounts closed before it, so those slots can no longer be
+    /// scanned. Slots scans are still running at are kept, along with the
+    /// slots after them.
+    pub fn purge_older_than(&self, slot: Slot) {
+        let mut inner = self.write();
+        let slot = inner
+            .scanned_slots
+            .keys()
+            .next()
+            .map_or(slot, |scanned_slot| slot.min(*scanned_slot));
+        if slot <= inner.oldest_slot {
+            return;
+        }
+        inner.versions.retain(|_, versions| {
+            // The newest version at or before `slot` is still the one it
+            // sees.
+            let seen = versions.partition_point(|(version_slot, _)| *version_slot <= slot);
+            versions.drain(..seen.saturating_sub(1));
+            !(versions.len() == 1 && versions[0].1.is_none() && versions[0].0 <= slot)
+        });
+        inner.oldest_slot = slot;
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, VersionsInner> {
+        self.inner.read().expect("versions lock is never poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, VersionsInner> {
+        self.inner.write().expect("versions lock is never poisoned")
+    }
+}
+
+/// The accounts as of a published slot, from
+/// [`AccountsVersions::scan_at_slot`].
+pub struct ScanAtSlot<'a> {
+    versions: &'a AccountsVersions,
+    slot: Slot,
+    /// Accounts that have a version at or before the slot, left to read.
+    pubkeys: std::vec::IntoIter<Pubkey>,
+}
+
+impl ScanAtSlot<'_> {
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+}
+
+impl Iterator for ScanAtSlot<'_> {
+    type Item = (Pubkey, AccountSharedData);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = self.versions.read();
+        self.pubkeys.by_ref().find_map(|pubkey| {
+            let versions = &inner.versions[&pubkey];
+            let seen = versions.partition_point(|(version_slot, _)| *version_slot <= self.slot);
+            let (_, account) = &versions[seen - 1];
+            account.clone().map(|account| (pubkey, account))
+        })
+    }
+}
+
+impl Drop for ScanAtSlot<'_> {
+    fn drop(&mut self) {
+        let mut inner = self.versions.write();
+        if let Some(scans) = inner.scanned_slots.get_mut(&self.slot) {
+            *scans -= 1;
+            if *scans == 0 {
+                inner.scanned_slots.remove(&self.slot);
+            }
+        }
+    }
+}
+
+impl AccountLoader for AccountsDb {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.get_account(pubkey)
+    }
+}
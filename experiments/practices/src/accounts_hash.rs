@@ -0,0 +1,191 @@
+//! Hashes of account state.
+//!
+//! The accounts lattice hash ([`LtHash`]) is how validators commit to the
+//! state of every account: each account hashes to a vector of 1024 `u16`s,
+//! and the hash of a set of accounts is the element-wise wrapping sum of
+//! theirs. Since sums can be taken apart again, the hash of the whole
+//! state is kept up to date by mixing out the old state of every written
+//! account and mixing in the new one, so [`calculate_accounts_delta_lt_hash`]
+//! of a slot's writes added to the previous slot's hash equals
+//! [`calculate_accounts_lt_hash`] over all accounts. The 32-byte
+//! [`LtHash::checksum`] is what gets compared across runs.
+//!
+//! [`calculate_accounts_delta_hash`] is the older accounts delta hash: a
+//! Merkle root, with a fanout of 16, of the hashes of the accounts a slot
+//! wrote, sorted by pubkey.
+
+use {
+    crate::accounts_db::AccountsDb,
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_hash::Hash,
+    solana_pubkey::Pubkey,
+    std::fmt,
+};
+
+/// Number of `u16` elements of an [`LtHash`].
+pub const LT_HASH_NUM_ELEMENTS: usize = 1024;
+
+const MERKLE_FANOUT: usize = 16;
+
+/// A lattice hash.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LtHash(pub [u16; LT_HASH_NUM_ELEMENTS]);
+
+impl LtHash {
+    /// The hash of no accounts, which mixing in leaves unchanged.
+    pub const fn identity() -> Self {
+        Self([0; LT_HASH_NUM_ELEMENTS])
+    }
+
+    /// Fills a lattice hash with the extendable output of `hasher`.
+    pub fn with(hasher: &blake3::Hasher) -> Self {
+        let mut bytes = [0; LT_HASH_NUM_ELEMENTS * 2];
+        hasher.finalize_xof().fill(&mut bytes);
+        let mut elements = [0; LT_HASH_NUM_ELEMENTS];
+        for (element, chunk) in elements.iter_mut().zip(bytes.chunks_exact(2)) {
+            *element = u16::from_le_bytes([chunk[0], chunk[1]]);
+        }
+        Self(elements)
+    }
+
+    pub fn mix_in(&mut self, other: &Self) {
+        for (element, other) in self.0.iter_mut().zip(other.0) {
+            *element = element.wrapping_add(other);
+        }
+    }
+
+    pub fn mix_out(&mut self, other: &Self) {
+        for (element, other) in self.0.iter_mut().zip(other.0) {
+            *element = element.wrapping_sub(other);
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.0.iter().all(|element| *element == 0)
+    }
+
+    /// BLAKE3 of the elements in little-endian order.
+    pub fn checksum(&self) -> Hash {
+        let mut hasher = blake3::Hasher::new();
+        for element in self.0 {
+            hasher.update(&element.to_le_bytes());
+        }
+        Hash::new_from_array(hasher.finalize().into())
+    }
+}
+
+impl Default for LtHash {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl fmt::Debug for LtHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LtHash").field(&self.checksum()).finish()
+    }
+}
+
+/// The lattice hash of `account` stored under `pubkey`.
+///
+/// An account with zero lamports does not exist, so it hashes to the
+/// identity.
+pub fn account_lt_hash(pubkey: &Pubkey, account: &AccountSharedData) -> LtHash {
+    if account.lamports() == 0 {
+        return LtHash::identity();
+    }
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&account.lamports().to_le_bytes());
+    hasher.update(account.data());
+    hasher.update(&[account.executable() as u8]);
+    hasher.update(account.owner().as_ref());
+    hasher.update(pubkey.as_ref());
+    LtHash::with(&hasher)
+}
+
+/// The hash of `account` stored under `pubkey` that the accounts delta
+/// hash is built from.
+///
+/// An account with zero lamports hashes to `Hash::default()`.
+pub fn account_hash(pubkey: &Pubkey, account: &AccountSharedData) -> Hash {
+    if account.lamports() == 0 {
+        return Hash::default();
+    }
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&account.lamports().to_le_bytes());
+    hasher.update(&account.rent_epoch().to_le_bytes());
+    hasher.update(account.data());
+    hasher.update(&[account.executable() as u8]);
+    hasher.update(account.owner().as_ref());
+    hasher.update(pubkey.as_ref());
+    Hash::new_from_array(hasher.finalize().into())
+}
+
+/// The lattice hash of every account in `accounts_db`.
+pub fn calculate_accounts_lt_hash(accounts_db: &AccountsDb) -> LtHash {
+    let mut lt_hash = LtHash::identity();
+    for (pubkey, account) in accounts_db.iter() {
+        lt_hash.mix_in(&account_lt_hash(&pubkey, &account));
+    }
+    lt_hash
+}
+
+/// How the lattice hash of `accounts_db` changed since its last
+/// [`checkpoint`](AccountsDb::checkpoint): the new state of every written
+/// account mixed in and its state at the checkpoint mixed out.
+pub fn calculate_accounts_delta_lt_hash(accounts_db: &AccountsDb) -> LtHash {
+    let mut delta = LtHash::identity();
+    for (pubkey, previous) in accounts_db.written_accounts() {
+        if let Some(previous) = previous {
+            delta.mix_out(&account_lt_hash(pubkey, previous));
+        }
+        if let Some(account) = accounts_db.get_account(pubkey) {
+            delta.mix_in(&account_lt_hash(pubkey, &account));
+        }
+    }
+    delta
+}
+
+/// The accounts delta hash of the accounts written to `accounts_db` since
+/// its last [`checkpoint`](AccountsDb::checkpoint).
+///
+/// Accounts that were written but ended up as they were at the checkpoint
+/// are left out, and a closed account contributes `Hash::default()`.
+pub fn calculate_accounts_delta_hash(accounts_db: &AccountsDb) -> Hash {
+    let mut written: Vec<_> = accounts_db
+        .written_accounts()
+        .filter_map(|(pubkey, previous)| {
+            let account = accounts_db.get_account(pubkey);
+            (account.as_ref() != previous).then_some((*pubkey, account))
+        })
+        .collect();
+    written.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+    let hashes = written
+        .iter()
+        .map(|(pubkey, account)| {
+            account
+                .as_ref()
+                .map_or_else(Hash::default, |account| account_hash(pubkey, account))
+        })
+        .collect();
+    merkle_root(hashes)
+}
+
+/// Hashes `hashes` in groups of [`MERKLE_FANOUT`] until one is left.
+fn merkle_root(mut hashes: Vec<Hash>) -> Hash {
+    if hashes.is_empty() {
+        return solana_sha256_hasher::hashv(&[]);
+    }
+    loop {
+        hashes = hashes
+            .chunks(MERKLE_FANOUT)
+            .map(|chunk| {
+                let chunk: Vec<&[u8]> = chunk.iter().map(|hash| hash.as_ref()).collect();
+                solana_sha256_hasher::hashv(&chunk)
+            })
+            .collect();
+        if hashes.len() == 1 {
+            return hashes[0];
+        }
+    }
+}
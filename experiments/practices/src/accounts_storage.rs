@@ -0,0 +1,380 @@
+//! Where an [`AccountsDb`](crate::accounts_db::AccountsDb) keeps its
+//! accounts.
+//!
+//! The store's snapshots, write tracking and secondary indexes work the
+//! same on top of any [`AccountsStorage`]. [`InMemoryStorage`] is the
+//! default; with the `sled` feature, [`SledStorage`] keeps the accounts in
+//! a sled database on disk instead, so a replay whose accounts do not fit
+//! in memory still runs.
+//!
+//! [`ShardedStorage`] splits the accounts across maps by pubkey prefix,
+//! each behind a lock of its own, so worker threads loading and
+//! committing through a shared reference only wait on each other when
+//! their accounts land in the same shard.
+
+use {
+    crate::svm::AccountLoader,
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_pubkey::Pubkey,
+    std::{
+        collections::HashMap,
+        fmt,
+        sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    },
+};
+
+/// A map from pubkeys to accounts.
+///
+/// Stored accounts always hold lamports; [`AccountsDb`] removes the ones
+/// closed instead of storing them.
+///
+/// [`AccountsDb`]: crate::accounts_db::AccountsDb
+pub trait AccountsStorage: fmt::Debug + Send + Sync {
+    fn get(&self, pubkey: &Pubkey) -> Option<AccountSharedData>;
+
+    /// Stores `account` under `pubkey`, returning what was stored before.
+    fn insert(&mut self, pubkey: Pubkey, account: AccountSharedData) -> Option<AccountSharedData>;
+
+    /// Removes the account under `pubkey`, returning it.
+    fn remove(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every stored account, in no particular order.
+    fn iter(&self) -> Box<dyn Iterator<Item = (Pubkey, AccountSharedData)> + '_>;
+
+    /// A storage holding the same accounts, which later writes to either
+    /// do not reach the other. This is how a bank forked from a parent
+    /// gets accounts of its own.
+    fn fork(&self) -> Box<dyn AccountsStorage>;
+}
+
+/// Accounts in a `HashMap`.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryStorage {
+    accounts: HashMap<Pubkey, AccountSharedData>,
+}
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AccountsStorage for InMemoryStorage {
+    fn get(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.accounts.get(pubkey).cloned()
+    }
+
+    fn insert(&mut self, pubkey: Pubkey, account: AccountSharedData) -> Option<AccountSharedData> {
+        self.accounts.insert(pubkey, account)
+    }
+
+    fn remove(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.accounts.remove(pubkey)
+    }
+
+    fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Pubkey, AccountSharedData)> + '_> {
+        Box::new(
+            self.accounts
+                .iter()
+                .map(|(pubkey, account)| (*pubkey, account.clone())),
+        )
+    }
+
+    fn fork(&self) -> Box<dyn AccountsStorage> {
+        Box::new(self.clone())
+    }
+}
+
+type Shard = HashMap<Pubkey, AccountSharedData>;
+
+/// Accounts split across shards by the first two bytes of their pubkey,
+/// each shard behind its own lock.
+///
+/// Through `&self`, [`load`](Self::load) and [`commit`](Self::commit) lock
+/// only the shards the accounts fall in, so workers committing disjoint
+/// accounts rarely contend. Owned by an [`AccountsDb`] it works through
+/// `&mut self` and never locks at all.
+///
+/// [`AccountsDb`]: crate::accounts_db::AccountsDb
+#[derive(Debug)]
+pub struct ShardedStorage {
+    shards: Box<[RwLock<Shard>]>,
+}
+
+impl Default for ShardedStorage {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_NUM_SHARDS)
+    }
+}
+
+impl ShardedStorage {
+    /// Enough shards that 16 threads committing random accounts seldom
+    /// meet.
+    pub const DEFAULT_NUM_SHARDS: usize = 64;
+
+    /// A storage of `num_shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// If `num_shards` is zero or more than the 65536 two-byte prefixes.
+    pub fn new(num_shards: usize) -> Self {
+        assert!(
+            (1..=1 << 16).contains(&num_shards),
+            "{num_shards} shards cannot split accounts by two-byte prefix"
+        );
+        Self {
+            shards: (0..num_shards).map(|_| RwLock::default()).collect(),
+        }
+    }
+
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The shard `pubkey` falls in.
+    pub fn shard_index(&self, pubkey: &Pubkey) -> usize {
+        let prefix = pubkey.as_ref();
+        usize::from(u16::from_be_bytes([prefix[0], prefix[1]])) % self.shards.len()
+    }
+
+    pub fn load(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.read(self.shard_index(pubkey)).get(pubkey).cloned()
+    }
+
+    /// Stores `accounts`, removing the ones left with zero lamports the
+    /// way [`AccountsDb::store_account`] does, and locks every shard they
+    /// fall in once.
+    ///
+    /// Each account is stored atomically, but accounts in different shards
+    /// are not stored at the same instant: a concurrent load may see some
+    /// of them written and not others.
+    ///
+    /// [`AccountsDb::store_account`]: crate::accounts_db::AccountsDb::store_account
+    pub fn commit(&self, accounts: impl IntoIterator<Item = (Pubkey, AccountSharedData)>) {
+        let mut accounts: Vec<_> = accounts
+            .into_iter()
+            .map(|(pubkey, account)| (self.shard_index(&pubkey), pubkey, account))
+            .collect();
+        // Stable, so an account written twice keeps its last write.
+        accounts.sort_by_key(|(index, _, _)| *index);
+        let mut accounts = accounts.into_iter().peekable();
+        while let Some(&(index, _, _)) = accounts.peek() {
+            let mut shard = self.write(index);
+            while let Some((_, pubkey, account)) = accounts.next_if(|(next, _, _)| *next == index) {
+                if account.lamports() == 0 {
+                    shard.remove(&pubkey);
+                } else {
+                    shard.insert(pubkey, account);
+                }
+            }
+        }
+    }
+
+    fn shard_mut(&mut self, pubkey: &Pubkey) -> &mut Shard {
+        let index = self.shard_index(pubkey);
+        self.shards[index]
+            .get_mut()
+            .expect("shard locks are never poisoned")
+    }
+
+    fn read(&self, index: usize) -> RwLockReadGuard<'_, Shard> {
+        self.shards[index]
+            .read()
+            .expect("shard locks are never poisoned")
+    }
+
+    fn write(&self, index: usize) -> RwLockWriteGuard<'_, Shard> {
+        self.shards[index]
+            .write()
+            .expect("shard locks are never poisoned")
+    }
+}
+
+impl AccountLoader for ShardedStorage {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.load(pubkey)
+    }
+}
+
+impl AccountsStorage for ShardedStorage {
+    fn get(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.load(pubkey)
+    }
+
+    fn insert(&mut self, pubkey: Pubkey, account: AccountSharedData) -> Option<AccountSharedData> {
+        self.shard_mut(&pubkey).insert(pubkey, account)
+    }
+
+    fn remove(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.shard_mut(pubkey).remove(pubkey)
+    }
+
+    fn len(&self) -> usize {
+        (0..self.shards.len())
+            .map(|index| self.read(index).len())
+            .sum()
+    }
+
+    /// Copies out one shard at a time, so a shard is never locked while
+    /// the caller holds its accounts.
+    fn iter(&self) -> Box<dyn Iterator<Item = (Pubkey, AccountSharedData)> + '_> {
+        Box::new((0..self.shards.len()).flat_map(|index| {
+            let shard: Vec<_> = self
+                .read(index)
+                .iter()
+                .map(|(pubkey, account)| (*pubkey, account.clone()))
+                .collect();
+            shard
+        }))
+    }
+
+    fn fork(&self) -> Box<dyn AccountsStorage> {
+        Box::new(Self {
+            shards: (0..self.shards.len())
+                .map(|index| RwLock::new(self.read(index).clone()))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(feature = "sled")]
+pub use self::sled_storage::SledStorage;
+
+#[cfg(feature = "sled")]
+mod sled_storage {
+    use {
+        super::AccountsStorage,
+        crate::accounts_db::{from_snapshot_account, to_snapshot_account, SnapshotAccount},
+        solana_account::AccountSharedData,
+        solana_pubkey::Pubkey,
+        std::{
+            path::Path,
+            sync::atomic::{AtomicU64, Ordering},
+        },
+    };
+
+    /// Accounts in a sled database on disk, encoded like the accounts of a
+    /// snapshot file.
+    ///
+    /// The database is scratch space for one run: each fork gets a tree
+    /// of its own, dropped with it, and the files are removed once the
+    /// last fork is. [Snapshot files](crate::accounts_db::AccountsDb::snapshot_to_file)
+    /// and the [write-ahead log](crate::accounts_wal) are what outlive a
+    /// run.
+    ///
+    /// # Panics
+    ///
+    /// Every method panics if the database fails, as an in-memory map
+    /// failing to allocate would.
+    #[derive(Debug)]
+    pub struct SledStorage {
+        db: sled::Db,
+        tree: sled::Tree,
+        /// Counted here, since sled counts a tree by walking it.
+        len: usize,
+    }
+
+    impl SledStorage {
+        /// Opens an empty storage in a database at `path`, which must not
+        /// be in use.
+        pub fn open(path: impl AsRef<Path>) -> sled::Result<Self> {
+            let db = sled::Config::new().path(path).temporary(true).open()?;
+            Self::new_tree(db)
+        }
+
+        fn new_tree(db: sled::Db) -> sled::Result<Self> {
+            static TREES: AtomicU64 = AtomicU64::new(0);
+            let name = format!("accounts-{}", TREES.fetch_add(1, Ordering::Relaxed));
+            let tree = db.open_tree(name)?;
+            tree.clear()?;
+            Ok(Self { db, tree, len: 0 })
+        }
+    }
+
+    fn decode(value: &[u8]) -> (Pubkey, AccountSharedData) {
+        let account: SnapshotAccount =
+            bincode::deserialize(value).expect("sled holds encoded accounts");
+        from_snapshot_account(account)
+    }
+
+    fn encode(pubkey: &Pubkey, account: &AccountSharedData) -> Vec<u8> {
+        bincode::serialize(&to_snapshot_account(pubkey, account)).expect("accounts serialize")
+    }
+
+    impl AccountsStorage for SledStorage {
+        fn get(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+            let value = self.tree.get(pubkey).expect("sled read failed")?;
+            Some(decode(&value).1)
+        }
+
+        fn insert(
+            &mut self,
+            pubkey: Pubkey,
+            account: AccountSharedData,
+        ) -> Option<AccountSharedData> {
+            let previous = self
+                .tree
+                .insert(pubkey, encode(&pubkey, &account))
+                .expect("sled write failed")
+                .map(|value| decode(&value).1);
+            if previous.is_none() {
+                self.len += 1;
+            }
+            previous
+        }
+
+        fn remove(&mut self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+            let previous = self
+                .tree
+                .remove(pubkey)
+                .expect("sled write failed")
+                .map(|value| decode(&value).1);
+            if previous.is_some() {
+                self.len -= 1;
+            }
+            previous
+        }
+
+        fn len(&self) -> usize {
+            self.len
+        }
+
+        fn iter(&self) -> Box<dyn Iterator<Item = (Pubkey, AccountSharedData)> + '_> {
+            Box::new(
+                self.tree
+                    .iter()
+                    .values()
+                    .map(|value| decode(&value.expect("sled read failed"))),
+            )
+        }
+
+        fn fork(&self) -> Box<dyn AccountsStorage> {
+            let mut fork = Self::new_tree(self.db.clone()).expect("sled write failed");
+            let mut batch = sled::Batch::default();
+            for entry in self.tree.iter() {
+                let (key, value) = entry.expect("sled read failed");
+                batch.insert(key, value);
+            }
+            fork.tree.apply_batch(batch).expect("sled write failed");
+            fork.len = self.len;
+            Box::new(fork)
+        }
+    }
+
+    impl Drop for SledStorage {
+        fn drop(&mut self) {
+            // Failing to drop scratch space is not worth a panic in drop.
+            let _ = self.db.drop_tree(self.tree.name());
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Write-ahead log of the accounts every slot commits.
+//!
+//! An [`AccountsWal`] attached to a bank with
+//! [`Bank::set_accounts_wal`](crate::bank::Bank::set_accounts_wal) gets the
+//! accounts of every slot the bank freezes appended, synced to disk before
+//! the freeze returns. After a crash, [`recover_from_wal`] rebuilds the
+//! accounts store as the last slot that made it to disk left it, and
+//! [`AccountsWal::open`] goes on appending where the log ends.
+//!
+//! The file starts with a magic and a format version, like an accounts
+//! snapshot. Every slot follows as one record: the length of its bincode
+//! payload, the payload itself, which is the slot and every account it
+//! wrote with closed ones at zero lamports, and the SHA-256 of the
+//! payload. A record cut short or failing its checksum is one a crash
+//! interrupted, so the log ends before it.
+
+use {
+    crate::accounts_db::{from_snapshot_account, to_snapshot_account, AccountsDb, SnapshotAccount},
+    solana_account::AccountSharedData,
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    std::{
+        fs::{File, OpenOptions},
+        io::{self, BufReader, Read, Write},
+        path::Path,
+        sync::{Mutex, MutexGuard},
+    },
+};
+
+const WAL_MAGIC: &[u8; 7] = b"SVMAWAL";
+const WAL_VERSION: u8 = 1;
+const HEADER_LEN: u64 = WAL_MAGIC.len() as u64 + 1;
+const CHECKSUM_LEN: usize = 32;
+
+/// The payload of a record: a slot and the accounts it wrote.
+type WalRecord = (Slot, Vec<SnapshotAccount>);
+
+/// Why a write-ahead log could not be written or recovered.
+#[derive(Debug, thiserror::Error)]
+pub enum WalError {
+    #[error("write-ahead log I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("file is not an accounts write-ahead log")]
+    InvalidHeader,
+    #[error("unsupported write-ahead log version {0}")]
+    UnsupportedVersion(u8),
+    #[error("write-ahead log is corrupted")]
+    Corrupted,
+}
+
+/// The accounts store [`recover_from_wal`] rebuilt.
+#[derive(Debug)]
+pub struct RecoveredAccounts {
+    /// The accounts as the last slot of the log left them, with no writes
+    /// tracked.
+    pub accounts_db: AccountsDb,
+    /// Last slot the log holds, if it holds any.
+    pub last_slot: Option<Slot>,
+}
+
+/// Rebuilds the accounts store from the log at `path`, replaying every
+/// complete record in order.
+///
+/// A record a crash cut short ends the log; a complete record that does
+/// not decode, or a slot that does not follow the one before, fails with
+/// [`WalError::Corrupted`].
+pub fn recover_from_wal(path: impl AsRef<Path>) -> Result<RecoveredAccounts, WalError> {
+    read_wal(path.as_ref()).map(|(recovered, _)| recovered)
+}
+
+/// Account writes journaled slot by slot to a file, shared by the banks of
+/// one fork.
+#[derive(Debug)]
+pub struct AccountsWal {
+    inner: Mutex<WalWriter>,
+}
+
+#[derive(Debug)]
+struct WalWriter {
+    file: File,
+    last_slot: Option<Slot>,
+}
+
+impl AccountsWal {
+    /// Starts an empty log at `path`, replacing the file if it exists.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, WalError> {
+        let mut file = File::create(path)?;
+        file.write_all(WAL_MAGIC)?;
+        file.write_all(&[WAL_VERSION])?;
+        file.sync_all()?;
+        Ok(Self::with_file(file, None))
+    }
+
+    /// Opens the log at `path` to append to, cutting off the record a crash
+    /// interrupted, if any.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, WalError> {
+        let path = path.as_ref();
+        let (recovered, intact_len) = read_wal(path)?;
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(intact_len)?;
+        file.sync_all()?;
+        Ok(Self::with_file(file, recovered.last_slot))
+    }
+
+    /// Last slot appended, if any was.
+    pub fn last_slot(&self) -> Option<Slot> {
+        self.lock().last_slot
+    }
+
+    /// Appends the state `slot` left `accounts` in and syncs it to disk;
+    /// `None` means the account was closed.
+    ///
+    /// # Panics
+    ///
+    /// If `slot` is not after the last appended slot.
+    pub fn append(
+        &self,
+        slot: Slot,
+        accounts: impl IntoIterator<Item = (Pubkey, Option<AccountSharedData>)>,
+    ) -> Result<(), WalError> {
+        let mut writer = self.lock();
+        assert!(
+            writer.last_slot.is_none_or(|last_slot| slot > last_slot),
+            "slot {slot} is appended after a later slot"
+        );
+        let accounts: Vec<SnapshotAccount> = accounts
+            .into_iter()
+            .map(|(pubkey, account)| to_snapshot_account(&pubkey, &account.unwrap_or_default()))
+            .collect();
+        let payload =
+            bincode::serialize::<WalRecord>(&(slot, accounts)).expect("accounts serialize");
+        let mut record = Vec::with_capacity(8 + payload.len() + CHECKSUM_LEN);
+        record.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        record.extend_from_slice(&payload);
+        record.extend_from_slice(solana_sha256_hasher::hash(&payload).as_ref());
+        writer.file.write_all(&record)?;
+        writer.file.sync_data()?;
+        writer.last_slot = Some(slot);
+        Ok(())
+    }
+
+    fn with_file(file: File, last_slot: Option<Slot>) -> Self {
+        Self {
+            inner: Mutex::new(WalWriter { file, last_slot }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, WalWriter> {
+        self.inner
+            .lock()
+            .expect("write-ahead log lock is never poisoned")
+    }
+}
+
+/// Replays the log at `path`, returning what it rebuilt and the length of
+/// the log up to its last complete record.
+fn read_wal(path: &Path) -> Result<(RecoveredAccounts, u64), WalError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0; HEADER_LEN as usize];
+    if !read_all(&mut reader, &mut header)? || &header[..WAL_MAGIC.len()] != WAL_MAGIC {
+        return Err(WalError::InvalidHeader);
+    }
+    let version = header[WAL_MAGIC.len()];
+    if version != WAL_VERSION {
+        return Err(WalError::UnsupportedVersion(version));
+    }
+
+    let mut accounts_db = AccountsDb::new();
+    let mut last_slot = None;
+    let mut intact_len = HEADER_LEN;
+    while let Some(payload) = read_record(&mut reader)? {
+        let (slot, accounts): WalRecord =
+            bincode::deserialize(&payload).map_err(|_| WalError::Corrupted)?;
+        if last_slot.is_some_and(|last_slot| slot <= last_slot) {
+            return Err(WalError::Corrupted);
+        }
+        for account in accounts {
+            let (pubkey, account) = from_snapshot_account(account);
+            accounts_db.store_account(pubkey, account);
+        }
+        last_slot = Some(slot);
+        intact_len += (8 + payload.len() + CHECKSUM_LEN) as u64;
+    }
+    accounts_db.checkpoint();
+    Ok((
+        RecoveredAccounts {
+            accounts_db,
+            last_slot,
+        },
+        intact_len,
+    ))
+}
+
+/// Reads the payload of the next record, or `None` where the complete
+/// records end.
+fn read_record(reader: &mut impl Read) -> Result<Option<Vec<u8>>, WalError> {
+    let mut len = [0; 8];
+    if !read_all(reader, &mut len)? {
+        return Ok(None);
+    }
+    let len = u64::from_le_bytes(len);
+    // A length torn by a crash may be anything, so the payload is read
+    // only as far as the file goes.
+    let mut payload = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut payload)?;
+    let mut checksum = [0; CHECKSUM_LEN];
+    if payload.len() as u64 != len || !read_all(reader, &mut checksum)? {
+        return Ok(None);
+    }
+    if solana_sha256_hasher::hash(&payload).as_ref() != checksum {
+        return Ok(None);
+    }
+    Ok(Some(payload))
+}
+
+/// Fills `buf`, or returns `false` if the reader ends first.
+fn read_all(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,103 @@
+//! Address lookup table resolution for v0 transactions.
+//!
+//! A v0 message can reference accounts by index into on-chain address
+//! lookup tables (ALTs). Resolving those indexes against the accounts store
+//! yields the full key list, which a [`SanitizedTransaction`] carries from
+//! then on: lock extraction and execution both see the looked up keys as
+//! regular message accounts.
+
+use {
+    crate::{accounts_db::AccountsDb, sanitize::sanitize_transaction},
+    solana_account::ReadableAccount,
+    solana_address_lookup_table_interface::{error::AddressLookupError, state::AddressLookupTable},
+    solana_clock::Slot,
+    solana_message::{
+        v0::{LoadedAddresses, MessageAddressTableLookup},
+        AddressLoader,
+    },
+    solana_sdk_ids::address_lookup_table,
+    solana_slot_hashes::SlotHashes,
+    solana_transaction::{sanitized::SanitizedTransaction, versioned::VersionedTransaction},
+    solana_transaction_error::{AddressLoaderError, TransactionError},
+};
+
+/// Loads lookup table addresses from an [`AccountsDb`] at a given slot.
+///
+/// Slot hashes are not tracked yet, so a deactivated table stops resolving
+/// as soon as the slot it was deactivated in has passed, instead of after
+/// the usual cool-down.
+#[derive(Clone, Copy)]
+pub struct AccountsDbAddressLoader<'a> {
+    accounts_db: &'a AccountsDb,
+    slot: Slot,
+}
+
+impl<'a> AccountsDbAddressLoader<'a> {
+    pub fn new(accounts_db: &'a AccountsDb, slot: Slot) -> Self {
+        Self { accounts_db, slot }
+    }
+
+    /// Resolves the writable and readonly indexes of a single lookup.
+    pub fn load_lookup_table_addresses(
+        &self,
+        lookup: &MessageAddressTableLookup,
+    ) -> Result<LoadedAddresses, AddressLookupError> {
+        let account = self
+            .accounts_db
+            .get_account(&lookup.account_key)
+            .ok_or(AddressLookupError::LookupTableAccountNotFound)?;
+        if *account.owner() != address_lookup_table::id() {
+            return Err(AddressLookupError::InvalidAccountOwner);
+        }
+
+        let lookup_table = AddressLookupTable::deserialize(account.data())
+            .map_err(|_| AddressLookupError::InvalidAccountData)?;
+        let slot_hashes = SlotHashes::default();
+        Ok(LoadedAddresses {
+            writable: lookup_table.lookup(self.slot, &lookup.writable_indexes, &slot_hashes)?,
+            readonly: lookup_table.lookup(self.slot, &lookup.readonly_indexes, &slot_hashes)?,
+        })
+    }
+}
+
+impl AddressLoader for AccountsDbAddressLoader<'_> {
+    /// Concatenates the addresses of every lookup, in lookup order. All
+    /// writable addresses come before all readonly ones, matching how a v0
+    /// message orders its loaded keys.
+    fn load_addresses(
+        self,
+        lookups: &[MessageAddressTableLookup],
+    ) -> Result<LoadedAddresses, AddressLoaderError> {
+        lookups
+            .iter()
+            .map(|lookup| {
+                self.load_lookup_table_addresses(lookup)
+                    .map_err(into_address_loader_error)
+            })
+            .collect()
+    }
+}
+
+/// Sanitizes `transaction`, resolving its address table lookups against
+/// `accounts_db` at `slot`.
+///
+/// Sanitize errors are reported as the [`TransactionError`] they map to.
+pub fn resolve_transaction(
+    accounts_db: &AccountsDb,
+    slot: Slot,
+    transaction: VersionedTransaction,
+) -> Result<SanitizedTransaction, TransactionError> {
+    sanitize_transaction(transaction, AccountsDbAddressLoader::new(accounts_db, slot))
+        .map_err(TransactionError::from)
+}
+
+fn into_address_loader_error(err: AddressLookupError) -> AddressLoaderError {
+    match err {
+        AddressLookupError::LookupTableAccountNotFound => {
+            AddressLoaderError::LookupTableAccountNotFound
+        }
+        AddressLookupError::InvalidAccountOwner => AddressLoaderError::InvalidAccountOwner,
+        AddressLookupError::InvalidAccountData => AddressLoaderError::InvalidAccountData,
+        AddressLookupError::InvalidLookupIndex => AddressLoaderError::InvalidLookupIndex,
+    }
+}
@@ -0,0 +1,66 @@
+use {solana_clock::MAX_RECENT_BLOCKHASHES, solana_hash::Hash, std::collections::HashMap};
+
+/// The most recent blockhashes, used to reject transactions that are too old.
+///
+/// Every registered hash gets the next index. The age of a hash is how many
+/// hashes were registered after it, and hashes older than `max_age` are
+/// evicted.
+#[derive(Clone, Debug)]
+pub struct BlockhashQueue {
+    last_hash_index: u64,
+    last_hash: Option<Hash>,
+    hashes: HashMap<Hash, u64>,
+    max_age: usize,
+}
+
+impl Default for BlockhashQueue {
+    fn default() -> Self {
+        Self::new(MAX_RECENT_BLOCKHASHES)
+    }
+}
+
+impl BlockhashQueue {
+    pub fn new(max_age: usize) -> Self {
+        Self {
+            last_hash_index: 0,
+            last_hash: None,
+            hashes: HashMap::new(),
+            max_age,
+        }
+    }
+
+    /// The most recently registered hash.
+    pub fn last_hash(&self) -> Option<Hash> {
+        self.last_hash
+    }
+
+    /// Number of hashes registered after `hash`, if it is still queued.
+    pub fn get_hash_age(&self, hash: &Hash) -> Option<u64> {
+        self.hashes
+            .get(hash)
+            .map(|hash_index| self.last_hash_index - hash_index)
+    }
+
+    /// Whether `hash` is queued and at most `max_age` hashes old.
+    pub fn is_hash_valid_for_age(&self, hash: &Hash, max_age: usize) -> bool {
+        self.get_hash_age(hash)
+            .is_some_and(|age| age <= max_age as u64)
+    }
+
+    /// Every queued hash, newest first.
+    pub fn get_recent_blockhashes(&self) -> Vec<Hash> {
+        let mut hashes: Vec<_> = self.hashes.iter().collect();
+        hashes.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+        hashes.into_iter().map(|(hash, _)| *hash).collect()
+    }
+
+    pub fn register_hash(&mut self, hash: Hash) {
+        self.last_hash_index += 1;
+        let max_age = self.max_age as u64;
+        let last_hash_index = self.last_hash_index;
+        self.hashes
+            .retain(|_, hash_index| last_hash_index - *hash_index <= max_age);
+        self.hashes.insert(hash, self.last_hash_index);
+        self.last_hash = Some(hash);
+    }
+}
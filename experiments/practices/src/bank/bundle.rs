@@ -0,0 +1,185 @@
+use {
+    super::{include_fee_in_diffs, Bank, NonceInfo},
+    crate::{
+        cost_model::{CostModel, TransactionCost},
+        events::DropReason,
+        scheduler::{Bundle, BundleError},
+        svm::{
+            AccountLoader, AccountOverrides, OverriddenAccountLoader, TransactionExecutionResult,
+        },
+    },
+    solana_account::{ReadableAccount, WritableAccount},
+    solana_clock::MAX_PROCESSING_AGE,
+    solana_transaction::sanitized::SanitizedTransaction,
+};
+
+impl Bank {
+    /// Checks, executes and commits the transactions of `bundle` in order,
+    /// all of them or none.
+    ///
+    /// Every transaction goes through the same checks as in
+    /// [`process_transaction_batch`](Self::process_transaction_batch) and
+    /// sees the writes, fees, write demand and nonce advances of the ones
+    /// before it. They
+    /// execute against a view of the bank that nothing else writes to, and
+    /// only once every one of them succeeded is the view committed. If one
+    /// is rejected or fails, the bundle leaves no trace at all: no account
+    /// is written, no fee charged, no cost counted and nothing recorded in
+    /// the [`StatusCache`](crate::status_cache::StatusCache), so each of
+    /// its transactions can still be processed later.
+    ///
+    /// # Panics
+    ///
+    /// If the bank is [frozen](Self::freeze).
+    pub fn process_bundle(
+        &mut self,
+        bundle: &Bundle,
+    ) -> Result<Vec<TransactionExecutionResult>, BundleError> {
+        assert!(!self.is_frozen(), "a frozen bank processes no transactions");
+        let transactions = bundle.transactions();
+        let checked = self
+            .check_transactions(transactions, MAX_PROCESSING_AGE)
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| {
+                result.map_err(|err| BundleError::TransactionFailed { index, err })
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let nonce_infos = match checked {
+            Ok(nonce_infos) => nonce_infos,
+            Err(err) => {
+                self.publish_bundle_dropped(transactions, &err);
+                return Err(err);
+            }
+        };
+
+        let mut costs = Vec::with_capacity(transactions.len());
+        let executed = self.execute_bundle(bundle, &nonce_infos, &mut costs);
+        let results = match executed {
+            Ok(results) => results,
+            Err(err) => {
+                for cost in &costs {
+                    self.cost_tracker.remove_transaction_cost(cost);
+                }
+                if let BundleError::TransactionFailed { index, .. } = err {
+                    for transaction in &transactions[..index] {
+                        self.write_demand.remove(transaction);
+                    }
+                }
+                self.publish_bundle_dropped(transactions, &err);
+                return Err(err);
+            }
+        };
+
+        for (index, (transaction, result)) in transactions.iter().zip(&results).enumerate() {
+            let lamports_before = self.touched_lamports(transaction);
+            self.collected_fees += result.fee_details.total_fee();
+            self.cost_tracker
+                .update_execution_cost(&costs[index], result.consumed_units);
+            self.commit_transaction(transaction, result);
+            self.status_cache()
+                .insert_transaction(transaction, self.slot, result.status.clone());
+            if let Some(nonce_info) = &nonce_infos[index] {
+                self.advance_nonce(nonce_info);
+                self.notify_stored_account(transaction, nonce_info.address());
+            }
+            self.check_lamports_conserved(
+                transaction,
+                lamports_before,
+                result.fee_details.total_fee(),
+            );
+            self.notify_transaction(transaction, result);
+            self.publish_committed(transaction, result);
+        }
+        Ok(results)
+    }
+
+    /// Publishes that the transactions of a bundle that failed with `err`
+    /// were dropped.
+    fn publish_bundle_dropped(&self, transactions: &[SanitizedTransaction], err: &BundleError) {
+        let BundleError::TransactionFailed { index, err } = err else {
+            return;
+        };
+        for (member, transaction) in transactions.iter().enumerate() {
+            let reason = if member == *index {
+                DropReason::Rejected(err.clone())
+            } else {
+                DropReason::BundleFailed
+            };
+            self.publish_dropped(transaction, reason);
+        }
+    }
+
+    /// Executes the transactions of `bundle` one after another on top of
+    /// the stored accounts, without storing anything.
+    ///
+    /// The cost of every transaction that got to execute is added to the
+    /// cost tracker and to `costs`, and the demand of every successful one
+    /// to the slot's write demand.
+    fn execute_bundle(
+        &mut self,
+        bundle: &Bundle,
+        nonce_infos: &[Option<NonceInfo>],
+        costs: &mut Vec<TransactionCost>,
+    ) -> Result<Vec<TransactionExecutionResult>, BundleError> {
+        // The bundle's writes so far.
+        let mut written = AccountOverrides::new();
+        let mut results = Vec::with_capacity(bundle.len());
+        for (index, transaction) in bundle.transactions().iter().enumerate() {
+            let failed = |err| BundleError::TransactionFailed { index, err };
+            let cost = CostModel::calculate_cost_with_features(transaction, self.feature_set());
+            self.cost_tracker
+                .try_add(&cost)
+                .map_err(|err| failed(err.into()))?;
+            costs.push(cost);
+
+            let loader = OverriddenAccountLoader {
+                overrides: &written,
+                loader: &self.accounts_db,
+            };
+            let fee_details = self
+                .validate_fee_payer_in(&loader, transaction)
+                .map_err(failed)?;
+            let fee_payer = transaction.message().fee_payer();
+            let mut payer = loader
+                .load_account(fee_payer)
+                .expect("the fee payer was validated");
+            payer.set_lamports(payer.lamports() - fee_details.total_fee());
+            written.set_account(*fee_payer, payer);
+
+            let loader = OverriddenAccountLoader {
+                overrides: &written,
+                loader: &self.accounts_db,
+            };
+            let mut result = self
+                .executor
+                .load_and_execute_transaction(&loader, transaction);
+            result.status.clone().map_err(failed)?;
+            self.write_demand.record(transaction);
+            result.pre_balances[0] += fee_details.total_fee();
+            if let Some(account_diffs) = &mut result.account_diffs {
+                include_fee_in_diffs(account_diffs, &result.post_accounts[0], &fee_details);
+            }
+            result.fee_details = fee_details;
+
+            let message = transaction.message();
+            for (account_index, (pubkey, account)) in result.post_accounts.iter().enumerate() {
+                if message.is_writable(account_index) {
+                    written.set_account(*pubkey, account.clone());
+                }
+            }
+            if let Some(nonce_info) = &nonce_infos[index] {
+                let loader = OverriddenAccountLoader {
+                    overrides: &written,
+                    loader: &self.accounts_db,
+                };
+                if let Some(mut account) = loader.load_account(nonce_info.address()) {
+                    nonce_info.advance(&mut account);
+                    written.set_account(*nonce_info.address(), account);
+                }
+            }
+            results.push(result);
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,77 @@
+use {
+    super::Bank, solana_account::ReadableAccount,
+    solana_transaction::sanitized::SanitizedTransaction,
+};
+
+/// Lamports the writable accounts of a transaction held before the bank
+/// charged and committed it.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct TouchedLamports(u128);
+
+impl Bank {
+    /// Whether every transaction is checked not to create or destroy
+    /// lamports. On by default in debug builds.
+    pub fn verify_lamports(&self) -> bool {
+        self.verify_lamports
+    }
+
+    /// Turns checking that lamports are conserved by every charged and
+    /// committed transaction on or off.
+    ///
+    /// The check sums the lamports of the accounts a transaction may write
+    /// before its fee is charged and after it is committed, and panics,
+    /// naming the transaction's signature, unless the difference is exactly
+    /// the fee. It costs a lookup per writable account, so release builds
+    /// leave it off unless asked.
+    pub fn set_verify_lamports(&mut self, verify_lamports: bool) {
+        self.verify_lamports = verify_lamports;
+    }
+
+    /// The lamports `transaction`'s writable accounts hold now, if the
+    /// bank verifies lamports.
+    pub(super) fn touched_lamports(
+        &self,
+        transaction: &SanitizedTransaction,
+    ) -> Option<TouchedLamports> {
+        self.verify_lamports
+            .then(|| TouchedLamports(self.sum_writable_lamports(transaction)))
+    }
+
+    /// Checks that `transaction`'s writable accounts hold what they held
+    /// `before`, less the `burned` lamports of its fee.
+    ///
+    /// # Panics
+    ///
+    /// If they do not.
+    pub(super) fn check_lamports_conserved(
+        &self,
+        transaction: &SanitizedTransaction,
+        before: Option<TouchedLamports>,
+        burned: u64,
+    ) {
+        let Some(TouchedLamports(before)) = before else {
+            return;
+        };
+        let after = self.sum_writable_lamports(transaction);
+        assert!(
+            after + u128::from(burned) == before,
+            "transaction {} changed the lamports of its accounts from {before} to {after} with \
+             {burned} lamports of fees",
+            transaction.signature(),
+        );
+    }
+
+    /// Sum of the writable accounts' lamports, which cannot overflow even
+    /// if each holds `u64::MAX`.
+    fn sum_writable_lamports(&self, transaction: &SanitizedTransaction) -> u128 {
+        let message = transaction.message();
+        message
+            .account_keys()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| message.is_writable(*index))
+            .filter_map(|(_, pubkey)| self.accounts_db.get_account(pubkey))
+            .map(|account| u128::from(account.lamports()))
+            .sum()
+    }
+}
@@ -0,0 +1,1385 @@
+//! The bank: account state for a slot plus the stages that update it.
+//!
+//! A [`Bank`] is where scheduling and execution meet. It checks that
+//! transactions are recent enough, splits them into conflict-free batches,
+//! charges each transaction's fee, executes every batch against its
+//! accounts and commits what succeeded. Transactions can also be simulated
+//! against the bank, optionally with some accounts overridden, without
+//! committing anything, and a [`Bundle`](crate::scheduler::Bundle) of
+//! transactions commits all together or not at all.
+//!
+//! A bank covers a single slot. It keeps the accounts lattice hash up to
+//! date from the accounts the slot wrote, and freezing it ends the slot
+//! with a bank hash that commits to the parent's bank hash, the accounts
+//! and the last blockhash. Rent can optionally be collected from accounts
+//! below their rent-exempt minimum when an epoch starts. Every epoch that
+//! ends adds the stake of every delegation, effective, activating or
+//! deactivating, to the stake history, which the StakeHistory sysvar
+//! serves alongside SlotHashes. The first block of an epoch then
+//! calculates the inflation rewards of the epoch that ended, which the
+//! blocks after it pay out to stake accounts partition by partition.
+//!
+//! The cost of every slot's transactions is tracked against the block
+//! limits, along with how often each account was write-locked, for a
+//! [`LocalFeeMarket`] to price.
+//!
+//! Every transaction the bank executes is recorded in a [`StatusCache`]
+//! shared with its forks, so neither the bank nor its descendants process
+//! the same transaction twice. [`AccountsNotifier`]s attached to a bank
+//! hear about what it commits and the slots it moves through, and an
+//! [`EventBus`] set on it streams what it schedules, commits and drops.
+//! In debug builds the bank also checks that every transaction it commits
+//! leaves its accounts with the lamports they held, less the fee.
+//!
+//! Durable nonce transactions pass the age check through their nonce
+//! account instead of a recent blockhash, and advance it even if they
+//! fail.
+
+mod blockhash_queue;
+mod bundle;
+mod lamports_invariant;
+mod nonce_info;
+mod partitioned_rewards;
+mod simulation;
+
+pub use {
+    blockhash_queue::BlockhashQueue,
+    nonce_info::{get_durable_nonce, load_message_nonce_info, NonceInfo},
+    partitioned_rewards::{
+        EpochRewards, StakeReward, MAX_FACTOR_OF_REWARD_BLOCKS_IN_EPOCH,
+        STAKE_ACCOUNT_STORES_PER_BLOCK,
+    },
+    simulation::SimulationResult,
+};
+
+use {
+    crate::{
+        accounts::{AccountLoadMetrics, AccountLocks, LockSet, LockSetConfig, PrefetchedAccounts},
+        accounts_db::{AccountsDb, AccountsVersions},
+        accounts_hash::{
+            calculate_accounts_delta_hash, calculate_accounts_delta_lt_hash,
+            calculate_accounts_lt_hash, LtHash,
+        },
+        accounts_wal::AccountsWal,
+        address_lookup_table,
+        cost_model::{CostModel, CostTracker},
+        events::{DropReason, Event, EventBus},
+        feature_set::FeatureSet,
+        fees::{FeeDetails, FeeStructure},
+        local_fee_market::{LocalFeeMarket, WriteDemand},
+        plugin::{AccountsNotifier, SlotStatus},
+        rent_collector::{CollectedInfo, RentCollector, RentState},
+        scheduler::{PriorityGraphScheduler, Scheduler, SchedulerMetrics},
+        spans,
+        status_cache::{Ancestors, StatusCache, TransactionStatus},
+        svm::{
+            AccountDiff, AccountLoader, ComputeCostTable, EnvironmentConfig, InvokeContext,
+            ProgramCacheStats, SysvarCache, SysvarSource, TransactionAccount,
+            TransactionExecutionResult, TransactionExecutor,
+        },
+        transaction_status::TransactionExecutionDetails,
+    },
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_clock::{Epoch, Slot, MAX_PROCESSING_AGE},
+    solana_epoch_schedule::EpochSchedule,
+    solana_hash::Hash,
+    solana_inflation::Inflation,
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::{bpf_loader, stake, system_program},
+    solana_signature::Signature,
+    solana_slot_hashes::SlotHashes,
+    solana_stake_interface::{
+        stake_history::{StakeHistory, StakeHistoryEntry},
+        state::StakeStateV2,
+    },
+    solana_transaction::{sanitized::SanitizedTransaction, versioned::VersionedTransaction},
+    solana_transaction_error::TransactionError,
+    std::{
+        collections::{BTreeMap, HashSet},
+        sync::{Arc, Mutex, MutexGuard},
+    },
+};
+
+/// Outcome of handing a transaction to the bank.
+///
+/// `Err` means the transaction was rejected before execution and left no
+/// trace; `Ok` holds the execution result, which may itself have failed.
+pub type TransactionProcessingResult = Result<TransactionExecutionResult, TransactionError>;
+
+/// Account state, recent blockhashes and sysvars for a slot.
+pub struct Bank {
+    slot: Slot,
+    /// Slot of the bank this one was created from, if any.
+    parent_slot: Option<Slot>,
+    /// Slots of this bank's fork since its last root, its own included.
+    ancestors: Ancestors,
+    accounts_db: AccountsDb,
+    /// Lattice hash of the accounts as of the start of the current slot.
+    accounts_lt_hash: LtHash,
+    /// Bank hash of the previous slot.
+    parent_bank_hash: Hash,
+    /// Bank hash of the current slot, once it is frozen.
+    bank_hash: Option<Hash>,
+    blockhash_queue: BlockhashQueue,
+    epoch_schedule: EpochSchedule,
+    rent: Rent,
+    /// The last blockhash of every earlier slot, standing in for its bank
+    /// hash.
+    slot_hashes: SlotHashes,
+    /// Stake of every epoch that ended, newest first.
+    stake_history: StakeHistory,
+    /// Inflation schedule epoch rewards are calculated with.
+    inflation: Inflation,
+    /// Most stake accounts a block pays rewards to.
+    stake_rewards_per_block: u64,
+    /// Rewards of the last epoch they were calculated for.
+    epoch_rewards: Option<EpochRewards>,
+    /// Whether every epoch starts with a rent collection sweep.
+    rent_collection_enabled: bool,
+    /// Rent collected by every sweep so far.
+    collected_rent: CollectedInfo,
+    fee_structure: FeeStructure,
+    /// Fees charged to the transactions of the current slot.
+    collected_fees: u64,
+    /// How the transactions of every slot that processed any scheduled.
+    scheduler_metrics: BTreeMap<Slot, SchedulerMetrics>,
+    /// How the accounts of every slot that processed any were loaded.
+    account_load_metrics: BTreeMap<Slot, AccountLoadMetrics>,
+    /// Cost of the transactions of the current slot.
+    cost_tracker: CostTracker,
+    local_fee_market: Option<Arc<dyn LocalFeeMarket>>,
+    /// Write locks of the transactions charged in the current slot.
+    write_demand: WriteDemand,
+    /// Transactions processed by this bank and every bank it was forked
+    /// from or into.
+    status_cache: Arc<Mutex<StatusCache>>,
+    notifiers: Vec<Arc<dyn AccountsNotifier>>,
+    event_bus: Option<EventBus>,
+    /// Where every frozen slot's accounts are published for scans.
+    accounts_versions: Option<Arc<AccountsVersions>>,
+    /// Where every frozen slot's writes are journaled.
+    accounts_wal: Option<Arc<AccountsWal>>,
+    executor: TransactionExecutor,
+    /// Whether every transaction is checked to conserve lamports.
+    verify_lamports: bool,
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Self::new(AccountsDb::default())
+    }
+}
+
+impl Bank {
+    /// Creates the bank for slot 0 on top of `accounts_db`.
+    ///
+    /// The genesis blockhash is `Hash::default()`, the blockhash
+    /// `Message::new` fills in, so freshly built transactions are accepted
+    /// until it ages out.
+    pub fn new(mut accounts_db: AccountsDb) -> Self {
+        let mut blockhash_queue = BlockhashQueue::default();
+        blockhash_queue.register_hash(Hash::default());
+        accounts_db.checkpoint();
+        let mut bank = Self {
+            slot: 0,
+            parent_slot: None,
+            ancestors: Ancestors::from([0]),
+            accounts_lt_hash: calculate_accounts_lt_hash(&accounts_db),
+            accounts_db,
+            parent_bank_hash: Hash::default(),
+            bank_hash: None,
+            blockhash_queue,
+            epoch_schedule: EpochSchedule::default(),
+            rent: Rent::default(),
+            slot_hashes: SlotHashes::default(),
+            stake_history: StakeHistory::default(),
+            inflation: Inflation::default(),
+            stake_rewards_per_block: STAKE_ACCOUNT_STORES_PER_BLOCK,
+            epoch_rewards: None,
+            rent_collection_enabled: false,
+            collected_rent: CollectedInfo::default(),
+            fee_structure: FeeStructure::default(),
+            collected_fees: 0,
+            scheduler_metrics: BTreeMap::new(),
+            account_load_metrics: BTreeMap::new(),
+            cost_tracker: CostTracker::default(),
+            local_fee_market: None,
+            write_demand: WriteDemand::default(),
+            status_cache: Arc::default(),
+            notifiers: Vec::new(),
+            event_bus: None,
+            accounts_versions: None,
+            accounts_wal: None,
+            executor: TransactionExecutor::new(),
+            verify_lamports: cfg!(debug_assertions),
+        };
+        bank.update_sysvar_cache();
+        bank
+    }
+
+    /// Creates a child of `parent` for `slot`, as if `parent`'s slot had
+    /// ended with `parent_blockhash` as its last blockhash. The parent must
+    /// be [frozen](Self::freeze) already, so what it published and what its
+    /// children start from cannot diverge.
+    ///
+    /// The child starts from a copy of the parent's accounts, blockhash
+    /// queue, sysvars and settings, so several children of the same parent
+    /// are independent forks. Slots between the parent's and `slot` are
+    /// skipped, as when their leaders produced no block; if `slot` starts a
+    /// new epoch, the parent's epoch enters the stake history and, if rent
+    /// collection is enabled, rent is collected first, and then the
+    /// parent's epoch is rewarded. A child pays the next partition of stake
+    /// rewards still unpaid.
+    /// Children share the parent's program cache, whose entries are keyed
+    /// by deployment, and its status cache, whose entries are keyed by
+    /// slot. They also notify the parent's notifiers.
+    ///
+    /// # Panics
+    ///
+    /// If the parent is not frozen, or `slot` is not after the parent's
+    /// slot.
+    pub fn new_from_parent(parent: &Bank, slot: Slot, parent_blockhash: Hash) -> Self {
+        assert!(parent.is_frozen(), "a child bank needs a frozen parent");
+        assert!(slot > parent.slot, "a child bank comes after its parent");
+        let mut bank = Self {
+            slot: parent.slot,
+            parent_slot: Some(parent.slot),
+            ancestors: parent.ancestors.clone(),
+            accounts_db: parent.accounts_db.clone(),
+            accounts_lt_hash: parent.accounts_lt_hash.clone(),
+            parent_bank_hash: parent.parent_bank_hash,
+            bank_hash: parent.bank_hash,
+            blockhash_queue: parent.blockhash_queue.clone(),
+            epoch_schedule: parent.epoch_schedule.clone(),
+            rent: parent.rent.clone(),
+            slot_hashes: SlotHashes::new(&parent.slot_hashes),
+            stake_history: parent.stake_history.clone(),
+            inflation: parent.inflation,
+            stake_rewards_per_block: parent.stake_rewards_per_block,
+            epoch_rewards: parent.epoch_rewards.clone(),
+            rent_collection_enabled: parent.rent_collection_enabled,
+            collected_rent: parent.collected_rent,
+            fee_structure: parent.fee_structure.clone(),
+            collected_fees: parent.collected_fees,
+            scheduler_metrics: parent.scheduler_metrics.clone(),
+            account_load_metrics: parent.account_load_metrics.clone(),
+            cost_tracker: parent.cost_tracker.clone(),
+            local_fee_market: parent.local_fee_market.clone(),
+            write_demand: WriteDemand::default(),
+            status_cache: parent.status_cache.clone(),
+            notifiers: parent.notifiers.clone(),
+            event_bus: parent.event_bus.clone(),
+            accounts_versions: parent.accounts_versions.clone(),
+            accounts_wal: parent.accounts_wal.clone(),
+            executor: parent.executor.clone(),
+            verify_lamports: parent.verify_lamports,
+        };
+        bank.start_slot(slot, parent_blockhash);
+        bank
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Slot of the bank this one was created from with
+    /// [`new_from_parent`](Self::new_from_parent).
+    pub fn parent_slot(&self) -> Option<Slot> {
+        self.parent_slot
+    }
+
+    pub fn epoch(&self) -> Epoch {
+        self.epoch_schedule.get_epoch(self.slot)
+    }
+
+    pub fn last_blockhash(&self) -> Hash {
+        self.blockhash_queue
+            .last_hash()
+            .expect("the genesis blockhash is always registered")
+    }
+
+    pub fn blockhash_queue(&self) -> &BlockhashQueue {
+        &self.blockhash_queue
+    }
+
+    pub fn epoch_schedule(&self) -> &EpochSchedule {
+        &self.epoch_schedule
+    }
+
+    /// Replaces the schedule slots are grouped into epochs with.
+    pub fn set_epoch_schedule(&mut self, epoch_schedule: EpochSchedule) {
+        self.epoch_schedule = epoch_schedule;
+        self.update_sysvar_cache();
+    }
+
+    pub fn rent(&self) -> &Rent {
+        &self.rent
+    }
+
+    /// Replaces the rent parameters transactions are checked against and
+    /// rent is collected with.
+    pub fn set_rent(&mut self, rent: Rent) {
+        self.rent = rent;
+        self.update_sysvar_cache();
+    }
+
+    pub fn slot_hashes(&self) -> &SlotHashes {
+        &self.slot_hashes
+    }
+
+    pub fn stake_history(&self) -> &StakeHistory {
+        &self.stake_history
+    }
+
+    pub fn inflation(&self) -> &Inflation {
+        &self.inflation
+    }
+
+    /// Replaces the inflation schedule epoch rewards are calculated with.
+    /// [`Inflation::new_disabled`] turns rewards off.
+    pub fn set_inflation(&mut self, inflation: Inflation) {
+        self.inflation = inflation;
+    }
+
+    pub fn stake_rewards_per_block(&self) -> u64 {
+        self.stake_rewards_per_block
+    }
+
+    /// Sets how many stake accounts a block pays rewards to, which decides
+    /// how many partitions rewards are paid in. It defaults to
+    /// [`STAKE_ACCOUNT_STORES_PER_BLOCK`].
+    ///
+    /// # Panics
+    ///
+    /// If `stake_rewards_per_block` is zero.
+    pub fn set_stake_rewards_per_block(&mut self, stake_rewards_per_block: u64) {
+        assert!(
+            stake_rewards_per_block > 0,
+            "blocks pay at least one reward"
+        );
+        self.stake_rewards_per_block = stake_rewards_per_block;
+    }
+
+    /// Rewards of the last epoch they were calculated for, and how many of
+    /// their partitions were paid.
+    pub fn epoch_rewards(&self) -> Option<&EpochRewards> {
+        self.epoch_rewards.as_ref()
+    }
+
+    /// The epoch from which stake warms up and cools down at the reduced
+    /// rate of `reduce_stake_warmup_cooldown`, if the feature is active.
+    pub fn new_warmup_cooldown_rate_epoch(&self) -> Option<Epoch> {
+        self.feature_set()
+            .new_warmup_cooldown_rate_epoch(&self.epoch_schedule)
+    }
+
+    pub fn rent_collection_enabled(&self) -> bool {
+        self.rent_collection_enabled
+    }
+
+    /// Turns the rent collection sweep at the start of every epoch on or
+    /// off. It is off by default.
+    pub fn set_rent_collection_enabled(&mut self, enabled: bool) {
+        self.rent_collection_enabled = enabled;
+    }
+
+    pub fn collected_rent(&self) -> CollectedInfo {
+        self.collected_rent
+    }
+
+    pub fn rent_collector(&self) -> RentCollector {
+        RentCollector::new(self.epoch(), self.epoch_schedule.clone(), self.rent.clone())
+    }
+
+    /// Collects the rent every stored account owes up to the end of the
+    /// current epoch.
+    ///
+    /// Accounts that cannot pay are closed. Returns what this sweep
+    /// collected, which is also added to [`Bank::collected_rent`].
+    pub fn collect_rent(&mut self) -> CollectedInfo {
+        let rent_collector = self.rent_collector();
+        let mut collected = CollectedInfo::default();
+        let mut updated = Vec::new();
+        for (pubkey, account) in self.accounts_db.iter() {
+            let mut collected_account = account.clone();
+            collected +=
+                rent_collector.collect_from_existing_account(&pubkey, &mut collected_account);
+            if collected_account != account {
+                updated.push((pubkey, collected_account));
+            }
+        }
+        for (pubkey, account) in updated {
+            self.accounts_db.store_account(pubkey, account);
+        }
+        self.collected_rent += collected;
+        collected
+    }
+
+    pub fn fee_structure(&self) -> &FeeStructure {
+        &self.fee_structure
+    }
+
+    /// Replaces the fee policy transactions processed from now on pay.
+    pub fn set_fee_structure(&mut self, fee_structure: FeeStructure) {
+        self.executor.set_environment_config(EnvironmentConfig {
+            lamports_per_signature: fee_structure.lamports_per_signature,
+            ..*self.executor.environment_config()
+        });
+        self.fee_structure = fee_structure;
+        self.update_sysvar_cache();
+    }
+
+    /// The features transactions processed by this bank run with. Every
+    /// feature is active unless [`Bank::set_feature_set`] says otherwise.
+    pub fn feature_set(&self) -> &FeatureSet {
+        self.executor.feature_set()
+    }
+
+    /// Replaces the features transactions processed from now on run with,
+    /// both while executing and while their fees are calculated.
+    pub fn set_feature_set(&mut self, feature_set: FeatureSet) {
+        self.executor.set_feature_set(feature_set);
+    }
+
+    /// What syscalls and builtins charge transactions processed by this
+    /// bank.
+    pub fn compute_costs(&self) -> &ComputeCostTable {
+        self.executor.compute_costs()
+    }
+
+    /// Replaces the cost table transactions processed from now on are
+    /// charged by.
+    pub fn set_compute_costs(&mut self, compute_costs: ComputeCostTable) {
+        self.executor.set_compute_costs(compute_costs);
+    }
+
+    /// Whether execution results carry the account diffs of their
+    /// transactions.
+    pub fn record_account_diffs(&self) -> bool {
+        self.executor.record_account_diffs()
+    }
+
+    /// See [`TransactionExecutor::set_record_account_diffs`].
+    pub fn set_record_account_diffs(&mut self, record_account_diffs: bool) {
+        self.executor.set_record_account_diffs(record_account_diffs);
+    }
+
+    /// Fees charged to the transactions processed in the current slot.
+    pub fn collected_fees(&self) -> u64 {
+        self.collected_fees
+    }
+
+    pub fn local_fee_market(&self) -> Option<&Arc<dyn LocalFeeMarket>> {
+        self.local_fee_market.as_ref()
+    }
+
+    /// Charges transactions processed from now on, by this bank and the
+    /// children it is forked into, what `local_fee_market` asks for the
+    /// accounts they write; `None` charges nothing extra.
+    pub fn set_local_fee_market(&mut self, local_fee_market: Option<Arc<dyn LocalFeeMarket>>) {
+        self.local_fee_market = local_fee_market;
+    }
+
+    /// How often each account was write-locked by the transactions charged
+    /// in the current slot.
+    pub fn write_demand(&self) -> &WriteDemand {
+        &self.write_demand
+    }
+
+    /// The local fee `transaction` would be charged if it were processed
+    /// next.
+    pub fn local_fee(&self, transaction: &SanitizedTransaction) -> u64 {
+        self.local_fee_market.as_ref().map_or(0, |market| {
+            market.local_fee(transaction, &self.write_demand)
+        })
+    }
+
+    /// How the transactions processed in `slot` were scheduled, if any
+    /// were.
+    pub fn scheduler_metrics(&self, slot: Slot) -> Option<&SchedulerMetrics> {
+        self.scheduler_metrics.get(&slot)
+    }
+
+    /// How the accounts of the transactions processed in `slot` were
+    /// loaded, if it processed any.
+    pub fn account_load_metrics(&self, slot: Slot) -> Option<&AccountLoadMetrics> {
+        self.account_load_metrics.get(&slot)
+    }
+
+    /// Cost of the transactions processed in the current slot so far.
+    pub fn cost_tracker(&self) -> &CostTracker {
+        &self.cost_tracker
+    }
+
+    /// Replaces the block and per-account cost limits, starting with the
+    /// current slot's costs from zero.
+    pub fn set_block_cost_limits(&mut self, block_cost_limit: u64, account_cost_limit: u64) {
+        self.cost_tracker = CostTracker::new(block_cost_limit, account_cost_limit);
+    }
+
+    pub fn ancestors(&self) -> &Ancestors {
+        &self.ancestors
+    }
+
+    /// The status cache this bank shares with its forks.
+    pub fn status_cache(&self) -> MutexGuard<'_, StatusCache> {
+        self.status_cache
+            .lock()
+            .expect("status cache lock is never poisoned")
+    }
+
+    /// Status of the transaction with `signature`, if this bank or one of
+    /// its ancestors processed it.
+    pub fn get_signature_status(&self, signature: &Signature) -> Option<TransactionStatus> {
+        self.status_cache()
+            .get_signature_status(signature, &self.ancestors)
+            .map(|(_, status)| status)
+    }
+
+    /// Attaches `notifier` to this bank and to the children it is forked
+    /// into from now on.
+    pub fn add_notifier(&mut self, notifier: Arc<dyn AccountsNotifier>) {
+        self.notifiers.push(notifier);
+    }
+
+    pub fn event_bus(&self) -> Option<&EventBus> {
+        self.event_bus.as_ref()
+    }
+
+    /// Publishes the [`Event`]s of this bank, and of the children it is
+    /// forked into from now on, to `event_bus`.
+    pub fn set_event_bus(&mut self, event_bus: Option<EventBus>) {
+        self.event_bus = event_bus;
+    }
+
+    pub fn accounts_versions(&self) -> Option<&Arc<AccountsVersions>> {
+        self.accounts_versions.as_ref()
+    }
+
+    /// Publishes the accounts of every slot this bank, and the children it
+    /// is forked into from now on, freeze to `accounts_versions`.
+    ///
+    /// The versions follow a single fork: a slot frozen after a later one
+    /// was published, on a fork that lost, is not published. The first
+    /// slot published carries every account, the later ones only the
+    /// accounts they wrote.
+    pub fn set_accounts_versions(&mut self, accounts_versions: Option<Arc<AccountsVersions>>) {
+        self.accounts_versions = accounts_versions;
+    }
+
+    pub fn accounts_wal(&self) -> Option<&Arc<AccountsWal>> {
+        self.accounts_wal.as_ref()
+    }
+
+    /// Journals the accounts of every slot this bank, and the children it
+    /// is forked into from now on, freeze to `accounts_wal`, so a crashed
+    /// run can [recover](crate::accounts_wal::recover_from_wal) the store
+    /// as the last frozen slot left it.
+    ///
+    /// Like the [accounts versions](Self::set_accounts_versions), the log
+    /// follows a single fork and starts with every account.
+    ///
+    /// # Panics
+    ///
+    /// Freezing panics if the slot cannot be journaled.
+    pub fn set_accounts_wal(&mut self, accounts_wal: Option<Arc<AccountsWal>>) {
+        self.accounts_wal = accounts_wal;
+    }
+
+    /// Roots this bank's slot and every ancestor still on its fork.
+    ///
+    /// What they processed stays visible to every bank sharing the status
+    /// cache, and the oldest roots beyond
+    /// [`MAX_CACHE_ENTRIES`](crate::status_cache::MAX_CACHE_ENTRIES) are
+    /// pruned from it.
+    pub fn set_root(&mut self) {
+        let mut status_cache = self.status_cache();
+        let mut slots: Vec<Slot> = self.ancestors.iter().copied().collect();
+        slots.sort_unstable();
+        let mut rooted = Vec::with_capacity(slots.len());
+        for slot in slots {
+            if !status_cache.roots().contains(&slot) {
+                rooted.push(slot);
+            }
+            status_cache.add_root(slot);
+        }
+        drop(status_cache);
+        self.ancestors = Ancestors::from([self.slot]);
+        for slot in rooted {
+            self.notify_slot_status(slot, SlotStatus::Rooted);
+        }
+    }
+
+    /// Lattice hash of every account, including the writes of the current
+    /// slot.
+    ///
+    /// It is kept up to date from the accounts each slot wrote, and always
+    /// equals [`calculate_accounts_lt_hash`] over the bank's accounts.
+    pub fn accounts_lt_hash(&self) -> LtHash {
+        let mut lt_hash = self.accounts_lt_hash.clone();
+        lt_hash.mix_in(&calculate_accounts_delta_lt_hash(&self.accounts_db));
+        lt_hash
+    }
+
+    /// Accounts delta hash of the accounts the current slot wrote.
+    pub fn accounts_delta_hash(&self) -> Hash {
+        calculate_accounts_delta_hash(&self.accounts_db)
+    }
+
+    /// Ends the current slot: no more transactions are processed in it, and
+    /// its bank hash is computed from the parent's bank hash, the accounts
+    /// lattice hash and the last blockhash. Freezing a frozen bank returns
+    /// the same hash.
+    pub fn freeze(&mut self) -> Hash {
+        if let Some(bank_hash) = self.bank_hash {
+            return bank_hash;
+        }
+        let bank_hash = solana_sha256_hasher::hashv(&[
+            self.parent_bank_hash.as_ref(),
+            self.accounts_lt_hash().checksum().as_ref(),
+            self.last_blockhash().as_ref(),
+        ]);
+        self.bank_hash = Some(bank_hash);
+        self.publish_slot_accounts();
+        self.notify_slot_status(self.slot, SlotStatus::Frozen);
+        bank_hash
+    }
+
+    /// Publishes the accounts the current slot left to the accounts
+    /// versions and the write-ahead log, if the bank has them.
+    fn publish_slot_accounts(&self) {
+        if let Some(accounts_versions) = &self.accounts_versions {
+            if let Some(accounts) = self.slot_accounts(accounts_versions.last_slot()) {
+                accounts_versions.publish(self.slot, accounts);
+            }
+        }
+        if let Some(accounts_wal) = &self.accounts_wal {
+            if let Some(accounts) = self.slot_accounts(accounts_wal.last_slot()) {
+                accounts_wal
+                    .append(self.slot, accounts)
+                    .unwrap_or_else(|err| panic!("failed to journal slot {}: {err}", self.slot));
+            }
+        }
+    }
+
+    /// The accounts to publish the current slot with after `last_slot`:
+    /// the ones the slot wrote, every account if nothing was published
+    /// yet, or none if a slot as late was already published.
+    fn slot_accounts(
+        &self,
+        last_slot: Option<Slot>,
+    ) -> Option<Vec<(Pubkey, Option<AccountSharedData>)>> {
+        match last_slot {
+            Some(last_slot) if last_slot >= self.slot => None,
+            Some(_) => Some(
+                self.accounts_db
+                    .written_accounts()
+                    .map(|(pubkey, _)| (*pubkey, self.accounts_db.get_account(pubkey)))
+                    .collect(),
+            ),
+            None => Some(
+                self.accounts_db
+                    .iter()
+                    .map(|(pubkey, account)| (pubkey, Some(account)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.bank_hash.is_some()
+    }
+
+    /// Bank hash of the current slot, if it is frozen.
+    pub fn bank_hash(&self) -> Option<Hash> {
+        self.bank_hash
+    }
+
+    /// The sysvars transactions processed by this bank see.
+    pub fn sysvar_cache(&self) -> &SysvarCache {
+        self.executor.sysvar_cache()
+    }
+
+    /// Ends the current slot with `blockhash` as its last blockhash and
+    /// moves on to the next slot, freezing the current one first if it is
+    /// not frozen yet.
+    ///
+    /// If the next slot starts a new epoch, the stake of the epoch that
+    /// ended is added to the stake history, and if rent collection is
+    /// enabled, rent is collected before the slot begins. Then the epoch
+    /// that ended is rewarded. Otherwise the next slot pays the next
+    /// partition of stake rewards still unpaid, if any.
+    pub fn advance_slot(&mut self, blockhash: Hash) {
+        self.start_slot(self.slot + 1, blockhash);
+    }
+
+    /// Freezes the ending slot and folds its writes into the accounts
+    /// lattice hash.
+    fn end_slot(&mut self) {
+        self.parent_bank_hash = self.freeze();
+        self.bank_hash = None;
+        self.accounts_lt_hash = self.accounts_lt_hash();
+        self.accounts_db.checkpoint();
+    }
+
+    /// Ends the current slot with `blockhash` and starts `slot`.
+    fn start_slot(&mut self, slot: Slot, blockhash: Hash) {
+        self.end_slot();
+        let epoch = self.epoch();
+        self.blockhash_queue.register_hash(blockhash);
+        self.slot_hashes.add(self.slot, blockhash);
+        self.slot = slot;
+        self.ancestors.insert(slot);
+        self.collected_fees = 0;
+        self.cost_tracker.reset();
+        self.write_demand.clear();
+        self.notify_slot_status(slot, SlotStatus::Created);
+        self.distribute_epoch_rewards();
+        if self.epoch() > epoch {
+            self.update_stake_history(epoch);
+            if self.rent_collection_enabled {
+                self.collect_rent();
+            }
+            self.begin_epoch_rewards(epoch, blockhash);
+        }
+        self.executor.set_environment_config(EnvironmentConfig {
+            blockhash,
+            ..*self.executor.environment_config()
+        });
+        self.update_sysvar_cache();
+    }
+
+    /// Moves straight on to `slot`, skipping the slots in between.
+    ///
+    /// The current slot is frozen first. Nothing happens for the skipped
+    /// slots: no blockhashes are registered and no rent is collected. This
+    /// is for replaying transactions recorded at `slot` on another cluster.
+    pub fn warp_to_slot(&mut self, slot: Slot) {
+        assert!(slot >= self.slot, "banks only move forward");
+        self.end_slot();
+        self.slot = slot;
+        self.ancestors.insert(slot);
+        self.collected_fees = 0;
+        self.cost_tracker.reset();
+        self.write_demand.clear();
+        self.notify_slot_status(slot, SlotStatus::Created);
+        self.update_sysvar_cache();
+    }
+
+    /// Registers `blockhash` as the most recent blockhash without ending the
+    /// current slot, so transactions recorded against it on another
+    /// cluster pass the age check.
+    pub fn register_recent_blockhash(&mut self, blockhash: Hash) {
+        self.blockhash_queue.register_hash(blockhash);
+        self.update_sysvar_cache();
+    }
+
+    /// Registers a builtin program that transactions processed by this bank
+    /// can invoke.
+    pub fn add_builtin<F>(&mut self, program_id: Pubkey, entrypoint: F)
+    where
+        F: Fn(&mut InvokeContext) -> Result<(), InstructionError> + Send + Sync + 'static,
+    {
+        self.executor.add_program(program_id, entrypoint);
+    }
+
+    pub fn accounts_db(&self) -> &AccountsDb {
+        &self.accounts_db
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.accounts_db.get_account(pubkey)
+    }
+
+    /// Stores `account` under `pubkey`.
+    ///
+    /// Storing over a program drops it from the program cache, so the next
+    /// instruction for it loads the new ELF.
+    pub fn store_account(&mut self, pubkey: Pubkey, account: AccountSharedData) {
+        if *account.owner() == bpf_loader::id() {
+            self.executor.program_cache().remove(&pubkey);
+        }
+        self.accounts_db.store_account(pubkey, account);
+    }
+
+    /// How the program cache of this bank's executor has been used.
+    pub fn program_cache_stats(&self) -> ProgramCacheStats {
+        self.executor.program_cache().stats()
+    }
+
+    /// Sanitizes `transaction`, resolving v0 address table lookups against
+    /// the accounts of this bank.
+    pub fn resolve_transaction(
+        &self,
+        transaction: VersionedTransaction,
+    ) -> Result<SanitizedTransaction, TransactionError> {
+        address_lookup_table::resolve_transaction(&self.accounts_db, self.slot, transaction)
+    }
+
+    /// Rejects transactions whose recent blockhash is older than
+    /// `max_age` blockhashes or unknown to this bank.
+    ///
+    /// A transaction with an unknown blockhash is still accepted if it is a
+    /// valid durable nonce transaction; the returned [`NonceInfo`] is what
+    /// gets advanced when it commits.
+    pub fn check_age(
+        &self,
+        transaction: &SanitizedTransaction,
+        max_age: usize,
+    ) -> Result<Option<NonceInfo>, TransactionError> {
+        let message = transaction.message();
+        if self
+            .blockhash_queue
+            .is_hash_valid_for_age(message.recent_blockhash(), max_age)
+        {
+            return Ok(None);
+        }
+
+        load_message_nonce_info(&self.accounts_db, message, &self.last_blockhash())
+            .map(Some)
+            .ok_or(TransactionError::BlockhashNotFound)
+    }
+
+    /// Runs the [age check](Self::check_age) on every transaction, and
+    /// rejects the ones already processed with
+    /// [`TransactionError::AlreadyProcessed`]. While stake rewards are
+    /// being paid out, transactions invoking the Stake program fail with
+    /// [`TransactionError::ProgramExecutionTemporarilyRestricted`].
+    ///
+    /// A transaction counts as processed once this bank, one of its
+    /// ancestors or a rooted bank executed it, whether it succeeded or not,
+    /// and also if it appears earlier in `transactions`. Results line up
+    /// with `transactions`.
+    pub fn check_transactions(
+        &self,
+        transactions: &[SanitizedTransaction],
+        max_age: usize,
+    ) -> Vec<Result<Option<NonceInfo>, TransactionError>> {
+        let status_cache = self.status_cache();
+        let mut checked = HashSet::with_capacity(transactions.len());
+        transactions
+            .iter()
+            .map(|transaction| {
+                let nonce_info = self.check_age(transaction, max_age)?;
+                self.check_stake_program_restricted(transaction)?;
+                let blockhash = transaction.message().recent_blockhash();
+                let message_hash = transaction.message_hash();
+                if status_cache
+                    .get_status(message_hash, blockhash, &self.ancestors)
+                    .is_some()
+                    || !checked.insert((blockhash, message_hash))
+                {
+                    return Err(TransactionError::AlreadyProcessed);
+                }
+                Ok(nonce_info)
+            })
+            .collect()
+    }
+
+    /// Checks that the fee payer of `transaction` can pay its fee.
+    ///
+    /// The payer has to be an existing System account holding at least the
+    /// base, priority and [local](Self::local_fee) fee, and paying may not
+    /// leave it below its rent-exempt minimum unless it pays its whole
+    /// balance. Returns the fee it will be charged.
+    pub fn validate_fee_payer(
+        &self,
+        transaction: &SanitizedTransaction,
+    ) -> Result<FeeDetails, TransactionError> {
+        self.validate_fee_payer_in(&self.accounts_db, transaction)
+    }
+
+    /// [`validate_fee_payer`](Self::validate_fee_payer) against the
+    /// accounts of `loader`.
+    fn validate_fee_payer_in(
+        &self,
+        loader: &impl AccountLoader,
+        transaction: &SanitizedTransaction,
+    ) -> Result<FeeDetails, TransactionError> {
+        let fee_payer = transaction.message().fee_payer();
+        let account = loader
+            .load_account(fee_payer)
+            .ok_or(TransactionError::AccountNotFound)?;
+        if *account.owner() != system_program::id() {
+            return Err(TransactionError::InvalidAccountForFee);
+        }
+
+        let fee_details = FeeDetails {
+            local_fee: self.local_fee(transaction),
+            ..self
+                .fee_structure
+                .calculate_fee_details_with_features(transaction, self.feature_set())
+        };
+        let mut charged = account.clone();
+        charged
+            .checked_sub_lamports(fee_details.total_fee())
+            .map_err(|_| TransactionError::InsufficientFundsForFee)?;
+        RentState::check_transition(fee_payer, &account, &charged, &self.rent, 0)?;
+        Ok(fee_details)
+    }
+
+    /// Checks, schedules, executes and commits `transactions`.
+    ///
+    /// Transactions that pass [`check_transactions`](Self::check_transactions)
+    /// are split into conflict-free batches by priority. Batches execute
+    /// one after another and each one is committed before the next starts,
+    /// so later batches see the writes of earlier ones. Right before a
+    /// transaction executes, its cost is added to the slot's
+    /// [`CostTracker`], which turns it away if the block or an account it
+    /// writes would go over its limit; once it executed, the requested
+    /// compute units it did not consume are given back. Then its fee payer
+    /// is validated and charged, and the accounts it writes count towards
+    /// the slot's [`write_demand`](Self::write_demand); the fee stays
+    /// charged even if the transaction fails, and so does the nonce
+    /// advance of a durable nonce transaction, so it cannot be replayed.
+    /// Every executed transaction is added to the [`StatusCache`]. Results
+    /// come back in input order.
+    ///
+    /// While a batch executes, the accounts of the next one are prefetched
+    /// on the rayon pool, apart from those that could still change before
+    /// it executes: the accounts the executing batch may write and the
+    /// next batch's fee payers. [`account_load_metrics`](Self::account_load_metrics)
+    /// tells how many loads the prefetch served and how long it took.
+    ///
+    /// Signatures are not checked here; transactions are expected to have
+    /// passed [`sigverify`](crate::sigverify) already.
+    ///
+    /// # Panics
+    ///
+    /// If the bank is [frozen](Self::freeze).
+    pub fn process_transaction_batch(
+        &mut self,
+        transactions: &[SanitizedTransaction],
+    ) -> Vec<TransactionProcessingResult> {
+        let mut scheduler = PriorityGraphScheduler::new();
+        let results = self.process_transaction_batch_with_scheduler(transactions, &mut scheduler);
+        self.scheduler_metrics
+            .entry(self.slot)
+            .or_default()
+            .accumulate(scheduler.metrics());
+        results
+    }
+
+    /// [`process_transaction_batch`](Self::process_transaction_batch) with
+    /// the batches of `scheduler`.
+    ///
+    /// The bank does not take the batches on trust: the transactions of a
+    /// batch lock their accounts in an [`AccountLocks`] table first, and
+    /// one that conflicts with a transaction ahead of it in its batch fails
+    /// with [`TransactionError::AccountInUse`] without being executed or
+    /// charged. The locks are released once the batch is committed.
+    ///
+    /// # Panics
+    ///
+    /// If the bank is [frozen](Self::freeze).
+    pub fn process_transaction_batch_with_scheduler(
+        &mut self,
+        transactions: &[SanitizedTransaction],
+        scheduler: &mut impl Scheduler,
+    ) -> Vec<TransactionProcessingResult> {
+        assert!(!self.is_frozen(), "a frozen bank processes no transactions");
+        let _slot_span = spans::slot_span(self.slot).entered();
+        let mut processing_results: Vec<Option<TransactionProcessingResult>> =
+            (0..transactions.len()).map(|_| None).collect();
+        let mut checked_indexes = Vec::with_capacity(transactions.len());
+        let mut checked_transactions = Vec::with_capacity(transactions.len());
+        let mut nonce_infos = Vec::with_capacity(transactions.len());
+        let check_results = self.check_transactions(transactions, MAX_PROCESSING_AGE);
+        for (index, (transaction, check_result)) in
+            transactions.iter().zip(check_results).enumerate()
+        {
+            match check_result {
+                Ok(nonce_info) => {
+                    checked_indexes.push(index);
+                    checked_transactions.push(transaction.clone());
+                    nonce_infos.push(nonce_info);
+                }
+                Err(err) => {
+                    self.publish_dropped(transaction, DropReason::Rejected(err.clone()));
+                    processing_results[index] = Some(Err(err));
+                }
+            }
+        }
+
+        let batches = scheduler.schedule(&checked_transactions);
+        let mut account_locks = AccountLocks::new();
+        let mut load_metrics = AccountLoadMetrics::default();
+        let mut prefetched = match batches.first() {
+            Some(batch) => {
+                let keys = prefetch_keys(&checked_transactions, &[], &batch.transaction_indexes);
+                PrefetchedAccounts::load(&self.accounts_db, &keys)
+            }
+            None => PrefetchedAccounts::default(),
+        };
+        load_metrics.blocking_prefetch_us += prefetched.load_time().as_micros() as u64;
+        for (batch_index, batch) in batches.iter().enumerate() {
+            let next_keys = batches.get(batch_index + 1).map_or_else(Vec::new, |next| {
+                prefetch_keys(
+                    &checked_transactions,
+                    &batch.transaction_indexes,
+                    &next.transaction_indexes,
+                )
+            });
+            // Every execution overlaps with loading a share of the next
+            // batch's accounts.
+            let chunk_len = next_keys
+                .len()
+                .div_ceil(batch.transaction_indexes.len())
+                .max(1);
+            let mut next_chunks = next_keys.chunks(chunk_len);
+            let mut next_prefetched = PrefetchedAccounts::default();
+            let mut lock_sets = Vec::with_capacity(batch.transaction_indexes.len());
+            let _batch_span = spans::batch_span(batch.transaction_indexes.len()).entered();
+            self.publish(|| Event::BatchStarted {
+                slot: self.slot,
+                num_transactions: batch.transaction_indexes.len(),
+            });
+
+            for &checked_index in &batch.transaction_indexes {
+                let transaction = &checked_transactions[checked_index];
+                let index = checked_indexes[checked_index];
+                let _transaction_span = spans::transaction_span(transaction).entered();
+                self.publish(|| Event::TransactionScheduled {
+                    slot: self.slot,
+                    signature: *transaction.signature(),
+                });
+                let lock_set = LockSet::from_transaction(transaction, LockSetConfig::default());
+                if let Err(err) = account_locks.try_lock(&lock_set) {
+                    self.publish_dropped(transaction, DropReason::Rejected(err.clone()));
+                    processing_results[index] = Some(Err(err));
+                    continue;
+                }
+                lock_sets.push(lock_set);
+                let cost = CostModel::calculate_cost_with_features(transaction, self.feature_set());
+                if let Err(err) = self.cost_tracker.try_add(&cost) {
+                    let err = TransactionError::from(err);
+                    self.publish_dropped(transaction, DropReason::Rejected(err.clone()));
+                    processing_results[index] = Some(Err(err));
+                    continue;
+                }
+                let lamports_before = self.touched_lamports(transaction);
+                let fee_details = match self.validate_fee_payer(transaction) {
+                    Ok(fee_details) => fee_details,
+                    Err(err) => {
+                        self.cost_tracker.remove_transaction_cost(&cost);
+                        self.publish_dropped(transaction, DropReason::Rejected(err.clone()));
+                        processing_results[index] = Some(Err(err));
+                        continue;
+                    }
+                };
+                self.charge_fee(transaction.message().fee_payer(), &fee_details);
+                self.write_demand.record(transaction);
+
+                let (executor, accounts_db) = (&self.executor, &self.accounts_db);
+                let loader = prefetched.loader(accounts_db);
+                let chunk = next_chunks.next().unwrap_or_default();
+                let (mut result, chunk_prefetched) = rayon::join(
+                    || executor.load_and_execute_transaction(&loader, transaction),
+                    || PrefetchedAccounts::load(accounts_db, chunk),
+                );
+                next_prefetched.extend(chunk_prefetched);
+                // The payer was loaded with the fee already taken.
+                result.pre_balances[0] += fee_details.total_fee();
+                if let Some(account_diffs) = &mut result.account_diffs {
+                    include_fee_in_diffs(account_diffs, &result.post_accounts[0], &fee_details);
+                }
+                result.fee_details = fee_details;
+                self.cost_tracker
+                    .update_execution_cost(&cost, result.consumed_units);
+                self.commit_transaction(transaction, &result);
+                self.status_cache().insert_transaction(
+                    transaction,
+                    self.slot,
+                    result.status.clone(),
+                );
+                // A failed transaction still wrote its fee payer.
+                if !result.was_successful() {
+                    self.notify_stored_account(transaction, transaction.message().fee_payer());
+                }
+                if let Some(nonce_info) = &nonce_infos[checked_index] {
+                    self.advance_nonce(nonce_info);
+                    self.notify_stored_account(transaction, nonce_info.address());
+                }
+                self.check_lamports_conserved(
+                    transaction,
+                    lamports_before,
+                    result.fee_details.total_fee(),
+                );
+                self.notify_transaction(transaction, &result);
+                self.publish_committed(transaction, &result);
+                processing_results[index] = Some(Ok(result));
+            }
+
+            // Transactions turned away before executing left some of the
+            // next batch's accounts to load on their own.
+            for chunk in next_chunks {
+                let chunk_prefetched = PrefetchedAccounts::load(&self.accounts_db, chunk);
+                load_metrics.blocking_prefetch_us +=
+                    chunk_prefetched.load_time().as_micros() as u64;
+                next_prefetched.extend(chunk_prefetched);
+            }
+            for lock_set in &lock_sets {
+                account_locks.unlock(lock_set);
+            }
+            load_metrics.accumulate(&prefetched.metrics());
+            prefetched = next_prefetched;
+        }
+        if !batches.is_empty() {
+            self.account_load_metrics
+                .entry(self.slot)
+                .or_default()
+                .accumulate(&load_metrics);
+        }
+
+        processing_results
+            .into_iter()
+            .map(|result| result.expect("every transaction is checked or executed"))
+            .collect()
+    }
+
+    /// Stores the account changes of every successful transaction.
+    ///
+    /// `results` line up with `transactions`. Only accounts the message
+    /// marks writable are stored, so a stale copy of a read-only account
+    /// never overwrites a newer write. Failed transactions are skipped
+    /// entirely: none of their writes land. Returns the number of committed
+    /// transactions.
+    ///
+    /// # Panics
+    ///
+    /// If the bank [verifies lamports](Self::set_verify_lamports) and a
+    /// result changes the total lamports of its transaction's accounts.
+    pub fn commit(
+        &mut self,
+        transactions: &[SanitizedTransaction],
+        results: &[TransactionExecutionResult],
+    ) -> usize {
+        assert_eq!(
+            transactions.len(),
+            results.len(),
+            "every transaction needs an execution result"
+        );
+
+        transactions
+            .iter()
+            .zip(results)
+            .filter(|(transaction, result)| {
+                let lamports_before = self.touched_lamports(transaction);
+                let committed = self.commit_transaction(transaction, result);
+                self.check_lamports_conserved(transaction, lamports_before, 0);
+                committed
+            })
+            .count()
+    }
+
+    /// Adds the stake every stake account delegated as of `epoch`, which
+    /// just ended, to the stake history.
+    ///
+    /// Only the ending epoch is recorded, even if the bank skipped past
+    /// later ones.
+    fn update_stake_history(&mut self, epoch: Epoch) {
+        let new_rate_activation_epoch = self.new_warmup_cooldown_rate_epoch();
+        let entry = self
+            .accounts_db
+            .get_program_accounts(&stake::id())
+            .into_iter()
+            .filter_map(|(_, account)| {
+                bincode::deserialize::<StakeStateV2>(account.data())
+                    .ok()?
+                    .delegation()
+            })
+            .fold(StakeHistoryEntry::default(), |entry, delegation| {
+                entry.saturating_add(delegation.stake_activating_and_deactivating_v2(
+                    epoch,
+                    &self.stake_history,
+                    new_rate_activation_epoch,
+                ))
+            });
+        self.stake_history.add(epoch, entry);
+    }
+
+    fn update_sysvar_cache(&mut self) {
+        let sysvar_cache = SysvarCache::new(SysvarSource {
+            slot: self.slot,
+            epoch_schedule: &self.epoch_schedule,
+            rent: &self.rent,
+            slot_hashes: &self.slot_hashes,
+            stake_history: &self.stake_history,
+            recent_blockhashes: &self.blockhash_queue.get_recent_blockhashes(),
+            lamports_per_signature: self.executor.environment_config().lamports_per_signature,
+        });
+        self.executor.set_sysvar_cache(sysvar_cache);
+    }
+
+    /// Debits a validated fee from `fee_payer`.
+    fn charge_fee(&mut self, fee_payer: &Pubkey, fee_details: &FeeDetails) {
+        let mut account = self
+            .accounts_db
+            .get_account(fee_payer)
+            .expect("the fee payer was validated");
+        account.set_lamports(account.lamports() - fee_details.total_fee());
+        self.accounts_db.store_account(*fee_payer, account);
+        self.collected_fees += fee_details.total_fee();
+    }
+
+    fn advance_nonce(&mut self, nonce_info: &NonceInfo) {
+        if let Some(mut account) = self.accounts_db.get_account(nonce_info.address()) {
+            nonce_info.advance(&mut account);
+            self.accounts_db
+                .store_account(*nonce_info.address(), account);
+        }
+    }
+
+    /// Commits a single transaction, returning whether it was successful.
+    fn commit_transaction(
+        &mut self,
+        transaction: &SanitizedTransaction,
+        result: &TransactionExecutionResult,
+    ) -> bool {
+        if !result.was_successful() {
+            return false;
+        }
+
+        let message = transaction.message();
+        for (index, (pubkey, account)) in result.post_accounts.iter().enumerate() {
+            if message.is_writable(index) {
+                self.accounts_db.store_account(*pubkey, account.clone());
+                for notifier in &self.notifiers {
+                    notifier.notify_account_update(self.slot, pubkey, account, transaction);
+                }
+            }
+        }
+        true
+    }
+
+    /// Notifies the account stored at `pubkey` on behalf of `transaction`.
+    fn notify_stored_account(&self, transaction: &SanitizedTransaction, pubkey: &Pubkey) {
+        if self.notifiers.is_empty() {
+            return;
+        }
+        let account = self.accounts_db.get_account(pubkey).unwrap_or_default();
+        for notifier in &self.notifiers {
+            notifier.notify_account_update(self.slot, pubkey, &account, transaction);
+        }
+    }
+
+    fn notify_transaction(
+        &self,
+        transaction: &SanitizedTransaction,
+        result: &TransactionExecutionResult,
+    ) {
+        if self.notifiers.is_empty() {
+            return;
+        }
+        let details = TransactionExecutionDetails::new(transaction, result);
+        for notifier in &self.notifiers {
+            notifier.notify_transaction(self.slot, transaction, &details);
+        }
+    }
+
+    fn notify_slot_status(&self, slot: Slot, status: SlotStatus) {
+        for notifier in &self.notifiers {
+            notifier.notify_slot_status(slot, status);
+        }
+    }
+
+    /// Publishes the event `event` builds, if an event bus is set.
+    fn publish(&self, event: impl FnOnce() -> Event) {
+        if let Some(event_bus) = &self.event_bus {
+            event_bus.publish(event());
+        }
+    }
+
+    fn publish_committed(
+        &self,
+        transaction: &SanitizedTransaction,
+        result: &TransactionExecutionResult,
+    ) {
+        self.publish(|| Event::TransactionCommitted {
+            slot: self.slot,
+            signature: *transaction.signature(),
+            status: result.status.clone(),
+            fee: result.fee_details.total_fee(),
+            consumed_units: result.consumed_units,
+        });
+    }
+
+    fn publish_dropped(&self, transaction: &SanitizedTransaction, reason: DropReason) {
+        self.publish(|| Event::TransactionDropped {
+            slot: self.slot,
+            signature: *transaction.signature(),
+            reason,
+        });
+    }
+}
+
+/// The account keys of the transactions at `batch` that keep their state
+/// until `batch` executes, if `executing` runs first.
+///
+/// Those are all but the accounts `executing` may write and the fee
+/// payers of `batch`, which are charged right before their transaction
+/// executes. The rest of `batch` cannot write them either, as its
+/// transactions do not conflict.
+fn prefetch_keys(
+    transactions: &[SanitizedTransaction],
+    executing: &[usize],
+    batch: &[usize],
+) -> Vec<Pubkey> {
+    let mut excluded: HashSet<Pubkey> = executing
+        .iter()
+        .flat_map(|&index| {
+            let message = transactions[index].message();
+            message
+                .account_keys()
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| message.is_writable(*index))
+                .map(|(_, key)| *key)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    excluded.extend(
+        batch
+            .iter()
+            .map(|&index| *transactions[index].message().fee_payer()),
+    );
+    batch
+        .iter()
+        .flat_map(|&index| transactions[index].message().account_keys().iter())
+        // Inserting also skips keys listed before.
+        .filter(|key| excluded.insert(**key))
+        .copied()
+        .collect()
+}
+
+/// Adds the fee the bank charged `fee_payer` ahead of execution to the
+/// payer's diff, which the executor only saw with the fee already taken.
+fn include_fee_in_diffs(
+    account_diffs: &mut Vec<AccountDiff>,
+    (fee_payer, payer_account): &TransactionAccount,
+    fee_details: &FeeDetails,
+) {
+    let fee = fee_details.total_fee();
+    if fee == 0 {
+        return;
+    }
+    match account_diffs.first_mut() {
+        Some(diff) if diff.pubkey == *fee_payer => {
+            diff.before.set_lamports(diff.before.lamports() + fee);
+        }
+        _ => {
+            let mut before = payer_account.clone();
+            before.set_lamports(before.lamports() + fee);
+            account_diffs.insert(
+                0,
+                AccountDiff {
+                    pubkey: *fee_payer,
+                    before,
+                    after: payer_account.clone(),
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use {
+    crate::{accounts_db::AccountsDb, system_program::SystemInstruction},
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_hash::Hash,
+    solana_message::SanitizedMessage,
+    solana_nonce::{
+        state::{Data, DurableNonce, State},
+        versions::Versions,
+        NONCED_TX_MARKER_IX_INDEX,
+    },
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::system_program,
+};
+
+/// A nonce account a durable nonce transaction consumes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonceInfo {
+    address: Pubkey,
+    /// The nonce stored once the transaction commits.
+    next_durable_nonce: DurableNonce,
+}
+
+impl NonceInfo {
+    pub fn address(&self) -> &Pubkey {
+        &self.address
+    }
+
+    pub fn next_durable_nonce(&self) -> &DurableNonce {
+        &self.next_durable_nonce
+    }
+
+    /// Writes the next durable nonce into `account`, keeping its authority
+    /// and fee calculator.
+    pub(crate) fn advance(&self, account: &mut AccountSharedData) {
+        let Some(data) = nonce_data(account) else {
+            return;
+        };
+        let state = State::new_initialized(
+            &data.authority,
+            self.next_durable_nonce,
+            data.get_lamports_per_signature(),
+        );
+        let serialized =
+            bincode::serialize(&Versions::new(state)).expect("nonce state is always serializable");
+        account.set_data_from_slice(&serialized);
+    }
+}
+
+/// Returns the nonce account of `message` if it starts with a well-formed
+/// `AdvanceNonceAccount` instruction.
+pub fn get_durable_nonce(message: &SanitizedMessage) -> Option<&Pubkey> {
+    let instruction = message
+        .instructions()
+        .get(usize::from(NONCED_TX_MARKER_IX_INDEX))?;
+    let account_keys = message.account_keys();
+    let program_id = account_keys.get(usize::from(instruction.program_id_index))?;
+    if *program_id != system_program::id()
+        || SystemInstruction::parse(&instruction.data) != Ok(SystemInstruction::AdvanceNonceAccount)
+    {
+        return None;
+    }
+
+    let nonce_index = usize::from(*instruction.accounts.first()?);
+    message
+        .is_writable(nonce_index)
+        .then(|| account_keys.get(nonce_index))
+        .flatten()
+}
+
+/// Validates the durable nonce `message` uses in place of a recent
+/// blockhash.
+///
+/// The nonce account must be an initialized System account whose stored
+/// nonce matches the message's recent blockhash, and its authority must
+/// sign the message. A nonce that was already advanced during the current
+/// blockhash cannot be used again, since advancing it would be a no-op.
+pub fn load_message_nonce_info(
+    accounts_db: &AccountsDb,
+    message: &SanitizedMessage,
+    last_blockhash: &Hash,
+) -> Option<NonceInfo> {
+    let address = get_durable_nonce(message)?;
+    let account = accounts_db.get_account(address)?;
+    if *account.owner() != system_program::id() {
+        return None;
+    }
+
+    let versions: Versions = bincode::deserialize(account.data()).ok()?;
+    let data = versions.verify_recent_blockhash(message.recent_blockhash())?;
+    let next_durable_nonce = DurableNonce::from_blockhash(last_blockhash);
+    if data.durable_nonce == next_durable_nonce {
+        return None;
+    }
+
+    let authority_signed = message
+        .account_keys()
+        .iter()
+        .enumerate()
+        .any(|(index, key)| *key == data.authority && message.is_signer(index));
+    authority_signed.then_some(NonceInfo {
+        address: *address,
+        next_durable_nonce,
+    })
+}
+
+fn nonce_data(account: &AccountSharedData) -> Option<Data> {
+    let versions: Versions = bincode::deserialize(account.data()).ok()?;
+    match versions.state() {
+        State::Initialized(data) => Some(data.clone()),
+        State::Uninitialized => None,
+    }
+}
@@ -0,0 +1,311 @@
+use {
+    super::Bank,
+    crate::rent_collector::SLOTS_PER_YEAR,
+    solana_account::{ReadableAccount, WritableAccount},
+    solana_clock::Epoch,
+    solana_hash::Hash,
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{stake, vote},
+    solana_stake_interface::state::{Stake, StakeStateV2},
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+    solana_vote_interface::state::VoteStateV3,
+    std::collections::{BTreeMap, HashMap},
+};
+
+/// Most stake accounts a block credits rewards to, unless the bank is
+/// [configured](Bank::set_stake_rewards_per_block) otherwise.
+pub const STAKE_ACCOUNT_STORES_PER_BLOCK: u64 = 4_096;
+
+/// Stake rewards are paid out within a tenth of an epoch's slots, however
+/// many stake accounts there are.
+pub const MAX_FACTOR_OF_REWARD_BLOCKS_IN_EPOCH: u64 = 10;
+
+/// What a stake account earned in an epoch, after its vote account's
+/// commission.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StakeReward {
+    pub stake_pubkey: Pubkey,
+    /// Lamports credited to the account and added to its stake.
+    pub lamports: u64,
+    /// Vote credits the stake has observed once it is rewarded.
+    pub credits_observed: u64,
+}
+
+/// The rewards of an epoch, and how far paying them out got.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpochRewards {
+    /// The epoch the rewards were earned in.
+    pub rewarded_epoch: Epoch,
+    /// Validator inflation of the epoch, shared out by points.
+    pub total_rewards: u64,
+    /// Effective stake times vote credits earned, over every stake account.
+    pub total_points: u128,
+    /// Commission credited to vote accounts when the rewards were
+    /// calculated.
+    pub vote_rewards: u64,
+    /// Stake rewards, by the block after the calculation that pays them.
+    pub partitions: Vec<Vec<StakeReward>>,
+    /// How many of the partitions were paid.
+    pub distributed_partitions: usize,
+    /// Lamports credited to stake accounts so far. Rewards of accounts
+    /// that stopped being delegated before their partition was paid are
+    /// dropped.
+    pub distributed_stake_rewards: u64,
+}
+
+impl EpochRewards {
+    /// Whether partitions are still waiting to be paid.
+    pub fn is_active(&self) -> bool {
+        self.distributed_partitions < self.partitions.len()
+    }
+}
+
+impl Bank {
+    /// Lamports held by every stored account.
+    pub fn capitalization(&self) -> u64 {
+        self.accounts_db
+            .iter()
+            .map(|(_, account)| account.lamports())
+            .sum()
+    }
+
+    /// Rejects transactions that invoke the Stake program while stake
+    /// rewards are being paid out, so no stake account changes under them.
+    pub(super) fn check_stake_program_restricted(
+        &self,
+        transaction: &SanitizedTransaction,
+    ) -> Result<(), TransactionError> {
+        if !self
+            .epoch_rewards
+            .as_ref()
+            .is_some_and(EpochRewards::is_active)
+        {
+            return Ok(());
+        }
+        match transaction
+            .message()
+            .program_instructions_iter()
+            .find(|(program_id, _)| **program_id == stake::id())
+        {
+            Some((_, instruction)) => {
+                Err(TransactionError::ProgramExecutionTemporarilyRestricted {
+                    account_index: instruction.program_id_index,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Calculates the rewards of `rewarded_epoch`, which just ended, in
+    /// the first block of the next epoch, whose parent's last blockhash is
+    /// `parent_blockhash`.
+    ///
+    /// Vote accounts are paid their commission right away; stake rewards
+    /// are hashed into partitions for the following blocks. Whatever the
+    /// previous epoch left unpaid is paid first.
+    pub(super) fn begin_epoch_rewards(&mut self, rewarded_epoch: Epoch, parent_blockhash: Hash) {
+        while self
+            .epoch_rewards
+            .as_ref()
+            .is_some_and(EpochRewards::is_active)
+        {
+            self.distribute_epoch_rewards();
+        }
+
+        let slots_per_year = SLOTS_PER_YEAR as f64;
+        let year =
+            self.epoch_schedule.get_first_slot_in_epoch(self.epoch()) as f64 / slots_per_year;
+        let epoch_duration_in_years =
+            self.epoch_schedule.get_slots_in_epoch(rewarded_epoch) as f64 / slots_per_year;
+        let total_rewards = (self.inflation.validator(year)
+            * self.capitalization() as f64
+            * epoch_duration_in_years) as u64;
+
+        let new_rate_activation_epoch = self.new_warmup_cooldown_rate_epoch();
+        let mut vote_states = HashMap::new();
+        let mut stakes = Vec::new();
+        for (pubkey, account) in self.accounts_db.get_program_accounts(&stake::id()) {
+            let Some(stake) = bincode::deserialize::<StakeStateV2>(account.data())
+                .ok()
+                .and_then(|state| state.stake())
+            else {
+                continue;
+            };
+            let voter_pubkey = stake.delegation.voter_pubkey;
+            let vote_state = vote_states.entry(voter_pubkey).or_insert_with(|| {
+                self.accounts_db
+                    .get_account(&voter_pubkey)
+                    .filter(|account| *account.owner() == vote::id())
+                    .and_then(|account| VoteStateV3::deserialize(account.data()).ok())
+            });
+            let Some(vote_state) = vote_state else {
+                continue;
+            };
+            let (points, credits_observed) =
+                stake_points_and_credits(&stake, vote_state, |epoch| {
+                    stake
+                        .delegation
+                        .stake_v2(epoch, &self.stake_history, new_rate_activation_epoch)
+                });
+            stakes.push((pubkey, voter_pubkey, points, credits_observed));
+        }
+        let total_points: u128 = stakes.iter().map(|(_, _, points, _)| points).sum();
+
+        let mut vote_rewards = BTreeMap::<Pubkey, u64>::new();
+        let mut stake_rewards = Vec::new();
+        for (stake_pubkey, voter_pubkey, points, credits_observed) in stakes {
+            let Some(rewards) = points
+                .checked_mul(u128::from(total_rewards))
+                .and_then(|rewards| rewards.checked_div(total_points))
+                .filter(|rewards| *rewards > 0)
+            else {
+                continue;
+            };
+            let rewards = rewards as u64;
+            let commission = vote_states[&voter_pubkey]
+                .as_ref()
+                .map_or(0, |vote_state| vote_state.commission);
+            let (voter_rewards, staker_rewards) = commission_split(commission, rewards);
+            if voter_rewards > 0 {
+                *vote_rewards.entry(voter_pubkey).or_default() += voter_rewards;
+            }
+            if staker_rewards > 0 {
+                stake_rewards.push(StakeReward {
+                    stake_pubkey,
+                    lamports: staker_rewards,
+                    credits_observed,
+                });
+            }
+        }
+
+        let mut paid_vote_rewards = 0;
+        for (vote_pubkey, lamports) in vote_rewards {
+            let mut account = self
+                .accounts_db
+                .get_account(&vote_pubkey)
+                .expect("rewarded vote accounts exist");
+            if account.checked_add_lamports(lamports).is_ok() {
+                self.accounts_db.store_account(vote_pubkey, account);
+                paid_vote_rewards += lamports;
+            }
+        }
+
+        // Without stake rewards nothing is paid out, and the Stake program
+        // stays available.
+        let num_partitions = (stake_rewards.len() as u64)
+            .div_ceil(self.stake_rewards_per_block)
+            .min(
+                self.epoch_schedule.get_slots_in_epoch(self.epoch())
+                    / MAX_FACTOR_OF_REWARD_BLOCKS_IN_EPOCH,
+            )
+            .max(u64::from(!stake_rewards.is_empty())) as usize;
+        let mut partitions = vec![Vec::new(); num_partitions];
+        for reward in stake_rewards {
+            let partition =
+                partition_index(&parent_blockhash, &reward.stake_pubkey, num_partitions);
+            partitions[partition].push(reward);
+        }
+        for partition in &mut partitions {
+            partition.sort_unstable_by_key(|reward| reward.stake_pubkey);
+        }
+        self.epoch_rewards = Some(EpochRewards {
+            rewarded_epoch,
+            total_rewards,
+            total_points,
+            vote_rewards: paid_vote_rewards,
+            partitions,
+            distributed_partitions: 0,
+            distributed_stake_rewards: 0,
+        });
+    }
+
+    /// Pays the next partition of stake rewards, if any is left.
+    pub(super) fn distribute_epoch_rewards(&mut self) {
+        let Some(epoch_rewards) = self.epoch_rewards.as_mut() else {
+            return;
+        };
+        let Some(partition) = epoch_rewards
+            .partitions
+            .get(epoch_rewards.distributed_partitions)
+        else {
+            return;
+        };
+        let mut distributed = 0;
+        for reward in partition {
+            let Some(mut account) = self.accounts_db.get_account(&reward.stake_pubkey) else {
+                continue;
+            };
+            let Ok(StakeStateV2::Stake(meta, mut stake, flags)) =
+                bincode::deserialize::<StakeStateV2>(account.data())
+            else {
+                continue;
+            };
+            stake.delegation.stake = stake.delegation.stake.saturating_add(reward.lamports);
+            stake.credits_observed = reward.credits_observed;
+            if account.checked_add_lamports(reward.lamports).is_err()
+                || bincode::serialize_into(
+                    account.data_as_mut_slice(),
+                    &StakeStateV2::Stake(meta, stake, flags),
+                )
+                .is_err()
+            {
+                continue;
+            }
+            self.accounts_db.store_account(reward.stake_pubkey, account);
+            distributed += reward.lamports;
+        }
+        epoch_rewards.distributed_partitions += 1;
+        epoch_rewards.distributed_stake_rewards += distributed;
+    }
+}
+
+/// Points `stake` earned since it last observed its vote account's credits,
+/// and the credits it observes once rewarded for them.
+///
+/// Every epoch in the vote account's credit history adds the vote credits
+/// earned in it times the effective stake `stake_at` returns for it.
+fn stake_points_and_credits(
+    stake: &Stake,
+    vote_state: &VoteStateV3,
+    stake_at: impl Fn(Epoch) -> u64,
+) -> (u128, u64) {
+    let credits_observed = stake.credits_observed;
+    let mut new_credits_observed = credits_observed;
+    let mut points = 0u128;
+    for &(epoch, final_credits, initial_credits) in &vote_state.epoch_credits {
+        let earned_credits = if credits_observed < initial_credits {
+            final_credits - initial_credits
+        } else if credits_observed < final_credits {
+            final_credits - new_credits_observed
+        } else {
+            0
+        };
+        new_credits_observed = new_credits_observed.max(final_credits);
+        points += u128::from(stake_at(epoch)) * u128::from(earned_credits);
+    }
+    (points, new_credits_observed)
+}
+
+/// Splits `rewards` into the vote account's `commission` percent and the
+/// staker's rest, rounding both down.
+fn commission_split(commission: u8, rewards: u64) -> (u64, u64) {
+    match commission.min(100) {
+        0 => (0, rewards),
+        100 => (rewards, 0),
+        commission => {
+            let rewards = u128::from(rewards);
+            let voter = rewards * u128::from(commission) / 100;
+            let staker = rewards * u128::from(100 - commission) / 100;
+            (voter as u64, staker as u64)
+        }
+    }
+}
+
+/// The partition of `num_partitions` whose block pays `stake_pubkey`,
+/// spread by hashing it with the blockhash the calculation started from.
+fn partition_index(blockhash: &Hash, stake_pubkey: &Pubkey, num_partitions: usize) -> usize {
+    let hash = solana_sha256_hasher::hashv(&[blockhash.as_ref(), stake_pubkey.as_ref()]);
+    let bytes: [u8; 8] = hash.as_ref()[..8].try_into().unwrap();
+    ((u128::from(u64::from_le_bytes(bytes)) * num_partitions as u128) >> 64) as usize
+}
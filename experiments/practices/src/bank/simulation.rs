@@ -0,0 +1,118 @@
+use {
+    super::Bank,
+    crate::{
+        fees::FeeDetails,
+        svm::{
+            AccountLoader, AccountOverrides, InnerInstruction, OverriddenAccountLoader,
+            TransactionAccount, TransactionReturnData,
+        },
+    },
+    solana_account::{ReadableAccount, WritableAccount},
+    solana_clock::MAX_PROCESSING_AGE,
+    solana_sdk_ids::{bpf_loader, bpf_loader_upgradeable},
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+};
+
+/// What a transaction would do if the bank processed it now.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimulationResult {
+    /// Whether the transaction would be rejected, fail or succeed.
+    pub status: Result<(), TransactionError>,
+    /// Log lines of every instruction that ran.
+    pub log_messages: Vec<String>,
+    pub units_consumed: u64,
+    pub return_data: Option<TransactionReturnData>,
+    pub inner_instructions: Vec<Vec<InnerInstruction>>,
+    /// Fee the transaction would be charged.
+    pub fee_details: FeeDetails,
+    /// Every account the message marks writable, in message order, as it
+    /// would be committed. The fee payer has its fee taken; a failed
+    /// transaction leaves the other accounts as they were loaded.
+    pub post_accounts: Vec<TransactionAccount>,
+}
+
+impl Bank {
+    /// Runs `transaction` the way [`process_transaction_batch`] would,
+    /// without committing anything.
+    ///
+    /// Accounts in `account_overrides` are loaded instead of the ones this
+    /// bank stores, fee payer included. The age check still goes through
+    /// the bank's blockhash queue and nonce accounts. Nothing the
+    /// simulation does outlives it: no account is stored, no fee collected
+    /// and, when programs are overridden, no program cached. Simulating the
+    /// same transaction against the same bank and overrides always gives
+    /// the same result.
+    ///
+    /// [`process_transaction_batch`]: Bank::process_transaction_batch
+    pub fn simulate_transaction(
+        &self,
+        transaction: &SanitizedTransaction,
+        account_overrides: &AccountOverrides,
+    ) -> SimulationResult {
+        let rejected = |err| SimulationResult {
+            status: Err(err),
+            log_messages: vec![],
+            units_consumed: 0,
+            return_data: None,
+            inner_instructions: vec![],
+            fee_details: FeeDetails::default(),
+            post_accounts: vec![],
+        };
+        if let Err(err) = self.check_age(transaction, MAX_PROCESSING_AGE) {
+            return rejected(err);
+        }
+        let loader = OverriddenAccountLoader {
+            overrides: account_overrides,
+            loader: &self.accounts_db,
+        };
+        let fee_details = match self.validate_fee_payer_in(&loader, transaction) {
+            Ok(fee_details) => fee_details,
+            Err(err) => return rejected(err),
+        };
+
+        let fee_payer = transaction.message().fee_payer();
+        let mut charged_overrides = account_overrides.clone();
+        let mut payer = loader
+            .load_account(fee_payer)
+            .expect("the fee payer was validated");
+        payer.set_lamports(payer.lamports() - fee_details.total_fee());
+        charged_overrides.set_account(*fee_payer, payer);
+        let loader = OverriddenAccountLoader {
+            overrides: &charged_overrides,
+            loader: &self.accounts_db,
+        };
+
+        // An overridden program must not be cached under the deployment
+        // key of the stored one.
+        let overrides_programs = account_overrides.iter().any(|(_, account)| {
+            *account.owner() == bpf_loader::id() || *account.owner() == bpf_loader_upgradeable::id()
+        });
+        let result = if overrides_programs {
+            self.executor
+                .with_private_program_cache()
+                .load_and_execute_transaction(&loader, transaction)
+        } else {
+            self.executor
+                .load_and_execute_transaction(&loader, transaction)
+        };
+
+        let message = transaction.message();
+        let post_accounts = result
+            .post_accounts
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| message.is_writable(*index))
+            .map(|(_, account)| account)
+            .collect();
+        SimulationResult {
+            status: result.status,
+            log_messages: result.log_messages,
+            units_consumed: result.consumed_units,
+            return_data: result.return_data,
+            inner_instructions: result.inner_instructions,
+            fee_details,
+            post_accounts,
+        }
+    }
+}
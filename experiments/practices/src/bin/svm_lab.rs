@@ -0,0 +1,67 @@
+//! Runs a scheduling and execution experiment described in a TOML file.
+//!
+//! ```text
+//! svm-lab <experiment.toml> [--output <report.json|report.csv>]
+//! ```
+//!
+//! See [`priority_graph_practice::lab`] for what an experiment file holds.
+//! Without `--output` the JSON report goes to stdout.
+
+use {
+    priority_graph_practice::lab::{run_experiment, ExperimentConfig, ExperimentError},
+    std::{
+        env,
+        path::{Path, PathBuf},
+        process::ExitCode,
+    },
+};
+
+const USAGE: &str = "usage: svm-lab <experiment.toml> [--output <report.json|report.csv>]";
+
+fn main() -> ExitCode {
+    let Some((experiment, output)) = parse_args(env::args().skip(1)) else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    match run(&experiment, output.as_deref()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("svm-lab: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<(PathBuf, Option<PathBuf>)> {
+    let experiment = PathBuf::from(args.next()?);
+    let output = match args.next().as_deref() {
+        None => None,
+        Some("--output" | "-o") => Some(PathBuf::from(args.next()?)),
+        Some(_) => return None,
+    };
+    args.next().is_none().then_some((experiment, output))
+}
+
+fn run(experiment: &Path, output: Option<&Path>) -> Result<(), ExperimentError> {
+    let config = ExperimentConfig::load(experiment)?;
+    let report = run_experiment(&config)?;
+    match output {
+        Some(output) => {
+            report.write(output)?;
+            eprintln!(
+                "{}: {} of {} transactions executed in {} slots, {:.0} per second",
+                config.name,
+                report.transactions_executed(),
+                config.workload.num_transactions,
+                report.slots.len(),
+                report.throughput()
+            );
+        }
+        None => println!(
+            "{}",
+            serde_json::to_string_pretty(&report.to_json())
+                .expect("a JSON value always serializes")
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,272 @@
+//! An append-only ledger of executed transactions.
+//!
+//! A [`Blockstore`] persists the transactions a [`Bank`] executed as
+//! [`Entry`]s, the unit a leader streams blocks in, so an experiment can be
+//! stopped and its slots replayed later into a bank rebuilt from the same
+//! starting state. Each entry chains to the previous one through a
+//! PoH-like hash: `num_hashes` rounds of SHA-256 over the previous entry's
+//! hash, the last one mixed with the entry's transaction signatures. An
+//! entry with `num_hashes` zero skips the hashing rounds but still mixes
+//! its transactions in, so the chain verifies either way.
+//!
+//! The file starts with a magic and a format version, followed by one
+//! record per entry: the slot as a little-endian `u64`, the length of the
+//! payload as a little-endian `u32`, and the payload itself, the entry's
+//! `num_hashes`, hash and transactions encoded with bincode. A record cut
+//! short by a crash is dropped when the store is reopened.
+
+use {
+    crate::bank::{Bank, TransactionProcessingResult},
+    solana_clock::Slot,
+    solana_hash::Hash,
+    solana_transaction::{sanitized::SanitizedTransaction, versioned::VersionedTransaction},
+    std::{
+        fs::{File, OpenOptions},
+        io::{self, Read, Write},
+        path::{Path, PathBuf},
+    },
+};
+
+const MAGIC: &[u8; 7] = b"SVMLDGR";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+/// Slot and payload length.
+const RECORD_HEADER_LEN: usize = 8 + 4;
+
+/// Why a blockstore could not be opened or read.
+#[derive(Debug, thiserror::Error)]
+pub enum BlockstoreError {
+    #[error("blockstore I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("file is not a blockstore")]
+    InvalidHeader,
+    #[error("unsupported blockstore version {0}")]
+    UnsupportedVersion(u8),
+    #[error("record at offset {0} is corrupted")]
+    CorruptedRecord(usize),
+}
+
+/// A group of transactions and the PoH-like hash that orders it after the
+/// previous entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub num_hashes: u64,
+    pub hash: Hash,
+    pub transactions: Vec<VersionedTransaction>,
+}
+
+impl Entry {
+    /// Creates the entry that follows the one hashed to `prev_hash`.
+    pub fn new(prev_hash: &Hash, num_hashes: u64, transactions: Vec<VersionedTransaction>) -> Self {
+        let hash = next_hash(prev_hash, num_hashes, &transactions);
+        Self {
+            num_hashes,
+            hash,
+            transactions,
+        }
+    }
+
+    /// Creates the entry of the transactions of `transactions` that
+    /// executed, leaving out the ones the bank rejected, which do not make
+    /// it into blocks.
+    ///
+    /// `results` line up with `transactions`, as
+    /// [`Bank::process_transaction_batch`] returns them.
+    pub fn from_executed_batch(
+        prev_hash: &Hash,
+        num_hashes: u64,
+        transactions: &[SanitizedTransaction],
+        results: &[TransactionProcessingResult],
+    ) -> Self {
+        assert_eq!(
+            transactions.len(),
+            results.len(),
+            "every transaction needs a processing result"
+        );
+        let executed = transactions
+            .iter()
+            .zip(results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(transaction, _)| transaction.to_versioned_transaction())
+            .collect();
+        Self::new(prev_hash, num_hashes, executed)
+    }
+
+    /// Whether this entry's hash follows from `prev_hash`.
+    pub fn verify(&self, prev_hash: &Hash) -> bool {
+        self.hash == next_hash(prev_hash, self.num_hashes, &self.transactions)
+    }
+}
+
+fn next_hash(prev_hash: &Hash, num_hashes: u64, transactions: &[VersionedTransaction]) -> Hash {
+    let mut hash = *prev_hash;
+    if transactions.is_empty() {
+        for _ in 0..num_hashes {
+            hash = solana_sha256_hasher::hash(hash.as_ref());
+        }
+        return hash;
+    }
+    for _ in 1..num_hashes {
+        hash = solana_sha256_hasher::hash(hash.as_ref());
+    }
+    let signatures: Vec<&[u8]> = transactions
+        .iter()
+        .flat_map(|transaction| &transaction.signatures)
+        .map(|signature| signature.as_ref())
+        .collect();
+    let mixin = solana_sha256_hasher::hashv(&signatures);
+    solana_sha256_hasher::hashv(&[hash.as_ref(), mixin.as_ref()])
+}
+
+/// Whether `entries` chain one after another starting from `start_hash`.
+pub fn verify_entries(start_hash: &Hash, entries: &[Entry]) -> bool {
+    let mut prev_hash = *start_hash;
+    entries.iter().all(|entry| {
+        let verified = entry.verify(&prev_hash);
+        prev_hash = entry.hash;
+        verified
+    })
+}
+
+/// Executes the transactions of `entries` on `bank`, one entry per batch.
+///
+/// Returns the results of every transaction in entry order. A transaction
+/// that no longer resolves against the bank, for example because an
+/// address lookup table it uses is missing, gets the resolution error.
+pub fn replay_entries(bank: &mut Bank, entries: &[Entry]) -> Vec<TransactionProcessingResult> {
+    let mut results = Vec::new();
+    for entry in entries {
+        let resolved: Vec<_> = entry
+            .transactions
+            .iter()
+            .map(|transaction| bank.resolve_transaction(transaction.clone()))
+            .collect();
+        let transactions: Vec<SanitizedTransaction> = resolved
+            .iter()
+            .filter_map(|transaction| transaction.as_ref().ok().cloned())
+            .collect();
+        let mut processed = bank.process_transaction_batch(&transactions).into_iter();
+        results.extend(resolved.into_iter().map(|transaction| {
+            match transaction {
+                Ok(_) => processed
+                    .next()
+                    .expect("every resolved transaction is processed"),
+                Err(err) => Err(err),
+            }
+        }));
+    }
+    results
+}
+
+/// An append-only file of entries, keyed by slot.
+pub struct Blockstore {
+    path: PathBuf,
+    file: File,
+}
+
+impl Blockstore {
+    /// Opens the blockstore at `path`, creating it if it does not exist.
+    ///
+    /// A trailing record left incomplete by an interrupted write is
+    /// truncated away, so appending resumes after the last complete entry.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BlockstoreError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        if contents.is_empty() {
+            file.write_all(MAGIC)?;
+            file.write_all(&[VERSION])?;
+            file.sync_data()?;
+        } else {
+            let complete_len = parse_records(&contents)?.1;
+            if complete_len < contents.len() {
+                file.set_len(complete_len as u64)?;
+            }
+        }
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `entries` to `slot` and flushes them to disk.
+    pub fn append_entries(&mut self, slot: Slot, entries: &[Entry]) -> Result<(), BlockstoreError> {
+        let mut records = Vec::new();
+        for entry in entries {
+            let payload =
+                bincode::serialize(&(entry.num_hashes, entry.hash.to_bytes(), &entry.transactions))
+                    .expect("entries serialize");
+            records.extend_from_slice(&slot.to_le_bytes());
+            records.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            records.extend_from_slice(&payload);
+        }
+        self.file.write_all(&records)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Every entry in the store, in the order it was appended.
+    pub fn read_all(&self) -> Result<Vec<(Slot, Entry)>, BlockstoreError> {
+        let contents = std::fs::read(&self.path)?;
+        Ok(parse_records(&contents)?.0)
+    }
+
+    /// The entries of `slot`, in the order they were appended.
+    pub fn slot_entries(&self, slot: Slot) -> Result<Vec<Entry>, BlockstoreError> {
+        Ok(self
+            .read_all()?
+            .into_iter()
+            .filter(|(entry_slot, _)| *entry_slot == slot)
+            .map(|(_, entry)| entry)
+            .collect())
+    }
+
+    /// The slots that have entries, in ascending order.
+    pub fn slots(&self) -> Result<Vec<Slot>, BlockstoreError> {
+        let mut slots: Vec<Slot> = self.read_all()?.into_iter().map(|(slot, _)| slot).collect();
+        slots.sort_unstable();
+        slots.dedup();
+        Ok(slots)
+    }
+}
+
+/// Parses the header and records of `contents`, returning the entries and
+/// the length of the complete records.
+fn parse_records(contents: &[u8]) -> Result<(Vec<(Slot, Entry)>, usize), BlockstoreError> {
+    if contents.len() < HEADER_LEN || &contents[..MAGIC.len()] != MAGIC {
+        return Err(BlockstoreError::InvalidHeader);
+    }
+    if contents[MAGIC.len()] != VERSION {
+        return Err(BlockstoreError::UnsupportedVersion(contents[MAGIC.len()]));
+    }
+
+    let mut entries = Vec::new();
+    let mut offset = HEADER_LEN;
+    while contents.len() - offset >= RECORD_HEADER_LEN {
+        let slot = Slot::from_le_bytes(contents[offset..offset + 8].try_into().unwrap());
+        let payload_len =
+            u32::from_le_bytes(contents[offset + 8..offset + 12].try_into().unwrap()) as usize;
+        let payload_start = offset + RECORD_HEADER_LEN;
+        let Some(payload) = contents.get(payload_start..payload_start + payload_len) else {
+            break;
+        };
+        let (num_hashes, hash, transactions): (u64, [u8; 32], Vec<VersionedTransaction>) =
+            bincode::deserialize(payload).map_err(|_| BlockstoreError::CorruptedRecord(offset))?;
+        entries.push((
+            slot,
+            Entry {
+                num_hashes,
+                hash: Hash::new_from_array(hash),
+                transactions,
+            },
+        ));
+        offset = payload_start + payload_len;
+    }
+    Ok((entries, offset))
+}
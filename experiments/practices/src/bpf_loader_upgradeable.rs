@@ -0,0 +1,884 @@
+//! The upgradeable BPF loader.
+//!
+//! A program of this loader lives in two accounts. The program account at
+//! the program id only records the address of its programdata account,
+//! which holds the deployment slot, the upgrade authority and the ELF.
+//! Programs are written into a buffer account first: `DeployWithMaxDataLen`
+//! creates the programdata account from a buffer, `Upgrade` replaces the
+//! ELF with the contents of another buffer, `SetAuthority` hands buffers and
+//! programs to a new authority or makes a program immutable, and `Close`
+//! reclaims the lamports of buffers and programs.
+//!
+//! Deployed programs only become invocable in the slot after their
+//! deployment, so a program deployed or upgraded in a slot keeps its old
+//! behavior, or none, until the bank moves on.
+
+use {
+    crate::{
+        svm::{self, InvokeContext},
+        system_program::{self, InstructionReader, MAX_PERMITTED_DATA_LENGTH},
+    },
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_clock::Slot,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{bpf_loader_upgradeable, sysvar},
+};
+
+/// CUs consumed by executing a single loader instruction.
+pub const DEFAULT_COMPUTE_UNITS: u64 = 2_370;
+
+/// Account state of the upgradeable loader.
+///
+/// The wire format is bincode, as for [`SystemInstruction`]; optional
+/// authorities take one byte for the variant and 32 more if present.
+///
+/// [`SystemInstruction`]: crate::system_program::SystemInstruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpgradeableLoaderState {
+    /// An account that holds nothing yet.
+    Uninitialized,
+    /// A buffer a program is written into before deployment.
+    Buffer { authority_address: Option<Pubkey> },
+    /// A deployed program, pointing at its programdata account.
+    Program { programdata_address: Pubkey },
+    /// The data of a deployed program. Without an upgrade authority the
+    /// program is immutable.
+    ProgramData {
+        slot: Slot,
+        upgrade_authority_address: Option<Pubkey>,
+    },
+}
+
+impl UpgradeableLoaderState {
+    pub const fn size_of_uninitialized() -> usize {
+        4
+    }
+
+    /// Size of the state at the start of a buffer account.
+    pub const fn size_of_buffer_metadata() -> usize {
+        4 + 1 + 32
+    }
+
+    /// Size of a buffer account holding a program of `program_len` bytes.
+    pub const fn size_of_buffer(program_len: usize) -> usize {
+        Self::size_of_buffer_metadata() + program_len
+    }
+
+    pub const fn size_of_program() -> usize {
+        4 + 32
+    }
+
+    /// Size of the state at the start of a programdata account.
+    pub const fn size_of_programdata_metadata() -> usize {
+        4 + 8 + 1 + 32
+    }
+
+    /// Size of a programdata account holding a program of `program_len`
+    /// bytes.
+    pub const fn size_of_programdata(program_len: usize) -> usize {
+        Self::size_of_programdata_metadata() + program_len
+    }
+
+    /// Reads the state at the start of `data`.
+    ///
+    /// Fails with [`InstructionError::InvalidAccountData`] if `data` does
+    /// not start with a valid state.
+    pub fn deserialize(data: &[u8]) -> Result<Self, InstructionError> {
+        let mut reader = InstructionReader::new(data);
+        let read_authority = |reader: &mut InstructionReader| match reader.read_u8()? {
+            0 => Ok(None),
+            1 => reader.read_pubkey().map(Some),
+            _ => Err(InstructionError::InvalidAccountData),
+        };
+        let state = match reader.read_u32() {
+            Ok(0) => Ok(Self::Uninitialized),
+            Ok(1) => read_authority(&mut reader)
+                .map(|authority_address| Self::Buffer { authority_address }),
+            Ok(2) => reader
+                .read_pubkey()
+                .map(|programdata_address| Self::Program {
+                    programdata_address,
+                }),
+            Ok(3) => reader.read_u64().and_then(|slot| {
+                read_authority(&mut reader).map(|upgrade_authority_address| Self::ProgramData {
+                    slot,
+                    upgrade_authority_address,
+                })
+            }),
+            _ => Err(InstructionError::InvalidAccountData),
+        };
+        state.map_err(|_| InstructionError::InvalidAccountData)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let discriminant: u32 = match self {
+            Self::Uninitialized => 0,
+            Self::Buffer { .. } => 1,
+            Self::Program { .. } => 2,
+            Self::ProgramData { .. } => 3,
+        };
+        let mut data = discriminant.to_le_bytes().to_vec();
+        let write_authority = |data: &mut Vec<u8>, authority: &Option<Pubkey>| match authority {
+            Some(authority) => {
+                data.push(1);
+                data.extend_from_slice(authority.as_ref());
+            }
+            None => data.push(0),
+        };
+        match self {
+            Self::Uninitialized => {}
+            Self::Buffer { authority_address } => write_authority(&mut data, authority_address),
+            Self::Program {
+                programdata_address,
+            } => data.extend_from_slice(programdata_address.as_ref()),
+            Self::ProgramData {
+                slot,
+                upgrade_authority_address,
+            } => {
+                data.extend_from_slice(&slot.to_le_bytes());
+                write_authority(&mut data, upgrade_authority_address);
+            }
+        }
+        data
+    }
+}
+
+/// Instructions understood by the upgradeable loader.
+///
+/// The wire format is bincode; discriminants match the on-chain program.
+/// Instructions of the on-chain program missing here fail with
+/// [`InstructionError::InvalidInstructionData`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpgradeableLoaderInstruction {
+    /// Initialize a buffer account, handing it to an authority.
+    ///
+    /// Accounts: `[writable]` uninitialized buffer account, `[]` buffer
+    /// authority.
+    InitializeBuffer,
+    /// Write program data into a buffer, `offset` bytes after its state.
+    ///
+    /// Accounts: `[writable]` buffer account, `[signer]` buffer authority.
+    Write { offset: u32, bytes: Vec<u8> },
+    /// Deploy the program in a buffer, creating a programdata account
+    /// that can hold programs of up to `max_data_len` bytes.
+    ///
+    /// The program account must be rent exempt and uninitialized. The
+    /// buffer's lamports go to the payer, which funds the programdata
+    /// account.
+    ///
+    /// Accounts: `[writable, signer]` payer, `[writable]` programdata
+    /// account, `[writable]` program account, `[writable]` buffer account,
+    /// `[]` Rent sysvar, `[]` Clock sysvar, `[]` System program, `[signer]`
+    /// authority of the buffer, which becomes the upgrade authority.
+    DeployWithMaxDataLen { max_data_len: usize },
+    /// Replace a program with the program in a buffer.
+    ///
+    /// Lamports of the buffer and of the programdata account beyond its
+    /// rent-exempt minimum go to the spill account.
+    ///
+    /// Accounts: `[writable]` programdata account, `[writable]` program
+    /// account, `[writable]` buffer account, `[writable]` spill account,
+    /// `[]` Rent sysvar, `[]` Clock sysvar, `[signer]` upgrade authority.
+    Upgrade,
+    /// Hand a buffer or program to a new authority. A program without a
+    /// new authority becomes immutable.
+    ///
+    /// Accounts: `[writable]` buffer or programdata account, `[signer]`
+    /// current authority, `[]` new authority, optional for programs.
+    SetAuthority,
+    /// Close an account, sending its lamports to the recipient.
+    ///
+    /// Accounts: `[writable]` account to close, `[writable]` recipient,
+    /// `[signer]` authority unless the account is uninitialized,
+    /// `[writable]` program account when closing a programdata account.
+    Close,
+}
+
+impl UpgradeableLoaderInstruction {
+    pub fn parse(data: &[u8]) -> Result<Self, InstructionError> {
+        let mut reader = InstructionReader::new(data);
+        match reader.read_u32()? {
+            0 => Ok(Self::InitializeBuffer),
+            1 => Ok(Self::Write {
+                offset: reader.read_u32()?,
+                bytes: reader.read_bytes()?.to_vec(),
+            }),
+            2 => Ok(Self::DeployWithMaxDataLen {
+                max_data_len: usize::try_from(reader.read_u64()?)
+                    .map_err(|_| InstructionError::InvalidInstructionData)?,
+            }),
+            3 => Ok(Self::Upgrade),
+            4 => Ok(Self::SetAuthority),
+            5 => Ok(Self::Close),
+            _ => Err(InstructionError::InvalidInstructionData),
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let discriminant: u32 = match self {
+            Self::InitializeBuffer => 0,
+            Self::Write { .. } => 1,
+            Self::DeployWithMaxDataLen { .. } => 2,
+            Self::Upgrade => 3,
+            Self::SetAuthority => 4,
+            Self::Close => 5,
+        };
+        let mut data = discriminant.to_le_bytes().to_vec();
+        match self {
+            Self::Write { offset, bytes } => {
+                data.extend_from_slice(&offset.to_le_bytes());
+                data.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                data.extend_from_slice(bytes);
+            }
+            Self::DeployWithMaxDataLen { max_data_len } => {
+                data.extend_from_slice(&(*max_data_len as u64).to_le_bytes())
+            }
+            Self::InitializeBuffer | Self::Upgrade | Self::SetAuthority | Self::Close => {}
+        }
+        data
+    }
+}
+
+/// Address of the programdata account of `program_address`.
+pub fn get_program_data_address(program_address: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_address.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+/// Builds the instructions that create a buffer for a program of
+/// `program_len` bytes, funded with `lamports` and handed to `authority`.
+pub fn create_buffer(
+    payer_address: &Pubkey,
+    buffer_address: &Pubkey,
+    authority_address: &Pubkey,
+    lamports: u64,
+    program_len: usize,
+) -> Vec<Instruction> {
+    vec![
+        system_program::create_account(
+            payer_address,
+            buffer_address,
+            lamports,
+            UpgradeableLoaderState::size_of_buffer(program_len) as u64,
+            &bpf_loader_upgradeable::id(),
+        ),
+        Instruction::new_with_bytes(
+            bpf_loader_upgradeable::id(),
+            &UpgradeableLoaderInstruction::InitializeBuffer.serialize(),
+            vec![
+                AccountMeta::new(*buffer_address, false),
+                AccountMeta::new_readonly(*authority_address, false),
+            ],
+        ),
+    ]
+}
+
+/// Builds a `Write` of `bytes` at `offset` into a buffer.
+pub fn write(
+    buffer_address: &Pubkey,
+    authority_address: &Pubkey,
+    offset: u32,
+    bytes: Vec<u8>,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        bpf_loader_upgradeable::id(),
+        &UpgradeableLoaderInstruction::Write { offset, bytes }.serialize(),
+        vec![
+            AccountMeta::new(*buffer_address, false),
+            AccountMeta::new_readonly(*authority_address, true),
+        ],
+    )
+}
+
+/// Builds the instructions that create a program account funded with
+/// `program_lamports` and deploy the program in a buffer to it.
+pub fn deploy_with_max_program_len(
+    payer_address: &Pubkey,
+    program_address: &Pubkey,
+    buffer_address: &Pubkey,
+    upgrade_authority_address: &Pubkey,
+    program_lamports: u64,
+    max_data_len: usize,
+) -> Vec<Instruction> {
+    let programdata_address = get_program_data_address(program_address);
+    vec![
+        system_program::create_account(
+            payer_address,
+            program_address,
+            program_lamports,
+            UpgradeableLoaderState::size_of_program() as u64,
+            &bpf_loader_upgradeable::id(),
+        ),
+        Instruction::new_with_bytes(
+            bpf_loader_upgradeable::id(),
+            &UpgradeableLoaderInstruction::DeployWithMaxDataLen { max_data_len }.serialize(),
+            vec![
+                AccountMeta::new(*payer_address, true),
+                AccountMeta::new(programdata_address, false),
+                AccountMeta::new(*program_address, false),
+                AccountMeta::new(*buffer_address, false),
+                AccountMeta::new_readonly(sysvar::rent::id(), false),
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+                AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+                AccountMeta::new_readonly(*upgrade_authority_address, true),
+            ],
+        ),
+    ]
+}
+
+/// Builds an `Upgrade` of a program to the program in a buffer.
+pub fn upgrade(
+    program_address: &Pubkey,
+    buffer_address: &Pubkey,
+    authority_address: &Pubkey,
+    spill_address: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        bpf_loader_upgradeable::id(),
+        &UpgradeableLoaderInstruction::Upgrade.serialize(),
+        vec![
+            AccountMeta::new(get_program_data_address(program_address), false),
+            AccountMeta::new(*program_address, false),
+            AccountMeta::new(*buffer_address, false),
+            AccountMeta::new(*spill_address, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(*authority_address, true),
+        ],
+    )
+}
+
+/// Builds a `SetAuthority` handing a buffer to `new_authority_address`.
+pub fn set_buffer_authority(
+    buffer_address: &Pubkey,
+    current_authority_address: &Pubkey,
+    new_authority_address: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        bpf_loader_upgradeable::id(),
+        &UpgradeableLoaderInstruction::SetAuthority.serialize(),
+        vec![
+            AccountMeta::new(*buffer_address, false),
+            AccountMeta::new_readonly(*current_authority_address, true),
+            AccountMeta::new_readonly(*new_authority_address, false),
+        ],
+    )
+}
+
+/// Builds a `SetAuthority` handing a program to `new_authority_address`,
+/// or making it immutable if that is `None`.
+pub fn set_upgrade_authority(
+    program_address: &Pubkey,
+    current_authority_address: &Pubkey,
+    new_authority_address: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(get_program_data_address(program_address), false),
+        AccountMeta::new_readonly(*current_authority_address, true),
+    ];
+    if let Some(new_authority_address) = new_authority_address {
+        accounts.push(AccountMeta::new_readonly(*new_authority_address, false));
+    }
+    Instruction::new_with_bytes(
+        bpf_loader_upgradeable::id(),
+        &UpgradeableLoaderInstruction::SetAuthority.serialize(),
+        accounts,
+    )
+}
+
+/// Builds a `Close` of a buffer or uninitialized account.
+pub fn close(
+    close_address: &Pubkey,
+    recipient_address: &Pubkey,
+    authority_address: &Pubkey,
+) -> Instruction {
+    close_any(
+        close_address,
+        recipient_address,
+        Some(authority_address),
+        None,
+    )
+}
+
+/// Builds a `Close` of any loader account. Closing a programdata account
+/// needs `program_address`; uninitialized accounts need no authority.
+pub fn close_any(
+    close_address: &Pubkey,
+    recipient_address: &Pubkey,
+    authority_address: Option<&Pubkey>,
+    program_address: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*close_address, false),
+        AccountMeta::new(*recipient_address, false),
+    ];
+    if let Some(authority_address) = authority_address {
+        accounts.push(AccountMeta::new_readonly(*authority_address, true));
+    }
+    if let Some(program_address) = program_address {
+        accounts.push(AccountMeta::new(*program_address, false));
+    }
+    Instruction::new_with_bytes(
+        bpf_loader_upgradeable::id(),
+        &UpgradeableLoaderInstruction::Close.serialize(),
+        accounts,
+    )
+}
+
+/// Whether `account` is a program account of this loader.
+pub(crate) fn is_upgradeable_program(account: &AccountSharedData) -> bool {
+    account.executable() && *account.owner() == bpf_loader_upgradeable::id()
+}
+
+/// Address of the programdata account a program account points at.
+pub(crate) fn programdata_address(program: &AccountSharedData) -> Option<Pubkey> {
+    match UpgradeableLoaderState::deserialize(program.data()) {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => Some(programdata_address),
+        _ => None,
+    }
+}
+
+/// The deployment slot and ELF held by a programdata account, if it holds
+/// a deployed program.
+pub(crate) fn deployed_program(programdata: &AccountSharedData) -> Option<(Slot, &[u8])> {
+    match UpgradeableLoaderState::deserialize(programdata.data()) {
+        Ok(UpgradeableLoaderState::ProgramData { slot, .. }) => Some((
+            slot,
+            programdata
+                .data()
+                .get(UpgradeableLoaderState::size_of_programdata_metadata()..)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Entrypoint of the upgradeable loader.
+pub fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    invoke_context.consume_builtin_cost(DEFAULT_COMPUTE_UNITS)?;
+    match UpgradeableLoaderInstruction::parse(invoke_context.instruction_data())? {
+        UpgradeableLoaderInstruction::InitializeBuffer => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_initialize_buffer(invoke_context)
+        }
+        UpgradeableLoaderInstruction::Write { offset, bytes } => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_write(invoke_context, offset as usize, &bytes)
+        }
+        UpgradeableLoaderInstruction::DeployWithMaxDataLen { max_data_len } => {
+            check_number_of_accounts(invoke_context, 8)?;
+            check_sysvar_account(invoke_context, 4, &sysvar::rent::id())?;
+            check_sysvar_account(invoke_context, 5, &sysvar::clock::id())?;
+            process_deploy(invoke_context, max_data_len)
+        }
+        UpgradeableLoaderInstruction::Upgrade => {
+            check_number_of_accounts(invoke_context, 7)?;
+            check_sysvar_account(invoke_context, 4, &sysvar::rent::id())?;
+            check_sysvar_account(invoke_context, 5, &sysvar::clock::id())?;
+            process_upgrade(invoke_context)
+        }
+        UpgradeableLoaderInstruction::SetAuthority => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_set_authority(invoke_context)
+        }
+        UpgradeableLoaderInstruction::Close => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_close(invoke_context)
+        }
+    }
+}
+
+fn check_number_of_accounts(
+    invoke_context: &InvokeContext,
+    expected: usize,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_number_of_accounts() < expected {
+        return Err(InstructionError::MissingAccount);
+    }
+    Ok(())
+}
+
+fn check_sysvar_account(
+    invoke_context: &InvokeContext,
+    index: usize,
+    sysvar_id: &Pubkey,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_key(index)? != sysvar_id {
+        return Err(InstructionError::InvalidArgument);
+    }
+    Ok(())
+}
+
+fn get_state(
+    invoke_context: &InvokeContext,
+    index: usize,
+) -> Result<UpgradeableLoaderState, InstructionError> {
+    UpgradeableLoaderState::deserialize(invoke_context.get_account(index)?.data())
+}
+
+fn set_state(
+    invoke_context: &mut InvokeContext,
+    index: usize,
+    state: UpgradeableLoaderState,
+) -> Result<(), InstructionError> {
+    let serialized = state.serialize();
+    invoke_context
+        .get_account_mut(index)?
+        .data_as_mut_slice()
+        .get_mut(..serialized.len())
+        .ok_or(InstructionError::AccountDataTooSmall)?
+        .copy_from_slice(&serialized);
+    Ok(())
+}
+
+/// Checks that `authority_address` is the authority recorded in an
+/// account, and that the instruction account at `index` is that authority
+/// and signed.
+fn check_authority(
+    invoke_context: &InvokeContext,
+    authority_address: Option<Pubkey>,
+    index: usize,
+) -> Result<(), InstructionError> {
+    let Some(authority_address) = authority_address else {
+        return Err(InstructionError::Immutable);
+    };
+    if *invoke_context.get_key(index)? != authority_address {
+        return Err(InstructionError::IncorrectAuthority);
+    }
+    if !invoke_context.is_signer(index)? {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+/// The program held by the buffer at `index`, after checking that the
+/// account at `authority_index` is its authority.
+fn buffer_program(
+    invoke_context: &InvokeContext,
+    index: usize,
+    authority_index: usize,
+) -> Result<Vec<u8>, InstructionError> {
+    let UpgradeableLoaderState::Buffer { authority_address } = get_state(invoke_context, index)?
+    else {
+        return Err(InstructionError::InvalidArgument);
+    };
+    check_authority(invoke_context, authority_address, authority_index)?;
+    let program = invoke_context
+        .get_account(index)?
+        .data()
+        .get(UpgradeableLoaderState::size_of_buffer_metadata()..)
+        .unwrap_or_default();
+    if program.is_empty() {
+        return Err(InstructionError::InvalidAccountData);
+    }
+    Ok(program.to_vec())
+}
+
+/// Writes the state and ELF of a programdata account and zeroes the rest
+/// of its data.
+fn write_programdata(
+    invoke_context: &mut InvokeContext,
+    index: usize,
+    slot: Slot,
+    upgrade_authority_address: Option<Pubkey>,
+    program: &[u8],
+) -> Result<(), InstructionError> {
+    set_state(
+        invoke_context,
+        index,
+        UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        },
+    )?;
+    let data = invoke_context
+        .get_account_mut(index)?
+        .data_as_mut_slice()
+        .get_mut(UpgradeableLoaderState::size_of_programdata_metadata()..)
+        .ok_or(InstructionError::AccountDataTooSmall)?;
+    if data.len() < program.len() {
+        return Err(InstructionError::AccountDataTooSmall);
+    }
+    let (elf, rest) = data.split_at_mut(program.len());
+    elf.copy_from_slice(program);
+    rest.fill(0);
+    Ok(())
+}
+
+/// Empties the buffer at `index` once its program was deployed.
+fn drain_buffer(invoke_context: &mut InvokeContext, index: usize) -> Result<u64, InstructionError> {
+    let buffer = invoke_context.get_account_mut(index)?;
+    let lamports = buffer.lamports();
+    buffer.set_lamports(0);
+    buffer.resize(UpgradeableLoaderState::size_of_buffer(0), 0);
+    Ok(lamports)
+}
+
+fn process_initialize_buffer(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    if get_state(invoke_context, 0)? != UpgradeableLoaderState::Uninitialized {
+        return Err(InstructionError::AccountAlreadyInitialized);
+    }
+    let authority_address = *invoke_context.get_key(1)?;
+    set_state(
+        invoke_context,
+        0,
+        UpgradeableLoaderState::Buffer {
+            authority_address: Some(authority_address),
+        },
+    )
+}
+
+fn process_write(
+    invoke_context: &mut InvokeContext,
+    offset: usize,
+    bytes: &[u8],
+) -> Result<(), InstructionError> {
+    let UpgradeableLoaderState::Buffer { authority_address } = get_state(invoke_context, 0)? else {
+        return Err(InstructionError::InvalidAccountData);
+    };
+    check_authority(invoke_context, authority_address, 1)?;
+    let start = UpgradeableLoaderState::size_of_buffer_metadata().saturating_add(offset);
+    invoke_context
+        .get_account_mut(0)?
+        .data_as_mut_slice()
+        .get_mut(start..start.saturating_add(bytes.len()))
+        .ok_or(InstructionError::AccountDataTooSmall)?
+        .copy_from_slice(bytes);
+    Ok(())
+}
+
+fn process_deploy(
+    invoke_context: &mut InvokeContext,
+    max_data_len: usize,
+) -> Result<(), InstructionError> {
+    let payer_address = *invoke_context.get_key(0)?;
+    let programdata_address = *invoke_context.get_key(1)?;
+    let program_address = *invoke_context.get_key(2)?;
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    let rent = sysvar_cache.get_rent().clone();
+    let slot = sysvar_cache.get_clock().slot;
+
+    let program = invoke_context.get_account(2)?;
+    if *program.owner() != bpf_loader_upgradeable::id() {
+        return Err(InstructionError::IncorrectProgramId);
+    }
+    if get_state(invoke_context, 2)? != UpgradeableLoaderState::Uninitialized {
+        return Err(InstructionError::AccountAlreadyInitialized);
+    }
+    if program.data().len() < UpgradeableLoaderState::size_of_program() {
+        return Err(InstructionError::AccountDataTooSmall);
+    }
+    if !rent.is_exempt(program.lamports(), program.data().len()) {
+        return Err(InstructionError::ExecutableAccountNotRentExempt);
+    }
+
+    let elf = buffer_program(invoke_context, 3, 7)?;
+    if max_data_len < elf.len() {
+        return Err(InstructionError::AccountDataTooSmall);
+    }
+    let programdata_len = UpgradeableLoaderState::size_of_programdata(max_data_len);
+    if programdata_len as u64 > MAX_PERMITTED_DATA_LENGTH {
+        return Err(InstructionError::InvalidArgument);
+    }
+    let (derived_address, bump_seed) =
+        Pubkey::find_program_address(&[program_address.as_ref()], &bpf_loader_upgradeable::id());
+    if derived_address != programdata_address {
+        return Err(InstructionError::InvalidArgument);
+    }
+    svm::verify_program(&elf, invoke_context)?;
+
+    // The payer gets the buffer's lamports and funds the programdata
+    // account, which only the loader can sign for.
+    let buffer_lamports = drain_buffer(invoke_context, 3)?;
+    invoke_context
+        .get_account_mut(0)?
+        .checked_add_lamports(buffer_lamports)
+        .map_err(|_| InstructionError::ArithmeticOverflow)?;
+    let create_programdata = system_program::create_account(
+        &payer_address,
+        &programdata_address,
+        rent.minimum_balance(programdata_len).max(1),
+        programdata_len as u64,
+        &bpf_loader_upgradeable::id(),
+    );
+    invoke_context.invoke_signed(
+        &create_programdata,
+        &[&[program_address.as_ref(), &[bump_seed]]],
+    )?;
+
+    let upgrade_authority_address = Some(*invoke_context.get_key(7)?);
+    write_programdata(invoke_context, 1, slot, upgrade_authority_address, &elf)?;
+    set_state(
+        invoke_context,
+        2,
+        UpgradeableLoaderState::Program {
+            programdata_address,
+        },
+    )?;
+    invoke_context.get_account_mut(2)?.set_executable(true);
+    invoke_context
+        .log_collector_mut()
+        .log(&format!("Deployed program {program_address}"));
+    Ok(())
+}
+
+fn process_upgrade(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    let programdata_address = *invoke_context.get_key(0)?;
+    let program_address = *invoke_context.get_key(1)?;
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    let rent = sysvar_cache.get_rent().clone();
+    let slot = sysvar_cache.get_clock().slot;
+
+    let program = invoke_context.get_account(1)?;
+    if !program.executable() {
+        return Err(InstructionError::AccountNotExecutable);
+    }
+    if !invoke_context.is_writable(1)? {
+        return Err(InstructionError::InvalidArgument);
+    }
+    if *program.owner() != bpf_loader_upgradeable::id() {
+        return Err(InstructionError::IncorrectProgramId);
+    }
+    match get_state(invoke_context, 1)? {
+        UpgradeableLoaderState::Program {
+            programdata_address: address,
+        } if address == programdata_address => {}
+        UpgradeableLoaderState::Program { .. } => return Err(InstructionError::InvalidArgument),
+        _ => return Err(InstructionError::InvalidAccountData),
+    }
+
+    let elf = buffer_program(invoke_context, 2, 6)?;
+    let UpgradeableLoaderState::ProgramData {
+        slot: deployment_slot,
+        upgrade_authority_address,
+    } = get_state(invoke_context, 0)?
+    else {
+        return Err(InstructionError::InvalidAccountData);
+    };
+    if deployment_slot == slot {
+        // The program was deployed or upgraded in this slot already.
+        return Err(InstructionError::InvalidArgument);
+    }
+    check_authority(invoke_context, upgrade_authority_address, 6)?;
+    let programdata = invoke_context.get_account(0)?;
+    let programdata_len = programdata.data().len();
+    if programdata_len < UpgradeableLoaderState::size_of_programdata(elf.len()) {
+        return Err(InstructionError::AccountDataTooSmall);
+    }
+    let required_lamports = rent.minimum_balance(programdata_len).max(1);
+    let available_lamports = programdata
+        .lamports()
+        .saturating_add(invoke_context.get_account(2)?.lamports());
+    if available_lamports < required_lamports {
+        return Err(InstructionError::InsufficientFunds);
+    }
+    svm::verify_program(&elf, invoke_context)?;
+
+    write_programdata(invoke_context, 0, slot, upgrade_authority_address, &elf)?;
+    drain_buffer(invoke_context, 2)?;
+    invoke_context
+        .get_account_mut(0)?
+        .set_lamports(required_lamports);
+    invoke_context
+        .get_account_mut(3)?
+        .checked_add_lamports(available_lamports - required_lamports)
+        .map_err(|_| InstructionError::ArithmeticOverflow)?;
+    invoke_context
+        .log_collector_mut()
+        .log(&format!("Upgraded program {program_address}"));
+    Ok(())
+}
+
+fn process_set_authority(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    let new_authority_address = match invoke_context.get_number_of_accounts() {
+        2 => None,
+        _ => Some(*invoke_context.get_key(2)?),
+    };
+    match get_state(invoke_context, 0)? {
+        UpgradeableLoaderState::Buffer { authority_address } => {
+            // Buffers always need an authority to deploy them.
+            if new_authority_address.is_none() {
+                return Err(InstructionError::IncorrectAuthority);
+            }
+            check_authority(invoke_context, authority_address, 1)?;
+            set_state(
+                invoke_context,
+                0,
+                UpgradeableLoaderState::Buffer {
+                    authority_address: new_authority_address,
+                },
+            )
+        }
+        UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        } => {
+            check_authority(invoke_context, upgrade_authority_address, 1)?;
+            set_state(
+                invoke_context,
+                0,
+                UpgradeableLoaderState::ProgramData {
+                    slot,
+                    upgrade_authority_address: new_authority_address,
+                },
+            )
+        }
+        _ => Err(InstructionError::InvalidArgument),
+    }
+}
+
+fn process_close(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    if invoke_context.get_index_in_transaction(0)? == invoke_context.get_index_in_transaction(1)? {
+        return Err(InstructionError::InvalidArgument);
+    }
+    match get_state(invoke_context, 0)? {
+        UpgradeableLoaderState::Uninitialized => {}
+        UpgradeableLoaderState::Buffer { authority_address } => {
+            check_number_of_accounts(invoke_context, 3)?;
+            check_authority(invoke_context, authority_address, 2)?;
+        }
+        UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        } => {
+            check_number_of_accounts(invoke_context, 4)?;
+            let programdata_address = *invoke_context.get_key(0)?;
+            let program = invoke_context.get_account(3)?;
+            if !invoke_context.is_writable(3)? {
+                return Err(InstructionError::InvalidArgument);
+            }
+            if *program.owner() != bpf_loader_upgradeable::id() {
+                return Err(InstructionError::IncorrectProgramId);
+            }
+            if slot == invoke_context.get_sysvar_cache().get_clock().slot {
+                // The program was deployed or upgraded in this slot.
+                return Err(InstructionError::InvalidArgument);
+            }
+            match get_state(invoke_context, 3)? {
+                UpgradeableLoaderState::Program {
+                    programdata_address: address,
+                } if address == programdata_address => {}
+                _ => return Err(InstructionError::InvalidArgument),
+            }
+            check_authority(invoke_context, upgrade_authority_address, 2)?;
+        }
+        UpgradeableLoaderState::Program { .. } => return Err(InstructionError::InvalidArgument),
+    }
+
+    let account = invoke_context.get_account_mut(0)?;
+    let lamports = account.lamports();
+    account.set_lamports(0);
+    account.resize(UpgradeableLoaderState::size_of_uninitialized(), 0);
+    set_state(invoke_context, 0, UpgradeableLoaderState::Uninitialized)?;
+    invoke_context
+        .get_account_mut(1)?
+        .checked_add_lamports(lamports)
+        .map_err(|_| InstructionError::ArithmeticOverflow)?;
+    let closed_address = *invoke_context.get_key(0)?;
+    invoke_context
+        .log_collector_mut()
+        .log(&format!("Closed account {closed_address}"));
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Named errors of the builtin programs.
+//!
+//! Transactions fail with the SDK's [`TransactionError`] and instructions
+//! with its [`InstructionError`], so statuses compare and serialize exactly
+//! as a cluster reports them. Builtins report errors of their own as
+//! [`InstructionError::Custom`] codes, which only mean something together
+//! with the program that failed: [`BuiltinError`] names the error a code
+//! stands for and converts back to the code.
+
+use {
+    crate::{precompiles::PrecompileError, system_program::SystemError, token_program::TokenError},
+    num_traits::{FromPrimitive, ToPrimitive},
+    solana_instruction_error::InstructionError,
+    solana_message::SanitizedMessage,
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{ed25519_program, secp256k1_program, secp256r1_program, stake, vote},
+    solana_stake_interface::error::StakeError,
+    solana_transaction_error::TransactionError,
+    solana_vote_interface::error::VoteError,
+};
+
+/// A custom error of one of the builtins.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum BuiltinError {
+    #[error(transparent)]
+    System(#[from] SystemError),
+    #[error(transparent)]
+    Token(#[from] TokenError),
+    #[error(transparent)]
+    Precompile(#[from] PrecompileError),
+    #[error(transparent)]
+    Vote(#[from] VoteError),
+    #[error(transparent)]
+    Stake(#[from] StakeError),
+}
+
+impl BuiltinError {
+    /// The error `program_id` reports with the custom code `code`, if it is
+    /// a builtin and the code one of its errors.
+    pub fn decode(program_id: &Pubkey, code: u32) -> Option<Self> {
+        match *program_id {
+            solana_sdk_ids::system_program::ID => SystemError::from_code(code).map(Self::System),
+            crate::token_program::ID => TokenError::from_code(code).map(Self::Token),
+            ed25519_program::ID | secp256k1_program::ID | secp256r1_program::ID => {
+                PrecompileError::from_code(code).map(Self::Precompile)
+            }
+            vote::ID => VoteError::from_u32(code).map(Self::Vote),
+            stake::ID => StakeError::from_u32(code).map(Self::Stake),
+            _ => None,
+        }
+    }
+
+    /// The error a transaction of `message` failed with, if an instruction
+    /// for a builtin failed with a custom code.
+    ///
+    /// The code is decoded as an error of the program the failing
+    /// top-level instruction is for, even if a program it invoked raised it.
+    pub fn from_transaction_error(
+        message: &SanitizedMessage,
+        err: &TransactionError,
+    ) -> Option<Self> {
+        let TransactionError::InstructionError(index, InstructionError::Custom(code)) = err else {
+            return None;
+        };
+        let (program_id, _) = message.program_instructions_iter().nth(*index as usize)?;
+        Self::decode(program_id, *code)
+    }
+
+    /// The custom code the error is reported with.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::System(err) => *err as u32,
+            Self::Token(err) => *err as u32,
+            Self::Precompile(err) => *err as u32,
+            Self::Vote(err) => err.to_u32().expect("vote errors have codes"),
+            Self::Stake(err) => err.to_u32().expect("stake errors have codes"),
+        }
+    }
+}
+
+impl From<BuiltinError> for InstructionError {
+    fn from(err: BuiltinError) -> Self {
+        Self::Custom(err.code())
+    }
+}
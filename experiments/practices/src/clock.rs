@@ -0,0 +1,120 @@
+//! Slot progression in wall-clock time.
+//!
+//! A leader only has its slot's duration to pack a block. [`SlotClock`]
+//! puts experiments under the same pressure: every slot lasts a configured
+//! duration, and once it is over the clock freezes the [`Bank`], moves it
+//! on to the next slot and tells its subscribers with [`SlotEvent`]s, which
+//! is what [`WorkerPool::run_in_slot`](crate::scheduler::WorkerPool::run_in_slot)
+//! watches to stop packing.
+//!
+//! The clock never reads the time itself; callers pass `now` in, so tests
+//! can drive it with made-up instants.
+
+use {
+    crate::bank::Bank,
+    crossbeam_channel::{unbounded, Receiver, Sender},
+    solana_clock::{Slot, DEFAULT_MS_PER_SLOT},
+    solana_hash::Hash,
+    std::time::{Duration, Instant},
+};
+
+/// How long a slot lasts unless configured otherwise.
+pub const DEFAULT_SLOT_DURATION: Duration = Duration::from_millis(DEFAULT_MS_PER_SLOT);
+
+/// A slot boundary, as a [`SlotClock`] reports it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlotEvent {
+    /// `slot` is over and its bank was frozen with `bank_hash`.
+    Ended { slot: Slot, bank_hash: Hash },
+    /// `slot` started and lasts until `deadline`.
+    Started { slot: Slot, deadline: Instant },
+}
+
+/// Ends slots once their time is up.
+pub struct SlotClock {
+    slot: Slot,
+    slot_duration: Duration,
+    slot_start: Instant,
+    subscribers: Vec<Sender<SlotEvent>>,
+}
+
+impl SlotClock {
+    /// Creates a clock whose current slot is `slot`, started at `now`.
+    pub fn new(slot: Slot, slot_duration: Duration, now: Instant) -> Self {
+        assert!(!slot_duration.is_zero(), "slots must last some time");
+        Self {
+            slot,
+            slot_duration,
+            slot_start: now,
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Returns a receiver of every event from now on.
+    pub fn subscribe(&mut self) -> Receiver<SlotEvent> {
+        let (sender, receiver) = unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    pub fn slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+
+    /// When the current slot ends.
+    pub fn deadline(&self) -> Instant {
+        self.slot_start + self.slot_duration
+    }
+
+    /// Time left in the current slot at `now`.
+    pub fn time_remaining(&self, now: Instant) -> Duration {
+        self.deadline().saturating_duration_since(now)
+    }
+
+    pub fn is_slot_over(&self, now: Instant) -> bool {
+        now >= self.deadline()
+    }
+
+    /// Ends every slot whose deadline passed by `now`, returning the events
+    /// it sent.
+    ///
+    /// For each one, `bank` is frozen and moved on to the next slot, with a
+    /// last blockhash derived from the bank hash standing in for the slot's
+    /// final PoH hash. A leader keeps producing blocks while it has nothing
+    /// to pack, so when several deadlines passed, every slot in between is
+    /// frozen empty rather than skipped.
+    ///
+    /// # Panics
+    ///
+    /// If `bank` is not at the clock's slot.
+    pub fn tick(&mut self, bank: &mut Bank, now: Instant) -> Vec<SlotEvent> {
+        assert_eq!(bank.slot(), self.slot, "bank must be at the clock's slot");
+        let mut events = Vec::new();
+        while self.is_slot_over(now) {
+            let bank_hash = bank.freeze();
+            events.push(SlotEvent::Ended {
+                slot: self.slot,
+                bank_hash,
+            });
+            bank.advance_slot(solana_sha256_hasher::hash(bank_hash.as_ref()));
+            self.slot = bank.slot();
+            self.slot_start += self.slot_duration;
+            events.push(SlotEvent::Started {
+                slot: self.slot,
+                deadline: self.deadline(),
+            });
+        }
+
+        // Subscribers that hung up are dropped.
+        self.subscribers.retain(|subscriber| {
+            events
+                .iter()
+                .all(|event| subscriber.send(event.clone()).is_ok())
+        });
+        events
+    }
+}
@@ -0,0 +1,264 @@
+//! Compute budget instructions and per-transaction compute unit limits.
+//!
+//! Transactions request their compute budget through instructions to the
+//! ComputeBudget program. Those instructions are parsed before execution;
+//! the resulting limit caps how many compute units (CUs) the transaction's
+//! instructions may consume, and the requested heap frame sets how much
+//! heap its SBF programs get.
+
+use {
+    crate::{
+        feature_set::{reserve_minimal_cus_for_builtin_instructions, FeatureSet},
+        svm::{InvokeContext, BUILTINS},
+    },
+    solana_instruction::Instruction,
+    solana_instruction_error::InstructionError,
+    solana_message::SanitizedMessage,
+    solana_sdk_ids::compute_budget,
+    solana_transaction_error::TransactionError,
+};
+
+/// CUs granted to every non-compute-budget instruction when the transaction
+/// does not set an explicit limit.
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+/// CUs granted to every builtin instruction when the transaction does not
+/// set an explicit limit, once builtins reserve only what they need.
+pub const MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT: u32 = 3_000;
+/// Upper bound on the CU limit a transaction can request.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// Upper bound on the total account data a transaction may load.
+pub const MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES: u32 = 64 * 1024 * 1024;
+/// CUs consumed by executing a single ComputeBudget instruction.
+pub const DEFAULT_COMPUTE_UNITS: u64 = 150;
+/// Heap every SBF program gets unless the transaction requests more.
+pub const MIN_HEAP_FRAME_BYTES: u32 = 32 * 1024;
+/// Largest heap a transaction can request.
+pub const MAX_HEAP_FRAME_BYTES: u32 = 256 * 1024;
+/// Granularity of heap requests.
+pub const HEAP_FRAME_PAGE_BYTES: u32 = 1024;
+
+/// Instructions understood by the ComputeBudget program.
+///
+/// The wire format is Borsh: a one byte discriminant followed by the
+/// little-endian argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeBudgetInstruction {
+    /// Request a specific heap frame size in bytes.
+    RequestHeapFrame(u32),
+    /// Set the transaction-wide compute unit limit.
+    SetComputeUnitLimit(u32),
+    /// Set the price of a compute unit in micro-lamports.
+    SetComputeUnitPrice(u64),
+    /// Set the maximum number of account data bytes the transaction loads.
+    SetLoadedAccountsDataSizeLimit(u32),
+}
+
+impl ComputeBudgetInstruction {
+    pub fn parse(data: &[u8]) -> Result<Self, InstructionError> {
+        let (&discriminant, argument) = data
+            .split_first()
+            .ok_or(InstructionError::InvalidInstructionData)?;
+        let u32_argument = || {
+            argument
+                .try_into()
+                .map(u32::from_le_bytes)
+                .map_err(|_| InstructionError::InvalidInstructionData)
+        };
+        match discriminant {
+            1 => u32_argument().map(Self::RequestHeapFrame),
+            2 => u32_argument().map(Self::SetComputeUnitLimit),
+            3 => argument
+                .try_into()
+                .map(|bytes| Self::SetComputeUnitPrice(u64::from_le_bytes(bytes)))
+                .map_err(|_| InstructionError::InvalidInstructionData),
+            4 => u32_argument().map(Self::SetLoadedAccountsDataSizeLimit),
+            _ => Err(InstructionError::InvalidInstructionData),
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        match *self {
+            Self::RequestHeapFrame(bytes) => [&[1], &bytes.to_le_bytes()[..]].concat(),
+            Self::SetComputeUnitLimit(units) => [&[2], &units.to_le_bytes()[..]].concat(),
+            Self::SetComputeUnitPrice(price) => [&[3], &price.to_le_bytes()[..]].concat(),
+            Self::SetLoadedAccountsDataSizeLimit(bytes) => {
+                [&[4], &bytes.to_le_bytes()[..]].concat()
+            }
+        }
+    }
+
+    pub fn set_compute_unit_limit(units: u32) -> Instruction {
+        Self::SetComputeUnitLimit(units).to_instruction()
+    }
+
+    pub fn request_heap_frame(bytes: u32) -> Instruction {
+        Self::RequestHeapFrame(bytes).to_instruction()
+    }
+
+    pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+        Self::SetComputeUnitPrice(micro_lamports).to_instruction()
+    }
+
+    fn to_instruction(self) -> Instruction {
+        Instruction::new_with_bytes(compute_budget::id(), &self.serialize(), vec![])
+    }
+}
+
+/// Compute budget a transaction requested, with defaults and caps applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComputeBudgetLimits {
+    pub compute_unit_limit: u32,
+    /// Price of a compute unit in micro-lamports.
+    pub compute_unit_price: u64,
+    pub loaded_accounts_bytes: u32,
+    /// Bytes of heap every SBF program of the transaction gets.
+    pub updated_heap_bytes: u32,
+}
+
+impl Default for ComputeBudgetLimits {
+    fn default() -> Self {
+        Self {
+            compute_unit_limit: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+            compute_unit_price: 0,
+            loaded_accounts_bytes: MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES,
+            updated_heap_bytes: MIN_HEAP_FRAME_BYTES,
+        }
+    }
+}
+
+/// Parses every ComputeBudget instruction in `message`, with every feature
+/// active.
+///
+/// Each kind of instruction may appear at most once. Requested values above
+/// the protocol maximums are clamped rather than rejected, except for heap
+/// frames: a request outside [`MIN_HEAP_FRAME_BYTES`] to
+/// [`MAX_HEAP_FRAME_BYTES`], or not a multiple of
+/// [`HEAP_FRAME_PAGE_BYTES`], fails the transaction with
+/// [`InstructionError::InvalidInstructionData`].
+pub fn process_compute_budget_instructions(
+    message: &SanitizedMessage,
+) -> Result<ComputeBudgetLimits, TransactionError> {
+    process_compute_budget_instructions_impl(message, true)
+}
+
+/// Parses every ComputeBudget instruction in `message` under the rules of
+/// `feature_set`.
+///
+/// Without an explicit limit, every instruction is granted
+/// [`DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT`]; once
+/// `reserve_minimal_cus_for_builtin_instructions` is active, instructions
+/// for builtins, ComputeBudget ones included, are granted
+/// [`MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT`] instead.
+pub fn process_compute_budget_instructions_with_features(
+    message: &SanitizedMessage,
+    feature_set: &FeatureSet,
+) -> Result<ComputeBudgetLimits, TransactionError> {
+    process_compute_budget_instructions_impl(
+        message,
+        feature_set.is_active(&reserve_minimal_cus_for_builtin_instructions::id()),
+    )
+}
+
+fn process_compute_budget_instructions_impl(
+    message: &SanitizedMessage,
+    reserve_minimal_cus_for_builtins: bool,
+) -> Result<ComputeBudgetLimits, TransactionError> {
+    let mut compute_unit_limit = None;
+    let mut compute_unit_price = None;
+    let mut loaded_accounts_bytes = None;
+    let mut requested_heap_frame = None;
+    let mut num_non_compute_budget_instructions: u32 = 0;
+    let mut num_builtin_instructions: u32 = 0;
+
+    for (index, (program_id, instruction)) in message.program_instructions_iter().enumerate() {
+        if BUILTINS
+            .iter()
+            .any(|builtin| builtin.program_id == *program_id)
+        {
+            num_builtin_instructions = num_builtin_instructions.saturating_add(1);
+        }
+        if *program_id != compute_budget::id() {
+            num_non_compute_budget_instructions =
+                num_non_compute_budget_instructions.saturating_add(1);
+            continue;
+        }
+
+        let index = index as u8;
+        let duplicate = || TransactionError::DuplicateInstruction(index);
+        match ComputeBudgetInstruction::parse(&instruction.data)
+            .map_err(|err| TransactionError::InstructionError(index, err))?
+        {
+            ComputeBudgetInstruction::RequestHeapFrame(bytes) => {
+                if requested_heap_frame.replace((index, bytes)).is_some() {
+                    return Err(duplicate());
+                }
+            }
+            ComputeBudgetInstruction::SetComputeUnitLimit(units) => {
+                if compute_unit_limit.replace(units).is_some() {
+                    return Err(duplicate());
+                }
+            }
+            ComputeBudgetInstruction::SetComputeUnitPrice(price) => {
+                if compute_unit_price.replace(price).is_some() {
+                    return Err(duplicate());
+                }
+            }
+            ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(bytes) => {
+                if loaded_accounts_bytes.replace(bytes).is_some() {
+                    return Err(duplicate());
+                }
+            }
+        }
+    }
+
+    let compute_unit_limit = compute_unit_limit
+        .unwrap_or_else(|| {
+            if reserve_minimal_cus_for_builtins {
+                let num_instructions = message.instructions().len() as u32;
+                num_builtin_instructions
+                    .saturating_mul(MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT)
+                    .saturating_add(
+                        num_instructions
+                            .saturating_sub(num_builtin_instructions)
+                            .saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT),
+                    )
+            } else {
+                num_non_compute_budget_instructions
+                    .saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)
+            }
+        })
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let loaded_accounts_bytes = loaded_accounts_bytes
+        .unwrap_or(MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES)
+        .min(MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES);
+    let updated_heap_bytes = match requested_heap_frame {
+        Some((_, bytes)) if is_valid_heap_frame(bytes) => bytes,
+        Some((index, _)) => {
+            return Err(TransactionError::InstructionError(
+                index,
+                InstructionError::InvalidInstructionData,
+            ))
+        }
+        None => MIN_HEAP_FRAME_BYTES,
+    };
+
+    Ok(ComputeBudgetLimits {
+        compute_unit_limit,
+        compute_unit_price: compute_unit_price.unwrap_or_default(),
+        loaded_accounts_bytes,
+        updated_heap_bytes,
+    })
+}
+
+fn is_valid_heap_frame(bytes: u32) -> bool {
+    (MIN_HEAP_FRAME_BYTES..=MAX_HEAP_FRAME_BYTES).contains(&bytes)
+        && bytes.is_multiple_of(HEAP_FRAME_PAGE_BYTES)
+}
+
+/// Entrypoint of the ComputeBudget program.
+///
+/// The instructions were already applied before execution started, so
+/// executing them only costs CUs.
+pub fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    invoke_context.consume_builtin_cost(DEFAULT_COMPUTE_UNITS)
+}
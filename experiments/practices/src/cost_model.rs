@@ -0,0 +1,291 @@
+//! Block packing costs, as the validator's QoS tracks them.
+//!
+//! [`CostModel`] estimates what a transaction costs a block in compute
+//! units, before it executes: its signatures, write locks, instruction
+//! data, requested compute units and loaded account data. A
+//! [`CostTracker`] adds those costs up for a block and turns away
+//! transactions that would push the block, or any account it writes, over
+//! its limit, so a single hot account cannot take up a whole block.
+
+use {
+    crate::{
+        compute_budget::{
+            process_compute_budget_instructions, process_compute_budget_instructions_with_features,
+            ComputeBudgetLimits,
+        },
+        feature_set::FeatureSet,
+    },
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+    std::collections::HashMap,
+};
+
+/// Compute units that stand for one microsecond of execution time.
+const COMPUTE_UNIT_TO_US_RATIO: u64 = 30;
+/// Cost of verifying a transaction signature.
+pub const SIGNATURE_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 24;
+/// Cost of verifying a secp256k1 precompile signature.
+pub const SECP256K1_VERIFY_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 223;
+/// Cost of verifying an ed25519 precompile signature.
+pub const ED25519_VERIFY_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 76;
+/// Cost of verifying a secp256r1 precompile signature.
+pub const SECP256R1_VERIFY_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 160;
+/// Cost of taking a write lock.
+pub const WRITE_LOCK_UNITS: u64 = COMPUTE_UNIT_TO_US_RATIO * 10;
+/// Bytes of instruction data that cost one compute unit.
+pub const INSTRUCTION_DATA_BYTES_COST: u64 = 140 / COMPUTE_UNIT_TO_US_RATIO;
+/// Loaded account data is charged per page of this many bytes.
+pub const ACCOUNT_DATA_COST_PAGE_SIZE: u64 = 32 * 1024;
+/// Cost of one page of loaded account data.
+pub const LOADED_ACCOUNTS_DATA_PAGE_COST: u64 = 8;
+
+/// Most compute units the transactions of a block may cost together.
+pub const MAX_BLOCK_UNITS: u64 = 48_000_000;
+/// Most compute units the transactions writing a single account may cost
+/// together within a block.
+pub const MAX_WRITABLE_ACCOUNT_UNITS: u64 = 12_000_000;
+
+/// What a transaction costs a block, part by part.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionCost {
+    /// Transaction signatures plus precompile signatures.
+    pub signature_cost: u64,
+    pub write_lock_cost: u64,
+    pub data_bytes_cost: u64,
+    /// Requested compute units, until [`CostTracker::update_execution_cost`]
+    /// replaces them with the consumed ones.
+    pub programs_execution_cost: u64,
+    pub loaded_accounts_data_size_cost: u64,
+    /// Accounts the transaction write locks, each charged the whole cost.
+    pub writable_accounts: Vec<Pubkey>,
+}
+
+impl TransactionCost {
+    pub fn sum(&self) -> u64 {
+        self.signature_cost
+            .saturating_add(self.write_lock_cost)
+            .saturating_add(self.data_bytes_cost)
+            .saturating_add(self.programs_execution_cost)
+            .saturating_add(self.loaded_accounts_data_size_cost)
+    }
+}
+
+/// Estimates the [`TransactionCost`] of transactions.
+pub struct CostModel;
+
+impl CostModel {
+    /// Cost of `transaction` with every feature active.
+    pub fn calculate_cost(transaction: &SanitizedTransaction) -> TransactionCost {
+        // A transaction with invalid compute budget instructions fails
+        // before executing anything, so it requests nothing.
+        let limits = process_compute_budget_instructions(transaction.message()).ok();
+        Self::calculate_cost_impl(transaction, limits)
+    }
+
+    /// Cost of `transaction` under the compute budget rules of
+    /// `feature_set`.
+    pub fn calculate_cost_with_features(
+        transaction: &SanitizedTransaction,
+        feature_set: &FeatureSet,
+    ) -> TransactionCost {
+        let limits =
+            process_compute_budget_instructions_with_features(transaction.message(), feature_set)
+                .ok();
+        Self::calculate_cost_impl(transaction, limits)
+    }
+
+    fn calculate_cost_impl(
+        transaction: &SanitizedTransaction,
+        limits: Option<ComputeBudgetLimits>,
+    ) -> TransactionCost {
+        let message = transaction.message();
+        let signature_details = message.get_signature_details();
+        let signature_cost = signature_details
+            .num_transaction_signatures()
+            .saturating_mul(SIGNATURE_COST)
+            .saturating_add(
+                signature_details
+                    .num_secp256k1_instruction_signatures()
+                    .saturating_mul(SECP256K1_VERIFY_COST),
+            )
+            .saturating_add(
+                signature_details
+                    .num_ed25519_instruction_signatures()
+                    .saturating_mul(ED25519_VERIFY_COST),
+            )
+            .saturating_add(
+                signature_details
+                    .num_secp256r1_instruction_signatures()
+                    .saturating_mul(SECP256R1_VERIFY_COST),
+            );
+
+        let writable_accounts: Vec<Pubkey> = message
+            .account_keys()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| message.is_writable(*index))
+            .map(|(_, pubkey)| *pubkey)
+            .collect();
+        let data_len: u64 = message
+            .instructions()
+            .iter()
+            .map(|instruction| instruction.data.len() as u64)
+            .sum();
+        let (programs_execution_cost, loaded_accounts_data_size_cost) =
+            limits.map_or((0, 0), |limits| {
+                (
+                    u64::from(limits.compute_unit_limit),
+                    u64::from(limits.loaded_accounts_bytes)
+                        .div_ceil(ACCOUNT_DATA_COST_PAGE_SIZE)
+                        .saturating_mul(LOADED_ACCOUNTS_DATA_PAGE_COST),
+                )
+            });
+
+        TransactionCost {
+            signature_cost,
+            write_lock_cost: (writable_accounts.len() as u64).saturating_mul(WRITE_LOCK_UNITS),
+            data_bytes_cost: data_len / INSTRUCTION_DATA_BYTES_COST,
+            programs_execution_cost,
+            loaded_accounts_data_size_cost,
+            writable_accounts,
+        }
+    }
+}
+
+/// Why a [`CostTracker`] turned a transaction away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CostTrackerError {
+    #[error("would exceed the block cost limit")]
+    WouldExceedBlockMaxLimit,
+    #[error("would exceed the cost limit of a writable account")]
+    WouldExceedAccountMaxLimit,
+}
+
+impl From<CostTrackerError> for TransactionError {
+    fn from(err: CostTrackerError) -> Self {
+        match err {
+            CostTrackerError::WouldExceedBlockMaxLimit => Self::WouldExceedMaxBlockCostLimit,
+            CostTrackerError::WouldExceedAccountMaxLimit => Self::WouldExceedMaxAccountCostLimit,
+        }
+    }
+}
+
+/// Running cost of a block, in total and per writable account.
+#[derive(Clone, Debug)]
+pub struct CostTracker {
+    block_cost_limit: u64,
+    account_cost_limit: u64,
+    block_cost: u64,
+    cost_by_writable_accounts: HashMap<Pubkey, u64>,
+    transaction_count: u64,
+}
+
+impl Default for CostTracker {
+    fn default() -> Self {
+        Self::new(MAX_BLOCK_UNITS, MAX_WRITABLE_ACCOUNT_UNITS)
+    }
+}
+
+impl CostTracker {
+    pub fn new(block_cost_limit: u64, account_cost_limit: u64) -> Self {
+        Self {
+            block_cost_limit,
+            account_cost_limit,
+            block_cost: 0,
+            cost_by_writable_accounts: HashMap::new(),
+            transaction_count: 0,
+        }
+    }
+
+    pub fn block_cost_limit(&self) -> u64 {
+        self.block_cost_limit
+    }
+
+    pub fn account_cost_limit(&self) -> u64 {
+        self.account_cost_limit
+    }
+
+    pub fn block_cost(&self) -> u64 {
+        self.block_cost
+    }
+
+    /// Cost of the transactions so far that write `pubkey`.
+    pub fn account_cost(&self, pubkey: &Pubkey) -> u64 {
+        self.cost_by_writable_accounts
+            .get(pubkey)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn transaction_count(&self) -> u64 {
+        self.transaction_count
+    }
+
+    /// Checks that `cost` fits within the limits, without adding it.
+    pub fn would_fit(&self, cost: &TransactionCost) -> Result<(), CostTrackerError> {
+        let sum = cost.sum();
+        if self.block_cost.saturating_add(sum) > self.block_cost_limit {
+            return Err(CostTrackerError::WouldExceedBlockMaxLimit);
+        }
+        let exceeds_account_limit = cost
+            .writable_accounts
+            .iter()
+            .any(|pubkey| self.account_cost(pubkey).saturating_add(sum) > self.account_cost_limit);
+        if exceeds_account_limit {
+            return Err(CostTrackerError::WouldExceedAccountMaxLimit);
+        }
+        Ok(())
+    }
+
+    /// Adds `cost` if it fits within the limits, returning the new block
+    /// cost.
+    pub fn try_add(&mut self, cost: &TransactionCost) -> Result<u64, CostTrackerError> {
+        self.would_fit(cost)?;
+        self.add(cost, cost.sum());
+        self.transaction_count += 1;
+        Ok(self.block_cost)
+    }
+
+    /// Replaces the requested compute units of an added `cost` with the
+    /// `actual` ones the transaction consumed, freeing what it did not use.
+    pub fn update_execution_cost(&mut self, cost: &TransactionCost, actual: u64) {
+        let estimated = cost.programs_execution_cost;
+        if actual > estimated {
+            self.add(cost, actual - estimated);
+        } else {
+            self.remove(cost, estimated - actual);
+        }
+    }
+
+    /// Takes an added `cost` back out, as when its transaction was dropped
+    /// from the block after all.
+    pub fn remove_transaction_cost(&mut self, cost: &TransactionCost) {
+        self.remove(cost, cost.sum());
+        self.transaction_count = self.transaction_count.saturating_sub(1);
+    }
+
+    /// Starts tracking a new block with the same limits.
+    pub fn reset(&mut self) {
+        self.block_cost = 0;
+        self.cost_by_writable_accounts.clear();
+        self.transaction_count = 0;
+    }
+
+    fn add(&mut self, cost: &TransactionCost, units: u64) {
+        self.block_cost = self.block_cost.saturating_add(units);
+        for pubkey in &cost.writable_accounts {
+            let account_cost = self.cost_by_writable_accounts.entry(*pubkey).or_default();
+            *account_cost = account_cost.saturating_add(units);
+        }
+    }
+
+    fn remove(&mut self, cost: &TransactionCost, units: u64) {
+        self.block_cost = self.block_cost.saturating_sub(units);
+        for pubkey in &cost.writable_accounts {
+            if let Some(account_cost) = self.cost_by_writable_accounts.get_mut(pubkey) {
+                *account_cost = account_cost.saturating_sub(units);
+            }
+        }
+    }
+}
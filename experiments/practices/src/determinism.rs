@@ -0,0 +1,53 @@
+//! Reproducible randomness for experiment runs.
+//!
+//! Every randomized component takes a [`Determinism`]: the random
+//! tie-breaks of a [`PriorityGraphScheduler`](crate::scheduler::PriorityGraphScheduler)
+//! and the execution jitter of a [`WorkerPool`](crate::scheduler::WorkerPool).
+//! With [`Determinism::Seeded`] each of them draws from its own stream of
+//! one seed, and a seeded worker pool also completes work in a fixed order
+//! rather than in the order its threads happen to finish, so a whole
+//! multi-threaded run repeats exactly. [`Workload`](crate::workload::Workload)
+//! and [`GenesisBuilder`](crate::genesis::GenesisBuilder) are always
+//! seeded; [`Determinism::seed`] gives them the seed of a run.
+
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Random stream of a scheduler's tie-breaks.
+pub const TIE_BREAK_STREAM: u64 = 1;
+/// Random stream of a worker pool's execution jitter.
+pub const JITTER_STREAM: u64 = 2;
+
+/// Whether randomized components repeat from run to run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Determinism {
+    /// Randomness comes from the operating system, so runs differ.
+    #[default]
+    Unseeded,
+    /// Randomness comes from this seed, so runs with the same seed repeat.
+    Seeded(u64),
+}
+
+impl Determinism {
+    pub fn is_seeded(&self) -> bool {
+        matches!(self, Self::Seeded(_))
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        match *self {
+            Self::Unseeded => None,
+            Self::Seeded(seed) => Some(seed),
+        }
+    }
+
+    /// A generator for the random stream `stream`; the streams of one seed
+    /// are independent of each other.
+    pub fn rng(&self, stream: u64) -> StdRng {
+        match *self {
+            Self::Unseeded => StdRng::from_entropy(),
+            Self::Seeded(seed) => {
+                // Spreads nearby streams over the whole seed space.
+                StdRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            }
+        }
+    }
+}
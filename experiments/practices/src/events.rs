@@ -0,0 +1,100 @@
+//! A single stream of what happens to transactions.
+//!
+//! Banks and schedulers publish typed [`Event`]s to an [`EventBus`] as
+//! they schedule, execute, commit and drop transactions. Every subscriber
+//! receives every event published after it subscribed, in publication
+//! order, so metrics, plugins and tests all see the same history without
+//! scraping logs. The bus is cheap to clone and every clone publishes to
+//! the same subscribers, so a bank, its children and the schedulers
+//! feeding it can share one stream.
+
+use {
+    crate::{scheduler, status_cache::TransactionStatus},
+    crossbeam_channel::{unbounded, Receiver, Sender},
+    solana_clock::Slot,
+    solana_signature::Signature,
+    solana_transaction_error::TransactionError,
+    std::sync::{Arc, Mutex},
+};
+
+/// Why a transaction left without being committed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DropReason {
+    /// The bank turned it away before executing it.
+    Rejected(TransactionError),
+    /// A scheduler gave up on it because its deadline passed.
+    Expired,
+    /// A scheduler handed it to another node.
+    Forwarded,
+    /// Another transaction of its bundle failed, so none committed.
+    BundleFailed,
+    /// It arrived while the queue in front of the scheduler was full.
+    QueueFull,
+}
+
+impl From<scheduler::DropReason> for DropReason {
+    fn from(reason: scheduler::DropReason) -> Self {
+        match reason {
+            scheduler::DropReason::Expired => Self::Expired,
+            scheduler::DropReason::Forwarded => Self::Forwarded,
+        }
+    }
+}
+
+/// Something that happened to a transaction or batch.
+///
+/// The events of a batch follow its [`BatchStarted`](Self::BatchStarted).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A transaction was put into a batch.
+    TransactionScheduled { slot: Slot, signature: Signature },
+    /// A batch of `num_transactions` transactions started executing.
+    BatchStarted { slot: Slot, num_transactions: usize },
+    /// A transaction executed and was committed with `status`; a failed
+    /// one only committed its fee.
+    TransactionCommitted {
+        slot: Slot,
+        signature: Signature,
+        status: TransactionStatus,
+        fee: u64,
+        consumed_units: u64,
+    },
+    /// A transaction left without being committed.
+    TransactionDropped {
+        slot: Slot,
+        signature: Signature,
+        reason: DropReason,
+    },
+}
+
+/// Broadcasts [`Event`]s to every subscriber.
+#[derive(Clone, Debug, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a receiver of every event published from now on.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = unbounded();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Sends `event` to every subscriber. Subscribers that hung up are
+    /// dropped.
+    pub fn publish(&self, event: Event) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn num_subscribers(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+}
@@ -0,0 +1,200 @@
+//! Runtime features and the sets of them a bank runs with.
+//!
+//! Behavior changes of the runtime are rolled out as features, each one
+//! identified by the address of its on-chain feature account. A
+//! [`FeatureSet`] records which features are active and since which slot;
+//! the parts of this crate whose behavior one of the features below
+//! changes consult the feature set of the executor or bank they run in.
+//!
+//! [`FeatureSet::all_enabled`] is what executors and banks start with.
+//! [`FeatureSet::mainnet_beta_as_of`] approximates the features active on
+//! mainnet-beta at a slot, so experiments can replay both sides of an
+//! activation.
+
+use {
+    crate::precompiles::PrecompileConfig,
+    solana_clock::{Epoch, Slot, DEFAULT_SLOTS_PER_EPOCH},
+    solana_epoch_schedule::EpochSchedule,
+    solana_pubkey::Pubkey,
+    std::collections::{HashMap, HashSet},
+};
+
+pub mod blake3_syscall_enabled {
+    solana_pubkey::declare_id!("HTW2pSyErTj4BV6KBM9NZ9VBUJVxt7sacNWcf76wtzb3");
+}
+
+pub mod curve25519_syscall_enabled {
+    solana_pubkey::declare_id!("7rcw5UtqgDTBBv2EcynNfYckgdAaH1MAsCjKgXMkN7Ri");
+}
+
+pub mod enable_alt_bn128_syscall {
+    solana_pubkey::declare_id!("A16q37opZdQMCbe5qJ6xpBB9usykfv8jZaMkxvZQi4GJ");
+}
+
+pub mod delay_visibility_of_program_deployment {
+    solana_pubkey::declare_id!("GmuBvtFb2aHfSfMXpuFeWZGHyDeCLPS79s48fmCWCfM5");
+}
+
+pub mod ed25519_precompile_verify_strict {
+    solana_pubkey::declare_id!("ed9tNscbWLYBooxWA7FE2B5KHWs8A6sxfY8EzezEcoo");
+}
+
+pub mod enable_secp256r1_precompile {
+    solana_pubkey::declare_id!("srremy31J5Y25FrAApwVb9kZcfXbusYMMsvTK9aWv5q");
+}
+
+pub mod reserve_minimal_cus_for_builtin_instructions {
+    solana_pubkey::declare_id!("C9oAhLxDBm3ssWtJx1yBGzPY55r2rArHmN1pbQn6HogH");
+}
+
+pub mod reduce_stake_warmup_cooldown {
+    solana_pubkey::declare_id!("GwtDQBghCTBgmX2cpEGNPxTEBUTQRaDMGTr5qychdGMj");
+}
+
+/// Every feature this crate knows, with a description of what it changes.
+pub static FEATURE_NAMES: &[(Pubkey, &str)] = &[
+    (
+        blake3_syscall_enabled::ID,
+        "register the sol_blake3 syscall",
+    ),
+    (
+        curve25519_syscall_enabled::ID,
+        "register the curve25519 syscalls",
+    ),
+    (
+        enable_alt_bn128_syscall::ID,
+        "register the sol_alt_bn128_group_op syscall",
+    ),
+    (
+        delay_visibility_of_program_deployment::ID,
+        "make upgradeable programs visible only from the slot after their deployment",
+    ),
+    (
+        ed25519_precompile_verify_strict::ID,
+        "verify ed25519 precompile signatures with the strict rules",
+    ),
+    (
+        enable_secp256r1_precompile::ID,
+        "enable the secp256r1 precompile and charge for its signatures",
+    ),
+    (
+        reserve_minimal_cus_for_builtin_instructions::ID,
+        "grant builtin instructions 3,000 CUs instead of 200,000 by default",
+    ),
+    (
+        reduce_stake_warmup_cooldown::ID,
+        "warm up and cool down stake by 9% of the cluster's stake per epoch instead of 25%",
+    ),
+];
+
+/// Approximate mainnet-beta activation epochs of the features above;
+/// `None` for features not active there. Use `solana feature status` for
+/// the exact slots when a replay depends on them.
+pub static MAINNET_BETA_ACTIVATION_EPOCHS: &[(Pubkey, Option<u64>)] = &[
+    (blake3_syscall_enabled::ID, None),
+    (curve25519_syscall_enabled::ID, Some(607)),
+    (enable_alt_bn128_syscall::ID, Some(591)),
+    (delay_visibility_of_program_deployment::ID, Some(479)),
+    (ed25519_precompile_verify_strict::ID, Some(706)),
+    (enable_secp256r1_precompile::ID, Some(801)),
+    (reserve_minimal_cus_for_builtin_instructions::ID, Some(790)),
+    (reduce_stake_warmup_cooldown::ID, Some(576)),
+];
+
+/// The features a bank runs with: active ones with the slot they activated
+/// in, and the known ones that are not active.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeatureSet {
+    active: HashMap<Pubkey, Slot>,
+    inactive: HashSet<Pubkey>,
+}
+
+impl Default for FeatureSet {
+    /// Every feature inactive.
+    fn default() -> Self {
+        Self {
+            active: HashMap::new(),
+            inactive: FEATURE_NAMES.iter().map(|(id, _)| *id).collect(),
+        }
+    }
+}
+
+impl FeatureSet {
+    /// Every feature active since slot 0.
+    pub fn all_enabled() -> Self {
+        Self {
+            active: FEATURE_NAMES.iter().map(|(id, _)| (*id, 0)).collect(),
+            inactive: HashSet::new(),
+        }
+    }
+
+    /// The features active on mainnet-beta at `slot`, going by
+    /// [`MAINNET_BETA_ACTIVATION_EPOCHS`]. Features activate at the first
+    /// slot of their epoch.
+    pub fn mainnet_beta_as_of(slot: Slot) -> Self {
+        let mut feature_set = Self::default();
+        for (id, epoch) in MAINNET_BETA_ACTIVATION_EPOCHS {
+            let Some(activation_slot) = epoch.map(|epoch| epoch * DEFAULT_SLOTS_PER_EPOCH) else {
+                continue;
+            };
+            if activation_slot <= slot {
+                feature_set.activate(id, activation_slot);
+            }
+        }
+        feature_set
+    }
+
+    /// This set with `feature_id` active since `slot`.
+    pub fn with_feature(mut self, feature_id: &Pubkey, slot: Slot) -> Self {
+        self.activate(feature_id, slot);
+        self
+    }
+
+    /// This set with `feature_id` inactive.
+    pub fn without_feature(mut self, feature_id: &Pubkey) -> Self {
+        self.deactivate(feature_id);
+        self
+    }
+
+    pub fn active(&self) -> &HashMap<Pubkey, Slot> {
+        &self.active
+    }
+
+    pub fn inactive(&self) -> &HashSet<Pubkey> {
+        &self.inactive
+    }
+
+    pub fn is_active(&self, feature_id: &Pubkey) -> bool {
+        self.active.contains_key(feature_id)
+    }
+
+    /// The slot `feature_id` activated in, if it is active.
+    pub fn activated_slot(&self, feature_id: &Pubkey) -> Option<Slot> {
+        self.active.get(feature_id).copied()
+    }
+
+    pub fn activate(&mut self, feature_id: &Pubkey, slot: Slot) {
+        self.inactive.remove(feature_id);
+        self.active.insert(*feature_id, slot);
+    }
+
+    pub fn deactivate(&mut self, feature_id: &Pubkey) {
+        self.active.remove(feature_id);
+        self.inactive.insert(*feature_id);
+    }
+
+    /// The epoch from which stake warms up and cools down at the reduced
+    /// rate of `reduce_stake_warmup_cooldown`, if the feature is active.
+    pub fn new_warmup_cooldown_rate_epoch(&self, epoch_schedule: &EpochSchedule) -> Option<Epoch> {
+        self.activated_slot(&reduce_stake_warmup_cooldown::id())
+            .map(|slot| epoch_schedule.get_epoch(slot))
+    }
+
+    /// The precompile rules this set selects.
+    pub fn precompile_config(&self) -> PrecompileConfig {
+        PrecompileConfig {
+            ed25519_verify_strict: self.is_active(&ed25519_precompile_verify_strict::id()),
+            enable_secp256r1: self.is_active(&enable_secp256r1_precompile::id()),
+        }
+    }
+}
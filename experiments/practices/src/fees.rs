@@ -0,0 +1,161 @@
+//! Fees a transaction pays before it executes.
+//!
+//! The base fee comes from the bank's [`FeeStructure`]; the priority fee
+//! comes from the transaction's compute budget. A bank with a
+//! [`LocalFeeMarket`](crate::local_fee_market::LocalFeeMarket) also
+//! charges for the contended accounts a transaction writes.
+
+use {
+    crate::{
+        compute_budget::{
+            process_compute_budget_instructions, process_compute_budget_instructions_with_features,
+            ComputeBudgetLimits,
+        },
+        feature_set::{enable_secp256r1_precompile, FeatureSet},
+    },
+    solana_transaction::sanitized::SanitizedTransaction,
+};
+
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// Fee charged for every signature a transaction carries.
+pub const DEFAULT_LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// The fee a transaction pays, split into its parts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeDetails {
+    /// Base fee for the signatures and write locks of the transaction.
+    pub transaction_fee: u64,
+    /// Priority fee bid through ComputeBudget instructions.
+    pub prioritization_fee: u64,
+    /// Fee a local fee market charged for the accounts the transaction
+    /// writes; [`FeeStructure`] alone never charges one.
+    pub local_fee: u64,
+}
+
+impl FeeDetails {
+    pub fn total_fee(&self) -> u64 {
+        self.transaction_fee
+            .saturating_add(self.prioritization_fee)
+            .saturating_add(self.local_fee)
+    }
+}
+
+/// Fee policy of a bank: what each signature and write lock costs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeStructure {
+    pub lamports_per_signature: u64,
+    pub lamports_per_write_lock: u64,
+}
+
+impl Default for FeeStructure {
+    fn default() -> Self {
+        Self::new(DEFAULT_LAMPORTS_PER_SIGNATURE, 0)
+    }
+}
+
+impl FeeStructure {
+    pub fn new(lamports_per_signature: u64, lamports_per_write_lock: u64) -> Self {
+        Self {
+            lamports_per_signature,
+            lamports_per_write_lock,
+        }
+    }
+
+    /// Calculates the fee `transaction` pays, with every feature active.
+    ///
+    /// Signatures verified by precompile instructions count towards the
+    /// base fee like the transaction's own signatures.
+    pub fn calculate_fee_details(&self, transaction: &SanitizedTransaction) -> FeeDetails {
+        self.calculate_fee_details_impl(
+            transaction,
+            transaction.message().num_total_signatures(),
+            PriorityFeeCalculator::new().calculate_prioritization_fee(transaction),
+        )
+    }
+
+    /// Calculates the fee `transaction` pays under the rules of
+    /// `feature_set`.
+    ///
+    /// Signatures of secp256r1 instructions are only charged for once
+    /// `enable_secp256r1_precompile` is active, and the priority fee is
+    /// bid on the compute unit limit `feature_set` yields.
+    pub fn calculate_fee_details_with_features(
+        &self,
+        transaction: &SanitizedTransaction,
+        feature_set: &FeatureSet,
+    ) -> FeeDetails {
+        let signature_details = transaction.message().get_signature_details();
+        let mut num_signatures = signature_details.total_signatures();
+        if !feature_set.is_active(&enable_secp256r1_precompile::id()) {
+            num_signatures = num_signatures
+                .saturating_sub(signature_details.num_secp256r1_instruction_signatures());
+        }
+        let prioritization_fee =
+            process_compute_budget_instructions_with_features(transaction.message(), feature_set)
+                .map(|limits| PriorityFeeCalculator::prioritization_fee(&limits))
+                .unwrap_or_default();
+        self.calculate_fee_details_impl(transaction, num_signatures, prioritization_fee)
+    }
+
+    fn calculate_fee_details_impl(
+        &self,
+        transaction: &SanitizedTransaction,
+        num_signatures: u64,
+        prioritization_fee: u64,
+    ) -> FeeDetails {
+        let message = transaction.message();
+        let signature_fee = num_signatures.saturating_mul(self.lamports_per_signature);
+        let write_lock_fee = message
+            .num_write_locks()
+            .saturating_mul(self.lamports_per_write_lock);
+        FeeDetails {
+            transaction_fee: signature_fee.saturating_add(write_lock_fee),
+            prioritization_fee,
+            local_fee: 0,
+        }
+    }
+}
+
+/// Calculates priority fees from ComputeBudget instructions.
+///
+/// A transaction bids `compute_unit_price` micro-lamports for each of the
+/// `compute_unit_limit` units it requests; the product of the two is what
+/// the scheduler uses as its priority.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PriorityFeeCalculator;
+
+impl PriorityFeeCalculator {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Returns the scheduling priority of `transaction`: its priority fee in
+    /// micro-lamports.
+    ///
+    /// Transactions with malformed compute budget instructions get the
+    /// lowest priority; they fail once executed anyway.
+    pub fn calculate_priority(&self, transaction: &SanitizedTransaction) -> u64 {
+        process_compute_budget_instructions(transaction.message())
+            .map(|limits| Self::priority_fee_micro_lamports(&limits))
+            .unwrap_or_default()
+    }
+
+    /// Returns the priority fee in lamports charged for `transaction`.
+    pub fn calculate_prioritization_fee(&self, transaction: &SanitizedTransaction) -> u64 {
+        process_compute_budget_instructions(transaction.message())
+            .map(|limits| Self::prioritization_fee(&limits))
+            .unwrap_or_default()
+    }
+
+    /// Priority fee in lamports, rounded up to the next whole lamport.
+    pub fn prioritization_fee(limits: &ComputeBudgetLimits) -> u64 {
+        Self::priority_fee_micro_lamports(limits).div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+    }
+
+    fn priority_fee_micro_lamports(limits: &ComputeBudgetLimits) -> u64 {
+        limits
+            .compute_unit_price
+            .saturating_mul(u64::from(limits.compute_unit_limit))
+    }
+}
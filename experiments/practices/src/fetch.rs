@@ -0,0 +1,323 @@
+//! Downloads blocks from a cluster into replay fixtures.
+//!
+//! [`BlockFetcher::fetch_to_dir`] asks an RPC endpoint for a block with
+//! `getBlock` and for every account its transactions reference with
+//! `getMultipleAccounts`, and writes both into a fixture directory
+//! [`BlockFixture::load_dir`] reads without a network: the fixture as
+//! [`FIXTURE_FILE`], and the block as RPC returned it as [`BLOCK_FILE`],
+//! which [`load_transactions`](crate::fixtures::load_transactions) reads
+//! too.
+//!
+//! RPC only serves accounts as they are now, not as they were before the
+//! block. Every account gets the balance the block recorded before the
+//! first transaction that referenced it, so balances match the chain, but
+//! data and owners are whatever they are at the time of the fetch. Accounts
+//! the block created are left out, and those closed since keep their
+//! balance alone. Upgradeable programs come with their programdata
+//! accounts, and address lookup tables with the tables themselves.
+
+use {
+    crate::{
+        bpf_loader_upgradeable::UpgradeableLoaderState,
+        replay::{BlockFixture, ReplayError, FIXTURE_FILE},
+        sanitize::deserialize_transaction,
+    },
+    base64::{prelude::BASE64_STANDARD, Engine},
+    serde_json::{json, Value},
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    solana_transaction::versioned::VersionedTransaction,
+    std::{
+        collections::HashMap,
+        fs, io,
+        path::Path,
+        str::FromStr,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+    thiserror::Error,
+};
+
+/// Name of the block, as `getBlock` returned it, in a fixture directory.
+pub const BLOCK_FILE: &str = "block.json";
+
+/// Most accounts a single `getMultipleAccounts` request may ask for.
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// Why a block could not be fetched.
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("RPC request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("RPC response has a missing or malformed `{0}`")]
+    InvalidResponse(String),
+    #[error("transaction {0} of the block is not a valid transaction")]
+    InvalidTransaction(usize),
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+    #[error("fixture I/O failed: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Fetches blocks and the accounts they touch from a JSON-RPC endpoint.
+pub struct BlockFetcher {
+    client: reqwest::Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+impl BlockFetcher {
+    /// A fetcher asking the RPC endpoint at `url`, e.g.
+    /// `https://api.mainnet-beta.solana.com`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Downloads the block at `slot` and the accounts it references into
+    /// the fixture directory `dir`, creating it if needed, and returns the
+    /// fixture written.
+    pub async fn fetch_to_dir(
+        &self,
+        slot: Slot,
+        dir: impl AsRef<Path>,
+    ) -> Result<BlockFixture, FetchError> {
+        let block = self.get_block(slot).await?;
+        let transactions = block
+            .get("transactions")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("transactions"))?;
+
+        let referenced = referenced_accounts(transactions)?;
+        let pubkeys: Vec<Pubkey> = referenced.iter().map(|(pubkey, _)| *pubkey).collect();
+        let mut accounts: Vec<_> = referenced
+            .into_iter()
+            .zip(self.get_multiple_accounts(&pubkeys).await?)
+            .collect();
+        let mut programdata_addresses: Vec<Pubkey> = accounts
+            .iter()
+            .filter_map(|(_, account)| programdata_address(account.as_ref()?))
+            .filter(|address| !pubkeys.contains(address))
+            .collect();
+        programdata_addresses.sort_unstable();
+        programdata_addresses.dedup();
+        let programdata = self.get_multiple_accounts(&programdata_addresses).await?;
+        accounts.extend(
+            programdata_addresses
+                .into_iter()
+                .map(|address| (address, None))
+                .zip(programdata),
+        );
+
+        let accounts: Vec<Value> = accounts
+            .into_iter()
+            .filter_map(|((pubkey, pre_balance), account)| {
+                let account = match (account, pre_balance) {
+                    // Created by the block, or never created at all.
+                    (_, Some(0)) | (None, None) => return None,
+                    (Some(mut account), Some(lamports)) => {
+                        account["lamports"] = json!(lamports);
+                        account
+                    }
+                    (Some(account), None) => account,
+                    // Closed since the block.
+                    (None, Some(lamports)) => json!({
+                        "lamports": lamports,
+                        "data": ["", "base64"],
+                        "owner": solana_sdk_ids::system_program::id().to_string(),
+                        "executable": false,
+                        "rentEpoch": u64::MAX,
+                    }),
+                };
+                Some(json!({ "pubkey": pubkey.to_string(), "account": account }))
+            })
+            .collect();
+        let fixture_json = json!({
+            "slot": slot,
+            "accounts": accounts,
+            "transactions": transactions,
+        });
+        let fixture = BlockFixture::from_json(&fixture_json.to_string())?;
+
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(BLOCK_FILE), to_pretty_json(&block))?;
+        fs::write(dir.join(FIXTURE_FILE), to_pretty_json(&fixture_json))?;
+        Ok(fixture)
+    }
+
+    /// The block at `slot`, with full transaction details in the `base64`
+    /// encoding.
+    async fn get_block(&self, slot: Slot) -> Result<Value, FetchError> {
+        let config = json!({
+            "encoding": "base64",
+            "transactionDetails": "full",
+            "rewards": false,
+            "maxSupportedTransactionVersion": 0,
+        });
+        self.call("getBlock", json!([slot, config])).await
+    }
+
+    /// The accounts at `pubkeys` in the `base64` encoding, `None` for those
+    /// that do not exist.
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Value>>, FetchError> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+            let chunk: Vec<String> = chunk.iter().map(ToString::to_string).collect();
+            let result = self
+                .call(
+                    "getMultipleAccounts",
+                    json!([chunk, { "encoding": "base64" }]),
+                )
+                .await?;
+            let values = result
+                .get("value")
+                .and_then(Value::as_array)
+                .filter(|values| values.len() == chunk.len())
+                .ok_or_else(|| invalid("value"))?;
+            accounts.extend(
+                values
+                    .iter()
+                    .map(|account| (!account.is_null()).then(|| account.clone())),
+            );
+        }
+        Ok(accounts)
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, FetchError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        let mut response: Value = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            return Err(FetchError::Rpc {
+                code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+        }
+        response
+            .get_mut("result")
+            .map(Value::take)
+            .ok_or_else(|| invalid("result"))
+    }
+}
+
+/// Every account the transactions of a block reference, looked up
+/// addresses and the lookup tables included, in the order they are first
+/// referenced, with the balance recorded before the first transaction
+/// whose balances list it.
+fn referenced_accounts(transactions: &[Value]) -> Result<Vec<(Pubkey, Option<u64>)>, FetchError> {
+    let mut accounts: Vec<(Pubkey, Option<u64>)> = Vec::new();
+    let mut indexes: HashMap<Pubkey, usize> = HashMap::new();
+    let mut reference = |pubkey: Pubkey, pre_balance: Option<u64>| {
+        let index = *indexes.entry(pubkey).or_insert_with(|| {
+            accounts.push((pubkey, None));
+            accounts.len() - 1
+        });
+        let recorded = &mut accounts[index].1;
+        if recorded.is_none() {
+            *recorded = pre_balance;
+        }
+    };
+
+    for (index, entry) in transactions.iter().enumerate() {
+        let transaction = decode_transaction(entry).ok_or(FetchError::InvalidTransaction(index))?;
+
+        let meta = entry.get("meta");
+        // A key dropped here would shift every later key against its
+        // pre-balance, so a malformed one fails the fetch.
+        let loaded = |kind: &str| {
+            meta.and_then(|meta| meta.get("loadedAddresses")?.get(kind)?.as_array())
+                .map_or(Ok(Vec::new()), |keys| {
+                    keys.iter()
+                        .map(parse_pubkey)
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| invalid("loadedAddresses"))
+                })
+        };
+        let pre_balances = meta
+            .and_then(|meta| meta.get("preBalances")?.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let account_keys = transaction
+            .message
+            .static_account_keys()
+            .iter()
+            .copied()
+            .chain(loaded("writable")?)
+            .chain(loaded("readonly")?);
+        for (position, pubkey) in account_keys.enumerate() {
+            reference(pubkey, pre_balances.get(position).and_then(Value::as_u64));
+        }
+        for lookup in transaction
+            .message
+            .address_table_lookups()
+            .unwrap_or_default()
+        {
+            reference(lookup.account_key, None);
+        }
+    }
+    Ok(accounts)
+}
+
+/// The transaction of a `getBlock` entry in the `base64` encoding.
+fn decode_transaction(entry: &Value) -> Option<VersionedTransaction> {
+    match entry.get("transaction")?.as_array()?.as_slice() {
+        [Value::String(data), encoding] if encoding == "base64" => {
+            deserialize_transaction(&BASE64_STANDARD.decode(data).ok()?).ok()
+        }
+        _ => None,
+    }
+}
+
+/// The programdata account of an upgradeable program, from its account as
+/// `getMultipleAccounts` reports it.
+fn programdata_address(account: &Value) -> Option<Pubkey> {
+    let owner = parse_pubkey(account.get("owner")?)?;
+    if owner != solana_sdk_ids::bpf_loader_upgradeable::id() {
+        return None;
+    }
+    let data = BASE64_STANDARD
+        .decode(account.get("data")?.get(0)?.as_str()?)
+        .ok()?;
+    match UpgradeableLoaderState::deserialize(&data).ok()? {
+        UpgradeableLoaderState::Program {
+            programdata_address,
+        } => Some(programdata_address),
+        _ => None,
+    }
+}
+
+fn parse_pubkey(value: &Value) -> Option<Pubkey> {
+    Pubkey::from_str(value.as_str()?).ok()
+}
+
+fn to_pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).expect("a JSON value always serializes")
+}
+
+fn invalid(name: &str) -> FetchError {
+    FetchError::InvalidResponse(name.to_string())
+}
@@ -0,0 +1,304 @@
+//! Transactions captured from a cluster, loaded from files.
+//!
+//! [`load_transactions`] reads a file of transactions and sanitizes each,
+//! so captured mainnet traffic can be handed straight to a scheduler. The
+//! file is either JSON in the shapes RPC returns or raw wire transactions,
+//! base64 encoded, one per line.
+//!
+//! JSON fixtures hold a `getBlock` result, its `transactions` array, a
+//! single `getTransaction` result or a whole JSON-RPC response around
+//! either. Every transaction in them may come in the `json` encoding, as a
+//! `[data, "base64"]` or `[data, "base58"]` pair, or as a bare base64
+//! string:
+//!
+//! ```json
+//! [
+//!   {
+//!     "transaction": {
+//!       "signatures": ["..."],
+//!       "message": {
+//!         "header": {
+//!           "numRequiredSignatures": 1,
+//!           "numReadonlySignedAccounts": 0,
+//!           "numReadonlyUnsignedAccounts": 1
+//!         },
+//!         "accountKeys": ["...", "...", "11111111111111111111111111111111"],
+//!         "recentBlockhash": "...",
+//!         "instructions": [{ "programIdIndex": 2, "accounts": [0, 1], "data": "..." }],
+//!         "addressTableLookups": []
+//!       }
+//!     },
+//!     "meta": { "loadedAddresses": { "writable": [], "readonly": [] } },
+//!     "version": 0
+//!   },
+//!   ["...", "base64"]
+//! ]
+//! ```
+//!
+//! Address table lookups are resolved with the `loadedAddresses` of the
+//! transaction's `meta`, since the tables themselves are not part of the
+//! fixture; a transaction that looks addresses up without them fails to
+//! load. Signatures are not verified.
+
+use {
+    crate::sanitize::{deserialize_transaction, sanitize_transaction, SanitizeError},
+    base64::{prelude::BASE64_STANDARD, Engine},
+    serde_json::Value,
+    solana_hash::Hash,
+    solana_message::{
+        compiled_instruction::CompiledInstruction,
+        legacy,
+        v0::{self, LoadedAddresses, MessageAddressTableLookup},
+        MessageHeader, SimpleAddressLoader, VersionedMessage,
+    },
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    solana_transaction::{sanitized::SanitizedTransaction, versioned::VersionedTransaction},
+    std::{fs, io, path::Path, str::FromStr},
+    thiserror::Error,
+};
+
+/// Why a fixture could not be loaded.
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    #[error("fixture I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("fixture is not valid JSON")]
+    InvalidJson,
+    #[error("transaction {index} has a missing or malformed field `{field}`")]
+    InvalidField { index: usize, field: String },
+    #[error("transaction {index} is not a valid transaction: {source}")]
+    InvalidTransaction { index: usize, source: SanitizeError },
+}
+
+/// Reads the transactions of the fixture at `path`, in file order.
+pub fn load_transactions(
+    path: impl AsRef<Path>,
+) -> Result<Vec<SanitizedTransaction>, FixtureError> {
+    parse_transactions(&fs::read_to_string(path)?)
+}
+
+/// Reads the transactions of a fixture in one of the formats of the
+/// [module](self) docs, told apart by their first character.
+pub fn parse_transactions(contents: &str) -> Result<Vec<SanitizedTransaction>, FixtureError> {
+    let contents = contents.trim_start();
+    if !contents.starts_with(['[', '{']) {
+        return contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                let bytes = BASE64_STANDARD
+                    .decode(line)
+                    .map_err(|_| invalid(index, "transaction"))?;
+                load(index, deserialize_transaction(&bytes), None)
+            })
+            .collect();
+    }
+
+    let mut fixture: Value =
+        serde_json::from_str(contents).map_err(|_| FixtureError::InvalidJson)?;
+    if let Some(result) = fixture.get_mut("result") {
+        fixture = result.take();
+    }
+    let entries = match fixture {
+        Value::Array(entries) => entries,
+        Value::Object(mut block) if block.contains_key("transactions") => {
+            match block.remove("transactions") {
+                Some(Value::Array(entries)) => entries,
+                _ => return Err(invalid(0, "transactions")),
+            }
+        }
+        entry => vec![entry],
+    };
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| parse_entry(index, entry))
+        .collect()
+}
+
+/// An entry of a JSON fixture: a `getTransaction` result or a transaction
+/// in one of its encodings.
+fn parse_entry(index: usize, entry: &Value) -> Result<SanitizedTransaction, FixtureError> {
+    let Some(transaction) = entry.get("transaction") else {
+        return load(index, parse_encoded(entry, None), None);
+    };
+    let loaded_addresses = entry
+        .get("meta")
+        .and_then(|meta| meta.get("loadedAddresses"))
+        .map(|loaded| parse_loaded_addresses(index, loaded))
+        .transpose()?;
+    let version = entry.get("version");
+    load(index, parse_encoded(transaction, version), loaded_addresses)
+}
+
+fn parse_encoded(
+    transaction: &Value,
+    version: Option<&Value>,
+) -> Result<VersionedTransaction, SanitizeError> {
+    let bytes = match transaction {
+        Value::String(data) => BASE64_STANDARD.decode(data).ok(),
+        Value::Array(pair) => match pair.as_slice() {
+            [Value::String(data), encoding] if encoding == "base64" => {
+                BASE64_STANDARD.decode(data).ok()
+            }
+            [Value::String(data), encoding] if encoding == "base58" => {
+                bs58::decode(data).into_vec().ok()
+            }
+            _ => None,
+        },
+        Value::Object(_) => return parse_json_transaction(transaction, version),
+        _ => None,
+    };
+    deserialize_transaction(&bytes.ok_or(SanitizeError::InvalidEncoding)?)
+}
+
+/// A transaction in the `json` encoding. It has a version 0 message if
+/// `version` says so or the message has address table lookups.
+fn parse_json_transaction(
+    transaction: &Value,
+    version: Option<&Value>,
+) -> Result<VersionedTransaction, SanitizeError> {
+    let encoding = || SanitizeError::InvalidEncoding;
+    let message = transaction.get("message").ok_or_else(encoding)?;
+    let signatures = strings(transaction.get("signatures"))
+        .ok_or_else(encoding)?
+        .into_iter()
+        .map(|signature| Signature::from_str(signature).map_err(|_| encoding()))
+        .collect::<Result<_, _>>()?;
+
+    let header = message.get("header").ok_or_else(encoding)?;
+    let count = |name: &str| {
+        header
+            .get(name)
+            .and_then(Value::as_u64)
+            .and_then(|count| u8::try_from(count).ok())
+            .ok_or_else(encoding)
+    };
+    let header = MessageHeader {
+        num_required_signatures: count("numRequiredSignatures")?,
+        num_readonly_signed_accounts: count("numReadonlySignedAccounts")?,
+        num_readonly_unsigned_accounts: count("numReadonlyUnsignedAccounts")?,
+    };
+    let account_keys = pubkeys(message.get("accountKeys")).ok_or_else(encoding)?;
+    let recent_blockhash = message
+        .get("recentBlockhash")
+        .and_then(Value::as_str)
+        .and_then(|hash| Hash::from_str(hash).ok())
+        .ok_or_else(encoding)?;
+    let instructions = message
+        .get("instructions")
+        .and_then(Value::as_array)
+        .ok_or_else(encoding)?
+        .iter()
+        .map(|instruction| {
+            Some(CompiledInstruction {
+                program_id_index: u8::try_from(instruction.get("programIdIndex")?.as_u64()?)
+                    .ok()?,
+                accounts: indexes(instruction.get("accounts"))?,
+                data: bs58::decode(instruction.get("data")?.as_str()?)
+                    .into_vec()
+                    .ok()?,
+            })
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(encoding)?;
+
+    let lookups = message
+        .get("addressTableLookups")
+        .filter(|lookups| !lookups.is_null());
+    let message = if lookups.is_some() || version.is_some_and(|version| version == 0) {
+        let address_table_lookups = match lookups {
+            Some(lookups) => lookups
+                .as_array()
+                .ok_or_else(encoding)?
+                .iter()
+                .map(|lookup| {
+                    Some(MessageAddressTableLookup {
+                        account_key: Pubkey::from_str(lookup.get("accountKey")?.as_str()?).ok()?,
+                        writable_indexes: indexes(lookup.get("writableIndexes"))?,
+                        readonly_indexes: indexes(lookup.get("readonlyIndexes"))?,
+                    })
+                })
+                .collect::<Option<_>>()
+                .ok_or_else(encoding)?,
+            None => Vec::new(),
+        };
+        VersionedMessage::V0(v0::Message {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups,
+        })
+    } else {
+        VersionedMessage::Legacy(legacy::Message {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+        })
+    };
+    Ok(VersionedTransaction {
+        signatures,
+        message,
+    })
+}
+
+fn parse_loaded_addresses(index: usize, loaded: &Value) -> Result<LoadedAddresses, FixtureError> {
+    Ok(LoadedAddresses {
+        writable: pubkeys(loaded.get("writable"))
+            .ok_or_else(|| invalid(index, "loadedAddresses.writable"))?,
+        readonly: pubkeys(loaded.get("readonly"))
+            .ok_or_else(|| invalid(index, "loadedAddresses.readonly"))?,
+    })
+}
+
+/// Sanitizes a decoded transaction, resolving its address table lookups
+/// with `loaded_addresses`.
+fn load(
+    index: usize,
+    transaction: Result<VersionedTransaction, SanitizeError>,
+    loaded_addresses: Option<LoadedAddresses>,
+) -> Result<SanitizedTransaction, FixtureError> {
+    let invalid_transaction = |source| FixtureError::InvalidTransaction { index, source };
+    let transaction = transaction.map_err(invalid_transaction)?;
+    let looks_up = transaction
+        .message
+        .address_table_lookups()
+        .is_some_and(|lookups| !lookups.is_empty());
+    let address_loader = match loaded_addresses {
+        Some(loaded_addresses) => SimpleAddressLoader::Enabled(loaded_addresses),
+        None if looks_up => SimpleAddressLoader::Disabled,
+        None => SimpleAddressLoader::Enabled(LoadedAddresses::default()),
+    };
+    sanitize_transaction(transaction, address_loader).map_err(invalid_transaction)
+}
+
+fn invalid(index: usize, field: &str) -> FixtureError {
+    FixtureError::InvalidField {
+        index,
+        field: field.to_string(),
+    }
+}
+
+fn strings(value: Option<&Value>) -> Option<Vec<&str>> {
+    value?.as_array()?.iter().map(Value::as_str).collect()
+}
+
+fn pubkeys(value: Option<&Value>) -> Option<Vec<Pubkey>> {
+    strings(value)?
+        .into_iter()
+        .map(|pubkey| Pubkey::from_str(pubkey).ok())
+        .collect()
+}
+
+fn indexes(value: Option<&Value>) -> Option<Vec<u8>> {
+    value?
+        .as_array()?
+        .iter()
+        .map(|index| u8::try_from(index.as_u64()?).ok())
+        .collect()
+}
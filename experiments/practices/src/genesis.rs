@@ -0,0 +1,259 @@
+//! Genesis configuration.
+//!
+//! [`GenesisBuilder`] collects what a bank at slot 0 starts with: funded
+//! accounts and keypairs, programs, builtins, sysvar parameters, the
+//! inflation schedule and the features in effect. [`GenesisBuilder::build`] stores every account
+//! before the bank computes its first accounts hash, so the seeded state is
+//! part of genesis rather than a first slot's writes.
+//!
+//! Keypairs are derived from the builder's seed, so the same builder always
+//! produces the same accounts and bank hashes.
+
+use {
+    crate::{
+        accounts_db::AccountsDb,
+        bank::Bank,
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        feature_set::FeatureSet,
+        fees::FeeStructure,
+        svm::{BuiltinFunction, InvokeContext},
+    },
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    solana_account::{AccountSharedData, WritableAccount},
+    solana_clock::Slot,
+    solana_epoch_schedule::EpochSchedule,
+    solana_inflation::Inflation,
+    solana_instruction_error::InstructionError,
+    solana_keypair::Keypair,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::{bpf_loader, system_program},
+    solana_signer::Signer,
+    std::{collections::BTreeMap, fs, io, path::Path, sync::Arc},
+};
+
+/// Accounts, programs and parameters of a bank at slot 0.
+pub struct GenesisBuilder {
+    accounts: BTreeMap<Pubkey, AccountSharedData>,
+    keypairs: Vec<Keypair>,
+    builtins: Vec<(Pubkey, BuiltinFunction)>,
+    rent: Rent,
+    epoch_schedule: EpochSchedule,
+    inflation: Inflation,
+    fee_structure: FeeStructure,
+    feature_set: FeatureSet,
+    rng: StdRng,
+}
+
+impl Default for GenesisBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GenesisBuilder {
+    /// A genesis without accounts, with default sysvars and fees and every
+    /// feature active, like [`Bank::default`].
+    pub fn new() -> Self {
+        Self {
+            accounts: BTreeMap::new(),
+            keypairs: Vec::new(),
+            builtins: Vec::new(),
+            rent: Rent::default(),
+            epoch_schedule: EpochSchedule::default(),
+            inflation: Inflation::default(),
+            fee_structure: FeeStructure::default(),
+            feature_set: FeatureSet::all_enabled(),
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+
+    /// Seeds the keypairs [`with_funded_keypairs`](Self::with_funded_keypairs)
+    /// creates from now on.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Stores `account` at `pubkey`, replacing any account added before.
+    pub fn with_account(mut self, pubkey: Pubkey, account: AccountSharedData) -> Self {
+        self.accounts.insert(pubkey, account);
+        self
+    }
+
+    /// Stores every account of `accounts`, such as a workload's payers.
+    pub fn with_accounts(
+        mut self,
+        accounts: impl IntoIterator<Item = (Pubkey, AccountSharedData)>,
+    ) -> Self {
+        self.accounts.extend(accounts);
+        self
+    }
+
+    /// Stores a System account holding `lamports` at `pubkey`.
+    pub fn with_funded_account(self, pubkey: Pubkey, lamports: u64) -> Self {
+        self.with_account(
+            pubkey,
+            AccountSharedData::new(lamports, 0, &system_program::id()),
+        )
+    }
+
+    /// Creates `count` keypairs, each with a System account holding
+    /// `lamports`. They are listed by [`keypairs`](Self::keypairs).
+    pub fn with_funded_keypairs(mut self, count: usize, lamports: u64) -> Self {
+        for _ in 0..count {
+            let keypair = Keypair::new_from_array(self.rng.gen());
+            self = self.with_funded_account(keypair.pubkey(), lamports);
+            self.keypairs.push(keypair);
+        }
+        self
+    }
+
+    /// Deploys `elf` at `program_id` with the BPF loader.
+    pub fn with_program(self, program_id: Pubkey, elf: &[u8]) -> Self {
+        let account = self.rent_exempt_account(elf, &bpf_loader::id(), true);
+        self.with_account(program_id, account)
+    }
+
+    /// Deploys the ELF at `path` at `program_id` with the BPF loader.
+    pub fn with_program_file(self, program_id: Pubkey, path: impl AsRef<Path>) -> io::Result<Self> {
+        let elf = fs::read(path)?;
+        Ok(self.with_program(program_id, &elf))
+    }
+
+    /// Deploys `elf` at `program_id` with the upgradeable loader, as if it
+    /// had been deployed in slot 0 by `upgrade_authority`, or made
+    /// immutable without one.
+    ///
+    /// Like any program deployed in a slot, it becomes invocable in the
+    /// next one.
+    pub fn with_upgradeable_program(
+        self,
+        program_id: Pubkey,
+        upgrade_authority: Option<Pubkey>,
+        elf: &[u8],
+    ) -> Self {
+        let programdata_address = bpf_loader_upgradeable::get_program_data_address(&program_id);
+        let program = UpgradeableLoaderState::Program {
+            programdata_address,
+        };
+        let program = self.rent_exempt_account(
+            &program.serialize(),
+            &solana_sdk_ids::bpf_loader_upgradeable::id(),
+            true,
+        );
+
+        let metadata = UpgradeableLoaderState::ProgramData {
+            slot: Slot::default(),
+            upgrade_authority_address: upgrade_authority,
+        }
+        .serialize();
+        let mut data = vec![0; UpgradeableLoaderState::size_of_programdata(elf.len())];
+        data[..metadata.len()].copy_from_slice(&metadata);
+        data[UpgradeableLoaderState::size_of_programdata_metadata()..].copy_from_slice(elf);
+        let programdata =
+            self.rent_exempt_account(&data, &solana_sdk_ids::bpf_loader_upgradeable::id(), false);
+
+        self.with_account(program_id, program)
+            .with_account(programdata_address, programdata)
+    }
+
+    /// Registers a builtin the bank can invoke, such as
+    /// [`token_program::process_instruction`](crate::token_program::process_instruction).
+    pub fn with_builtin<F>(mut self, program_id: Pubkey, entrypoint: F) -> Self
+    where
+        F: Fn(&mut InvokeContext) -> Result<(), InstructionError> + Send + Sync + 'static,
+    {
+        self.builtins.push((program_id, Arc::new(entrypoint)));
+        self
+    }
+
+    /// Sets the Rent sysvar. Accounts stored by the builder afterwards are
+    /// funded to be exempt under it.
+    pub fn with_rent(mut self, rent: Rent) -> Self {
+        self.rent = rent;
+        self
+    }
+
+    /// Sets the EpochSchedule sysvar.
+    pub fn with_epoch_schedule(mut self, epoch_schedule: EpochSchedule) -> Self {
+        self.epoch_schedule = epoch_schedule;
+        self
+    }
+
+    /// Sets the inflation schedule epoch rewards are calculated with.
+    pub fn with_inflation(mut self, inflation: Inflation) -> Self {
+        self.inflation = inflation;
+        self
+    }
+
+    /// Sets the fees transactions pay, and with them the fee rate of the
+    /// RecentBlockhashes sysvar.
+    pub fn with_fee_structure(mut self, fee_structure: FeeStructure) -> Self {
+        self.fee_structure = fee_structure;
+        self
+    }
+
+    /// Replaces the features in effect.
+    pub fn with_feature_set(mut self, feature_set: FeatureSet) -> Self {
+        self.feature_set = feature_set;
+        self
+    }
+
+    /// Activates `feature_id` from `slot` on.
+    pub fn with_feature(mut self, feature_id: &Pubkey, slot: Slot) -> Self {
+        self.feature_set.activate(feature_id, slot);
+        self
+    }
+
+    /// Deactivates `feature_id`.
+    pub fn without_feature(mut self, feature_id: &Pubkey) -> Self {
+        self.feature_set.deactivate(feature_id);
+        self
+    }
+
+    /// The keypairs created by
+    /// [`with_funded_keypairs`](Self::with_funded_keypairs), in creation
+    /// order.
+    pub fn keypairs(&self) -> &[Keypair] {
+        &self.keypairs
+    }
+
+    pub fn accounts(&self) -> &BTreeMap<Pubkey, AccountSharedData> {
+        &self.accounts
+    }
+
+    /// Creates the bank for slot 0. Every call returns an identical bank,
+    /// so the same genesis can back several experiments.
+    pub fn build(&self) -> Bank {
+        let mut accounts_db = AccountsDb::default();
+        for (pubkey, account) in &self.accounts {
+            accounts_db.store_account(*pubkey, account.clone());
+        }
+        let mut bank = Bank::new(accounts_db);
+        bank.set_rent(self.rent.clone());
+        bank.set_epoch_schedule(self.epoch_schedule.clone());
+        bank.set_inflation(self.inflation);
+        bank.set_fee_structure(self.fee_structure.clone());
+        bank.set_feature_set(self.feature_set.clone());
+        for (program_id, entrypoint) in &self.builtins {
+            let entrypoint = Arc::clone(entrypoint);
+            bank.add_builtin(*program_id, move |invoke_context: &mut InvokeContext| {
+                entrypoint(invoke_context)
+            });
+        }
+        bank
+    }
+
+    fn rent_exempt_account(
+        &self,
+        data: &[u8],
+        owner: &Pubkey,
+        executable: bool,
+    ) -> AccountSharedData {
+        let mut account = AccountSharedData::new(self.rent.minimum_balance(data.len()), 0, owner);
+        account.set_data_from_slice(data);
+        account.set_executable(executable);
+        account
+    }
+}
@@ -0,0 +1,547 @@
+//! Scheduling and execution experiments described in TOML.
+//!
+//! An [`ExperimentConfig`] says what to run: the [`Workload`] to generate,
+//! which streaming scheduler packs it, how many workers execute it, how
+//! long a slot lasts, how many slots the experiment gets and the
+//! [`BatchLimits`] of every batch. [`run_experiment`] pushes the workload
+//! through a [`WorkerPool`] slot by slot, under a [`SlotClock`], and sums
+//! up what every slot got done in an [`ExperimentReport`]. The `svm-lab`
+//! binary does both from the command line:
+//!
+//! ```toml
+//! name = "hot accounts"
+//! slots = 4
+//!
+//! [workload]
+//! num_transactions = 10000
+//! num_payers = 1000
+//! hot_account_ratio = 0.2
+//! num_hot_accounts = 4
+//! priority_distribution = { kind = "zipf", levels = 100, exponent = 1.1, cu_price_step = 10 }
+//!
+//! [scheduler]
+//! kind = "prio_graph"
+//! num_workers = 4
+//! slot_duration_ms = 400
+//!
+//! [limits]
+//! max_txs = 64
+//! max_cus = 12000000
+//! ```
+//!
+//! Every key is optional and falls back to the defaults of
+//! [`WorkloadConfig`], [`BatchLimits`] and [`ExperimentConfig`]; unknown
+//! keys are rejected so a typo does not silently run the default.
+
+use {
+    crate::{
+        accounts_storage::ShardedStorage,
+        bank::Bank,
+        clock::{SlotClock, DEFAULT_SLOT_DURATION},
+        pipeline::{ExecutedBatch, PipelineAccounts},
+        scheduler::{
+            BatchLimits, FifoScheduler, MultiIteratorScheduler, PriorityGraphScheduler,
+            TransactionScheduler, WorkerPool, WorkerPoolError,
+        },
+        svm::{TransactionExecutionResult, TransactionExecutor},
+        workload::{PriorityDistribution, Workload, WorkloadConfig},
+    },
+    crossbeam_channel::{bounded, RecvTimeoutError},
+    serde_json::{json, Value},
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        fmt::Write,
+        fs, io,
+        path::Path,
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    },
+    thiserror::Error,
+    toml::{Table, Value as TomlValue},
+};
+
+/// Lamports every payer of an experiment starts with.
+pub const PAYER_LAMPORTS: u64 = 1_000_000_000_000;
+
+/// Why an experiment could not be loaded or run.
+#[derive(Debug, Error)]
+pub enum ExperimentError {
+    #[error("experiment I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("experiment is not valid TOML: {0}")]
+    InvalidToml(#[from] toml::de::Error),
+    #[error("unknown key `{0}`")]
+    UnknownKey(String),
+    #[error("`{0}` has an invalid value")]
+    InvalidValue(String),
+    #[error("report file `{0}` is neither .json nor .csv")]
+    UnsupportedFormat(String),
+    #[error(transparent)]
+    WorkerPool(#[from] WorkerPoolError),
+}
+
+/// The streaming scheduler an experiment packs its batches with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchedulerKind {
+    #[default]
+    PriorityGraph,
+    MultiIterator,
+    Fifo,
+}
+
+impl SchedulerKind {
+    /// The name the scheduler goes by in experiment files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PriorityGraph => "prio_graph",
+            Self::MultiIterator => "multi_iterator",
+            Self::Fifo => "fifo",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [Self::PriorityGraph, Self::MultiIterator, Self::Fifo]
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+}
+
+/// What an experiment runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExperimentConfig {
+    pub name: String,
+    pub workload: WorkloadConfig,
+    pub scheduler: SchedulerKind,
+    pub num_workers: usize,
+    pub slot_duration: Duration,
+    /// Slots the experiment gets before the transactions left are counted
+    /// as not executed.
+    pub num_slots: u64,
+    pub batch_limits: BatchLimits,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            name: "experiment".to_string(),
+            workload: WorkloadConfig::default(),
+            scheduler: SchedulerKind::default(),
+            num_workers: 4,
+            slot_duration: DEFAULT_SLOT_DURATION,
+            num_slots: 1,
+            batch_limits: BatchLimits::default(),
+        }
+    }
+}
+
+impl ExperimentConfig {
+    /// The default experiment with what `toml` lists overridden.
+    pub fn from_toml(toml: &str) -> Result<Self, ExperimentError> {
+        let mut table: Table = toml.parse()?;
+        let mut config = Self::default();
+        if let Some(name) = take(&mut table, "", "name", |value| {
+            value.as_str().map(str::to_string)
+        })? {
+            config.name = name;
+        }
+        if let Some(num_slots) = take_u64(&mut table, "", "slots")? {
+            config.num_slots = num_slots;
+        }
+        if let Some(mut workload) = take_table(&mut table, "workload")? {
+            config.workload = parse_workload(&mut workload)?;
+        }
+        if let Some(mut scheduler) = take_table(&mut table, "scheduler")? {
+            if let Some(kind) = take(&mut scheduler, "scheduler.", "kind", |value| {
+                value.as_str().and_then(SchedulerKind::from_name)
+            })? {
+                config.scheduler = kind;
+            }
+            if let Some(num_workers) = take_u64(&mut scheduler, "scheduler.", "num_workers")? {
+                config.num_workers = num_workers as usize;
+            }
+            if let Some(millis) = take_u64(&mut scheduler, "scheduler.", "slot_duration_ms")? {
+                config.slot_duration = Duration::from_millis(millis);
+            }
+            reject_rest(&scheduler, "scheduler.")?;
+        }
+        if let Some(mut limits) = take_table(&mut table, "limits")? {
+            let batch_limits = &mut config.batch_limits;
+            if let Some(max_txs) = take_u64(&mut limits, "limits.", "max_txs")? {
+                batch_limits.max_txs = max_txs as usize;
+            }
+            if let Some(max_cus) = take_u64(&mut limits, "limits.", "max_cus")? {
+                batch_limits.max_cus = max_cus;
+            }
+            if let Some(size) = take_u64(&mut limits, "limits.", "max_account_data_size")? {
+                batch_limits.max_account_data_size = size;
+            }
+            reject_rest(&limits, "limits.")?;
+        }
+        reject_rest(&table, "")?;
+
+        let invalid = |key: &str| Err(ExperimentError::InvalidValue(key.to_string()));
+        if config.num_workers == 0 {
+            return invalid("scheduler.num_workers");
+        }
+        if config.slot_duration.is_zero() {
+            return invalid("scheduler.slot_duration_ms");
+        }
+        if config.num_slots == 0 {
+            return invalid("slots");
+        }
+        Ok(config)
+    }
+
+    /// Reads an experiment from a TOML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ExperimentError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+}
+
+fn parse_workload(table: &mut Table) -> Result<WorkloadConfig, ExperimentError> {
+    const PREFIX: &str = "workload.";
+    let mut workload = WorkloadConfig::default();
+    if let Some(value) = take_u64(table, PREFIX, "num_transactions")? {
+        workload.num_transactions = value as usize;
+    }
+    if let Some(value) = take_u64(table, PREFIX, "num_payers")? {
+        workload.num_payers = value as usize;
+    }
+    if let Some(value) = take_f64(table, PREFIX, "hot_account_ratio")? {
+        workload.hot_account_ratio = value;
+    }
+    if let Some(value) = take_u64(table, PREFIX, "num_hot_accounts")? {
+        workload.num_hot_accounts = value as usize;
+    }
+    if let Some(value) = take_u64(table, PREFIX, "transfers_per_transaction")? {
+        workload.transfers_per_transaction = value as usize;
+    }
+    if let Some(value) = take_u64(table, PREFIX, "seed")? {
+        workload.seed = value;
+    }
+    if let Some(mut distribution) = take(table, PREFIX, "priority_distribution", |value| {
+        value.as_table().cloned()
+    })? {
+        workload.priority_distribution = parse_priority_distribution(&mut distribution)?;
+    }
+    reject_rest(table, PREFIX)?;
+
+    // What `Workload::generate` would panic on.
+    if workload.num_payers == 0 {
+        return Err(ExperimentError::InvalidValue(format!("{PREFIX}num_payers")));
+    }
+    if !(0.0..=1.0).contains(&workload.hot_account_ratio)
+        || (workload.hot_account_ratio > 0.0 && workload.num_hot_accounts == 0)
+    {
+        return Err(ExperimentError::InvalidValue(format!(
+            "{PREFIX}hot_account_ratio"
+        )));
+    }
+    Ok(workload)
+}
+
+fn parse_priority_distribution(table: &mut Table) -> Result<PriorityDistribution, ExperimentError> {
+    const PREFIX: &str = "workload.priority_distribution.";
+    let kind = take(table, PREFIX, "kind", |value| {
+        value.as_str().map(str::to_string)
+    })?;
+    let distribution = match kind.as_deref() {
+        Some("uniform") => PriorityDistribution::Uniform {
+            min_cu_price: required(take_u64(table, PREFIX, "min_cu_price")?, "min_cu_price")?,
+            max_cu_price: required(take_u64(table, PREFIX, "max_cu_price")?, "max_cu_price")?,
+        },
+        Some("zipf") => PriorityDistribution::Zipf {
+            levels: required(take_u64(table, PREFIX, "levels")?, "levels")?,
+            exponent: required(take_f64(table, PREFIX, "exponent")?, "exponent")?,
+            cu_price_step: required(take_u64(table, PREFIX, "cu_price_step")?, "cu_price_step")?,
+        },
+        _ => return Err(ExperimentError::InvalidValue(format!("{PREFIX}kind"))),
+    };
+    reject_rest(table, PREFIX)?;
+    Ok(distribution)
+}
+
+/// A value of a priority distribution that has to be there.
+fn required<T>(value: Option<T>, key: &str) -> Result<T, ExperimentError> {
+    value.ok_or_else(|| {
+        ExperimentError::InvalidValue(format!("workload.priority_distribution.{key}"))
+    })
+}
+
+/// Removes `key` from `table`, converted by `convert`.
+fn take<T>(
+    table: &mut Table,
+    prefix: &str,
+    key: &str,
+    convert: impl FnOnce(&TomlValue) -> Option<T>,
+) -> Result<Option<T>, ExperimentError> {
+    let Some(value) = table.remove(key) else {
+        return Ok(None);
+    };
+    convert(&value)
+        .map(Some)
+        .ok_or_else(|| ExperimentError::InvalidValue(format!("{prefix}{key}")))
+}
+
+fn take_u64(table: &mut Table, prefix: &str, key: &str) -> Result<Option<u64>, ExperimentError> {
+    take(table, prefix, key, |value| {
+        value
+            .as_integer()
+            .and_then(|value| u64::try_from(value).ok())
+    })
+}
+
+/// Integers are taken for floats, so `ratio = 1` works.
+fn take_f64(table: &mut Table, prefix: &str, key: &str) -> Result<Option<f64>, ExperimentError> {
+    take(table, prefix, key, |value| {
+        value
+            .as_float()
+            .or_else(|| value.as_integer().map(|value| value as f64))
+    })
+}
+
+fn take_table(table: &mut Table, key: &str) -> Result<Option<Table>, ExperimentError> {
+    take(table, "", key, |value| value.as_table().cloned())
+}
+
+fn reject_rest(table: &Table, prefix: &str) -> Result<(), ExperimentError> {
+    match table.keys().next() {
+        Some(key) => Err(ExperimentError::UnknownKey(format!("{prefix}{key}"))),
+        None => Ok(()),
+    }
+}
+
+/// What an experiment got done in one slot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SlotReport {
+    /// Slots since the experiment started.
+    pub slot: u64,
+    pub transactions_executed: usize,
+    pub transactions_succeeded: usize,
+    pub consumed_units: u64,
+    /// Transactions still waiting once the slot ended.
+    pub transactions_pending: usize,
+    pub elapsed: Duration,
+}
+
+/// The results of an experiment, slot by slot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExperimentReport {
+    pub config: ExperimentConfig,
+    pub slots: Vec<SlotReport>,
+    pub elapsed: Duration,
+}
+
+impl ExperimentReport {
+    pub fn transactions_executed(&self) -> usize {
+        self.slots
+            .iter()
+            .map(|slot| slot.transactions_executed)
+            .sum()
+    }
+
+    pub fn transactions_succeeded(&self) -> usize {
+        self.slots
+            .iter()
+            .map(|slot| slot.transactions_succeeded)
+            .sum()
+    }
+
+    /// Transactions the experiment ran out of slots for.
+    pub fn transactions_not_executed(&self) -> usize {
+        self.config.workload.num_transactions - self.transactions_executed()
+    }
+
+    /// Executed transactions per second of the whole run; 0 if it took no
+    /// time.
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.transactions_executed() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let config = &self.config;
+        json!({
+            "name": config.name,
+            "scheduler": config.scheduler.name(),
+            "numWorkers": config.num_workers,
+            "slotDurationMs": config.slot_duration.as_millis() as u64,
+            "numTransactions": config.workload.num_transactions,
+            "transactionsExecuted": self.transactions_executed(),
+            "transactionsSucceeded": self.transactions_succeeded(),
+            "transactionsNotExecuted": self.transactions_not_executed(),
+            "elapsedMs": self.elapsed.as_secs_f64() * 1_000.0,
+            "throughput": self.throughput(),
+            "slots": self
+                .slots
+                .iter()
+                .map(|slot| json!({
+                    "slot": slot.slot,
+                    "transactionsExecuted": slot.transactions_executed,
+                    "transactionsSucceeded": slot.transactions_succeeded,
+                    "consumedUnits": slot.consumed_units,
+                    "transactionsPending": slot.transactions_pending,
+                    "elapsedMs": slot.elapsed.as_secs_f64() * 1_000.0,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// The slots as CSV, with a header line and a line per slot.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(concat!(
+            "slot,transactions_executed,transactions_succeeded,consumed_units,",
+            "transactions_pending,elapsed_ms\n",
+        ));
+        for slot in &self.slots {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{:.3}",
+                slot.slot,
+                slot.transactions_executed,
+                slot.transactions_succeeded,
+                slot.consumed_units,
+                slot.transactions_pending,
+                slot.elapsed.as_secs_f64() * 1_000.0
+            )
+            .expect("writing to a String cannot fail");
+        }
+        csv
+    }
+
+    /// Writes the report to a `.json` or `.csv` file.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), ExperimentError> {
+        let path = path.as_ref();
+        let contents = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::to_string_pretty(&self.to_json())
+                .expect("a JSON value always serializes"),
+            Some("csv") => self.to_csv(),
+            _ => {
+                return Err(ExperimentError::UnsupportedFormat(
+                    path.display().to_string(),
+                ))
+            }
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Runs the experiment `config` describes.
+///
+/// The workload's payers are funded with [`PAYER_LAMPORTS`] in a storage
+/// of their own, which the workers execute against and commit to as soon
+/// as a transaction is done; the scheduler keeps the accounts of a
+/// transaction locked until then. Every slot gets a fresh scheduler with
+/// the transactions the slots before it did not get to. The slot clock
+/// moves a bank of its own along, which only keeps the time.
+pub fn run_experiment(config: &ExperimentConfig) -> Result<ExperimentReport, ExperimentError> {
+    match config.scheduler {
+        SchedulerKind::PriorityGraph => run_with(config, PriorityGraphScheduler::new),
+        SchedulerKind::MultiIterator => run_with(config, MultiIteratorScheduler::new),
+        SchedulerKind::Fifo => run_with(config, FifoScheduler::new),
+    }
+}
+
+fn run_with<S: TransactionScheduler>(
+    config: &ExperimentConfig,
+    new_scheduler: impl Fn() -> S,
+) -> Result<ExperimentReport, ExperimentError> {
+    let workload = Workload::generate(&config.workload);
+    let storage = ShardedStorage::default();
+    storage.commit(workload.payer_accounts(PAYER_LAMPORTS));
+    let accounts = Arc::new(PipelineAccounts::new(storage));
+    let executor = Arc::new(TransactionExecutor::new());
+    let pool = WorkerPool::new(config.num_workers, {
+        let accounts = Arc::clone(&accounts);
+        move |transaction: &SanitizedTransaction| {
+            execute_and_commit(&executor, &accounts, transaction)
+        }
+    })
+    .with_batch_limits(config.batch_limits);
+
+    let start = Instant::now();
+    let mut clock = SlotClock::new(0, config.slot_duration, start);
+    let slot_events = clock.subscribe();
+    let (stop_sender, stop_receiver) = bounded::<()>(0);
+    let driver = thread::Builder::new()
+        .name("svmLabClock".to_string())
+        .spawn(move || {
+            let mut bank = Bank::default();
+            loop {
+                let now = Instant::now();
+                match stop_receiver.recv_timeout(clock.time_remaining(now)) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        clock.tick(&mut bank, Instant::now());
+                    }
+                    _ => break,
+                }
+            }
+        })?;
+
+    let mut pending = workload.transactions;
+    let mut slots = Vec::new();
+    let mut result: Result<(), ExperimentError> = Ok(());
+    for slot in 0..config.num_slots {
+        if pending.is_empty() {
+            break;
+        }
+        let slot_start = Instant::now();
+        let mut scheduler = new_scheduler();
+        let outputs = match pool.run_in_slot(&mut scheduler, pending.clone(), &slot_events) {
+            Ok(outputs) => outputs,
+            Err(err) => {
+                result = Err(err.into());
+                break;
+            }
+        };
+        let mut report = SlotReport {
+            slot,
+            elapsed: slot_start.elapsed(),
+            ..SlotReport::default()
+        };
+        let mut left = Vec::new();
+        for (transaction, output) in pending.into_iter().zip(outputs) {
+            match output {
+                Some(executed) => {
+                    report.transactions_executed += 1;
+                    report.transactions_succeeded += usize::from(executed.was_successful());
+                    report.consumed_units += executed.consumed_units;
+                }
+                None => left.push(transaction),
+            }
+        }
+        report.transactions_pending = left.len();
+        slots.push(report);
+        pending = left;
+    }
+    let elapsed = start.elapsed();
+    drop(stop_sender);
+    let _ = driver.join();
+    result?;
+
+    Ok(ExperimentReport {
+        config: config.clone(),
+        slots,
+        elapsed,
+    })
+}
+
+/// Executes `transaction` against `accounts` and stores what it wrote if
+/// it succeeded.
+fn execute_and_commit(
+    executor: &TransactionExecutor,
+    accounts: &PipelineAccounts,
+    transaction: &SanitizedTransaction,
+) -> TransactionExecutionResult {
+    let result = executor.load_and_execute_transaction(accounts.storage(), transaction);
+    let mut batch = ExecutedBatch {
+        transactions: vec![transaction.clone()],
+        results: vec![result],
+    };
+    accounts.commit_batch(&batch);
+    batch.results.pop().expect("the batch holds the result")
+}
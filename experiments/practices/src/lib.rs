@@ -0,0 +1,49 @@
+//! Building blocks for experimenting with the Solana Virtual Machine (SVM).
+//!
+//! The modules here promote the ideas explored in the experiment notes
+//! (priority graphs, account locks, scheduling) into reusable code that
+//! operates on real Solana transaction types.
+
+pub mod accounts;
+pub mod accounts_db;
+pub mod accounts_hash;
+pub mod accounts_storage;
+pub mod accounts_wal;
+pub mod address_lookup_table;
+pub mod bank;
+pub mod blockstore;
+pub mod bpf_loader_upgradeable;
+pub mod builtin_error;
+pub mod clock;
+pub mod compute_budget;
+pub mod cost_model;
+pub mod determinism;
+pub mod events;
+pub mod feature_set;
+pub mod fees;
+#[cfg(feature = "fetch")]
+pub mod fetch;
+pub mod fixtures;
+pub mod genesis;
+pub mod lab;
+pub mod local_fee_market;
+pub mod metrics;
+pub mod pipeline;
+pub mod plugin;
+pub mod precompiles;
+pub mod rent_collector;
+pub mod replay;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod sanitize;
+pub mod scheduler;
+pub mod sigverify;
+pub mod spans;
+pub mod stake_program;
+pub mod status_cache;
+pub mod svm;
+pub mod system_program;
+pub mod token_program;
+pub mod transaction_status;
+pub mod vote_program;
+pub mod workload;
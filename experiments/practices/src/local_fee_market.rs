@@ -0,0 +1,114 @@
+//! Local fee markets: fees for writing to contended accounts.
+//!
+//! The cluster charges one price for block space, so a hot account bid up
+//! by everyone who wants to write it raises priority fees for everyone.
+//! A local fee market prices each account on its own instead. A
+//! [`LocalFeeMarket`] set on a [`Bank`](crate::bank::Bank) is asked for
+//! the fee of every account a transaction write-locks, given how often
+//! transactions of the same slot wrote to it already, and the fee is
+//! charged with the rest of the transaction's fee as
+//! [`FeeDetails::local_fee`](crate::fees::FeeDetails::local_fee). The
+//! bank counts that demand in [`WriteDemand`] as transactions are charged,
+//! and starts from zero every slot.
+
+use {
+    solana_pubkey::Pubkey, solana_transaction::sanitized::SanitizedTransaction,
+    std::collections::HashMap,
+};
+
+/// Prices write locks on individual accounts.
+pub trait LocalFeeMarket: Send + Sync {
+    /// Lamports a transaction pays for write-locking `account` after
+    /// `demand` earlier transactions of the slot did.
+    fn write_lock_fee(&self, account: &Pubkey, demand: u64) -> u64;
+
+    /// What `transaction` pays for all the accounts it write-locks, at the
+    /// demand in `write_demand`.
+    fn local_fee(&self, transaction: &SanitizedTransaction, write_demand: &WriteDemand) -> u64 {
+        writable_keys(transaction)
+            .map(|key| self.write_lock_fee(key, write_demand.demand(key)))
+            .fold(0, u64::saturating_add)
+    }
+}
+
+/// Charges nothing up to `target_demand` writes per slot and doubles a fee
+/// that starts at `base_fee` with every write beyond.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExponentialFeeMarket {
+    pub base_fee: u64,
+    pub target_demand: u64,
+}
+
+impl ExponentialFeeMarket {
+    pub fn new(base_fee: u64, target_demand: u64) -> Self {
+        Self {
+            base_fee,
+            target_demand,
+        }
+    }
+}
+
+impl LocalFeeMarket for ExponentialFeeMarket {
+    fn write_lock_fee(&self, _account: &Pubkey, demand: u64) -> u64 {
+        match demand.checked_sub(self.target_demand) {
+            Some(excess) => self
+                .base_fee
+                .saturating_mul(1u64.checked_shl(excess as u32).unwrap_or(u64::MAX)),
+            None => 0,
+        }
+    }
+}
+
+/// How many transactions write-locked each account so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteDemand {
+    demand: HashMap<Pubkey, u64>,
+}
+
+impl WriteDemand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn demand(&self, account: &Pubkey) -> u64 {
+        self.demand.get(account).copied().unwrap_or_default()
+    }
+
+    /// Counts a write to every account `transaction` write-locks.
+    pub fn record(&mut self, transaction: &SanitizedTransaction) {
+        for key in writable_keys(transaction) {
+            *self.demand.entry(*key).or_default() += 1;
+        }
+    }
+
+    /// Takes back what [`record`](Self::record) counted for `transaction`.
+    pub fn remove(&mut self, transaction: &SanitizedTransaction) {
+        for key in writable_keys(transaction) {
+            if let Some(demand) = self.demand.get_mut(key) {
+                *demand -= 1;
+                if *demand == 0 {
+                    self.demand.remove(key);
+                }
+            }
+        }
+    }
+
+    /// The accounts written to at all, with their demand.
+    pub fn iter(&self) -> impl Iterator<Item = (&Pubkey, u64)> + '_ {
+        self.demand.iter().map(|(key, demand)| (key, *demand))
+    }
+
+    pub fn clear(&mut self) {
+        self.demand.clear();
+    }
+}
+
+fn writable_keys(transaction: &SanitizedTransaction) -> impl Iterator<Item = &Pubkey> + '_ {
+    let message = transaction.message();
+    message
+        .account_keys()
+        .iter()
+        .enumerate()
+        .filter(move |(index, _)| message.is_writable(*index))
+        .map(|(_, key)| key)
+}
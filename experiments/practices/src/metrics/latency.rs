@@ -0,0 +1,232 @@
+use {
+    solana_clock::Slot,
+    solana_signature::Signature,
+    std::{
+        collections::{BTreeMap, HashMap},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// A point on a transaction's way through the pipeline at which its
+/// [`LatencyTracker`] takes the time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LatencyStage {
+    /// The transaction was pushed into the pipeline.
+    Ingest,
+    /// The scheduler placed the transaction in a batch.
+    Schedule,
+    /// The batch of the transaction started executing.
+    ExecuteStart,
+    /// What the transaction wrote was stored.
+    Commit,
+}
+
+/// When a transaction reached each [`LatencyStage`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransactionTimestamps {
+    pub ingest: Option<Instant>,
+    pub schedule: Option<Instant>,
+    pub execute_start: Option<Instant>,
+    pub commit: Option<Instant>,
+}
+
+impl TransactionTimestamps {
+    fn set(&mut self, stage: LatencyStage, now: Instant) {
+        let timestamp = match stage {
+            LatencyStage::Ingest => &mut self.ingest,
+            LatencyStage::Schedule => &mut self.schedule,
+            LatencyStage::ExecuteStart => &mut self.execute_start,
+            LatencyStage::Commit => &mut self.commit,
+        };
+        *timestamp = Some(now);
+    }
+
+    /// Time from ingest to schedule.
+    pub fn queued(&self) -> Option<Duration> {
+        between(self.ingest, self.schedule)
+    }
+
+    /// Time from schedule to the start of execution.
+    pub fn dispatched(&self) -> Option<Duration> {
+        between(self.schedule, self.execute_start)
+    }
+
+    /// Time from the start of execution to commit.
+    pub fn executed(&self) -> Option<Duration> {
+        between(self.execute_start, self.commit)
+    }
+
+    /// Time from ingest to commit.
+    pub fn total(&self) -> Option<Duration> {
+        between(self.ingest, self.commit)
+    }
+}
+
+fn between(start: Option<Instant>, end: Option<Instant>) -> Option<Duration> {
+    Some(end?.saturating_duration_since(start?))
+}
+
+/// Percentiles of a set of latencies, by nearest rank.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub samples: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyPercentiles {
+    /// Percentiles of `latencies`, all zero if there are none.
+    pub fn from_latencies(mut latencies: Vec<Duration>) -> Self {
+        latencies.sort_unstable();
+        let Some(&max) = latencies.last() else {
+            return Self::default();
+        };
+        let percentile = |percent: usize| {
+            let rank = (latencies.len() * percent).div_ceil(100);
+            latencies[rank.saturating_sub(1)]
+        };
+        Self {
+            samples: latencies.len(),
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max,
+        }
+    }
+}
+
+/// Latencies of the transactions committed in a slot.
+///
+/// Every phase only counts the transactions whose timestamps at both of
+/// its ends were taken.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SlotLatencySummary {
+    pub slot: Slot,
+    /// Transactions committed in the slot.
+    pub transactions: usize,
+    /// From ingest to schedule.
+    pub queued: LatencyPercentiles,
+    /// From schedule to the start of execution.
+    pub dispatched: LatencyPercentiles,
+    /// From the start of execution to commit.
+    pub executed: LatencyPercentiles,
+    /// From ingest to commit.
+    pub total: LatencyPercentiles,
+}
+
+impl SlotLatencySummary {
+    fn new(slot: Slot, committed: &[TransactionTimestamps]) -> Self {
+        let percentiles = |phase: fn(&TransactionTimestamps) -> Option<Duration>| {
+            LatencyPercentiles::from_latencies(committed.iter().filter_map(phase).collect())
+        };
+        Self {
+            slot,
+            transactions: committed.len(),
+            queued: percentiles(TransactionTimestamps::queued),
+            dispatched: percentiles(TransactionTimestamps::dispatched),
+            executed: percentiles(TransactionTimestamps::executed),
+            total: percentiles(TransactionTimestamps::total),
+        }
+    }
+}
+
+/// Times every transaction at each [`LatencyStage`] and sums up the
+/// latencies of those committed, slot by slot.
+///
+/// Transactions are told apart by their first signature, so unsigned ones
+/// need distinct placeholder signatures to be timed. A transaction is
+/// in flight from the first timestamp taken of it until its commit, which
+/// files it under the slot last [set](Self::set_slot). Transactions that
+/// never commit, e.g. because they failed signature verification, stay in
+/// flight until [`clear_in_flight`](Self::clear_in_flight).
+///
+/// Callers pass the time in, so tests can use made-up instants.
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    in_flight: Mutex<HashMap<Signature, TransactionTimestamps>>,
+    committed: Mutex<BTreeMap<Slot, Vec<TransactionTimestamps>>>,
+    slot: AtomicU64,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Files the transactions committed from now on under `slot`.
+    pub fn set_slot(&self, slot: Slot) {
+        self.slot.store(slot, Ordering::Relaxed);
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.slot.load(Ordering::Relaxed)
+    }
+
+    /// Notes that the transaction signed `signature` reached `stage` at
+    /// `now`.
+    pub fn record(&self, signature: &Signature, stage: LatencyStage, now: Instant) {
+        self.record_all([signature], stage, now);
+    }
+
+    /// Notes that every transaction in `signatures` reached `stage` at
+    /// `now`, taking the lock once.
+    pub fn record_all<'a>(
+        &self,
+        signatures: impl IntoIterator<Item = &'a Signature>,
+        stage: LatencyStage,
+        now: Instant,
+    ) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if stage != LatencyStage::Commit {
+            for signature in signatures {
+                in_flight.entry(*signature).or_default().set(stage, now);
+            }
+            return;
+        }
+
+        let mut committed = self.committed.lock().unwrap();
+        let committed = committed.entry(self.slot()).or_default();
+        for signature in signatures {
+            let mut timestamps = in_flight.remove(signature).unwrap_or_default();
+            timestamps.set(stage, now);
+            committed.push(timestamps);
+        }
+    }
+
+    /// Timestamps taken so far of a transaction that has not committed.
+    pub fn in_flight(&self, signature: &Signature) -> Option<TransactionTimestamps> {
+        self.in_flight.lock().unwrap().get(signature).copied()
+    }
+
+    pub fn num_in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    /// Forgets every transaction that has not committed.
+    pub fn clear_in_flight(&self) {
+        self.in_flight.lock().unwrap().clear();
+    }
+
+    /// Latencies of the transactions committed in `slot`, if any were.
+    pub fn slot_summary(&self, slot: Slot) -> Option<SlotLatencySummary> {
+        let committed = self.committed.lock().unwrap();
+        let timestamps = committed.get(&slot)?;
+        Some(SlotLatencySummary::new(slot, timestamps))
+    }
+
+    /// Latencies of every slot transactions committed in, by slot.
+    pub fn summaries(&self) -> Vec<SlotLatencySummary> {
+        self.committed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(slot, timestamps)| SlotLatencySummary::new(*slot, timestamps))
+            .collect()
+    }
+}
@@ -0,0 +1,22 @@
+//! Measurements that span more than one part of the crate.
+//!
+//! [`SchedulerMetrics`](crate::scheduler::SchedulerMetrics) and
+//! [`PipelineMetrics`](crate::pipeline::PipelineMetrics) count what a
+//! single component did. A [`LatencyTracker`] follows every transaction
+//! from ingest to commit instead, and tells how long transactions took to
+//! get through, slot by slot, in percentiles.
+//!
+//! With the `metrics-prometheus` feature, [`PrometheusMetrics`] gathers all
+//! of them in the Prometheus text format and a [`PrometheusExporter`]
+//! serves it over HTTP, so long-running experiments can be scraped and
+//! dashboarded.
+
+mod latency;
+#[cfg(feature = "metrics-prometheus")]
+mod prometheus;
+
+pub use latency::{
+    LatencyPercentiles, LatencyStage, LatencyTracker, SlotLatencySummary, TransactionTimestamps,
+};
+#[cfg(feature = "metrics-prometheus")]
+pub use prometheus::{Histogram, PrometheusExporter, PrometheusMetrics, COMPUTE_UNIT_BUCKETS};
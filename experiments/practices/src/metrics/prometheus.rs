@@ -0,0 +1,406 @@
+use {
+    super::{LatencyPercentiles, SlotLatencySummary},
+    crate::{
+        accounts::AccountLoadMetrics,
+        accounts_db::AccountsDb,
+        scheduler::SchedulerMetrics,
+        svm::{ProgramCacheStats, TransactionExecutionResult},
+    },
+    std::{
+        fmt::Write,
+        io,
+        net::{SocketAddr, ToSocketAddrs},
+        str::FromStr,
+        sync::{Arc, Mutex},
+        thread::{self, JoinHandle},
+    },
+    tiny_http::{Header, Method, Response, Server},
+};
+
+/// Upper bounds of the buckets compute units consumed per transaction are
+/// counted in, up to the most a transaction may request.
+pub const COMPUTE_UNIT_BUCKETS: [f64; 8] = [
+    1_000.0,
+    5_000.0,
+    20_000.0,
+    50_000.0,
+    100_000.0,
+    200_000.0,
+    400_000.0,
+    1_400_000.0,
+];
+
+/// Observations counted in buckets of increasing upper bounds, the way
+/// Prometheus histograms are exposed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    /// Observations per bucket, not cumulative; the last entry counts
+    /// those above every bound.
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+impl Histogram {
+    /// # Panics
+    ///
+    /// If `bounds` are not increasing.
+    pub fn new(bounds: &[f64]) -> Self {
+        assert!(
+            bounds.windows(2).all(|pair| pair[0] < pair[1]),
+            "histogram bounds must increase"
+        );
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        let bucket = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[bucket] += 1;
+        self.sum += value;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+}
+
+/// What the executor did, as observed from its results.
+#[derive(Clone, Debug, PartialEq)]
+struct ExecutorMetrics {
+    transactions_executed: u64,
+    transactions_failed: u64,
+    compute_units: Histogram,
+}
+
+impl Default for ExecutorMetrics {
+    fn default() -> Self {
+        Self {
+            transactions_executed: 0,
+            transactions_failed: 0,
+            compute_units: Histogram::new(&COMPUTE_UNIT_BUCKETS),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    scheduler: SchedulerMetrics,
+    executor: ExecutorMetrics,
+    program_cache: ProgramCacheStats,
+    account_loads: AccountLoadMetrics,
+    /// Accounts and open snapshots of the accounts db.
+    accounts_db: Option<(usize, usize)>,
+    latency: Option<SlotLatencySummary>,
+}
+
+/// Scheduler, executor and accounts-db metrics of a running experiment,
+/// rendered in the Prometheus text format.
+///
+/// Counters that a component already keeps cumulatively, like
+/// [`SchedulerMetrics`], are set to its latest values rather than added
+/// up; the executor is observed one batch of results at a time.
+#[derive(Debug, Default)]
+pub struct PrometheusMetrics {
+    state: Mutex<MetricsState>,
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_scheduler(&self, metrics: &SchedulerMetrics) {
+        self.state.lock().unwrap().scheduler = *metrics;
+    }
+
+    /// Counts the transactions of an executed batch and the compute units
+    /// each consumed.
+    pub fn observe_executed(&self, results: &[TransactionExecutionResult]) {
+        let executor = &mut self.state.lock().unwrap().executor;
+        for result in results {
+            executor.transactions_executed += 1;
+            if !result.was_successful() {
+                executor.transactions_failed += 1;
+            }
+            executor.compute_units.observe(result.consumed_units as f64);
+        }
+    }
+
+    pub fn set_program_cache(&self, stats: &ProgramCacheStats) {
+        self.state.lock().unwrap().program_cache = *stats;
+    }
+
+    pub fn set_account_loads(&self, metrics: &AccountLoadMetrics) {
+        self.state.lock().unwrap().account_loads = *metrics;
+    }
+
+    /// Takes the number of accounts and open snapshots of `accounts_db`.
+    pub fn set_accounts_db(&self, accounts_db: &AccountsDb) {
+        self.state.lock().unwrap().accounts_db =
+            Some((accounts_db.num_accounts(), accounts_db.num_snapshots()));
+    }
+
+    /// Exposes the latency percentiles of the last slot summed up.
+    pub fn set_latency(&self, summary: &SlotLatencySummary) {
+        self.state.lock().unwrap().latency = Some(*summary);
+    }
+
+    /// Every metric, in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut out = Exposition::default();
+
+        let scheduler = &state.scheduler;
+        for (name, help, value) in [
+            (
+                "svm_scheduler_transactions_queued_total",
+                "Transactions handed to the scheduler.",
+                scheduler.transactions_queued,
+            ),
+            (
+                "svm_scheduler_transactions_scheduled_total",
+                "Transactions the scheduler placed in a batch.",
+                scheduler.transactions_scheduled,
+            ),
+            (
+                "svm_scheduler_batches_emitted_total",
+                "Non-empty batches the scheduler emitted.",
+                scheduler.batches_emitted,
+            ),
+            (
+                "svm_scheduler_transactions_blocked_total",
+                "Scheduled transactions blocked by a conflict.",
+                scheduler.transactions_blocked,
+            ),
+            (
+                "svm_scheduler_transactions_retried_total",
+                "Transactions queued again after a transient failure.",
+                scheduler.transactions_retried,
+            ),
+            (
+                "svm_scheduler_transactions_expired_total",
+                "Queued transactions dropped because their deadline passed.",
+                scheduler.transactions_expired,
+            ),
+            (
+                "svm_scheduler_transactions_forwarded_total",
+                "Queued transactions handed to another node.",
+                scheduler.transactions_forwarded,
+            ),
+        ] {
+            out.counter(name, help, value);
+        }
+
+        let executor = &state.executor;
+        out.counter(
+            "svm_executor_transactions_executed_total",
+            "Transactions executed, whether or not they succeeded.",
+            executor.transactions_executed,
+        );
+        out.counter(
+            "svm_executor_transactions_failed_total",
+            "Executed transactions that failed.",
+            executor.transactions_failed,
+        );
+        out.histogram(
+            "svm_executor_compute_units",
+            "Compute units consumed per transaction.",
+            &executor.compute_units,
+        );
+        let cache = &state.program_cache;
+        for (name, help, value) in [
+            (
+                "svm_program_cache_hits_total",
+                "Program loads served by the cache.",
+                cache.hits,
+            ),
+            (
+                "svm_program_cache_misses_total",
+                "Program loads that had to verify the program.",
+                cache.misses,
+            ),
+            (
+                "svm_program_cache_evictions_total",
+                "Programs evicted from the cache.",
+                cache.evictions,
+            ),
+        ] {
+            out.counter(name, help, value);
+        }
+
+        let loads = &state.account_loads;
+        out.counter(
+            "svm_accounts_prefetched_total",
+            "Accounts loaded ahead of the batch that needed them.",
+            loads.accounts_prefetched,
+        );
+        out.counter(
+            "svm_accounts_prefetch_hits_total",
+            "Account loads during execution served by a prefetch.",
+            loads.prefetch_hits,
+        );
+        out.counter(
+            "svm_accounts_prefetch_misses_total",
+            "Account loads during execution that went to the store.",
+            loads.prefetch_misses,
+        );
+        if let Some((accounts, snapshots)) = state.accounts_db {
+            out.gauge(
+                "svm_accounts_db_accounts",
+                "Accounts the accounts db holds.",
+                accounts as f64,
+            );
+            out.gauge(
+                "svm_accounts_db_snapshots",
+                "Snapshots of the accounts db not yet released.",
+                snapshots as f64,
+            );
+        }
+
+        if let Some(latency) = &state.latency {
+            out.gauge(
+                "svm_latency_slot",
+                "Slot the latency percentiles belong to.",
+                latency.slot as f64,
+            );
+            out.latency(
+                "svm_transaction_latency_seconds",
+                "Latency percentiles of the transactions committed in the slot, by phase.",
+                [
+                    ("queued", &latency.queued),
+                    ("dispatched", &latency.dispatched),
+                    ("executed", &latency.executed),
+                    ("total", &latency.total),
+                ],
+            );
+        }
+        out.0
+    }
+}
+
+/// Text in the Prometheus exposition format, one metric family at a time.
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn header(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.0, "# HELP {name} {help}");
+        let _ = writeln!(self.0, "# TYPE {name} {kind}");
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.header(name, help, "counter");
+        let _ = writeln!(self.0, "{name} {value}");
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        self.header(name, help, "gauge");
+        let _ = writeln!(self.0, "{name} {value}");
+    }
+
+    fn histogram(&mut self, name: &str, help: &str, histogram: &Histogram) {
+        self.header(name, help, "histogram");
+        let mut cumulative = 0;
+        for (bound, count) in histogram.bounds.iter().zip(&histogram.counts) {
+            cumulative += count;
+            let _ = writeln!(self.0, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        let count = histogram.count();
+        let _ = writeln!(self.0, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let _ = writeln!(self.0, "{name}_sum {}", histogram.sum);
+        let _ = writeln!(self.0, "{name}_count {count}");
+    }
+
+    /// Percentiles are exposed as a gauge labelled by phase and quantile,
+    /// since they are computed per slot rather than kept as a running
+    /// summary.
+    fn latency<'a>(
+        &mut self,
+        name: &str,
+        help: &str,
+        phases: impl IntoIterator<Item = (&'a str, &'a LatencyPercentiles)>,
+    ) {
+        self.header(name, help, "gauge");
+        for (phase, percentiles) in phases {
+            for (quantile, latency) in [
+                ("0.5", percentiles.p50),
+                ("0.95", percentiles.p95),
+                ("0.99", percentiles.p99),
+                ("1", percentiles.max),
+            ] {
+                let _ = writeln!(
+                    self.0,
+                    "{name}{{phase=\"{phase}\",quantile=\"{quantile}\"}} {}",
+                    latency.as_secs_f64()
+                );
+            }
+        }
+    }
+}
+
+/// Serves a [`PrometheusMetrics`] on `GET /metrics` for Prometheus to
+/// scrape.
+pub struct PrometheusExporter {
+    local_addr: SocketAddr,
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PrometheusExporter {
+    /// Starts serving `metrics` on `addr`. Port 0 picks a free port, which
+    /// [`local_addr`](Self::local_addr) reports.
+    pub fn new(metrics: Arc<PrometheusMetrics>, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let server = Arc::new(Server::http(addr).map_err(io::Error::other)?);
+        let local_addr = server
+            .server_addr()
+            .to_ip()
+            .expect("the server listens on an IP address");
+        let thread = thread::Builder::new()
+            .name("prometheus".to_string())
+            .spawn({
+                let server = server.clone();
+                move || serve(&server, &metrics)
+            })?;
+        Ok(Self {
+            local_addr,
+            server,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for PrometheusExporter {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(server: &Server, metrics: &PrometheusMetrics) {
+    let content_type =
+        Header::from_str("Content-Type: text/plain; version=0.0.4").expect("the header is valid");
+    for request in server.incoming_requests() {
+        let response = if *request.method() != Method::Get {
+            Response::from_string("").with_status_code(405)
+        } else if request.url() != "/metrics" {
+            Response::from_string("").with_status_code(404)
+        } else {
+            Response::from_string(metrics.render()).with_header(content_type.clone())
+        };
+        let _ = request.respond(response);
+    }
+}
@@ -0,0 +1,196 @@
+use {
+    crate::{
+        events::{DropReason, Event, EventBus},
+        scheduler::{PriorityPolicy, TransactionPriorityId},
+    },
+    solana_clock::Slot,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        collections::BTreeMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Condvar, Mutex, MutexGuard,
+        },
+    },
+};
+
+/// What an [`IngestBuffer`] does with a transaction pushed while it is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait for the scheduler to make room, pushing back on whoever pushes.
+    #[default]
+    Block,
+    /// Drop the transaction pushed.
+    DropNewest,
+    /// Drop the lowest priority transaction among those queued and the one
+    /// pushed, so the buffer keeps the ones paying the most.
+    DropLowestPriority,
+}
+
+/// A bounded queue of transactions waiting for the scheduler, taken highest
+/// priority first.
+///
+/// Every transaction the buffer drops because it is full is published as a
+/// [`DropReason::QueueFull`] [`Event`] on the buffer's [`EventBus`], in the
+/// slot last [set](Self::set_slot).
+#[derive(Debug)]
+pub struct IngestBuffer {
+    capacity: usize,
+    policy: OverflowPolicy,
+    priority_policy: PriorityPolicy,
+    state: Mutex<BufferState>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    event_bus: Option<EventBus>,
+    slot: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct BufferState {
+    queue: BTreeMap<TransactionPriorityId, SanitizedTransaction>,
+    num_pushed: usize,
+    num_dropped: u64,
+    closed: bool,
+}
+
+impl IngestBuffer {
+    /// A buffer holding up to `capacity` transactions, ranked by the
+    /// default [`PriorityPolicy`].
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero.
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        assert!(
+            capacity > 0,
+            "an ingest buffer needs room for a transaction"
+        );
+        Self {
+            capacity,
+            policy,
+            priority_policy: PriorityPolicy::default(),
+            state: Mutex::default(),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            event_bus: None,
+            slot: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_priority_policy(mut self, priority_policy: PriorityPolicy) -> Self {
+        self.priority_policy = priority_policy;
+        self
+    }
+
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Sets the slot drop events are published in.
+    pub fn set_slot(&self, slot: Slot) {
+        self.slot.store(slot, Ordering::Relaxed);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Transactions dropped because the buffer was full.
+    pub fn num_dropped(&self) -> u64 {
+        self.lock().num_dropped
+    }
+
+    /// Queues `transaction`, handling a full buffer by the buffer's
+    /// [`OverflowPolicy`].
+    ///
+    /// Returns the transaction dropped to stay within capacity, if any:
+    /// `transaction` itself or a queued one it displaced.
+    pub fn push(&self, transaction: SanitizedTransaction) -> Option<SanitizedTransaction> {
+        let mut state = self.lock();
+        let id = self
+            .priority_policy
+            .priority_id(&transaction, state.num_pushed);
+        state.num_pushed += 1;
+        let dropped = if state.queue.len() < self.capacity {
+            state.queue.insert(id, transaction);
+            None
+        } else {
+            match self.policy {
+                OverflowPolicy::Block => {
+                    while state.queue.len() >= self.capacity && !state.closed {
+                        state = self.not_full.wait(state).unwrap();
+                    }
+                    state.queue.insert(id, transaction);
+                    None
+                }
+                OverflowPolicy::DropNewest => Some(transaction),
+                OverflowPolicy::DropLowestPriority => {
+                    let lowest = *state.queue.keys().next().expect("the buffer is full");
+                    if id > lowest {
+                        state.queue.insert(id, transaction);
+                        state.queue.remove(&lowest)
+                    } else {
+                        Some(transaction)
+                    }
+                }
+            }
+        };
+        if dropped.is_some() {
+            state.num_dropped += 1;
+        }
+        drop(state);
+        self.not_empty.notify_one();
+
+        if let (Some(event_bus), Some(dropped)) = (&self.event_bus, &dropped) {
+            event_bus.publish(Event::TransactionDropped {
+                slot: self.slot.load(Ordering::Relaxed),
+                signature: *dropped.signature(),
+                reason: DropReason::QueueFull,
+            });
+        }
+        dropped
+    }
+
+    /// Takes up to `max` transactions, highest priority first, waiting for
+    /// one to be pushed if the buffer is empty.
+    ///
+    /// Returns nothing once the buffer is [closed](Self::close) and empty.
+    pub fn pop_batch(&self, max: usize) -> Vec<SanitizedTransaction> {
+        let mut state = self.lock();
+        while state.queue.is_empty() && !state.closed {
+            state = self.not_empty.wait(state).unwrap();
+        }
+        let batch_len = max.min(state.queue.len());
+        let batch = (0..batch_len)
+            .map(|_| state.queue.pop_last().expect("batch fits the queue").1)
+            .collect();
+        drop(state);
+        self.not_full.notify_all();
+        batch
+    }
+
+    /// Closes the buffer: pops stop waiting once it is empty, and pushes
+    /// stop waiting for room.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BufferState> {
+        self.state.lock().unwrap()
+    }
+}
@@ -0,0 +1,644 @@
+//! Transaction processing as a pipeline of stages.
+//!
+//! Transactions flow through five stages, each on a thread of its own:
+//!
+//! ```text
+//! ingest -> sigverify -> schedule -> execute -> commit
+//! ```
+//!
+//! Ingest [sanitizes](crate::sanitize) the transactions pushed into the
+//! pipeline, sigverify drops those whose [signatures](crate::sigverify)
+//! fail, schedule splits what is left into conflict-free batches, execute
+//! runs every batch and commit stores what the batches wrote. Every stage
+//! is a [`Stage`] behind a trait object, so any of them can be swapped for
+//! another implementation without touching the rest.
+//!
+//! Stages are connected by bounded channels, one in front of every stage,
+//! whose capacities [`PipelineConfig`] sets. A stage that falls behind
+//! fills its channel, after which the stage in front of it blocks on
+//! sending, and so on up to whoever pushes transactions into the pipeline:
+//! the slowest stage sets the pace of the whole pipeline. In front of the
+//! schedule stage the channel is an [`IngestBuffer`] holding transactions
+//! rather than batches, which the scheduler takes highest priority first
+//! and which may drop transactions instead of blocking once it is full, as
+//! its [`OverflowPolicy`] says. Every stage
+//! reports [`StageMetrics`] telling how long it spent working, waiting for
+//! input and blocked on a full channel downstream, which is what
+//! [`PipelineMetrics::bottleneck`] goes by. A [`LatencyTracker`] handed to
+//! [`Pipeline::with_latency_tracker`] times every transaction from ingest
+//! to commit on top of that.
+//!
+//! Execution and commit go through [`PipelineAccounts`] and charge no fees:
+//! the pipeline is a harness for throughput experiments, not a bank.
+
+mod ingest_buffer;
+mod stages;
+
+pub use ingest_buffer::{IngestBuffer, OverflowPolicy};
+pub use stages::{
+    CommitStage, ExecuteStage, ExecutedBatch, PipelineAccounts, SanitizeStage, ScheduleStage,
+    SigVerifyStage,
+};
+
+use {
+    crate::{
+        events::EventBus,
+        metrics::{LatencyStage, LatencyTracker},
+        scheduler::PriorityGraphScheduler,
+        svm::TransactionExecutor,
+    },
+    crossbeam_channel::{bounded, unbounded, Receiver, Sender},
+    solana_signature::Signature,
+    solana_transaction::{sanitized::SanitizedTransaction, versioned::VersionedTransaction},
+    std::{
+        sync::Arc,
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    },
+    thiserror::Error,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PipelineError {
+    #[error("the pipeline stopped taking transactions")]
+    Stopped,
+}
+
+/// A step of the pipeline, turning every input it gets into any number of
+/// outputs for the next stage.
+///
+/// A closure taking an input and returning an output is a stage that hands
+/// on exactly one output per input.
+pub trait Stage<In, Out>: Send {
+    /// Processes `input`, handing every output to `emit` as soon as it is
+    /// ready.
+    fn process(&mut self, input: In, emit: &mut dyn FnMut(Out));
+
+    /// Hands on whatever the stage still holds back once its input is
+    /// exhausted. Stages keep nothing back by default.
+    fn flush(&mut self, _emit: &mut dyn FnMut(Out)) {}
+}
+
+impl<In, Out, F> Stage<In, Out> for F
+where
+    F: FnMut(In) -> Out + Send,
+{
+    fn process(&mut self, input: In, emit: &mut dyn FnMut(Out)) {
+        emit(self(input));
+    }
+}
+
+/// Capacity of the channel in front of every stage, counted in the items
+/// the stage takes: batches of transactions for ingest, sigverify and
+/// execute, transactions for schedule and executed batches for commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub ingest_capacity: usize,
+    pub sigverify_capacity: usize,
+    /// Capacity of the [`IngestBuffer`] in front of the scheduler.
+    pub schedule_capacity: usize,
+    /// What the [`IngestBuffer`] does once it holds
+    /// [`schedule_capacity`](Self::schedule_capacity) transactions.
+    pub overflow_policy: OverflowPolicy,
+    /// Most transactions the schedule stage takes from its buffer at once.
+    pub schedule_batch_size: usize,
+    pub execute_capacity: usize,
+    pub commit_capacity: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            ingest_capacity: 16,
+            sigverify_capacity: 16,
+            schedule_capacity: 4_096,
+            overflow_policy: OverflowPolicy::default(),
+            schedule_batch_size: 256,
+            execute_capacity: 64,
+            commit_capacity: 64,
+        }
+    }
+}
+
+/// What a stage did over a run of the pipeline.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StageMetrics {
+    pub name: &'static str,
+    /// Items the stage took from its channel.
+    pub inputs: u64,
+    /// Items the stage handed to the next one.
+    pub outputs: u64,
+    /// Transactions in the items the stage took.
+    pub transactions_in: u64,
+    /// Transactions in the items the stage handed on.
+    pub transactions_out: u64,
+    /// Time spent processing, not counting the time blocked on sending.
+    pub busy: Duration,
+    /// Time spent waiting for input.
+    pub idle: Duration,
+    /// Time spent blocked on sending because the next stage's channel was
+    /// full.
+    pub blocked: Duration,
+    /// Most items the stage found waiting in its channel when it went for
+    /// the next one.
+    pub max_queue_len: usize,
+}
+
+impl StageMetrics {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            ..Self::default()
+        }
+    }
+
+    /// Share of the stage's time spent processing, between 0 and 1.
+    pub fn utilization(&self) -> f64 {
+        let total = self.busy + self.idle + self.blocked;
+        if total.is_zero() {
+            return 0.0;
+        }
+        self.busy.as_secs_f64() / total.as_secs_f64()
+    }
+}
+
+/// Metrics of every stage of a run, in pipeline order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PipelineMetrics {
+    pub stages: Vec<StageMetrics>,
+    /// Time whoever pushed transactions into the pipeline spent blocked
+    /// because the ingest channel was full.
+    pub producer_blocked: Duration,
+    /// Transactions the [`IngestBuffer`] dropped because it was full.
+    pub transactions_dropped: u64,
+}
+
+impl PipelineMetrics {
+    pub fn stage(&self, name: &str) -> Option<&StageMetrics> {
+        self.stages.iter().find(|stage| stage.name == name)
+    }
+
+    /// The stage that spent the most time processing, which is the one
+    /// holding the others back: the stages in front of it end up blocked
+    /// on its channel and the ones behind it idle.
+    pub fn bottleneck(&self) -> Option<&StageMetrics> {
+        self.stages.iter().max_by_key(|stage| stage.busy)
+    }
+}
+
+/// Items that flow between stages, which metrics count transactions of.
+trait PipelineItem {
+    fn num_transactions(&self) -> usize;
+}
+
+impl<T> PipelineItem for Vec<T> {
+    fn num_transactions(&self) -> usize {
+        self.len()
+    }
+}
+
+impl PipelineItem for ExecutedBatch {
+    fn num_transactions(&self) -> usize {
+        self.transactions.len()
+    }
+}
+
+/// Items whose transactions a [`LatencyTracker`] can time.
+trait SanitizedItem: Send + 'static {
+    fn signatures(&self) -> impl Iterator<Item = &Signature>;
+}
+
+impl SanitizedItem for Vec<SanitizedTransaction> {
+    fn signatures(&self) -> impl Iterator<Item = &Signature> {
+        self.iter().map(SanitizedTransaction::signature)
+    }
+}
+
+impl SanitizedItem for ExecutedBatch {
+    fn signatures(&self) -> impl Iterator<Item = &Signature> {
+        self.transactions
+            .iter()
+            .map(SanitizedTransaction::signature)
+    }
+}
+
+/// Times the transactions of every input a stage takes at `at`.
+struct TimeInputs<In, Out> {
+    stage: BoxedStage<In, Out>,
+    tracker: Arc<LatencyTracker>,
+    at: LatencyStage,
+}
+
+impl<In: SanitizedItem, Out> Stage<In, Out> for TimeInputs<In, Out> {
+    fn process(&mut self, input: In, emit: &mut dyn FnMut(Out)) {
+        self.tracker
+            .record_all(input.signatures(), self.at, Instant::now());
+        self.stage.process(input, emit);
+    }
+
+    fn flush(&mut self, emit: &mut dyn FnMut(Out)) {
+        self.stage.flush(emit);
+    }
+}
+
+/// Times the transactions of every output a stage hands on at `at`.
+struct TimeOutputs<In, Out> {
+    stage: BoxedStage<In, Out>,
+    tracker: Arc<LatencyTracker>,
+    at: LatencyStage,
+}
+
+impl<In: Send + 'static, Out: SanitizedItem> Stage<In, Out> for TimeOutputs<In, Out> {
+    fn process(&mut self, input: In, emit: &mut dyn FnMut(Out)) {
+        let (tracker, at) = (&self.tracker, self.at);
+        self.stage.process(input, &mut |output: Out| {
+            tracker.record_all(output.signatures(), at, Instant::now());
+            emit(output);
+        });
+    }
+
+    fn flush(&mut self, emit: &mut dyn FnMut(Out)) {
+        let (tracker, at) = (&self.tracker, self.at);
+        self.stage.flush(&mut |output: Out| {
+            tracker.record_all(output.signatures(), at, Instant::now());
+            emit(output);
+        });
+    }
+}
+
+type BoxedStage<In, Out> = Box<dyn Stage<In, Out>>;
+
+/// The five stages of a pipeline and the channels between them, ready to
+/// be [spawned](Self::spawn).
+pub struct Pipeline {
+    config: PipelineConfig,
+    event_bus: Option<EventBus>,
+    latency_tracker: Option<Arc<LatencyTracker>>,
+    ingest: BoxedStage<Vec<VersionedTransaction>, Vec<SanitizedTransaction>>,
+    sigverify: BoxedStage<Vec<SanitizedTransaction>, Vec<SanitizedTransaction>>,
+    schedule: BoxedStage<Vec<SanitizedTransaction>, Vec<SanitizedTransaction>>,
+    execute: BoxedStage<Vec<SanitizedTransaction>, ExecutedBatch>,
+    commit: BoxedStage<ExecutedBatch, ExecutedBatch>,
+}
+
+impl Pipeline {
+    /// A pipeline of the default stages, executing with `executor` on
+    /// `accounts` and scheduling with a [`PriorityGraphScheduler`].
+    pub fn new(executor: Arc<TransactionExecutor>, accounts: Arc<PipelineAccounts>) -> Self {
+        Self {
+            config: PipelineConfig::default(),
+            event_bus: None,
+            latency_tracker: None,
+            ingest: Box::new(SanitizeStage),
+            sigverify: Box::new(SigVerifyStage::default()),
+            schedule: Box::new(ScheduleStage::new(PriorityGraphScheduler::new())),
+            execute: Box::new(ExecuteStage::new(executor, Arc::clone(&accounts))),
+            commit: Box::new(CommitStage::new(accounts)),
+        }
+    }
+
+    pub fn with_config(mut self, config: PipelineConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Publishes the transactions the [`IngestBuffer`] drops to
+    /// `event_bus`.
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Times every transaction with `tracker` as it is pushed, scheduled,
+    /// starts executing and is committed.
+    pub fn with_latency_tracker(mut self, tracker: Arc<LatencyTracker>) -> Self {
+        self.latency_tracker = Some(tracker);
+        self
+    }
+
+    pub fn with_ingest(
+        mut self,
+        stage: impl Stage<Vec<VersionedTransaction>, Vec<SanitizedTransaction>> + 'static,
+    ) -> Self {
+        self.ingest = Box::new(stage);
+        self
+    }
+
+    pub fn with_sigverify(
+        mut self,
+        stage: impl Stage<Vec<SanitizedTransaction>, Vec<SanitizedTransaction>> + 'static,
+    ) -> Self {
+        self.sigverify = Box::new(stage);
+        self
+    }
+
+    pub fn with_schedule(
+        mut self,
+        stage: impl Stage<Vec<SanitizedTransaction>, Vec<SanitizedTransaction>> + 'static,
+    ) -> Self {
+        self.schedule = Box::new(stage);
+        self
+    }
+
+    pub fn with_execute(
+        mut self,
+        stage: impl Stage<Vec<SanitizedTransaction>, ExecutedBatch> + 'static,
+    ) -> Self {
+        self.execute = Box::new(stage);
+        self
+    }
+
+    pub fn with_commit(
+        mut self,
+        stage: impl Stage<ExecutedBatch, ExecutedBatch> + 'static,
+    ) -> Self {
+        self.commit = Box::new(stage);
+        self
+    }
+
+    /// Starts every stage on a thread of its own.
+    ///
+    /// # Panics
+    ///
+    /// If any channel capacity is zero.
+    pub fn spawn(mut self) -> RunningPipeline {
+        let config = self.config;
+        for capacity in [
+            config.ingest_capacity,
+            config.sigverify_capacity,
+            config.schedule_capacity,
+            config.schedule_batch_size,
+            config.execute_capacity,
+            config.commit_capacity,
+        ] {
+            assert!(capacity > 0, "pipeline channels need room for an item");
+        }
+
+        if let Some(tracker) = &self.latency_tracker {
+            self.schedule = Box::new(TimeOutputs {
+                stage: self.schedule,
+                tracker: Arc::clone(tracker),
+                at: LatencyStage::Schedule,
+            });
+            self.execute = Box::new(TimeInputs {
+                stage: self.execute,
+                tracker: Arc::clone(tracker),
+                at: LatencyStage::ExecuteStart,
+            });
+            self.commit = Box::new(TimeOutputs {
+                stage: self.commit,
+                tracker: Arc::clone(tracker),
+                at: LatencyStage::Commit,
+            });
+        }
+
+        let (ingest_sender, ingest_receiver) = bounded(config.ingest_capacity);
+        let (sigverify_sender, sigverify_receiver) = bounded(config.sigverify_capacity);
+        let mut ingest_buffer = IngestBuffer::new(config.schedule_capacity, config.overflow_policy);
+        if let Some(event_bus) = self.event_bus {
+            ingest_buffer = ingest_buffer.with_event_bus(event_bus);
+        }
+        let ingest_buffer = Arc::new(ingest_buffer);
+        let schedule_sender = BufferSender(Arc::clone(&ingest_buffer));
+        let schedule_receiver = BufferReceiver {
+            buffer: Arc::clone(&ingest_buffer),
+            batch_size: config.schedule_batch_size,
+        };
+        let (execute_sender, execute_receiver) = bounded(config.execute_capacity);
+        let (commit_sender, commit_receiver) = bounded(config.commit_capacity);
+        // Nothing comes after commit, so its output never pushes back.
+        let (committed_sender, committed_receiver) = unbounded();
+        let handles = vec![
+            spawn_stage("ingest", self.ingest, ingest_receiver, sigverify_sender),
+            spawn_stage(
+                "sigverify",
+                self.sigverify,
+                sigverify_receiver,
+                schedule_sender,
+            ),
+            spawn_stage("schedule", self.schedule, schedule_receiver, execute_sender),
+            spawn_stage("execute", self.execute, execute_receiver, commit_sender),
+            spawn_stage("commit", self.commit, commit_receiver, committed_sender),
+        ];
+        RunningPipeline {
+            sender: Some(ingest_sender),
+            committed_receiver,
+            ingest_buffer,
+            latency_tracker: self.latency_tracker,
+            handles,
+            producer_blocked: Duration::ZERO,
+        }
+    }
+
+    /// Pushes every batch of `transactions` through the pipeline and
+    /// waits for them to be committed.
+    pub fn run(
+        self,
+        transactions: impl IntoIterator<Item = Vec<VersionedTransaction>>,
+    ) -> (Vec<ExecutedBatch>, PipelineMetrics) {
+        let mut pipeline = self.spawn();
+        for batch in transactions {
+            pipeline
+                .send(batch)
+                .expect("a stage stopped while transactions were pushed");
+        }
+        pipeline.finish()
+    }
+}
+
+/// A [`Pipeline`] whose stages are running.
+pub struct RunningPipeline {
+    sender: Option<Sender<Vec<VersionedTransaction>>>,
+    committed_receiver: Receiver<ExecutedBatch>,
+    ingest_buffer: Arc<IngestBuffer>,
+    latency_tracker: Option<Arc<LatencyTracker>>,
+    handles: Vec<JoinHandle<StageMetrics>>,
+    producer_blocked: Duration,
+}
+
+impl RunningPipeline {
+    /// Pushes a batch of transactions into the pipeline, blocking while
+    /// the ingest channel is full.
+    pub fn send(&mut self, transactions: Vec<VersionedTransaction>) -> Result<(), PipelineError> {
+        let sender = self.sender.as_ref().expect("sender lives until finish");
+        let started = Instant::now();
+        if let Some(tracker) = &self.latency_tracker {
+            let signatures = transactions
+                .iter()
+                .filter_map(|transaction| transaction.signatures.first());
+            tracker.record_all(signatures, LatencyStage::Ingest, started);
+        }
+        let result = sender.send(transactions);
+        self.producer_blocked += started.elapsed();
+        result.map_err(|_| PipelineError::Stopped)
+    }
+
+    /// Batches committed so far. Whatever is not taken from here is
+    /// returned by [`finish`](Self::finish).
+    pub fn committed_receiver(&self) -> &Receiver<ExecutedBatch> {
+        &self.committed_receiver
+    }
+
+    /// The buffer in front of the scheduler, e.g. to tell it the slot its
+    /// drop events belong to.
+    pub fn ingest_buffer(&self) -> &IngestBuffer {
+        &self.ingest_buffer
+    }
+
+    /// Stops taking transactions and waits for every stage to process what
+    /// it was given, returning the committed batches not yet taken from the
+    /// [`committed_receiver`](Self::committed_receiver).
+    ///
+    /// # Panics
+    ///
+    /// If a stage panicked.
+    pub fn finish(mut self) -> (Vec<ExecutedBatch>, PipelineMetrics) {
+        drop(self.sender.take());
+        let stages = self
+            .handles
+            .drain(..)
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect();
+        let committed = self.committed_receiver.try_iter().collect();
+        let metrics = PipelineMetrics {
+            stages,
+            producer_blocked: self.producer_blocked,
+            transactions_dropped: self.ingest_buffer.num_dropped(),
+        };
+        (committed, metrics)
+    }
+}
+
+/// The receiving end of the channel in front of a stage.
+trait StageReceiver<T>: Send + 'static {
+    /// Waits for the next item; `None` once the stage in front is done.
+    fn recv(&self) -> Option<T>;
+
+    fn len(&self) -> usize;
+}
+
+/// The sending end of the channel behind a stage.
+trait StageSender<T>: Send + 'static {
+    fn send(&self, item: T);
+}
+
+impl<T: Send + 'static> StageReceiver<T> for Receiver<T> {
+    fn recv(&self) -> Option<T> {
+        Receiver::recv(self).ok()
+    }
+
+    fn len(&self) -> usize {
+        Receiver::len(self)
+    }
+}
+
+impl<T: Send + 'static> StageSender<T> for Sender<T> {
+    fn send(&self, item: T) {
+        // A send only fails once the next stage is gone, which means it
+        // panicked; finishing the pipeline reports that.
+        let _ = Sender::send(self, item);
+    }
+}
+
+/// Pushes into an [`IngestBuffer`], closing it once the stage is done.
+struct BufferSender(Arc<IngestBuffer>);
+
+impl StageSender<Vec<SanitizedTransaction>> for BufferSender {
+    fn send(&self, transactions: Vec<SanitizedTransaction>) {
+        for transaction in transactions {
+            self.0.push(transaction);
+        }
+    }
+}
+
+impl Drop for BufferSender {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+struct BufferReceiver {
+    buffer: Arc<IngestBuffer>,
+    batch_size: usize,
+}
+
+/// Closes the buffer should the schedule stage stop early, so pushes never
+/// wait on it.
+impl Drop for BufferReceiver {
+    fn drop(&mut self) {
+        self.buffer.close();
+    }
+}
+
+impl StageReceiver<Vec<SanitizedTransaction>> for BufferReceiver {
+    fn recv(&self) -> Option<Vec<SanitizedTransaction>> {
+        let batch = self.buffer.pop_batch(self.batch_size);
+        (!batch.is_empty()).then_some(batch)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+fn spawn_stage<In, Out>(
+    name: &'static str,
+    mut stage: BoxedStage<In, Out>,
+    receiver: impl StageReceiver<In>,
+    sender: impl StageSender<Out>,
+) -> JoinHandle<StageMetrics>
+where
+    In: PipelineItem + Send + 'static,
+    Out: PipelineItem + Send + 'static,
+{
+    thread::Builder::new()
+        .name(format!("pipeline-{name}"))
+        .spawn(move || {
+            let mut metrics = StageMetrics::new(name);
+            loop {
+                metrics.max_queue_len = metrics.max_queue_len.max(receiver.len());
+                let waiting = Instant::now();
+                let Some(input) = receiver.recv() else {
+                    break;
+                };
+                metrics.idle += waiting.elapsed();
+                metrics.inputs += 1;
+                metrics.transactions_in += input.num_transactions() as u64;
+                timed(&mut metrics, |metrics| {
+                    stage.process(input, &mut |output| send(&sender, output, metrics))
+                });
+            }
+            timed(&mut metrics, |metrics| {
+                stage.flush(&mut |output| send(&sender, output, metrics))
+            });
+            metrics
+        })
+        .expect("failed to spawn a pipeline stage")
+}
+
+/// Runs `f`, counting the time it took as busy apart from the time it spent
+/// blocked on sending.
+fn timed(metrics: &mut StageMetrics, f: impl FnOnce(&mut StageMetrics)) {
+    let started = Instant::now();
+    let blocked_before = metrics.blocked;
+    f(metrics);
+    metrics.busy += started
+        .elapsed()
+        .saturating_sub(metrics.blocked - blocked_before);
+}
+
+fn send<Out: PipelineItem>(
+    sender: &impl StageSender<Out>,
+    output: Out,
+    metrics: &mut StageMetrics,
+) {
+    metrics.outputs += 1;
+    metrics.transactions_out += output.num_transactions() as u64;
+    let started = Instant::now();
+    sender.send(output);
+    metrics.blocked += started.elapsed();
+}
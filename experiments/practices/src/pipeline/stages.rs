@@ -0,0 +1,299 @@
+//! The stages a [`Pipeline`](super::Pipeline) is built of by default.
+
+use {
+    super::Stage,
+    crate::{
+        accounts_storage::ShardedStorage,
+        sanitize::sanitize_transaction,
+        scheduler::Scheduler,
+        sigverify::{self, SigVerifyConfig, SignatureVerifier},
+        spans,
+        svm::{AccountLoader, TransactionExecutionResult, TransactionExecutor},
+    },
+    rayon::prelude::*,
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_message::SimpleAddressLoader,
+    solana_pubkey::Pubkey,
+    solana_transaction::{sanitized::SanitizedTransaction, versioned::VersionedTransaction},
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// Transactions of a batch with the result of executing each of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutedBatch {
+    pub transactions: Vec<SanitizedTransaction>,
+    pub results: Vec<TransactionExecutionResult>,
+}
+
+impl ExecutedBatch {
+    /// The accounts the successful transactions of the batch wrote, in
+    /// execution order.
+    fn writes(&self) -> impl Iterator<Item = (Pubkey, &AccountSharedData)> {
+        self.transactions
+            .iter()
+            .zip(&self.results)
+            .filter(|(_, result)| result.was_successful())
+            .flat_map(|(transaction, result)| {
+                let message = transaction.message();
+                result
+                    .post_accounts
+                    .iter()
+                    .enumerate()
+                    .filter(move |(index, _)| message.is_writable(*index))
+                    .map(|(_, (pubkey, account))| (*pubkey, account))
+            })
+    }
+}
+
+/// Accounts the execute and commit stages of a pipeline share.
+///
+/// Commit is the only stage storing accounts, and it counts the batches it
+/// stored so the execute stage knows which of the writes it made are
+/// stored already.
+#[derive(Debug, Default)]
+pub struct PipelineAccounts {
+    storage: ShardedStorage,
+    committed_batches: AtomicU64,
+}
+
+impl PipelineAccounts {
+    pub fn new(storage: ShardedStorage) -> Self {
+        Self {
+            storage,
+            committed_batches: AtomicU64::new(0),
+        }
+    }
+
+    pub fn storage(&self) -> &ShardedStorage {
+        &self.storage
+    }
+
+    /// Batches stored by [`commit_batch`](Self::commit_batch) so far.
+    pub fn committed_batches(&self) -> u64 {
+        self.committed_batches.load(Ordering::Acquire)
+    }
+
+    /// Stores the writable accounts of every successful transaction of
+    /// `batch`, returning the number of those transactions.
+    ///
+    /// Batches have to be committed in the order they executed.
+    pub fn commit_batch(&self, batch: &ExecutedBatch) -> usize {
+        self.storage.commit(
+            batch
+                .writes()
+                .map(|(pubkey, account)| (pubkey, account.clone())),
+        );
+        self.committed_batches.fetch_add(1, Ordering::Release);
+        batch
+            .results
+            .iter()
+            .filter(|result| result.was_successful())
+            .count()
+    }
+}
+
+/// Ingests transactions by [sanitizing](sanitize_transaction) them,
+/// dropping the malformed ones. Address lookup tables are not resolved.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SanitizeStage;
+
+impl Stage<Vec<VersionedTransaction>, Vec<SanitizedTransaction>> for SanitizeStage {
+    fn process(
+        &mut self,
+        transactions: Vec<VersionedTransaction>,
+        emit: &mut dyn FnMut(Vec<SanitizedTransaction>),
+    ) {
+        let sanitized: Vec<_> = transactions
+            .into_iter()
+            .filter_map(|transaction| {
+                sanitize_transaction(transaction, SimpleAddressLoader::Disabled).ok()
+            })
+            .collect();
+        if !sanitized.is_empty() {
+            emit(sanitized);
+        }
+    }
+}
+
+/// Drops the transactions that fail [signature verification](sigverify).
+#[derive(Clone, Default)]
+pub struct SigVerifyStage {
+    config: SigVerifyConfig,
+    /// The [default verifier](sigverify::default_verifier) when `None`.
+    verifier: Option<Arc<dyn SignatureVerifier>>,
+}
+
+impl SigVerifyStage {
+    pub fn new(config: SigVerifyConfig) -> Self {
+        Self {
+            config,
+            verifier: None,
+        }
+    }
+
+    pub fn with_verifier(mut self, verifier: Arc<dyn SignatureVerifier>) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+}
+
+impl Stage<Vec<SanitizedTransaction>, Vec<SanitizedTransaction>> for SigVerifyStage {
+    fn process(
+        &mut self,
+        transactions: Vec<SanitizedTransaction>,
+        emit: &mut dyn FnMut(Vec<SanitizedTransaction>),
+    ) {
+        let verifier: &dyn SignatureVerifier = match &self.verifier {
+            Some(verifier) => verifier.as_ref(),
+            None => sigverify::default_verifier(),
+        };
+        let verified = sigverify::verify_transactions_with(&transactions, &self.config, verifier);
+        let verified: Vec<_> = transactions
+            .into_iter()
+            .zip(verified)
+            .filter_map(|(transaction, verified)| verified.then_some(transaction))
+            .collect();
+        if !verified.is_empty() {
+            emit(verified);
+        }
+    }
+}
+
+/// Splits every set of transactions it gets into the conflict-free
+/// batches of a [`Scheduler`], handing on one batch at a time.
+pub struct ScheduleStage<S> {
+    scheduler: S,
+}
+
+impl<S: Scheduler> ScheduleStage<S> {
+    pub fn new(scheduler: S) -> Self {
+        Self { scheduler }
+    }
+}
+
+impl<S: Scheduler + Send> Stage<Vec<SanitizedTransaction>, Vec<SanitizedTransaction>>
+    for ScheduleStage<S>
+{
+    fn process(
+        &mut self,
+        transactions: Vec<SanitizedTransaction>,
+        emit: &mut dyn FnMut(Vec<SanitizedTransaction>),
+    ) {
+        let batches = self.scheduler.schedule(&transactions);
+        let mut transactions: Vec<_> = transactions.into_iter().map(Some).collect();
+        for batch in batches {
+            let batch: Vec<_> = batch
+                .transaction_indexes
+                .iter()
+                .filter_map(|&index| transactions[index].take())
+                .collect();
+            if !batch.is_empty() {
+                emit(batch);
+            }
+        }
+    }
+}
+
+/// Executes every transaction of a conflict-free batch in parallel on the
+/// rayon pool.
+///
+/// Transactions see the writes of every batch executed before theirs,
+/// whether or not commit has stored them yet: the stage keeps the writes
+/// of the batches it handed on until commit catches up.
+pub struct ExecuteStage {
+    executor: Arc<TransactionExecutor>,
+    accounts: Arc<PipelineAccounts>,
+    /// Accounts written by executed batches, with the number of the last
+    /// batch that wrote each one.
+    pending_writes: HashMap<Pubkey, (u64, AccountSharedData)>,
+    executed_batches: u64,
+}
+
+impl ExecuteStage {
+    pub fn new(executor: Arc<TransactionExecutor>, accounts: Arc<PipelineAccounts>) -> Self {
+        Self {
+            executor,
+            accounts,
+            pending_writes: HashMap::new(),
+            executed_batches: 0,
+        }
+    }
+}
+
+impl Stage<Vec<SanitizedTransaction>, ExecutedBatch> for ExecuteStage {
+    fn process(
+        &mut self,
+        transactions: Vec<SanitizedTransaction>,
+        emit: &mut dyn FnMut(ExecutedBatch),
+    ) {
+        let committed_batches = self.accounts.committed_batches();
+        self.pending_writes
+            .retain(|_, (batch, _)| *batch >= committed_batches);
+        let loader = PendingLoader {
+            pending_writes: &self.pending_writes,
+            storage: self.accounts.storage(),
+        };
+        let batch_span = spans::batch_span(transactions.len());
+        let results = transactions
+            .par_iter()
+            .map(|transaction| {
+                // The rayon threads do not inherit the batch span.
+                batch_span.in_scope(|| {
+                    let _span = spans::transaction_span(transaction).entered();
+                    self.executor
+                        .load_and_execute_transaction(&loader, transaction)
+                })
+            })
+            .collect();
+        let batch = ExecutedBatch {
+            transactions,
+            results,
+        };
+        for (pubkey, account) in batch.writes() {
+            self.pending_writes
+                .insert(pubkey, (self.executed_batches, account.clone()));
+        }
+        self.executed_batches += 1;
+        emit(batch);
+    }
+}
+
+/// Loads accounts as the batches executed so far left them.
+struct PendingLoader<'a> {
+    pending_writes: &'a HashMap<Pubkey, (u64, AccountSharedData)>,
+    storage: &'a ShardedStorage,
+}
+
+impl AccountLoader for PendingLoader<'_> {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        match self.pending_writes.get(pubkey) {
+            // Commit removes the accounts a batch closed.
+            Some((_, account)) => (account.lamports() > 0).then(|| account.clone()),
+            None => self.storage.load(pubkey),
+        }
+    }
+}
+
+/// Stores what every executed batch wrote in the [`PipelineAccounts`].
+pub struct CommitStage {
+    accounts: Arc<PipelineAccounts>,
+}
+
+impl CommitStage {
+    pub fn new(accounts: Arc<PipelineAccounts>) -> Self {
+        Self { accounts }
+    }
+}
+
+impl Stage<ExecutedBatch, ExecutedBatch> for CommitStage {
+    fn process(&mut self, batch: ExecutedBatch, emit: &mut dyn FnMut(ExecutedBatch)) {
+        self.accounts.commit_batch(&batch);
+        emit(batch);
+    }
+}
@@ -0,0 +1,55 @@
+//! Notifications for consumers outside the VM.
+//!
+//! Like the validator's Geyser plugins, an [`AccountsNotifier`] attached to
+//! a [`Bank`](crate::bank::Bank) hears about every account a transaction
+//! commit writes, every transaction the bank executes and every slot it
+//! moves through, so indexers and dashboards can follow an experiment
+//! without patching the bank. Notifiers run synchronously on the thread
+//! that commits, so they should hand anything slow off to a thread of their
+//! own.
+
+use {
+    crate::transaction_status::TransactionExecutionDetails, solana_account::AccountSharedData,
+    solana_clock::Slot, solana_pubkey::Pubkey, solana_transaction::sanitized::SanitizedTransaction,
+};
+
+/// A stage a slot's bank went through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotStatus {
+    /// The bank started processing the slot.
+    Created,
+    /// The slot ended and its bank hash is final.
+    Frozen,
+    /// The slot was rooted and can no longer be rolled back.
+    Rooted,
+}
+
+/// Receives what a bank commits.
+///
+/// Every method does nothing by default, so a notifier only implements the
+/// callbacks it cares about.
+pub trait AccountsNotifier: Send + Sync {
+    /// `account` was stored at `pubkey` in `slot` by committing
+    /// `transaction`.
+    fn notify_account_update(
+        &self,
+        _slot: Slot,
+        _pubkey: &Pubkey,
+        _account: &AccountSharedData,
+        _transaction: &SanitizedTransaction,
+    ) {
+    }
+
+    /// `transaction` was executed in `slot`, successfully or not, and its
+    /// fee charged.
+    fn notify_transaction(
+        &self,
+        _slot: Slot,
+        _transaction: &SanitizedTransaction,
+        _details: &TransactionExecutionDetails,
+    ) {
+    }
+
+    /// The bank of `slot` reached `status`.
+    fn notify_slot_status(&self, _slot: Slot, _status: SlotStatus) {}
+}
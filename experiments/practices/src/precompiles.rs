@@ -0,0 +1,541 @@
+//! Signature verification precompiles.
+//!
+//! The ed25519, secp256k1 and secp256r1 programs are not executed like
+//! other programs. Their instructions describe signatures to check, by
+//! offsets into the data of any instruction of the transaction, and the
+//! signatures are verified ahead of execution, next to the transaction's
+//! own signatures. A transaction with a failing precompile instruction is
+//! dropped before it is scheduled; once executed, a precompile instruction
+//! does nothing.
+//!
+//! [`PrecompileConfig`] selects which rule changes of the precompiles are
+//! active, so both sides of an activation can be replayed.
+
+use {
+    crate::svm::InvokeContext,
+    ed25519_dalek::{Signature as Ed25519Signature, VerifyingKey as Ed25519VerifyingKey},
+    p256::ecdsa::{
+        signature::Verifier, Signature as Secp256r1Signature, VerifyingKey as Secp256r1VerifyingKey,
+    },
+    solana_instruction::Instruction,
+    solana_instruction_error::InstructionError,
+    solana_message::SanitizedMessage,
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{ed25519_program, secp256k1_program, secp256r1_program},
+    solana_transaction_error::TransactionError,
+};
+
+/// Size of a serialized ed25519 or secp256r1 signature offsets entry.
+pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
+
+/// Size of a serialized secp256k1 signature offsets entry.
+pub const SECP256K1_SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 11;
+
+/// Most signatures a single secp256r1 instruction may check.
+pub const SECP256R1_MAX_SIGNATURES: u8 = 8;
+
+/// Instruction index of ed25519 and secp256r1 offsets that refer to the
+/// precompile instruction itself.
+pub const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Why a precompile instruction failed to verify. Failures are reported as
+/// [`InstructionError::Custom`] holding the discriminant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PrecompileError {
+    #[error("public key is not valid")]
+    InvalidPublicKey = 0,
+    #[error("id is not valid")]
+    InvalidRecoveryId = 1,
+    #[error("signature is not valid")]
+    InvalidSignature = 2,
+    #[error("offset not valid")]
+    InvalidDataOffsets = 3,
+    #[error("instruction is incorrect size")]
+    InvalidInstructionDataSize = 4,
+}
+
+impl PrecompileError {
+    const ALL: [Self; 5] = [
+        Self::InvalidPublicKey,
+        Self::InvalidRecoveryId,
+        Self::InvalidSignature,
+        Self::InvalidDataOffsets,
+        Self::InvalidInstructionDataSize,
+    ];
+
+    /// The error reported with the custom code `code`, if any.
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+impl From<PrecompileError> for InstructionError {
+    fn from(err: PrecompileError) -> Self {
+        Self::Custom(err as u32)
+    }
+}
+
+/// Rule changes of the precompiles, each one active if set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrecompileConfig {
+    /// Verify ed25519 signatures with the strict rules, which reject small
+    /// order public keys and non-canonical encodings.
+    pub ed25519_verify_strict: bool,
+    /// Recognize the secp256r1 program as a precompile.
+    pub enable_secp256r1: bool,
+}
+
+impl Default for PrecompileConfig {
+    /// Every rule change active.
+    fn default() -> Self {
+        Self {
+            ed25519_verify_strict: true,
+            enable_secp256r1: true,
+        }
+    }
+}
+
+/// Verifies a precompile instruction, given its data and the data of every
+/// instruction of the transaction.
+pub type VerifyFunction = fn(&[u8], &[&[u8]], &PrecompileConfig) -> Result<(), PrecompileError>;
+
+/// A precompile and the function that verifies its instructions.
+#[derive(Clone, Copy, Debug)]
+pub struct Precompile {
+    pub name: &'static str,
+    pub program_id: Pubkey,
+    pub verify: VerifyFunction,
+}
+
+/// Every precompile, whether or not a configuration enables it.
+pub static PRECOMPILES: &[Precompile] = &[
+    Precompile {
+        name: "ed25519_program",
+        program_id: ed25519_program::ID,
+        verify: verify_ed25519,
+    },
+    Precompile {
+        name: "secp256k1_program",
+        program_id: secp256k1_program::ID,
+        verify: verify_secp256k1,
+    },
+    Precompile {
+        name: "secp256r1_program",
+        program_id: secp256r1_program::ID,
+        verify: verify_secp256r1,
+    },
+];
+
+/// The precompile at `program_id`, if `config` enables one there.
+pub fn get_precompile(
+    program_id: &Pubkey,
+    config: &PrecompileConfig,
+) -> Option<&'static Precompile> {
+    PRECOMPILES.iter().find(|precompile| {
+        precompile.program_id == *program_id
+            && (precompile.program_id != secp256r1_program::ID || config.enable_secp256r1)
+    })
+}
+
+pub fn is_precompile(program_id: &Pubkey, config: &PrecompileConfig) -> bool {
+    get_precompile(program_id, config).is_some()
+}
+
+/// Verifies every precompile instruction of `message`.
+///
+/// Fails with the [`TransactionError::InstructionError`] of the first
+/// instruction that does not verify.
+pub fn verify_precompiles(
+    message: &SanitizedMessage,
+    config: &PrecompileConfig,
+) -> Result<(), TransactionError> {
+    let instruction_datas: Vec<&[u8]> = message
+        .instructions()
+        .iter()
+        .map(|instruction| instruction.data.as_slice())
+        .collect();
+    for (index, (program_id, instruction)) in message.program_instructions_iter().enumerate() {
+        if let Some(precompile) = get_precompile(program_id, config) {
+            (precompile.verify)(&instruction.data, &instruction_datas, config)
+                .map_err(|err| TransactionError::InstructionError(index as u8, err.into()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Entrypoint of the precompiles. Their instructions were verified before
+/// the transaction was scheduled, so executing them does nothing.
+pub fn process_instruction(_invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    Ok(())
+}
+
+/// Offsets of one signature checked by an ed25519 or secp256r1
+/// instruction, each into the data of the instruction at the paired
+/// index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SignatureOffsets {
+    pub signature_offset: u16,
+    pub signature_instruction_index: u16,
+    pub public_key_offset: u16,
+    pub public_key_instruction_index: u16,
+    pub message_data_offset: u16,
+    pub message_data_size: u16,
+    pub message_instruction_index: u16,
+}
+
+impl SignatureOffsets {
+    fn parse(data: &[u8]) -> Self {
+        let field = |index: usize| u16::from_le_bytes([data[2 * index], data[2 * index + 1]]);
+        Self {
+            signature_offset: field(0),
+            signature_instruction_index: field(1),
+            public_key_offset: field(2),
+            public_key_instruction_index: field(3),
+            message_data_offset: field(4),
+            message_data_size: field(5),
+            message_instruction_index: field(6),
+        }
+    }
+
+    fn serialize(&self) -> [u8; SIGNATURE_OFFSETS_SERIALIZED_SIZE] {
+        let mut data = [0; SIGNATURE_OFFSETS_SERIALIZED_SIZE];
+        for (index, field) in [
+            self.signature_offset,
+            self.signature_instruction_index,
+            self.public_key_offset,
+            self.public_key_instruction_index,
+            self.message_data_offset,
+            self.message_data_size,
+            self.message_instruction_index,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            data[2 * index..2 * index + 2].copy_from_slice(&field.to_le_bytes());
+        }
+        data
+    }
+}
+
+/// Offsets of one signature checked by a secp256k1 instruction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Secp256k1SignatureOffsets {
+    pub signature_offset: u16,
+    pub signature_instruction_index: u8,
+    pub eth_address_offset: u16,
+    pub eth_address_instruction_index: u8,
+    pub message_data_offset: u16,
+    pub message_data_size: u16,
+    pub message_instruction_index: u8,
+}
+
+impl Secp256k1SignatureOffsets {
+    fn parse(data: &[u8]) -> Self {
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        Self {
+            signature_offset: u16_at(0),
+            signature_instruction_index: data[2],
+            eth_address_offset: u16_at(3),
+            eth_address_instruction_index: data[5],
+            message_data_offset: u16_at(6),
+            message_data_size: u16_at(8),
+            message_instruction_index: data[10],
+        }
+    }
+
+    fn serialize(&self) -> [u8; SECP256K1_SIGNATURE_OFFSETS_SERIALIZED_SIZE] {
+        let mut data = [0; SECP256K1_SIGNATURE_OFFSETS_SERIALIZED_SIZE];
+        data[0..2].copy_from_slice(&self.signature_offset.to_le_bytes());
+        data[2] = self.signature_instruction_index;
+        data[3..5].copy_from_slice(&self.eth_address_offset.to_le_bytes());
+        data[5] = self.eth_address_instruction_index;
+        data[6..8].copy_from_slice(&self.message_data_offset.to_le_bytes());
+        data[8..10].copy_from_slice(&self.message_data_size.to_le_bytes());
+        data[10] = self.message_instruction_index;
+        data
+    }
+}
+
+/// Reads `size` bytes at `offset` of the instruction at `instruction_index`,
+/// or of `data` itself for [`CURRENT_INSTRUCTION`].
+fn get_data_slice<'a>(
+    data: &'a [u8],
+    instruction_datas: &[&'a [u8]],
+    instruction_index: u16,
+    offset: u16,
+    size: usize,
+) -> Result<&'a [u8], PrecompileError> {
+    let instruction = if instruction_index == CURRENT_INSTRUCTION {
+        data
+    } else {
+        instruction_datas
+            .get(usize::from(instruction_index))
+            .ok_or(PrecompileError::InvalidDataOffsets)?
+    };
+    let start = usize::from(offset);
+    instruction
+        .get(start..start.saturating_add(size))
+        .ok_or(PrecompileError::InvalidDataOffsets)
+}
+
+/// Offsets entries of an ed25519 or secp256r1 instruction, after checking
+/// that `data` is large enough to hold them.
+fn signature_offsets(data: &[u8]) -> Result<Vec<SignatureOffsets>, PrecompileError> {
+    let (&[num_signatures, _padding], entries) = data
+        .split_first_chunk()
+        .ok_or(PrecompileError::InvalidInstructionDataSize)?;
+    if num_signatures == 0 && !entries.is_empty() {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    let entries = entries
+        .get(..usize::from(num_signatures) * SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+        .ok_or(PrecompileError::InvalidInstructionDataSize)?;
+    Ok(entries
+        .chunks_exact(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+        .map(SignatureOffsets::parse)
+        .collect())
+}
+
+/// Verifies an ed25519 program instruction.
+pub fn verify_ed25519(
+    data: &[u8],
+    instruction_datas: &[&[u8]],
+    config: &PrecompileConfig,
+) -> Result<(), PrecompileError> {
+    for offsets in signature_offsets(data)? {
+        let signature = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.signature_instruction_index,
+            offsets.signature_offset,
+            64,
+        )?;
+        let signature = Ed25519Signature::from_slice(signature)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        let public_key = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.public_key_instruction_index,
+            offsets.public_key_offset,
+            32,
+        )?;
+        let public_key = Ed25519VerifyingKey::from_bytes(public_key.try_into().unwrap())
+            .map_err(|_| PrecompileError::InvalidPublicKey)?;
+        let message = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            usize::from(offsets.message_data_size),
+        )?;
+        let verified = if config.ed25519_verify_strict {
+            public_key.verify_strict(message, &signature)
+        } else {
+            public_key.verify(message, &signature)
+        };
+        verified.map_err(|_| PrecompileError::InvalidSignature)?;
+    }
+    Ok(())
+}
+
+/// Verifies a secp256k1 program instruction: every signature must recover
+/// to the public key of the Ethereum address next to it.
+pub fn verify_secp256k1(
+    data: &[u8],
+    instruction_datas: &[&[u8]],
+    _config: &PrecompileConfig,
+) -> Result<(), PrecompileError> {
+    let (&num_signatures, entries) = data
+        .split_first()
+        .ok_or(PrecompileError::InvalidInstructionDataSize)?;
+    if num_signatures == 0 && !entries.is_empty() {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    let entries = entries
+        .get(..usize::from(num_signatures) * SECP256K1_SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+        .ok_or(PrecompileError::InvalidInstructionDataSize)?;
+    // Unlike the other precompiles, secp256k1 offsets always name the
+    // instruction they point into.
+    let get_data_slice = |instruction_index: u8, offset: u16, size: usize| {
+        let instruction = instruction_datas
+            .get(usize::from(instruction_index))
+            .ok_or(PrecompileError::InvalidDataOffsets)?;
+        let start = usize::from(offset);
+        instruction
+            .get(start..start.saturating_add(size))
+            .ok_or(PrecompileError::InvalidSignature)
+    };
+
+    for entry in entries.chunks_exact(SECP256K1_SIGNATURE_OFFSETS_SERIALIZED_SIZE) {
+        let offsets = Secp256k1SignatureOffsets::parse(entry);
+        let signature_instruction = instruction_datas
+            .get(usize::from(offsets.signature_instruction_index))
+            .ok_or(PrecompileError::InvalidInstructionDataSize)?;
+        let start = usize::from(offsets.signature_offset);
+        // The recovery id follows the 64 signature bytes.
+        let signature = signature_instruction
+            .get(start..start + 65)
+            .ok_or(PrecompileError::InvalidSignature)?;
+        let recovery_id = libsecp256k1::RecoveryId::parse(signature[64])
+            .map_err(|_| PrecompileError::InvalidRecoveryId)?;
+        let signature = libsecp256k1::Signature::parse_standard_slice(&signature[..64])
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        let eth_address = get_data_slice(
+            offsets.eth_address_instruction_index,
+            offsets.eth_address_offset,
+            20,
+        )?;
+        let message = get_data_slice(
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            usize::from(offsets.message_data_size),
+        )?;
+        let message_hash = solana_keccak_hasher::hash(message).to_bytes();
+        let public_key = libsecp256k1::recover(
+            &libsecp256k1::Message::parse(&message_hash),
+            &signature,
+            &recovery_id,
+        )
+        .map_err(|_| PrecompileError::InvalidSignature)?;
+        if eth_address != construct_eth_address(&public_key) {
+            return Err(PrecompileError::InvalidSignature);
+        }
+    }
+    Ok(())
+}
+
+/// The Ethereum address of a secp256k1 public key: the last 20 bytes of
+/// the Keccak-256 hash of its uncompressed encoding.
+pub fn construct_eth_address(public_key: &libsecp256k1::PublicKey) -> [u8; 20] {
+    let hash = solana_keccak_hasher::hash(&public_key.serialize()[1..]).to_bytes();
+    hash[12..].try_into().unwrap()
+}
+
+/// Verifies a secp256r1 program instruction. Signatures are ECDSA over
+/// SHA-256 and must have a low S value; public keys are compressed.
+pub fn verify_secp256r1(
+    data: &[u8],
+    instruction_datas: &[&[u8]],
+    _config: &PrecompileConfig,
+) -> Result<(), PrecompileError> {
+    let num_signatures = data
+        .first()
+        .ok_or(PrecompileError::InvalidInstructionDataSize)?;
+    if *num_signatures == 0 || *num_signatures > SECP256R1_MAX_SIGNATURES {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    for offsets in signature_offsets(data)? {
+        let signature = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.signature_instruction_index,
+            offsets.signature_offset,
+            64,
+        )?;
+        let signature = Secp256r1Signature::from_slice(signature)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        if signature.normalize_s().is_some() {
+            // Only the low S form of a signature is accepted.
+            return Err(PrecompileError::InvalidSignature);
+        }
+        let public_key = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.public_key_instruction_index,
+            offsets.public_key_offset,
+            33,
+        )?;
+        let public_key = Secp256r1VerifyingKey::from_sec1_bytes(public_key)
+            .map_err(|_| PrecompileError::InvalidPublicKey)?;
+        let message = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            usize::from(offsets.message_data_size),
+        )?;
+        public_key
+            .verify(message, &signature)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+    }
+    Ok(())
+}
+
+/// Lays out an ed25519 or secp256r1 instruction checking one signature,
+/// with the public key, signature and message in its own data.
+fn new_signature_instruction(
+    program_id: Pubkey,
+    public_key: &[u8],
+    signature: &[u8; 64],
+    message: &[u8],
+) -> Instruction {
+    let public_key_offset = 2 + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    let signature_offset = public_key_offset + public_key.len();
+    let message_data_offset = signature_offset + signature.len();
+    let offsets = SignatureOffsets {
+        signature_offset: signature_offset as u16,
+        signature_instruction_index: CURRENT_INSTRUCTION,
+        public_key_offset: public_key_offset as u16,
+        public_key_instruction_index: CURRENT_INSTRUCTION,
+        message_data_offset: message_data_offset as u16,
+        message_data_size: message.len() as u16,
+        message_instruction_index: CURRENT_INSTRUCTION,
+    };
+    let mut data = vec![1, 0];
+    data.extend_from_slice(&offsets.serialize());
+    data.extend_from_slice(public_key);
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+    Instruction::new_with_bytes(program_id, &data, vec![])
+}
+
+/// Builds an ed25519 program instruction checking `signature` of `message`
+/// by `public_key`.
+pub fn new_ed25519_instruction(
+    public_key: &[u8; 32],
+    signature: &[u8; 64],
+    message: &[u8],
+) -> Instruction {
+    new_signature_instruction(ed25519_program::ID, public_key, signature, message)
+}
+
+/// Builds a secp256r1 program instruction checking `signature` of
+/// `message` by the compressed `public_key`.
+pub fn new_secp256r1_instruction(
+    public_key: &[u8; 33],
+    signature: &[u8; 64],
+    message: &[u8],
+) -> Instruction {
+    new_signature_instruction(secp256r1_program::ID, public_key, signature, message)
+}
+
+/// Builds a secp256k1 program instruction checking that `signature`, with
+/// `recovery_id`, signs `message` for `eth_address`. The instruction must be
+/// the transaction's instruction at `instruction_index`.
+pub fn new_secp256k1_instruction(
+    eth_address: &[u8; 20],
+    signature: &[u8; 64],
+    recovery_id: u8,
+    message: &[u8],
+    instruction_index: u8,
+) -> Instruction {
+    let eth_address_offset = 1 + SECP256K1_SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    let signature_offset = eth_address_offset + eth_address.len();
+    let message_data_offset = signature_offset + signature.len() + 1;
+    let offsets = Secp256k1SignatureOffsets {
+        signature_offset: signature_offset as u16,
+        signature_instruction_index: instruction_index,
+        eth_address_offset: eth_address_offset as u16,
+        eth_address_instruction_index: instruction_index,
+        message_data_offset: message_data_offset as u16,
+        message_data_size: message.len() as u16,
+        message_instruction_index: instruction_index,
+    };
+    let mut data = vec![1];
+    data.extend_from_slice(&offsets.serialize());
+    data.extend_from_slice(eth_address);
+    data.extend_from_slice(signature);
+    data.push(recovery_id);
+    data.extend_from_slice(message);
+    Instruction::new_with_bytes(secp256k1_program::ID, &data, vec![])
+}
@@ -0,0 +1,204 @@
+//! Rent: what an account must hold to stay in the store.
+//!
+//! An account whose balance covers [`Rent::minimum_balance`] for its data
+//! length is rent exempt and never pays anything. Transactions may not
+//! leave an account they modified in a worse rent state than they found
+//! it, which [`RentState::check_transition`] enforces after execution.
+//! Accounts that are still below the minimum can be charged at epoch
+//! boundaries through a [`RentCollector`] sweep.
+
+use {
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_clock::{Epoch, DEFAULT_MS_PER_SLOT, SECONDS_PER_DAY},
+    solana_epoch_schedule::EpochSchedule,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::incinerator,
+    solana_transaction_error::TransactionError,
+    std::ops::AddAssign,
+};
+
+/// `rent_epoch` of accounts that are exempt and never collected from.
+pub const RENT_EXEMPT_RENT_EPOCH: Epoch = Epoch::MAX;
+
+/// Slots in a year of 365.25 days at [`DEFAULT_MS_PER_SLOT`].
+pub const SLOTS_PER_YEAR: u64 = SECONDS_PER_DAY * 36_525 / 100 * 1_000 / DEFAULT_MS_PER_SLOT;
+
+/// Rent state of a single account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RentState {
+    /// The account holds no lamports.
+    Uninitialized,
+    /// The account holds lamports, but fewer than its rent-exempt minimum.
+    RentPaying { lamports: u64, data_size: usize },
+    /// The account holds at least its rent-exempt minimum.
+    RentExempt,
+}
+
+impl RentState {
+    pub fn from_account(account: &AccountSharedData, rent: &Rent) -> Self {
+        if account.lamports() == 0 {
+            Self::Uninitialized
+        } else if rent.is_exempt(account.lamports(), account.data().len()) {
+            Self::RentExempt
+        } else {
+            Self::RentPaying {
+                lamports: account.lamports(),
+                data_size: account.data().len(),
+            }
+        }
+    }
+
+    /// Whether an account may go from `self` to `post`.
+    ///
+    /// Ending up uninitialized or rent exempt is always fine. An account
+    /// may only end up rent paying if it already was, kept its data size
+    /// and did not gain lamports, so a transaction can never make a new
+    /// account rent paying or top up one that should be closed.
+    pub fn transition_allowed(&self, post: &Self) -> bool {
+        match post {
+            Self::Uninitialized | Self::RentExempt => true,
+            Self::RentPaying {
+                lamports: post_lamports,
+                data_size: post_data_size,
+            } => match self {
+                Self::Uninitialized | Self::RentExempt => false,
+                Self::RentPaying {
+                    lamports: pre_lamports,
+                    data_size: pre_data_size,
+                } => post_data_size == pre_data_size && post_lamports <= pre_lamports,
+            },
+        }
+    }
+
+    /// Checks the rent state transition of the account at `account_index`
+    /// of a transaction.
+    ///
+    /// Fails with [`TransactionError::InsufficientFundsForRent`] if the
+    /// transition is not allowed. The incinerator is exempt from the check
+    /// since everything sent to it is burned.
+    pub fn check_transition(
+        pubkey: &Pubkey,
+        pre: &AccountSharedData,
+        post: &AccountSharedData,
+        rent: &Rent,
+        account_index: usize,
+    ) -> Result<(), TransactionError> {
+        if *pubkey == incinerator::id() {
+            return Ok(());
+        }
+        let pre_state = Self::from_account(pre, rent);
+        let post_state = Self::from_account(post, rent);
+        if pre_state.transition_allowed(&post_state) {
+            Ok(())
+        } else {
+            Err(TransactionError::InsufficientFundsForRent {
+                account_index: account_index as u8,
+            })
+        }
+    }
+}
+
+/// What a rent collection took from the accounts it swept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CollectedInfo {
+    /// Lamports collected.
+    pub rent_amount: u64,
+    /// Data bytes freed by accounts that could not pay and were closed.
+    pub account_data_len_reclaimed: u64,
+}
+
+impl AddAssign for CollectedInfo {
+    fn add_assign(&mut self, other: Self) {
+        self.rent_amount = self.rent_amount.saturating_add(other.rent_amount);
+        self.account_data_len_reclaimed = self
+            .account_data_len_reclaimed
+            .saturating_add(other.account_data_len_reclaimed);
+    }
+}
+
+/// Charges rent to the accounts that are not rent exempt.
+///
+/// An account pays, for every slot since its `rent_epoch`, the share of its
+/// rent-exempt minimum that one slot is of [`SLOTS_PER_YEAR`]. Collecting
+/// pays up to the end of the current epoch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RentCollector {
+    pub epoch: Epoch,
+    pub epoch_schedule: EpochSchedule,
+    pub rent: Rent,
+}
+
+impl RentCollector {
+    pub fn new(epoch: Epoch, epoch_schedule: EpochSchedule, rent: Rent) -> Self {
+        Self {
+            epoch,
+            epoch_schedule,
+            rent,
+        }
+    }
+
+    /// Rent `account` owes up to the end of the current epoch, capped at
+    /// its balance.
+    pub fn rent_due(&self, account: &AccountSharedData) -> u64 {
+        if account.executable()
+            || account.rent_epoch() > self.epoch
+            || self
+                .rent
+                .is_exempt(account.lamports(), account.data().len())
+        {
+            return 0;
+        }
+        let slots = self
+            .epoch_schedule
+            .get_first_slot_in_epoch(self.epoch + 1)
+            .saturating_sub(
+                self.epoch_schedule
+                    .get_first_slot_in_epoch(account.rent_epoch()),
+            );
+        let minimum_balance = self.rent.minimum_balance(account.data().len());
+        let due = u128::from(minimum_balance) * u128::from(slots) / u128::from(SLOTS_PER_YEAR);
+        u64::try_from(due)
+            .unwrap_or(u64::MAX)
+            .min(account.lamports())
+    }
+
+    /// Collects the rent `account` owes and moves its `rent_epoch` past the
+    /// current epoch.
+    ///
+    /// Rent exempt accounts are marked with [`RENT_EXEMPT_RENT_EPOCH`] and
+    /// pay nothing. An account that cannot pay in full is emptied, and the
+    /// store purges it.
+    pub fn collect_from_existing_account(
+        &self,
+        pubkey: &Pubkey,
+        account: &mut AccountSharedData,
+    ) -> CollectedInfo {
+        if *pubkey == incinerator::id() || account.executable() {
+            return CollectedInfo::default();
+        }
+        if self
+            .rent
+            .is_exempt(account.lamports(), account.data().len())
+        {
+            account.set_rent_epoch(RENT_EXEMPT_RENT_EPOCH);
+            return CollectedInfo::default();
+        }
+
+        let rent_amount = self.rent_due(account);
+        if rent_amount == account.lamports() {
+            let account_data_len_reclaimed = account.data().len() as u64;
+            *account = AccountSharedData::default();
+            return CollectedInfo {
+                rent_amount,
+                account_data_len_reclaimed,
+            };
+        }
+        account.set_lamports(account.lamports() - rent_amount);
+        account.set_rent_epoch(account.rent_epoch().max(self.epoch + 1));
+        CollectedInfo {
+            rent_amount,
+            account_data_len_reclaimed: 0,
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! Replay of confirmed blocks.
+//!
+//! A [`BlockFixture`] holds the transactions of a block together with the
+//! accounts they touch as they were before the block. [`replay_block`]
+//! executes the transactions through a [`Bank`] set up at the block's slot
+//! and compares every transaction's status, fee and balances with what the
+//! chain recorded, so disagreements between this runtime and a real
+//! cluster show up as a [`ReplayReport`] instead of going unnoticed.
+//!
+//! Fixtures are JSON in the shapes RPC returns, so they can be assembled
+//! from real responses: `transactions` is the `transactions` array of
+//! `getBlock` with the `base64` encoding and full transaction details, and
+//! `accounts` pairs every pubkey with its account as `getMultipleAccounts`
+//! reports it with the `base64` encoding:
+//!
+//! ```json
+//! {
+//!   "slot": 250000000,
+//!   "accounts": [
+//!     {
+//!       "pubkey": "...",
+//!       "account": {
+//!         "lamports": 1000000, "data": ["", "base64"], "owner": "...",
+//!         "executable": false, "rentEpoch": 0
+//!       }
+//!     }
+//!   ],
+//!   "transactions": [
+//!     {
+//!       "transaction": ["...", "base64"],
+//!       "meta": { "err": null, "fee": 5000, "preBalances": [], "postBalances": [] }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! A fixture directory holds the fixture as [`FIXTURE_FILE`], which
+//! [`BlockFixture::load_dir`] reads; the `fetch` feature downloads such
+//! directories from a cluster.
+
+use {
+    crate::{accounts_db::AccountsDb, bank::Bank, feature_set::FeatureSet},
+    base64::{prelude::BASE64_STANDARD, Engine},
+    serde_json::Value,
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    solana_transaction::versioned::VersionedTransaction,
+    solana_transaction_error::TransactionError,
+    std::{fs, io, path::Path, str::FromStr},
+};
+
+/// Name of the fixture in a fixture directory.
+pub const FIXTURE_FILE: &str = "fixture.json";
+
+/// Why a fixture could not be read.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ReplayError {
+    #[error("fixture could not be read: {0}")]
+    Io(io::ErrorKind),
+    #[error("fixture is not valid JSON")]
+    InvalidJson,
+    #[error("missing or malformed field `{0}`")]
+    InvalidField(String),
+    #[error("transaction {0} is not a valid transaction")]
+    InvalidTransaction(usize),
+}
+
+/// What the chain recorded about a transaction of the block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedTransaction {
+    pub transaction: VersionedTransaction,
+    /// The `err` of the transaction's `meta`, `None` if it succeeded.
+    pub err: Option<TransactionError>,
+    pub fee: u64,
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+}
+
+/// A block to replay: its transactions in block order and the accounts they
+/// touch as of the start of the block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockFixture {
+    pub slot: Slot,
+    pub accounts: Vec<(Pubkey, AccountSharedData)>,
+    pub transactions: Vec<RecordedTransaction>,
+}
+
+impl BlockFixture {
+    /// Reads a fixture in the JSON format of the [module](self) docs.
+    pub fn from_json(json: &str) -> Result<Self, ReplayError> {
+        let fixture: Value = serde_json::from_str(json).map_err(|_| ReplayError::InvalidJson)?;
+        let slot = field(&fixture, "slot")?
+            .as_u64()
+            .ok_or_else(|| invalid("slot"))?;
+        let accounts = array(&fixture, "accounts")?
+            .iter()
+            .map(parse_keyed_account)
+            .collect::<Result<_, _>>()?;
+        let transactions = array(&fixture, "transactions")?
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| parse_recorded_transaction(index, entry))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            slot,
+            accounts,
+            transactions,
+        })
+    }
+
+    /// Reads the [`FIXTURE_FILE`] of the fixture directory `dir`.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let json = fs::read_to_string(dir.as_ref().join(FIXTURE_FILE))
+            .map_err(|err| ReplayError::Io(err.kind()))?;
+        Self::from_json(&json)
+    }
+}
+
+/// A balance that differs from the recorded one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BalanceMismatch {
+    /// Index of the account in the message, loaded addresses included.
+    pub index: usize,
+    pub pubkey: Pubkey,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+/// How the replay of one transaction compares with the chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionReplay {
+    pub signature: Signature,
+    /// The recorded `err`, and the one of the replay. A transaction the
+    /// bank rejected before executing it has an `err` even though the chain
+    /// recorded none, since rejected transactions do not make it into
+    /// blocks.
+    pub expected_err: Option<TransactionError>,
+    pub actual_err: Option<TransactionError>,
+    pub expected_fee: u64,
+    pub actual_fee: u64,
+    /// Balances the transaction was loaded with that differ from the
+    /// recorded ones, which points at missing or stale fixture accounts
+    /// rather than at execution.
+    pub pre_balance_mismatches: Vec<BalanceMismatch>,
+    pub post_balance_mismatches: Vec<BalanceMismatch>,
+}
+
+impl TransactionReplay {
+    pub fn is_match(&self) -> bool {
+        self.expected_err == self.actual_err
+            && self.expected_fee == self.actual_fee
+            && self.pre_balance_mismatches.is_empty()
+            && self.post_balance_mismatches.is_empty()
+    }
+}
+
+/// The replay of every transaction of a block, in block order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub transactions: Vec<TransactionReplay>,
+}
+
+impl ReplayReport {
+    /// Whether every transaction replayed the way the chain recorded it.
+    pub fn is_match(&self) -> bool {
+        self.transactions.iter().all(TransactionReplay::is_match)
+    }
+
+    /// The transactions that did not.
+    pub fn mismatches(&self) -> impl Iterator<Item = &TransactionReplay> {
+        self.transactions
+            .iter()
+            .filter(|transaction| !transaction.is_match())
+    }
+}
+
+/// Replays `fixture` with the features active on mainnet-beta at its slot.
+pub fn replay_block(fixture: &BlockFixture) -> ReplayReport {
+    replay_block_with_features(fixture, FeatureSet::mainnet_beta_as_of(fixture.slot))
+}
+
+/// Replays `fixture` with `feature_set`.
+///
+/// The bank starts at the block's slot with the fixture's accounts, and
+/// knows the recent blockhash of every transaction. Transactions are
+/// handed to the bank one at a time in block order: the scheduler orders
+/// conflicting transactions by priority, which could differ from the order
+/// the leader executed them in, and so would the balances.
+/// Signatures are not verified; the cluster already did.
+pub fn replay_block_with_features(fixture: &BlockFixture, feature_set: FeatureSet) -> ReplayReport {
+    let mut accounts_db = AccountsDb::new();
+    for (pubkey, account) in &fixture.accounts {
+        accounts_db.store_account(*pubkey, account.clone());
+    }
+    let mut bank = Bank::new(accounts_db);
+    bank.warp_to_slot(fixture.slot);
+    bank.set_feature_set(feature_set);
+    for recorded in &fixture.transactions {
+        bank.register_recent_blockhash(*recorded.transaction.message.recent_blockhash());
+    }
+
+    let transactions = fixture
+        .transactions
+        .iter()
+        .map(|recorded| replay_transaction(&mut bank, recorded))
+        .collect();
+    ReplayReport { transactions }
+}
+
+fn replay_transaction(bank: &mut Bank, recorded: &RecordedTransaction) -> TransactionReplay {
+    let signature = recorded.transaction.signatures[0];
+    let result = bank
+        .resolve_transaction(recorded.transaction.clone())
+        .and_then(|transaction| {
+            let account_keys: Vec<Pubkey> = transaction
+                .message()
+                .account_keys()
+                .iter()
+                .copied()
+                .collect();
+            bank.process_transaction_batch(&[transaction])
+                .remove(0)
+                .map(|result| (account_keys, result))
+        });
+    let mut replay = TransactionReplay {
+        signature,
+        expected_err: recorded.err.clone(),
+        actual_err: None,
+        expected_fee: recorded.fee,
+        actual_fee: 0,
+        pre_balance_mismatches: Vec::new(),
+        post_balance_mismatches: Vec::new(),
+    };
+    match result {
+        Ok((account_keys, result)) => {
+            replay.actual_err = result.status.clone().err();
+            replay.actual_fee = result.fee_details.total_fee();
+            replay.pre_balance_mismatches =
+                balance_mismatches(&account_keys, &recorded.pre_balances, &result.pre_balances);
+            let post_balances: Vec<u64> = result
+                .post_accounts
+                .iter()
+                .map(|(_, account)| account.lamports())
+                .collect();
+            replay.post_balance_mismatches =
+                balance_mismatches(&account_keys, &recorded.post_balances, &post_balances);
+        }
+        Err(err) => replay.actual_err = Some(err),
+    }
+    replay
+}
+
+fn balance_mismatches(
+    account_keys: &[Pubkey],
+    expected: &[u64],
+    actual: &[u64],
+) -> Vec<BalanceMismatch> {
+    account_keys
+        .iter()
+        .zip(expected.iter().zip(actual))
+        .enumerate()
+        .filter(|(_, (_, (expected, actual)))| expected != actual)
+        .map(|(index, (pubkey, (expected, actual)))| BalanceMismatch {
+            index,
+            pubkey: *pubkey,
+            expected: *expected,
+            actual: *actual,
+        })
+        .collect()
+}
+
+fn invalid(name: &str) -> ReplayError {
+    ReplayError::InvalidField(name.to_string())
+}
+
+fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value, ReplayError> {
+    value.get(name).ok_or_else(|| invalid(name))
+}
+
+fn array<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, ReplayError> {
+    field(value, name)?.as_array().ok_or_else(|| invalid(name))
+}
+
+fn u64_array(value: &Value, name: &str) -> Result<Vec<u64>, ReplayError> {
+    array(value, name)?
+        .iter()
+        .map(|element| element.as_u64().ok_or_else(|| invalid(name)))
+        .collect()
+}
+
+fn pubkey(value: &Value, name: &str) -> Result<Pubkey, ReplayError> {
+    field(value, name)?
+        .as_str()
+        .and_then(|string| Pubkey::from_str(string).ok())
+        .ok_or_else(|| invalid(name))
+}
+
+/// Decodes a `[data, "base64"]` pair.
+fn base64_data(value: &Value, name: &str) -> Result<Vec<u8>, ReplayError> {
+    match array(value, name)?.as_slice() {
+        [Value::String(data), encoding] if encoding == "base64" => {
+            BASE64_STANDARD.decode(data).map_err(|_| invalid(name))
+        }
+        _ => Err(invalid(name)),
+    }
+}
+
+fn parse_keyed_account(entry: &Value) -> Result<(Pubkey, AccountSharedData), ReplayError> {
+    let account = field(entry, "account")?;
+    let lamports = field(account, "lamports")?
+        .as_u64()
+        .ok_or_else(|| invalid("lamports"))?;
+    let mut shared = AccountSharedData::new(lamports, 0, &pubkey(account, "owner")?);
+    shared.set_data_from_slice(&base64_data(account, "data")?);
+    shared.set_executable(
+        field(account, "executable")?
+            .as_bool()
+            .ok_or_else(|| invalid("executable"))?,
+    );
+    if let Some(rent_epoch) = account.get("rentEpoch").and_then(Value::as_u64) {
+        shared.set_rent_epoch(rent_epoch);
+    }
+    Ok((pubkey(entry, "pubkey")?, shared))
+}
+
+fn parse_recorded_transaction(
+    index: usize,
+    entry: &Value,
+) -> Result<RecordedTransaction, ReplayError> {
+    let transaction: VersionedTransaction =
+        bincode::deserialize(&base64_data(entry, "transaction")?)
+            .map_err(|_| ReplayError::InvalidTransaction(index))?;
+    if transaction.signatures.is_empty() {
+        return Err(ReplayError::InvalidTransaction(index));
+    }
+    let meta = field(entry, "meta")?;
+    Ok(RecordedTransaction {
+        transaction,
+        err: serde_json::from_value(field(meta, "err")?.clone()).map_err(|_| invalid("err"))?,
+        fee: field(meta, "fee")?.as_u64().ok_or_else(|| invalid("fee"))?,
+        pre_balances: u64_array(meta, "preBalances")?,
+        post_balances: u64_array(meta, "postBalances")?,
+    })
+}
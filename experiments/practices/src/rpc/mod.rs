@@ -0,0 +1,483 @@
+//! A JSON-RPC server over a [`Bank`], enabled by the `rpc` feature.
+//!
+//! [`JsonRpcService`] answers the handful of methods wallets and client
+//! SDKs need to treat the VM like a local test validator:
+//!
+//! - `sendTransaction` verifies the transaction's signatures, then has the
+//!   bank schedule, execute and commit it right away. It answers with the
+//!   signature as long as the transaction executed, even if it failed.
+//! - `simulateTransaction` runs it without committing anything.
+//! - `getAccountInfo`, `getBalance` and `getLatestBlockhash` read the
+//!   bank.
+//! - `getProgramAccounts` and `getTokenAccountsByOwner` scan it through the
+//!   secondary indexes of its [`AccountsDb`](crate::accounts_db::AccountsDb).
+//!
+//! Requests and responses follow the JSON-RPC 2.0 shapes of the validator's
+//! RPC, batches included, so existing clients can talk to the server
+//! unchanged. The request handling itself lives in
+//! [`JsonRpcRequestProcessor`], which needs no socket. Subscriptions over
+//! WebSocket live in [`pubsub`].
+
+pub mod pubsub;
+
+use {
+    crate::{
+        bank::Bank,
+        sanitize::deserialize_transaction,
+        sigverify::verify_transaction,
+        svm::AccountOverrides,
+        token_program::{self, TokenAccount},
+    },
+    base64::{prelude::BASE64_STANDARD, Engine},
+    serde_json::{json, Value},
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_clock::MAX_PROCESSING_AGE,
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+    std::{
+        io,
+        net::{SocketAddr, ToSocketAddrs},
+        str::FromStr,
+        sync::{Arc, Mutex, MutexGuard},
+        thread::{self, JoinHandle},
+    },
+    tiny_http::{Header, Method, Response, Server},
+};
+
+/// The request body is not valid JSON.
+pub const JSON_RPC_PARSE_ERROR: i64 = -32700;
+/// The request is not a JSON-RPC request object.
+pub const JSON_RPC_INVALID_REQUEST: i64 = -32600;
+pub const JSON_RPC_METHOD_NOT_FOUND: i64 = -32601;
+pub const JSON_RPC_INVALID_PARAMS: i64 = -32602;
+/// `sendTransaction` was rejected before execution, matching the
+/// validator's preflight failure code.
+pub const JSON_RPC_SEND_TRANSACTION_FAILURE: i64 = -32002;
+/// `sendTransaction` got a transaction whose signatures do not verify.
+pub const JSON_RPC_SIGNATURE_VERIFICATION_FAILURE: i64 = -32003;
+
+/// An error answer to a single request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(JSON_RPC_INVALID_PARAMS, message)
+    }
+}
+
+type RpcResult = Result<Value, RpcError>;
+
+/// Answers JSON-RPC requests against a shared bank.
+#[derive(Clone)]
+pub struct JsonRpcRequestProcessor {
+    bank: Arc<Mutex<Bank>>,
+}
+
+impl JsonRpcRequestProcessor {
+    pub fn new(bank: Arc<Mutex<Bank>>) -> Self {
+        Self { bank }
+    }
+
+    /// Answers a request body holding a single request or a batch of them.
+    pub fn process(&self, body: &str) -> String {
+        let response = match serde_json::from_str::<Value>(body) {
+            Ok(Value::Array(requests)) if !requests.is_empty() => Value::Array(
+                requests
+                    .iter()
+                    .map(|request| self.process_request(request))
+                    .collect(),
+            ),
+            Ok(request) => self.process_request(&request),
+            Err(err) => error_response(
+                &Value::Null,
+                RpcError::new(JSON_RPC_PARSE_ERROR, format!("Parse error: {err}")),
+            ),
+        };
+        response.to_string()
+    }
+
+    /// Answers a single request object.
+    pub fn process_request(&self, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let Some(method) = request
+            .get("method")
+            .and_then(Value::as_str)
+            .filter(|_| request.get("jsonrpc").and_then(Value::as_str) == Some("2.0"))
+        else {
+            return error_response(
+                &id,
+                RpcError::new(JSON_RPC_INVALID_REQUEST, "Invalid request"),
+            );
+        };
+        let params = match request.get("params") {
+            None | Some(Value::Null) => &[][..],
+            Some(Value::Array(params)) => params.as_slice(),
+            Some(_) => {
+                return error_response(&id, RpcError::invalid_params("params must be an array"))
+            }
+        };
+
+        let result = match method {
+            "getAccountInfo" => self.get_account_info(params),
+            "getBalance" => self.get_balance(params),
+            "getLatestBlockhash" => self.get_latest_blockhash(),
+            "getProgramAccounts" => self.get_program_accounts(params),
+            "getTokenAccountsByOwner" => self.get_token_accounts_by_owner(params),
+            "sendTransaction" => self.send_transaction(params),
+            "simulateTransaction" => self.simulate_transaction(params),
+            _ => Err(RpcError::new(
+                JSON_RPC_METHOD_NOT_FOUND,
+                format!("Method not found: {method}"),
+            )),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(err) => error_response(&id, err),
+        }
+    }
+
+    fn bank(&self) -> MutexGuard<'_, Bank> {
+        self.bank.lock().expect("bank lock is never poisoned")
+    }
+
+    /// `[pubkey, { encoding }]`, answering with the account or `null`.
+    /// Data is encoded as base64 unless `encoding` asks for base58.
+    fn get_account_info(&self, params: &[Value]) -> RpcResult {
+        let pubkey = pubkey_param(params)?;
+        let encoding = encoding_param(config_param(params)?, "base64")?;
+        let bank = self.bank();
+        let value = bank
+            .get_account(&pubkey)
+            .map(|account| account_json(&account, encoding));
+        Ok(with_context(&bank, value.into()))
+    }
+
+    /// `[programId, { encoding, withContext }]`, answering with every
+    /// account the program owns, wrapped in a context if `withContext` is
+    /// true.
+    fn get_program_accounts(&self, params: &[Value]) -> RpcResult {
+        let program_id = pubkey_param(params)?;
+        let config = config_param(params)?;
+        let encoding = encoding_param(config, "base64")?;
+        let with_context_param = config
+            .and_then(|config| config.get("withContext"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let bank = self.bank();
+        let accounts = bank.accounts_db().get_program_accounts(&program_id);
+        let value = keyed_accounts_json(&accounts, encoding);
+        Ok(match with_context_param {
+            true => with_context(&bank, value),
+            false => value,
+        })
+    }
+
+    /// `[owner, { mint } | { programId }, { encoding }]`, answering with
+    /// the token accounts of `owner`, of one mint or of the Token program.
+    fn get_token_accounts_by_owner(&self, params: &[Value]) -> RpcResult {
+        let owner = pubkey_param(params)?;
+        let filter = config_param(params)?
+            .ok_or_else(|| RpcError::invalid_params("missing mint or programId filter"))?;
+        let filter_pubkey = |field: &str| {
+            filter
+                .get(field)
+                .map(|value| {
+                    value
+                        .as_str()
+                        .and_then(|value| value.parse::<Pubkey>().ok())
+                        .ok_or_else(|| RpcError::invalid_params(format!("Invalid param: {field}")))
+                })
+                .transpose()
+        };
+        let mint = match (filter_pubkey("mint")?, filter_pubkey("programId")?) {
+            (Some(mint), None) => Some(mint),
+            (None, Some(program_id)) if program_id == token_program::ID => None,
+            (None, Some(_)) => {
+                return Err(RpcError::invalid_params(
+                    "Invalid param: unrecognized Token program id",
+                ))
+            }
+            _ => {
+                return Err(RpcError::invalid_params(
+                    "filter must hold exactly one of mint and programId",
+                ))
+            }
+        };
+        let encoding = encoding_param(config_param_at(params, 2)?, "base64")?;
+        let bank = self.bank();
+        let mut accounts = bank.accounts_db().get_token_accounts_by_owner(&owner);
+        if let Some(mint) = mint {
+            accounts.retain(|(_, account)| {
+                TokenAccount::unpack(account.data()).is_ok_and(|account| account.mint == mint)
+            });
+        }
+        Ok(with_context(
+            &bank,
+            keyed_accounts_json(&accounts, encoding),
+        ))
+    }
+
+    /// `[pubkey]`, answering with its lamports, zero if it does not exist.
+    fn get_balance(&self, params: &[Value]) -> RpcResult {
+        let pubkey = pubkey_param(params)?;
+        let bank = self.bank();
+        let lamports = bank
+            .get_account(&pubkey)
+            .map_or(0, |account| account.lamports());
+        Ok(with_context(&bank, lamports.into()))
+    }
+
+    /// The bank's last blockhash. Its last valid block height counts slots,
+    /// since every slot registers a blockhash.
+    fn get_latest_blockhash(&self) -> RpcResult {
+        let bank = self.bank();
+        let value = json!({
+            "blockhash": bank.last_blockhash().to_string(),
+            "lastValidBlockHeight": bank.slot() + MAX_PROCESSING_AGE as u64,
+        });
+        Ok(with_context(&bank, value))
+    }
+
+    /// `[transaction, { encoding }]`, with the transaction encoded as
+    /// base58 unless `encoding` says base64.
+    fn send_transaction(&self, params: &[Value]) -> RpcResult {
+        let mut bank = self.bank();
+        let transaction = transaction_param(&bank, params)?;
+        verify_transaction(&transaction).map_err(|err| {
+            RpcError::new(
+                JSON_RPC_SIGNATURE_VERIFICATION_FAILURE,
+                format!("Transaction signature verification failure: {err}"),
+            )
+        })?;
+        let signature = *transaction.signature();
+        match bank.process_transaction_batch(&[transaction]).remove(0) {
+            Ok(_) => Ok(signature.to_string().into()),
+            Err(err) => Err(RpcError::new(
+                JSON_RPC_SEND_TRANSACTION_FAILURE,
+                format!("Transaction rejected: {err}"),
+            )),
+        }
+    }
+
+    /// `[transaction, { encoding, sigVerify }]`. Signatures are only
+    /// checked when `sigVerify` is true.
+    fn simulate_transaction(&self, params: &[Value]) -> RpcResult {
+        let bank = self.bank();
+        let transaction = transaction_param(&bank, params)?;
+        let sig_verify = config_param(params)?
+            .and_then(|config| config.get("sigVerify"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let result = match sig_verify {
+            true => verify_transaction(&transaction),
+            false => Ok(()),
+        }
+        .map(|()| bank.simulate_transaction(&transaction, &AccountOverrides::default()));
+
+        let value = match result {
+            Ok(result) => json!({
+                "err": result.status.err(),
+                "logs": result.log_messages,
+                "accounts": null,
+                "unitsConsumed": result.units_consumed,
+                "fee": result.fee_details.total_fee(),
+                "returnData": result.return_data.map(|return_data| json!({
+                    "programId": return_data.program_id.to_string(),
+                    "data": [BASE64_STANDARD.encode(&return_data.data), "base64"],
+                })),
+            }),
+            Err(err) => simulation_rejected(err),
+        };
+        Ok(with_context(&bank, value))
+    }
+}
+
+/// Serves a [`JsonRpcRequestProcessor`] over HTTP on a thread of its own.
+///
+/// Every request is a `POST` whose body is the JSON-RPC request. The
+/// server stops when the service is dropped.
+pub struct JsonRpcService {
+    local_addr: SocketAddr,
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl JsonRpcService {
+    /// Starts serving `bank` on `addr`. Port 0 picks a free port, which
+    /// [`local_addr`](Self::local_addr) reports.
+    pub fn new(bank: Arc<Mutex<Bank>>, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let server = Arc::new(Server::http(addr).map_err(io::Error::other)?);
+        let local_addr = server
+            .server_addr()
+            .to_ip()
+            .expect("the server listens on an IP address");
+        let processor = JsonRpcRequestProcessor::new(bank);
+        let thread = thread::Builder::new().name("rpc".to_string()).spawn({
+            let server = server.clone();
+            move || serve(&server, &processor)
+        })?;
+        Ok(Self {
+            local_addr,
+            server,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for JsonRpcService {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(server: &Server, processor: &JsonRpcRequestProcessor) {
+    let content_type =
+        Header::from_str("Content-Type: application/json").expect("the header is valid");
+    for mut request in server.incoming_requests() {
+        if *request.method() != Method::Post {
+            let _ = request.respond(Response::empty(405));
+            continue;
+        }
+        let mut body = String::new();
+        let response = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => processor.process(&body),
+            Err(err) => error_response(
+                &Value::Null,
+                RpcError::new(JSON_RPC_PARSE_ERROR, format!("Parse error: {err}")),
+            )
+            .to_string(),
+        };
+        // A client that hung up misses its answer; the next one does not.
+        let _ = request.respond(Response::from_string(response).with_header(content_type.clone()));
+    }
+}
+
+fn error_response(id: &Value, err: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": err.code, "message": err.message },
+        "id": id,
+    })
+}
+
+/// Wraps `value` the way RPC answers methods that read a bank.
+fn with_context(bank: &Bank, value: Value) -> Value {
+    json!({ "context": { "slot": bank.slot() }, "value": value })
+}
+
+fn simulation_rejected(err: TransactionError) -> Value {
+    json!({
+        "err": err,
+        "logs": [],
+        "accounts": null,
+        "unitsConsumed": 0,
+        "fee": 0,
+        "returnData": null,
+    })
+}
+
+fn account_json(account: &AccountSharedData, encoding: &str) -> Value {
+    let data = match encoding {
+        "base58" => bs58::encode(account.data()).into_string(),
+        _ => BASE64_STANDARD.encode(account.data()),
+    };
+    json!({
+        "data": [data, encoding],
+        "executable": account.executable(),
+        "lamports": account.lamports(),
+        "owner": account.owner().to_string(),
+        "rentEpoch": account.rent_epoch(),
+        "space": account.data().len(),
+    })
+}
+
+fn keyed_accounts_json(accounts: &[(Pubkey, AccountSharedData)], encoding: &str) -> Value {
+    accounts
+        .iter()
+        .map(|(pubkey, account)| {
+            json!({
+                "pubkey": pubkey.to_string(),
+                "account": account_json(account, encoding),
+            })
+        })
+        .collect()
+}
+
+fn pubkey_param(params: &[Value]) -> Result<Pubkey, RpcError> {
+    params
+        .first()
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params("missing pubkey"))?
+        .parse()
+        .map_err(|err| RpcError::invalid_params(format!("Invalid param: {err}")))
+}
+
+/// The optional config object after the first param.
+fn config_param(params: &[Value]) -> Result<Option<&Value>, RpcError> {
+    config_param_at(params, 1)
+}
+
+/// The optional config object at `index`.
+fn config_param_at(params: &[Value], index: usize) -> Result<Option<&Value>, RpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(config @ Value::Object(_)) => Ok(Some(config)),
+        Some(_) => Err(RpcError::invalid_params("config must be an object")),
+    }
+}
+
+/// The encoding `config` asks for, or `default`.
+fn encoding_param<'a>(config: Option<&'a Value>, default: &'a str) -> Result<&'a str, RpcError> {
+    let encoding =
+        config
+            .and_then(|config| config.get("encoding"))
+            .map_or(Ok(default), |encoding| {
+                encoding
+                    .as_str()
+                    .ok_or_else(|| RpcError::invalid_params("encoding must be a string"))
+            })?;
+    match encoding {
+        "base58" | "base64" => Ok(encoding),
+        _ => Err(RpcError::invalid_params(format!(
+            "Unsupported encoding: {encoding}"
+        ))),
+    }
+}
+
+/// Decodes the wire transaction in the first param and sanitizes it
+/// against `bank`.
+fn transaction_param(bank: &Bank, params: &[Value]) -> Result<SanitizedTransaction, RpcError> {
+    let encoded = params
+        .first()
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params("missing transaction"))?;
+    let bytes = match encoding_param(config_param(params)?, "base58")? {
+        "base58" => bs58::decode(encoded).into_vec().ok(),
+        _ => BASE64_STANDARD.decode(encoded).ok(),
+    }
+    .ok_or_else(|| RpcError::invalid_params("invalid transaction encoding"))?;
+    let transaction = deserialize_transaction(&bytes).map_err(|err| {
+        RpcError::invalid_params(format!("failed to deserialize transaction: {err}"))
+    })?;
+    bank.resolve_transaction(transaction)
+        .map_err(|err| RpcError::invalid_params(format!("invalid transaction: {err}")))
+}
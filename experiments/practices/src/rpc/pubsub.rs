@@ -0,0 +1,403 @@
+//! WebSocket subscriptions, as the validator's pubsub service offers them.
+//!
+//! [`RpcSubscriptions`] is an [`AccountsNotifier`]: once attached to a bank
+//! with [`Bank::add_notifier`](crate::bank::Bank::add_notifier), it turns
+//! what the bank commits into `accountNotification`,
+//! `signatureNotification` and `slotNotification` messages for whoever
+//! subscribed. [`PubSubService`] accepts the WebSocket connections and
+//! answers `accountSubscribe`, `signatureSubscribe`, `slotSubscribe` and
+//! their `*Unsubscribe` counterparts.
+//!
+//! Notifications go out at commit time, so subscribers hear about
+//! processed state rather than waiting for it to be finalized. A signature
+//! subscription ends with its notification, as in the validator; every
+//! subscription of a connection ends when the connection closes.
+
+use {
+    super::{
+        config_param, encoding_param, error_response, pubkey_param, RpcError, RpcResult,
+        JSON_RPC_INVALID_REQUEST, JSON_RPC_METHOD_NOT_FOUND, JSON_RPC_PARSE_ERROR,
+    },
+    crate::{
+        plugin::{AccountsNotifier, SlotStatus},
+        transaction_status::TransactionExecutionDetails,
+    },
+    crossbeam_channel::{unbounded, Sender},
+    serde_json::{json, Value},
+    solana_account::AccountSharedData,
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        collections::{HashMap, HashSet},
+        io,
+        net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex, MutexGuard,
+        },
+        thread::{self, JoinHandle},
+        time::Duration,
+    },
+    tungstenite::{Error as WebSocketError, Message},
+};
+
+/// How often idle threads check for new connections, requests,
+/// notifications and shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Identifies a subscription across every connection.
+pub type SubscriptionId = u64;
+
+/// What a subscription is notified about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum SubscriptionParams {
+    Account {
+        pubkey: Pubkey,
+        encoding: &'static str,
+    },
+    Signature(Signature),
+    Slot,
+}
+
+struct Subscription {
+    params: SubscriptionParams,
+    /// Where the connection that subscribed picks up its notifications.
+    sender: Sender<String>,
+}
+
+#[derive(Default)]
+struct SubscriptionsInner {
+    next_id: SubscriptionId,
+    subscriptions: HashMap<SubscriptionId, Subscription>,
+    /// Slot of the last bank created, reported as the parent of the next.
+    last_slot: Option<Slot>,
+    /// Highest rooted slot.
+    root: Slot,
+}
+
+/// Every live subscription, notified from the banks it is attached to.
+#[derive(Default)]
+pub struct RpcSubscriptions {
+    inner: Mutex<SubscriptionsInner>,
+}
+
+impl RpcSubscriptions {
+    /// Number of live subscriptions across every connection.
+    pub fn num_subscriptions(&self) -> usize {
+        self.inner().subscriptions.len()
+    }
+
+    fn inner(&self) -> MutexGuard<'_, SubscriptionsInner> {
+        self.inner
+            .lock()
+            .expect("subscriptions lock is never poisoned")
+    }
+
+    fn subscribe(&self, params: SubscriptionParams, sender: Sender<String>) -> SubscriptionId {
+        let mut inner = self.inner();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner
+            .subscriptions
+            .insert(id, Subscription { params, sender });
+        id
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.inner().subscriptions.remove(&id).is_some()
+    }
+
+    /// Sends `method` with the result `result` builds to every
+    /// subscription `params` matches. Subscriptions whose connection is
+    /// gone are dropped, and so are the matching ones if `once`.
+    fn notify(
+        &self,
+        method: &str,
+        once: bool,
+        matches: impl Fn(&SubscriptionParams) -> bool,
+        result: impl Fn(&SubscriptionParams) -> Value,
+    ) {
+        self.inner().subscriptions.retain(|id, subscription| {
+            if !matches(&subscription.params) {
+                return true;
+            }
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": { "result": result(&subscription.params), "subscription": id },
+            });
+            subscription.sender.send(notification.to_string()).is_ok() && !once
+        });
+    }
+}
+
+impl AccountsNotifier for RpcSubscriptions {
+    fn notify_account_update(
+        &self,
+        slot: Slot,
+        pubkey: &Pubkey,
+        account: &AccountSharedData,
+        _transaction: &SanitizedTransaction,
+    ) {
+        self.notify(
+            "accountNotification",
+            false,
+            |params| {
+                matches!(params, SubscriptionParams::Account { pubkey: key, .. } if key == pubkey)
+            },
+            |params| {
+                let SubscriptionParams::Account { encoding, .. } = params else {
+                    unreachable!("only account subscriptions match");
+                };
+                json!({
+                    "context": { "slot": slot },
+                    "value": super::account_json(account, encoding),
+                })
+            },
+        );
+    }
+
+    fn notify_transaction(
+        &self,
+        slot: Slot,
+        transaction: &SanitizedTransaction,
+        details: &TransactionExecutionDetails,
+    ) {
+        let signature = transaction.signature();
+        self.notify(
+            "signatureNotification",
+            true,
+            |params| *params == SubscriptionParams::Signature(*signature),
+            |_| {
+                json!({
+                    "context": { "slot": slot },
+                    "value": { "err": details.status.as_ref().err() },
+                })
+            },
+        );
+    }
+
+    /// Sends `slotNotification`s as banks are created. The parent reported
+    /// is the slot created before (for the first one, the slot before it),
+    /// which is exact as long as the notifying banks form a single fork.
+    fn notify_slot_status(&self, slot: Slot, status: SlotStatus) {
+        let (parent, root) = {
+            let mut inner = self.inner();
+            match status {
+                SlotStatus::Created => {
+                    let parent = inner.last_slot.replace(slot);
+                    (parent.unwrap_or(slot.saturating_sub(1)), inner.root)
+                }
+                SlotStatus::Rooted => {
+                    inner.root = inner.root.max(slot);
+                    return;
+                }
+                SlotStatus::Frozen => return,
+            }
+        };
+        self.notify(
+            "slotNotification",
+            false,
+            |params| *params == SubscriptionParams::Slot,
+            |_| json!({ "parent": parent, "root": root, "slot": slot }),
+        );
+    }
+}
+
+/// Serves [`RpcSubscriptions`] over WebSocket, one thread per connection.
+///
+/// The service stops accepting connections and closes the open ones when
+/// it is dropped.
+pub struct PubSubService {
+    local_addr: SocketAddr,
+    exit: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PubSubService {
+    /// Starts serving `subscriptions` on `addr`. Port 0 picks a free port,
+    /// which [`local_addr`](Self::local_addr) reports.
+    pub fn new(subscriptions: Arc<RpcSubscriptions>, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let exit = Arc::new(AtomicBool::new(false));
+        let thread = thread::Builder::new()
+            .name("rpc-pubsub".to_string())
+            .spawn({
+                let exit = exit.clone();
+                move || serve(&listener, &subscriptions, &exit)
+            })?;
+        Ok(Self {
+            local_addr,
+            exit,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for PubSubService {
+    fn drop(&mut self) {
+        self.exit.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(listener: &TcpListener, subscriptions: &Arc<RpcSubscriptions>, exit: &Arc<AtomicBool>) {
+    let mut connections = Vec::new();
+    while !exit.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let subscriptions = subscriptions.clone();
+                let exit = exit.clone();
+                connections.push(thread::spawn(move || {
+                    serve_connection(stream, &subscriptions, &exit)
+                }));
+            }
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+        connections.retain(|connection: &JoinHandle<()>| !connection.is_finished());
+    }
+    for connection in connections {
+        let _ = connection.join();
+    }
+}
+
+fn serve_connection(stream: TcpStream, subscriptions: &RpcSubscriptions, exit: &AtomicBool) {
+    if stream.set_nonblocking(false).is_err() {
+        return;
+    }
+    let Ok(mut socket) = tungstenite::accept(stream) else {
+        return;
+    };
+    // Reads give up after a while, so notifications are not held back by a
+    // quiet client.
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+
+    let (sender, receiver) = unbounded();
+    let mut ids = HashSet::new();
+    'connection: while !exit.load(Ordering::Relaxed) {
+        match socket.read() {
+            Ok(Message::Text(request)) => {
+                let response = process_request(subscriptions, &request, &sender, &mut ids);
+                if socket.send(Message::text(response)).is_err() {
+                    break;
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(WebSocketError::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => break,
+        }
+        for notification in receiver.try_iter() {
+            if socket.send(Message::text(notification)).is_err() {
+                break 'connection;
+            }
+        }
+    }
+    let _ = socket.close(None);
+    for id in ids {
+        subscriptions.unsubscribe(id);
+    }
+}
+
+/// Answers a subscription request of a connection that picks up
+/// notifications from `sender` and holds the subscriptions `ids`.
+fn process_request(
+    subscriptions: &RpcSubscriptions,
+    body: &str,
+    sender: &Sender<String>,
+    ids: &mut HashSet<SubscriptionId>,
+) -> String {
+    let request = match serde_json::from_str::<Value>(body) {
+        Ok(request) => request,
+        Err(err) => {
+            let err = RpcError::new(JSON_RPC_PARSE_ERROR, format!("Parse error: {err}"));
+            return error_response(&Value::Null, err).to_string();
+        }
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .filter(|_| request.get("jsonrpc").and_then(Value::as_str) == Some("2.0"));
+    let params = match request.get("params") {
+        Some(Value::Array(params)) => params.as_slice(),
+        _ => &[][..],
+    };
+
+    let result = match method {
+        None => Err(RpcError::new(JSON_RPC_INVALID_REQUEST, "Invalid request")),
+        Some("accountUnsubscribe" | "signatureUnsubscribe" | "slotUnsubscribe") => {
+            unsubscribe(subscriptions, params, ids)
+        }
+        Some(method) => subscription_params(method, params).map(|params| {
+            let subscription = subscriptions.subscribe(params, sender.clone());
+            ids.insert(subscription);
+            subscription.into()
+        }),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(err) => error_response(&id, err),
+    }
+    .to_string()
+}
+
+fn subscription_params(method: &str, params: &[Value]) -> Result<SubscriptionParams, RpcError> {
+    match method {
+        "accountSubscribe" => Ok(SubscriptionParams::Account {
+            pubkey: pubkey_param(params)?,
+            encoding: match encoding_param(config_param(params)?, "base64")? {
+                "base58" => "base58",
+                _ => "base64",
+            },
+        }),
+        "signatureSubscribe" => params
+            .first()
+            .and_then(Value::as_str)
+            .and_then(|signature| signature.parse().ok())
+            .map(SubscriptionParams::Signature)
+            .ok_or_else(|| RpcError::invalid_params("Invalid param: missing or invalid signature")),
+        "slotSubscribe" => Ok(SubscriptionParams::Slot),
+        _ => Err(RpcError::new(
+            JSON_RPC_METHOD_NOT_FOUND,
+            format!("Method not found: {method}"),
+        )),
+    }
+}
+
+/// Ends a subscription of this connection, whichever `*Unsubscribe`
+/// method asked for it.
+fn unsubscribe(
+    subscriptions: &RpcSubscriptions,
+    params: &[Value],
+    ids: &mut HashSet<SubscriptionId>,
+) -> RpcResult {
+    let id = params
+        .first()
+        .and_then(Value::as_u64)
+        .ok_or_else(|| RpcError::invalid_params("Invalid param: missing subscription id"))?;
+    if !ids.remove(&id) || !subscriptions.unsubscribe(id) {
+        return Err(RpcError::invalid_params("Invalid subscription id."));
+    }
+    Ok(true.into())
+}
@@ -0,0 +1,167 @@
+//! Static transaction checks.
+//!
+//! [`sanitize_transaction`] is the only way a [`VersionedTransaction`]
+//! from the outside becomes a [`SanitizedTransaction`], the type every
+//! later stage (locking, scheduling, execution) takes. It rejects malformed
+//! transactions with a typed [`SanitizeError`] before any of their accounts
+//! are loaded. Untrusted wire bytes go through [`deserialize_transaction`]
+//! first, which rejects them with a typed error too.
+//!
+//! The `fuzz` directory next to this crate holds cargo-fuzz targets feeding
+//! arbitrary bytes to both, to make sure no input panics.
+
+use {
+    crate::accounts::reserved_account_keys,
+    bincode::Options,
+    solana_message::AddressLoader,
+    solana_pubkey::Pubkey,
+    solana_transaction::{
+        sanitized::{MessageHash, SanitizedTransaction},
+        versioned::VersionedTransaction,
+    },
+    solana_transaction_error::TransactionError,
+    std::collections::HashSet,
+};
+
+/// Most bytes of data a single instruction can carry: no instruction can be
+/// larger than the packet its transaction arrives in.
+pub const MAX_INSTRUCTION_DATA_LEN: usize = 1_232;
+
+/// Most bytes a serialized transaction can take: the payload of a single
+/// packet.
+pub const MAX_TRANSACTION_LEN: usize = 1_232;
+
+/// Why a transaction failed sanitization.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SanitizeError {
+    #[error("the transaction takes {len} bytes, above the limit")]
+    TransactionTooLarge { len: usize },
+    #[error("the transaction bytes do not decode")]
+    InvalidEncoding,
+    #[error("the header requires {expected} signatures, but the transaction carries {actual}")]
+    SignatureCountMismatch { expected: usize, actual: usize },
+    #[error("the message header does not fit its account keys")]
+    InvalidHeader,
+    #[error("account {0} is listed more than once")]
+    DuplicateAccountKey(Pubkey),
+    #[error("instruction {instruction_index} has a program id index out of range")]
+    ProgramIdIndexOutOfBounds { instruction_index: usize },
+    #[error("instruction {instruction_index} has an account index out of range")]
+    AccountIndexOutOfBounds { instruction_index: usize },
+    #[error("instruction {instruction_index} carries {len} bytes of data, above the limit")]
+    InstructionDataTooLarge {
+        instruction_index: usize,
+        len: usize,
+    },
+    /// The transaction passed the static checks, but resolving its address
+    /// table lookups or building the sanitized message failed.
+    #[error(transparent)]
+    Transaction(#[from] TransactionError),
+}
+
+impl From<SanitizeError> for TransactionError {
+    fn from(err: SanitizeError) -> Self {
+        match err {
+            SanitizeError::SignatureCountMismatch { .. } => TransactionError::SignatureFailure,
+            SanitizeError::DuplicateAccountKey(_) => TransactionError::AccountLoadedTwice,
+            SanitizeError::Transaction(err) => err,
+            SanitizeError::TransactionTooLarge { .. }
+            | SanitizeError::InvalidEncoding
+            | SanitizeError::InvalidHeader
+            | SanitizeError::ProgramIdIndexOutOfBounds { .. }
+            | SanitizeError::AccountIndexOutOfBounds { .. }
+            | SanitizeError::InstructionDataTooLarge { .. } => TransactionError::SanitizeFailure,
+        }
+    }
+}
+
+/// Decodes the bincode wire format of a transaction.
+///
+/// Input longer than [`MAX_TRANSACTION_LEN`] is rejected before decoding,
+/// and so is input with bytes left over once a transaction decoded.
+pub fn deserialize_transaction(bytes: &[u8]) -> Result<VersionedTransaction, SanitizeError> {
+    if bytes.len() > MAX_TRANSACTION_LEN {
+        return Err(SanitizeError::TransactionTooLarge { len: bytes.len() });
+    }
+    bincode::options()
+        .with_limit(MAX_TRANSACTION_LEN as u64)
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(bytes)
+        .map_err(|_| SanitizeError::InvalidEncoding)
+}
+
+/// Runs the static checks on `transaction`, then resolves its address
+/// table lookups through `address_loader`.
+///
+/// The checks cover the signature count against the header, duplicate
+/// static account keys, program id and account indexes and instruction
+/// data sizes. Program ids have to be static keys other than the fee
+/// payer; looked up keys are only valid as instruction accounts.
+pub fn sanitize_transaction(
+    transaction: VersionedTransaction,
+    address_loader: impl AddressLoader,
+) -> Result<SanitizedTransaction, SanitizeError> {
+    check_transaction(&transaction)?;
+    Ok(SanitizedTransaction::try_create(
+        transaction,
+        MessageHash::Compute,
+        None,
+        address_loader,
+        reserved_account_keys(),
+    )?)
+}
+
+fn check_transaction(transaction: &VersionedTransaction) -> Result<(), SanitizeError> {
+    let message = &transaction.message;
+    let header = message.header();
+    let static_keys = message.static_account_keys();
+
+    let num_required_signatures = usize::from(header.num_required_signatures);
+    if transaction.signatures.len() != num_required_signatures {
+        return Err(SanitizeError::SignatureCountMismatch {
+            expected: num_required_signatures,
+            actual: transaction.signatures.len(),
+        });
+    }
+    // The fee payer has to sign and stay writable.
+    if header.num_readonly_signed_accounts >= header.num_required_signatures
+        || num_required_signatures + usize::from(header.num_readonly_unsigned_accounts)
+            > static_keys.len()
+    {
+        return Err(SanitizeError::InvalidHeader);
+    }
+
+    let mut unique_keys = HashSet::with_capacity(static_keys.len());
+    if let Some(duplicate) = static_keys.iter().find(|key| !unique_keys.insert(*key)) {
+        return Err(SanitizeError::DuplicateAccountKey(*duplicate));
+    }
+
+    let num_loaded_keys: usize = message
+        .address_table_lookups()
+        .unwrap_or_default()
+        .iter()
+        .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+        .sum();
+    let num_keys = static_keys.len() + num_loaded_keys;
+    for (instruction_index, instruction) in message.instructions().iter().enumerate() {
+        let program_id_index = usize::from(instruction.program_id_index);
+        if program_id_index == 0 || program_id_index >= static_keys.len() {
+            return Err(SanitizeError::ProgramIdIndexOutOfBounds { instruction_index });
+        }
+        if instruction
+            .accounts
+            .iter()
+            .any(|&index| usize::from(index) >= num_keys)
+        {
+            return Err(SanitizeError::AccountIndexOutOfBounds { instruction_index });
+        }
+        if instruction.data.len() > MAX_INSTRUCTION_DATA_LEN {
+            return Err(SanitizeError::InstructionDataTooLarge {
+                instruction_index,
+                len: instruction.data.len(),
+            });
+        }
+    }
+    Ok(())
+}
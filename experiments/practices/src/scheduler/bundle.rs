@@ -0,0 +1,99 @@
+use {
+    crate::accounts::{LockSet, LockSetConfig},
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+    std::collections::HashSet,
+    thiserror::Error,
+};
+
+/// Transactions a bundle holds at most, as block engines cap MEV bundles.
+pub const MAX_BUNDLE_LEN: usize = 5;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum BundleError {
+    #[error("bundle holds no transactions")]
+    Empty,
+    #[error("bundle holds {0} transactions, more than {MAX_BUNDLE_LEN}")]
+    TooLong(usize),
+    #[error("transaction {0} of the bundle repeats an earlier one")]
+    DuplicateTransaction(usize),
+    #[error("transaction {index} of the bundle failed: {err}")]
+    TransactionFailed { index: usize, err: TransactionError },
+}
+
+/// Transactions that execute in order and commit all together or not at
+/// all.
+///
+/// A scheduler hands a bundle out as one unit that holds the locks of
+/// every account any of its transactions uses, so nothing that touches
+/// those accounts runs in between; see
+/// [`Bank::process_bundle`](crate::bank::Bank::process_bundle) for how one
+/// executes.
+#[derive(Clone, Debug)]
+pub struct Bundle {
+    transactions: Vec<SanitizedTransaction>,
+}
+
+impl Bundle {
+    /// Bundles `transactions`, which have to be between one and
+    /// [`MAX_BUNDLE_LEN`] distinct transactions.
+    pub fn new(transactions: Vec<SanitizedTransaction>) -> Result<Self, BundleError> {
+        if transactions.is_empty() {
+            return Err(BundleError::Empty);
+        }
+        if transactions.len() > MAX_BUNDLE_LEN {
+            return Err(BundleError::TooLong(transactions.len()));
+        }
+        let mut message_hashes = HashSet::with_capacity(transactions.len());
+        if let Some(index) = transactions
+            .iter()
+            .position(|transaction| !message_hashes.insert(*transaction.message_hash()))
+        {
+            return Err(BundleError::DuplicateTransaction(index));
+        }
+        Ok(Self { transactions })
+    }
+
+    pub fn transactions(&self) -> &[SanitizedTransaction] {
+        &self.transactions
+    }
+
+    pub fn into_transactions(self) -> Vec<SanitizedTransaction> {
+        self.transactions
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Always `false`; a bundle holds at least one transaction.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// The locks of all transactions together. An account one transaction
+    /// writes and another reads is write locked.
+    pub fn lock_set(&self, config: LockSetConfig) -> LockSet {
+        let lock_sets: Vec<_> = self
+            .transactions
+            .iter()
+            .map(|transaction| LockSet::from_transaction(transaction, config))
+            .collect();
+        let mut writable_keys = HashSet::new();
+        let writable: Vec<Pubkey> = lock_sets
+            .iter()
+            .flat_map(|lock_set| lock_set.writable())
+            .filter(|key| writable_keys.insert(**key))
+            .copied()
+            .collect();
+        let mut readonly_keys = HashSet::new();
+        let readonly = lock_sets
+            .iter()
+            .flat_map(|lock_set| lock_set.readonly())
+            .filter(|key| !writable_keys.contains(*key) && readonly_keys.insert(**key))
+            .copied()
+            .collect();
+        LockSet::new(writable, readonly)
+    }
+}
@@ -0,0 +1,96 @@
+use {
+    super::TransactionId,
+    serde_json::{json, Value},
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    std::collections::BTreeMap,
+};
+
+/// What a [`PriorityGraphScheduler`](super::PriorityGraphScheduler) holds
+/// at one point of a slot, taken by
+/// [`checkpoint`](super::PriorityGraphScheduler::checkpoint).
+///
+/// Transactions are named by the ids the scheduler handed out for them; a
+/// bundle by the id of its first transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchedulerCheckpoint {
+    pub slot: Slot,
+    /// Queued transactions not inserted into the graph yet, including
+    /// retried ones backing off and those outside the look-ahead window,
+    /// by id.
+    pub pending: Vec<TransactionId>,
+    /// Transactions in the graph that nothing blocks, highest priority
+    /// first. They go into the next batch that has room for them.
+    pub unblocked: Vec<TransactionId>,
+    /// Handed out transactions that have not completed yet, by id. They
+    /// still block the transactions queued behind them.
+    pub in_flight: Vec<TransactionId>,
+    /// Every blocked transaction with the transactions it waits on, by id.
+    pub blocked: BTreeMap<TransactionId, Vec<TransactionId>>,
+    /// Every edge of the graph, from a transaction to one it blocks, with
+    /// the accounts they conflict on.
+    pub edges: BTreeMap<(TransactionId, TransactionId), Vec<Pubkey>>,
+}
+
+impl SchedulerCheckpoint {
+    pub fn is_blocked(&self, id: TransactionId) -> bool {
+        self.blocked.contains_key(&id)
+    }
+
+    /// The chain `id` is blocked behind, nearest first: the transaction it
+    /// waits on, the one that one waits on, and so on up to one nothing
+    /// blocks. Where a transaction waits on several, the chain follows the
+    /// lowest id.
+    ///
+    /// "C is blocked behind B, which is blocked behind A" reads
+    /// `blocked_behind(c) == [b, a]`.
+    pub fn blocked_behind(&self, id: TransactionId) -> Vec<TransactionId> {
+        let mut chain = Vec::new();
+        let mut id = id;
+        while let Some(&blocker) = self.blocked.get(&id).and_then(|blockers| blockers.first()) {
+            chain.push(blocker);
+            id = blocker;
+        }
+        chain
+    }
+
+    /// Whether `id` waits on `blocker`, directly or through transactions
+    /// between them.
+    pub fn is_blocked_behind(&self, id: TransactionId, blocker: TransactionId) -> bool {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let Some(blockers) = self.blocked.get(&id) else {
+                continue;
+            };
+            if blockers.contains(&blocker) {
+                return true;
+            }
+            stack.extend(blockers);
+        }
+        false
+    }
+
+    /// The checkpoint as JSON, to dump next to a failing test or a log.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "slot": self.slot,
+            "pending": self.pending,
+            "unblocked": self.unblocked,
+            "inFlight": self.in_flight,
+            "blocked": self
+                .blocked
+                .iter()
+                .map(|(id, blockers)| (id.to_string(), json!(blockers)))
+                .collect::<serde_json::Map<_, _>>(),
+            "edges": self
+                .edges
+                .iter()
+                .map(|((blocker, blocked), accounts)| json!({
+                    "from": blocker,
+                    "to": blocked,
+                    "accounts": accounts.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
@@ -0,0 +1,213 @@
+//! Which accounts held transactions up in a run.
+//!
+//! A [`ContentionReport`] is built from the [`ScheduleLog`] of a run and
+//! the transactions it ran, so it works the same for a generated workload
+//! recorded with [`WorkerPool::run_recorded`](super::WorkerPool::run_recorded)
+//! and for a log loaded to replay. Time is counted in batches: the log
+//! records the order of scheduling decisions, not how long they took.
+
+use {
+    super::{PriorityPolicy, ScheduleLog},
+    crate::accounts::{LockSet, LockSetConfig},
+    serde_json::{json, Value},
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        collections::{HashMap, HashSet},
+        fmt::Write,
+    },
+};
+
+/// How contended one account was over a run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountContention {
+    pub account: Pubkey,
+    /// Times a handed out transaction write-locked the account.
+    pub write_locks: u64,
+    /// Times a handed out transaction read-locked the account.
+    pub read_locks: u64,
+    /// Transactions the account held up: the last transaction they
+    /// conflicted with before they were handed out locked it.
+    pub transactions_blocked: u64,
+    /// The priorities of those transactions, altogether.
+    pub blocked_priority: u64,
+    /// Batches handed out before each of those transactions, altogether.
+    /// A run queues every transaction up front, so this is how long they
+    /// waited.
+    pub wait_batches: u64,
+}
+
+/// The accounts of a run, most contended first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContentionReport {
+    /// Every account a handed out transaction locked, by the priority
+    /// they held up, then by the batches they made transactions wait,
+    /// then by how often they were write-locked.
+    pub accounts: Vec<AccountContention>,
+    pub num_batches: usize,
+}
+
+/// The last batches that locked an account.
+#[derive(Clone, Copy, Default)]
+struct LastLocks {
+    write: Option<usize>,
+    read: Option<usize>,
+}
+
+impl ContentionReport {
+    /// Walks the batches of `log` over the `transactions` it was recorded
+    /// for, ranking them by `priority_policy`.
+    ///
+    /// A transaction is only held up by an account if something handed
+    /// out in an earlier batch conflicted with it there. One that waited
+    /// only on the batch limits is not counted against any account, and
+    /// one that conflicted with the same transaction on several accounts
+    /// is counted against each of them. A retried transaction locks its
+    /// accounts again every time it is handed out, but is only counted as
+    /// held up before the first time.
+    pub fn from_schedule(
+        log: &ScheduleLog,
+        transactions: &[SanitizedTransaction],
+        priority_policy: &PriorityPolicy,
+    ) -> Self {
+        let mut accounts: HashMap<Pubkey, AccountContention> = HashMap::new();
+        let mut last_locks: HashMap<Pubkey, LastLocks> = HashMap::new();
+        let mut handed_out = HashSet::new();
+        let mut num_batches = 0;
+        for (batch_index, assignments) in log.batches().enumerate() {
+            num_batches += 1;
+            let lock_sets: Vec<LockSet> = assignments
+                .iter()
+                .flat_map(|assignment| &assignment.positions)
+                .map(|&position| {
+                    let transaction = &transactions[position];
+                    let lock_set = LockSet::from_transaction(transaction, LockSetConfig::default());
+                    if handed_out.insert(position) {
+                        let priority = priority_policy.priority_id(transaction, position).priority;
+                        charge_blockers(
+                            &mut accounts,
+                            &last_locks,
+                            &lock_set,
+                            batch_index,
+                            priority,
+                        );
+                    }
+                    lock_set
+                })
+                .collect();
+            // Transactions of one batch never conflict, so the locks they
+            // take only matter to later batches.
+            for lock_set in &lock_sets {
+                for account in lock_set.writable() {
+                    last_locks.entry(*account).or_default().write = Some(batch_index);
+                    accounts.entry(*account).or_default().write_locks += 1;
+                }
+                for account in lock_set.readonly() {
+                    last_locks.entry(*account).or_default().read = Some(batch_index);
+                    accounts.entry(*account).or_default().read_locks += 1;
+                }
+            }
+        }
+
+        let mut accounts: Vec<AccountContention> = accounts
+            .into_iter()
+            .map(|(account, contention)| AccountContention {
+                account,
+                ..contention
+            })
+            .collect();
+        accounts.sort_unstable_by(|a, b| {
+            (b.blocked_priority, b.wait_batches, b.write_locks, a.account).cmp(&(
+                a.blocked_priority,
+                a.wait_batches,
+                a.write_locks,
+                b.account,
+            ))
+        });
+        Self {
+            accounts,
+            num_batches,
+        }
+    }
+
+    /// The `n` most contended accounts.
+    pub fn top(&self, n: usize) -> &[AccountContention] {
+        &self.accounts[..n.min(self.accounts.len())]
+    }
+
+    /// The report as CSV, with a header line and a line per account, most
+    /// contended first.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "account,write_locks,read_locks,transactions_blocked,blocked_priority,wait_batches\n",
+        );
+        for contention in &self.accounts {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                contention.account,
+                contention.write_locks,
+                contention.read_locks,
+                contention.transactions_blocked,
+                contention.blocked_priority,
+                contention.wait_batches
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "numBatches": self.num_batches,
+            "accounts": self
+                .accounts
+                .iter()
+                .map(|contention| json!({
+                    "account": contention.account.to_string(),
+                    "writeLocks": contention.write_locks,
+                    "readLocks": contention.read_locks,
+                    "transactionsBlocked": contention.transactions_blocked,
+                    "blockedPriority": contention.blocked_priority,
+                    "waitBatches": contention.wait_batches,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Charges a transaction with `lock_set` and `priority`, handed out in
+/// `batch_index`, to the accounts on which it conflicted with the latest
+/// batch before it.
+fn charge_blockers(
+    accounts: &mut HashMap<Pubkey, AccountContention>,
+    last_locks: &HashMap<Pubkey, LastLocks>,
+    lock_set: &LockSet,
+    batch_index: usize,
+    priority: u64,
+) {
+    let last_lock = |account: &Pubkey| last_locks.get(account).copied().unwrap_or_default();
+    let blockers: Vec<(Pubkey, usize)> = lock_set
+        .writable()
+        .iter()
+        .filter_map(|account| {
+            let locks = last_lock(account);
+            Some((*account, locks.write.max(locks.read)?))
+        })
+        .chain(
+            lock_set
+                .readonly()
+                .iter()
+                .filter_map(|account| Some((*account, last_lock(account).write?))),
+        )
+        .collect();
+    let Some(latest) = blockers.iter().map(|&(_, batch)| batch).max() else {
+        return;
+    };
+    for (account, _) in blockers.into_iter().filter(|&(_, batch)| batch == latest) {
+        let contention = accounts.entry(account).or_default();
+        contention.transactions_blocked += 1;
+        contention.blocked_priority = contention.blocked_priority.saturating_add(priority);
+        contention.wait_batches += batch_index as u64;
+    }
+}
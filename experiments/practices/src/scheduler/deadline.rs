@@ -0,0 +1,43 @@
+use {
+    super::TransactionId, solana_clock::Slot, solana_transaction::sanitized::SanitizedTransaction,
+    std::time::Instant,
+};
+
+/// When a queued transaction stops being worth executing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deadline {
+    /// The transaction may run up to and including `Slot`.
+    Slot(Slot),
+    /// The transaction may run until `Instant`.
+    Time(Instant),
+}
+
+impl Deadline {
+    /// Whether the deadline passed in `slot` at `now`.
+    pub fn is_expired(&self, slot: Slot, now: Instant) -> bool {
+        match *self {
+            Self::Slot(last_slot) => slot > last_slot,
+            Self::Time(deadline) => now >= deadline,
+        }
+    }
+}
+
+/// Why a scheduler gave up on a transaction without handing it out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropReason {
+    /// Its [`Deadline`] passed while it was queued.
+    Expired,
+    /// It was handed to another node to execute; see
+    /// [`take_for_forwarding`].
+    ///
+    /// [`take_for_forwarding`]: super::PriorityGraphScheduler::take_for_forwarding
+    Forwarded,
+}
+
+/// A transaction a scheduler dropped.
+#[derive(Clone, Debug)]
+pub struct DroppedTransaction {
+    pub id: TransactionId,
+    pub transaction: SanitizedTransaction,
+    pub reason: DropReason,
+}
@@ -0,0 +1,132 @@
+use {
+    super::{PriorityGraphScheduler, Scheduler},
+    crate::accounts::{LockSet, LockSetConfig},
+    prio_graph::AccessKind,
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        collections::{hash_map::Entry, BTreeMap, HashMap},
+        fmt::Write,
+    },
+};
+
+/// Who holds the lock on an account while the graph is built, mirroring
+/// the locks of [`prio_graph::PrioGraph`].
+enum Lock {
+    /// Readers since the last write, and that write.
+    Read(Vec<usize>, Option<usize>),
+    Write(usize),
+}
+
+impl Lock {
+    /// Takes a read lock for `index`, returning the writer it waits on.
+    fn add_read(&mut self, index: usize) -> Option<usize> {
+        match self {
+            Lock::Read(readers, last_write) => {
+                readers.push(index);
+                *last_write
+            }
+            Lock::Write(writer) => {
+                let writer = *writer;
+                *self = Lock::Read(vec![index], Some(writer));
+                Some(writer)
+            }
+        }
+    }
+
+    /// Takes the write lock for `index`, returning the transactions it
+    /// waits on.
+    fn add_write(&mut self, index: usize) -> Vec<usize> {
+        match std::mem::replace(self, Lock::Write(index)) {
+            Lock::Read(readers, _) => readers,
+            Lock::Write(writer) => vec![writer],
+        }
+    }
+}
+
+/// The edges of the priority graph built from `nodes`, in insertion order,
+/// from each blocking node to each node it blocks, with the accounts they
+/// conflict on.
+pub(super) fn lock_edges<'a>(
+    nodes: impl IntoIterator<Item = (usize, &'a LockSet)>,
+) -> BTreeMap<(usize, usize), Vec<Pubkey>> {
+    let mut locks: HashMap<Pubkey, Lock> = HashMap::new();
+    let mut edges: BTreeMap<(usize, usize), Vec<Pubkey>> = BTreeMap::new();
+    for (index, lock_set) in nodes {
+        for (account, access_kind) in lock_set.iter() {
+            let blockers = match (locks.entry(account), access_kind) {
+                (Entry::Vacant(entry), AccessKind::Read) => {
+                    entry.insert(Lock::Read(vec![index], None));
+                    vec![]
+                }
+                (Entry::Vacant(entry), AccessKind::Write) => {
+                    entry.insert(Lock::Write(index));
+                    vec![]
+                }
+                (Entry::Occupied(mut entry), AccessKind::Read) => {
+                    entry.get_mut().add_read(index).into_iter().collect()
+                }
+                (Entry::Occupied(mut entry), AccessKind::Write) => entry.get_mut().add_write(index),
+            };
+            for blocker in blockers {
+                edges.entry((blocker, index)).or_default().push(account);
+            }
+        }
+    }
+    edges
+}
+
+/// Renders the priority graph [`PriorityGraphScheduler`] builds for
+/// `transactions` in the Graphviz DOT language.
+///
+/// `PrioGraph` does not expose its nodes and edges, so the graph is rebuilt
+/// here from the same insertion order and account locks. Every transaction
+/// is a node labelled with its index and priority, grouped into a cluster
+/// per batch [`Scheduler::schedule`] emits it in. Every edge points from a
+/// transaction to one it blocks, labelled with the accounts they conflict
+/// on, so a chain of edges shows why transactions land in separate batches.
+pub fn export_dot(transactions: &[SanitizedTransaction]) -> String {
+    let mut scheduler = PriorityGraphScheduler::new();
+    let insertion_order = scheduler.insertion_order(transactions);
+
+    let lock_sets: Vec<LockSet> = insertion_order
+        .iter()
+        .map(|id| LockSet::from_transaction(&transactions[id.index], LockSetConfig::default()))
+        .collect();
+    let edges = lock_edges(
+        insertion_order
+            .iter()
+            .zip(&lock_sets)
+            .map(|(id, lock_set)| (id.index, lock_set)),
+    );
+
+    let priorities: HashMap<usize, u64> = insertion_order
+        .iter()
+        .map(|id| (id.index, id.priority))
+        .collect();
+    let mut dot = String::from("digraph prio_graph {\n    node [shape=box];\n");
+    for (batch_index, batch) in scheduler.schedule(transactions).iter().enumerate() {
+        writeln!(dot, "    subgraph cluster_batch_{batch_index} {{").unwrap();
+        writeln!(dot, "        label=\"batch {batch_index}\";").unwrap();
+        for index in &batch.transaction_indexes {
+            writeln!(
+                dot,
+                "        tx{index} [label=\"tx {index}\\npriority {}\"];",
+                priorities[index]
+            )
+            .unwrap();
+        }
+        dot.push_str("    }\n");
+    }
+    for ((blocker, blocked), accounts) in edges {
+        let accounts: Vec<String> = accounts.iter().map(Pubkey::to_string).collect();
+        writeln!(
+            dot,
+            "    tx{blocker} -> tx{blocked} [label=\"{}\"];",
+            accounts.join("\\n")
+        )
+        .unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
@@ -0,0 +1,178 @@
+use {
+    super::{
+        Batch, BatchBudget, BatchCost, BatchLimits, Bundle, TransactionId, TransactionScheduler,
+    },
+    crate::accounts::{LockSet, LockSetConfig},
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::collections::{HashMap, HashSet, VecDeque},
+};
+
+/// Accounts locked by in-flight transactions.
+#[derive(Default)]
+pub(super) struct InFlightLocks {
+    write_locked: HashSet<Pubkey>,
+    /// Number of in-flight transactions reading each account.
+    read_locked: HashMap<Pubkey, usize>,
+}
+
+impl InFlightLocks {
+    pub(super) fn conflicts_with(&self, lock_set: &LockSet) -> bool {
+        lock_set
+            .writable()
+            .iter()
+            .any(|key| self.write_locked.contains(key) || self.read_locked.contains_key(key))
+            || lock_set
+                .readonly()
+                .iter()
+                .any(|key| self.write_locked.contains(key))
+    }
+
+    pub(super) fn lock(&mut self, lock_set: &LockSet) {
+        self.write_locked.extend(lock_set.writable());
+        for key in lock_set.readonly() {
+            *self.read_locked.entry(*key).or_default() += 1;
+        }
+    }
+
+    pub(super) fn unlock(&mut self, lock_set: &LockSet) {
+        for key in lock_set.writable() {
+            self.write_locked.remove(key);
+        }
+        for key in lock_set.readonly() {
+            if let Some(count) = self.read_locked.get_mut(key) {
+                *count -= 1;
+                if *count == 0 {
+                    self.read_locked.remove(key);
+                }
+            }
+        }
+    }
+}
+
+/// A queued transaction, or the transactions of a queued bundle.
+struct QueuedTransactions {
+    /// Id of the first transaction; the others follow consecutively.
+    first_id: TransactionId,
+    transactions: Vec<SanitizedTransaction>,
+    is_bundle: bool,
+    lock_set: LockSet,
+    cost: BatchCost,
+}
+
+/// The locks of handed out transactions, released once all of them
+/// completed.
+struct InFlightTransactions {
+    lock_set: LockSet,
+    num_in_flight: usize,
+}
+
+/// Hands out transactions strictly in arrival order.
+///
+/// A batch grows from the front of the queue and stops at the first
+/// transaction that conflicts with an in-flight one or does not fit the
+/// [`BatchLimits`], so no transaction ever overtakes an earlier arrival.
+/// Priority fees are ignored, which makes this the baseline the
+/// priority-based strategies are measured against.
+///
+/// A [`Bundle`] queues as a single entry that locks the accounts of all its
+/// transactions and keeps them locked until the last one completed.
+#[derive(Default)]
+pub struct FifoScheduler {
+    next_id: TransactionId,
+    queue: VecDeque<QueuedTransactions>,
+    /// The first id of the entry every in-flight transaction belongs to.
+    in_flight_ids: HashMap<TransactionId, TransactionId>,
+    in_flight: HashMap<TransactionId, InFlightTransactions>,
+    locks: InFlightLocks,
+    num_pending: usize,
+}
+
+impl FifoScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the transactions of `bundle` and returns their ids, in
+    /// bundle order.
+    pub fn push_bundle(&mut self, bundle: Bundle) -> Vec<TransactionId> {
+        let first_id = self.next_id;
+        self.next_id += bundle.len();
+        self.num_pending += bundle.len();
+        self.queue.push_back(QueuedTransactions {
+            first_id,
+            lock_set: bundle.lock_set(LockSetConfig::default()),
+            cost: BatchCost::from_bundle(&bundle),
+            transactions: bundle.into_transactions(),
+            is_bundle: true,
+        });
+        (first_id..self.next_id).collect()
+    }
+}
+
+impl TransactionScheduler for FifoScheduler {
+    fn push(&mut self, transaction: SanitizedTransaction) -> TransactionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.num_pending += 1;
+
+        self.queue.push_back(QueuedTransactions {
+            first_id: id,
+            lock_set: LockSet::from_transaction(&transaction, LockSetConfig::default()),
+            cost: BatchCost::from_transaction(&transaction),
+            transactions: vec![transaction],
+            is_bundle: false,
+        });
+        id
+    }
+
+    fn next_batch(&mut self, limits: &BatchLimits) -> Batch {
+        let mut batch = Batch::default();
+        let mut budget = BatchBudget::new(limits);
+        while let Some(queued) = self.queue.front() {
+            if self.locks.conflicts_with(&queued.lock_set) || !budget.try_add(&queued.cost) {
+                break;
+            }
+
+            let mut queued = self.queue.pop_front().unwrap();
+            let num_transactions = queued.transactions.len();
+            self.num_pending -= num_transactions;
+            self.locks.lock(&queued.lock_set);
+            for id in queued.first_id..queued.first_id + num_transactions {
+                self.in_flight_ids.insert(id, queued.first_id);
+            }
+            self.in_flight.insert(
+                queued.first_id,
+                InFlightTransactions {
+                    lock_set: queued.lock_set,
+                    num_in_flight: num_transactions,
+                },
+            );
+            if queued.is_bundle {
+                batch.push_bundle(queued.first_id, queued.transactions);
+            } else {
+                batch.push(queued.first_id, queued.transactions.remove(0));
+            }
+        }
+        batch
+    }
+
+    fn complete(&mut self, ids: &[TransactionId]) {
+        for id in ids {
+            let first_id = self
+                .in_flight_ids
+                .remove(id)
+                .expect("completed transaction must be in flight");
+            let in_flight = self.in_flight.get_mut(&first_id).unwrap();
+            in_flight.num_in_flight -= 1;
+            if in_flight.num_in_flight == 0 {
+                let in_flight = self.in_flight.remove(&first_id).unwrap();
+                self.locks.unlock(&in_flight.lock_set);
+            }
+        }
+    }
+
+    fn num_pending(&self) -> usize {
+        self.num_pending
+    }
+}
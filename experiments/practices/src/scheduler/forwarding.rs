@@ -0,0 +1,263 @@
+//! Forwarding between two nodes that take turns leading.
+//!
+//! Transactions reach whichever node a client sent them to, but only the
+//! leader of a slot includes any. [`ForwardingSimulation`] runs both nodes
+//! slot by slot: the leader packs its block from its scheduler, and at
+//! every slot boundary the node that does not lead the next slot forwards
+//! transactions to the node that does, as its [`ForwardingPolicy`] says.
+//! Every transaction's arrival and inclusion slots are recorded, so
+//! policies can be compared by the inclusion latency they lead to.
+
+use {
+    super::{BatchLimits, PriorityGraphScheduler, TransactionId, TransactionScheduler},
+    solana_clock::Slot,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::collections::HashMap,
+};
+
+/// Slots a leader leads in a row on the cluster.
+pub const NUM_CONSECUTIVE_LEADER_SLOTS: u64 = 4;
+
+/// Which of its queued transactions a node forwards to the next leader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ForwardingPolicy {
+    /// Keep every transaction until this node leads itself.
+    #[default]
+    Hold,
+    /// Forward up to `max_transactions` of the highest-priority
+    /// transactions at every slot boundary.
+    HighestPriority { max_transactions: usize },
+    /// Forward every transaction that can be taken.
+    All,
+}
+
+impl ForwardingPolicy {
+    /// Transactions forwarded at one slot boundary at most.
+    pub fn max_transactions(&self) -> usize {
+        match *self {
+            Self::Hold => 0,
+            Self::HighestPriority { max_transactions } => max_transactions,
+            Self::All => usize::MAX,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ForwardingConfig {
+    pub policy: ForwardingPolicy,
+    /// Consecutive slots each node leads before the other one takes over.
+    pub slots_per_leader: u64,
+    /// Batches the leader packs into one slot; each is capped by
+    /// `batch_limits`.
+    pub batches_per_slot: usize,
+    pub batch_limits: BatchLimits,
+}
+
+impl Default for ForwardingConfig {
+    /// Holds transactions, rotates leaders like the cluster does and packs
+    /// a single unlimited batch per slot.
+    fn default() -> Self {
+        Self {
+            policy: ForwardingPolicy::default(),
+            slots_per_leader: NUM_CONSECUTIVE_LEADER_SLOTS,
+            batches_per_slot: 1,
+            batch_limits: BatchLimits::default(),
+        }
+    }
+}
+
+/// What happened to one submitted transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InclusionRecord {
+    /// The node the transaction was submitted to.
+    pub origin: usize,
+    pub arrival_slot: Slot,
+    /// The slot whose leader included the transaction, if one did.
+    pub included_slot: Option<Slot>,
+    /// Times the transaction was forwarded.
+    pub forwards: u32,
+}
+
+impl InclusionRecord {
+    /// Slots from arrival to inclusion.
+    pub fn inclusion_latency(&self) -> Option<u64> {
+        self.included_slot
+            .map(|included_slot| included_slot - self.arrival_slot)
+    }
+}
+
+/// Totals over every [`InclusionRecord`] of a simulation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ForwardingMetrics {
+    pub transactions_submitted: u64,
+    pub transactions_included: u64,
+    /// Forwards altogether; a transaction forwarded twice counts twice.
+    pub forwards: u64,
+    /// Sum of the inclusion latencies of included transactions, in slots.
+    pub total_inclusion_latency: u64,
+}
+
+impl ForwardingMetrics {
+    /// Slots an included transaction waited on average; 0 before the first
+    /// inclusion.
+    pub fn average_inclusion_latency(&self) -> f64 {
+        if self.transactions_included == 0 {
+            return 0.0;
+        }
+        self.total_inclusion_latency as f64 / self.transactions_included as f64
+    }
+}
+
+struct SimulatedNode {
+    scheduler: PriorityGraphScheduler,
+    /// Index in the simulation's records of every transaction queued here.
+    records: HashMap<TransactionId, usize>,
+}
+
+/// Two nodes, 0 and 1, that lead in turns and forward to each other.
+///
+/// Node 0 leads the first `slots_per_leader` slots. Included transactions
+/// are not executed; the leader only takes them out of its scheduler.
+pub struct ForwardingSimulation {
+    config: ForwardingConfig,
+    nodes: [SimulatedNode; 2],
+    slot: Slot,
+    records: Vec<InclusionRecord>,
+}
+
+impl ForwardingSimulation {
+    /// Simulates two nodes with default [`PriorityGraphScheduler`]s.
+    pub fn new(config: ForwardingConfig) -> Self {
+        Self::with_schedulers(
+            config,
+            [PriorityGraphScheduler::new(), PriorityGraphScheduler::new()],
+        )
+    }
+
+    pub fn with_schedulers(
+        config: ForwardingConfig,
+        schedulers: [PriorityGraphScheduler; 2],
+    ) -> Self {
+        assert!(
+            config.slots_per_leader > 0,
+            "leaders lead at least one slot"
+        );
+        Self {
+            config,
+            nodes: schedulers.map(|scheduler| SimulatedNode {
+                scheduler,
+                records: HashMap::new(),
+            }),
+            slot: 0,
+            records: Vec::new(),
+        }
+    }
+
+    /// The slot [`run_slot`](Self::run_slot) runs next.
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// The node that leads `slot`.
+    pub fn leader(&self, slot: Slot) -> usize {
+        ((slot / self.config.slots_per_leader) % 2) as usize
+    }
+
+    /// Queues `transaction` at `node` in the current slot and returns the
+    /// index of its [`InclusionRecord`].
+    pub fn submit(&mut self, node: usize, transaction: SanitizedTransaction) -> usize {
+        let record = self.records.len();
+        self.records.push(InclusionRecord {
+            origin: node,
+            arrival_slot: self.slot,
+            included_slot: None,
+            forwards: 0,
+        });
+        let node = &mut self.nodes[node];
+        let id = node.scheduler.push(transaction);
+        node.records.insert(id, record);
+        record
+    }
+
+    /// Lets the leader pack the current slot, forwards at the boundary to
+    /// the next slot and moves on to it.
+    pub fn run_slot(&mut self) {
+        let slot = self.slot;
+        let leader = self.leader(slot);
+        let leader = &mut self.nodes[leader];
+        for _ in 0..self.config.batches_per_slot {
+            let batch = leader.scheduler.next_batch(&self.config.batch_limits);
+            if batch.is_empty() {
+                break;
+            }
+            for id in &batch.ids {
+                let record = leader
+                    .records
+                    .remove(id)
+                    .expect("every queued transaction is recorded");
+                self.records[record].included_slot = Some(slot);
+            }
+            leader.scheduler.complete(&batch.ids);
+        }
+
+        let next_leader = self.leader(slot + 1);
+        let [first, second] = &mut self.nodes;
+        let (forwarder, receiver) = match next_leader {
+            0 => (second, first),
+            _ => (first, second),
+        };
+        let forwarded = forwarder
+            .scheduler
+            .take_for_forwarding(self.config.policy.max_transactions());
+        for forwarded in forwarded {
+            let record = forwarder
+                .records
+                .remove(&forwarded.id)
+                .expect("every queued transaction is recorded");
+            self.records[record].forwards += 1;
+            let id = receiver.scheduler.push(forwarded.transaction);
+            receiver.records.insert(id, record);
+        }
+
+        self.slot += 1;
+        for node in &mut self.nodes {
+            node.scheduler.set_slot(self.slot);
+        }
+    }
+
+    /// Runs slots until every submitted transaction was included, or
+    /// `max_slots` slots passed. Returns whether everything was included.
+    pub fn run_until_included(&mut self, max_slots: u64) -> bool {
+        for _ in 0..max_slots {
+            if self.nodes.iter().all(|node| node.records.is_empty()) {
+                return true;
+            }
+            self.run_slot();
+        }
+        self.nodes.iter().all(|node| node.records.is_empty())
+    }
+
+    /// One record per submitted transaction, in submission order.
+    pub fn records(&self) -> &[InclusionRecord] {
+        &self.records
+    }
+
+    pub fn scheduler(&self, node: usize) -> &PriorityGraphScheduler {
+        &self.nodes[node].scheduler
+    }
+
+    pub fn metrics(&self) -> ForwardingMetrics {
+        let mut metrics = ForwardingMetrics {
+            transactions_submitted: self.records.len() as u64,
+            ..ForwardingMetrics::default()
+        };
+        for record in &self.records {
+            metrics.forwards += u64::from(record.forwards);
+            if let Some(latency) = record.inclusion_latency() {
+                metrics.transactions_included += 1;
+                metrics.total_inclusion_latency += latency;
+            }
+        }
+        metrics
+    }
+}
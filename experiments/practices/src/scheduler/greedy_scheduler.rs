@@ -0,0 +1,90 @@
+use {
+    super::{PriorityPolicy, ScheduleBatch, Scheduler},
+    crate::accounts::{LockSet, LockSetConfig},
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::collections::HashSet,
+};
+
+/// Accounts locked by the transactions already placed in a batch.
+#[derive(Default)]
+struct BatchLocks {
+    write_locked: HashSet<Pubkey>,
+    read_locked: HashSet<Pubkey>,
+}
+
+impl BatchLocks {
+    fn conflicts_with(&self, lock_set: &LockSet) -> bool {
+        lock_set
+            .writable()
+            .iter()
+            .any(|key| self.write_locked.contains(key) || self.read_locked.contains(key))
+            || lock_set
+                .readonly()
+                .iter()
+                .any(|key| self.write_locked.contains(key))
+    }
+
+    fn add(&mut self, lock_set: &LockSet) {
+        self.write_locked.extend(lock_set.writable());
+        self.read_locked.extend(lock_set.readonly());
+    }
+}
+
+/// Packs transactions into batches by first fit.
+///
+/// Transactions are visited in priority order and each one is placed in
+/// the first batch that holds no conflicting lock. Unlike the priority
+/// graph, nothing tracks the order between conflicting transactions: a
+/// lower priority transaction can land in an earlier batch than a higher
+/// priority one it conflicts with, as long as that earlier batch has no
+/// conflict of its own.
+#[derive(Default)]
+pub struct GreedyScheduler {
+    priority_policy: PriorityPolicy,
+}
+
+impl GreedyScheduler {
+    /// Creates a scheduler that ranks transactions by
+    /// [`PriorityPolicy::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_priority_policy(priority_policy: PriorityPolicy) -> Self {
+        Self { priority_policy }
+    }
+}
+
+impl Scheduler for GreedyScheduler {
+    fn schedule(&mut self, batch: &[SanitizedTransaction]) -> Vec<ScheduleBatch> {
+        let mut priority_ids: Vec<_> = batch
+            .iter()
+            .enumerate()
+            .map(|(index, transaction)| self.priority_policy.priority_id(transaction, index))
+            .collect();
+        priority_ids.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut batches: Vec<(ScheduleBatch, BatchLocks)> = Vec::new();
+        for id in priority_ids {
+            let lock_set = LockSet::from_transaction(&batch[id.index], LockSetConfig::default());
+            let position = batches
+                .iter()
+                .position(|(_, locks)| !locks.conflicts_with(&lock_set));
+            let (schedule_batch, locks) = match position {
+                Some(position) => &mut batches[position],
+                None => {
+                    batches.push(Default::default());
+                    batches.last_mut().unwrap()
+                }
+            };
+            schedule_batch.transaction_indexes.push(id.index);
+            locks.add(&lock_set);
+        }
+
+        batches
+            .into_iter()
+            .map(|(schedule_batch, _)| schedule_batch)
+            .collect()
+    }
+}
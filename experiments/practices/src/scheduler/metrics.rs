@@ -0,0 +1,73 @@
+/// How well the transactions a scheduler saw parallelized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SchedulerMetrics {
+    /// Transactions handed to the scheduler.
+    pub transactions_queued: u64,
+    /// Transactions the scheduler placed in a batch.
+    pub transactions_scheduled: u64,
+    /// Non-empty batches the scheduler emitted.
+    pub batches_emitted: u64,
+    /// Scheduled transactions that could not be popped right away because
+    /// they conflicted with a transaction ahead of them.
+    pub transactions_blocked: u64,
+    /// Times an executed transaction was queued again after a transient
+    /// failure.
+    pub transactions_retried: u64,
+    /// Transactions that failed transiently once more after using up their
+    /// retries.
+    pub retries_exhausted: u64,
+    /// Scheduled transactions that ranked above their own priority because
+    /// they waited.
+    pub transactions_aged: u64,
+    /// How much waiting raised the priority of scheduled transactions,
+    /// altogether.
+    pub total_priority_boost: u64,
+    /// Queued transactions dropped because their deadline passed.
+    pub transactions_expired: u64,
+    /// Queued transactions handed to another node.
+    pub transactions_forwarded: u64,
+}
+
+impl SchedulerMetrics {
+    /// Scheduled transactions per batch; 0 before the first batch.
+    pub fn average_batch_width(&self) -> f64 {
+        if self.batches_emitted == 0 {
+            return 0.0;
+        }
+        self.transactions_scheduled as f64 / self.batches_emitted as f64
+    }
+
+    /// Percentage of scheduled transactions that were blocked by a
+    /// conflict; 0 before the first transaction is scheduled.
+    pub fn blocked_percentage(&self) -> f64 {
+        if self.transactions_scheduled == 0 {
+            return 0.0;
+        }
+        100.0 * self.transactions_blocked as f64 / self.transactions_scheduled as f64
+    }
+
+    /// Average priority boost of the scheduled transactions that aged; 0
+    /// before the first one.
+    pub fn average_priority_boost(&self) -> f64 {
+        if self.transactions_aged == 0 {
+            return 0.0;
+        }
+        self.total_priority_boost as f64 / self.transactions_aged as f64
+    }
+
+    /// Adds the counts of `other` to these.
+    pub fn accumulate(&mut self, other: &SchedulerMetrics) {
+        self.transactions_queued += other.transactions_queued;
+        self.transactions_scheduled += other.transactions_scheduled;
+        self.batches_emitted += other.batches_emitted;
+        self.transactions_blocked += other.transactions_blocked;
+        self.transactions_retried += other.transactions_retried;
+        self.retries_exhausted += other.retries_exhausted;
+        self.transactions_aged += other.transactions_aged;
+        self.total_priority_boost = self
+            .total_priority_boost
+            .saturating_add(other.total_priority_boost);
+        self.transactions_expired += other.transactions_expired;
+        self.transactions_forwarded += other.transactions_forwarded;
+    }
+}
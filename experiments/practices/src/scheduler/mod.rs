@@ -0,0 +1,266 @@
+//! Transaction scheduling.
+//!
+//! A scheduler takes a set of transactions and splits them into batches
+//! whose members do not conflict on any account, so every transaction in a
+//! batch can execute in parallel. Which of two conflicting transactions
+//! goes first is up to the scheduler's [`PriorityPolicy`], and a
+//! streaming scheduler's [`PriorityAging`] lets a transaction that waits
+//! long enough overtake ones that pay more.
+//!
+//! [`Scheduler`] splits a whole slice up front. [`TransactionScheduler`] is
+//! the streaming counterpart used by the [`WorkerPool`]: transactions are
+//! pushed as they arrive and handed out batch by batch while earlier work
+//! is still executing. [`MultiIteratorScheduler`] is the priority ordered
+//! scan that came before the graph, kept to compare against it.
+//!
+//! [`export_dot`] renders the priority graph of a set of transactions for
+//! inspection with Graphviz, a [`SchedulerCheckpoint`] captures what a
+//! streaming scheduler holds mid-slot, and [`forwarding`] simulates two
+//! nodes forwarding queued transactions to each other. A
+//! [`ContentionReport`] ranks the accounts that held transactions up the
+//! most over a recorded run.
+
+mod bundle;
+mod checkpoint;
+pub mod contention;
+mod deadline;
+mod dot;
+mod fifo_scheduler;
+pub mod forwarding;
+mod greedy_scheduler;
+mod metrics;
+mod multi_iterator_scheduler;
+mod prio_graph_scheduler;
+mod priority_aging;
+mod priority_policy;
+pub mod schedule_log;
+pub mod worker_pool;
+
+pub use {
+    bundle::{Bundle, BundleError, MAX_BUNDLE_LEN},
+    checkpoint::SchedulerCheckpoint,
+    contention::{AccountContention, ContentionReport},
+    deadline::{Deadline, DropReason, DroppedTransaction},
+    dot::export_dot,
+    fifo_scheduler::FifoScheduler,
+    forwarding::{ForwardingConfig, ForwardingPolicy, ForwardingSimulation},
+    greedy_scheduler::GreedyScheduler,
+    metrics::SchedulerMetrics,
+    multi_iterator_scheduler::MultiIteratorScheduler,
+    prio_graph_scheduler::{PriorityGraphScheduler, TransactionPriorityId, DEFAULT_MAX_RETRIES},
+    priority_aging::PriorityAging,
+    priority_policy::PriorityPolicy,
+    schedule_log::{ScheduleEvent, ScheduleLog, ScheduleLogError, WorkAssignment},
+    worker_pool::{WorkerPool, WorkerPoolError},
+};
+
+use {
+    crate::compute_budget::process_compute_budget_instructions,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError, std::ops::Range,
+};
+
+/// A policy that splits transactions into conflict-free batches.
+///
+/// Implementations only differ in how they pack transactions; they all
+/// promise that batches run one after another and that no two transactions
+/// in the same batch conflict on an account.
+pub trait Scheduler {
+    fn schedule(&mut self, batch: &[SanitizedTransaction]) -> Vec<ScheduleBatch>;
+}
+
+/// A set of transactions that can execute concurrently.
+///
+/// Entries are indexes into the slice that was handed to the scheduler,
+/// ordered from highest to lowest priority.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScheduleBatch {
+    pub transaction_indexes: Vec<usize>,
+}
+
+/// Id a [`TransactionScheduler`] assigns to a pushed transaction.
+///
+/// Ids are handed out in push order, starting from zero.
+pub type TransactionId = usize;
+
+/// A streaming scheduling strategy.
+///
+/// Transactions handed out by [`Self::next_batch`] hold their account locks
+/// until they are passed back to [`Self::complete`], so a batch never
+/// conflicts with itself or with any earlier batch that is still in flight.
+pub trait TransactionScheduler {
+    /// Queues `transaction` for scheduling.
+    fn push(&mut self, transaction: SanitizedTransaction) -> TransactionId;
+
+    /// Takes queued transactions that can run right now, until the batch
+    /// reaches one of `limits`.
+    ///
+    /// An empty batch means every queued transaction is waiting on a
+    /// transaction that has not completed yet.
+    fn next_batch(&mut self, limits: &BatchLimits) -> Batch;
+
+    /// Releases the locks held by executed transactions.
+    fn complete(&mut self, ids: &[TransactionId]);
+
+    /// Number of pushed transactions that have not been handed out yet.
+    fn num_pending(&self) -> usize;
+
+    /// Releases the locks of an executed transaction that failed with a
+    /// [transient error](is_transient_error) and queues it again under the
+    /// same id.
+    ///
+    /// Returns `false` if the transaction is not retried, in which case it
+    /// is completed instead. By default no transaction is retried.
+    fn retry(&mut self, id: TransactionId, transaction: SanitizedTransaction) -> bool {
+        drop(transaction);
+        self.complete(&[id]);
+        false
+    }
+}
+
+/// Whether a transaction that failed with `err` may succeed if it runs
+/// again later in the same slot: its accounts were in use, or it did not
+/// fit into the block's cost limits next to the transactions ahead of it.
+pub fn is_transient_error(err: &TransactionError) -> bool {
+    matches!(
+        err,
+        TransactionError::AccountInUse
+            | TransactionError::WouldExceedMaxBlockCostLimit
+            | TransactionError::WouldExceedMaxAccountCostLimit
+            | TransactionError::WouldExceedMaxVoteCostLimit
+            | TransactionError::WouldExceedAccountDataBlockLimit
+    )
+}
+
+/// Transactions handed out together by a [`TransactionScheduler`].
+///
+/// `ids` and `transactions` line up entry by entry. A [`Bundle`] is handed
+/// out whole, as consecutive entries in bundle order.
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    pub ids: Vec<TransactionId>,
+    pub transactions: Vec<SanitizedTransaction>,
+    /// The entries of every bundle in the batch. They must run one after
+    /// another, on the same worker.
+    pub bundles: Vec<Range<usize>>,
+}
+
+impl Batch {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn push(&mut self, id: TransactionId, transaction: SanitizedTransaction) {
+        self.ids.push(id);
+        self.transactions.push(transaction);
+    }
+
+    /// Adds the transactions of a bundle, whose ids start at `first_id`.
+    fn push_bundle(&mut self, first_id: TransactionId, transactions: Vec<SanitizedTransaction>) {
+        let start = self.len();
+        for (id, transaction) in (first_id..).zip(transactions) {
+            self.push(id, transaction);
+        }
+        self.bundles.push(start..self.len());
+    }
+}
+
+/// Caps on the size of a single [`Batch`], similar to block packing limits.
+///
+/// Compute units and account data are counted from what each transaction
+/// requests through its compute budget instructions. A batch always takes
+/// at least one transaction, so a transaction that exceeds the limits on
+/// its own still runs, alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchLimits {
+    pub max_txs: usize,
+    pub max_cus: u64,
+    pub max_account_data_size: u64,
+}
+
+impl Default for BatchLimits {
+    /// No limits at all; batches are only bounded by account conflicts.
+    fn default() -> Self {
+        Self {
+            max_txs: usize::MAX,
+            max_cus: u64::MAX,
+            max_account_data_size: u64::MAX,
+        }
+    }
+}
+
+/// What a transaction counts against [`BatchLimits`].
+#[derive(Clone, Copy, Debug, Default)]
+struct BatchCost {
+    compute_units: u64,
+    account_data_size: u64,
+}
+
+impl BatchCost {
+    fn from_transaction(transaction: &SanitizedTransaction) -> Self {
+        // A transaction with invalid compute budget instructions fails
+        // before executing anything, so it costs nothing.
+        process_compute_budget_instructions(transaction.message())
+            .map(|limits| Self {
+                compute_units: u64::from(limits.compute_unit_limit),
+                account_data_size: u64::from(limits.loaded_accounts_bytes),
+            })
+            .unwrap_or_default()
+    }
+
+    /// The costs of all transactions of `bundle` together.
+    fn from_bundle(bundle: &Bundle) -> Self {
+        bundle
+            .transactions()
+            .iter()
+            .map(Self::from_transaction)
+            .fold(Self::default(), |total, cost| Self {
+                compute_units: total.compute_units.saturating_add(cost.compute_units),
+                account_data_size: total
+                    .account_data_size
+                    .saturating_add(cost.account_data_size),
+            })
+    }
+}
+
+/// Running totals of a batch that is being filled.
+struct BatchBudget<'a> {
+    limits: &'a BatchLimits,
+    num_txs: usize,
+    compute_units: u64,
+    account_data_size: u64,
+}
+
+impl<'a> BatchBudget<'a> {
+    fn new(limits: &'a BatchLimits) -> Self {
+        Self {
+            limits,
+            num_txs: 0,
+            compute_units: 0,
+            account_data_size: 0,
+        }
+    }
+
+    /// Adds `cost` if it fits within the limits, or if the batch is empty.
+    fn try_add(&mut self, cost: &BatchCost) -> bool {
+        let compute_units = self.compute_units.saturating_add(cost.compute_units);
+        let account_data_size = self
+            .account_data_size
+            .saturating_add(cost.account_data_size);
+        let fits = self.num_txs < self.limits.max_txs
+            && compute_units <= self.limits.max_cus
+            && account_data_size <= self.limits.max_account_data_size;
+        if !fits && self.num_txs > 0 {
+            return false;
+        }
+
+        self.num_txs += 1;
+        self.compute_units = compute_units;
+        self.account_data_size = account_data_size;
+        true
+    }
+}
@@ -0,0 +1,126 @@
+use {
+    super::{
+        fifo_scheduler::InFlightLocks, Batch, BatchBudget, BatchCost, BatchLimits, PriorityPolicy,
+        TransactionId, TransactionPriorityId, TransactionScheduler,
+    },
+    crate::accounts::{LockSet, LockSetConfig},
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        cmp::Reverse,
+        collections::{BTreeMap, HashMap},
+        ops::Bound,
+    },
+};
+
+/// Ranks the queue highest priority first.
+type QueueKey = Reverse<TransactionPriorityId>;
+
+struct QueuedTransaction {
+    transaction: SanitizedTransaction,
+    lock_set: LockSet,
+    cost: BatchCost,
+}
+
+/// Hands out transactions the way the banking stage did before the
+/// priority graph: by scanning a priority ordered buffer.
+///
+/// Each batch slot is filled by an iterator that moves down the queue to
+/// the next transaction that conflicts with neither an in-flight
+/// transaction nor one already in the batch. Transactions it skips stay
+/// queued for a later pass. Every call to `next_batch` picks the scan up
+/// where the previous one stopped and wraps around at the end of the
+/// queue, so it visits each queued transaction at most once.
+///
+/// Nothing remembers the order between conflicting transactions: a
+/// skipped transaction can be overtaken by a lower priority one on the
+/// same accounts, and one that arrives above the scan's position waits
+/// for the scan to wrap around, however much it pays. That is what the
+/// [`PriorityGraphScheduler`](super::PriorityGraphScheduler) was built to
+/// fix, and this scheduler is kept to measure it against.
+#[derive(Default)]
+pub struct MultiIteratorScheduler {
+    priority_policy: PriorityPolicy,
+    next_id: TransactionId,
+    queue: BTreeMap<QueueKey, QueuedTransaction>,
+    /// The last transaction the previous scan visited.
+    cursor: Option<QueueKey>,
+    /// The locks of every handed out transaction, by id.
+    in_flight: HashMap<TransactionId, LockSet>,
+    locks: InFlightLocks,
+}
+
+impl MultiIteratorScheduler {
+    /// Creates a scheduler that ranks transactions by
+    /// [`PriorityPolicy::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_priority_policy(priority_policy: PriorityPolicy) -> Self {
+        Self {
+            priority_policy,
+            ..Self::default()
+        }
+    }
+}
+
+impl TransactionScheduler for MultiIteratorScheduler {
+    fn push(&mut self, transaction: SanitizedTransaction) -> TransactionId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let priority_id = self.priority_policy.priority_id(&transaction, id);
+        self.queue.insert(
+            Reverse(priority_id),
+            QueuedTransaction {
+                lock_set: LockSet::from_transaction(&transaction, LockSetConfig::default()),
+                cost: BatchCost::from_transaction(&transaction),
+                transaction,
+            },
+        );
+        id
+    }
+
+    fn next_batch(&mut self, limits: &BatchLimits) -> Batch {
+        let mut budget = BatchBudget::new(limits);
+        let mut taken = Vec::new();
+        let start = self.cursor.map_or(Bound::Unbounded, Bound::Excluded);
+        let scan = self.queue.range((start, Bound::Unbounded)).chain(
+            self.cursor
+                .into_iter()
+                .flat_map(|cursor| self.queue.range(..=cursor)),
+        );
+        for (key, queued) in scan {
+            if !self.locks.conflicts_with(&queued.lock_set) {
+                if !budget.try_add(&queued.cost) {
+                    break;
+                }
+                self.locks.lock(&queued.lock_set);
+                taken.push(*key);
+            }
+            self.cursor = Some(*key);
+        }
+
+        let mut batch = Batch::default();
+        for key in taken {
+            let queued = self.queue.remove(&key).unwrap();
+            self.in_flight.insert(key.0.index, queued.lock_set);
+            batch.push(key.0.index, queued.transaction);
+        }
+        batch
+    }
+
+    fn complete(&mut self, ids: &[TransactionId]) {
+        for id in ids {
+            let lock_set = self
+                .in_flight
+                .remove(id)
+                .expect("completed transaction must be in flight");
+            self.locks.unlock(&lock_set);
+        }
+    }
+
+    fn num_pending(&self) -> usize {
+        self.queue.len()
+    }
+}
@@ -0,0 +1,869 @@
+use {
+    super::{
+        dot::lock_edges, Batch, BatchBudget, BatchCost, BatchLimits, Bundle, Deadline, DropReason,
+        DroppedTransaction, PriorityAging, PriorityPolicy, ScheduleBatch, Scheduler,
+        SchedulerCheckpoint, SchedulerMetrics, TransactionId, TransactionScheduler,
+    },
+    crate::{
+        accounts::{LockSet, LockSetConfig},
+        determinism::{Determinism, TIE_BREAK_STREAM},
+        events::{Event, EventBus},
+    },
+    prio_graph::{GraphNode, PrioGraph, TopLevelId},
+    rand::{rngs::StdRng, Rng},
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        cmp::{Ordering, Reverse},
+        collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+        time::Instant,
+    },
+};
+
+/// Times [`PriorityGraphScheduler`] retries a transaction unless
+/// configured otherwise.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Identifies a transaction inside the priority graph.
+///
+/// Transactions are ordered by `priority` first, `tie_breaker` second and
+/// `shuffle` third. Among equal ones the transaction that arrived first
+/// (lowest `index`) wins, so the graph never reorders otherwise identical
+/// transactions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TransactionPriorityId {
+    pub priority: u64,
+    pub tie_breaker: u64,
+    /// A random rank among transactions that are equal otherwise; 0 unless
+    /// the scheduler breaks ties randomly.
+    pub shuffle: u64,
+    pub index: usize,
+}
+
+impl TransactionPriorityId {
+    pub fn new(priority: u64, index: usize) -> Self {
+        Self::with_tie_breaker(priority, 0, index)
+    }
+
+    pub fn with_tie_breaker(priority: u64, tie_breaker: u64, index: usize) -> Self {
+        Self {
+            priority,
+            tie_breaker,
+            shuffle: 0,
+            index,
+        }
+    }
+}
+
+impl Ord for TransactionPriorityId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| self.tie_breaker.cmp(&other.tie_breaker))
+            .then_with(|| self.shuffle.cmp(&other.shuffle))
+            .then_with(|| Reverse(self.index).cmp(&Reverse(other.index)))
+    }
+}
+
+impl PartialOrd for TransactionPriorityId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl TopLevelId<Self> for TransactionPriorityId {
+    fn id(&self) -> Self {
+        *self
+    }
+}
+
+/// A pushed transaction, or bundle, that has not been handed out yet.
+struct QueuedTransaction {
+    /// The transaction, or the transactions of the bundle in bundle order.
+    /// Their ids count up from the id they are queued under.
+    transactions: Vec<SanitizedTransaction>,
+    is_bundle: bool,
+    /// The locks of all of `transactions`, which make the node's edges.
+    lock_set: LockSet,
+    cost: BatchCost,
+    /// The id the node is inserted into the graph with.
+    priority_id: TransactionPriorityId,
+    /// `batches_handed_out` when the node was pushed.
+    queued_at: u64,
+    /// How many nodes were inserted into the graph before this one, once
+    /// it is in the graph.
+    graph_position: Option<u64>,
+}
+
+/// A graph node whose transactions were handed out.
+struct InFlightNode {
+    priority_id: TransactionPriorityId,
+    /// `batches_handed_out` when the node was pushed.
+    queued_at: u64,
+    /// Transactions of the node that have not completed yet.
+    num_in_flight: usize,
+    lock_set: LockSet,
+    graph_position: u64,
+}
+
+type PriorityFunction =
+    fn(&TransactionPriorityId, &GraphNode<TransactionPriorityId>) -> TransactionPriorityId;
+
+type SchedulerPrioGraph =
+    PrioGraph<TransactionPriorityId, Pubkey, TransactionPriorityId, PriorityFunction>;
+
+fn passthrough_priority(
+    id: &TransactionPriorityId,
+    _graph_node: &GraphNode<TransactionPriorityId>,
+) -> TransactionPriorityId {
+    *id
+}
+
+/// Schedules [`SanitizedTransaction`]s with a [`PrioGraph`].
+///
+/// Account locks are derived from each transaction's [`LockSet`] and the
+/// priority from its [`PriorityPolicy`], so callers only hand over
+/// transactions; the graph edges follow from the accounts those
+/// transactions read and write.
+///
+/// A [`Bundle`] is a single node of the graph, whose edges are the locks of
+/// all its transactions together. It is serialized against every
+/// conflicting transaction like one transaction would be, and does not
+/// unblock anything until its last transaction completed.
+pub struct PriorityGraphScheduler {
+    prio_graph: SchedulerPrioGraph,
+    priority_policy: PriorityPolicy,
+    next_id: TransactionId,
+    /// Pushed transactions that are not in the graph yet. They are inserted
+    /// on the next call to `next_batch`, highest priority first.
+    pending_ids: BinaryHeap<TransactionPriorityId>,
+    /// Transactions popped from the graph that did not fit into a batch
+    /// yet, ranked by their aged priority. They hold their place in the
+    /// graph until handed out.
+    unblocked_ids: BinaryHeap<TransactionPriorityId>,
+    /// Queued nodes by the id of their first transaction.
+    transactions: HashMap<TransactionId, QueuedTransaction>,
+    /// Handed out nodes that still block the graph.
+    in_flight: HashMap<TransactionId, InFlightNode>,
+    /// The node every in-flight bundle transaction belongs to.
+    bundle_ids: HashMap<TransactionId, TransactionId>,
+    /// Transactions that were blocked when inserted into the graph and
+    /// have not been handed out yet.
+    blocked_ids: HashSet<TransactionId>,
+    priority_aging: PriorityAging,
+    max_retries: u32,
+    /// Retried transactions backing off, with the value of
+    /// `batches_handed_out` at which they rejoin the graph.
+    backoff_ids: Vec<(u64, TransactionPriorityId)>,
+    batches_handed_out: u64,
+    /// Nodes inserted into the graph so far.
+    num_inserted: u64,
+    /// How many queued nodes the graph holds at most.
+    look_ahead_window: usize,
+    /// Queued nodes in the graph, which have not been handed out.
+    num_in_window: usize,
+    /// How often every retried transaction was retried.
+    retry_counts: HashMap<TransactionId, u32>,
+    /// Deadlines of the transactions that were pushed with one and have
+    /// not completed yet.
+    deadlines: HashMap<TransactionId, Deadline>,
+    /// The slot deadlines are checked against.
+    slot: Slot,
+    dropped: Vec<DroppedTransaction>,
+    event_bus: Option<EventBus>,
+    /// Draws the `shuffle` of every pushed transaction, if ties are broken
+    /// randomly.
+    tie_break_rng: Option<StdRng>,
+    metrics: SchedulerMetrics,
+}
+
+impl Default for PriorityGraphScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler for PriorityGraphScheduler {
+    /// Splits `batch` into conflict-free [`ScheduleBatch`]es.
+    ///
+    /// Transactions are prioritized by the scheduler's [`PriorityPolicy`],
+    /// then by arrival order.
+    /// Each returned batch only contains transactions whose blockers were
+    /// all emitted in earlier batches, so the batches must execute one after
+    /// another while the members of a single batch may execute in parallel.
+    ///
+    /// This builds a separate graph and leaves transactions queued through
+    /// [`TransactionScheduler`] untouched.
+    fn schedule(&mut self, batch: &[SanitizedTransaction]) -> Vec<ScheduleBatch> {
+        let mut prio_graph = SchedulerPrioGraph::new(passthrough_priority);
+        for id in self.insertion_order(batch) {
+            let lock_set = LockSet::from_transaction(&batch[id.index], LockSetConfig::default());
+            prio_graph.insert_transaction(id, lock_set.iter());
+        }
+
+        let batches: Vec<ScheduleBatch> = prio_graph
+            .make_natural_batches()
+            .into_iter()
+            .map(|ids| ScheduleBatch {
+                transaction_indexes: ids.into_iter().map(|id| id.index).collect(),
+            })
+            .collect();
+        // Every transaction nothing blocked is in the first natural batch.
+        let unblocked = batches
+            .first()
+            .map_or(0, |batch| batch.transaction_indexes.len());
+        self.metrics.accumulate(&SchedulerMetrics {
+            transactions_queued: batch.len() as u64,
+            transactions_scheduled: batch.len() as u64,
+            batches_emitted: batches.len() as u64,
+            transactions_blocked: (batch.len() - unblocked) as u64,
+            ..SchedulerMetrics::default()
+        });
+        batches
+    }
+}
+
+impl TransactionScheduler for PriorityGraphScheduler {
+    fn push(&mut self, transaction: SanitizedTransaction) -> TransactionId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let priority_id = self.break_tie(self.priority_policy.priority_id(&transaction, id));
+        self.pending_ids.push(priority_id);
+        self.transactions.insert(
+            id,
+            QueuedTransaction {
+                lock_set: LockSet::from_transaction(&transaction, LockSetConfig::default()),
+                cost: BatchCost::from_transaction(&transaction),
+                transactions: vec![transaction],
+                is_bundle: false,
+                priority_id,
+                queued_at: self.batches_handed_out,
+                graph_position: None,
+            },
+        );
+        self.metrics.transactions_queued += 1;
+        id
+    }
+
+    /// Inserts everything pushed since the last call into the graph, then
+    /// hands out unblocked transactions, highest priority first, until the
+    /// next one would exceed `limits`. With a
+    /// [look-ahead window](Self::with_look_ahead_window), only as many as
+    /// the window holds are inserted, and every transaction handed out lets
+    /// the next one in.
+    ///
+    /// A transaction pushed after a conflicting transaction was already
+    /// inserted queues behind it, even if it pays a higher priority.
+    /// Retried transactions rejoin the graph once their backoff is over, or
+    /// as soon as nothing else could run.
+    ///
+    /// Among unblocked transactions, those that waited for earlier batches
+    /// rank by the priority their [`PriorityAging`] gave them since.
+    ///
+    /// Transactions whose [`Deadline`] passed are dropped once nothing
+    /// ahead of them blocks them any more, rather than handed out.
+    fn next_batch(&mut self, limits: &BatchLimits) -> Batch {
+        let now = Instant::now();
+        let is_idle = self.in_flight.is_empty()
+            && self.pending_ids.is_empty()
+            && self.unblocked_ids.is_empty()
+            && self.prio_graph.is_empty();
+        let batches_handed_out = self.batches_handed_out;
+        let pending_ids = &mut self.pending_ids;
+        self.backoff_ids.retain(|&(rejoin_at, id)| {
+            let rejoins = is_idle || rejoin_at <= batches_handed_out;
+            if rejoins {
+                pending_ids.push(id);
+            }
+            !rejoins
+        });
+        self.fill_look_ahead_window(now);
+        // Dropping an unblocked transaction may unblock others, which the
+        // graph then pops.
+        let unblocked_ids = std::mem::take(&mut self.unblocked_ids);
+        for id in unblocked_ids {
+            if self.is_expired(id.index, now) {
+                let priority_id = self.drop_expired(id.index);
+                self.prio_graph.unblock(&priority_id);
+            } else {
+                self.unblocked_ids.push(id);
+            }
+        }
+        self.pop_unblocked(now);
+        if !matches!(self.priority_aging, PriorityAging::None) {
+            let unblocked_ids = std::mem::take(&mut self.unblocked_ids);
+            self.unblocked_ids = unblocked_ids
+                .into_iter()
+                .map(|id| self.aged_priority_id(id.index))
+                .collect();
+        }
+
+        let mut batch = Batch::default();
+        let mut budget = BatchBudget::new(limits);
+        while let Some(id) = self.unblocked_ids.peek() {
+            if !budget.try_add(&self.transactions[&id.index].cost) {
+                break;
+            }
+
+            let aged_id = self.unblocked_ids.pop().unwrap();
+            let queued = self
+                .transactions
+                .remove(&aged_id.index)
+                .expect("graph only holds pushed transactions");
+            self.num_in_window -= 1;
+            let priority_boost = aged_id.priority - queued.priority_id.priority;
+            if priority_boost > 0 {
+                self.metrics.transactions_aged += 1;
+                self.metrics.total_priority_boost = self
+                    .metrics
+                    .total_priority_boost
+                    .saturating_add(priority_boost);
+            }
+            let num_transactions = queued.transactions.len();
+            self.in_flight.insert(
+                aged_id.index,
+                InFlightNode {
+                    priority_id: queued.priority_id,
+                    queued_at: queued.queued_at,
+                    num_in_flight: num_transactions,
+                    lock_set: queued.lock_set,
+                    graph_position: queued
+                        .graph_position
+                        .expect("handed out transactions are in the graph"),
+                },
+            );
+            if self.blocked_ids.remove(&aged_id.index) {
+                self.metrics.transactions_blocked += num_transactions as u64;
+            }
+            let mut transactions = queued.transactions;
+            if let Some(event_bus) = &self.event_bus {
+                for transaction in &transactions {
+                    event_bus.publish(Event::TransactionScheduled {
+                        slot: self.slot,
+                        signature: *transaction.signature(),
+                    });
+                }
+            }
+            if queued.is_bundle {
+                for id in aged_id.index..aged_id.index + num_transactions {
+                    self.bundle_ids.insert(id, aged_id.index);
+                }
+                batch.push_bundle(aged_id.index, transactions);
+            } else {
+                batch.push(aged_id.index, transactions.remove(0));
+            }
+            // Handing a transaction out makes room in the window for the
+            // next pending one, which may fit into this batch still.
+            if !self.pending_ids.is_empty() {
+                self.fill_look_ahead_window(now);
+                self.pop_unblocked(now);
+            }
+        }
+        if !batch.is_empty() {
+            self.metrics.transactions_scheduled += batch.len() as u64;
+            self.metrics.batches_emitted += 1;
+            self.batches_handed_out += 1;
+        }
+        self.clear_graph_if_done();
+        batch
+    }
+
+    fn complete(&mut self, ids: &[TransactionId]) {
+        for id in ids {
+            let node_id = self.bundle_ids.remove(id).unwrap_or(*id);
+            let node = self
+                .in_flight
+                .get_mut(&node_id)
+                .expect("completed transaction must be in flight");
+            node.num_in_flight -= 1;
+            if node.num_in_flight == 0 {
+                let node = self.in_flight.remove(&node_id).unwrap();
+                self.prio_graph.unblock(&node.priority_id);
+                self.deadlines.remove(&node_id);
+            }
+        }
+        self.clear_graph_if_done();
+    }
+
+    fn num_pending(&self) -> usize {
+        self.transactions
+            .values()
+            .map(|queued| queued.transactions.len())
+            .sum()
+    }
+
+    /// Queues `transaction` again with its original priority, unless it
+    /// was retried [`max_retries`](Self::max_retries) times already.
+    ///
+    /// The `n`th retry backs off until `2^(n - 1)` more batches have been
+    /// handed out, so the transactions that turned it away get to finish
+    /// first.
+    ///
+    /// The transactions of a bundle are never retried on their own.
+    fn retry(&mut self, id: TransactionId, transaction: SanitizedTransaction) -> bool {
+        if self.bundle_ids.contains_key(&id) {
+            self.complete(&[id]);
+            return false;
+        }
+        let retries = self.retry_counts.get(&id).copied().unwrap_or_default();
+        if retries >= self.max_retries {
+            self.metrics.retries_exhausted += 1;
+            self.complete(&[id]);
+            return false;
+        }
+
+        let InFlightNode {
+            priority_id,
+            queued_at,
+            ..
+        } = self
+            .in_flight
+            .remove(&id)
+            .expect("retried transaction must be in flight");
+        self.prio_graph.unblock(&priority_id);
+        // The transaction keeps aging from when it was first pushed.
+        self.transactions.insert(
+            id,
+            QueuedTransaction {
+                lock_set: LockSet::from_transaction(&transaction, LockSetConfig::default()),
+                cost: BatchCost::from_transaction(&transaction),
+                transactions: vec![transaction],
+                is_bundle: false,
+                priority_id,
+                queued_at,
+                graph_position: None,
+            },
+        );
+        let rejoin_at = self.batches_handed_out.saturating_add(1 << retries.min(63));
+        self.backoff_ids.push((rejoin_at, priority_id));
+        self.retry_counts.insert(id, retries + 1);
+        self.metrics.transactions_retried += 1;
+        true
+    }
+}
+
+impl PriorityGraphScheduler {
+    /// Priority ids of `batch` in the order [`Scheduler::schedule`] inserts
+    /// them into its graph.
+    pub(super) fn insertion_order(
+        &self,
+        batch: &[SanitizedTransaction],
+    ) -> Vec<TransactionPriorityId> {
+        let mut priority_ids: Vec<_> = batch
+            .iter()
+            .enumerate()
+            .map(|(index, transaction)| self.priority_policy.priority_id(transaction, index))
+            .collect();
+        // The graph expects transactions in priority order: between two
+        // conflicting transactions, the one inserted first runs first.
+        priority_ids.sort_unstable_by(|a, b| b.cmp(a));
+        priority_ids
+    }
+
+    /// Creates a scheduler that ranks transactions by
+    /// [`PriorityPolicy::default`].
+    pub fn new() -> Self {
+        Self::with_priority_policy(PriorityPolicy::default())
+    }
+
+    pub fn with_priority_policy(priority_policy: PriorityPolicy) -> Self {
+        Self {
+            prio_graph: PrioGraph::new(passthrough_priority),
+            priority_policy,
+            next_id: 0,
+            pending_ids: BinaryHeap::new(),
+            unblocked_ids: BinaryHeap::new(),
+            transactions: HashMap::new(),
+            in_flight: HashMap::new(),
+            bundle_ids: HashMap::new(),
+            blocked_ids: HashSet::new(),
+            priority_aging: PriorityAging::None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            backoff_ids: Vec::new(),
+            batches_handed_out: 0,
+            num_inserted: 0,
+            look_ahead_window: usize::MAX,
+            num_in_window: 0,
+            retry_counts: HashMap::new(),
+            deadlines: HashMap::new(),
+            slot: 0,
+            dropped: Vec::new(),
+            event_bus: None,
+            tie_break_rng: None,
+            metrics: SchedulerMetrics::default(),
+        }
+    }
+
+    /// Queues the transactions of `bundle` as one node of the graph and
+    /// returns their ids, in bundle order.
+    ///
+    /// The bundle ranks by its
+    /// [`PriorityPolicy::bundle_priority_id`] and is handed out whole, as
+    /// consecutive entries of one [`Batch`].
+    pub fn push_bundle(&mut self, bundle: Bundle) -> Vec<TransactionId> {
+        let first_id = self.next_id;
+        self.next_id += bundle.len();
+
+        let priority_id =
+            self.break_tie(self.priority_policy.bundle_priority_id(&bundle, first_id));
+        self.pending_ids.push(priority_id);
+        self.metrics.transactions_queued += bundle.len() as u64;
+        self.transactions.insert(
+            first_id,
+            QueuedTransaction {
+                lock_set: bundle.lock_set(LockSetConfig::default()),
+                cost: BatchCost::from_bundle(&bundle),
+                transactions: bundle.into_transactions(),
+                is_bundle: true,
+                priority_id,
+                queued_at: self.batches_handed_out,
+                graph_position: None,
+            },
+        );
+        (first_id..self.next_id).collect()
+    }
+
+    /// Queues `transaction` like [`TransactionScheduler::push`], to be
+    /// dropped with [`DropReason::Expired`] if `deadline` passes before it
+    /// is handed out.
+    ///
+    /// A retried transaction keeps its deadline.
+    pub fn push_with_deadline(
+        &mut self,
+        transaction: SanitizedTransaction,
+        deadline: Deadline,
+    ) -> TransactionId {
+        let id = self.push(transaction);
+        self.deadlines.insert(id, deadline);
+        id
+    }
+
+    /// Moves the scheduler to `slot`, which [`Deadline::Slot`]s are checked
+    /// against.
+    pub fn set_slot(&mut self, slot: Slot) {
+        self.slot = slot;
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Takes up to `max_transactions` queued transactions nothing blocks,
+    /// highest priority first, to hand them to another node.
+    ///
+    /// Transactions still blocked in the graph, in flight or backing off
+    /// after a retry stay, and so do bundles. Taking a transaction
+    /// unblocks the ones that queued behind it.
+    pub fn take_for_forwarding(&mut self, max_transactions: usize) -> Vec<DroppedTransaction> {
+        while let Some(id) = self.prio_graph.pop() {
+            self.unblocked_ids.push(id);
+        }
+        // Whether each candidate is in the graph already.
+        let mut candidates: Vec<(TransactionPriorityId, bool)> = self
+            .pending_ids
+            .drain()
+            .map(|id| (id, false))
+            .chain(self.unblocked_ids.drain().map(|id| (id, true)))
+            .collect();
+        for (id, _) in &mut candidates {
+            *id = self.aged_priority_id(id.index);
+        }
+        candidates.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+        let (bundles, mut candidates): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|(id, _)| self.transactions[&id.index].is_bundle);
+
+        let kept = candidates.split_off(max_transactions.min(candidates.len()));
+        for (id, in_graph) in kept.into_iter().chain(bundles) {
+            if in_graph {
+                self.unblocked_ids.push(id);
+            } else {
+                self.pending_ids
+                    .push(self.transactions[&id.index].priority_id);
+            }
+        }
+        let forwarded = candidates
+            .into_iter()
+            .map(|(id, in_graph)| {
+                let (priority_id, dropped) = self.drop_transaction(id.index, DropReason::Forwarded);
+                if in_graph {
+                    self.prio_graph.unblock(&priority_id);
+                }
+                dropped
+            })
+            .collect();
+        self.clear_graph_if_done();
+        forwarded
+    }
+
+    /// Returns the transactions dropped since the last call.
+    pub fn take_dropped(&mut self) -> Vec<DroppedTransaction> {
+        std::mem::take(&mut self.dropped)
+    }
+
+    fn is_expired(&self, index: TransactionId, now: Instant) -> bool {
+        self.deadlines
+            .get(&index)
+            .is_some_and(|deadline| deadline.is_expired(self.slot, now))
+    }
+
+    /// Drops the expired transaction `index` and returns its graph id,
+    /// which a caller whose transaction is in the graph still has to
+    /// unblock.
+    fn drop_expired(&mut self, index: TransactionId) -> TransactionPriorityId {
+        let (priority_id, dropped) = self.drop_transaction(index, DropReason::Expired);
+        self.dropped.push(dropped);
+        priority_id
+    }
+
+    /// Forgets the queued transaction `index`, like
+    /// [`drop_expired`](Self::drop_expired).
+    fn drop_transaction(
+        &mut self,
+        index: TransactionId,
+        reason: DropReason,
+    ) -> (TransactionPriorityId, DroppedTransaction) {
+        let mut queued = self
+            .transactions
+            .remove(&index)
+            .expect("only queued transactions are dropped");
+        debug_assert!(!queued.is_bundle, "bundles are never dropped");
+        self.deadlines.remove(&index);
+        self.blocked_ids.remove(&index);
+        if queued.graph_position.is_some() {
+            self.num_in_window -= 1;
+        }
+        match reason {
+            DropReason::Expired => self.metrics.transactions_expired += 1,
+            DropReason::Forwarded => self.metrics.transactions_forwarded += 1,
+        }
+        let dropped = DroppedTransaction {
+            id: index,
+            transaction: queued.transactions.remove(0),
+            reason,
+        };
+        if let Some(event_bus) = &self.event_bus {
+            event_bus.publish(Event::TransactionDropped {
+                slot: self.slot,
+                signature: *dropped.transaction.signature(),
+                reason: reason.into(),
+            });
+        }
+        (queued.priority_id, dropped)
+    }
+
+    /// Inserts pending transactions into the graph, highest priority first,
+    /// until the look-ahead window is full.
+    fn fill_look_ahead_window(&mut self, now: Instant) {
+        while self.num_in_window < self.look_ahead_window {
+            let Some(id) = self.pending_ids.pop() else {
+                break;
+            };
+            if self.is_expired(id.index, now) {
+                self.drop_expired(id.index);
+                continue;
+            }
+            let queued = self.transactions.get_mut(&id.index).unwrap();
+            queued.graph_position = Some(self.num_inserted);
+            self.num_inserted += 1;
+            self.num_in_window += 1;
+            self.prio_graph
+                .insert_transaction(id, queued.lock_set.iter());
+            if self.prio_graph.is_blocked(id) {
+                self.blocked_ids.insert(id.index);
+            }
+        }
+    }
+
+    /// Moves every transaction the graph unblocked to `unblocked_ids`,
+    /// dropping the expired ones instead.
+    fn pop_unblocked(&mut self, now: Instant) {
+        while let Some(id) = self.prio_graph.pop() {
+            if self.is_expired(id.index, now) {
+                self.drop_expired(id.index);
+                self.prio_graph.unblock(&id);
+            } else {
+                self.unblocked_ids.push(self.aged_priority_id(id.index));
+            }
+        }
+    }
+
+    /// Completed nodes stay in the graph until it is cleared, so clear it
+    /// as soon as nothing is left to schedule.
+    fn clear_graph_if_done(&mut self) {
+        if self.transactions.is_empty() && self.in_flight.is_empty() {
+            self.prio_graph.clear();
+        }
+    }
+
+    /// Raises the priority of transactions queued through
+    /// [`TransactionScheduler`] as they wait. [`Scheduler::schedule`] takes
+    /// a whole slice at once, so nothing waits there.
+    pub fn with_priority_aging(mut self, priority_aging: PriorityAging) -> Self {
+        self.priority_aging = priority_aging;
+        self
+    }
+
+    pub fn priority_aging(&self) -> PriorityAging {
+        self.priority_aging
+    }
+
+    /// Only lets the `window` highest priority queued transactions into the
+    /// graph at a time, like the validator's scheduler does. The rest wait
+    /// outside it, blocking nothing, until handing out transactions makes
+    /// room.
+    ///
+    /// A small window keeps the graph cheap to build, but when everything
+    /// in it is blocked, batches stay small even though transactions
+    /// further down the queue could run. By default the window is
+    /// unbounded.
+    ///
+    /// # Panics
+    ///
+    /// If `window` is 0.
+    pub fn with_look_ahead_window(mut self, window: usize) -> Self {
+        assert!(window > 0, "look-ahead window must hold a transaction");
+        self.look_ahead_window = window;
+        self
+    }
+
+    /// The look-ahead window, or `None` if it is unbounded.
+    pub fn look_ahead_window(&self) -> Option<usize> {
+        (self.look_ahead_window != usize::MAX).then_some(self.look_ahead_window)
+    }
+
+    /// The priority the queued transaction `id`, or the queued bundle whose
+    /// first transaction it is, currently ranks by, or `None` if it is not
+    /// queued.
+    pub fn effective_priority(&self, id: TransactionId) -> Option<u64> {
+        self.transactions
+            .contains_key(&id)
+            .then(|| self.aged_priority_id(id).priority)
+    }
+
+    /// A snapshot of the queued, blocked and in-flight transactions and of
+    /// the edges between them, to assert on mid-slot state.
+    ///
+    /// `PrioGraph` does not expose its edges, so they are rebuilt like
+    /// [`export_dot`](super::export_dot) does, from the locks of the nodes
+    /// still in the graph in the order they were inserted.
+    pub fn checkpoint(&self) -> SchedulerCheckpoint {
+        let mut nodes: Vec<(u64, TransactionId, &LockSet)> = self
+            .transactions
+            .iter()
+            .filter_map(|(id, queued)| Some((queued.graph_position?, *id, &queued.lock_set)))
+            .chain(
+                self.in_flight
+                    .iter()
+                    .map(|(id, node)| (node.graph_position, *id, &node.lock_set)),
+            )
+            .collect();
+        nodes.sort_unstable_by_key(|&(position, ..)| position);
+        let edges = lock_edges(nodes.iter().map(|&(_, id, lock_set)| (id, lock_set)));
+
+        let mut blocked: BTreeMap<TransactionId, Vec<TransactionId>> = BTreeMap::new();
+        for &(blocker, id) in edges.keys() {
+            blocked.entry(id).or_default().push(blocker);
+        }
+        let mut unblocked: Vec<TransactionPriorityId> = self
+            .transactions
+            .iter()
+            .filter(|(id, queued)| queued.graph_position.is_some() && !blocked.contains_key(id))
+            .map(|(id, _)| self.aged_priority_id(*id))
+            .collect();
+        unblocked.sort_unstable_by(|a, b| b.cmp(a));
+        let mut pending: Vec<TransactionId> = self
+            .transactions
+            .iter()
+            .filter(|(_, queued)| queued.graph_position.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        pending.sort_unstable();
+        let mut in_flight: Vec<TransactionId> = self.in_flight.keys().copied().collect();
+        in_flight.sort_unstable();
+        SchedulerCheckpoint {
+            slot: self.slot,
+            pending,
+            unblocked: unblocked.into_iter().map(|id| id.index).collect(),
+            in_flight,
+            blocked,
+            edges,
+        }
+    }
+
+    /// The graph id of the queued transaction `index` with its priority
+    /// aged to now.
+    fn aged_priority_id(&self, index: TransactionId) -> TransactionPriorityId {
+        let queued = &self.transactions[&index];
+        let age = self.batches_handed_out - queued.queued_at;
+        TransactionPriorityId {
+            priority: self
+                .priority_aging
+                .effective_priority(queued.priority_id.priority, age),
+            ..queued.priority_id
+        }
+    }
+
+    /// Orders transactions the [`PriorityPolicy`] ranks the same randomly,
+    /// rather than by arrival, when they are pushed through
+    /// [`TransactionScheduler`]. With [`Determinism::Seeded`] the order is
+    /// the same on every run.
+    pub fn with_random_tie_breaks(mut self, determinism: Determinism) -> Self {
+        self.tie_break_rng = Some(determinism.rng(TIE_BREAK_STREAM));
+        self
+    }
+
+    /// `priority_id` with a random `shuffle`, if ties are broken randomly.
+    fn break_tie(&mut self, priority_id: TransactionPriorityId) -> TransactionPriorityId {
+        match &mut self.tie_break_rng {
+            Some(rng) => TransactionPriorityId {
+                shuffle: rng.gen(),
+                ..priority_id
+            },
+            None => priority_id,
+        }
+    }
+
+    /// Publishes an [`Event`] to `event_bus` for every transaction handed
+    /// out through [`TransactionScheduler`], and for every one dropped.
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Retries a transaction that failed transiently at most `max_retries`
+    /// times; 0 turns retries off.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// How often the transaction `id` was retried so far.
+    pub fn retry_count(&self, id: TransactionId) -> u32 {
+        self.retry_counts.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Returns the retry count of every retried transaction and forgets
+    /// them.
+    pub fn take_retry_counts(&mut self) -> HashMap<TransactionId, u32> {
+        std::mem::take(&mut self.retry_counts)
+    }
+
+    /// What this scheduler has done since it was created or its metrics
+    /// were last taken, through both [`Scheduler`] and
+    /// [`TransactionScheduler`].
+    pub fn metrics(&self) -> &SchedulerMetrics {
+        &self.metrics
+    }
+
+    /// Returns the metrics and starts counting from zero.
+    pub fn take_metrics(&mut self) -> SchedulerMetrics {
+        std::mem::take(&mut self.metrics)
+    }
+}
@@ -0,0 +1,41 @@
+/// How a queued transaction gains priority while it waits.
+///
+/// Age is counted in batches the scheduler handed out since the
+/// transaction was pushed, so a low-fee transaction that keeps losing its
+/// place to fresh high-fee transactions eventually outranks them.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum PriorityAging {
+    /// Transactions keep the priority their [`PriorityPolicy`] gave them.
+    ///
+    /// [`PriorityPolicy`]: super::PriorityPolicy
+    #[default]
+    None,
+    /// Adds `increment` for every batch waited.
+    Linear { increment: u64 },
+    /// Doubles the priority every `half_life` batches waited; a
+    /// transaction without priority stays at zero.
+    Exponential { half_life: u64 },
+    /// Any function of the priority and the age.
+    Custom(fn(priority: u64, age: u64) -> u64),
+}
+
+impl PriorityAging {
+    /// The priority a transaction of `priority` has once it waited `age`
+    /// batches. Never below `priority`.
+    pub fn effective_priority(&self, priority: u64, age: u64) -> u64 {
+        let aged = match *self {
+            Self::None => priority,
+            Self::Linear { increment } => priority.saturating_add(increment.saturating_mul(age)),
+            Self::Exponential { half_life } => {
+                let doublings = age.checked_div(half_life).unwrap_or_default();
+                let factor = u32::try_from(doublings)
+                    .ok()
+                    .and_then(|doublings| 1u64.checked_shl(doublings))
+                    .unwrap_or(u64::MAX);
+                priority.saturating_mul(factor)
+            }
+            Self::Custom(aging) => aging(priority, age),
+        };
+        aged.max(priority)
+    }
+}
@@ -0,0 +1,139 @@
+use {
+    super::{Bundle, TransactionPriorityId},
+    crate::{
+        compute_budget::process_compute_budget_instructions,
+        cost_model::CostModel,
+        fees::{FeeStructure, PriorityFeeCalculator},
+    },
+    solana_transaction::sanitized::SanitizedTransaction,
+};
+
+/// Scale of the reward/cost ratio, so ratios below one lamport per compute
+/// unit still rank.
+const REWARD_COST_RATIO_MULTIPLIER: u64 = 1_000_000;
+
+/// How a scheduler ranks transactions against each other.
+///
+/// Whatever the policy, transactions it ranks the same are taken in
+/// arrival order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PriorityPolicy {
+    /// Total priority fee: the compute unit price times the requested
+    /// compute units.
+    #[default]
+    PriorityFee,
+    /// Compute unit price first, then the ratio of what the leader earns
+    /// from the transaction to what it costs the block, as the validator's
+    /// scheduler ranks transactions.
+    ComputeUnitPrice,
+    /// Arrival order alone, ignoring fees.
+    Fifo,
+}
+
+impl PriorityPolicy {
+    /// The id `transaction`, pushed as the `index`-th one, is ranked by.
+    pub fn priority_id(
+        &self,
+        transaction: &SanitizedTransaction,
+        index: usize,
+    ) -> TransactionPriorityId {
+        match self {
+            Self::PriorityFee => TransactionPriorityId::new(
+                PriorityFeeCalculator::new().calculate_priority(transaction),
+                index,
+            ),
+            Self::ComputeUnitPrice => {
+                // Malformed compute budget instructions rank lowest; the
+                // transaction fails once executed anyway.
+                let compute_unit_price = process_compute_budget_instructions(transaction.message())
+                    .map(|limits| limits.compute_unit_price)
+                    .unwrap_or_default();
+                TransactionPriorityId::with_tie_breaker(
+                    compute_unit_price,
+                    reward_cost_ratio(transaction),
+                    index,
+                )
+            }
+            Self::Fifo => TransactionPriorityId::new(0, index),
+        }
+    }
+
+    /// The id `bundle`, whose first transaction was pushed as the
+    /// `index`-th one, is ranked by as a whole.
+    ///
+    /// A bundle ranks by what all its transactions pay together: their
+    /// summed priority fees, or their compute unit prices weighted by the
+    /// compute units each requests.
+    pub fn bundle_priority_id(&self, bundle: &Bundle, index: usize) -> TransactionPriorityId {
+        let transactions = bundle.transactions();
+        match self {
+            Self::PriorityFee => {
+                let calculator = PriorityFeeCalculator::new();
+                TransactionPriorityId::new(
+                    transactions
+                        .iter()
+                        .map(|transaction| calculator.calculate_priority(transaction))
+                        .fold(0, u64::saturating_add),
+                    index,
+                )
+            }
+            Self::ComputeUnitPrice => {
+                let (fees, compute_units) = transactions
+                    .iter()
+                    .filter_map(|transaction| {
+                        process_compute_budget_instructions(transaction.message()).ok()
+                    })
+                    .map(|limits| {
+                        let compute_units = u64::from(limits.compute_unit_limit);
+                        (
+                            limits.compute_unit_price.saturating_mul(compute_units),
+                            compute_units,
+                        )
+                    })
+                    .fold((0u64, 0u64), |(fees, total), (fee, compute_units)| {
+                        (
+                            fees.saturating_add(fee),
+                            total.saturating_add(compute_units),
+                        )
+                    });
+                let (reward, cost) = transactions
+                    .iter()
+                    .map(|transaction| (reward(transaction), cost(transaction)))
+                    .fold((0u64, 0u64), |(rewards, costs), (reward, cost)| {
+                        (rewards.saturating_add(reward), costs.saturating_add(cost))
+                    });
+                TransactionPriorityId::with_tie_breaker(
+                    fees.checked_div(compute_units).unwrap_or_default(),
+                    ratio(reward, cost),
+                    index,
+                )
+            }
+            Self::Fifo => TransactionPriorityId::new(0, index),
+        }
+    }
+}
+
+/// Leader reward per compute unit of cost, scaled by
+/// [`REWARD_COST_RATIO_MULTIPLIER`].
+fn reward_cost_ratio(transaction: &SanitizedTransaction) -> u64 {
+    ratio(reward(transaction), cost(transaction))
+}
+
+/// What the leader earns from `transaction`: the whole priority fee and the
+/// half of the base fee that is not burned.
+fn reward(transaction: &SanitizedTransaction) -> u64 {
+    let fee_details = FeeStructure::default().calculate_fee_details(transaction);
+    fee_details
+        .prioritization_fee
+        .saturating_add(fee_details.transaction_fee / 2)
+}
+
+fn cost(transaction: &SanitizedTransaction) -> u64 {
+    CostModel::calculate_cost(transaction).sum()
+}
+
+fn ratio(reward: u64, cost: u64) -> u64 {
+    reward
+        .saturating_mul(REWARD_COST_RATIO_MULTIPLIER)
+        .saturating_div(cost.saturating_add(1))
+}
@@ -0,0 +1,162 @@
+//! Recorded schedules of a [`WorkerPool`](super::WorkerPool).
+//!
+//! [`WorkerPool::run_recorded`](super::WorkerPool::run_recorded) writes
+//! down every batch it hands out, which worker executed each part of it and
+//! the order in which those parts completed. Handing the [`ScheduleLog`] to
+//! [`WorkerPool::replay`](super::WorkerPool::replay) forces the same
+//! schedule on a later run: the same transactions execute side by side on
+//! the same workers, and no part starts before the parts that completed
+//! ahead of it in the recording. That reproduces a concurrency bug that
+//! only shows up under one interleaving, without the scheduler or the
+//! threads' timing getting a say.
+//!
+//! Transactions are named by their position in the input of the recorded
+//! run, so a log replays against the same input no matter which ids a
+//! scheduler assigned. A log is saved as JSON lines: a header with the
+//! format version and the number of workers, then one event per line.
+
+use {
+    serde_json::{json, Value},
+    std::{
+        fs::File,
+        io::{self, BufRead, BufReader, BufWriter, Write},
+        path::Path,
+    },
+    thiserror::Error,
+};
+
+const SCHEDULE_LOG_VERSION: u64 = 1;
+
+/// Why a schedule log could not be written or loaded.
+#[derive(Debug, Error)]
+pub enum ScheduleLogError {
+    #[error("schedule log I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("schedule log is not valid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("unsupported schedule log version {0}")]
+    UnsupportedVersion(u64),
+    #[error("malformed schedule log line {0}")]
+    Malformed(usize),
+}
+
+/// Part of a batch that one worker executed, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkAssignment {
+    pub worker: usize,
+    /// Positions of the transactions in the input of the run.
+    pub positions: Vec<usize>,
+}
+
+/// A scheduling decision, in the order it was made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleEvent {
+    /// A batch was handed out, split across workers.
+    Batch(Vec<WorkAssignment>),
+    /// The assignment of these positions finished executing.
+    Completed(Vec<usize>),
+}
+
+/// Every scheduling decision of one worker pool run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScheduleLog {
+    /// Workers of the recording pool; a replaying pool needs at least as
+    /// many.
+    pub num_workers: usize,
+    pub events: Vec<ScheduleEvent>,
+}
+
+impl ScheduleLog {
+    pub fn new(num_workers: usize) -> Self {
+        Self {
+            num_workers,
+            events: Vec::new(),
+        }
+    }
+
+    /// The batches that were handed out, in order.
+    pub fn batches(&self) -> impl Iterator<Item = &[WorkAssignment]> {
+        self.events.iter().filter_map(|event| match event {
+            ScheduleEvent::Batch(assignments) => Some(assignments.as_slice()),
+            ScheduleEvent::Completed(_) => None,
+        })
+    }
+
+    /// Writes the log to `path`, replacing the file if it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ScheduleLogError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = json!({
+            "version": SCHEDULE_LOG_VERSION,
+            "numWorkers": self.num_workers,
+        });
+        writeln!(writer, "{header}")?;
+        for event in &self.events {
+            let line = match event {
+                ScheduleEvent::Batch(assignments) => json!({
+                    "batch": assignments
+                        .iter()
+                        .map(|assignment| json!({
+                            "worker": assignment.worker,
+                            "positions": assignment.positions,
+                        }))
+                        .collect::<Vec<_>>(),
+                }),
+                ScheduleEvent::Completed(positions) => json!({ "completed": positions }),
+            };
+            writeln!(writer, "{line}")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads a log written by [`save`](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScheduleLogError> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header: Value =
+            serde_json::from_str(&lines.next().ok_or(ScheduleLogError::Malformed(1))??)?;
+        let version = header["version"]
+            .as_u64()
+            .ok_or(ScheduleLogError::Malformed(1))?;
+        if version != SCHEDULE_LOG_VERSION {
+            return Err(ScheduleLogError::UnsupportedVersion(version));
+        }
+        let num_workers = header["numWorkers"]
+            .as_u64()
+            .ok_or(ScheduleLogError::Malformed(1))? as usize;
+
+        let mut log = Self::new(num_workers);
+        for (index, line) in lines.enumerate() {
+            // Lines count from one, and the header is the first.
+            let line_number = index + 2;
+            let line: Value = serde_json::from_str(&line?)?;
+            let malformed = || ScheduleLogError::Malformed(line_number);
+            let event = if let Some(assignments) = line.get("batch") {
+                let assignments = assignments
+                    .as_array()
+                    .ok_or_else(malformed)?
+                    .iter()
+                    .map(|assignment| {
+                        Some(WorkAssignment {
+                            worker: assignment["worker"].as_u64()? as usize,
+                            positions: positions(&assignment["positions"])?,
+                        })
+                    })
+                    .collect::<Option<_>>()
+                    .ok_or_else(malformed)?;
+                ScheduleEvent::Batch(assignments)
+            } else {
+                ScheduleEvent::Completed(positions(&line["completed"]).ok_or_else(malformed)?)
+            };
+            log.events.push(event);
+        }
+        Ok(log)
+    }
+}
+
+fn positions(value: &Value) -> Option<Vec<usize>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|position| position.as_u64().map(|position| position as usize))
+        .collect()
+}
@@ -0,0 +1,558 @@
+//! A pool of worker threads that executes conflict-free batches.
+//!
+//! The scheduler thread owns a [`TransactionScheduler`]. It hands every
+//! schedulable transaction to the pool over a shared work channel, and each
+//! worker reports back on a completion channel once its work is done.
+//! Receiving a completion is what lets the scheduler release the
+//! transactions that were waiting on those accounts. The transactions of
+//! a [`Bundle`](super::Bundle) all go to the same worker, which runs them
+//! in bundle order.
+//!
+//! [`WorkerPool::run_in_slot`] additionally watches a
+//! [`SlotClock`](crate::clock::SlotClock)'s events and stops handing out
+//! work once the slot is over, the way a leader stops packing its block.
+//!
+//! A pool built [`with_retryable_outputs`](WorkerPool::with_retryable_outputs)
+//! hands transactions whose output marks a transient failure back to the
+//! scheduler's [`retry`](TransactionScheduler::retry) rather than
+//! completing them, and only reports the output of their last attempt.
+//!
+//! A pool [`with_jitter`](WorkerPool::with_jitter) delays every piece of
+//! work by a random amount to shake out timing-dependent bugs. A pool
+//! [`with_determinism`](WorkerPool::with_determinism) seeded waits for all
+//! the work it handed out and completes it in id order before asking the
+//! scheduler for more, so neither the jitter nor the threads' timing
+//! changes what is scheduled: runs with the same seed hand out the same
+//! batches to the same workers.
+//!
+//! [`WorkerPool::run_recorded`] also returns a [`ScheduleLog`] of every
+//! batch it handed out, the worker that executed each part of it and the
+//! order the parts completed in, and [`WorkerPool::replay`] forces a
+//! recorded schedule on a later run. Besides the shared work channel every
+//! worker has one of its own, which is how a replay picks the worker.
+
+use {
+    super::{
+        schedule_log::{ScheduleEvent, ScheduleLog, WorkAssignment},
+        BatchLimits, TransactionId, TransactionScheduler,
+    },
+    crate::{
+        clock::SlotEvent,
+        determinism::{Determinism, JITTER_STREAM},
+        spans,
+    },
+    crossbeam_channel::{select, unbounded, Receiver, Sender},
+    rand::{rngs::StdRng, Rng},
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{
+        collections::HashMap,
+        sync::Arc,
+        thread::{self, JoinHandle},
+        time::Duration,
+    },
+    thiserror::Error,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WorkerPoolError {
+    #[error("sending channel disconnected")]
+    DisconnectedSendChannel,
+    #[error("receiving channel disconnected")]
+    DisconnectedRecvChannel,
+    #[error("scheduler stalled with {0} pending transactions")]
+    SchedulerStalled(usize),
+    #[error("schedule log does not match the run: {0}")]
+    ScheduleMismatch(&'static str),
+}
+
+/// Work sent from the scheduler to a worker.
+struct ConsumeWork {
+    ids: Vec<TransactionId>,
+    transactions: Vec<SanitizedTransaction>,
+    /// How long the worker waits before executing.
+    delay: Duration,
+}
+
+/// Completion report sent from a worker back to the scheduler, with the
+/// executed transactions so they can be retried.
+struct FinishedConsumeWork<Output> {
+    /// Index of the worker that executed the work.
+    worker: usize,
+    ids: Vec<TransactionId>,
+    transactions: Vec<SanitizedTransaction>,
+    outputs: Vec<Output>,
+}
+
+type RetryFilter<Output> = Box<dyn Fn(&Output) -> bool + Send + Sync>;
+
+/// Executes scheduled transactions on `num_workers` threads.
+///
+/// `Output` is whatever the processing function returns for a single
+/// transaction; [`WorkerPool::run`] hands the outputs back in the same order
+/// as the input transactions.
+pub struct WorkerPool<Output> {
+    num_workers: usize,
+    batch_limits: BatchLimits,
+    is_retryable: Option<RetryFilter<Output>>,
+    determinism: Determinism,
+    max_jitter: Duration,
+    work_sender: Option<Sender<ConsumeWork>>,
+    /// The channel of each single worker.
+    worker_senders: Vec<Sender<ConsumeWork>>,
+    finished_receiver: Receiver<FinishedConsumeWork<Output>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl<Output: Send + 'static> WorkerPool<Output> {
+    /// Spawns `num_workers` threads that run `processor` on every
+    /// transaction they receive.
+    pub fn new<F>(num_workers: usize, processor: F) -> Self
+    where
+        F: Fn(&SanitizedTransaction) -> Output + Send + Sync + 'static,
+    {
+        assert!(num_workers > 0, "worker pool needs at least one worker");
+
+        let (work_sender, work_receiver) = unbounded::<ConsumeWork>();
+        let (finished_sender, finished_receiver) = unbounded();
+        let processor = Arc::new(processor);
+
+        let mut worker_senders = Vec::with_capacity(num_workers);
+        let handles = (0..num_workers)
+            .map(|worker_index| {
+                let (worker_sender, worker_receiver) = unbounded::<ConsumeWork>();
+                worker_senders.push(worker_sender);
+                let work_receiver = work_receiver.clone();
+                let finished_sender = finished_sender.clone();
+                let processor = Arc::clone(&processor);
+                thread::Builder::new()
+                    .name(format!("svmWorker{worker_index:02}"))
+                    .spawn(move || loop {
+                        let work = select! {
+                            recv(work_receiver) -> work => work,
+                            recv(worker_receiver) -> work => work,
+                        };
+                        let Ok(work) = work else {
+                            break;
+                        };
+                        if !work.delay.is_zero() {
+                            thread::sleep(work.delay);
+                        }
+                        let _batch_span = spans::batch_span(work.transactions.len()).entered();
+                        let outputs = work
+                            .transactions
+                            .iter()
+                            .map(|transaction| {
+                                let _span = spans::transaction_span(transaction).entered();
+                                processor(transaction)
+                            })
+                            .collect();
+                        let finished = FinishedConsumeWork {
+                            worker: worker_index,
+                            ids: work.ids,
+                            transactions: work.transactions,
+                            outputs,
+                        };
+                        if finished_sender.send(finished).is_err() {
+                            break;
+                        }
+                    })
+                    .expect("failed to spawn worker thread")
+            })
+            .collect();
+
+        Self {
+            num_workers,
+            batch_limits: BatchLimits::default(),
+            is_retryable: None,
+            determinism: Determinism::default(),
+            max_jitter: Duration::ZERO,
+            work_sender: Some(work_sender),
+            worker_senders,
+            finished_receiver,
+            handles,
+        }
+    }
+
+    /// Caps every batch the pool requests from the scheduler.
+    pub fn with_batch_limits(mut self, batch_limits: BatchLimits) -> Self {
+        self.batch_limits = batch_limits;
+        self
+    }
+
+    /// Retries every transaction whose output `is_retryable` accepts, for
+    /// as long as the scheduler agrees; see [`is_transient_error`](super::is_transient_error).
+    pub fn with_retryable_outputs<F>(mut self, is_retryable: F) -> Self
+    where
+        F: Fn(&Output) -> bool + Send + Sync + 'static,
+    {
+        self.is_retryable = Some(Box::new(is_retryable));
+        self
+    }
+
+    /// Delays every piece of work by up to `max_jitter` before it
+    /// executes.
+    pub fn with_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = max_jitter;
+        self
+    }
+
+    /// Draws the jitter from `determinism`; a seeded pool also completes
+    /// work in a fixed order.
+    pub fn with_determinism(mut self, determinism: Determinism) -> Self {
+        self.determinism = determinism;
+        self
+    }
+
+    pub fn num_workers(&self) -> usize {
+        self.num_workers
+    }
+
+    /// Schedules and executes `transactions` until every one of them has
+    /// completed.
+    ///
+    /// Each time the scheduler has schedulable transactions they are split
+    /// evenly across the workers. When a worker finishes, its transactions
+    /// are completed in `scheduler`, which may release further work.
+    /// `scheduler` must not hold transactions pushed outside of this call.
+    pub fn run(
+        &self,
+        scheduler: &mut impl TransactionScheduler,
+        transactions: Vec<SanitizedTransaction>,
+    ) -> Result<Vec<Output>, WorkerPoolError> {
+        let (outputs, _) = self.run_until_slot_end(scheduler, transactions, None, None)?;
+        Self::all_outputs(scheduler, outputs)
+    }
+
+    /// Like [`run`](Self::run), but also records the schedule so that
+    /// [`replay`](Self::replay) can force it on a later run.
+    pub fn run_recorded(
+        &self,
+        scheduler: &mut impl TransactionScheduler,
+        transactions: Vec<SanitizedTransaction>,
+    ) -> Result<(Vec<Output>, ScheduleLog), WorkerPoolError> {
+        let mut log = ScheduleLog::new(self.num_workers);
+        let (outputs, _) =
+            self.run_until_slot_end(scheduler, transactions, None, Some(&mut log))?;
+        Ok((Self::all_outputs(scheduler, outputs)?, log))
+    }
+
+    /// Executes `transactions` on the schedule `log` recorded for the same
+    /// input, without a scheduler.
+    ///
+    /// Every batch goes to the workers it went to in the recording, and
+    /// work is only handed out once the work that completed before it in
+    /// the recording has completed again. A retried transaction reports the
+    /// output of its last attempt; one the recording never executed is
+    /// `None`.
+    pub fn replay(
+        &self,
+        log: &ScheduleLog,
+        transactions: Vec<SanitizedTransaction>,
+    ) -> Result<Vec<Option<Output>>, WorkerPoolError> {
+        // Nothing is handed out before the whole log checks out, so a
+        // mismatch leaves no work behind in the pool.
+        self.check_replayable(log, transactions.len())?;
+        let mut outputs: Vec<Option<Output>> = (0..transactions.len()).map(|_| None).collect();
+        let mut jitter_rng =
+            (!self.max_jitter.is_zero()).then(|| self.determinism.rng(JITTER_STREAM));
+        // Positions of the work handed out and not yet completed in `log`,
+        // and of the work among it that already finished.
+        let mut in_flight: Vec<Vec<usize>> = Vec::new();
+        let mut finished: Vec<Vec<usize>> = Vec::new();
+        let mut store = |work: FinishedConsumeWork<Output>| {
+            for (&position, output) in work.ids.iter().zip(work.outputs) {
+                outputs[position] = Some(output);
+            }
+            work.ids
+        };
+
+        for event in &log.events {
+            match event {
+                ScheduleEvent::Batch(assignments) => {
+                    for assignment in assignments {
+                        let work = ConsumeWork {
+                            ids: assignment.positions.clone(),
+                            transactions: assignment
+                                .positions
+                                .iter()
+                                .map(|&position| transactions[position].clone())
+                                .collect(),
+                            delay: self.jitter(jitter_rng.as_mut()),
+                        };
+                        self.worker_senders[assignment.worker]
+                            .send(work)
+                            .map_err(|_| WorkerPoolError::DisconnectedSendChannel)?;
+                        in_flight.push(assignment.positions.clone());
+                    }
+                }
+                ScheduleEvent::Completed(positions) => {
+                    let index = in_flight
+                        .iter()
+                        .position(|work| work == positions)
+                        .expect("the log was checked");
+                    in_flight.swap_remove(index);
+                    while !finished.contains(positions) {
+                        finished.push(store(self.recv_finished()?));
+                    }
+                    finished.retain(|work| work != positions);
+                }
+            }
+        }
+        for _ in finished.len()..in_flight.len() {
+            store(self.recv_finished()?);
+        }
+        Ok(outputs)
+    }
+
+    /// Checks that `log` only names workers of this pool and positions of
+    /// `num_transactions` transactions, and only completes work that is in
+    /// flight.
+    fn check_replayable(
+        &self,
+        log: &ScheduleLog,
+        num_transactions: usize,
+    ) -> Result<(), WorkerPoolError> {
+        if log.num_workers > self.num_workers {
+            return Err(WorkerPoolError::ScheduleMismatch(
+                "the log was recorded with more workers",
+            ));
+        }
+        let mut in_flight: Vec<&[usize]> = Vec::new();
+        for event in &log.events {
+            match event {
+                ScheduleEvent::Batch(assignments) => {
+                    for assignment in assignments {
+                        if assignment.worker >= self.num_workers {
+                            return Err(WorkerPoolError::ScheduleMismatch(
+                                "the log names a missing worker",
+                            ));
+                        }
+                        if assignment
+                            .positions
+                            .iter()
+                            .any(|&position| position >= num_transactions)
+                        {
+                            return Err(WorkerPoolError::ScheduleMismatch(
+                                "the log names a missing transaction",
+                            ));
+                        }
+                        if in_flight.contains(&assignment.positions.as_slice()) {
+                            return Err(WorkerPoolError::ScheduleMismatch(
+                                "the log hands out work that is in flight",
+                            ));
+                        }
+                        in_flight.push(&assignment.positions);
+                    }
+                }
+                ScheduleEvent::Completed(positions) => {
+                    let index = in_flight.iter().position(|work| work == positions).ok_or(
+                        WorkerPoolError::ScheduleMismatch(
+                            "the log completes work that is not in flight",
+                        ),
+                    )?;
+                    in_flight.swap_remove(index);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`run`](Self::run), but stops handing out batches once
+    /// `slot_events` reports the end of a slot.
+    ///
+    /// Work already handed out still completes. The outputs of transactions
+    /// the slot ended before, including retried ones waiting for another
+    /// attempt, are `None`; those transactions stay queued in `scheduler`.
+    pub fn run_in_slot(
+        &self,
+        scheduler: &mut impl TransactionScheduler,
+        transactions: Vec<SanitizedTransaction>,
+        slot_events: &Receiver<SlotEvent>,
+    ) -> Result<Vec<Option<Output>>, WorkerPoolError> {
+        let (outputs, slot_ended) =
+            self.run_until_slot_end(scheduler, transactions, Some(slot_events), None)?;
+        match scheduler.num_pending() {
+            num_pending if num_pending > 0 && !slot_ended => {
+                Err(WorkerPoolError::SchedulerStalled(num_pending))
+            }
+            _ => Ok(outputs),
+        }
+    }
+
+    /// The outputs of a run that `scheduler` has nothing left of.
+    fn all_outputs(
+        scheduler: &impl TransactionScheduler,
+        outputs: Vec<Option<Output>>,
+    ) -> Result<Vec<Output>, WorkerPoolError> {
+        match scheduler.num_pending() {
+            0 => Ok(outputs
+                .into_iter()
+                .map(|output| output.expect("every transaction is executed exactly once"))
+                .collect()),
+            num_pending => Err(WorkerPoolError::SchedulerStalled(num_pending)),
+        }
+    }
+
+    /// Runs `transactions` until they all completed or, if `slot_events` is
+    /// given, a slot ended, recording the schedule into `log` if given.
+    /// Returns their outputs and whether a slot ended.
+    fn run_until_slot_end(
+        &self,
+        scheduler: &mut impl TransactionScheduler,
+        transactions: Vec<SanitizedTransaction>,
+        slot_events: Option<&Receiver<SlotEvent>>,
+        mut log: Option<&mut ScheduleLog>,
+    ) -> Result<(Vec<Option<Output>>, bool), WorkerPoolError> {
+        let work_sender = self
+            .work_sender
+            .as_ref()
+            .ok_or(WorkerPoolError::DisconnectedSendChannel)?;
+
+        let num_transactions = transactions.len();
+        let positions: HashMap<TransactionId, usize> = transactions
+            .into_iter()
+            .enumerate()
+            .map(|(position, transaction)| (scheduler.push(transaction), position))
+            .collect();
+
+        let mut outputs: Vec<Option<Output>> = (0..num_transactions).map(|_| None).collect();
+        let mut jitter_rng =
+            (!self.max_jitter.is_zero()).then(|| self.determinism.rng(JITTER_STREAM));
+        let mut num_in_flight = 0;
+        // Where `log` holds the assignment of the work in flight, by the
+        // work's first id.
+        let mut assignments: HashMap<TransactionId, (usize, usize)> = HashMap::new();
+        let mut slot_ended = false;
+        loop {
+            slot_ended = slot_ended
+                || slot_events.is_some_and(|slot_events| {
+                    slot_events
+                        .try_iter()
+                        .any(|event| matches!(event, SlotEvent::Ended { .. }))
+                });
+            let mut batch = if slot_ended {
+                Default::default()
+            } else {
+                scheduler.next_batch(&self.batch_limits)
+            };
+            let chunk_size = batch.len().div_ceil(self.num_workers).max(1);
+            if let Some(log) = log.as_deref_mut().filter(|_| !batch.is_empty()) {
+                log.events.push(ScheduleEvent::Batch(Vec::new()));
+            }
+            // Entries of `batch` handed out so far.
+            let mut offset = 0;
+            while !batch.is_empty() {
+                let mut end = offset + chunk_size.min(batch.len());
+                // A bundle runs in order on a single worker.
+                if let Some(bundle) = batch
+                    .bundles
+                    .iter()
+                    .find(|bundle| bundle.start < end && end < bundle.end)
+                {
+                    end = bundle.end;
+                }
+                let len = end - offset;
+                offset = end;
+                let work = ConsumeWork {
+                    ids: batch.ids.drain(..len).collect(),
+                    transactions: batch.transactions.drain(..len).collect(),
+                    delay: self.jitter(jitter_rng.as_mut()),
+                };
+                if let Some(log) = log.as_deref_mut() {
+                    let event_index = log.events.len() - 1;
+                    let Some(ScheduleEvent::Batch(batch_assignments)) = log.events.last_mut()
+                    else {
+                        unreachable!("a batch event precedes its work");
+                    };
+                    assignments.insert(work.ids[0], (event_index, batch_assignments.len()));
+                    // The worker is only known once the work finished.
+                    batch_assignments.push(WorkAssignment {
+                        worker: 0,
+                        positions: work.ids.iter().map(|id| positions[id]).collect(),
+                    });
+                }
+                work_sender
+                    .send(work)
+                    .map_err(|_| WorkerPoolError::DisconnectedSendChannel)?;
+                num_in_flight += 1;
+            }
+
+            if num_in_flight == 0 {
+                break;
+            }
+
+            let mut finished = vec![self.recv_finished()?];
+            // A seeded pool completes whole rounds, in id order.
+            if self.determinism.is_seeded() {
+                while finished.len() < num_in_flight {
+                    finished.push(self.recv_finished()?);
+                }
+                finished.sort_unstable_by_key(|finished| finished.ids[0]);
+            }
+            num_in_flight -= finished.len();
+
+            for finished in finished {
+                if let Some(log) = log.as_deref_mut() {
+                    let (event_index, assignment_index) = assignments
+                        .remove(&finished.ids[0])
+                        .expect("finished work was handed out");
+                    let ScheduleEvent::Batch(batch_assignments) = &mut log.events[event_index]
+                    else {
+                        unreachable!("assignments point at batch events");
+                    };
+                    let assignment = &mut batch_assignments[assignment_index];
+                    assignment.worker = finished.worker;
+                    let positions = assignment.positions.clone();
+                    log.events.push(ScheduleEvent::Completed(positions));
+                }
+                let mut completed = Vec::with_capacity(finished.ids.len());
+                for ((id, transaction), output) in finished
+                    .ids
+                    .into_iter()
+                    .zip(finished.transactions)
+                    .zip(finished.outputs)
+                {
+                    let is_retryable = self
+                        .is_retryable
+                        .as_ref()
+                        .is_some_and(|is_retryable| is_retryable(&output));
+                    // A transaction the scheduler declines to retry is
+                    // completed by `retry` itself.
+                    let is_retried = if is_retryable {
+                        scheduler.retry(id, transaction)
+                    } else {
+                        completed.push(id);
+                        false
+                    };
+                    outputs[positions[&id]] = (!is_retried).then_some(output);
+                }
+                scheduler.complete(&completed);
+            }
+        }
+        Ok((outputs, slot_ended))
+    }
+
+    /// A random delay of up to the pool's jitter, drawn from `rng`.
+    fn jitter(&self, rng: Option<&mut StdRng>) -> Duration {
+        rng.map_or(Duration::ZERO, |rng| {
+            rng.gen_range(Duration::ZERO..=self.max_jitter)
+        })
+    }
+
+    fn recv_finished(&self) -> Result<FinishedConsumeWork<Output>, WorkerPoolError> {
+        self.finished_receiver
+            .recv()
+            .map_err(|_| WorkerPoolError::DisconnectedRecvChannel)
+    }
+}
+
+impl<Output> Drop for WorkerPool<Output> {
+    fn drop(&mut self) {
+        // Closing the work channels ends each worker's receive loop.
+        self.work_sender.take();
+        self.worker_senders.clear();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Signature verification stage.
+//!
+//! Transactions are verified in front of the bank, so only transactions
+//! whose every signature checks out reach scheduling, including the
+//! signatures checked by [precompile](crate::precompiles) instructions. The bank itself
+//! trusts its input and never looks at signatures.
+//!
+//! [`verify_transaction`] checks one transaction signature by signature.
+//! [`verify_transactions`] hands the signatures of a whole batch to a
+//! [`SignatureVerifier`]. The default [`CpuVerifier`] splits them into
+//! chunks, verifies each chunk with a single ed25519 batch verification on
+//! the rayon pool and only falls back to one-by-one checks for chunks that
+//! fail, to find out which signatures are to blame. With the
+//! `sigverify-simd` feature the default is the [`SimdVerifier`] instead,
+//! and [`verify_transactions_with`] takes any verifier, such as one that
+//! offloads the batch to a GPU. Precompile instructions are verified one
+//! transaction at a time.
+
+use {
+    crate::precompiles::{self, PrecompileConfig},
+    rayon::prelude::*,
+    solana_message::SanitizedMessage,
+    solana_signature::Signature,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+};
+
+/// Signatures verified together in a single batch verification.
+pub const VERIFY_CHUNK_SIZE: usize = 64;
+
+/// A signature, the public key of its signer and the message it signs.
+pub type SignatureData<'a> = (&'a Signature, &'a [u8], &'a [u8]);
+
+/// Checks the signatures of a batch of transactions.
+///
+/// Implementations get every signature of the batch at once, so one that
+/// offloads work to a GPU or another machine pays for the round trip once
+/// per batch rather than once per transaction.
+pub trait SignatureVerifier: Send + Sync {
+    /// Returns whether each of `signatures` verifies, in order.
+    fn verify_signatures(&self, signatures: &[SignatureData<'_>]) -> Vec<bool>;
+}
+
+/// Verifies chunks of [`VERIFY_CHUNK_SIZE`] signatures on the rayon pool,
+/// each with a single batch verification under the ZIP-215 rules of
+/// [`Signature::batch_verify`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuVerifier;
+
+impl SignatureVerifier for CpuVerifier {
+    fn verify_signatures(&self, signatures: &[SignatureData<'_>]) -> Vec<bool> {
+        signatures
+            .par_chunks(VERIFY_CHUNK_SIZE)
+            .flat_map_iter(|chunk| {
+                if Signature::batch_verify(chunk.iter().copied()) {
+                    vec![true; chunk.len()]
+                } else {
+                    verify_one_by_one(chunk)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Verifies chunks of [`VERIFY_CHUNK_SIZE`] signatures on the rayon pool,
+/// each with a single batch verification of `ed25519-dalek`, whose
+/// multiscalar multiplication runs on the AVX2 or AVX-512 IFMA backend of
+/// `curve25519-dalek` when the CPU supports it.
+///
+/// A chunk that fails, or holds a public key that is not a valid point, is
+/// checked one signature at a time.
+#[cfg(feature = "sigverify-simd")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SimdVerifier;
+
+#[cfg(feature = "sigverify-simd")]
+impl SignatureVerifier for SimdVerifier {
+    fn verify_signatures(&self, signatures: &[SignatureData<'_>]) -> Vec<bool> {
+        use ed25519_dalek::{Signature as Ed25519Signature, VerifyingKey};
+
+        signatures
+            .par_chunks(VERIFY_CHUNK_SIZE)
+            .flat_map_iter(|chunk| {
+                let verifying_keys = chunk
+                    .iter()
+                    .map(|(_, pubkey, _)| {
+                        let bytes = <&[u8; 32]>::try_from(*pubkey).ok()?;
+                        VerifyingKey::from_bytes(bytes).ok()
+                    })
+                    .collect::<Option<Vec<_>>>();
+                let batch_verified = verifying_keys.is_some_and(|verifying_keys| {
+                    let signatures: Vec<_> = chunk
+                        .iter()
+                        .map(|(signature, _, _)| Ed25519Signature::from_bytes(signature.as_array()))
+                        .collect();
+                    let messages: Vec<_> = chunk.iter().map(|(_, _, message)| *message).collect();
+                    ed25519_dalek::verify_batch(&messages, &signatures, &verifying_keys).is_ok()
+                });
+                if batch_verified {
+                    vec![true; chunk.len()]
+                } else {
+                    verify_one_by_one(chunk)
+                }
+            })
+            .collect()
+    }
+}
+
+/// The verifier [`verify_transactions`] uses: the [`SimdVerifier`] with the
+/// `sigverify-simd` feature, the [`CpuVerifier`] without.
+pub fn default_verifier() -> &'static dyn SignatureVerifier {
+    #[cfg(feature = "sigverify-simd")]
+    return &SimdVerifier;
+    #[cfg(not(feature = "sigverify-simd"))]
+    return &CpuVerifier;
+}
+
+fn verify_one_by_one(signatures: &[SignatureData<'_>]) -> Vec<bool> {
+    signatures
+        .iter()
+        .map(|(signature, pubkey, message)| signature.verify(pubkey, message))
+        .collect()
+}
+
+/// How the stage verifies signatures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SigVerifyConfig {
+    /// Accept every transaction without checking its signatures, for
+    /// workloads such as simulation that run unsigned transactions.
+    pub skip_sigverify: bool,
+    /// Rules the precompile instructions are verified with, as selected by
+    /// [`FeatureSet::precompile_config`](crate::feature_set::FeatureSet::precompile_config).
+    pub precompiles: PrecompileConfig,
+}
+
+/// Checks every signature of `transaction` against the signer it belongs
+/// to.
+///
+/// Fails with [`TransactionError::SignatureFailure`] at the first
+/// signature that does not verify.
+pub fn verify_transaction(transaction: &SanitizedTransaction) -> Result<(), TransactionError> {
+    let message_data = message_data(transaction.message());
+    let signers = transaction.message().account_keys();
+    let all_valid = transaction
+        .signatures()
+        .iter()
+        .zip(signers.iter())
+        .all(|(signature, signer)| signature.verify(signer.as_ref(), &message_data));
+    if all_valid {
+        Ok(())
+    } else {
+        Err(TransactionError::SignatureFailure)
+    }
+}
+
+/// Verifies `transactions` in parallel with the [`default_verifier`],
+/// returning whether each one passed both its signatures and its
+/// precompile instructions.
+pub fn verify_transactions(
+    transactions: &[SanitizedTransaction],
+    config: &SigVerifyConfig,
+) -> Vec<bool> {
+    verify_transactions_with(transactions, config, default_verifier())
+}
+
+/// Like [`verify_transactions`], with the signatures of the whole batch
+/// checked by `verifier`.
+pub fn verify_transactions_with(
+    transactions: &[SanitizedTransaction],
+    config: &SigVerifyConfig,
+    verifier: &dyn SignatureVerifier,
+) -> Vec<bool> {
+    if config.skip_sigverify {
+        return vec![true; transactions.len()];
+    }
+
+    let message_data: Vec<_> = transactions
+        .par_iter()
+        .map(|transaction| message_data(transaction.message()))
+        .collect();
+    let signature_data: Vec<SignatureData<'_>> = transactions
+        .iter()
+        .zip(&message_data)
+        .flat_map(|(transaction, message_data)| {
+            transaction
+                .signatures()
+                .iter()
+                .zip(transaction.message().account_keys().iter())
+                .map(|(signature, signer)| (signature, signer.as_ref(), message_data.as_slice()))
+        })
+        .collect();
+    let signatures_verified = verifier.verify_signatures(&signature_data);
+    assert_eq!(
+        signatures_verified.len(),
+        signature_data.len(),
+        "the verifier must return a result per signature"
+    );
+
+    let mut signatures_verified = signatures_verified.as_slice();
+    let transactions_verified: Vec<_> = transactions
+        .iter()
+        .map(|transaction| {
+            let (verified, rest) = signatures_verified.split_at(transaction.signatures().len());
+            signatures_verified = rest;
+            verified.iter().all(|verified| *verified)
+        })
+        .collect();
+    transactions
+        .par_iter()
+        .zip(transactions_verified)
+        .map(|(transaction, verified)| {
+            verified
+                && precompiles::verify_precompiles(transaction.message(), &config.precompiles)
+                    .is_ok()
+        })
+        .collect()
+}
+
+/// Drops the transactions that fail verification, keeping the order of the
+/// rest.
+pub fn filter_verified(
+    transactions: Vec<SanitizedTransaction>,
+    config: &SigVerifyConfig,
+) -> Vec<SanitizedTransaction> {
+    let verified = verify_transactions(&transactions, config);
+    transactions
+        .into_iter()
+        .zip(verified)
+        .filter_map(|(transaction, verified)| verified.then_some(transaction))
+        .collect()
+}
+
+/// The serialized message the signatures of a transaction sign.
+fn message_data(message: &SanitizedMessage) -> Vec<u8> {
+    match message {
+        SanitizedMessage::Legacy(message) => message.message.serialize(),
+        SanitizedMessage::V0(message) => message.message.serialize(),
+        SanitizedMessage::V1(message) => message.message.serialize(),
+    }
+}
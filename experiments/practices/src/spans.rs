@@ -0,0 +1,78 @@
+//! Structured tracing of the work done per slot, batch and transaction.
+//!
+//! The bank, the pipeline and the worker pool open [`tracing`] spans as
+//! they go: a `slot` span around the transactions a bank processes, a
+//! `batch` span around every conflict-free batch and a `transaction` span,
+//! carrying the transaction's signature and priority, around every
+//! execution. Without a subscriber the spans cost next to nothing; their
+//! fields are only computed once something listens.
+//!
+//! With the `chrome-trace` feature, [`install_chrome_trace`] writes every
+//! span to a file `chrome://tracing` or Perfetto can open, one track per
+//! thread.
+
+use {
+    crate::fees::PriorityFeeCalculator, solana_clock::Slot,
+    solana_transaction::sanitized::SanitizedTransaction, tracing::Span,
+};
+#[cfg(feature = "chrome-trace")]
+use {
+    std::{fs::File, io, io::BufWriter, path::Path},
+    thiserror::Error,
+    tracing_chrome::{ChromeLayerBuilder, FlushGuard},
+    tracing_subscriber::{layer::SubscriberExt, Registry},
+};
+
+/// Span of the work done in `slot`.
+pub fn slot_span(slot: Slot) -> Span {
+    tracing::info_span!("slot", slot)
+}
+
+/// Span of a batch of `num_transactions` that execute together.
+pub fn batch_span(num_transactions: usize) -> Span {
+    tracing::info_span!("batch", num_transactions)
+}
+
+/// Span of executing `transaction`, with its signature and its
+/// [priority](PriorityFeeCalculator::calculate_priority).
+pub fn transaction_span(transaction: &SanitizedTransaction) -> Span {
+    tracing::info_span!(
+        "transaction",
+        signature = %transaction.signature(),
+        priority = PriorityFeeCalculator::new().calculate_priority(transaction),
+    )
+}
+
+#[cfg(feature = "chrome-trace")]
+#[derive(Debug, Error)]
+pub enum ChromeTraceError {
+    #[error("failed to create the trace file: {0}")]
+    Io(#[from] io::Error),
+    #[error("a tracing subscriber is installed already")]
+    SubscriberInstalled,
+}
+
+/// Writes the rest of the trace to its file when dropped.
+#[cfg(feature = "chrome-trace")]
+pub struct ChromeTraceGuard {
+    _flush_guard: FlushGuard,
+}
+
+/// Sends every span of every thread to a Chrome trace at `path`, with
+/// their fields, for as long as the returned guard lives.
+///
+/// This installs the process-wide tracing subscriber, so it can only
+/// succeed once per process.
+#[cfg(feature = "chrome-trace")]
+pub fn install_chrome_trace(path: impl AsRef<Path>) -> Result<ChromeTraceGuard, ChromeTraceError> {
+    let file = BufWriter::new(File::create(path)?);
+    let (layer, flush_guard) = ChromeLayerBuilder::new()
+        .writer(file)
+        .include_args(true)
+        .build();
+    tracing::subscriber::set_global_default(Registry::default().with(layer))
+        .map_err(|_| ChromeTraceError::SubscriberInstalled)?;
+    Ok(ChromeTraceGuard {
+        _flush_guard: flush_guard,
+    })
+}
@@ -0,0 +1,609 @@
+//! The Stake program.
+//!
+//! Stake accounts hold lamports their staker delegates to a vote account.
+//! A delegation warms up over the epochs after it is made and cools down
+//! over the epochs after it is deactivated, at the rate the bank's
+//! [`StakeHistory`] allows, and only what is neither staked nor reserved
+//! for rent can be withdrawn.
+//!
+//! Accounts hold a `StakeStateV2` in the layout of
+//! `solana-stake-interface`, whose instruction builders produce
+//! instructions this program accepts. It processes `Initialize`,
+//! `DelegateStake`, `Deactivate`, `Withdraw`, `Split` and `Merge`; any
+//! other instruction fails with [`InstructionError::InvalidInstructionData`].
+//! Accounts are found by position and authorities among the signers, so
+//! instructions with or without the sysvar accounts older clients list
+//! both work, and signing as a lockup's custodian lifts the lockup. The
+//! minimum delegation is a single lamport.
+
+use {
+    crate::{
+        sanitize::MAX_TRANSACTION_LEN, svm::InvokeContext, system_program::check_number_of_accounts,
+    },
+    bincode::Options,
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_clock::Clock,
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::{stake::ID, vote},
+    solana_stake_interface::{
+        error::StakeError,
+        instruction::StakeInstruction,
+        stake_flags::StakeFlags,
+        state::{
+            Authorized, Delegation, Lockup, Meta, Stake, StakeActivationStatus, StakeAuthorize,
+            StakeStateV2,
+        },
+    },
+    solana_vote_interface::state::VoteStateV3,
+    std::collections::HashSet,
+};
+
+/// CUs consumed by executing a single Stake program instruction.
+pub const DEFAULT_COMPUTE_UNITS: u64 = 750;
+
+/// Fewest lamports a delegation may stake.
+pub const MINIMUM_DELEGATION: u64 = 1;
+
+/// A stake account holding `lamports`, initialized with `authorized` and
+/// `lockup` and ready to be stored before any transaction runs.
+pub fn stake_account(
+    authorized: &Authorized,
+    lockup: &Lockup,
+    rent: &Rent,
+    lamports: u64,
+) -> AccountSharedData {
+    let space = StakeStateV2::size_of();
+    let meta = new_meta(*authorized, *lockup, rent.minimum_balance(space));
+    let mut account = AccountSharedData::new(lamports, space, &ID);
+    bincode::serialize_into(
+        account.data_as_mut_slice(),
+        &StakeStateV2::Initialized(meta),
+    )
+    .expect("stake state fits its account");
+    account
+}
+
+// `rent_exempt_reserve` is only written, for clients that still read it;
+// the program takes reserves from the Rent sysvar.
+#[allow(deprecated)]
+fn new_meta(authorized: Authorized, lockup: Lockup, rent_exempt_reserve: u64) -> Meta {
+    Meta {
+        rent_exempt_reserve,
+        authorized,
+        lockup,
+    }
+}
+
+/// Entrypoint of the Stake program.
+pub fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    invoke_context.consume_builtin_cost(DEFAULT_COMPUTE_UNITS)?;
+    let instruction: StakeInstruction = bincode::options()
+        .with_limit(MAX_TRANSACTION_LEN as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize(invoke_context.instruction_data())
+        .map_err(|_| InstructionError::InvalidInstructionData)?;
+    check_number_of_accounts(invoke_context, 1)?;
+    let signers = invoke_context.get_signers();
+    match instruction {
+        StakeInstruction::Initialize(authorized, lockup) => {
+            process_initialize(invoke_context, authorized, lockup)
+        }
+        StakeInstruction::DelegateStake => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_delegate(invoke_context, &signers)
+        }
+        StakeInstruction::Deactivate => {
+            let (meta, mut stake, flags) = match get_stake_state(invoke_context, 0)? {
+                StakeStateV2::Stake(meta, stake, flags) => (meta, stake, flags),
+                _ => return Err(InstructionError::InvalidAccountData),
+            };
+            meta.authorized.check(&signers, StakeAuthorize::Staker)?;
+            stake.deactivate(invoke_context.get_sysvar_cache().get_clock().epoch)?;
+            set_stake_state(invoke_context, 0, &StakeStateV2::Stake(meta, stake, flags))
+        }
+        StakeInstruction::Withdraw(lamports) => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_withdraw(invoke_context, lamports, &signers)
+        }
+        StakeInstruction::Split(lamports) => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_split(invoke_context, lamports, &signers)
+        }
+        StakeInstruction::Merge => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_merge(invoke_context, &signers)
+        }
+        _ => Err(InstructionError::InvalidInstructionData),
+    }
+}
+
+fn get_stake_state(
+    invoke_context: &InvokeContext,
+    index: usize,
+) -> Result<StakeStateV2, InstructionError> {
+    let account = invoke_context.get_account(index)?;
+    if *account.owner() != ID {
+        return Err(InstructionError::InvalidAccountOwner);
+    }
+    bincode::deserialize(account.data()).map_err(|_| InstructionError::InvalidAccountData)
+}
+
+fn set_stake_state(
+    invoke_context: &mut InvokeContext,
+    index: usize,
+    state: &StakeStateV2,
+) -> Result<(), InstructionError> {
+    let account = invoke_context.get_account_mut(index)?;
+    bincode::serialize_into(account.data_as_mut_slice(), state)
+        .map_err(|_| InstructionError::AccountDataTooSmall)
+}
+
+/// The rent-exempt minimum of the account at `index`.
+fn rent_exempt_reserve(
+    invoke_context: &InvokeContext,
+    index: usize,
+) -> Result<u64, InstructionError> {
+    let length = invoke_context.get_account(index)?.data().len();
+    Ok(invoke_context
+        .get_sysvar_cache()
+        .get_rent()
+        .minimum_balance(length))
+}
+
+/// How much of `delegation` is effective, activating and deactivating in
+/// the current epoch.
+fn stake_status(invoke_context: &InvokeContext, delegation: &Delegation) -> StakeActivationStatus {
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    let new_rate_activation_epoch = invoke_context
+        .feature_set()
+        .new_warmup_cooldown_rate_epoch(sysvar_cache.get_epoch_schedule());
+    delegation.stake_activating_and_deactivating_v2(
+        sysvar_cache.get_clock().epoch,
+        sysvar_cache.get_stake_history(),
+        new_rate_activation_epoch,
+    )
+}
+
+/// The lockup's custodian, if it signed.
+fn custodian<'a>(lockup: &'a Lockup, signers: &HashSet<Pubkey>) -> Option<&'a Pubkey> {
+    signers
+        .contains(&lockup.custodian)
+        .then_some(&lockup.custodian)
+}
+
+/// Moves `lamports` from the account at `from` to the one at `to`.
+fn move_lamports(
+    invoke_context: &mut InvokeContext,
+    from: usize,
+    to: usize,
+    lamports: u64,
+) -> Result<(), InstructionError> {
+    invoke_context
+        .get_account_mut(from)?
+        .checked_sub_lamports(lamports)
+        .map_err(|_| InstructionError::InsufficientFunds)?;
+    invoke_context
+        .get_account_mut(to)?
+        .checked_add_lamports(lamports)
+        .map_err(|_| InstructionError::ArithmeticOverflow)
+}
+
+fn process_initialize(
+    invoke_context: &mut InvokeContext,
+    authorized: Authorized,
+    lockup: Lockup,
+) -> Result<(), InstructionError> {
+    let account = invoke_context.get_account(0)?;
+    if account.data().len() != StakeStateV2::size_of() {
+        return Err(InstructionError::InvalidAccountData);
+    }
+    if get_stake_state(invoke_context, 0)? != StakeStateV2::Uninitialized {
+        return Err(InstructionError::InvalidAccountData);
+    }
+    let reserve = rent_exempt_reserve(invoke_context, 0)?;
+    if invoke_context.get_account(0)?.lamports() < reserve {
+        return Err(InstructionError::InsufficientFunds);
+    }
+    let meta = new_meta(authorized, lockup, reserve);
+    set_stake_state(invoke_context, 0, &StakeStateV2::Initialized(meta))
+}
+
+/// Delegates everything above the rent-exempt reserve to the vote account
+/// at index 1.
+///
+/// A delegated account can only be delegated anew once none of its stake
+/// is effective, except to undo a deactivation in the epoch it was made.
+fn process_delegate(
+    invoke_context: &mut InvokeContext,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let vote_account = invoke_context.get_account(1)?;
+    if *vote_account.owner() != vote::id() {
+        return Err(InstructionError::IncorrectProgramId);
+    }
+    let credits = VoteStateV3::deserialize(vote_account.data())?.credits();
+    let voter_pubkey = *invoke_context.get_key(1)?;
+    let epoch = invoke_context.get_sysvar_cache().get_clock().epoch;
+    let stake_amount = invoke_context
+        .get_account(0)?
+        .lamports()
+        .saturating_sub(rent_exempt_reserve(invoke_context, 0)?);
+    if stake_amount < MINIMUM_DELEGATION {
+        return Err(StakeError::InsufficientDelegation.into());
+    }
+
+    let state = match get_stake_state(invoke_context, 0)? {
+        StakeStateV2::Initialized(meta) => {
+            meta.authorized.check(signers, StakeAuthorize::Staker)?;
+            let stake = Stake {
+                delegation: Delegation::new(&voter_pubkey, stake_amount, epoch),
+                credits_observed: credits,
+            };
+            StakeStateV2::Stake(meta, stake, StakeFlags::empty())
+        }
+        StakeStateV2::Stake(meta, mut stake, flags) => {
+            meta.authorized.check(signers, StakeAuthorize::Staker)?;
+            if stake_status(invoke_context, &stake.delegation).effective != 0 {
+                if stake.delegation.voter_pubkey != voter_pubkey
+                    || stake.delegation.deactivation_epoch != epoch
+                {
+                    return Err(StakeError::TooSoonToRedelegate.into());
+                }
+                stake.delegation.deactivation_epoch = u64::MAX;
+            } else {
+                stake.delegation = Delegation::new(&voter_pubkey, stake_amount, epoch);
+                stake.credits_observed = credits;
+            }
+            StakeStateV2::Stake(meta, stake, flags)
+        }
+        _ => return Err(InstructionError::InvalidAccountData),
+    };
+    set_stake_state(invoke_context, 0, &state)
+}
+
+/// Moves `lamports` to the account at index 1.
+///
+/// Staked lamports stay until they cooled down, and the rent-exempt
+/// reserve stays unless the account is emptied, which leaves it
+/// uninitialized. Lockups block withdrawals until they expire.
+fn process_withdraw(
+    invoke_context: &mut InvokeContext,
+    lamports: u64,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let clock = invoke_context.get_sysvar_cache().get_clock().clone();
+    let (lockup, reserve, is_staked) = match get_stake_state(invoke_context, 0)? {
+        StakeStateV2::Stake(meta, stake, _) => {
+            meta.authorized.check(signers, StakeAuthorize::Withdrawer)?;
+            // Until it is deactivated, a delegation may still warm up to
+            // its full stake.
+            let staked = if clock.epoch >= stake.delegation.deactivation_epoch {
+                stake_status(invoke_context, &stake.delegation).effective
+            } else {
+                stake.delegation.stake
+            };
+            let reserve = staked
+                .checked_add(rent_exempt_reserve(invoke_context, 0)?)
+                .ok_or(InstructionError::InsufficientFunds)?;
+            (meta.lockup, reserve, staked != 0)
+        }
+        StakeStateV2::Initialized(meta) => {
+            meta.authorized.check(signers, StakeAuthorize::Withdrawer)?;
+            (meta.lockup, rent_exempt_reserve(invoke_context, 0)?, false)
+        }
+        StakeStateV2::Uninitialized => {
+            if !signers.contains(invoke_context.get_key(0)?) {
+                return Err(InstructionError::MissingRequiredSignature);
+            }
+            (Lockup::default(), 0, false)
+        }
+        StakeStateV2::RewardsPool => return Err(InstructionError::InvalidAccountData),
+    };
+    if lockup.is_in_force(&clock, custodian(&lockup, signers)) {
+        return Err(StakeError::LockupInForce.into());
+    }
+
+    let balance = invoke_context.get_account(0)?.lamports();
+    let lamports_and_reserve = lamports
+        .checked_add(reserve)
+        .ok_or(InstructionError::InsufficientFunds)?;
+    if (is_staked || lamports != balance) && lamports_and_reserve > balance {
+        return Err(InstructionError::InsufficientFunds);
+    }
+    if lamports == balance {
+        set_stake_state(invoke_context, 0, &StakeStateV2::Uninitialized)?;
+    }
+    move_lamports(invoke_context, 0, 1, lamports)
+}
+
+/// Moves `lamports` of the account at index 0, and the stake they back,
+/// into the uninitialized account at index 1.
+///
+/// Both accounts have to stay rent exempt, and end up with at least the
+/// minimum delegation if the stake is delegated. Splitting everything
+/// leaves the source uninitialized.
+fn process_split(
+    invoke_context: &mut InvokeContext,
+    lamports: u64,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let destination = invoke_context.get_account(1)?;
+    if destination.data().len() != StakeStateV2::size_of() {
+        return Err(InstructionError::InvalidAccountData);
+    }
+    if get_stake_state(invoke_context, 1)? != StakeStateV2::Uninitialized {
+        return Err(InstructionError::InvalidAccountData);
+    }
+    let source_lamports = invoke_context.get_account(0)?.lamports();
+    if lamports > source_lamports {
+        return Err(InstructionError::InsufficientFunds);
+    }
+    let split = Split {
+        lamports,
+        source_lamports,
+        source_reserve: rent_exempt_reserve(invoke_context, 0)?,
+        destination_lamports: invoke_context.get_account(1)?.lamports(),
+        destination_reserve: rent_exempt_reserve(invoke_context, 1)?,
+    };
+
+    match get_stake_state(invoke_context, 0)? {
+        StakeStateV2::Stake(meta, mut stake, flags) => {
+            meta.authorized.check(signers, StakeAuthorize::Staker)?;
+            let is_active = stake_status(invoke_context, &stake.delegation).effective > 0;
+            split.validate(MINIMUM_DELEGATION, is_active)?;
+            let (remaining_stake_delta, split_stake_amount) = if split.source_remaining() == 0 {
+                // Everything moves, so the destination's reserve comes out
+                // of the stake.
+                let delta = lamports.saturating_sub(split.source_reserve);
+                (delta, delta)
+            } else {
+                if stake.delegation.stake.saturating_sub(lamports) < MINIMUM_DELEGATION {
+                    return Err(StakeError::InsufficientDelegation.into());
+                }
+                // What the destination lacks of its reserve is not staked.
+                let deficit = split
+                    .destination_reserve
+                    .saturating_sub(split.destination_lamports);
+                (lamports, lamports.saturating_sub(deficit))
+            };
+            if split_stake_amount < MINIMUM_DELEGATION {
+                return Err(StakeError::InsufficientDelegation.into());
+            }
+            let split_stake = stake.split(remaining_stake_delta, split_stake_amount)?;
+            let split_meta = new_meta(meta.authorized, meta.lockup, split.destination_reserve);
+            set_stake_state(invoke_context, 0, &StakeStateV2::Stake(meta, stake, flags))?;
+            set_stake_state(
+                invoke_context,
+                1,
+                &StakeStateV2::Stake(split_meta, split_stake, flags),
+            )?;
+        }
+        StakeStateV2::Initialized(meta) => {
+            meta.authorized.check(signers, StakeAuthorize::Staker)?;
+            split.validate(0, false)?;
+            let split_meta = new_meta(meta.authorized, meta.lockup, split.destination_reserve);
+            set_stake_state(invoke_context, 1, &StakeStateV2::Initialized(split_meta))?;
+        }
+        StakeStateV2::Uninitialized => {
+            if !signers.contains(invoke_context.get_key(0)?) {
+                return Err(InstructionError::MissingRequiredSignature);
+            }
+        }
+        StakeStateV2::RewardsPool => return Err(InstructionError::InvalidAccountData),
+    }
+    if lamports == source_lamports {
+        set_stake_state(invoke_context, 0, &StakeStateV2::Uninitialized)?;
+    }
+    move_lamports(invoke_context, 0, 1, lamports)
+}
+
+/// The balances a split moves lamports between.
+struct Split {
+    lamports: u64,
+    source_lamports: u64,
+    source_reserve: u64,
+    destination_lamports: u64,
+    destination_reserve: u64,
+}
+
+impl Split {
+    fn source_remaining(&self) -> u64 {
+        self.source_lamports - self.lamports
+    }
+
+    /// Checks that both sides keep their reserve plus `additional`
+    /// lamports, unless the source is emptied, and that an active stake is
+    /// only split into a destination that is already rent exempt.
+    fn validate(&self, additional: u64, is_active: bool) -> Result<(), InstructionError> {
+        if self.lamports == 0 {
+            return Err(InstructionError::InsufficientFunds);
+        }
+        let remaining = self.source_remaining();
+        if remaining != 0 && remaining < self.source_reserve.saturating_add(additional) {
+            return Err(InstructionError::InsufficientFunds);
+        }
+        if is_active && remaining != 0 && self.destination_lamports < self.destination_reserve {
+            return Err(InstructionError::InsufficientFunds);
+        }
+        let deficit = self
+            .destination_reserve
+            .saturating_add(additional)
+            .saturating_sub(self.destination_lamports);
+        if self.lamports < deficit {
+            return Err(InstructionError::InsufficientFunds);
+        }
+        Ok(())
+    }
+}
+
+/// Drains the stake account at index 1 into the one at index 0.
+fn process_merge(
+    invoke_context: &mut InvokeContext,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_index_in_transaction(0)? == invoke_context.get_index_in_transaction(1)? {
+        return Err(InstructionError::InvalidArgument);
+    }
+    let clock = invoke_context.get_sysvar_cache().get_clock().clone();
+    let destination = MergeKind::get_if_mergeable(invoke_context, 0)?;
+    destination
+        .meta()
+        .authorized
+        .check(signers, StakeAuthorize::Staker)?;
+    let source = MergeKind::get_if_mergeable(invoke_context, 1)?;
+    if let Some(merged) = destination.merge(source, &clock)? {
+        set_stake_state(invoke_context, 0, &merged)?;
+    }
+    set_stake_state(invoke_context, 1, &StakeStateV2::Uninitialized)?;
+    let lamports = invoke_context.get_account(1)?.lamports();
+    move_lamports(invoke_context, 1, 0, lamports)
+}
+
+/// A stake account in one of the states merges accept.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MergeKind {
+    /// Undelegated, or fully cooled down, with the account's balance.
+    Inactive(Meta, u64, StakeFlags),
+    /// Delegated this epoch, with nothing effective yet.
+    ActivationEpoch(Meta, Stake, StakeFlags),
+    /// Fully warmed up, with nothing deactivating.
+    FullyActive(Meta, Stake),
+}
+
+impl MergeKind {
+    fn get_if_mergeable(
+        invoke_context: &InvokeContext,
+        index: usize,
+    ) -> Result<Self, InstructionError> {
+        let lamports = invoke_context.get_account(index)?.lamports();
+        match get_stake_state(invoke_context, index)? {
+            StakeStateV2::Stake(meta, stake, flags) => {
+                let status = stake_status(invoke_context, &stake.delegation);
+                match (status.effective, status.activating, status.deactivating) {
+                    (0, 0, 0) => Ok(Self::Inactive(meta, lamports, flags)),
+                    (0, _, _) => Ok(Self::ActivationEpoch(meta, stake, flags)),
+                    (_, 0, 0) => Ok(Self::FullyActive(meta, stake)),
+                    _ => Err(StakeError::MergeTransientStake.into()),
+                }
+            }
+            StakeStateV2::Initialized(meta) => {
+                Ok(Self::Inactive(meta, lamports, StakeFlags::empty()))
+            }
+            _ => Err(InstructionError::InvalidAccountData),
+        }
+    }
+
+    fn meta(&self) -> &Meta {
+        match self {
+            Self::Inactive(meta, _, _)
+            | Self::ActivationEpoch(meta, _, _)
+            | Self::FullyActive(meta, _) => meta,
+        }
+    }
+
+    fn active_stake(&self) -> Option<&Stake> {
+        match self {
+            Self::Inactive(_, _, _) => None,
+            Self::ActivationEpoch(_, stake, _) | Self::FullyActive(_, stake) => Some(stake),
+        }
+    }
+
+    /// The state of the destination once `source` is merged into it, or
+    /// `None` if it keeps its state and only gains the lamports.
+    ///
+    /// Both need the same authorities, and the same lockup unless neither
+    /// is locked. Delegated stakes need the same vote account.
+    fn merge(self, source: Self, clock: &Clock) -> Result<Option<StakeStateV2>, InstructionError> {
+        let (meta, source_meta) = (self.meta(), source.meta());
+        let lockups_compatible = meta.lockup == source_meta.lockup
+            || (!meta.lockup.is_in_force(clock, None)
+                && !source_meta.lockup.is_in_force(clock, None));
+        if meta.authorized != source_meta.authorized || !lockups_compatible {
+            return Err(StakeError::MergeMismatch.into());
+        }
+        if let (Some(stake), Some(source_stake)) = (self.active_stake(), source.active_stake()) {
+            let delegation = &stake.delegation;
+            let source_delegation = &source_stake.delegation;
+            if delegation.voter_pubkey != source_delegation.voter_pubkey
+                || delegation.deactivation_epoch != u64::MAX
+                || source_delegation.deactivation_epoch != u64::MAX
+            {
+                return Err(StakeError::MergeMismatch.into());
+            }
+        }
+
+        match (self, source) {
+            (Self::Inactive(..), Self::Inactive(..))
+            | (Self::Inactive(..), Self::ActivationEpoch(..)) => Ok(None),
+            (
+                Self::ActivationEpoch(meta, mut stake, flags),
+                Self::Inactive(_, source_lamports, source_flags),
+            ) => {
+                stake.delegation.stake = stake
+                    .delegation
+                    .stake
+                    .checked_add(source_lamports)
+                    .ok_or(InstructionError::ArithmeticOverflow)?;
+                Ok(Some(StakeStateV2::Stake(
+                    meta,
+                    stake,
+                    flags.union(source_flags),
+                )))
+            }
+            (
+                Self::ActivationEpoch(meta, mut stake, flags),
+                Self::ActivationEpoch(source_meta, source_stake, source_flags),
+            ) => {
+                // The source's reserve becomes stake as well.
+                #[allow(deprecated)]
+                let source_lamports = source_meta
+                    .rent_exempt_reserve
+                    .checked_add(source_stake.delegation.stake)
+                    .ok_or(InstructionError::ArithmeticOverflow)?;
+                absorb_stake(&mut stake, source_lamports, source_stake.credits_observed)?;
+                Ok(Some(StakeStateV2::Stake(
+                    meta,
+                    stake,
+                    flags.union(source_flags),
+                )))
+            }
+            (Self::FullyActive(meta, mut stake), Self::FullyActive(_, source_stake)) => {
+                absorb_stake(
+                    &mut stake,
+                    source_stake.delegation.stake,
+                    source_stake.credits_observed,
+                )?;
+                Ok(Some(StakeStateV2::Stake(meta, stake, StakeFlags::empty())))
+            }
+            _ => Err(StakeError::MergeMismatch.into()),
+        }
+    }
+}
+
+/// Adds `lamports` observed at `credits_observed` to `stake`, which then
+/// observes the stake-weighted average of both, rounded up.
+fn absorb_stake(
+    stake: &mut Stake,
+    lamports: u64,
+    credits_observed: u64,
+) -> Result<(), InstructionError> {
+    let total = stake
+        .delegation
+        .stake
+        .checked_add(lamports)
+        .ok_or(InstructionError::ArithmeticOverflow)?;
+    if stake.credits_observed != credits_observed {
+        let weighted = u128::from(stake.credits_observed) * u128::from(stake.delegation.stake)
+            + u128::from(credits_observed) * u128::from(lamports);
+        let total = u128::from(total);
+        stake.credits_observed = weighted
+            .div_ceil(total)
+            .try_into()
+            .map_err(|_| InstructionError::ArithmeticOverflow)?;
+    }
+    stake.delegation.stake = total;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! Statuses of processed transactions, shared by the banks of a fork tree.
+//!
+//! Every transaction a bank executes is recorded under its recent
+//! blockhash, at the bank's slot, with its status. A bank treats a
+//! transaction as already processed if it was recorded at one of the
+//! bank's [`Ancestors`] or at a rooted slot, so a transaction processed on
+//! one fork is rejected by that fork's descendants but can still land on a
+//! sibling fork, as in the validator.
+//!
+//! Transactions are recorded twice: by message hash, which is what
+//! duplicates are detected by since a signature can be altered without
+//! invalidating it, and by signature, which is what clients look statuses
+//! up by. Like the validator, the cache only keeps the first
+//! [`CACHED_KEY_SIZE`] bytes of each key.
+//!
+//! Entries are pruned by root: once more than [`MAX_CACHE_ENTRIES`] slots
+//! are rooted the oldest root is dropped, along with every blockhash
+//! nothing was recorded under since. Transactions under those blockhashes
+//! fail the age check anyway.
+
+use {
+    solana_clock::{Slot, MAX_RECENT_BLOCKHASHES},
+    solana_hash::Hash,
+    solana_signature::Signature,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+    std::collections::{BTreeSet, HashMap, HashSet},
+};
+
+/// Number of rooted slots the cache remembers.
+pub const MAX_CACHE_ENTRIES: usize = MAX_RECENT_BLOCKHASHES;
+
+/// Number of leading bytes of a message hash or signature the cache keys
+/// by.
+pub const CACHED_KEY_SIZE: usize = 20;
+
+/// The slots of a fork that are not rooted yet, a bank's own slot included.
+pub type Ancestors = HashSet<Slot>;
+
+/// Status a transaction was recorded with.
+pub type TransactionStatus = Result<(), TransactionError>;
+
+type CachedKey = [u8; CACHED_KEY_SIZE];
+
+/// What was recorded under one recent blockhash.
+#[derive(Debug, Default)]
+struct BlockhashStatuses {
+    /// Highest slot anything was recorded at.
+    max_slot: Slot,
+    statuses: HashMap<CachedKey, Vec<(Slot, TransactionStatus)>>,
+}
+
+/// Processed transactions by recent blockhash, across forks.
+#[derive(Debug, Default)]
+pub struct StatusCache {
+    cache: HashMap<Hash, BlockhashStatuses>,
+    roots: BTreeSet<Slot>,
+}
+
+impl StatusCache {
+    /// Slot and status `key` was recorded with under `blockhash`, if it was
+    /// recorded at a slot in `ancestors` or at a root.
+    pub fn get_status(
+        &self,
+        key: impl AsRef<[u8]>,
+        blockhash: &Hash,
+        ancestors: &Ancestors,
+    ) -> Option<(Slot, TransactionStatus)> {
+        let statuses = self
+            .cache
+            .get(blockhash)?
+            .statuses
+            .get(&cached_key(key.as_ref()))?;
+        statuses
+            .iter()
+            .find(|(slot, _)| ancestors.contains(slot) || self.roots.contains(slot))
+            .cloned()
+    }
+
+    /// Slot and status of the transaction with `signature`, under whatever
+    /// blockhash it was recorded.
+    pub fn get_signature_status(
+        &self,
+        signature: &Signature,
+        ancestors: &Ancestors,
+    ) -> Option<(Slot, TransactionStatus)> {
+        self.cache
+            .keys()
+            .find_map(|blockhash| self.get_status(signature, blockhash, ancestors))
+    }
+
+    /// Records `key` under `blockhash` at `slot`.
+    pub fn insert(
+        &mut self,
+        blockhash: Hash,
+        key: impl AsRef<[u8]>,
+        slot: Slot,
+        status: TransactionStatus,
+    ) {
+        let blockhash_statuses = self.cache.entry(blockhash).or_default();
+        blockhash_statuses.max_slot = blockhash_statuses.max_slot.max(slot);
+        blockhash_statuses
+            .statuses
+            .entry(cached_key(key.as_ref()))
+            .or_default()
+            .push((slot, status));
+    }
+
+    /// Records `transaction` by message hash and by signature under its
+    /// recent blockhash.
+    pub fn insert_transaction(
+        &mut self,
+        transaction: &SanitizedTransaction,
+        slot: Slot,
+        status: TransactionStatus,
+    ) {
+        let blockhash = *transaction.message().recent_blockhash();
+        self.insert(blockhash, transaction.message_hash(), slot, status.clone());
+        self.insert(blockhash, transaction.signature(), slot, status);
+    }
+
+    /// Roots `slot`: what was recorded at it is visible to every bank from
+    /// now on, whatever its ancestors.
+    pub fn add_root(&mut self, slot: Slot) {
+        self.roots.insert(slot);
+        self.purge_roots();
+    }
+
+    pub fn roots(&self) -> &BTreeSet<Slot> {
+        &self.roots
+    }
+
+    /// Number of blockhashes anything is recorded under.
+    pub fn num_blockhashes(&self) -> usize {
+        self.cache.len()
+    }
+
+    fn purge_roots(&mut self) {
+        while self.roots.len() > MAX_CACHE_ENTRIES {
+            let min_root = self.roots.pop_first().expect("roots are not empty");
+            self.cache
+                .retain(|_, blockhash_statuses| blockhash_statuses.max_slot > min_root);
+        }
+    }
+}
+
+fn cached_key(key: &[u8]) -> CachedKey {
+    let mut cached_key = [0; CACHED_KEY_SIZE];
+    cached_key.copy_from_slice(&key[..CACHED_KEY_SIZE]);
+    cached_key
+}
@@ -0,0 +1,63 @@
+use {
+    super::TransactionAccount,
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_hash::Hash,
+    solana_pubkey::Pubkey,
+};
+
+/// An account a transaction changed: its state as loaded and as the
+/// transaction left it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub pubkey: Pubkey,
+    pub before: AccountSharedData,
+    pub after: AccountSharedData,
+}
+
+impl AccountDiff {
+    /// Lamports the account gained; negative if it lost some.
+    pub fn lamports_delta(&self) -> i128 {
+        i128::from(self.after.lamports()) - i128::from(self.before.lamports())
+    }
+
+    /// SHA-256 of the account's data as loaded.
+    pub fn data_hash_before(&self) -> Hash {
+        solana_sha256_hasher::hash(self.before.data())
+    }
+
+    /// SHA-256 of the account's data as the transaction left it.
+    pub fn data_hash_after(&self) -> Hash {
+        solana_sha256_hasher::hash(self.after.data())
+    }
+
+    pub fn data_changed(&self) -> bool {
+        self.before.data() != self.after.data()
+    }
+
+    pub fn owner_changed(&self) -> bool {
+        self.before.owner() != self.after.owner()
+    }
+
+    pub fn executable_changed(&self) -> bool {
+        self.before.executable() != self.after.executable()
+    }
+}
+
+/// Diffs of the accounts that differ between `before` and `after`, which
+/// must line up, in their order.
+pub fn diff_accounts(
+    before: &[TransactionAccount],
+    after: &[TransactionAccount],
+) -> Vec<AccountDiff> {
+    assert_eq!(before.len(), after.len(), "account lists must line up");
+    before
+        .iter()
+        .zip(after)
+        .filter(|((_, before), (_, after))| before != after)
+        .map(|((pubkey, before), (_, after))| AccountDiff {
+            pubkey: *pubkey,
+            before: before.clone(),
+            after: after.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,98 @@
+use {
+    super::SysvarCache, crate::bpf_loader_upgradeable, solana_account::AccountSharedData,
+    solana_instructions_sysvar::construct_instructions_data, solana_message::SanitizedMessage,
+    solana_pubkey::Pubkey, solana_sdk_ids::sysvar,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError, std::collections::HashMap,
+};
+
+/// An account key paired with the account state a transaction operates on.
+pub type TransactionAccount = (Pubkey, AccountSharedData);
+
+/// Source of account state for transaction execution.
+pub trait AccountLoader {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData>;
+}
+
+impl AccountLoader for HashMap<Pubkey, AccountSharedData> {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.get(pubkey).cloned()
+    }
+}
+
+/// Serves sysvar accounts from a [`SysvarCache`] and every other account
+/// from `loader`.
+pub(crate) struct SysvarAccountLoader<'a, L> {
+    pub sysvar_cache: &'a SysvarCache,
+    pub loader: &'a L,
+}
+
+impl<L: AccountLoader> AccountLoader for SysvarAccountLoader<'_, L> {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.sysvar_cache
+            .get_account(pubkey)
+            .or_else(|| self.loader.load_account(pubkey))
+    }
+}
+
+/// Loads every account referenced by `transaction`, in message order.
+///
+/// Accounts unknown to `loader` are loaded as empty default accounts, the
+/// same way the runtime treats addresses that were never funded.
+pub fn load_transaction_accounts(
+    loader: &impl AccountLoader,
+    transaction: &SanitizedTransaction,
+) -> Vec<TransactionAccount> {
+    transaction
+        .message()
+        .account_keys()
+        .iter()
+        .map(|key| (*key, loader.load_account(key).unwrap_or_default()))
+        .collect()
+}
+
+/// The Instructions sysvar account of `message`: every instruction of the
+/// transaction, followed by the index of the one executing, which starts
+/// out as 0.
+///
+/// Unlike the other sysvars it differs between transactions, so it is
+/// built for every transaction that lists it rather than cached. A message
+/// too large for the sysvar's 16-bit offsets fails with
+/// [`TransactionError::SanitizeFailure`].
+pub(crate) fn construct_instructions_account(
+    message: &SanitizedMessage,
+) -> Result<AccountSharedData, TransactionError> {
+    let data = construct_instructions_data(&message.decompile_instructions())
+        .map_err(|_| TransactionError::SanitizeFailure)?;
+    let mut account = AccountSharedData::new(0, data.len(), &sysvar::id());
+    account.set_data_from_slice(&data);
+    Ok(account)
+}
+
+/// Loads the programdata accounts of the upgradeable programs among
+/// `accounts` that the transaction does not reference itself.
+///
+/// Instructions for an upgradeable program run the ELF in its programdata
+/// account, which transactions do not have to list.
+pub(crate) fn load_programdata_accounts(
+    loader: &impl AccountLoader,
+    accounts: &[TransactionAccount],
+) -> Vec<TransactionAccount> {
+    let mut programdata_accounts: Vec<TransactionAccount> = Vec::new();
+    for (_, account) in accounts {
+        if !bpf_loader_upgradeable::is_upgradeable_program(account) {
+            continue;
+        }
+        let Some(address) = bpf_loader_upgradeable::programdata_address(account) else {
+            continue;
+        };
+        let is_loaded = accounts
+            .iter()
+            .chain(&programdata_accounts)
+            .any(|(key, _)| *key == address);
+        if let (false, Some(programdata)) = (is_loaded, loader.load_account(&address)) {
+            programdata_accounts.push((address, programdata));
+        }
+    }
+    programdata_accounts
+}
@@ -0,0 +1,61 @@
+use {
+    super::AccountLoader, solana_account::AccountSharedData, solana_pubkey::Pubkey,
+    std::collections::HashMap,
+};
+
+/// Accounts that replace the stored state of their pubkeys for a single
+/// simulation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountOverrides {
+    accounts: HashMap<Pubkey, AccountSharedData>,
+}
+
+impl AccountOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_account(&mut self, pubkey: Pubkey, account: AccountSharedData) {
+        self.accounts.insert(pubkey, account);
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&AccountSharedData> {
+        self.accounts.get(pubkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Pubkey, &AccountSharedData)> {
+        self.accounts.iter()
+    }
+}
+
+impl FromIterator<(Pubkey, AccountSharedData)> for AccountOverrides {
+    fn from_iter<I: IntoIterator<Item = (Pubkey, AccountSharedData)>>(iter: I) -> Self {
+        Self {
+            accounts: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Serves overridden accounts from `overrides` and every other account
+/// from `loader`.
+pub(crate) struct OverriddenAccountLoader<'a, L> {
+    pub overrides: &'a AccountOverrides,
+    pub loader: &'a L,
+}
+
+impl<L: AccountLoader> AccountLoader for OverriddenAccountLoader<'_, L> {
+    fn load_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.overrides
+            .get(pubkey)
+            .cloned()
+            .or_else(|| self.loader.load_account(pubkey))
+    }
+}
@@ -0,0 +1,25 @@
+use {bumpalo::Bump, std::cell::RefCell};
+
+thread_local! {
+    /// Scratch memory of the transaction the thread is executing. Resetting
+    /// keeps the largest chunk, so once a thread has executed a transaction
+    /// or two the arena stops allocating.
+    static ARENA: RefCell<Bump> = RefCell::new(Bump::new());
+}
+
+/// Runs `f` with the calling thread's arena, reset once `f` returns.
+///
+/// Everything allocated in the arena is freed at once by the reset, but
+/// only values in arena collections such as [`bumpalo::collections::Vec`]
+/// are dropped: anything else placed in it must not need dropping. A call
+/// made while the thread's arena is in use gets a fresh arena instead.
+pub(crate) fn with_arena<R>(f: impl FnOnce(&Bump) -> R) -> R {
+    ARENA.with(|arena| match arena.try_borrow_mut() {
+        Ok(mut arena) => {
+            let result = f(&arena);
+            arena.reset();
+            result
+        }
+        Err(_) => f(&Bump::new()),
+    })
+}
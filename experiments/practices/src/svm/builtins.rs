@@ -0,0 +1,142 @@
+//! The builtin program table.
+//!
+//! Builtins are native Rust entrypoints the executor calls directly; an
+//! instruction for a builtin never loads an ELF. [`BUILTINS`] lists the
+//! programs every executor knows, and [`BuiltinPrograms`] is the table an
+//! executor dispatches through, so callers can add mock programs to it or
+//! replace existing entries.
+
+use {
+    super::{sbf_loader, InvokeContext},
+    crate::{
+        bpf_loader_upgradeable, compute_budget, precompiles, stake_program, system_program,
+        vote_program,
+    },
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{bpf_loader, stake, vote},
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// Native entrypoint of a program executed by the
+/// [`TransactionExecutor`](super::TransactionExecutor).
+pub type BuiltinFunction =
+    Arc<dyn Fn(&mut InvokeContext) -> Result<(), InstructionError> + Send + Sync>;
+
+/// A builtin every executor starts with.
+#[derive(Clone, Copy, Debug)]
+pub struct BuiltinPrototype {
+    pub name: &'static str,
+    pub program_id: Pubkey,
+    pub entrypoint: fn(&mut InvokeContext) -> Result<(), InstructionError>,
+}
+
+/// The builtins of a fresh [`BuiltinPrograms`] table.
+pub static BUILTINS: &[BuiltinPrototype] = &[
+    BuiltinPrototype {
+        name: "system_program",
+        program_id: solana_sdk_ids::system_program::ID,
+        entrypoint: system_program::process_instruction,
+    },
+    BuiltinPrototype {
+        name: "compute_budget_program",
+        program_id: solana_sdk_ids::compute_budget::ID,
+        entrypoint: compute_budget::process_instruction,
+    },
+    BuiltinPrototype {
+        name: "vote_program",
+        program_id: vote::ID,
+        entrypoint: vote_program::process_instruction,
+    },
+    BuiltinPrototype {
+        name: "stake_program",
+        program_id: stake::ID,
+        entrypoint: stake_program::process_instruction,
+    },
+    BuiltinPrototype {
+        name: "bpf_loader_program",
+        program_id: bpf_loader::ID,
+        entrypoint: sbf_loader::process_loader_instruction,
+    },
+    BuiltinPrototype {
+        name: "bpf_loader_upgradeable_program",
+        program_id: solana_sdk_ids::bpf_loader_upgradeable::ID,
+        entrypoint: bpf_loader_upgradeable::process_instruction,
+    },
+    BuiltinPrototype {
+        name: "ed25519_program",
+        program_id: solana_sdk_ids::ed25519_program::ID,
+        entrypoint: precompiles::process_instruction,
+    },
+    BuiltinPrototype {
+        name: "secp256k1_program",
+        program_id: solana_sdk_ids::secp256k1_program::ID,
+        entrypoint: precompiles::process_instruction,
+    },
+    BuiltinPrototype {
+        name: "secp256r1_program",
+        program_id: solana_sdk_ids::secp256r1_program::ID,
+        entrypoint: precompiles::process_instruction,
+    },
+];
+
+/// Program ids mapped to their native entrypoints.
+#[derive(Clone)]
+pub struct BuiltinPrograms {
+    programs: HashMap<Pubkey, BuiltinFunction>,
+}
+
+impl Default for BuiltinPrograms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuiltinPrograms {
+    /// Creates a table holding every program in [`BUILTINS`].
+    pub fn new() -> Self {
+        let mut builtins = Self::empty();
+        for prototype in BUILTINS {
+            builtins.add(prototype.program_id, prototype.entrypoint);
+        }
+        builtins
+    }
+
+    /// Creates a table without any programs.
+    pub fn empty() -> Self {
+        Self {
+            programs: HashMap::new(),
+        }
+    }
+
+    /// Registers the entrypoint invoked for instructions targeting
+    /// `program_id`, replacing any previous registration.
+    pub fn add<F>(&mut self, program_id: Pubkey, entrypoint: F)
+    where
+        F: Fn(&mut InvokeContext) -> Result<(), InstructionError> + Send + Sync + 'static,
+    {
+        self.programs.insert(program_id, Arc::new(entrypoint));
+    }
+
+    /// Removes the builtin for `program_id`, returning whether it was
+    /// registered.
+    pub fn remove(&mut self, program_id: &Pubkey) -> bool {
+        self.programs.remove(program_id).is_some()
+    }
+
+    pub fn get(&self, program_id: &Pubkey) -> Option<&BuiltinFunction> {
+        self.programs.get(program_id)
+    }
+
+    pub fn contains(&self, program_id: &Pubkey) -> bool {
+        self.programs.contains_key(program_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+}
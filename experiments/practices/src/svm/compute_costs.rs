@@ -0,0 +1,269 @@
+//! The compute cost table.
+//!
+//! What syscalls and builtins charge is data rather than constants: every
+//! [`TransactionExecutor`](super::TransactionExecutor) holds a
+//! [`ComputeCostTable`], which defaults to the costs mainnet charges and can
+//! be replaced to evaluate another cost schedule. Tables are read from JSON
+//! or TOML, and only override the costs they list:
+//!
+//! ```toml
+//! [syscalls]
+//! syscall_base_cost = 200
+//! edwards_multiply_cost = 4000
+//!
+//! [builtins]
+//! # The system program.
+//! "11111111111111111111111111111111" = 300
+//! ```
+//!
+//! Syscall and heap costs are named after the fields of
+//! [`ComputeCostTable`], and builtins are keyed by their base58 program id.
+
+use {
+    crate::{
+        bpf_loader_upgradeable,
+        compute_budget::{self, MIN_HEAP_FRAME_BYTES},
+        stake_program, system_program, token_program, vote_program,
+    },
+    serde_json::{json, Map, Value},
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::bpf_loader,
+    std::{collections::HashMap, fs, io, path::Path, str::FromStr},
+    thiserror::Error,
+};
+
+/// Why a cost table could not be loaded.
+#[derive(Debug, Error)]
+pub enum ComputeCostTableError {
+    #[error("cost table I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("cost table is not valid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("cost table is not valid TOML: {0}")]
+    InvalidToml(#[from] toml::de::Error),
+    #[error("cost table file `{0}` is neither .json nor .toml")]
+    UnsupportedFormat(String),
+    #[error("unknown cost `{0}`")]
+    UnknownCost(String),
+    #[error("cost `{0}` is not an unsigned integer")]
+    InvalidCost(String),
+}
+
+/// CUs charged by syscalls, for the heap of SBF programs and by the
+/// builtins that charge a flat cost per instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComputeCostTable {
+    /// Charged by every syscall.
+    pub syscall_base_cost: u64,
+    /// Bytes a syscall copies per CU on top of its base cost.
+    pub bytes_per_unit: u64,
+    /// Charged by every hashing syscall.
+    pub hash_base_cost: u64,
+    /// Charged by a hashing syscall per two bytes hashed.
+    pub hash_byte_cost: u64,
+    /// Least a hashing syscall charges per slice hashed.
+    pub mem_op_base_cost: u64,
+    /// Charged by `sol_curve_validate_point`.
+    pub edwards_validate_point_cost: u64,
+    pub ristretto_validate_point_cost: u64,
+    /// Charged by `sol_curve_group_op`.
+    pub edwards_add_cost: u64,
+    pub edwards_subtract_cost: u64,
+    pub edwards_multiply_cost: u64,
+    pub ristretto_add_cost: u64,
+    pub ristretto_subtract_cost: u64,
+    pub ristretto_multiply_cost: u64,
+    /// Charged by `sol_alt_bn128_group_op` for G1 addition and scalar
+    /// multiplication.
+    pub alt_bn128_addition_cost: u64,
+    pub alt_bn128_multiplication_cost: u64,
+    /// Charged by `sol_alt_bn128_group_op` for the first pair of a pairing
+    /// check, and for every further pair.
+    pub alt_bn128_pairing_one_pair_cost_first: u64,
+    pub alt_bn128_pairing_one_pair_cost_other: u64,
+    /// Charged by every SBF program invocation per
+    /// [`MIN_HEAP_FRAME_BYTES`] of heap beyond the first.
+    pub heap_cost: u64,
+    /// Charged by every instruction of a builtin, by program id.
+    pub builtins: HashMap<Pubkey, u64>,
+}
+
+impl Default for ComputeCostTable {
+    fn default() -> Self {
+        Self {
+            syscall_base_cost: 100,
+            bytes_per_unit: 250,
+            hash_base_cost: 85,
+            hash_byte_cost: 1,
+            mem_op_base_cost: 10,
+            edwards_validate_point_cost: 159,
+            ristretto_validate_point_cost: 169,
+            edwards_add_cost: 473,
+            edwards_subtract_cost: 475,
+            edwards_multiply_cost: 2_177,
+            ristretto_add_cost: 521,
+            ristretto_subtract_cost: 519,
+            ristretto_multiply_cost: 2_208,
+            alt_bn128_addition_cost: 334,
+            alt_bn128_multiplication_cost: 3_840,
+            alt_bn128_pairing_one_pair_cost_first: 36_364,
+            alt_bn128_pairing_one_pair_cost_other: 12_121,
+            heap_cost: 8,
+            builtins: HashMap::from([
+                (
+                    solana_sdk_ids::system_program::ID,
+                    system_program::DEFAULT_COMPUTE_UNITS,
+                ),
+                (
+                    solana_sdk_ids::compute_budget::ID,
+                    compute_budget::DEFAULT_COMPUTE_UNITS,
+                ),
+                (bpf_loader::ID, super::DEFAULT_LOADER_COMPUTE_UNITS),
+                (
+                    solana_sdk_ids::bpf_loader_upgradeable::ID,
+                    bpf_loader_upgradeable::DEFAULT_COMPUTE_UNITS,
+                ),
+                (token_program::ID, token_program::DEFAULT_COMPUTE_UNITS),
+                (
+                    solana_sdk_ids::vote::ID,
+                    vote_program::DEFAULT_COMPUTE_UNITS,
+                ),
+                (
+                    solana_sdk_ids::stake::ID,
+                    stake_program::DEFAULT_COMPUTE_UNITS,
+                ),
+            ]),
+        }
+    }
+}
+
+impl ComputeCostTable {
+    /// What an instruction of the builtin `program_id` costs, or `default`
+    /// if the table does not list it.
+    pub fn builtin_cost(&self, program_id: &Pubkey, default: u64) -> u64 {
+        self.builtins.get(program_id).copied().unwrap_or(default)
+    }
+
+    /// What an SBF program invocation with `heap_bytes` of heap is charged
+    /// for the heap, which is nothing for the default heap.
+    pub fn heap_frame_cost(&self, heap_bytes: u32) -> u64 {
+        u64::from(heap_bytes.div_ceil(MIN_HEAP_FRAME_BYTES))
+            .saturating_sub(1)
+            .saturating_mul(self.heap_cost)
+    }
+
+    /// The default table with the costs `json` lists overridden, in the
+    /// format of the [module](self) docs.
+    pub fn from_json(json: &str) -> Result<Self, ComputeCostTableError> {
+        let mut table = Self::default();
+        table.apply_overrides(&serde_json::from_str(json)?)?;
+        Ok(table)
+    }
+
+    /// The default table with the costs `toml` lists overridden.
+    pub fn from_toml(toml: &str) -> Result<Self, ComputeCostTableError> {
+        let overrides: toml::Table = toml.parse()?;
+        let mut table = Self::default();
+        table.apply_overrides(&serde_json::to_value(overrides)?)?;
+        Ok(table)
+    }
+
+    /// Reads a table from a `.json` or `.toml` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ComputeCostTableError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            Some("toml") => Self::from_toml(&contents),
+            _ => Err(ComputeCostTableError::UnsupportedFormat(
+                path.display().to_string(),
+            )),
+        }
+    }
+
+    /// Every cost of the table as JSON [`from_json`](Self::from_json)
+    /// reads back, a starting point for an alternative schedule.
+    pub fn to_json(&self) -> Value {
+        // The costs are only borrowed mutably to be read.
+        let mut table = self.clone();
+        let syscalls: Map<_, _> = table
+            .syscall_costs_mut()
+            .into_iter()
+            .map(|(name, cost)| (name.to_string(), json!(cost)))
+            .collect();
+        let builtins: Map<_, _> = self
+            .builtins
+            .iter()
+            .map(|(program_id, cost)| (program_id.to_string(), json!(cost)))
+            .collect();
+        json!({ "syscalls": syscalls, "builtins": builtins })
+    }
+
+    fn syscall_costs_mut(&mut self) -> [(&'static str, &mut u64); 18] {
+        [
+            ("syscall_base_cost", &mut self.syscall_base_cost),
+            ("bytes_per_unit", &mut self.bytes_per_unit),
+            ("hash_base_cost", &mut self.hash_base_cost),
+            ("hash_byte_cost", &mut self.hash_byte_cost),
+            ("mem_op_base_cost", &mut self.mem_op_base_cost),
+            (
+                "edwards_validate_point_cost",
+                &mut self.edwards_validate_point_cost,
+            ),
+            (
+                "ristretto_validate_point_cost",
+                &mut self.ristretto_validate_point_cost,
+            ),
+            ("edwards_add_cost", &mut self.edwards_add_cost),
+            ("edwards_subtract_cost", &mut self.edwards_subtract_cost),
+            ("edwards_multiply_cost", &mut self.edwards_multiply_cost),
+            ("ristretto_add_cost", &mut self.ristretto_add_cost),
+            ("ristretto_subtract_cost", &mut self.ristretto_subtract_cost),
+            ("ristretto_multiply_cost", &mut self.ristretto_multiply_cost),
+            ("alt_bn128_addition_cost", &mut self.alt_bn128_addition_cost),
+            (
+                "alt_bn128_multiplication_cost",
+                &mut self.alt_bn128_multiplication_cost,
+            ),
+            (
+                "alt_bn128_pairing_one_pair_cost_first",
+                &mut self.alt_bn128_pairing_one_pair_cost_first,
+            ),
+            (
+                "alt_bn128_pairing_one_pair_cost_other",
+                &mut self.alt_bn128_pairing_one_pair_cost_other,
+            ),
+            ("heap_cost", &mut self.heap_cost),
+        ]
+    }
+
+    fn apply_overrides(&mut self, overrides: &Value) -> Result<(), ComputeCostTableError> {
+        let unknown = |name: &str| ComputeCostTableError::UnknownCost(name.to_string());
+        let sections = overrides.as_object().ok_or_else(|| unknown(""))?;
+        for (section, costs) in sections {
+            let costs = costs.as_object().ok_or_else(|| unknown(section))?;
+            for (name, cost) in costs {
+                let qualified = format!("{section}.{name}");
+                let cost = cost
+                    .as_u64()
+                    .ok_or_else(|| ComputeCostTableError::InvalidCost(qualified.clone()))?;
+                match section.as_str() {
+                    "syscalls" => {
+                        let (_, slot) = self
+                            .syscall_costs_mut()
+                            .into_iter()
+                            .find(|(known, _)| known == name)
+                            .ok_or_else(|| unknown(&qualified))?;
+                        *slot = cost;
+                    }
+                    "builtins" => {
+                        let program_id = Pubkey::from_str(name).map_err(|_| unknown(&qualified))?;
+                        self.builtins.insert(program_id, cost);
+                    }
+                    _ => return Err(unknown(&qualified)),
+                }
+            }
+        }
+        Ok(())
+    }
+}
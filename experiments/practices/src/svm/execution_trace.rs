@@ -0,0 +1,91 @@
+use {
+    serde_json::{json, Value},
+    solana_pubkey::Pubkey,
+    std::{fmt::Write, time::Duration},
+};
+
+/// Profile of one program invocation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionTrace {
+    pub program_id: Pubkey,
+    /// The transaction instruction the invocation belongs to.
+    pub instruction_index: usize,
+    /// 1 for a transaction instruction, one more for every level of
+    /// cross-program invocation.
+    pub stack_height: usize,
+    /// Units consumed by the invocation, the ones of the programs it
+    /// invoked included.
+    pub compute_units_consumed: u64,
+    /// Wall-clock time of the invocation, the programs it invoked included.
+    pub elapsed: Duration,
+    pub succeeded: bool,
+}
+
+/// Every program invocation of a transaction, in the order they started,
+/// so an invocation comes right before the ones it made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionTrace {
+    pub instructions: Vec<InstructionTrace>,
+}
+
+impl ExecutionTrace {
+    /// The trace as a JSON array with an object per invocation; times are
+    /// in nanoseconds.
+    pub fn to_json(&self) -> Value {
+        self.instructions
+            .iter()
+            .map(|trace| {
+                json!({
+                    "program_id": trace.program_id.to_string(),
+                    "instruction_index": trace.instruction_index,
+                    "stack_height": trace.stack_height,
+                    "compute_units_consumed": trace.compute_units_consumed,
+                    "elapsed_ns": u64::try_from(trace.elapsed.as_nanos()).unwrap_or(u64::MAX),
+                    "succeeded": trace.succeeded,
+                })
+            })
+            .collect()
+    }
+
+    /// The trace in the folded stacks format flamegraph tools read, with
+    /// compute units as the sample counts: one line per invocation holding
+    /// the program ids of its stack, outermost first, and the units it
+    /// consumed itself.
+    pub fn folded_compute_units(&self) -> String {
+        self.folded(|trace| u128::from(trace.compute_units_consumed))
+    }
+
+    /// [`folded_compute_units`](Self::folded_compute_units) with
+    /// nanoseconds of wall-clock time as the sample counts.
+    pub fn folded_wall_time(&self) -> String {
+        self.folded(|trace| trace.elapsed.as_nanos())
+    }
+
+    fn folded(&self, weight: impl Fn(&InstructionTrace) -> u128) -> String {
+        // Exclusive weight of each invocation: its own minus the weight of
+        // the invocations it made directly.
+        let mut exclusive: Vec<u128> = self.instructions.iter().map(&weight).collect();
+        let mut stack: Vec<usize> = Vec::new();
+        let mut paths = Vec::with_capacity(self.instructions.len());
+        for (index, trace) in self.instructions.iter().enumerate() {
+            stack.truncate(trace.stack_height.saturating_sub(1));
+            if let Some(&parent) = stack.last() {
+                exclusive[parent] = exclusive[parent].saturating_sub(weight(trace));
+            }
+            stack.push(index);
+            paths.push(
+                stack
+                    .iter()
+                    .map(|&frame| self.instructions[frame].program_id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(";"),
+            );
+        }
+
+        let mut folded = String::new();
+        for (path, weight) in paths.iter().zip(exclusive) {
+            writeln!(folded, "{path} {weight}").expect("writing to a string cannot fail");
+        }
+        folded
+    }
+}
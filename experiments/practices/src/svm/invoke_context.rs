@@ -0,0 +1,611 @@
+use {
+    super::{
+        pre_account::{self, PreAccount},
+        sbf_loader::{self, SbfContext, MAX_RETURN_DATA},
+        transaction_executor::{LoadedProgram, TransactionExecutor},
+        ComputeCostTable, LogCollector, SysvarCache, TransactionAccount,
+    },
+    crate::{feature_set::FeatureSet, fees::DEFAULT_LAMPORTS_PER_SIGNATURE},
+    base64::{display::Base64Display, prelude::BASE64_STANDARD},
+    bumpalo::{collections::Vec as BumpVec, Bump},
+    solana_account::AccountSharedData,
+    solana_hash::Hash,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_instruction_error::InstructionError,
+    solana_message::compiled_instruction::CompiledInstruction,
+    solana_pubkey::Pubkey,
+    solana_sbpf::program::BuiltinProgram,
+    std::{collections::HashSet, fmt::Write, sync::Arc},
+};
+#[cfg(feature = "trace")]
+use {
+    super::{ExecutionTrace, InstructionTrace},
+    std::time::{Duration, Instant},
+};
+
+/// Default limit on the number of nested program invocations, counting the
+/// instruction of the transaction itself.
+pub const MAX_INVOKE_DEPTH: usize = 5;
+
+/// Values of the bank a transaction executes in that programs can read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnvironmentConfig {
+    /// The last blockhash, from which new durable nonces are derived.
+    pub blockhash: Hash,
+    /// Fee rate recorded in nonce accounts advanced by the transaction.
+    pub lamports_per_signature: u64,
+}
+
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        Self {
+            blockhash: Hash::default(),
+            lamports_per_signature: DEFAULT_LAMPORTS_PER_SIGNATURE,
+        }
+    }
+}
+
+/// Data a program set as its return value, and the program that set it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionReturnData {
+    pub program_id: Pubkey,
+    pub data: Vec<u8>,
+}
+
+/// A cross-program invocation, with its account and program indexes
+/// pointing into the transaction's account keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerInstruction {
+    pub instruction: CompiledInstruction,
+    /// Height the invoked program ran at; a program invoked by a
+    /// transaction instruction runs at height 2.
+    pub stack_height: u8,
+}
+
+/// What executing a transaction leaves behind besides account changes.
+#[derive(Debug, Default)]
+pub(crate) struct ExecutionRecord {
+    /// Invocations made by the transaction instruction being executed.
+    pub inner_instructions: Vec<InnerInstruction>,
+    pub return_data: TransactionReturnData,
+    pub log_collector: LogCollector,
+    /// Index of the transaction instruction being executed.
+    #[cfg(feature = "trace")]
+    pub instruction_index: usize,
+    #[cfg(feature = "trace")]
+    pub trace: ExecutionTrace,
+}
+
+/// An account as referenced by the instruction being executed.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct InstructionAccount {
+    pub index_in_transaction: usize,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// Everything a program can see and touch while processing one instruction.
+///
+/// Instruction account indexes are relative to the instruction, i.e. index
+/// 0 is the first account listed in the instruction, not in the message.
+///
+/// Scratch memory that lives no longer than the transaction, such as the
+/// instruction's accounts, the invoke stack, account snapshots and log
+/// lines being formatted, is allocated in the transaction's arena.
+pub struct InvokeContext<'a> {
+    transaction_accounts: &'a mut [TransactionAccount],
+    program_id: Pubkey,
+    instruction_accounts: &'a [InstructionAccount],
+    instruction_data: &'a [u8],
+    /// Compute units left for the rest of the transaction.
+    compute_meter: &'a mut u64,
+    /// Bytes of heap the transaction's SBF programs get.
+    heap_size: u32,
+    executor: &'a TransactionExecutor,
+    /// Programs of the instructions being executed, outermost first.
+    invoke_stack: &'a [Pubkey],
+    record: &'a mut ExecutionRecord,
+    /// Programdata accounts of upgradeable programs the transaction does
+    /// not reference itself.
+    programdata_accounts: &'a [TransactionAccount],
+    /// The instruction's accounts as the program last got them, to check
+    /// its changes against.
+    pre_accounts: BumpVec<'a, PreAccount>,
+    arena: &'a Bump,
+}
+
+impl<'a> InvokeContext<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        transaction_accounts: &'a mut [TransactionAccount],
+        program_id: Pubkey,
+        instruction_accounts: &'a [InstructionAccount],
+        instruction_data: &'a [u8],
+        compute_meter: &'a mut u64,
+        heap_size: u32,
+        executor: &'a TransactionExecutor,
+        record: &'a mut ExecutionRecord,
+        programdata_accounts: &'a [TransactionAccount],
+        arena: &'a Bump,
+    ) -> Self {
+        Self {
+            transaction_accounts,
+            program_id,
+            instruction_accounts,
+            instruction_data,
+            compute_meter,
+            heap_size,
+            executor,
+            invoke_stack: arena.alloc_slice_copy(&[program_id]),
+            record,
+            programdata_accounts,
+            pre_accounts: BumpVec::new_in(arena),
+            arena,
+        }
+    }
+
+    pub fn environment_config(&self) -> &EnvironmentConfig {
+        self.executor.environment_config()
+    }
+
+    pub fn get_sysvar_cache(&self) -> &SysvarCache {
+        self.executor.sysvar_cache()
+    }
+
+    /// The features the transaction executes with.
+    pub fn feature_set(&self) -> &FeatureSet {
+        self.executor.feature_set()
+    }
+
+    /// What syscalls and builtins charge.
+    pub fn compute_costs(&self) -> &ComputeCostTable {
+        self.executor.compute_costs()
+    }
+
+    pub(crate) fn shared_compute_costs(&self) -> Arc<ComputeCostTable> {
+        Arc::clone(self.executor.shared_compute_costs())
+    }
+
+    pub(crate) fn program_loader(&self) -> Arc<BuiltinProgram<SbfContext>> {
+        Arc::clone(self.executor.program_loader())
+    }
+
+    pub(crate) fn shared_sysvar_cache(&self) -> Arc<SysvarCache> {
+        Arc::clone(self.executor.shared_sysvar_cache())
+    }
+
+    /// Bytes of heap the transaction's SBF programs get, as it requested
+    /// through the ComputeBudget program.
+    pub fn heap_size(&self) -> u32 {
+        self.heap_size
+    }
+
+    /// Number of instructions being executed, this one included. The
+    /// instruction of the transaction itself is at height 1.
+    pub fn get_stack_height(&self) -> usize {
+        self.invoke_stack.len()
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    pub fn instruction_data(&self) -> &[u8] {
+        self.instruction_data
+    }
+
+    pub fn get_number_of_accounts(&self) -> usize {
+        self.instruction_accounts.len()
+    }
+
+    pub fn get_key(&self, index: usize) -> Result<&Pubkey, InstructionError> {
+        let account = self.instruction_account(index)?;
+        Ok(&self.transaction_accounts[account.index_in_transaction].0)
+    }
+
+    /// Position in the transaction of the instruction account at `index`.
+    ///
+    /// Two instruction accounts with the same position are duplicates.
+    pub(crate) fn get_index_in_transaction(&self, index: usize) -> Result<usize, InstructionError> {
+        Ok(self.instruction_account(index)?.index_in_transaction)
+    }
+
+    pub fn is_signer(&self, index: usize) -> Result<bool, InstructionError> {
+        Ok(self.instruction_account(index)?.is_signer)
+    }
+
+    /// Keys of every instruction account that signed the transaction.
+    pub fn get_signers(&self) -> HashSet<Pubkey> {
+        self.instruction_accounts
+            .iter()
+            .filter(|account| account.is_signer)
+            .map(|account| self.transaction_accounts[account.index_in_transaction].0)
+            .collect()
+    }
+
+    pub fn is_writable(&self, index: usize) -> Result<bool, InstructionError> {
+        Ok(self.instruction_account(index)?.is_writable)
+    }
+
+    pub fn get_account(&self, index: usize) -> Result<&AccountSharedData, InstructionError> {
+        let account = self.instruction_account(index)?;
+        Ok(&self.transaction_accounts[account.index_in_transaction].1)
+    }
+
+    /// Returns the account for modification.
+    ///
+    /// Fails with [`InstructionError::ReadonlyDataModified`] if the
+    /// transaction did not lock the account as writable.
+    pub fn get_account_mut(
+        &mut self,
+        index: usize,
+    ) -> Result<&mut AccountSharedData, InstructionError> {
+        let account = *self.instruction_account(index)?;
+        if !account.is_writable {
+            return Err(InstructionError::ReadonlyDataModified);
+        }
+        Ok(&mut self.transaction_accounts[account.index_in_transaction].1)
+    }
+
+    /// Charges `units` against the transaction's compute budget.
+    ///
+    /// Once the budget is exhausted every further charge fails with
+    /// [`InstructionError::ComputationalBudgetExceeded`].
+    pub fn consume_checked(&mut self, units: u64) -> Result<(), InstructionError> {
+        let exceeded = *self.compute_meter < units;
+        *self.compute_meter = self.compute_meter.saturating_sub(units);
+        if exceeded {
+            Err(InstructionError::ComputationalBudgetExceeded)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Charges the flat cost of an instruction of the executing builtin,
+    /// as the executor's [`ComputeCostTable`] lists it, or `default_units`
+    /// if the table does not list the builtin.
+    pub fn consume_builtin_cost(&mut self, default_units: u64) -> Result<(), InstructionError> {
+        let units = self
+            .compute_costs()
+            .builtin_cost(&self.program_id, default_units);
+        self.consume_checked(units)
+    }
+
+    pub fn get_remaining(&self) -> u64 {
+        *self.compute_meter
+    }
+
+    /// The data last set by any program of the transaction, and the program
+    /// that set it.
+    ///
+    /// Return data outlives the instruction that set it, so after
+    /// [`invoke`](Self::invoke) this is what the callee, or a program it
+    /// invoked in turn, set; a callee that set nothing leaves whatever was
+    /// there before, which the program id tells apart.
+    pub fn get_return_data(&self) -> (&Pubkey, &[u8]) {
+        let return_data = &self.record.return_data;
+        (&return_data.program_id, &return_data.data)
+    }
+
+    /// Sets the current program's return value, replacing whatever any
+    /// program set before. Empty data clears it.
+    ///
+    /// Builtins are bound by the same [`MAX_RETURN_DATA`] as the
+    /// `sol_set_return_data` syscall; more fails with
+    /// [`InstructionError::InvalidArgument`].
+    pub fn set_return_data(&mut self, data: Vec<u8>) -> Result<(), InstructionError> {
+        if data.len() > MAX_RETURN_DATA {
+            return Err(InstructionError::InvalidArgument);
+        }
+        self.record.return_data = TransactionReturnData {
+            program_id: self.program_id,
+            data,
+        };
+        Ok(())
+    }
+
+    pub(crate) fn return_data_mut(&mut self) -> &mut TransactionReturnData {
+        &mut self.record.return_data
+    }
+
+    /// Records `message` as a `Program log:` line.
+    pub fn log(&mut self, message: &str) {
+        let line = bumpalo::format!(in self.arena, "Program log: {}", message);
+        self.record.log_collector.log(&line);
+    }
+
+    /// Records `fields` as a `Program data:` line, each field base64
+    /// encoded.
+    pub fn log_data(&mut self, fields: &[&[u8]]) {
+        let mut line = bumpalo::collections::String::from_str_in("Program data:", self.arena);
+        for field in fields {
+            let _ = write!(line, " {}", Base64Display::new(field, &BASE64_STANDARD));
+        }
+        self.record.log_collector.log(&line);
+    }
+
+    pub fn log_collector_mut(&mut self) -> &mut LogCollector {
+        &mut self.record.log_collector
+    }
+
+    /// Runs the program of the current instruction, logging its invocation
+    /// and how it returned.
+    ///
+    /// A program that returns successfully still fails the instruction if
+    /// it changed its accounts in a way the runtime does not allow: see
+    /// [`verify_accounts`](Self::verify_accounts).
+    pub(crate) fn process_instruction(&mut self) -> Result<(), InstructionError> {
+        #[cfg(feature = "trace")]
+        let trace = self.start_trace();
+        let program_id = self.program_id;
+        let stack_height = self.get_stack_height();
+        let arena = self.arena;
+        self.record.log_collector.log(&bumpalo::format!(
+            in arena,
+            "Program {} invoke [{}]",
+            program_id,
+            stack_height
+        ));
+        self.pre_accounts = self.snapshot_accounts();
+        let result = self.run_program().and_then(|()| self.verify_accounts());
+        let line = match &result {
+            Ok(()) => bumpalo::format!(in arena, "Program {} success", program_id),
+            Err(err) => bumpalo::format!(in arena, "Program {} failed: {}", program_id, err),
+        };
+        self.record.log_collector.log(&line);
+        #[cfg(feature = "trace")]
+        self.finish_trace(trace, &result);
+        result
+    }
+
+    /// Adds the invocation starting now to the trace, returning its index
+    /// there, when it started and the units left at that point.
+    #[cfg(feature = "trace")]
+    fn start_trace(&mut self) -> (usize, Instant, u64) {
+        let trace = InstructionTrace {
+            program_id: self.program_id,
+            instruction_index: self.record.instruction_index,
+            stack_height: self.get_stack_height(),
+            compute_units_consumed: 0,
+            elapsed: Duration::ZERO,
+            succeeded: false,
+        };
+        let instructions = &mut self.record.trace.instructions;
+        instructions.push(trace);
+        (instructions.len() - 1, Instant::now(), *self.compute_meter)
+    }
+
+    #[cfg(feature = "trace")]
+    fn finish_trace(
+        &mut self,
+        (index, started, units_left): (usize, Instant, u64),
+        result: &Result<(), InstructionError>,
+    ) {
+        let trace = &mut self.record.trace.instructions[index];
+        trace.compute_units_consumed = units_left.saturating_sub(*self.compute_meter);
+        trace.elapsed = started.elapsed();
+        trace.succeeded = result.is_ok();
+    }
+
+    fn run_program(&mut self) -> Result<(), InstructionError> {
+        let program_account = self
+            .transaction_accounts
+            .iter()
+            .find(|(key, _)| *key == self.program_id)
+            .ok_or(InstructionError::MissingAccount)?;
+        let find_programdata = |address: &Pubkey| {
+            self.transaction_accounts
+                .iter()
+                .chain(self.programdata_accounts)
+                .find(|(key, _)| key == address)
+                .map(|(_, account)| account)
+        };
+        match self
+            .executor
+            .load_program(program_account, find_programdata)?
+        {
+            LoadedProgram::Builtin(entrypoint) => entrypoint(self),
+            LoadedProgram::Sbf(executable) => sbf_loader::process_instruction(&executable, self),
+            LoadedProgram::NotDeployed => {
+                self.record.log_collector.log("Program is not deployed");
+                Err(InstructionError::UnsupportedProgramId)
+            }
+        }
+    }
+
+    /// The instruction's accounts as they are now, each listed once.
+    fn snapshot_accounts(&self) -> BumpVec<'a, PreAccount> {
+        let mut pre_accounts: BumpVec<PreAccount> =
+            BumpVec::with_capacity_in(self.instruction_accounts.len(), self.arena);
+        for account in self.instruction_accounts {
+            let index = account.index_in_transaction;
+            if pre_accounts
+                .iter()
+                .all(|pre_account| pre_account.index_in_transaction != index)
+            {
+                let (_, data) = &self.transaction_accounts[index];
+                pre_accounts.push(PreAccount::new(index, account.is_writable, data.clone()));
+            }
+        }
+        pre_accounts
+    }
+
+    /// Checks what the current program did to the instruction's accounts
+    /// since it got them.
+    ///
+    /// Only writable accounts may change, and of those only the ones the
+    /// program owns may lose lamports, change their data or be assigned to
+    /// another program; executable accounts keep their lamports and data.
+    /// Across all the accounts not a lamport may be created or destroyed,
+    /// or the instruction fails with
+    /// [`InstructionError::UnbalancedInstruction`]. Accounts the message
+    /// demotes to read-only, such as program ids and sysvars, are read-only
+    /// here as well.
+    pub(crate) fn verify_accounts(&self) -> Result<(), InstructionError> {
+        pre_account::verify_accounts(
+            &self.pre_accounts,
+            &self.program_id,
+            self.transaction_accounts,
+        )
+    }
+
+    /// Executes `instruction` as a cross-program invocation of the current
+    /// program.
+    pub fn invoke(&mut self, instruction: &Instruction) -> Result<(), InstructionError> {
+        self.invoke_signed(instruction, &[])
+    }
+
+    /// Executes `instruction` as a cross-program invocation, signed by the
+    /// program derived addresses of the current program that `signers_seeds`
+    /// produce.
+    ///
+    /// The callee can only be given accounts, and privileges on them, that
+    /// the current instruction holds; anything else fails with
+    /// [`InstructionError::MissingAccount`] or
+    /// [`InstructionError::PrivilegeEscalation`]. Nesting is limited by the
+    /// executor's max invoke depth, and a program further down the stack
+    /// cannot be reentered. The callee works on the caller's accounts, so
+    /// its changes are visible once it returns, as is any return data it
+    /// set; if it fails both are rolled back. Every invocation that passes
+    /// these checks is recorded as an [`InnerInstruction`] of the
+    /// transaction instruction.
+    ///
+    /// What the current program changed before the invocation is checked
+    /// first, as [`verify_accounts`](Self::verify_accounts) checks it on
+    /// return, and what the callee changed is checked against the callee's
+    /// rules, not the caller's.
+    pub fn invoke_signed(
+        &mut self,
+        instruction: &Instruction,
+        signers_seeds: &[&[&[u8]]],
+    ) -> Result<(), InstructionError> {
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| {
+                Pubkey::create_program_address(seeds, &self.program_id)
+                    .map_err(|_| InstructionError::InvalidSeeds)
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        if self.invoke_stack.len() >= self.executor.max_invoke_depth() {
+            return Err(InstructionError::CallDepth);
+        }
+        // A program may invoke itself, but not one it is nested in.
+        let is_reentrant = self.invoke_stack.contains(&instruction.program_id)
+            && self.invoke_stack.last() != Some(&instruction.program_id);
+        if is_reentrant {
+            return Err(InstructionError::ReentrancyNotAllowed);
+        }
+        let program_account = self.find_instruction_account(&instruction.program_id)?;
+        let instruction_accounts =
+            self.prepare_instruction_accounts(&instruction.accounts, &signers)?;
+        self.record.inner_instructions.push(InnerInstruction {
+            instruction: CompiledInstruction {
+                program_id_index: program_account.index_in_transaction as u8,
+                accounts: instruction_accounts
+                    .iter()
+                    .map(|account| account.index_in_transaction as u8)
+                    .collect(),
+                data: instruction.data.clone(),
+            },
+            stack_height: (self.invoke_stack.len() + 1) as u8,
+        });
+
+        self.verify_accounts()?;
+        self.update_pre_accounts();
+        let mut snapshot = BumpVec::with_capacity_in(self.transaction_accounts.len(), self.arena);
+        snapshot.extend_from_slice(self.transaction_accounts);
+        let return_data = self.record.return_data.clone();
+        let mut invoke_stack = BumpVec::with_capacity_in(self.invoke_stack.len() + 1, self.arena);
+        invoke_stack.extend_from_slice(self.invoke_stack);
+        invoke_stack.push(instruction.program_id);
+        let result = InvokeContext {
+            transaction_accounts: self.transaction_accounts,
+            program_id: instruction.program_id,
+            instruction_accounts,
+            instruction_data: &instruction.data,
+            compute_meter: self.compute_meter,
+            heap_size: self.heap_size,
+            executor: self.executor,
+            invoke_stack: invoke_stack.into_bump_slice(),
+            record: self.record,
+            programdata_accounts: self.programdata_accounts,
+            pre_accounts: BumpVec::new_in(self.arena),
+            arena: self.arena,
+        }
+        .process_instruction();
+        if result.is_err() {
+            self.transaction_accounts.clone_from_slice(&snapshot);
+            self.record.return_data = return_data;
+        }
+        self.update_pre_accounts();
+        result
+    }
+
+    /// Takes the instruction's accounts as they are now as the state the
+    /// current program's further changes are checked against.
+    fn update_pre_accounts(&mut self) {
+        for pre_account in &mut self.pre_accounts {
+            pre_account.update(&self.transaction_accounts[pre_account.index_in_transaction].1);
+        }
+    }
+
+    /// Maps the accounts of a cross-program invocation onto the current
+    /// instruction's accounts.
+    ///
+    /// An account listed more than once gets the privileges of all of its
+    /// entries.
+    fn prepare_instruction_accounts(
+        &self,
+        account_metas: &[AccountMeta],
+        signers: &HashSet<Pubkey>,
+    ) -> Result<&'a [InstructionAccount], InstructionError> {
+        let mut instruction_accounts = BumpVec::with_capacity_in(account_metas.len(), self.arena);
+        for account_meta in account_metas {
+            let caller_account = self.find_instruction_account(&account_meta.pubkey)?;
+            if account_meta.is_writable && !caller_account.is_writable {
+                return Err(InstructionError::PrivilegeEscalation);
+            }
+            if account_meta.is_signer
+                && !caller_account.is_signer
+                && !signers.contains(&account_meta.pubkey)
+            {
+                return Err(InstructionError::PrivilegeEscalation);
+            }
+            instruction_accounts.push(InstructionAccount {
+                index_in_transaction: caller_account.index_in_transaction,
+                is_signer: account_meta.is_signer,
+                is_writable: account_meta.is_writable,
+            });
+        }
+
+        let instruction_accounts = instruction_accounts.into_bump_slice_mut();
+        let listed = self.arena.alloc_slice_copy(instruction_accounts);
+        for account in instruction_accounts.iter_mut() {
+            for other in listed.iter() {
+                if other.index_in_transaction == account.index_in_transaction {
+                    account.is_signer |= other.is_signer;
+                    account.is_writable |= other.is_writable;
+                }
+            }
+        }
+        Ok(instruction_accounts)
+    }
+
+    fn find_instruction_account(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<InstructionAccount, InstructionError> {
+        self.instruction_accounts
+            .iter()
+            .find(|account| self.transaction_accounts[account.index_in_transaction].0 == *pubkey)
+            .copied()
+            .ok_or(InstructionError::MissingAccount)
+    }
+
+    fn instruction_account(&self, index: usize) -> Result<&InstructionAccount, InstructionError> {
+        self.instruction_accounts
+            .get(index)
+            .ok_or(InstructionError::MissingAccount)
+    }
+}
@@ -0,0 +1,66 @@
+//! Program logs.
+//!
+//! Every transaction records its log lines the way the validator does:
+//! `Program <id> invoke [<depth>]` when a program starts, with the depth
+//! of the invocation, `Program log: ...` and `Program data: ...` for what
+//! programs log, and `Program <id> success` or `Program <id> failed: ...`
+//! when they return.
+
+/// Bytes of log messages a transaction may produce by default.
+pub const DEFAULT_LOG_MESSAGES_BYTES_LIMIT: usize = 10 * 1000;
+
+/// Collects the log messages of one transaction.
+///
+/// A message that would take the collector to its byte limit is dropped;
+/// the first one dropped leaves a single `Log truncated` line instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogCollector {
+    messages: Vec<String>,
+    bytes_written: usize,
+    bytes_limit: Option<usize>,
+    limit_warning: bool,
+}
+
+impl Default for LogCollector {
+    fn default() -> Self {
+        Self::new_with_limit(Some(DEFAULT_LOG_MESSAGES_BYTES_LIMIT))
+    }
+}
+
+impl LogCollector {
+    /// Creates a collector that keeps at most `bytes_limit` bytes of
+    /// messages, or all of them if it is `None`.
+    pub fn new_with_limit(bytes_limit: Option<usize>) -> Self {
+        Self {
+            messages: Vec::new(),
+            bytes_written: 0,
+            bytes_limit,
+            limit_warning: false,
+        }
+    }
+
+    pub fn log(&mut self, message: &str) {
+        let Some(limit) = self.bytes_limit else {
+            self.messages.push(message.to_owned());
+            return;
+        };
+        let bytes_written = self.bytes_written.saturating_add(message.len());
+        if bytes_written >= limit {
+            if !self.limit_warning {
+                self.limit_warning = true;
+                self.messages.push(String::from("Log truncated"));
+            }
+        } else {
+            self.bytes_written = bytes_written;
+            self.messages.push(message.to_owned());
+        }
+    }
+
+    pub fn get_recorded_content(&self) -> &[String] {
+        &self.messages
+    }
+
+    pub fn into_messages(self) -> Vec<String> {
+        self.messages
+    }
+}
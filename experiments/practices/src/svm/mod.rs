@@ -0,0 +1,59 @@
+//! Transaction execution.
+//!
+//! The executor runs each instruction of a transaction against a private
+//! copy of the accounts it loaded, so nothing outside the executor observes
+//! a transaction's writes until the caller decides to keep them.
+//! Instructions either go to a builtin from the executor's
+//! [`BuiltinPrograms`] table or, if the program account holds an SBF
+//! program deployed with the BPF loader or the upgradeable loader, to the
+//! SBF interpreter, which keeps verified programs in a [`ProgramCache`]. Builtins can invoke
+//! other programs through [`InvokeContext::invoke_signed`]. Whatever a
+//! program returns, its instruction only succeeds if it changed nothing
+//! but the writable accounts it owns, and kept their lamports balanced.
+//! Sysvars come from the executor's [`SysvarCache`], both as accounts and
+//! through direct reads. What programs log goes to the transaction's
+//! [`LogCollector`]. What syscalls and builtins charge comes from the
+//! executor's [`ComputeCostTable`]. With the `trace` feature, every program invocation is
+//! also profiled into the transaction's [`ExecutionTrace`]. Scratch memory
+//! that does not outlive a transaction, such as instruction account lists
+//! and the account snapshots taken around every invocation, comes from a
+//! bump arena of the executing thread, reset once the transaction is
+//! done.
+
+mod account_diff;
+mod account_loader;
+mod account_overrides;
+mod arena;
+mod builtins;
+mod compute_costs;
+mod execution_trace;
+mod invoke_context;
+mod log_collector;
+mod pre_account;
+mod program_cache;
+mod sbf_loader;
+mod serialization;
+mod sysvar_cache;
+mod transaction_executor;
+
+pub use {
+    account_diff::{diff_accounts, AccountDiff},
+    account_loader::{load_transaction_accounts, AccountLoader, TransactionAccount},
+    account_overrides::AccountOverrides,
+    builtins::{BuiltinFunction, BuiltinPrograms, BuiltinPrototype, BUILTINS},
+    compute_costs::{ComputeCostTable, ComputeCostTableError},
+    execution_trace::{ExecutionTrace, InstructionTrace},
+    invoke_context::{
+        EnvironmentConfig, InnerInstruction, InvokeContext, TransactionReturnData, MAX_INVOKE_DEPTH,
+    },
+    log_collector::{LogCollector, DEFAULT_LOG_MESSAGES_BYTES_LIMIT},
+    program_cache::{
+        ProgramCache, ProgramCacheKey, ProgramCacheStats, DEFAULT_PROGRAM_CACHE_CAPACITY,
+    },
+    sbf_loader::{SyscallError, VmConfig, DEFAULT_LOADER_COMPUTE_UNITS, MAX_RETURN_DATA},
+    serialization::{MAX_PERMITTED_DATA_INCREASE, MIN_DIRECT_MAPPED_DATA_LEN},
+    sysvar_cache::{RecentBlockhashEntry, SysvarCache, SysvarSource},
+    transaction_executor::{TransactionExecutionResult, TransactionExecutor},
+};
+
+pub(crate) use {account_overrides::OverriddenAccountLoader, sbf_loader::verify_program};
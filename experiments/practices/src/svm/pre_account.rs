@@ -0,0 +1,120 @@
+use {
+    super::TransactionAccount,
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+};
+
+/// An account as it was when the program now executing got it, to check
+/// what the program did to it against the runtime's rules.
+#[derive(Clone, Debug)]
+pub(crate) struct PreAccount {
+    pub index_in_transaction: usize,
+    pub is_writable: bool,
+    account: AccountSharedData,
+}
+
+impl PreAccount {
+    pub fn new(index_in_transaction: usize, is_writable: bool, account: AccountSharedData) -> Self {
+        Self {
+            index_in_transaction,
+            is_writable,
+            account,
+        }
+    }
+
+    pub fn lamports(&self) -> u64 {
+        self.account.lamports()
+    }
+
+    /// Forgets the changes checked so far, so the next check starts from
+    /// `account`.
+    pub fn update(&mut self, account: &AccountSharedData) {
+        self.account = account.clone();
+    }
+
+    /// Checks that `program_id` changed the account into `post` only as
+    /// the runtime allows.
+    ///
+    /// Only a writable account can change at all. On top of that only the
+    /// program owning it may debit its lamports, change its data or hand it
+    /// to another owner, the latter only with the data zeroed, and the
+    /// lamports and data of executable accounts are frozen. Violations fail
+    /// with the [`InstructionError`] the cluster reports for them.
+    pub fn verify(
+        &self,
+        program_id: &Pubkey,
+        post: &AccountSharedData,
+    ) -> Result<(), InstructionError> {
+        let pre = &self.account;
+        let is_owner = pre.owner() == program_id;
+
+        if pre.owner() != post.owner()
+            && (!self.is_writable || pre.executable() || !is_owner || !is_zeroed(post.data()))
+        {
+            return Err(InstructionError::ModifiedProgramId);
+        }
+
+        if pre.lamports() != post.lamports() {
+            if !self.is_writable {
+                return Err(InstructionError::ReadonlyLamportChange);
+            }
+            if pre.executable() {
+                return Err(InstructionError::ExecutableLamportChange);
+            }
+        }
+        if !is_owner && post.lamports() < pre.lamports() {
+            return Err(InstructionError::ExternalAccountLamportSpend);
+        }
+
+        if pre.data().len() != post.data().len() && !(self.is_writable && is_owner) {
+            return Err(InstructionError::AccountDataSizeChanged);
+        }
+        if pre.data() != post.data() && !(self.is_writable && is_owner && !pre.executable()) {
+            return Err(if pre.executable() {
+                InstructionError::ExecutableDataModified
+            } else if self.is_writable {
+                InstructionError::ExternalAccountDataModified
+            } else {
+                InstructionError::ReadonlyDataModified
+            });
+        }
+
+        if pre.executable() != post.executable()
+            && (pre.executable() || !self.is_writable || !is_owner)
+        {
+            return Err(InstructionError::ExecutableModified);
+        }
+        if pre.rent_epoch() != post.rent_epoch() {
+            return Err(InstructionError::RentEpochModified);
+        }
+        Ok(())
+    }
+}
+
+/// Checks every account in `pre_accounts` against what `program_id` left
+/// in `transaction_accounts`, and that the lamports they hold add up to
+/// what they held before, which fails with
+/// [`InstructionError::UnbalancedInstruction`].
+pub(crate) fn verify_accounts(
+    pre_accounts: &[PreAccount],
+    program_id: &Pubkey,
+    transaction_accounts: &[TransactionAccount],
+) -> Result<(), InstructionError> {
+    let mut pre_lamports = 0u128;
+    let mut post_lamports = 0u128;
+    for pre_account in pre_accounts {
+        let (_, post) = &transaction_accounts[pre_account.index_in_transaction];
+        pre_account.verify(program_id, post)?;
+        pre_lamports += u128::from(pre_account.lamports());
+        post_lamports += u128::from(post.lamports());
+    }
+    if pre_lamports != post_lamports {
+        return Err(InstructionError::UnbalancedInstruction);
+    }
+    Ok(())
+}
+
+fn is_zeroed(data: &[u8]) -> bool {
+    data.iter().all(|byte| *byte == 0)
+}
@@ -0,0 +1,137 @@
+//! Cache of loaded SBF programs.
+//!
+//! Loading a program parses and verifies its ELF, which costs far more than
+//! most instructions do. The executor keeps every program it loaded in a
+//! [`ProgramCache`] keyed by program id and deployment slot, so the next
+//! instruction for the same deployment reuses the verified executable. A
+//! redeployment gets a new slot and therefore a new entry. Once the cache
+//! is full the least recently used program is evicted.
+
+use {
+    super::sbf_loader::SbfContext,
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    solana_sbpf::elf::Executable,
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// Programs a cache holds by default.
+pub const DEFAULT_PROGRAM_CACHE_CAPACITY: usize = 256;
+
+/// Identifies one deployment of a program.
+pub type ProgramCacheKey = (Pubkey, Slot);
+
+/// How a cache has been used since it was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProgramCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub insertions: u64,
+    pub evictions: u64,
+}
+
+struct CacheEntry {
+    executable: Arc<Executable<SbfContext>>,
+    /// Value of the cache's use counter when the entry was last used.
+    last_used: u64,
+}
+
+/// Verified executables by deployment, with least recently used eviction.
+pub struct ProgramCache {
+    capacity: usize,
+    entries: HashMap<ProgramCacheKey, CacheEntry>,
+    use_counter: u64,
+    stats: ProgramCacheStats,
+}
+
+impl Default for ProgramCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROGRAM_CACHE_CAPACITY)
+    }
+}
+
+impl ProgramCache {
+    /// Creates a cache that holds at most `capacity` programs. A cache of
+    /// capacity 0 never keeps anything.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            use_counter: 0,
+            stats: ProgramCacheStats::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, key: &ProgramCacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn stats(&self) -> ProgramCacheStats {
+        self.stats
+    }
+
+    /// Removes every deployment of `program_id`.
+    pub fn remove(&mut self, program_id: &Pubkey) {
+        self.entries.retain(|(key, _), _| key != program_id);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns the executable of `key`, counting a hit or a miss.
+    pub(crate) fn get(&mut self, key: &ProgramCacheKey) -> Option<Arc<Executable<SbfContext>>> {
+        self.use_counter += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.use_counter;
+                self.stats.hits += 1;
+                Some(Arc::clone(&entry.executable))
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Caches `executable` under `key`, evicting the least recently used
+    /// program if the cache is full.
+    pub(crate) fn insert(&mut self, key: ProgramCacheKey, executable: Arc<Executable<SbfContext>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let least_recently_used = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(least_recently_used) = least_recently_used {
+                self.entries.remove(&least_recently_used);
+                self.stats.evictions += 1;
+            }
+        }
+        self.use_counter += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                executable,
+                last_used: self.use_counter,
+            },
+        );
+        self.stats.insertions += 1;
+    }
+}
@@ -0,0 +1,926 @@
+//! Execution of SBF programs deployed with the BPF loader.
+//!
+//! A program account owned by the loader holds the program's ELF. Every
+//! instruction runs in a fresh VM: its accounts and data are serialized
+//! into the input region, the program is interpreted with the
+//! transaction's compute meter as its instruction meter, and the accounts
+//! are read back out of the input region once the program returns.
+//! Large account data is mapped into the input region rather than copied;
+//! see [`serialization`](super::serialization).
+
+use {
+    super::{
+        serialization::{deserialize_parameters, serialize_parameters},
+        ComputeCostTable, InvokeContext, LogCollector, SysvarCache, TransactionReturnData,
+    },
+    crate::feature_set::{
+        blake3_syscall_enabled, curve25519_syscall_enabled, enable_alt_bn128_syscall, FeatureSet,
+    },
+    base64::{prelude::BASE64_STANDARD, Engine},
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_bn254::prelude::{
+        alt_bn128_g1_addition_be, alt_bn128_g1_multiplication_be, alt_bn128_pairing_be,
+        ALT_BN128_G1_ADD_BE, ALT_BN128_G1_MUL_BE, ALT_BN128_G1_POINT_SIZE, ALT_BN128_PAIRING_BE,
+        ALT_BN128_PAIRING_ELEMENT_SIZE, ALT_BN128_PAIRING_OUTPUT_SIZE,
+    },
+    solana_curve25519::{
+        curve_syscall_traits::{ADD, CURVE25519_EDWARDS, CURVE25519_RISTRETTO, MUL, SUB},
+        edwards::{self, PodEdwardsPoint},
+        ristretto::{self, PodRistrettoPoint},
+        scalar::PodScalar,
+    },
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_sbpf::{
+        aligned_memory::AlignedMemory,
+        declare_builtin_function,
+        ebpf::{HOST_ALIGN, MM_HEAP_START, MM_INPUT_START, MM_STACK_START},
+        elf::Executable,
+        error::{EbpfError, ProgramResult},
+        memory_region::{
+            AccessType, AccessViolationHandler, HostBuffer, MemoryMapping, MemoryRegion,
+        },
+        program::{BuiltinProgram, SBPFVersion},
+        verifier::RequisiteVerifier,
+        vm::{CallFrame, Config, ContextObject, EbpfVm, ExecutionMode},
+    },
+    solana_sdk_ids::bpf_loader,
+    std::{
+        cell::{Cell, RefCell},
+        error::Error,
+        ptr::NonNull,
+        rc::Rc,
+        sync::Arc,
+    },
+};
+
+/// CUs consumed by an instruction addressed to the loader itself.
+pub const DEFAULT_LOADER_COMPUTE_UNITS: u64 = 570;
+
+/// Most bytes of return data a program can set.
+pub const MAX_RETURN_DATA: usize = 1024;
+
+/// Most slices a hashing syscall hashes at once.
+const HASH_MAX_SLICES: u64 = 20_000;
+
+/// Status a program returns, and syscalls report, on success.
+const SUCCESS: u64 = 0;
+
+/// Status curve syscalls report for an invalid point or input.
+const INVALID_INPUT: u64 = 1;
+
+/// Status `sol_get_sysvar` reports for a range outside the sysvar.
+const OFFSET_LENGTH_EXCEEDS_SYSVAR: u64 = 1;
+
+/// Status `sol_get_sysvar` reports for a sysvar the cache does not hold.
+const SYSVAR_NOT_FOUND: u64 = 2;
+
+/// Errors raised by syscalls.
+#[derive(Debug, thiserror::Error)]
+pub enum SyscallError {
+    #[error("program aborted")]
+    Abort,
+    #[error("program panicked in {file} at {line}:{column}")]
+    Panic {
+        file: String,
+        line: u64,
+        column: u64,
+    },
+    #[error("string is not valid UTF-8")]
+    InvalidString,
+    #[error("return data of {0} bytes exceeds the limit of {MAX_RETURN_DATA}")]
+    ReturnDataTooLarge(u64),
+    #[error("{0} slices exceed the limit of {HASH_MAX_SLICES}")]
+    TooManySlices(u64),
+    #[error("invalid curve or group operation")]
+    InvalidAttribute,
+}
+
+/// Shape of the stack SBF programs run with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VmConfig {
+    /// Most call frames a program may have at once, its entrypoint's
+    /// included; a deeper call fails the instruction.
+    pub max_call_depth: usize,
+    /// Bytes of stack every call frame gets. Programs are compiled for a
+    /// frame size, so changing it only suits experiments.
+    pub stack_frame_size: usize,
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        let config = Config::default();
+        Self {
+            max_call_depth: config.max_call_depth,
+            stack_frame_size: config.stack_frame_size,
+        }
+    }
+}
+
+/// State the VM and its syscalls share while a program runs.
+pub(crate) struct SbfContext {
+    compute_meter: u64,
+    memory_mapping: MemoryMapping,
+    sysvar_cache: Arc<SysvarCache>,
+    compute_costs: Arc<ComputeCostTable>,
+    program_id: Pubkey,
+    /// Return data and logs of the transaction, moved in before the
+    /// program runs and back out once it returns.
+    return_data: TransactionReturnData,
+    log_collector: LogCollector,
+}
+
+impl SbfContext {
+    fn consume_checked(&mut self, units: u64) -> Result<(), Box<dyn Error>> {
+        if self.compute_meter < units {
+            self.compute_meter = 0;
+            return Err(Box::new(InstructionError::ComputationalBudgetExceeded));
+        }
+        self.compute_meter -= units;
+        Ok(())
+    }
+
+    fn translate_slice(&self, vm_addr: u64, len: u64) -> Result<&[u8], Box<dyn Error>> {
+        let host_buffer: Result<HostBuffer, EbpfError> = self
+            .memory_mapping
+            .map(AccessType::Load, vm_addr, len)
+            .into();
+        let slice = match host_buffer? {
+            HostBuffer::Immutable(slice) => slice,
+            HostBuffer::Mutable(slice) => slice.cast_const(),
+        };
+        // SAFETY: every region of the mapping is backed by memory that
+        // outlives the mapping, and nothing writes to it while a syscall
+        // runs.
+        Ok(unsafe { &*slice })
+    }
+
+    fn translate_slice_mut(&mut self, vm_addr: u64, len: u64) -> Result<&mut [u8], Box<dyn Error>> {
+        // Mapped account data is copied on the first write, by syscalls
+        // as much as by the program.
+        let host_buffer: Result<HostBuffer, EbpfError> = self
+            .memory_mapping
+            .map_with_access_violation_handler(AccessType::Store, vm_addr, len)
+            .into();
+        let HostBuffer::Mutable(slice) = host_buffer? else {
+            // Stores are only ever mapped to writable regions.
+            return Err(Box::new(InstructionError::ProgramFailedToComplete));
+        };
+        // SAFETY: as in `translate_slice`; the region is writable and the
+        // returned slice borrows the context mutably, so nothing else
+        // accesses it until the syscall is done with it.
+        Ok(unsafe { &mut *slice })
+    }
+
+    /// Reads `len` pointer and length pairs at `vm_addr`.
+    fn translate_slice_descriptors(
+        &self,
+        vm_addr: u64,
+        len: u64,
+    ) -> Result<Vec<(u64, u64)>, Box<dyn Error>> {
+        Ok(self
+            .translate_slice(vm_addr, len.saturating_mul(16))?
+            .chunks_exact(16)
+            .map(|descriptor| {
+                let (ptr, len) = descriptor.split_at(8);
+                (
+                    u64::from_le_bytes(ptr.try_into().unwrap()),
+                    u64::from_le_bytes(len.try_into().unwrap()),
+                )
+            })
+            .collect())
+    }
+
+    fn translate_string(&self, vm_addr: u64, len: u64) -> Result<&str, Box<dyn Error>> {
+        let bytes = self.translate_slice(vm_addr, len)?;
+        std::str::from_utf8(bytes).map_err(|_| Box::new(SyscallError::InvalidString) as _)
+    }
+}
+
+impl ContextObject for SbfContext {
+    fn consume(&mut self, amount: u64) {
+        self.compute_meter = self.compute_meter.saturating_sub(amount);
+    }
+
+    fn get_remaining(&self) -> u64 {
+        self.compute_meter
+    }
+
+    fn active_mapping_ptr(&mut self) -> NonNull<MemoryMapping> {
+        NonNull::from_mut(&mut self.memory_mapping)
+    }
+}
+
+declare_builtin_function!(
+    /// Aborts the program.
+    SyscallAbort,
+    fn rust(
+        _context: &mut SbfContext,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        Err(Box::new(SyscallError::Abort))
+    }
+);
+
+declare_builtin_function!(
+    /// Aborts the program with the location of a panic.
+    SyscallPanic,
+    fn rust(
+        context: &mut SbfContext,
+        file: u64,
+        len: u64,
+        line: u64,
+        column: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        context.consume_checked(len)?;
+        let file = context.translate_string(file, len)?.to_owned();
+        Err(Box::new(SyscallError::Panic { file, line, column }))
+    }
+);
+
+declare_builtin_function!(
+    /// Logs a UTF-8 string.
+    SyscallLog,
+    fn rust(
+        context: &mut SbfContext,
+        addr: u64,
+        len: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        context.consume_checked(context.compute_costs.syscall_base_cost.max(len))?;
+        let message = format!("Program log: {}", context.translate_string(addr, len)?);
+        context.log_collector.log(&message);
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// Logs five integers.
+    SyscallLogU64,
+    fn rust(
+        context: &mut SbfContext,
+        arg1: u64,
+        arg2: u64,
+        arg3: u64,
+        arg4: u64,
+        arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        context.consume_checked(context.compute_costs.syscall_base_cost)?;
+        context.log_collector.log(&format!(
+            "Program log: {arg1:#x}, {arg2:#x}, {arg3:#x}, {arg4:#x}, {arg5:#x}"
+        ));
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// Logs a list of byte slices, given as `len` pointer and length pairs
+    /// at `addr`, base64 encoded.
+    SyscallLogData,
+    fn rust(
+        context: &mut SbfContext,
+        addr: u64,
+        len: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        let base_cost = context.compute_costs.syscall_base_cost;
+        context.consume_checked(base_cost.saturating_mul(len.saturating_add(1)))?;
+        let descriptors = context.translate_slice_descriptors(addr, len)?;
+        context.consume_checked(descriptors.iter().map(|(_, len)| len).sum())?;
+        let fields = descriptors
+            .into_iter()
+            .map(|(ptr, len)| Ok(BASE64_STANDARD.encode(context.translate_slice(ptr, len)?)))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        context
+            .log_collector
+            .log(&format!("Program data: {}", fields.join(" ")));
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// Copies part of a sysvar's account data into program memory.
+    SyscallGetSysvar,
+    fn rust(
+        context: &mut SbfContext,
+        sysvar_id_addr: u64,
+        var_addr: u64,
+        offset: u64,
+        length: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        let costs = Arc::clone(&context.compute_costs);
+        context.consume_checked(costs.syscall_base_cost + length / costs.bytes_per_unit)?;
+        let sysvar_id = Pubkey::try_from(context.translate_slice(sysvar_id_addr, 32)?)?;
+        let sysvar_cache = Arc::clone(&context.sysvar_cache);
+        let data = match sysvar_cache.read_sysvar(&sysvar_id, offset as usize, length as usize) {
+            Ok(data) => data,
+            Err(InstructionError::UnsupportedSysvar) => return Ok(SYSVAR_NOT_FOUND),
+            Err(_) => return Ok(OFFSET_LENGTH_EXCEEDS_SYSVAR),
+        };
+        context
+            .translate_slice_mut(var_addr, length)?
+            .copy_from_slice(data);
+        Ok(SUCCESS)
+    }
+);
+
+declare_builtin_function!(
+    /// Sets the program's return data.
+    SyscallSetReturnData,
+    fn rust(
+        context: &mut SbfContext,
+        addr: u64,
+        len: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        let costs = Arc::clone(&context.compute_costs);
+        context.consume_checked(costs.syscall_base_cost + len / costs.bytes_per_unit)?;
+        if len > MAX_RETURN_DATA as u64 {
+            return Err(Box::new(SyscallError::ReturnDataTooLarge(len)));
+        }
+        let data = context.translate_slice(addr, len)?.to_vec();
+        context.log_collector.log(&format!(
+            "Program return: {} {}",
+            context.program_id,
+            BASE64_STANDARD.encode(&data)
+        ));
+        context.return_data = TransactionReturnData {
+            program_id: context.program_id,
+            data,
+        };
+        Ok(0)
+    }
+);
+
+declare_builtin_function!(
+    /// Copies up to `length` bytes of the transaction's return data into
+    /// program memory, along with the program that set it, and returns the
+    /// full length of the data.
+    SyscallGetReturnData,
+    fn rust(
+        context: &mut SbfContext,
+        return_data_addr: u64,
+        length: u64,
+        program_id_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        let TransactionReturnData { program_id, data } = context.return_data.clone();
+        let length = length.min(data.len() as u64);
+        let costs = Arc::clone(&context.compute_costs);
+        context.consume_checked(costs.syscall_base_cost + (length + 32) / costs.bytes_per_unit)?;
+        if length != 0 {
+            context
+                .translate_slice_mut(return_data_addr, length)?
+                .copy_from_slice(&data[..length as usize]);
+            context
+                .translate_slice_mut(program_id_addr, 32)?
+                .copy_from_slice(program_id.as_ref());
+        }
+        Ok(data.len() as u64)
+    }
+);
+
+/// Hashes the `vals_len` slices at `vals_addr` with `hashv` and writes the
+/// 32 byte digest to `result_addr`.
+///
+/// Every hash costs the table's `hash_base_cost`, plus `hash_byte_cost` per
+/// two bytes of each slice but at least `mem_op_base_cost` per slice.
+fn hash_slices(
+    context: &mut SbfContext,
+    vals_addr: u64,
+    vals_len: u64,
+    result_addr: u64,
+    hashv: fn(&[&[u8]]) -> [u8; 32],
+) -> Result<u64, Box<dyn Error>> {
+    if vals_len > HASH_MAX_SLICES {
+        return Err(Box::new(SyscallError::TooManySlices(vals_len)));
+    }
+    let costs = Arc::clone(&context.compute_costs);
+    context.consume_checked(costs.hash_base_cost)?;
+    let descriptors = context.translate_slice_descriptors(vals_addr, vals_len)?;
+    let cost = descriptors
+        .iter()
+        .map(|(_, len)| costs.mem_op_base_cost.max(costs.hash_byte_cost * (len / 2)))
+        .fold(0, u64::saturating_add);
+    context.consume_checked(cost)?;
+    let vals = descriptors
+        .into_iter()
+        .map(|(ptr, len)| context.translate_slice(ptr, len))
+        .collect::<Result<Vec<_>, _>>()?;
+    let digest = hashv(&vals);
+    context
+        .translate_slice_mut(result_addr, 32)?
+        .copy_from_slice(&digest);
+    Ok(SUCCESS)
+}
+
+declare_builtin_function!(
+    /// Writes the SHA-256 digest of a list of byte slices.
+    SyscallSha256,
+    fn rust(
+        context: &mut SbfContext,
+        vals_addr: u64,
+        vals_len: u64,
+        result_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        hash_slices(context, vals_addr, vals_len, result_addr, |vals| {
+            solana_sha256_hasher::hashv(vals).to_bytes()
+        })
+    }
+);
+
+declare_builtin_function!(
+    /// Writes the Keccak-256 digest of a list of byte slices.
+    SyscallKeccak256,
+    fn rust(
+        context: &mut SbfContext,
+        vals_addr: u64,
+        vals_len: u64,
+        result_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        hash_slices(context, vals_addr, vals_len, result_addr, |vals| {
+            solana_keccak_hasher::hashv(vals).to_bytes()
+        })
+    }
+);
+
+declare_builtin_function!(
+    /// Writes the BLAKE3 digest of a list of byte slices.
+    SyscallBlake3,
+    fn rust(
+        context: &mut SbfContext,
+        vals_addr: u64,
+        vals_len: u64,
+        result_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        hash_slices(context, vals_addr, vals_len, result_addr, |vals| {
+            solana_blake3_hasher::hashv(vals).to_bytes()
+        })
+    }
+);
+
+declare_builtin_function!(
+    /// Checks whether the 32 bytes at `point_addr` encode a point on a
+    /// curve25519 group, reporting [`SUCCESS`] if they do.
+    SyscallCurvePointValidation,
+    fn rust(
+        context: &mut SbfContext,
+        curve_id: u64,
+        point_addr: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        let costs = &context.compute_costs;
+        let (cost, validate): (u64, fn([u8; 32]) -> bool) = match curve_id {
+            CURVE25519_EDWARDS => (costs.edwards_validate_point_cost, |point| {
+                edwards::validate_edwards(&PodEdwardsPoint(point))
+            }),
+            CURVE25519_RISTRETTO => (costs.ristretto_validate_point_cost, |point| {
+                ristretto::validate_ristretto(&PodRistrettoPoint(point))
+            }),
+            _ => return Err(Box::new(SyscallError::InvalidAttribute)),
+        };
+        context.consume_checked(cost)?;
+        let point = context.translate_slice(point_addr, 32)?.try_into()?;
+        Ok(if validate(point) { SUCCESS } else { INVALID_INPUT })
+    }
+);
+
+/// A curve25519 group operation on two 32 byte inputs.
+type CurveGroupOp = fn(&[u8; 32], &[u8; 32]) -> Option<[u8; 32]>;
+
+declare_builtin_function!(
+    /// Adds, subtracts or multiplies curve25519 points, writing the 32 byte
+    /// result to `result_addr`. For multiplication the left input is the
+    /// scalar. An input that is not a point reports [`INVALID_INPUT`].
+    SyscallCurveGroupOps,
+    fn rust(
+        context: &mut SbfContext,
+        curve_id: u64,
+        group_op: u64,
+        left_input_addr: u64,
+        right_input_addr: u64,
+        result_addr: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        let costs = &context.compute_costs;
+        let (cost, operation): (u64, CurveGroupOp) = match (curve_id, group_op) {
+            (CURVE25519_EDWARDS, ADD) => (costs.edwards_add_cost, |left, right| {
+                edwards::add_edwards(&PodEdwardsPoint(*left), &PodEdwardsPoint(*right))
+                    .map(|point| point.0)
+            }),
+            (CURVE25519_EDWARDS, SUB) => (costs.edwards_subtract_cost, |left, right| {
+                edwards::subtract_edwards(&PodEdwardsPoint(*left), &PodEdwardsPoint(*right))
+                    .map(|point| point.0)
+            }),
+            (CURVE25519_EDWARDS, MUL) => (costs.edwards_multiply_cost, |scalar, point| {
+                edwards::multiply_edwards(&PodScalar(*scalar), &PodEdwardsPoint(*point))
+                    .map(|point| point.0)
+            }),
+            (CURVE25519_RISTRETTO, ADD) => (costs.ristretto_add_cost, |left, right| {
+                ristretto::add_ristretto(&PodRistrettoPoint(*left), &PodRistrettoPoint(*right))
+                    .map(|point| point.0)
+            }),
+            (CURVE25519_RISTRETTO, SUB) => (costs.ristretto_subtract_cost, |left, right| {
+                ristretto::subtract_ristretto(
+                    &PodRistrettoPoint(*left),
+                    &PodRistrettoPoint(*right),
+                )
+                .map(|point| point.0)
+            }),
+            (CURVE25519_RISTRETTO, MUL) => (costs.ristretto_multiply_cost, |scalar, point| {
+                ristretto::multiply_ristretto(&PodScalar(*scalar), &PodRistrettoPoint(*point))
+                    .map(|point| point.0)
+            }),
+            _ => return Err(Box::new(SyscallError::InvalidAttribute)),
+        };
+        context.consume_checked(cost)?;
+        let left = context.translate_slice(left_input_addr, 32)?.try_into()?;
+        let right = context.translate_slice(right_input_addr, 32)?.try_into()?;
+        let Some(result) = operation(&left, &right) else {
+            return Ok(INVALID_INPUT);
+        };
+        context
+            .translate_slice_mut(result_addr, 32)?
+            .copy_from_slice(&result);
+        Ok(SUCCESS)
+    }
+);
+
+declare_builtin_function!(
+    /// Runs an alt_bn128 operation on the `input_size` big-endian bytes at
+    /// `input_addr`: G1 addition or scalar multiplication, which write a
+    /// 64 byte point, or a pairing check, which writes 32 bytes ending in
+    /// 1 if the pairing holds. Invalid input reports [`INVALID_INPUT`].
+    SyscallAltBn128,
+    fn rust(
+        context: &mut SbfContext,
+        group_op: u64,
+        input_addr: u64,
+        input_size: u64,
+        result_addr: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        type AltBn128Op = fn(&[u8]) -> Result<Vec<u8>, solana_bn254::prelude::AltBn128Error>;
+        let costs = &context.compute_costs;
+        let (cost, output_size, operation): (u64, usize, AltBn128Op) = match group_op {
+            ALT_BN128_G1_ADD_BE => (
+                costs.alt_bn128_addition_cost,
+                ALT_BN128_G1_POINT_SIZE,
+                alt_bn128_g1_addition_be,
+            ),
+            ALT_BN128_G1_MUL_BE => (
+                costs.alt_bn128_multiplication_cost,
+                ALT_BN128_G1_POINT_SIZE,
+                alt_bn128_g1_multiplication_be,
+            ),
+            ALT_BN128_PAIRING_BE => {
+                let pairs = input_size / ALT_BN128_PAIRING_ELEMENT_SIZE as u64;
+                let cost = costs
+                    .alt_bn128_pairing_one_pair_cost_first
+                    .saturating_add(
+                        costs
+                            .alt_bn128_pairing_one_pair_cost_other
+                            .saturating_mul(pairs.saturating_sub(1)),
+                    )
+                    .saturating_add(costs.hash_base_cost)
+                    .saturating_add(input_size)
+                    .saturating_add(ALT_BN128_PAIRING_OUTPUT_SIZE as u64);
+                (cost, ALT_BN128_PAIRING_OUTPUT_SIZE, alt_bn128_pairing_be)
+            }
+            _ => return Err(Box::new(SyscallError::InvalidAttribute)),
+        };
+        context.consume_checked(cost)?;
+        let Ok(result) = operation(context.translate_slice(input_addr, input_size)?) else {
+            return Ok(INVALID_INPUT);
+        };
+        if result.len() != output_size {
+            return Ok(INVALID_INPUT);
+        }
+        context
+            .translate_slice_mut(result_addr, output_size as u64)?
+            .copy_from_slice(&result);
+        Ok(SUCCESS)
+    }
+);
+
+/// The loader SBF programs are linked against, with the syscalls
+/// `feature_set` enables and the stack `vm_config` describes.
+pub(crate) fn create_loader(
+    feature_set: &FeatureSet,
+    vm_config: &VmConfig,
+) -> Arc<BuiltinProgram<SbfContext>> {
+    let config = Config {
+        enabled_sbpf_versions: SBPFVersion::V0..=SBPFVersion::V3,
+        max_call_depth: vm_config.max_call_depth,
+        stack_frame_size: vm_config.stack_frame_size,
+        ..Config::default()
+    };
+    let mut loader = BuiltinProgram::new_loader(config);
+    for (name, result) in [
+        ("abort", loader.register_definition::<SyscallAbort>("abort")),
+        (
+            "sol_panic_",
+            loader.register_definition::<SyscallPanic>("sol_panic_"),
+        ),
+        (
+            "sol_log_",
+            loader.register_definition::<SyscallLog>("sol_log_"),
+        ),
+        (
+            "sol_log_64_",
+            loader.register_definition::<SyscallLogU64>("sol_log_64_"),
+        ),
+        (
+            "sol_log_data",
+            loader.register_definition::<SyscallLogData>("sol_log_data"),
+        ),
+        (
+            "sol_get_sysvar",
+            loader.register_definition::<SyscallGetSysvar>("sol_get_sysvar"),
+        ),
+        (
+            "sol_set_return_data",
+            loader.register_definition::<SyscallSetReturnData>("sol_set_return_data"),
+        ),
+        (
+            "sol_get_return_data",
+            loader.register_definition::<SyscallGetReturnData>("sol_get_return_data"),
+        ),
+        (
+            "sol_sha256",
+            loader.register_definition::<SyscallSha256>("sol_sha256"),
+        ),
+        (
+            "sol_keccak256",
+            loader.register_definition::<SyscallKeccak256>("sol_keccak256"),
+        ),
+        (
+            "sol_blake3",
+            loader.register_definition::<SyscallBlake3>("sol_blake3"),
+        ),
+        (
+            "sol_curve_validate_point",
+            loader.register_definition::<SyscallCurvePointValidation>("sol_curve_validate_point"),
+        ),
+        (
+            "sol_curve_group_op",
+            loader.register_definition::<SyscallCurveGroupOps>("sol_curve_group_op"),
+        ),
+        (
+            "sol_alt_bn128_group_op",
+            loader.register_definition::<SyscallAltBn128>("sol_alt_bn128_group_op"),
+        ),
+    ] {
+        result.unwrap_or_else(|err| panic!("failed to register {name}: {err}"));
+    }
+    for (name, feature_id) in [
+        ("sol_blake3", blake3_syscall_enabled::id()),
+        ("sol_curve_validate_point", curve25519_syscall_enabled::id()),
+        ("sol_curve_group_op", curve25519_syscall_enabled::id()),
+        ("sol_alt_bn128_group_op", enable_alt_bn128_syscall::id()),
+    ] {
+        if !feature_set.is_active(&feature_id) {
+            loader.unregister_function(name);
+        }
+    }
+    Arc::new(loader)
+}
+
+/// Whether `account` is a program deployed with the BPF loader.
+pub(crate) fn is_sbf_program(account: &AccountSharedData) -> bool {
+    account.executable() && *account.owner() == bpf_loader::id()
+}
+
+/// Entrypoint of the BPF loader itself.
+///
+/// Programs of this loader are deployed by storing their ELF in an
+/// executable account; the loader has no management instructions, so
+/// every instruction addressed to it fails.
+pub(crate) fn process_loader_instruction(
+    invoke_context: &mut InvokeContext,
+) -> Result<(), InstructionError> {
+    invoke_context.consume_builtin_cost(DEFAULT_LOADER_COMPUTE_UNITS)?;
+    Err(InstructionError::UnsupportedProgramId)
+}
+
+/// Loads and verifies the ELF of an SBF program against `loader`.
+///
+/// Fails with [`InstructionError::InvalidAccountData`] if `elf_bytes` is
+/// not a valid program.
+pub(crate) fn load_program(
+    elf_bytes: &[u8],
+    loader: Arc<BuiltinProgram<SbfContext>>,
+) -> Result<Executable<SbfContext>, InstructionError> {
+    let executable =
+        Executable::load(elf_bytes, loader).map_err(|_| InstructionError::InvalidAccountData)?;
+    executable
+        .verify::<RequisiteVerifier>()
+        .map_err(|_| InstructionError::InvalidAccountData)?;
+    Ok(executable)
+}
+
+/// Checks that `elf_bytes` is a valid program for the loader of
+/// `invoke_context`'s executor, as [`load_program`] does.
+pub(crate) fn verify_program(
+    elf_bytes: &[u8],
+    invoke_context: &InvokeContext,
+) -> Result<(), InstructionError> {
+    load_program(elf_bytes, invoke_context.program_loader()).map(drop)
+}
+
+/// Runs `executable` on the instruction of `invoke_context`.
+///
+/// The program gets the heap its transaction requested, charged up front
+/// if it is larger than the default. Every executed SBF instruction costs
+/// one CU, and what the program consumed is logged once it returns. A program that does not
+/// return [`SUCCESS`] fails the instruction with the error its status
+/// encodes, and none of its account changes are applied.
+pub(crate) fn process_instruction(
+    executable: &Executable<SbfContext>,
+    invoke_context: &mut InvokeContext,
+) -> Result<(), InstructionError> {
+    let heap_size = invoke_context.heap_size();
+    invoke_context.consume_checked(invoke_context.compute_costs().heap_frame_cost(heap_size))?;
+    let parameters = serialize_parameters(invoke_context)?;
+    let config = executable.get_config();
+    let sbpf_version = executable.get_sbpf_version();
+    let mut input = AlignedMemory::<HOST_ALIGN>::from_slice(&parameters.buffer);
+    let input_buffer = HostBuffer::Mutable(input.as_slice_mut());
+    let mut stack = AlignedMemory::<HOST_ALIGN>::zero_filled(config.stack_size());
+    let mut heap = AlignedMemory::<HOST_ALIGN>::zero_filled(heap_size as usize);
+    let stack_gap = if sbpf_version.stack_frame_gaps() && config.enable_stack_frame_gaps {
+        config.stack_frame_size as u64
+    } else {
+        0
+    };
+    let mut regions = vec![
+        executable.get_ro_region(),
+        MemoryRegion::new_gapped(&mut stack, MM_STACK_START, stack_gap),
+        MemoryRegion::new(&mut heap, MM_HEAP_START),
+    ];
+    // The input region alternates between parts of the buffer and mapped
+    // account data.
+    let (mut buffer_offset, mut vm_offset) = (0, 0);
+    for (index, mapped) in parameters.mapped.iter().enumerate() {
+        let buffer = input_buffer
+            .get(buffer_offset..mapped.buffer_offset)
+            .expect("mapped data lies within the buffer");
+        regions.push(MemoryRegion::new(buffer, MM_INPUT_START + vm_offset as u64));
+        let data: *const [u8] = mapped.data.as_slice();
+        let mut region = MemoryRegion::new(data, MM_INPUT_START + mapped.vm_offset as u64);
+        region.access_violation_handler_payload =
+            Some(u16::try_from(index).map_err(|_| InstructionError::MaxAccountsExceeded)?);
+        regions.push(region);
+        (buffer_offset, vm_offset) = (mapped.buffer_offset, mapped.vm_end);
+    }
+    let buffer = input_buffer
+        .get(buffer_offset..input_buffer.len())
+        .expect("offset lies within the buffer");
+    regions.push(MemoryRegion::new(buffer, MM_INPUT_START + vm_offset as u64));
+
+    let written = Rc::new(RefCell::new(vec![None; parameters.mapped.len()]));
+    let readonly_data_modified = Rc::new(Cell::new(false));
+    let copy_on_write = copy_on_write_handler(
+        parameters
+            .mapped
+            .iter()
+            .map(|mapped| mapped.is_writable)
+            .collect(),
+        Rc::clone(&written),
+        Rc::clone(&readonly_data_modified),
+    );
+    // SAFETY: the executable, stack, heap, input and mapped account data
+    // all outlive the mapping, which is dropped together with `context`
+    // at the end of this function. The writable regions are plain bytes,
+    // and the handler only redirects regions to copies it owns.
+    let memory_mapping = unsafe {
+        MemoryMapping::new_with_access_violation_handler(
+            regions,
+            config,
+            sbpf_version,
+            copy_on_write,
+        )
+    }
+    .map_err(|_| InstructionError::ProgramEnvironmentSetupFailure)?;
+
+    let remaining = invoke_context.get_remaining();
+    let mut context = SbfContext {
+        compute_meter: remaining,
+        memory_mapping,
+        sysvar_cache: invoke_context.shared_sysvar_cache(),
+        compute_costs: invoke_context.shared_compute_costs(),
+        program_id: *invoke_context.program_id(),
+        return_data: std::mem::take(invoke_context.return_data_mut()),
+        log_collector: std::mem::take(invoke_context.log_collector_mut()),
+    };
+    let mut vm = EbpfVm::new(
+        executable.get_loader().clone(),
+        sbpf_version,
+        &mut context,
+        config.stack_size(),
+    );
+    vm.registers[1] = MM_INPUT_START;
+    vm.registers[2] = MM_INPUT_START + parameters.instruction_data_offset as u64;
+    let mut call_frames = vec![CallFrame::default(); config.max_call_depth];
+    let (_, result) = vm.execute_program(
+        executable,
+        &mut ExecutionMode::Interpreted,
+        &mut call_frames,
+    );
+    drop(vm);
+    *invoke_context.return_data_mut() = std::mem::take(&mut context.return_data);
+    *invoke_context.log_collector_mut() = std::mem::take(&mut context.log_collector);
+    let consumed = remaining.saturating_sub(context.compute_meter);
+    let program_id = *invoke_context.program_id();
+    invoke_context.log_collector_mut().log(&format!(
+        "Program {program_id} consumed {consumed} of {remaining} compute units"
+    ));
+    invoke_context.consume_checked(consumed)?;
+
+    if readonly_data_modified.get() {
+        return Err(InstructionError::ReadonlyDataModified);
+    }
+    match result {
+        ProgramResult::Ok(SUCCESS) => {}
+        ProgramResult::Ok(status) => return Err(InstructionError::from(status)),
+        ProgramResult::Err(EbpfError::ExceededMaxInstructions) => {
+            return Err(InstructionError::ComputationalBudgetExceeded)
+        }
+        ProgramResult::Err(EbpfError::SyscallError(err)) => {
+            return Err(err
+                .downcast::<InstructionError>()
+                .map(|err| *err)
+                .unwrap_or(InstructionError::ProgramFailedToComplete))
+        }
+        ProgramResult::Err(_) => return Err(InstructionError::ProgramFailedToComplete),
+    }
+    drop(context);
+
+    let written = written.take();
+    deserialize_parameters(
+        invoke_context,
+        input.as_slice(),
+        &parameters.accounts,
+        &written,
+    )
+}
+
+/// Copies mapped account data on the first access past what the mapping
+/// allows: a write, or a read of the spare bytes after the data.
+///
+/// The copy of writable data, spare bytes included, replaces the region
+/// and is recorded in `written`. Read-only data stays mapped as is; a
+/// write to it fails and sets `readonly_data_modified`.
+fn copy_on_write_handler(
+    is_writable: Vec<bool>,
+    written: Rc<RefCell<Vec<Option<Vec<u8>>>>>,
+    readonly_data_modified: Rc<Cell<bool>>,
+) -> AccessViolationHandler {
+    Box::new(move |region, max_len, access_type, _vm_addr, _len| {
+        let Some(index) = region.access_violation_handler_payload.map(usize::from) else {
+            return;
+        };
+        if !is_writable[index] {
+            if access_type == AccessType::Store {
+                readonly_data_modified.set(true);
+            }
+            return;
+        }
+        let mut written = written.borrow_mut();
+        if written[index].is_some() {
+            // Already copied; the access is out of the account's bounds.
+            return;
+        }
+        // SAFETY: the region still maps the account's data, which outlives
+        // the mapping.
+        let data = unsafe { &*region.host_buffer().ptr() };
+        let mut copy = Vec::with_capacity(max_len as usize);
+        copy.extend_from_slice(data);
+        copy.resize(max_len as usize, 0);
+        let copy: *mut [u8] = written[index].insert(copy).as_mut_slice();
+        // SAFETY: the copy covers exactly the data and the spare bytes up
+        // to the next region, and is neither moved nor dropped before the
+        // mapping is.
+        unsafe { region.redirect(copy) };
+    })
+}
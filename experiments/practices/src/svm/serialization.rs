@@ -0,0 +1,240 @@
+//! The input region an SBF program receives.
+//!
+//! This is the aligned layout of the BPF loader: the number of accounts,
+//! then every instruction account, then the instruction data and the
+//! program id. An account listed twice is serialized once; later mentions
+//! only refer back to the first one. Every account is followed by
+//! [`MAX_PERMITTED_DATA_INCREASE`] spare bytes so the program can grow its
+//! data in place.
+//!
+//! The data of an account of at least [`MIN_DIRECT_MAPPED_DATA_LEN`] bytes
+//! is not copied into the buffer. The input region maps the account's own
+//! data in its place instead, read-only, and the loader copies it only
+//! once the program first writes to it. Programs that merely read a large
+//! account thus never copy it, and one that did not write it leaves it
+//! unchanged without comparing its data.
+
+use {
+    super::InvokeContext,
+    solana_account::{ReadableAccount, WritableAccount},
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    std::sync::Arc,
+};
+
+/// Bytes a program may add to an account's data during one instruction.
+pub const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+/// Accounts with at least this many bytes of data are mapped into the
+/// input region rather than copied into it.
+pub const MIN_DIRECT_MAPPED_DATA_LEN: usize = 1024 * 1024;
+
+/// Leads an account that is serialized in full rather than as a
+/// reference to an earlier instruction account.
+const NON_DUP_MARKER: u8 = u8::MAX;
+
+/// Alignment of the fields following an account's data.
+const BPF_ALIGN_OF_U128: usize = 8;
+
+/// Where an instruction account ended up in the input region.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SerializedAccount {
+    /// Refers back to an earlier instruction account.
+    Duplicate,
+    Account {
+        /// Offset of the owner, which is followed by the lamports, the
+        /// data length and the data.
+        owner_offset: usize,
+        original_data_len: usize,
+        /// Index of the account's entry in
+        /// [`SerializedParameters::mapped`] if its data is mapped.
+        mapped: Option<usize>,
+    },
+}
+
+/// Account data the input region maps in place of the buffer bytes.
+pub(crate) struct MappedData {
+    /// The account's data as of the start of the instruction.
+    pub data: Arc<Vec<u8>>,
+    pub is_writable: bool,
+    /// Where the data starts in the buffer and in the input region. The
+    /// buffer only resumes after the data and its spare bytes, at
+    /// `vm_end`.
+    pub buffer_offset: usize,
+    pub vm_offset: usize,
+    pub vm_end: usize,
+}
+
+/// The serialized input region of one instruction.
+pub(crate) struct SerializedParameters {
+    /// The input region, except for mapped account data.
+    pub buffer: Vec<u8>,
+    /// One entry per instruction account.
+    pub accounts: Vec<SerializedAccount>,
+    /// Mapped account data, in input region order.
+    pub mapped: Vec<MappedData>,
+    /// Offset of the instruction data in the input region.
+    pub instruction_data_offset: usize,
+}
+
+/// Serializes the accounts and data of the instruction being executed.
+pub(crate) fn serialize_parameters(
+    invoke_context: &InvokeContext,
+) -> Result<SerializedParameters, InstructionError> {
+    let num_accounts = invoke_context.get_number_of_accounts();
+    let mut buffer = Vec::new();
+    let mut accounts = Vec::with_capacity(num_accounts);
+    let mut mapped = Vec::new();
+    // Bytes of the input region that are mapped rather than in the buffer.
+    let mut gap = 0;
+    buffer.extend_from_slice(&(num_accounts as u64).to_le_bytes());
+
+    for index in 0..num_accounts {
+        let index_in_transaction = invoke_context.get_index_in_transaction(index)?;
+        let position = (0..index).find(|&earlier| {
+            invoke_context.get_index_in_transaction(earlier) == Ok(index_in_transaction)
+        });
+        if let Some(position) = position {
+            buffer.push(position as u8);
+            buffer.extend_from_slice(&[0; 7]);
+            accounts.push(SerializedAccount::Duplicate);
+            continue;
+        }
+
+        let account = invoke_context.get_account(index)?;
+        let data_len = account.data().len();
+        let is_writable = invoke_context.is_writable(index)?;
+        buffer.push(NON_DUP_MARKER);
+        buffer.push(u8::from(invoke_context.is_signer(index)?));
+        buffer.push(u8::from(is_writable));
+        buffer.push(u8::from(account.executable()));
+        buffer.extend_from_slice(&(data_len as u32).to_le_bytes());
+        buffer.extend_from_slice(invoke_context.get_key(index)?.as_ref());
+        let owner_offset = buffer.len();
+        buffer.extend_from_slice(account.owner().as_ref());
+        buffer.extend_from_slice(&account.lamports().to_le_bytes());
+        buffer.extend_from_slice(&(data_len as u64).to_le_bytes());
+        let mapped_index = if data_len >= MIN_DIRECT_MAPPED_DATA_LEN {
+            // Every account header is a multiple of the alignment long,
+            // so the gap is too and buffer offsets stay as aligned as
+            // region offsets.
+            let vm_offset = buffer.len() + gap;
+            let vm_end = (vm_offset + data_len + MAX_PERMITTED_DATA_INCREASE)
+                .next_multiple_of(BPF_ALIGN_OF_U128);
+            mapped.push(MappedData {
+                data: account.data_clone(),
+                is_writable,
+                buffer_offset: buffer.len(),
+                vm_offset,
+                vm_end,
+            });
+            gap = vm_end - buffer.len();
+            Some(mapped.len() - 1)
+        } else {
+            buffer.extend_from_slice(account.data());
+            buffer.resize(buffer.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            buffer.resize(buffer.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+            None
+        };
+        buffer.extend_from_slice(&account.rent_epoch().to_le_bytes());
+        accounts.push(SerializedAccount::Account {
+            owner_offset,
+            original_data_len: data_len,
+            mapped: mapped_index,
+        });
+    }
+
+    let instruction_data = invoke_context.instruction_data();
+    buffer.extend_from_slice(&(instruction_data.len() as u64).to_le_bytes());
+    let instruction_data_offset = buffer.len() + gap;
+    buffer.extend_from_slice(instruction_data);
+    buffer.extend_from_slice(invoke_context.program_id().as_ref());
+
+    Ok(SerializedParameters {
+        buffer,
+        accounts,
+        mapped,
+        instruction_data_offset,
+    })
+}
+
+/// Applies the account changes a program made to its input region.
+///
+/// Only writable accounts may change. A program that modified a read-only
+/// account fails the instruction, as does one that grew an account by more
+/// than [`MAX_PERMITTED_DATA_INCREASE`] bytes.
+///
+/// `written` holds, for every mapped account, the copy of its data and
+/// spare bytes the program wrote to, or `None` if it only read the data.
+pub(crate) fn deserialize_parameters(
+    invoke_context: &mut InvokeContext,
+    buffer: &[u8],
+    accounts: &[SerializedAccount],
+    written: &[Option<Vec<u8>>],
+) -> Result<(), InstructionError> {
+    for (index, serialized) in accounts.iter().enumerate() {
+        let SerializedAccount::Account {
+            owner_offset,
+            original_data_len,
+            mapped,
+        } = *serialized
+        else {
+            continue;
+        };
+
+        let owner = Pubkey::new_from_array(read_array(buffer, owner_offset)?);
+        let lamports = u64::from_le_bytes(read_array(buffer, owner_offset + 32)?);
+        let data_len = u64::from_le_bytes(read_array(buffer, owner_offset + 40)?) as usize;
+        if data_len > original_data_len.saturating_add(MAX_PERMITTED_DATA_INCREASE) {
+            return Err(InstructionError::InvalidRealloc);
+        }
+        let data_offset = owner_offset + 48;
+        // `None` if the data is still the mapped original.
+        let data = match mapped.map(|mapped| &written[mapped]) {
+            None => Some(buffer.get(data_offset..data_offset + data_len)),
+            Some(Some(copy)) => Some(copy.get(..data_len)),
+            Some(None) => None,
+        }
+        .map(|data| data.ok_or(InstructionError::InvalidRealloc))
+        .transpose()?;
+
+        let account = invoke_context.get_account(index)?;
+        let lamports_changed = account.lamports() != lamports;
+        let data_changed = match data {
+            Some(data) => account.data() != data,
+            None => data_len != original_data_len,
+        };
+        let owner_changed = *account.owner() != owner;
+        if !(lamports_changed || data_changed || owner_changed) {
+            continue;
+        }
+        if !invoke_context.is_writable(index)? {
+            return Err(if lamports_changed {
+                InstructionError::ReadonlyLamportChange
+            } else if data_changed {
+                InstructionError::ReadonlyDataModified
+            } else {
+                InstructionError::ModifiedProgramId
+            });
+        }
+
+        let account = invoke_context.get_account_mut(index)?;
+        account.set_lamports(lamports);
+        if data_changed {
+            match data {
+                Some(data) => account.set_data_from_slice(data),
+                // The program only changed the data length.
+                None => account.resize(data_len, 0),
+            }
+        }
+        account.set_owner(owner);
+    }
+    Ok(())
+}
+
+fn read_array<const N: usize>(buffer: &[u8], offset: usize) -> Result<[u8; N], InstructionError> {
+    buffer
+        .get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(InstructionError::InvalidArgument)
+}
@@ -0,0 +1,218 @@
+//! Sysvars programs can read while executing.
+//!
+//! The bank materializes its sysvars into a [`SysvarCache`] whenever they
+//! change. Transactions that list a sysvar load its account from the cache,
+//! and programs read sysvars straight from it without any account at all.
+//! Every sysvar is serialized once, when the cache is built.
+//!
+//! The Instructions sysvar is the exception: it describes the transaction
+//! being executed, so the executor builds it for every transaction that
+//! lists it instead.
+
+use {
+    crate::fees::DEFAULT_LAMPORTS_PER_SIGNATURE,
+    solana_account::AccountSharedData,
+    solana_clock::{Clock, Slot, DEFAULT_MS_PER_SLOT, MAX_RECENT_BLOCKHASHES},
+    solana_epoch_schedule::EpochSchedule,
+    solana_hash::Hash,
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::sysvar,
+    solana_slot_hashes::SlotHashes,
+    solana_stake_interface::stake_history::StakeHistory,
+    std::collections::HashMap,
+};
+
+/// A blockhash the RecentBlockhashes sysvar lists, with the fee rate that
+/// applied while it was the last blockhash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecentBlockhashEntry {
+    pub blockhash: Hash,
+    pub lamports_per_signature: u64,
+}
+
+/// The bank state sysvars are derived from.
+#[derive(Debug)]
+pub struct SysvarSource<'a> {
+    pub slot: Slot,
+    pub epoch_schedule: &'a EpochSchedule,
+    pub rent: &'a Rent,
+    pub slot_hashes: &'a SlotHashes,
+    pub stake_history: &'a StakeHistory,
+    /// Recent blockhashes, newest first.
+    pub recent_blockhashes: &'a [Hash],
+    pub lamports_per_signature: u64,
+}
+
+/// The sysvars of one bank, as values and as serialized account data.
+#[derive(Debug)]
+pub struct SysvarCache {
+    clock: Clock,
+    epoch_schedule: EpochSchedule,
+    rent: Rent,
+    slot_hashes: SlotHashes,
+    stake_history: StakeHistory,
+    recent_blockhashes: Vec<RecentBlockhashEntry>,
+    /// Account data of every sysvar, by sysvar id.
+    data: HashMap<Pubkey, Vec<u8>>,
+}
+
+impl Default for SysvarCache {
+    /// The sysvars of a bank at slot 0 whose only blockhash is
+    /// `Hash::default()`.
+    fn default() -> Self {
+        Self::new(SysvarSource {
+            slot: 0,
+            epoch_schedule: &EpochSchedule::default(),
+            rent: &Rent::default(),
+            slot_hashes: &SlotHashes::default(),
+            stake_history: &StakeHistory::default(),
+            recent_blockhashes: &[Hash::default()],
+            lamports_per_signature: DEFAULT_LAMPORTS_PER_SIGNATURE,
+        })
+    }
+}
+
+impl SysvarCache {
+    /// Derives every sysvar from `source`.
+    ///
+    /// The cluster has no clock oracle here, so the clock assumes slot 0
+    /// started at the Unix epoch and every slot took
+    /// [`DEFAULT_MS_PER_SLOT`].
+    pub fn new(source: SysvarSource) -> Self {
+        let epoch_schedule = source.epoch_schedule.clone();
+        let epoch = epoch_schedule.get_epoch(source.slot);
+        let timestamp = |slot: Slot| (slot.saturating_mul(DEFAULT_MS_PER_SLOT) / 1000) as i64;
+        let clock = Clock {
+            slot: source.slot,
+            epoch_start_timestamp: timestamp(epoch_schedule.get_first_slot_in_epoch(epoch)),
+            epoch,
+            leader_schedule_epoch: epoch_schedule.get_leader_schedule_epoch(source.slot),
+            unix_timestamp: timestamp(source.slot),
+        };
+        let recent_blockhashes: Vec<_> = source
+            .recent_blockhashes
+            .iter()
+            .take(MAX_RECENT_BLOCKHASHES)
+            .map(|blockhash| RecentBlockhashEntry {
+                blockhash: *blockhash,
+                lamports_per_signature: source.lamports_per_signature,
+            })
+            .collect();
+        let slot_hashes = SlotHashes::new(source.slot_hashes.slot_hashes());
+
+        const SERIALIZABLE: &str = "sysvars are always serializable";
+        let data = HashMap::from([
+            (
+                sysvar::clock::id(),
+                bincode::serialize(&clock).expect(SERIALIZABLE),
+            ),
+            (
+                sysvar::epoch_schedule::id(),
+                bincode::serialize(&epoch_schedule).expect(SERIALIZABLE),
+            ),
+            (
+                sysvar::rent::id(),
+                bincode::serialize(source.rent).expect(SERIALIZABLE),
+            ),
+            (
+                sysvar::slot_hashes::id(),
+                bincode::serialize(&slot_hashes).expect(SERIALIZABLE),
+            ),
+            (
+                sysvar::stake_history::id(),
+                bincode::serialize(source.stake_history).expect(SERIALIZABLE),
+            ),
+            (
+                sysvar::recent_blockhashes::id(),
+                serialize_recent_blockhashes(&recent_blockhashes),
+            ),
+        ]);
+
+        Self {
+            clock,
+            epoch_schedule,
+            rent: source.rent.clone(),
+            slot_hashes,
+            stake_history: source.stake_history.clone(),
+            recent_blockhashes,
+            data,
+        }
+    }
+
+    pub fn get_clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn get_epoch_schedule(&self) -> &EpochSchedule {
+        &self.epoch_schedule
+    }
+
+    pub fn get_rent(&self) -> &Rent {
+        &self.rent
+    }
+
+    pub fn get_slot_hashes(&self) -> &SlotHashes {
+        &self.slot_hashes
+    }
+
+    pub fn get_stake_history(&self) -> &StakeHistory {
+        &self.stake_history
+    }
+
+    /// Recent blockhashes, newest first.
+    pub fn get_recent_blockhashes(&self) -> &[RecentBlockhashEntry] {
+        &self.recent_blockhashes
+    }
+
+    /// Whether `pubkey` is a sysvar this cache holds.
+    pub fn contains(&self, pubkey: &Pubkey) -> bool {
+        self.data.contains_key(pubkey)
+    }
+
+    /// Copies `length` bytes at `offset` of a sysvar's account data, the
+    /// way the `sol_get_sysvar` syscall does.
+    ///
+    /// Fails with [`InstructionError::UnsupportedSysvar`] for sysvars the
+    /// cache does not hold and with [`InstructionError::InvalidArgument`] if
+    /// the range is out of bounds.
+    pub fn read_sysvar(
+        &self,
+        sysvar_id: &Pubkey,
+        offset: usize,
+        length: usize,
+    ) -> Result<&[u8], InstructionError> {
+        let data = self
+            .data
+            .get(sysvar_id)
+            .ok_or(InstructionError::UnsupportedSysvar)?;
+        offset
+            .checked_add(length)
+            .and_then(|end| data.get(offset..end))
+            .ok_or(InstructionError::InvalidArgument)
+    }
+
+    /// The account of a sysvar, owned by the sysvar program and funded to
+    /// be rent exempt.
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        let data = self.data.get(pubkey)?;
+        let lamports = self.rent.minimum_balance(data.len()).max(1);
+        let mut account = AccountSharedData::new(lamports, data.len(), &sysvar::id());
+        account.set_data_from_slice(data);
+        Some(account)
+    }
+}
+
+/// The SDK's RecentBlockhashes type is deprecated, so its account data is
+/// laid out by hand: a u64 count, then every blockhash with its fee
+/// calculator.
+fn serialize_recent_blockhashes(entries: &[RecentBlockhashEntry]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + entries.len() * 40);
+    data.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for entry in entries {
+        data.extend_from_slice(entry.blockhash.as_ref());
+        data.extend_from_slice(&entry.lamports_per_signature.to_le_bytes());
+    }
+    data
+}
@@ -0,0 +1,557 @@
+#[cfg(feature = "trace")]
+use super::ExecutionTrace;
+use {
+    super::{
+        account_diff::{diff_accounts, AccountDiff},
+        account_loader::{
+            construct_instructions_account, load_programdata_accounts, SysvarAccountLoader,
+        },
+        arena,
+        invoke_context::{
+            ExecutionRecord, InnerInstruction, InstructionAccount, TransactionReturnData,
+            MAX_INVOKE_DEPTH,
+        },
+        load_transaction_accounts,
+        sbf_loader::{self, SbfContext, VmConfig},
+        AccountLoader, BuiltinFunction, BuiltinPrograms, ComputeCostTable, EnvironmentConfig,
+        InvokeContext, LogCollector, ProgramCache, ProgramCacheKey, SysvarCache,
+        TransactionAccount, DEFAULT_LOG_MESSAGES_BYTES_LIMIT,
+    },
+    crate::{
+        bpf_loader_upgradeable,
+        compute_budget::process_compute_budget_instructions_with_features,
+        feature_set::{delay_visibility_of_program_deployment, FeatureSet},
+        fees::FeeDetails,
+        rent_collector::RentState,
+    },
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_instruction_error::InstructionError,
+    solana_instructions_sysvar::store_current_index_checked,
+    solana_pubkey::Pubkey,
+    solana_sbpf::{elf::Executable, program::BuiltinProgram},
+    solana_sdk_ids::sysvar,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+    std::sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Outcome of executing a single transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionExecutionResult {
+    pub status: Result<(), TransactionError>,
+    /// Result of every instruction that ran. Execution stops at the first
+    /// failing instruction, so later instructions have no entry.
+    pub instruction_results: Vec<Result<(), InstructionError>>,
+    /// Account state after execution, in message order. When the
+    /// transaction fails this is the state it was loaded with.
+    pub post_accounts: Vec<TransactionAccount>,
+    /// Compute units consumed by the instructions that ran.
+    pub consumed_units: u64,
+    /// Cross-program invocations of every instruction that ran, in
+    /// instruction order.
+    pub inner_instructions: Vec<Vec<InnerInstruction>>,
+    /// What the last program to set return data left, unless that was
+    /// nothing.
+    pub return_data: Option<TransactionReturnData>,
+    /// Lamports of every account as loaded, in message order.
+    pub pre_balances: Vec<u64>,
+    /// Fee charged for the transaction. The executor charges none; the
+    /// bank fills this in.
+    pub fee_details: FeeDetails,
+    /// Log lines of every instruction that ran.
+    pub log_messages: Vec<String>,
+    /// Every account the transaction changed, in message order, when the
+    /// executor records account diffs. Empty if the transaction failed,
+    /// since its changes are discarded. The bank adds the fee it charged
+    /// to the fee payer's diff.
+    pub account_diffs: Option<Vec<AccountDiff>>,
+    /// Profile of every program invocation that ran.
+    #[cfg(feature = "trace")]
+    pub trace: ExecutionTrace,
+}
+
+impl TransactionExecutionResult {
+    pub fn was_successful(&self) -> bool {
+        self.status.is_ok()
+    }
+}
+
+/// What an instruction's program id resolved to.
+pub(crate) enum LoadedProgram {
+    Builtin(BuiltinFunction),
+    Sbf(Arc<Executable<SbfContext>>),
+    /// An upgradeable program that is closed, or not visible yet because
+    /// it was deployed in the current slot.
+    NotDeployed,
+}
+
+/// Runs transactions against isolated copies of their accounts.
+///
+/// Clones share one [`ProgramCache`].
+#[derive(Clone)]
+pub struct TransactionExecutor {
+    builtins: BuiltinPrograms,
+    environment_config: EnvironmentConfig,
+    sysvar_cache: Arc<SysvarCache>,
+    max_invoke_depth: usize,
+    log_messages_bytes_limit: Option<usize>,
+    record_account_diffs: bool,
+    feature_set: Arc<FeatureSet>,
+    vm_config: VmConfig,
+    compute_costs: Arc<ComputeCostTable>,
+    /// Loader SBF programs are loaded with, holding the syscalls
+    /// `feature_set` enables and configured by `vm_config`.
+    program_loader: Arc<BuiltinProgram<SbfContext>>,
+    program_cache: Arc<Mutex<ProgramCache>>,
+}
+
+impl Default for TransactionExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionExecutor {
+    /// Creates an executor that knows every program in
+    /// [`BUILTINS`](super::BUILTINS).
+    pub fn new() -> Self {
+        Self::with_builtins(BuiltinPrograms::new())
+    }
+
+    /// Creates an executor that dispatches through `builtins`, with every
+    /// feature active.
+    pub fn with_builtins(builtins: BuiltinPrograms) -> Self {
+        let feature_set = FeatureSet::all_enabled();
+        Self {
+            builtins,
+            environment_config: EnvironmentConfig::default(),
+            sysvar_cache: Arc::default(),
+            max_invoke_depth: MAX_INVOKE_DEPTH,
+            log_messages_bytes_limit: Some(DEFAULT_LOG_MESSAGES_BYTES_LIMIT),
+            record_account_diffs: false,
+            program_loader: sbf_loader::create_loader(&feature_set, &VmConfig::default()),
+            feature_set: Arc::new(feature_set),
+            vm_config: VmConfig::default(),
+            compute_costs: Arc::default(),
+            program_cache: Arc::default(),
+        }
+    }
+
+    pub fn builtins(&self) -> &BuiltinPrograms {
+        &self.builtins
+    }
+
+    pub fn environment_config(&self) -> &EnvironmentConfig {
+        &self.environment_config
+    }
+
+    /// Sets the bank values programs see in transactions executed from now
+    /// on.
+    pub fn set_environment_config(&mut self, environment_config: EnvironmentConfig) {
+        self.environment_config = environment_config;
+    }
+
+    pub fn sysvar_cache(&self) -> &SysvarCache {
+        &self.sysvar_cache
+    }
+
+    pub(crate) fn shared_sysvar_cache(&self) -> &Arc<SysvarCache> {
+        &self.sysvar_cache
+    }
+
+    /// Replaces the sysvars transactions executed from now on see.
+    pub fn set_sysvar_cache(&mut self, sysvar_cache: SysvarCache) {
+        self.sysvar_cache = Arc::new(sysvar_cache);
+    }
+
+    /// Most instructions that can be executing at once, the transaction's
+    /// own instruction included.
+    pub fn max_invoke_depth(&self) -> usize {
+        self.max_invoke_depth
+    }
+
+    /// Limits how deeply programs may nest cross-program invocations.
+    /// Invocations beyond the limit fail with
+    /// [`InstructionError::CallDepth`].
+    pub fn set_max_invoke_depth(&mut self, max_invoke_depth: usize) {
+        self.max_invoke_depth = max_invoke_depth;
+    }
+
+    /// Bytes of log messages a transaction may record before its logs are
+    /// truncated; `None` means no limit.
+    pub fn log_messages_bytes_limit(&self) -> Option<usize> {
+        self.log_messages_bytes_limit
+    }
+
+    pub fn set_log_messages_bytes_limit(&mut self, log_messages_bytes_limit: Option<usize>) {
+        self.log_messages_bytes_limit = log_messages_bytes_limit;
+    }
+
+    /// Whether execution results carry the [`AccountDiff`]s of their
+    /// transactions.
+    pub fn record_account_diffs(&self) -> bool {
+        self.record_account_diffs
+    }
+
+    /// Makes execution results carry account diffs, which costs a copy of
+    /// every changed account; meant for debugging.
+    pub fn set_record_account_diffs(&mut self, record_account_diffs: bool) {
+        self.record_account_diffs = record_account_diffs;
+    }
+
+    pub fn feature_set(&self) -> &FeatureSet {
+        &self.feature_set
+    }
+
+    /// Replaces the features transactions executed from now on run with.
+    ///
+    /// Programs are linked against the syscalls of the features, so the
+    /// program cache is emptied.
+    pub fn set_feature_set(&mut self, feature_set: FeatureSet) {
+        self.program_loader = sbf_loader::create_loader(&feature_set, &self.vm_config);
+        self.feature_set = Arc::new(feature_set);
+        self.program_cache().clear();
+    }
+
+    /// The call depth and stack frames SBF programs run with.
+    pub fn vm_config(&self) -> &VmConfig {
+        &self.vm_config
+    }
+
+    /// Replaces the VM configuration SBF programs executed from now on run
+    /// with.
+    ///
+    /// Programs are loaded for a configuration, so the program cache is
+    /// emptied.
+    pub fn set_vm_config(&mut self, vm_config: VmConfig) {
+        self.program_loader = sbf_loader::create_loader(&self.feature_set, &vm_config);
+        self.vm_config = vm_config;
+        self.program_cache().clear();
+    }
+
+    /// What syscalls and builtins charge.
+    pub fn compute_costs(&self) -> &ComputeCostTable {
+        &self.compute_costs
+    }
+
+    pub(crate) fn shared_compute_costs(&self) -> &Arc<ComputeCostTable> {
+        &self.compute_costs
+    }
+
+    /// Replaces the costs transactions executed from now on are charged.
+    pub fn set_compute_costs(&mut self, compute_costs: ComputeCostTable) {
+        self.compute_costs = Arc::new(compute_costs);
+    }
+
+    pub(crate) fn program_loader(&self) -> &Arc<BuiltinProgram<SbfContext>> {
+        &self.program_loader
+    }
+
+    /// The cache of SBF programs this executor has loaded.
+    pub fn program_cache(&self) -> MutexGuard<'_, ProgramCache> {
+        self.program_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Replaces the program cache with an empty one holding at most
+    /// `capacity` programs.
+    pub fn set_program_cache_capacity(&mut self, capacity: usize) {
+        *self.program_cache() = ProgramCache::new(capacity);
+    }
+
+    /// A clone of this executor with an empty program cache of its own, so
+    /// the programs it loads never reach this executor's cache.
+    pub(crate) fn with_private_program_cache(&self) -> Self {
+        Self {
+            program_cache: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Registers the entrypoint invoked for instructions targeting
+    /// `program_id`, replacing any previous registration.
+    pub fn add_program<F>(&mut self, program_id: Pubkey, entrypoint: F)
+    where
+        F: Fn(&mut InvokeContext) -> Result<(), InstructionError> + Send + Sync + 'static,
+    {
+        self.builtins.add(program_id, entrypoint);
+    }
+
+    /// Finds the entrypoint of the program `account` holds.
+    ///
+    /// Registered builtins take precedence over SBF programs. SBF programs
+    /// come from the program cache if it holds their deployment, and are
+    /// cached once loaded otherwise. Upgradeable programs run the ELF of the
+    /// programdata account `find_programdata` returns, from the slot after
+    /// their deployment on, or from the slot of their deployment on while
+    /// `delay_visibility_of_program_deployment` is inactive. An SBF program
+    /// whose ELF does not load fails with
+    /// [`InstructionError::InvalidAccountData`]; any other account fails
+    /// with [`InstructionError::UnsupportedProgramId`].
+    pub(crate) fn load_program<'b>(
+        &self,
+        (program_id, account): &TransactionAccount,
+        find_programdata: impl Fn(&Pubkey) -> Option<&'b AccountSharedData>,
+    ) -> Result<LoadedProgram, InstructionError> {
+        if let Some(entrypoint) = self.builtins.get(program_id) {
+            return Ok(LoadedProgram::Builtin(Arc::clone(entrypoint)));
+        }
+        if sbf_loader::is_sbf_program(account) {
+            // Programs of the BPF loader cannot be redeployed, so every one
+            // of them has a single deployment.
+            return self.load_sbf_program((*program_id, 0), account.data());
+        }
+        if bpf_loader_upgradeable::is_upgradeable_program(account) {
+            let deployed_program = bpf_loader_upgradeable::programdata_address(account)
+                .and_then(|address| find_programdata(&address))
+                .and_then(bpf_loader_upgradeable::deployed_program);
+            let current_slot = self.sysvar_cache.get_clock().slot;
+            let is_visible = |slot| {
+                if self
+                    .feature_set
+                    .is_active(&delay_visibility_of_program_deployment::id())
+                {
+                    slot < current_slot
+                } else {
+                    slot <= current_slot
+                }
+            };
+            return match deployed_program {
+                Some((slot, elf)) if is_visible(slot) => {
+                    self.load_sbf_program((*program_id, slot), elf)
+                }
+                _ => Ok(LoadedProgram::NotDeployed),
+            };
+        }
+        Err(InstructionError::UnsupportedProgramId)
+    }
+
+    fn load_sbf_program(
+        &self,
+        key: ProgramCacheKey,
+        elf: &[u8],
+    ) -> Result<LoadedProgram, InstructionError> {
+        if let Some(executable) = self.program_cache().get(&key) {
+            return Ok(LoadedProgram::Sbf(executable));
+        }
+        let executable = Arc::new(sbf_loader::load_program(
+            elf,
+            Arc::clone(&self.program_loader),
+        )?);
+        self.program_cache().insert(key, Arc::clone(&executable));
+        Ok(LoadedProgram::Sbf(executable))
+    }
+
+    /// Loads the transaction's accounts from `loader` and executes it.
+    ///
+    /// Sysvar accounts are loaded from the executor's [`SysvarCache`]
+    /// instead. The programdata accounts of upgradeable programs are loaded
+    /// as well, whether or not the transaction references them.
+    pub fn load_and_execute_transaction(
+        &self,
+        loader: &impl AccountLoader,
+        transaction: &SanitizedTransaction,
+    ) -> TransactionExecutionResult {
+        let loader = SysvarAccountLoader {
+            sysvar_cache: &self.sysvar_cache,
+            loader,
+        };
+        let loaded_accounts = load_transaction_accounts(&loader, transaction);
+        let programdata_accounts = load_programdata_accounts(&loader, &loaded_accounts);
+        self.execute(transaction, loaded_accounts, &programdata_accounts)
+    }
+
+    /// Executes every instruction of `transaction` in order.
+    ///
+    /// `loaded_accounts` must line up with the message's account keys, as
+    /// returned by [`load_transaction_accounts`]. The instructions share the
+    /// compute unit limit requested through the ComputeBudget program, with
+    /// defaults that depend on the executor's features. A
+    /// transaction that leaves a writable account it modified below its
+    /// rent-exempt minimum fails with
+    /// [`TransactionError::InsufficientFundsForRent`], unless the account
+    /// was already rent paying and only lost lamports.
+    ///
+    /// If the transaction lists the Instructions sysvar, its account is
+    /// built from the message, whatever `loaded_accounts` holds for it, and
+    /// tells every instruction its own index.
+    ///
+    /// Upgradeable programs can only run if `loaded_accounts` holds their
+    /// programdata accounts.
+    pub fn execute_transaction(
+        &self,
+        transaction: &SanitizedTransaction,
+        loaded_accounts: Vec<TransactionAccount>,
+    ) -> TransactionExecutionResult {
+        self.execute(transaction, loaded_accounts, &[])
+    }
+
+    fn execute(
+        &self,
+        transaction: &SanitizedTransaction,
+        mut loaded_accounts: Vec<TransactionAccount>,
+        programdata_accounts: &[TransactionAccount],
+    ) -> TransactionExecutionResult {
+        let message = transaction.message();
+        assert_eq!(
+            loaded_accounts.len(),
+            message.account_keys().len(),
+            "loaded accounts must match the message account keys"
+        );
+
+        let pre_balances = loaded_accounts
+            .iter()
+            .map(|(_, account)| account.lamports())
+            .collect();
+        let instructions_sysvar_index = loaded_accounts
+            .iter()
+            .position(|(key, _)| *key == sysvar::instructions::id());
+        let limits = process_compute_budget_instructions_with_features(message, &self.feature_set)
+            .and_then(|limits| {
+                if let Some(index) = instructions_sysvar_index {
+                    loaded_accounts[index].1 = construct_instructions_account(message)?;
+                }
+                Ok(limits)
+            });
+        let limits = match limits {
+            Ok(limits) => limits,
+            Err(err) => {
+                return TransactionExecutionResult {
+                    status: Err(err),
+                    instruction_results: vec![],
+                    post_accounts: loaded_accounts,
+                    consumed_units: 0,
+                    inner_instructions: vec![],
+                    return_data: None,
+                    pre_balances,
+                    fee_details: FeeDetails::default(),
+                    log_messages: vec![],
+                    account_diffs: self.record_account_diffs.then(Vec::new),
+                    #[cfg(feature = "trace")]
+                    trace: ExecutionTrace::default(),
+                }
+            }
+        };
+        let mut compute_meter = u64::from(limits.compute_unit_limit);
+
+        let mut accounts = loaded_accounts.clone();
+        let mut instruction_results = Vec::with_capacity(message.instructions().len());
+        let mut inner_instructions = Vec::with_capacity(message.instructions().len());
+        let mut record = ExecutionRecord {
+            log_collector: LogCollector::new_with_limit(self.log_messages_bytes_limit),
+            ..ExecutionRecord::default()
+        };
+        let mut status = Ok(());
+
+        // Everything an instruction allocates in the arena goes once the
+        // transaction is done.
+        arena::with_arena(|arena| {
+            for (instruction_index, instruction) in message.instructions().iter().enumerate() {
+                #[cfg(feature = "trace")]
+                {
+                    record.instruction_index = instruction_index;
+                }
+                if let Some(index) = instructions_sysvar_index {
+                    store_current_index_checked(
+                        accounts[index].1.data_as_mut_slice(),
+                        instruction_index as u16,
+                    )
+                    .expect("the instructions sysvar ends with the current index");
+                }
+                let program_index = usize::from(instruction.program_id_index);
+                let program_id = accounts[program_index].0;
+                let instruction_accounts =
+                    arena.alloc_slice_fill_iter(instruction.accounts.iter().map(|&index| {
+                        let index = usize::from(index);
+                        InstructionAccount {
+                            index_in_transaction: index,
+                            is_signer: message.is_signer(index),
+                            is_writable: message.is_writable(index),
+                        }
+                    }));
+
+                let result = InvokeContext::new(
+                    &mut accounts,
+                    program_id,
+                    instruction_accounts,
+                    &instruction.data,
+                    &mut compute_meter,
+                    limits.updated_heap_bytes,
+                    self,
+                    &mut record,
+                    programdata_accounts,
+                    arena,
+                )
+                .process_instruction();
+
+                instruction_results.push(result.clone());
+                inner_instructions.push(std::mem::take(&mut record.inner_instructions));
+                if let Err(err) = result {
+                    status = Err(TransactionError::InstructionError(
+                        instruction_index as u8,
+                        err,
+                    ));
+                    break;
+                }
+            }
+        });
+
+        // The index only means something while the transaction executes,
+        // so the sysvar is not reported as changed.
+        if let Some(index) = instructions_sysvar_index {
+            accounts[index].1 = loaded_accounts[index].1.clone();
+        }
+        if status.is_ok() {
+            status = self.check_rent_states(transaction, &loaded_accounts, &accounts);
+        }
+
+        let account_diffs = self.record_account_diffs.then(|| {
+            if status.is_ok() {
+                diff_accounts(&loaded_accounts, &accounts)
+            } else {
+                Vec::new()
+            }
+        });
+        let post_accounts = if status.is_ok() {
+            accounts
+        } else {
+            loaded_accounts
+        };
+
+        TransactionExecutionResult {
+            status,
+            instruction_results,
+            post_accounts,
+            consumed_units: u64::from(limits.compute_unit_limit).saturating_sub(compute_meter),
+            inner_instructions,
+            return_data: (!record.return_data.data.is_empty()).then_some(record.return_data),
+            pre_balances,
+            fee_details: FeeDetails::default(),
+            log_messages: record.log_collector.into_messages(),
+            account_diffs,
+            #[cfg(feature = "trace")]
+            trace: record.trace,
+        }
+    }
+
+    /// Checks the rent state transition of every account the transaction
+    /// modified, against the rent in the executor's [`SysvarCache`].
+    fn check_rent_states(
+        &self,
+        transaction: &SanitizedTransaction,
+        pre_accounts: &[TransactionAccount],
+        post_accounts: &[TransactionAccount],
+    ) -> Result<(), TransactionError> {
+        let message = transaction.message();
+        let rent = self.sysvar_cache.get_rent();
+        pre_accounts
+            .iter()
+            .zip(post_accounts)
+            .enumerate()
+            .filter(|(index, ((_, pre), (_, post)))| message.is_writable(*index) && pre != post)
+            .try_for_each(|(index, ((pubkey, pre), (_, post)))| {
+                RentState::check_transition(pubkey, pre, post, rent, index)
+            })
+    }
+}
@@ -0,0 +1,766 @@
+//! The System program.
+//!
+//! System accounts are funded, created, assigned to other programs and
+//! allocated through this program, and it manages durable nonce accounts.
+//! The bank validates nonce transactions before execution and advances
+//! their nonce at commit even if they fail, so `AdvanceNonceAccount` writes
+//! the same nonce the bank would.
+
+use {
+    crate::svm::InvokeContext,
+    solana_account::{ReadableAccount, WritableAccount},
+    solana_instruction::{AccountMeta, Instruction},
+    solana_instruction_error::InstructionError,
+    solana_nonce::{
+        state::{Data, DurableNonce, State},
+        versions::Versions,
+    },
+    solana_pubkey::{Pubkey, PubkeyError},
+    solana_sdk_ids::{system_program, sysvar},
+    std::collections::HashSet,
+};
+
+/// CUs consumed by executing a single System program instruction.
+pub const DEFAULT_COMPUTE_UNITS: u64 = 150;
+
+/// Largest data an account can be allocated with.
+pub const MAX_PERMITTED_DATA_LENGTH: u64 = 10 * 1024 * 1024;
+
+/// Errors specific to the System program, reported as
+/// [`InstructionError::Custom`] with the variant's index as the code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SystemError {
+    #[error("an account with the same address already exists")]
+    AccountAlreadyInUse,
+    #[error("account does not have enough lamports to perform the operation")]
+    ResultWithNegativeLamports,
+    #[error("cannot assign account to this program id")]
+    InvalidProgramId,
+    #[error("cannot allocate account data of this length")]
+    InvalidAccountDataLength,
+    #[error("length of requested seed is too long")]
+    MaxSeedLengthExceeded,
+    #[error("provided address does not match the address derived from the seed")]
+    AddressWithSeedMismatch,
+    #[error("no recent blockhashes to derive a durable nonce from")]
+    NonceNoRecentBlockhashes,
+    #[error("advancing the stored nonce requires a newer blockhash")]
+    NonceBlockhashNotExpired,
+}
+
+impl SystemError {
+    const ALL: [Self; 8] = [
+        Self::AccountAlreadyInUse,
+        Self::ResultWithNegativeLamports,
+        Self::InvalidProgramId,
+        Self::InvalidAccountDataLength,
+        Self::MaxSeedLengthExceeded,
+        Self::AddressWithSeedMismatch,
+        Self::NonceNoRecentBlockhashes,
+        Self::NonceBlockhashNotExpired,
+    ];
+
+    /// The error reported with the custom code `code`, if any.
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+impl From<SystemError> for InstructionError {
+    fn from(err: SystemError) -> Self {
+        Self::Custom(err as u32)
+    }
+}
+
+/// Instructions understood by the System program.
+///
+/// The wire format is bincode: a four byte little-endian discriminant
+/// followed by the arguments, with strings prefixed by their u64 length.
+/// Discriminants match the on-chain program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SystemInstruction {
+    /// Create a new account owned by `owner` and fund it.
+    ///
+    /// Accounts: `[writable, signer]` funding account, `[writable, signer]`
+    /// new account.
+    CreateAccount {
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    /// Assign an account to a program.
+    ///
+    /// Accounts: `[writable, signer]` assigned account.
+    Assign { owner: Pubkey },
+    /// Move lamports between accounts.
+    ///
+    /// Accounts: `[writable, signer]` funding account, `[writable]`
+    /// recipient account.
+    Transfer { lamports: u64 },
+    /// Create a new account at an address derived from `base` and `seed`.
+    ///
+    /// Accounts: `[writable, signer]` funding account, `[writable]` new
+    /// account, `[signer]` base account if it is not the funding account.
+    CreateAccountWithSeed {
+        base: Pubkey,
+        seed: String,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    /// Consume the stored durable nonce and replace it with a new one.
+    ///
+    /// Accounts: `[writable]` nonce account, `[]` RecentBlockhashes sysvar,
+    /// `[signer]` nonce authority.
+    AdvanceNonceAccount,
+    /// Withdraw lamports from a nonce account.
+    ///
+    /// Accounts: `[writable]` nonce account, `[writable]` recipient
+    /// account, `[]` RecentBlockhashes sysvar, `[]` Rent sysvar, `[signer]`
+    /// nonce authority.
+    WithdrawNonceAccount(u64),
+    /// Store a first durable nonce and the given authority in an
+    /// uninitialized nonce account.
+    ///
+    /// Accounts: `[writable]` nonce account, `[]` RecentBlockhashes sysvar,
+    /// `[]` Rent sysvar.
+    InitializeNonceAccount(Pubkey),
+    /// Hand the nonce account to a new authority.
+    ///
+    /// Accounts: `[writable]` nonce account, `[signer]` nonce authority.
+    AuthorizeNonceAccount(Pubkey),
+    /// Allocate zeroed data in an account with none.
+    ///
+    /// Accounts: `[writable, signer]` allocated account.
+    Allocate { space: u64 },
+}
+
+impl SystemInstruction {
+    pub fn parse(data: &[u8]) -> Result<Self, InstructionError> {
+        let mut reader = InstructionReader::new(data);
+        match reader.read_u32()? {
+            0 => Ok(Self::CreateAccount {
+                lamports: reader.read_u64()?,
+                space: reader.read_u64()?,
+                owner: reader.read_pubkey()?,
+            }),
+            1 => Ok(Self::Assign {
+                owner: reader.read_pubkey()?,
+            }),
+            2 => Ok(Self::Transfer {
+                lamports: reader.read_u64()?,
+            }),
+            3 => Ok(Self::CreateAccountWithSeed {
+                base: reader.read_pubkey()?,
+                seed: reader.read_string()?,
+                lamports: reader.read_u64()?,
+                space: reader.read_u64()?,
+                owner: reader.read_pubkey()?,
+            }),
+            4 => Ok(Self::AdvanceNonceAccount),
+            5 => Ok(Self::WithdrawNonceAccount(reader.read_u64()?)),
+            6 => Ok(Self::InitializeNonceAccount(reader.read_pubkey()?)),
+            7 => Ok(Self::AuthorizeNonceAccount(reader.read_pubkey()?)),
+            8 => Ok(Self::Allocate {
+                space: reader.read_u64()?,
+            }),
+            _ => Err(InstructionError::InvalidInstructionData),
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let discriminant: u32 = match self {
+            Self::CreateAccount { .. } => 0,
+            Self::Assign { .. } => 1,
+            Self::Transfer { .. } => 2,
+            Self::CreateAccountWithSeed { .. } => 3,
+            Self::AdvanceNonceAccount => 4,
+            Self::WithdrawNonceAccount(_) => 5,
+            Self::InitializeNonceAccount(_) => 6,
+            Self::AuthorizeNonceAccount(_) => 7,
+            Self::Allocate { .. } => 8,
+        };
+        let mut data = discriminant.to_le_bytes().to_vec();
+        match self {
+            Self::CreateAccount {
+                lamports,
+                space,
+                owner,
+            } => {
+                data.extend_from_slice(&lamports.to_le_bytes());
+                data.extend_from_slice(&space.to_le_bytes());
+                data.extend_from_slice(owner.as_ref());
+            }
+            Self::Assign { owner } => data.extend_from_slice(owner.as_ref()),
+            Self::Transfer { lamports } | Self::WithdrawNonceAccount(lamports) => {
+                data.extend_from_slice(&lamports.to_le_bytes())
+            }
+            Self::CreateAccountWithSeed {
+                base,
+                seed,
+                lamports,
+                space,
+                owner,
+            } => {
+                data.extend_from_slice(base.as_ref());
+                data.extend_from_slice(&(seed.len() as u64).to_le_bytes());
+                data.extend_from_slice(seed.as_bytes());
+                data.extend_from_slice(&lamports.to_le_bytes());
+                data.extend_from_slice(&space.to_le_bytes());
+                data.extend_from_slice(owner.as_ref());
+            }
+            Self::AdvanceNonceAccount => {}
+            Self::InitializeNonceAccount(authority) | Self::AuthorizeNonceAccount(authority) => {
+                data.extend_from_slice(authority.as_ref())
+            }
+            Self::Allocate { space } => data.extend_from_slice(&space.to_le_bytes()),
+        }
+        data
+    }
+}
+
+/// Reads instruction arguments front to back.
+pub(crate) struct InstructionReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> InstructionReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], InstructionError> {
+        let (bytes, rest) = self
+            .data
+            .split_first_chunk()
+            .ok_or(InstructionError::InvalidInstructionData)?;
+        self.data = rest;
+        Ok(*bytes)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, InstructionError> {
+        self.read().map(u8::from_le_bytes)
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, InstructionError> {
+        self.read().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, InstructionError> {
+        self.read().map(u64::from_le_bytes)
+    }
+
+    pub(crate) fn read_pubkey(&mut self) -> Result<Pubkey, InstructionError> {
+        self.read().map(Pubkey::new_from_array)
+    }
+
+    /// Reads bytes prefixed by their u64 length.
+    pub(crate) fn read_bytes(&mut self) -> Result<&'a [u8], InstructionError> {
+        let len = usize::try_from(self.read_u64()?)
+            .map_err(|_| InstructionError::InvalidInstructionData)?;
+        if len > self.data.len() {
+            return Err(InstructionError::InvalidInstructionData);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn read_string(&mut self) -> Result<String, InstructionError> {
+        let bytes = self.read_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| InstructionError::InvalidInstructionData)
+    }
+}
+
+/// Builds a `Transfer` of `lamports` from `from_pubkey` to `to_pubkey`.
+pub fn transfer(from_pubkey: &Pubkey, to_pubkey: &Pubkey, lamports: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &SystemInstruction::Transfer { lamports }.serialize(),
+        vec![
+            AccountMeta::new(*from_pubkey, true),
+            AccountMeta::new(*to_pubkey, false),
+        ],
+    )
+}
+
+/// Builds a `CreateAccount` funded by `from_pubkey`.
+pub fn create_account(
+    from_pubkey: &Pubkey,
+    to_pubkey: &Pubkey,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+) -> Instruction {
+    let instruction = SystemInstruction::CreateAccount {
+        lamports,
+        space,
+        owner: *owner,
+    };
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &instruction.serialize(),
+        vec![
+            AccountMeta::new(*from_pubkey, true),
+            AccountMeta::new(*to_pubkey, true),
+        ],
+    )
+}
+
+/// Builds a `CreateAccountWithSeed` for the address derived from `base`,
+/// `seed` and `owner`.
+pub fn create_account_with_seed(
+    from_pubkey: &Pubkey,
+    to_pubkey: &Pubkey,
+    base: &Pubkey,
+    seed: &str,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+) -> Instruction {
+    let instruction = SystemInstruction::CreateAccountWithSeed {
+        base: *base,
+        seed: seed.to_owned(),
+        lamports,
+        space,
+        owner: *owner,
+    };
+    let mut accounts = vec![
+        AccountMeta::new(*from_pubkey, true),
+        AccountMeta::new(*to_pubkey, false),
+    ];
+    if base != from_pubkey {
+        accounts.push(AccountMeta::new_readonly(*base, true));
+    }
+    Instruction::new_with_bytes(system_program::id(), &instruction.serialize(), accounts)
+}
+
+/// Builds an `Assign` of `pubkey` to `owner`.
+pub fn assign(pubkey: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &SystemInstruction::Assign { owner: *owner }.serialize(),
+        vec![AccountMeta::new(*pubkey, true)],
+    )
+}
+
+/// Builds an `Allocate` of `space` bytes in `pubkey`.
+pub fn allocate(pubkey: &Pubkey, space: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &SystemInstruction::Allocate { space }.serialize(),
+        vec![AccountMeta::new(*pubkey, true)],
+    )
+}
+
+/// Builds the instructions that create and initialize a nonce account
+/// holding `lamports`.
+pub fn create_nonce_account(
+    from_pubkey: &Pubkey,
+    nonce_pubkey: &Pubkey,
+    authority: &Pubkey,
+    lamports: u64,
+) -> Vec<Instruction> {
+    vec![
+        create_account(
+            from_pubkey,
+            nonce_pubkey,
+            lamports,
+            State::size() as u64,
+            &system_program::id(),
+        ),
+        Instruction::new_with_bytes(
+            system_program::id(),
+            &SystemInstruction::InitializeNonceAccount(*authority).serialize(),
+            vec![
+                AccountMeta::new(*nonce_pubkey, false),
+                AccountMeta::new_readonly(sysvar::recent_blockhashes::id(), false),
+                AccountMeta::new_readonly(sysvar::rent::id(), false),
+            ],
+        ),
+    ]
+}
+
+/// Builds the `AdvanceNonceAccount` instruction that must open every
+/// durable nonce transaction.
+pub fn advance_nonce_account(nonce_pubkey: &Pubkey, authorized_pubkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &SystemInstruction::AdvanceNonceAccount.serialize(),
+        vec![
+            AccountMeta::new(*nonce_pubkey, false),
+            AccountMeta::new_readonly(sysvar::recent_blockhashes::id(), false),
+            AccountMeta::new_readonly(*authorized_pubkey, true),
+        ],
+    )
+}
+
+/// Builds a `WithdrawNonceAccount` of `lamports` to `to_pubkey`.
+pub fn withdraw_nonce_account(
+    nonce_pubkey: &Pubkey,
+    authorized_pubkey: &Pubkey,
+    to_pubkey: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &SystemInstruction::WithdrawNonceAccount(lamports).serialize(),
+        vec![
+            AccountMeta::new(*nonce_pubkey, false),
+            AccountMeta::new(*to_pubkey, false),
+            AccountMeta::new_readonly(sysvar::recent_blockhashes::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(*authorized_pubkey, true),
+        ],
+    )
+}
+
+/// Builds an `AuthorizeNonceAccount` handing the nonce to `new_authority`.
+pub fn authorize_nonce_account(
+    nonce_pubkey: &Pubkey,
+    authorized_pubkey: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &SystemInstruction::AuthorizeNonceAccount(*new_authority).serialize(),
+        vec![
+            AccountMeta::new(*nonce_pubkey, false),
+            AccountMeta::new_readonly(*authorized_pubkey, true),
+        ],
+    )
+}
+
+/// Entrypoint of the System program.
+pub fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    invoke_context.consume_builtin_cost(DEFAULT_COMPUTE_UNITS)?;
+    let instruction = SystemInstruction::parse(invoke_context.instruction_data())?;
+    let signers = invoke_context.get_signers();
+    match instruction {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            check_number_of_accounts(invoke_context, 2)?;
+            let address = *invoke_context.get_key(1)?;
+            process_create_account(invoke_context, &address, lamports, space, &owner, &signers)
+        }
+        SystemInstruction::Assign { owner } => {
+            check_number_of_accounts(invoke_context, 1)?;
+            let address = *invoke_context.get_key(0)?;
+            process_assign(invoke_context, 0, &address, &owner, &signers)
+        }
+        SystemInstruction::Transfer { lamports } => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_transfer(invoke_context, 0, 1, lamports)
+        }
+        SystemInstruction::CreateAccountWithSeed {
+            base,
+            seed,
+            lamports,
+            space,
+            owner,
+        } => {
+            check_number_of_accounts(invoke_context, 2)?;
+            let derived =
+                Pubkey::create_with_seed(&base, &seed, &owner).map_err(|err| match err {
+                    PubkeyError::MaxSeedLengthExceeded => SystemError::MaxSeedLengthExceeded.into(),
+                    PubkeyError::InvalidSeeds => InstructionError::InvalidSeeds,
+                    PubkeyError::IllegalOwner => InstructionError::IllegalOwner,
+                })?;
+            if derived != *invoke_context.get_key(1)? {
+                return Err(SystemError::AddressWithSeedMismatch.into());
+            }
+            process_create_account(invoke_context, &base, lamports, space, &owner, &signers)
+        }
+        SystemInstruction::AdvanceNonceAccount => {
+            check_number_of_accounts(invoke_context, 2)?;
+            check_sysvar_account(invoke_context, 1, &sysvar::recent_blockhashes::id())?;
+            process_advance_nonce_account(invoke_context, &signers)
+        }
+        SystemInstruction::WithdrawNonceAccount(lamports) => {
+            check_number_of_accounts(invoke_context, 4)?;
+            check_sysvar_account(invoke_context, 2, &sysvar::recent_blockhashes::id())?;
+            check_sysvar_account(invoke_context, 3, &sysvar::rent::id())?;
+            process_withdraw_nonce_account(invoke_context, lamports, &signers)
+        }
+        SystemInstruction::InitializeNonceAccount(authority) => {
+            check_number_of_accounts(invoke_context, 3)?;
+            check_sysvar_account(invoke_context, 1, &sysvar::recent_blockhashes::id())?;
+            check_sysvar_account(invoke_context, 2, &sysvar::rent::id())?;
+            process_initialize_nonce_account(invoke_context, &authority)
+        }
+        SystemInstruction::AuthorizeNonceAccount(new_authority) => {
+            check_number_of_accounts(invoke_context, 1)?;
+            process_authorize_nonce_account(invoke_context, &new_authority, &signers)
+        }
+        SystemInstruction::Allocate { space } => {
+            check_number_of_accounts(invoke_context, 1)?;
+            let address = *invoke_context.get_key(0)?;
+            process_allocate(invoke_context, 0, &address, space, &signers)
+        }
+    }
+}
+
+pub(crate) fn check_number_of_accounts(
+    invoke_context: &InvokeContext,
+    expected: usize,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_number_of_accounts() < expected {
+        return Err(InstructionError::MissingAccount);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_sysvar_account(
+    invoke_context: &InvokeContext,
+    index: usize,
+    sysvar_id: &Pubkey,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_key(index)? != sysvar_id {
+        return Err(InstructionError::InvalidArgument);
+    }
+    Ok(())
+}
+
+/// Allocates and assigns the account at index 1, then funds it from the
+/// account at index 0. `address` is the key that must sign for the new
+/// account: the account itself, or the base of a seeded address.
+fn process_create_account(
+    invoke_context: &mut InvokeContext,
+    address: &Pubkey,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_account(1)?.lamports() > 0 {
+        return Err(SystemError::AccountAlreadyInUse.into());
+    }
+    process_allocate(invoke_context, 1, address, space, signers)?;
+    process_assign(invoke_context, 1, address, owner, signers)?;
+    process_transfer(invoke_context, 0, 1, lamports)
+}
+
+fn process_allocate(
+    invoke_context: &mut InvokeContext,
+    index: usize,
+    address: &Pubkey,
+    space: u64,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    if !signers.contains(address) {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    let account = invoke_context.get_account(index)?;
+    if !account.data().is_empty() || *account.owner() != system_program::id() {
+        return Err(SystemError::AccountAlreadyInUse.into());
+    }
+    if space > MAX_PERMITTED_DATA_LENGTH {
+        return Err(SystemError::InvalidAccountDataLength.into());
+    }
+    invoke_context
+        .get_account_mut(index)?
+        .resize(space as usize, 0);
+    Ok(())
+}
+
+fn process_assign(
+    invoke_context: &mut InvokeContext,
+    index: usize,
+    address: &Pubkey,
+    owner: &Pubkey,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_account(index)?.owner() == owner {
+        return Ok(());
+    }
+    if !signers.contains(address) {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    invoke_context.get_account_mut(index)?.set_owner(*owner);
+    Ok(())
+}
+
+/// Moves `lamports` between two instruction accounts. The funding account
+/// must sign and must not carry data.
+fn process_transfer(
+    invoke_context: &mut InvokeContext,
+    from_index: usize,
+    to_index: usize,
+    lamports: u64,
+) -> Result<(), InstructionError> {
+    if !invoke_context.is_signer(from_index)? {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    let from = invoke_context.get_account(from_index)?;
+    if !from.data().is_empty() {
+        return Err(InstructionError::InvalidArgument);
+    }
+    if lamports > from.lamports() {
+        return Err(SystemError::ResultWithNegativeLamports.into());
+    }
+    move_lamports(invoke_context, from_index, to_index, lamports)
+}
+
+fn move_lamports(
+    invoke_context: &mut InvokeContext,
+    from_index: usize,
+    to_index: usize,
+    lamports: u64,
+) -> Result<(), InstructionError> {
+    invoke_context
+        .get_account_mut(from_index)?
+        .checked_sub_lamports(lamports)
+        .map_err(|_| InstructionError::InsufficientFunds)?;
+    invoke_context
+        .get_account_mut(to_index)?
+        .checked_add_lamports(lamports)
+        .map_err(|_| InstructionError::ArithmeticOverflow)
+}
+
+/// Reads the state of the nonce account at index 0, which must be a
+/// writable System account.
+fn get_nonce_state(invoke_context: &InvokeContext) -> Result<State, InstructionError> {
+    if !invoke_context.is_writable(0)? {
+        return Err(InstructionError::InvalidArgument);
+    }
+    let account = invoke_context.get_account(0)?;
+    if *account.owner() != system_program::id() {
+        return Err(InstructionError::InvalidAccountOwner);
+    }
+    bincode::deserialize::<Versions>(account.data())
+        .map(|versions| versions.state().clone())
+        .map_err(|_| InstructionError::InvalidAccountData)
+}
+
+fn set_nonce_state(
+    invoke_context: &mut InvokeContext,
+    state: State,
+) -> Result<(), InstructionError> {
+    let serialized =
+        bincode::serialize(&Versions::new(state)).expect("nonce state is always serializable");
+    invoke_context
+        .get_account_mut(0)?
+        .data_as_mut_slice()
+        .get_mut(..serialized.len())
+        .ok_or(InstructionError::AccountDataTooSmall)?
+        .copy_from_slice(&serialized);
+    Ok(())
+}
+
+/// The nonce a nonce account advanced in the current bank stores.
+fn next_durable_nonce(invoke_context: &InvokeContext) -> DurableNonce {
+    DurableNonce::from_blockhash(&invoke_context.environment_config().blockhash)
+}
+
+fn process_advance_nonce_account(
+    invoke_context: &mut InvokeContext,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let State::Initialized(data) = get_nonce_state(invoke_context)? else {
+        return Err(InstructionError::InvalidAccountData);
+    };
+    if !signers.contains(&data.authority) {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    let next_durable_nonce = next_durable_nonce(invoke_context);
+    if data.durable_nonce == next_durable_nonce {
+        return Err(SystemError::NonceBlockhashNotExpired.into());
+    }
+    let lamports_per_signature = invoke_context.environment_config().lamports_per_signature;
+    let data = Data::new(data.authority, next_durable_nonce, lamports_per_signature);
+    set_nonce_state(invoke_context, State::Initialized(data))
+}
+
+/// Withdraws from the nonce account at index 0 to the account at index 1.
+///
+/// An initialized nonce account must keep a rent-exempt balance unless it
+/// is emptied, which deinitializes it.
+fn process_withdraw_nonce_account(
+    invoke_context: &mut InvokeContext,
+    lamports: u64,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let nonce = invoke_context.get_account(0)?;
+    let balance = nonce.lamports();
+    let data_len = nonce.data().len();
+    let (signer, deinitialize) = match get_nonce_state(invoke_context)? {
+        State::Uninitialized => {
+            if lamports > balance {
+                return Err(InstructionError::InsufficientFunds);
+            }
+            (*invoke_context.get_key(0)?, false)
+        }
+        State::Initialized(data) if lamports == balance => {
+            if data.durable_nonce == next_durable_nonce(invoke_context) {
+                return Err(SystemError::NonceBlockhashNotExpired.into());
+            }
+            (data.authority, true)
+        }
+        State::Initialized(data) => {
+            let min_balance = invoke_context
+                .get_sysvar_cache()
+                .get_rent()
+                .minimum_balance(data_len);
+            let required = lamports
+                .checked_add(min_balance)
+                .ok_or(InstructionError::InsufficientFunds)?;
+            if required > balance {
+                return Err(InstructionError::InsufficientFunds);
+            }
+            (data.authority, false)
+        }
+    };
+    if !signers.contains(&signer) {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    if deinitialize {
+        set_nonce_state(invoke_context, State::Uninitialized)?;
+    }
+    move_lamports(invoke_context, 0, 1, lamports)
+}
+
+fn process_initialize_nonce_account(
+    invoke_context: &mut InvokeContext,
+    authority: &Pubkey,
+) -> Result<(), InstructionError> {
+    let State::Uninitialized = get_nonce_state(invoke_context)? else {
+        return Err(InstructionError::InvalidAccountData);
+    };
+    let nonce = invoke_context.get_account(0)?;
+    let min_balance = invoke_context
+        .get_sysvar_cache()
+        .get_rent()
+        .minimum_balance(nonce.data().len());
+    if nonce.lamports() < min_balance {
+        return Err(InstructionError::InsufficientFunds);
+    }
+    let lamports_per_signature = invoke_context.environment_config().lamports_per_signature;
+    let data = Data::new(
+        *authority,
+        next_durable_nonce(invoke_context),
+        lamports_per_signature,
+    );
+    set_nonce_state(invoke_context, State::Initialized(data))
+}
+
+fn process_authorize_nonce_account(
+    invoke_context: &mut InvokeContext,
+    new_authority: &Pubkey,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let State::Initialized(data) = get_nonce_state(invoke_context)? else {
+        return Err(InstructionError::InvalidAccountData);
+    };
+    if !signers.contains(&data.authority) {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    let data = Data::new(
+        *new_authority,
+        data.durable_nonce,
+        data.get_lamports_per_signature(),
+    );
+    set_nonce_state(invoke_context, State::Initialized(data))
+}
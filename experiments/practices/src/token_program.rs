@@ -0,0 +1,1090 @@
+//! A native SPL Token program.
+//!
+//! The token program on a cluster is an SBF program whose ELF this crate
+//! does not ship, so this module reimplements its processor natively. It
+//! keeps the on-chain program id, account layouts, instruction encoding and
+//! error codes, so mints, token accounts and instructions built for SPL
+//! Token work unchanged. Multisig authorities, `SetAuthority`, native
+//! (wrapped SOL) accounts and the checked variants other than
+//! `TransferChecked` are not supported.
+//!
+//! The program is not one of the [`BUILTINS`](crate::svm::BUILTINS): on a
+//! cluster its instructions get the compute budget of a regular program,
+//! and so they do here. Register [`process_instruction`] under [`ID`] with
+//! [`Bank::add_builtin`](crate::bank::Bank::add_builtin), and seed mints and
+//! token accounts with [`mint_account`] and [`token_account`] or create them
+//! with [`create_mint`] and [`create_token_account`].
+
+use {
+    crate::{
+        svm::InvokeContext,
+        system_program::{self, check_number_of_accounts, check_sysvar_account, InstructionReader},
+    },
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_instruction::{AccountMeta, Instruction},
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::sysvar,
+};
+
+solana_pubkey::declare_id!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// CUs consumed by executing a single token instruction, about what the
+/// on-chain program spends on a transfer.
+pub const DEFAULT_COMPUTE_UNITS: u64 = 4_500;
+
+/// Errors specific to the token program, reported as
+/// [`InstructionError::Custom`] with the variant's index as the code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TokenError {
+    #[error("lamport balance below the rent-exempt threshold")]
+    NotRentExempt,
+    #[error("insufficient funds")]
+    InsufficientFunds,
+    #[error("invalid mint")]
+    InvalidMint,
+    #[error("account not associated with this mint")]
+    MintMismatch,
+    #[error("owner does not match")]
+    OwnerMismatch,
+    #[error("fixed supply")]
+    FixedSupply,
+    #[error("already in use")]
+    AlreadyInUse,
+    #[error("invalid number of provided signers")]
+    InvalidNumberOfProvidedSigners,
+    #[error("invalid number of required signers")]
+    InvalidNumberOfRequiredSigners,
+    #[error("state is uninitialized")]
+    UninitializedState,
+    #[error("instruction does not support native tokens")]
+    NativeNotSupported,
+    #[error("non-native account can only be closed if its balance is zero")]
+    NonNativeHasBalance,
+    #[error("invalid instruction")]
+    InvalidInstruction,
+    #[error("state is invalid for the requested operation")]
+    InvalidState,
+    #[error("operation overflowed")]
+    Overflow,
+    #[error("account does not support the specified authority type")]
+    AuthorityTypeNotSupported,
+    #[error("this token mint cannot freeze accounts")]
+    MintCannotFreeze,
+    #[error("account is frozen")]
+    AccountFrozen,
+    #[error("the provided decimals differ from the mint's decimals")]
+    MintDecimalsMismatch,
+    #[error("instruction does not support non-native tokens")]
+    NonNativeNotSupported,
+}
+
+impl TokenError {
+    const ALL: [Self; 20] = [
+        Self::NotRentExempt,
+        Self::InsufficientFunds,
+        Self::InvalidMint,
+        Self::MintMismatch,
+        Self::OwnerMismatch,
+        Self::FixedSupply,
+        Self::AlreadyInUse,
+        Self::InvalidNumberOfProvidedSigners,
+        Self::InvalidNumberOfRequiredSigners,
+        Self::UninitializedState,
+        Self::NativeNotSupported,
+        Self::NonNativeHasBalance,
+        Self::InvalidInstruction,
+        Self::InvalidState,
+        Self::Overflow,
+        Self::AuthorityTypeNotSupported,
+        Self::MintCannotFreeze,
+        Self::AccountFrozen,
+        Self::MintDecimalsMismatch,
+        Self::NonNativeNotSupported,
+    ];
+
+    /// The error reported with the custom code `code`, if any.
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+impl From<TokenError> for InstructionError {
+    fn from(err: TokenError) -> Self {
+        Self::Custom(err as u32)
+    }
+}
+
+/// A token type: who may mint it, how much exists and how it is divided.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mint {
+    /// Signs `MintTo`; without one the supply is fixed.
+    pub mint_authority: Option<Pubkey>,
+    pub supply: u64,
+    /// Digits to the right of the decimal point of a display amount.
+    pub decimals: u8,
+    pub is_initialized: bool,
+    /// Signs `FreezeAccount` and `ThawAccount`.
+    pub freeze_authority: Option<Pubkey>,
+}
+
+impl Mint {
+    /// Size of a mint's account data.
+    pub const LEN: usize = 82;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, InstructionError> {
+        let mut reader = StateReader::new(data, Self::LEN)?;
+        Ok(Self {
+            mint_authority: reader.read_pubkey_option()?,
+            supply: reader.read_u64(),
+            decimals: reader.read_u8(),
+            is_initialized: reader.read_bool()?,
+            freeze_authority: reader.read_pubkey_option()?,
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::LEN);
+        pack_pubkey_option(&mut data, self.mint_authority);
+        data.extend_from_slice(&self.supply.to_le_bytes());
+        data.push(self.decimals);
+        data.push(self.is_initialized.into());
+        pack_pubkey_option(&mut data, self.freeze_authority);
+        data
+    }
+}
+
+/// Whether a token account can be used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccountState {
+    #[default]
+    Uninitialized,
+    Initialized,
+    /// Held tokens cannot move until the mint's freeze authority thaws
+    /// the account.
+    Frozen,
+}
+
+/// A balance of one mint's tokens held for an owner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// May spend up to `delegated_amount` on the owner's behalf.
+    pub delegate: Option<Pubkey>,
+    pub state: AccountState,
+    /// The rent-exempt reserve of a wrapped SOL account. Such accounts are
+    /// rejected by this program.
+    pub is_native: Option<u64>,
+    pub delegated_amount: u64,
+    /// Signs `CloseAccount` instead of the owner.
+    pub close_authority: Option<Pubkey>,
+}
+
+impl TokenAccount {
+    /// Size of a token account's data.
+    pub const LEN: usize = 165;
+
+    /// An initialized account of `owner` holding `amount` tokens of `mint`.
+    pub fn new(mint: Pubkey, owner: Pubkey, amount: u64) -> Self {
+        Self {
+            mint,
+            owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Self::default()
+        }
+    }
+
+    pub fn unpack(data: &[u8]) -> Result<Self, InstructionError> {
+        let mut reader = StateReader::new(data, Self::LEN)?;
+        Ok(Self {
+            mint: reader.read_pubkey(),
+            owner: reader.read_pubkey(),
+            amount: reader.read_u64(),
+            delegate: reader.read_pubkey_option()?,
+            state: match reader.read_u8() {
+                0 => AccountState::Uninitialized,
+                1 => AccountState::Initialized,
+                2 => AccountState::Frozen,
+                _ => return Err(InstructionError::InvalidAccountData),
+            },
+            is_native: reader.read_u64_option()?,
+            delegated_amount: reader.read_u64(),
+            close_authority: reader.read_pubkey_option()?,
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::LEN);
+        data.extend_from_slice(self.mint.as_ref());
+        data.extend_from_slice(self.owner.as_ref());
+        data.extend_from_slice(&self.amount.to_le_bytes());
+        pack_pubkey_option(&mut data, self.delegate);
+        data.push(self.state as u8);
+        match self.is_native {
+            Some(reserve) => {
+                data.extend_from_slice(&1u32.to_le_bytes());
+                data.extend_from_slice(&reserve.to_le_bytes());
+            }
+            None => data.extend_from_slice(&[0; 12]),
+        }
+        data.extend_from_slice(&self.delegated_amount.to_le_bytes());
+        pack_pubkey_option(&mut data, self.close_authority);
+        data
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.state == AccountState::Frozen
+    }
+}
+
+/// Reads fixed-size account state front to back.
+///
+/// Optional fields are stored as a four byte little-endian tag followed by
+/// the value, zeroed when the tag is 0.
+struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    fn new(data: &'a [u8], len: usize) -> Result<Self, InstructionError> {
+        if data.len() != len {
+            return Err(InstructionError::InvalidAccountData);
+        }
+        Ok(Self { data })
+    }
+
+    // The length checked in `new` covers every read.
+    fn read<const N: usize>(&mut self) -> [u8; N] {
+        let (bytes, rest) = self.data.split_first_chunk().unwrap();
+        self.data = rest;
+        *bytes
+    }
+
+    fn read_u8(&mut self) -> u8 {
+        u8::from_le_bytes(self.read())
+    }
+
+    fn read_u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.read())
+    }
+
+    fn read_bool(&mut self) -> Result<bool, InstructionError> {
+        match self.read_u8() {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(InstructionError::InvalidAccountData),
+        }
+    }
+
+    fn read_pubkey(&mut self) -> Pubkey {
+        Pubkey::new_from_array(self.read())
+    }
+
+    fn read_tag(&mut self) -> Result<bool, InstructionError> {
+        match u32::from_le_bytes(self.read()) {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(InstructionError::InvalidAccountData),
+        }
+    }
+
+    fn read_pubkey_option(&mut self) -> Result<Option<Pubkey>, InstructionError> {
+        let is_some = self.read_tag()?;
+        let pubkey = self.read_pubkey();
+        Ok(is_some.then_some(pubkey))
+    }
+
+    fn read_u64_option(&mut self) -> Result<Option<u64>, InstructionError> {
+        let is_some = self.read_tag()?;
+        let value = self.read_u64();
+        Ok(is_some.then_some(value))
+    }
+}
+
+fn pack_pubkey_option(data: &mut Vec<u8>, pubkey: Option<Pubkey>) {
+    match pubkey {
+        Some(pubkey) => {
+            data.extend_from_slice(&1u32.to_le_bytes());
+            data.extend_from_slice(pubkey.as_ref());
+        }
+        None => data.extend_from_slice(&[0; 36]),
+    }
+}
+
+/// Instructions understood by the token program.
+///
+/// The wire format is a one byte discriminant followed by the little-endian
+/// arguments; an optional key is a one byte tag followed by the key if the
+/// tag is 1. Discriminants match the on-chain program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenInstruction {
+    /// Initialize a rent-exempt mint.
+    ///
+    /// Accounts: `[writable]` mint, `[]` Rent sysvar.
+    InitializeMint {
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+    /// Initialize a rent-exempt token account for `owner`.
+    ///
+    /// Accounts: `[writable]` account, `[]` mint, `[]` owner, `[]` Rent
+    /// sysvar.
+    InitializeAccount,
+    /// Move tokens between two accounts of the same mint.
+    ///
+    /// Accounts: `[writable]` source, `[writable]` destination, `[signer]`
+    /// source owner or delegate.
+    Transfer { amount: u64 },
+    /// Let a delegate spend up to `amount` of the source's tokens.
+    ///
+    /// Accounts: `[writable]` source, `[]` delegate, `[signer]` source
+    /// owner.
+    Approve { amount: u64 },
+    /// Remove the source's delegate.
+    ///
+    /// Accounts: `[writable]` source, `[signer]` source owner.
+    Revoke,
+    /// Create new tokens in an account.
+    ///
+    /// Accounts: `[writable]` mint, `[writable]` destination, `[signer]`
+    /// mint authority.
+    MintTo { amount: u64 },
+    /// Destroy tokens held by an account.
+    ///
+    /// Accounts: `[writable]` account, `[writable]` mint, `[signer]`
+    /// account owner or delegate.
+    Burn { amount: u64 },
+    /// Close an empty token account, moving its lamports out.
+    ///
+    /// Accounts: `[writable]` account, `[writable]` destination, `[signer]`
+    /// close authority or owner.
+    CloseAccount,
+    /// Freeze an account so its tokens cannot move.
+    ///
+    /// Accounts: `[writable]` account, `[]` mint, `[signer]` freeze
+    /// authority.
+    FreezeAccount,
+    /// Thaw a frozen account.
+    ///
+    /// Accounts: `[writable]` account, `[]` mint, `[signer]` freeze
+    /// authority.
+    ThawAccount,
+    /// `Transfer`, also checking the mint and its decimals.
+    ///
+    /// Accounts: `[writable]` source, `[]` mint, `[writable]` destination,
+    /// `[signer]` source owner or delegate.
+    TransferChecked { amount: u64, decimals: u8 },
+    /// `InitializeAccount` with the owner passed as an argument and without
+    /// the Rent sysvar.
+    ///
+    /// Accounts: `[writable]` account, `[]` mint.
+    InitializeAccount3 { owner: Pubkey },
+    /// `InitializeMint` without the Rent sysvar.
+    ///
+    /// Accounts: `[writable]` mint.
+    InitializeMint2 {
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+}
+
+impl TokenInstruction {
+    /// Parses instruction data, failing with
+    /// [`TokenError::InvalidInstruction`] for malformed data and for
+    /// instructions this program does not support.
+    pub fn parse(data: &[u8]) -> Result<Self, InstructionError> {
+        Self::parse_unchecked(data).map_err(|_| TokenError::InvalidInstruction.into())
+    }
+
+    fn parse_unchecked(data: &[u8]) -> Result<Self, InstructionError> {
+        let mut reader = InstructionReader::new(data);
+        match reader.read_u8()? {
+            0 => Ok(Self::InitializeMint {
+                decimals: reader.read_u8()?,
+                mint_authority: reader.read_pubkey()?,
+                freeze_authority: read_pubkey_option(&mut reader)?,
+            }),
+            1 => Ok(Self::InitializeAccount),
+            3 => Ok(Self::Transfer {
+                amount: reader.read_u64()?,
+            }),
+            4 => Ok(Self::Approve {
+                amount: reader.read_u64()?,
+            }),
+            5 => Ok(Self::Revoke),
+            7 => Ok(Self::MintTo {
+                amount: reader.read_u64()?,
+            }),
+            8 => Ok(Self::Burn {
+                amount: reader.read_u64()?,
+            }),
+            9 => Ok(Self::CloseAccount),
+            10 => Ok(Self::FreezeAccount),
+            11 => Ok(Self::ThawAccount),
+            12 => Ok(Self::TransferChecked {
+                amount: reader.read_u64()?,
+                decimals: reader.read_u8()?,
+            }),
+            18 => Ok(Self::InitializeAccount3 {
+                owner: reader.read_pubkey()?,
+            }),
+            20 => Ok(Self::InitializeMint2 {
+                decimals: reader.read_u8()?,
+                mint_authority: reader.read_pubkey()?,
+                freeze_authority: read_pubkey_option(&mut reader)?,
+            }),
+            _ => Err(InstructionError::InvalidInstructionData),
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let discriminant: u8 = match self {
+            Self::InitializeMint { .. } => 0,
+            Self::InitializeAccount => 1,
+            Self::Transfer { .. } => 3,
+            Self::Approve { .. } => 4,
+            Self::Revoke => 5,
+            Self::MintTo { .. } => 7,
+            Self::Burn { .. } => 8,
+            Self::CloseAccount => 9,
+            Self::FreezeAccount => 10,
+            Self::ThawAccount => 11,
+            Self::TransferChecked { .. } => 12,
+            Self::InitializeAccount3 { .. } => 18,
+            Self::InitializeMint2 { .. } => 20,
+        };
+        let mut data = vec![discriminant];
+        match self {
+            Self::InitializeMint {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            }
+            | Self::InitializeMint2 {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => {
+                data.push(*decimals);
+                data.extend_from_slice(mint_authority.as_ref());
+                match freeze_authority {
+                    Some(freeze_authority) => {
+                        data.push(1);
+                        data.extend_from_slice(freeze_authority.as_ref());
+                    }
+                    None => data.push(0),
+                }
+            }
+            Self::Transfer { amount }
+            | Self::Approve { amount }
+            | Self::MintTo { amount }
+            | Self::Burn { amount } => data.extend_from_slice(&amount.to_le_bytes()),
+            Self::TransferChecked { amount, decimals } => {
+                data.extend_from_slice(&amount.to_le_bytes());
+                data.push(*decimals);
+            }
+            Self::InitializeAccount3 { owner } => data.extend_from_slice(owner.as_ref()),
+            Self::InitializeAccount
+            | Self::Revoke
+            | Self::CloseAccount
+            | Self::FreezeAccount
+            | Self::ThawAccount => {}
+        }
+        data
+    }
+}
+
+fn read_pubkey_option(reader: &mut InstructionReader) -> Result<Option<Pubkey>, InstructionError> {
+    match reader.read_u8()? {
+        0 => Ok(None),
+        1 => reader.read_pubkey().map(Some),
+        _ => Err(InstructionError::InvalidInstructionData),
+    }
+}
+
+fn instruction(data: TokenInstruction, accounts: Vec<AccountMeta>) -> Instruction {
+    Instruction::new_with_bytes(ID, &data.serialize(), accounts)
+}
+
+/// Builds an `InitializeMint2` of `mint`.
+pub fn initialize_mint2(
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    decimals: u8,
+) -> Instruction {
+    instruction(
+        TokenInstruction::InitializeMint2 {
+            decimals,
+            mint_authority: *mint_authority,
+            freeze_authority: freeze_authority.copied(),
+        },
+        vec![AccountMeta::new(*mint, false)],
+    )
+}
+
+/// Builds an `InitializeAccount3` of `account` for `owner`.
+pub fn initialize_account3(account: &Pubkey, mint: &Pubkey, owner: &Pubkey) -> Instruction {
+    instruction(
+        TokenInstruction::InitializeAccount3 { owner: *owner },
+        vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+    )
+}
+
+/// Builds a `Transfer` of `amount` tokens signed by `authority`, the
+/// source's owner or delegate.
+pub fn transfer(
+    source: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(
+        TokenInstruction::Transfer { amount },
+        vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Builds a `TransferChecked` of `amount` tokens of `mint`.
+pub fn transfer_checked(
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    instruction(
+        TokenInstruction::TransferChecked { amount, decimals },
+        vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Builds an `Approve` letting `delegate` spend `amount` of `source`'s
+/// tokens.
+pub fn approve(source: &Pubkey, delegate: &Pubkey, owner: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        TokenInstruction::Approve { amount },
+        vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*delegate, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+    )
+}
+
+/// Builds a `Revoke` of `source`'s delegate.
+pub fn revoke(source: &Pubkey, owner: &Pubkey) -> Instruction {
+    instruction(
+        TokenInstruction::Revoke,
+        vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+    )
+}
+
+/// Builds a `MintTo` of `amount` new tokens into `destination`.
+pub fn mint_to(
+    mint: &Pubkey,
+    destination: &Pubkey,
+    mint_authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(
+        TokenInstruction::MintTo { amount },
+        vec![
+            AccountMeta::new(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*mint_authority, true),
+        ],
+    )
+}
+
+/// Builds a `Burn` of `amount` tokens held by `account`.
+pub fn burn(account: &Pubkey, mint: &Pubkey, authority: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        TokenInstruction::Burn { amount },
+        vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Builds a `CloseAccount` of `account`, paying its lamports to
+/// `destination`.
+pub fn close_account(account: &Pubkey, destination: &Pubkey, authority: &Pubkey) -> Instruction {
+    instruction(
+        TokenInstruction::CloseAccount,
+        vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Builds a `FreezeAccount` of `account`.
+pub fn freeze_account(account: &Pubkey, mint: &Pubkey, freeze_authority: &Pubkey) -> Instruction {
+    instruction(
+        TokenInstruction::FreezeAccount,
+        vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*freeze_authority, true),
+        ],
+    )
+}
+
+/// Builds a `ThawAccount` of `account`.
+pub fn thaw_account(account: &Pubkey, mint: &Pubkey, freeze_authority: &Pubkey) -> Instruction {
+    instruction(
+        TokenInstruction::ThawAccount,
+        vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*freeze_authority, true),
+        ],
+    )
+}
+
+/// Builds the instructions that create and initialize a mint holding
+/// `lamports`, which must cover its rent-exempt minimum.
+pub fn create_mint(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    decimals: u8,
+    lamports: u64,
+) -> Vec<Instruction> {
+    vec![
+        system_program::create_account(payer, mint, lamports, Mint::LEN as u64, &ID),
+        initialize_mint2(mint, mint_authority, freeze_authority, decimals),
+    ]
+}
+
+/// Builds the instructions that create and initialize a token account of
+/// `owner` holding `lamports`, which must cover its rent-exempt minimum.
+pub fn create_token_account(
+    payer: &Pubkey,
+    account: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    lamports: u64,
+) -> Vec<Instruction> {
+    vec![
+        system_program::create_account(payer, account, lamports, TokenAccount::LEN as u64, &ID),
+        initialize_account3(account, mint, owner),
+    ]
+}
+
+/// A rent-exempt account holding `mint`, ready to be stored before any
+/// transaction runs.
+pub fn mint_account(mint: &Mint, rent: &Rent) -> AccountSharedData {
+    state_account(&mint.pack(), rent)
+}
+
+/// A rent-exempt account holding `account`, ready to be stored before any
+/// transaction runs.
+pub fn token_account(account: &TokenAccount, rent: &Rent) -> AccountSharedData {
+    state_account(&account.pack(), rent)
+}
+
+fn state_account(data: &[u8], rent: &Rent) -> AccountSharedData {
+    let mut account = AccountSharedData::new(rent.minimum_balance(data.len()), 0, &ID);
+    account.set_data_from_slice(data);
+    account
+}
+
+/// Entrypoint of the token program.
+pub fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    invoke_context.consume_builtin_cost(DEFAULT_COMPUTE_UNITS)?;
+    match TokenInstruction::parse(invoke_context.instruction_data())? {
+        TokenInstruction::InitializeMint {
+            decimals,
+            mint_authority,
+            freeze_authority,
+        } => {
+            check_number_of_accounts(invoke_context, 2)?;
+            check_sysvar_account(invoke_context, 1, &sysvar::rent::id())?;
+            process_initialize_mint(invoke_context, decimals, mint_authority, freeze_authority)
+        }
+        TokenInstruction::InitializeMint2 {
+            decimals,
+            mint_authority,
+            freeze_authority,
+        } => {
+            check_number_of_accounts(invoke_context, 1)?;
+            process_initialize_mint(invoke_context, decimals, mint_authority, freeze_authority)
+        }
+        TokenInstruction::InitializeAccount => {
+            check_number_of_accounts(invoke_context, 4)?;
+            check_sysvar_account(invoke_context, 3, &sysvar::rent::id())?;
+            let owner = *invoke_context.get_key(2)?;
+            process_initialize_account(invoke_context, owner)
+        }
+        TokenInstruction::InitializeAccount3 { owner } => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_initialize_account(invoke_context, owner)
+        }
+        TokenInstruction::Transfer { amount } => {
+            check_number_of_accounts(invoke_context, 3)?;
+            process_transfer(invoke_context, 0, 1, 2, amount, None)
+        }
+        TokenInstruction::TransferChecked { amount, decimals } => {
+            check_number_of_accounts(invoke_context, 4)?;
+            process_transfer(invoke_context, 0, 2, 3, amount, Some((1, decimals)))
+        }
+        TokenInstruction::Approve { amount } => {
+            check_number_of_accounts(invoke_context, 3)?;
+            process_approve(invoke_context, amount)
+        }
+        TokenInstruction::Revoke => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_revoke(invoke_context)
+        }
+        TokenInstruction::MintTo { amount } => {
+            check_number_of_accounts(invoke_context, 3)?;
+            process_mint_to(invoke_context, amount)
+        }
+        TokenInstruction::Burn { amount } => {
+            check_number_of_accounts(invoke_context, 3)?;
+            process_burn(invoke_context, amount)
+        }
+        TokenInstruction::CloseAccount => {
+            check_number_of_accounts(invoke_context, 3)?;
+            process_close_account(invoke_context)
+        }
+        TokenInstruction::FreezeAccount => {
+            check_number_of_accounts(invoke_context, 3)?;
+            process_toggle_freeze(invoke_context, true)
+        }
+        TokenInstruction::ThawAccount => {
+            check_number_of_accounts(invoke_context, 3)?;
+            process_toggle_freeze(invoke_context, false)
+        }
+    }
+}
+
+/// Returns the account at `index` if the token program owns it.
+fn get_owned_account<'a>(
+    invoke_context: &'a InvokeContext,
+    index: usize,
+) -> Result<&'a AccountSharedData, InstructionError> {
+    let account = invoke_context.get_account(index)?;
+    if *account.owner() != ID {
+        return Err(InstructionError::IncorrectProgramId);
+    }
+    Ok(account)
+}
+
+fn get_mint(invoke_context: &InvokeContext, index: usize) -> Result<Mint, InstructionError> {
+    let mint = Mint::unpack(get_owned_account(invoke_context, index)?.data())?;
+    if !mint.is_initialized {
+        return Err(InstructionError::UninitializedAccount);
+    }
+    Ok(mint)
+}
+
+fn get_token_account(
+    invoke_context: &InvokeContext,
+    index: usize,
+) -> Result<TokenAccount, InstructionError> {
+    let account = TokenAccount::unpack(get_owned_account(invoke_context, index)?.data())?;
+    if account.state == AccountState::Uninitialized {
+        return Err(InstructionError::UninitializedAccount);
+    }
+    if account.is_native.is_some() {
+        return Err(TokenError::NativeNotSupported.into());
+    }
+    Ok(account)
+}
+
+// The state was unpacked from the same account, so the lengths match.
+fn set_state(
+    invoke_context: &mut InvokeContext,
+    index: usize,
+    data: &[u8],
+) -> Result<(), InstructionError> {
+    invoke_context
+        .get_account_mut(index)?
+        .data_as_mut_slice()
+        .copy_from_slice(data);
+    Ok(())
+}
+
+/// Checks that the account at `index` is `authority` and signed.
+fn validate_authority(
+    invoke_context: &InvokeContext,
+    authority: &Pubkey,
+    index: usize,
+) -> Result<(), InstructionError> {
+    if invoke_context.get_key(index)? != authority {
+        return Err(TokenError::OwnerMismatch.into());
+    }
+    if !invoke_context.is_signer(index)? {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+/// Checks that the account at `index` may spend `amount` of `account`'s
+/// tokens: its owner, or its delegate within the allowance left, which the
+/// spend then reduces.
+fn authorize_spend(
+    invoke_context: &InvokeContext,
+    account: &mut TokenAccount,
+    index: usize,
+    amount: u64,
+) -> Result<(), InstructionError> {
+    match account.delegate {
+        Some(delegate) if *invoke_context.get_key(index)? == delegate => {
+            validate_authority(invoke_context, &delegate, index)?;
+            account.delegated_amount = account
+                .delegated_amount
+                .checked_sub(amount)
+                .ok_or(TokenError::InsufficientFunds)?;
+            if account.delegated_amount == 0 {
+                account.delegate = None;
+            }
+            Ok(())
+        }
+        _ => validate_authority(invoke_context, &account.owner, index),
+    }
+}
+
+fn check_rent_exempt(invoke_context: &InvokeContext, index: usize) -> Result<(), InstructionError> {
+    let account = invoke_context.get_account(index)?;
+    let min_balance = invoke_context
+        .get_sysvar_cache()
+        .get_rent()
+        .minimum_balance(account.data().len());
+    if account.lamports() < min_balance {
+        return Err(TokenError::NotRentExempt.into());
+    }
+    Ok(())
+}
+
+fn process_initialize_mint(
+    invoke_context: &mut InvokeContext,
+    decimals: u8,
+    mint_authority: Pubkey,
+    freeze_authority: Option<Pubkey>,
+) -> Result<(), InstructionError> {
+    let mint = Mint::unpack(get_owned_account(invoke_context, 0)?.data())?;
+    if mint.is_initialized {
+        return Err(TokenError::AlreadyInUse.into());
+    }
+    check_rent_exempt(invoke_context, 0)?;
+    let mint = Mint {
+        mint_authority: Some(mint_authority),
+        supply: 0,
+        decimals,
+        is_initialized: true,
+        freeze_authority,
+    };
+    set_state(invoke_context, 0, &mint.pack())
+}
+
+fn process_initialize_account(
+    invoke_context: &mut InvokeContext,
+    owner: Pubkey,
+) -> Result<(), InstructionError> {
+    let account = TokenAccount::unpack(get_owned_account(invoke_context, 0)?.data())?;
+    if account.state != AccountState::Uninitialized {
+        return Err(TokenError::AlreadyInUse.into());
+    }
+    check_rent_exempt(invoke_context, 0)?;
+    get_mint(invoke_context, 1).map_err(|_| TokenError::InvalidMint)?;
+    let mint = *invoke_context.get_key(1)?;
+    set_state(invoke_context, 0, &TokenAccount::new(mint, owner, 0).pack())
+}
+
+/// Moves tokens between the accounts at `source_index` and
+/// `destination_index`. `checked` holds the index of the mint and the
+/// decimals the instruction expects, for `TransferChecked`.
+fn process_transfer(
+    invoke_context: &mut InvokeContext,
+    source_index: usize,
+    destination_index: usize,
+    authority_index: usize,
+    amount: u64,
+    checked: Option<(usize, u8)>,
+) -> Result<(), InstructionError> {
+    let mut source = get_token_account(invoke_context, source_index)?;
+    let mut destination = get_token_account(invoke_context, destination_index)?;
+    if source.is_frozen() || destination.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    if source.amount < amount {
+        return Err(TokenError::InsufficientFunds.into());
+    }
+    if source.mint != destination.mint {
+        return Err(TokenError::MintMismatch.into());
+    }
+    if let Some((mint_index, decimals)) = checked {
+        if *invoke_context.get_key(mint_index)? != source.mint {
+            return Err(TokenError::MintMismatch.into());
+        }
+        if get_mint(invoke_context, mint_index)?.decimals != decimals {
+            return Err(TokenError::MintDecimalsMismatch.into());
+        }
+    }
+    authorize_spend(invoke_context, &mut source, authority_index, amount)?;
+
+    // A transfer to itself is validated like any other but moves nothing.
+    if invoke_context.get_index_in_transaction(source_index)?
+        == invoke_context.get_index_in_transaction(destination_index)?
+    {
+        return Ok(());
+    }
+    source.amount -= amount;
+    destination.amount = destination
+        .amount
+        .checked_add(amount)
+        .ok_or(TokenError::Overflow)?;
+    set_state(invoke_context, source_index, &source.pack())?;
+    set_state(invoke_context, destination_index, &destination.pack())
+}
+
+fn process_approve(
+    invoke_context: &mut InvokeContext,
+    amount: u64,
+) -> Result<(), InstructionError> {
+    let mut source = get_token_account(invoke_context, 0)?;
+    if source.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    validate_authority(invoke_context, &source.owner, 2)?;
+    source.delegate = Some(*invoke_context.get_key(1)?);
+    source.delegated_amount = amount;
+    set_state(invoke_context, 0, &source.pack())
+}
+
+fn process_revoke(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    let mut source = get_token_account(invoke_context, 0)?;
+    if source.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    validate_authority(invoke_context, &source.owner, 1)?;
+    source.delegate = None;
+    source.delegated_amount = 0;
+    set_state(invoke_context, 0, &source.pack())
+}
+
+fn process_mint_to(
+    invoke_context: &mut InvokeContext,
+    amount: u64,
+) -> Result<(), InstructionError> {
+    let mut destination = get_token_account(invoke_context, 1)?;
+    if destination.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    if destination.mint != *invoke_context.get_key(0)? {
+        return Err(TokenError::MintMismatch.into());
+    }
+    let mut mint = get_mint(invoke_context, 0)?;
+    let mint_authority = mint.mint_authority.ok_or(TokenError::FixedSupply)?;
+    validate_authority(invoke_context, &mint_authority, 2)?;
+    mint.supply = mint
+        .supply
+        .checked_add(amount)
+        .ok_or(TokenError::Overflow)?;
+    destination.amount = destination
+        .amount
+        .checked_add(amount)
+        .ok_or(TokenError::Overflow)?;
+    set_state(invoke_context, 0, &mint.pack())?;
+    set_state(invoke_context, 1, &destination.pack())
+}
+
+fn process_burn(invoke_context: &mut InvokeContext, amount: u64) -> Result<(), InstructionError> {
+    let mut account = get_token_account(invoke_context, 0)?;
+    if account.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    if account.mint != *invoke_context.get_key(1)? {
+        return Err(TokenError::MintMismatch.into());
+    }
+    let mut mint = get_mint(invoke_context, 1)?;
+    if account.amount < amount {
+        return Err(TokenError::InsufficientFunds.into());
+    }
+    authorize_spend(invoke_context, &mut account, 2, amount)?;
+    account.amount -= amount;
+    mint.supply = mint
+        .supply
+        .checked_sub(amount)
+        .ok_or(TokenError::Overflow)?;
+    set_state(invoke_context, 0, &account.pack())?;
+    set_state(invoke_context, 1, &mint.pack())
+}
+
+/// Empties the account at index 0 into the one at index 1 and hands it
+/// back to the System program without data.
+fn process_close_account(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    let account = get_token_account(invoke_context, 0)?;
+    if invoke_context.get_index_in_transaction(0)? == invoke_context.get_index_in_transaction(1)? {
+        return Err(InstructionError::InvalidAccountData);
+    }
+    if account.amount != 0 {
+        return Err(TokenError::NonNativeHasBalance.into());
+    }
+    let authority = account.close_authority.unwrap_or(account.owner);
+    validate_authority(invoke_context, &authority, 2)?;
+
+    let lamports = invoke_context.get_account(0)?.lamports();
+    invoke_context
+        .get_account_mut(1)?
+        .checked_add_lamports(lamports)
+        .map_err(|_| InstructionError::ArithmeticOverflow)?;
+    let closed = invoke_context.get_account_mut(0)?;
+    closed.set_lamports(0);
+    closed.resize(0, 0);
+    closed.set_owner(solana_sdk_ids::system_program::id());
+    Ok(())
+}
+
+fn process_toggle_freeze(
+    invoke_context: &mut InvokeContext,
+    freeze: bool,
+) -> Result<(), InstructionError> {
+    let mut account = get_token_account(invoke_context, 0)?;
+    if account.is_frozen() == freeze {
+        return Err(TokenError::InvalidState.into());
+    }
+    if account.mint != *invoke_context.get_key(1)? {
+        return Err(TokenError::MintMismatch.into());
+    }
+    let freeze_authority = get_mint(invoke_context, 1)?
+        .freeze_authority
+        .ok_or(TokenError::MintCannotFreeze)?;
+    validate_authority(invoke_context, &freeze_authority, 2)?;
+    account.state = if freeze {
+        AccountState::Frozen
+    } else {
+        AccountState::Initialized
+    };
+    set_state(invoke_context, 0, &account.pack())
+}
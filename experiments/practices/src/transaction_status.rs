@@ -0,0 +1,141 @@
+//! Transaction status metadata.
+//!
+//! [`TransactionExecutionDetails`] collects what RPC reports about an
+//! executed transaction (its status, fee, balances, inner instructions,
+//! return data and consumed compute units), so the output of an experiment
+//! can be compared field by field with `getTransaction` on a real cluster.
+//! [`TransactionExecutionDetails::to_rpc_json`] renders it in the JSON
+//! encoding of RPC's `meta` object.
+
+use {
+    crate::svm::{InnerInstruction, TransactionExecutionResult, TransactionReturnData},
+    base64::{prelude::BASE64_STANDARD, Engine},
+    serde_json::{json, Value},
+    solana_account::ReadableAccount,
+    solana_message::{v0::LoadedAddresses, SanitizedMessage},
+    solana_pubkey::Pubkey,
+    solana_transaction::sanitized::SanitizedTransaction,
+    solana_transaction_error::TransactionError,
+};
+
+/// Cross-program invocations made by one top-level instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerInstructions {
+    /// Index of the top-level instruction in the message.
+    pub index: u8,
+    pub instructions: Vec<InnerInstruction>,
+}
+
+/// Status metadata of an executed transaction, as RPC reports it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionExecutionDetails {
+    pub status: Result<(), TransactionError>,
+    /// Lamports charged to the fee payer, priority fee included.
+    pub fee: u64,
+    /// Lamports of every account before the fee was charged, in message
+    /// order.
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    pub compute_units_consumed: u64,
+    /// Program logs. RPC reports them as `null` for transactions executed
+    /// without log recording.
+    pub log_messages: Option<Vec<String>>,
+    /// Only instructions that invoked anything have an entry.
+    pub inner_instructions: Vec<InnerInstructions>,
+    pub return_data: Option<TransactionReturnData>,
+    /// Accounts the transaction loaded through address lookup tables.
+    pub loaded_addresses: LoadedAddresses,
+}
+
+impl TransactionExecutionDetails {
+    pub fn new(transaction: &SanitizedTransaction, result: &TransactionExecutionResult) -> Self {
+        let inner_instructions = result
+            .inner_instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, instructions)| !instructions.is_empty())
+            .map(|(index, instructions)| InnerInstructions {
+                index: index as u8,
+                instructions: instructions.clone(),
+            })
+            .collect();
+        let loaded_addresses = match transaction.message() {
+            SanitizedMessage::V0(message) => message.loaded_addresses.clone().into_owned(),
+            SanitizedMessage::Legacy(_) | SanitizedMessage::V1(_) => LoadedAddresses::default(),
+        };
+        Self {
+            status: result.status.clone(),
+            fee: result.fee_details.total_fee(),
+            pre_balances: result.pre_balances.clone(),
+            post_balances: result
+                .post_accounts
+                .iter()
+                .map(|(_, account)| account.lamports())
+                .collect(),
+            compute_units_consumed: result.consumed_units,
+            log_messages: Some(result.log_messages.clone()),
+            inner_instructions,
+            return_data: result.return_data.clone(),
+            loaded_addresses,
+        }
+    }
+
+    /// Renders the details the way RPC's `getTransaction` renders the
+    /// `meta` of a transaction with the `json` encoding.
+    ///
+    /// Token balances and rewards are always empty, since the runtime
+    /// tracks neither.
+    pub fn to_rpc_json(&self) -> Value {
+        let err = self.status.as_ref().err();
+        let status = match &self.status {
+            Ok(()) => json!({ "Ok": null }),
+            Err(err) => json!({ "Err": err }),
+        };
+        let inner_instructions: Vec<Value> = self
+            .inner_instructions
+            .iter()
+            .map(|inner| {
+                let instructions: Vec<Value> = inner
+                    .instructions
+                    .iter()
+                    .map(|inner_instruction| {
+                        let instruction = &inner_instruction.instruction;
+                        json!({
+                            "programIdIndex": instruction.program_id_index,
+                            "accounts": instruction.accounts,
+                            "data": bs58::encode(&instruction.data).into_string(),
+                            "stackHeight": inner_instruction.stack_height,
+                        })
+                    })
+                    .collect();
+                json!({ "index": inner.index, "instructions": instructions })
+            })
+            .collect();
+        let return_data = self.return_data.as_ref().map(|return_data| {
+            json!({
+                "programId": return_data.program_id.to_string(),
+                "data": [BASE64_STANDARD.encode(&return_data.data), "base64"],
+            })
+        });
+        let to_strings = |keys: &[Pubkey]| keys.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        json!({
+            "err": err,
+            "status": status,
+            "fee": self.fee,
+            "preBalances": self.pre_balances,
+            "postBalances": self.post_balances,
+            "innerInstructions": inner_instructions,
+            "logMessages": self.log_messages,
+            "preTokenBalances": [],
+            "postTokenBalances": [],
+            "rewards": [],
+            "loadedAddresses": {
+                "writable": to_strings(&self.loaded_addresses.writable),
+                "readonly": to_strings(&self.loaded_addresses.readonly),
+            },
+            "returnData": return_data,
+            "computeUnitsConsumed": self.compute_units_consumed,
+        })
+    }
+}
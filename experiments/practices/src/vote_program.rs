@@ -0,0 +1,510 @@
+//! The Vote program.
+//!
+//! Validators record their votes in vote accounts owned by this program.
+//! Every vote pushes the slots it votes on onto the account's tower of
+//! lockouts; a vote deep enough in the tower roots its slot and earns
+//! credits for the epoch, more the sooner after its slot the vote landed.
+//! The commission is the share of rewards the validator keeps.
+//!
+//! Accounts hold a `VoteStateV3` in the layout of `solana-vote-interface`,
+//! whose instruction builders produce instructions this program accepts.
+//! It processes `InitializeAccount`, `Vote`, `TowerSync`, their `Switch`
+//! variants, `Withdraw`, `UpdateValidatorIdentity` and `UpdateCommission`;
+//! any other instruction fails with
+//! [`InstructionError::InvalidInstructionData`]. A `TowerSync` replaces the
+//! tower once it is well formed and its last slot matches SlotHashes, so
+//! it is not checked for lockouts it would break. Vote errors are reported
+//! as [`InstructionError::Custom`] with the `VoteError` code of the
+//! on-chain program.
+
+use {
+    crate::{
+        sanitize::MAX_TRANSACTION_LEN,
+        svm::InvokeContext,
+        system_program::{check_number_of_accounts, check_sysvar_account},
+    },
+    bincode::Options,
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_clock::{Clock, Epoch, Slot, UnixTimestamp},
+    solana_epoch_schedule::EpochSchedule,
+    solana_instruction_error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::{sysvar, vote::ID},
+    solana_slot_hashes::SlotHashes,
+    solana_vote_interface::{
+        error::VoteError,
+        instruction::VoteInstruction,
+        state::{
+            BlockTimestamp, LandedVote, Lockout, TowerSync, Vote, VoteInit, VoteStateV3,
+            VoteStateVersions, MAX_EPOCH_CREDITS_HISTORY, MAX_LOCKOUT_HISTORY,
+            VOTE_CREDITS_GRACE_SLOTS, VOTE_CREDITS_MAXIMUM_PER_SLOT,
+        },
+    },
+    std::collections::HashSet,
+};
+
+/// CUs consumed by executing a single Vote program instruction.
+pub const DEFAULT_COMPUTE_UNITS: u64 = 2_100;
+
+/// A rent-exempt vote account initialized with `vote_init` in `clock`'s
+/// epoch, ready to be stored before any transaction runs.
+pub fn vote_account(vote_init: &VoteInit, clock: &Clock, rent: &Rent) -> AccountSharedData {
+    let space = VoteStateV3::size_of();
+    let mut account = AccountSharedData::new(rent.minimum_balance(space), space, &ID);
+    let vote_state = VoteStateVersions::new_v3(VoteStateV3::new(vote_init, clock));
+    VoteStateV3::serialize(&vote_state, account.data_as_mut_slice())
+        .expect("a new vote state fits its account");
+    account
+}
+
+/// Entrypoint of the Vote program.
+pub fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    invoke_context.consume_builtin_cost(DEFAULT_COMPUTE_UNITS)?;
+    let instruction: VoteInstruction = bincode::options()
+        .with_limit(MAX_TRANSACTION_LEN as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize(invoke_context.instruction_data())
+        .map_err(|_| InstructionError::InvalidInstructionData)?;
+    check_number_of_accounts(invoke_context, 1)?;
+    if *invoke_context.get_account(0)?.owner() != ID {
+        return Err(InstructionError::InvalidAccountOwner);
+    }
+    let signers = invoke_context.get_signers();
+    match instruction {
+        VoteInstruction::InitializeAccount(vote_init) => {
+            check_number_of_accounts(invoke_context, 4)?;
+            check_sysvar_account(invoke_context, 1, &sysvar::rent::id())?;
+            check_sysvar_account(invoke_context, 2, &sysvar::clock::id())?;
+            process_initialize_account(invoke_context, &vote_init, &signers)
+        }
+        VoteInstruction::Vote(vote) | VoteInstruction::VoteSwitch(vote, _) => {
+            check_number_of_accounts(invoke_context, 3)?;
+            check_sysvar_account(invoke_context, 1, &sysvar::slot_hashes::id())?;
+            check_sysvar_account(invoke_context, 2, &sysvar::clock::id())?;
+            process_vote(invoke_context, &vote, &signers)
+        }
+        VoteInstruction::TowerSync(tower_sync)
+        | VoteInstruction::TowerSyncSwitch(tower_sync, _) => {
+            process_tower_sync(invoke_context, &tower_sync, &signers)
+        }
+        VoteInstruction::Withdraw(lamports) => {
+            check_number_of_accounts(invoke_context, 2)?;
+            process_withdraw(invoke_context, lamports, &signers)
+        }
+        VoteInstruction::UpdateValidatorIdentity => {
+            check_number_of_accounts(invoke_context, 2)?;
+            let node_pubkey = *invoke_context.get_key(1)?;
+            let mut vote_state = get_vote_state(invoke_context)?;
+            verify_signer(&vote_state.authorized_withdrawer, &signers)?;
+            verify_signer(&node_pubkey, &signers)?;
+            vote_state.node_pubkey = node_pubkey;
+            set_vote_state(invoke_context, vote_state)
+        }
+        VoteInstruction::UpdateCommission(commission) => {
+            process_update_commission(invoke_context, commission, &signers)
+        }
+        _ => Err(InstructionError::InvalidInstructionData),
+    }
+}
+
+fn into_instruction_error(err: VoteError) -> InstructionError {
+    InstructionError::Custom(err as u32)
+}
+
+fn verify_signer(authorized: &Pubkey, signers: &HashSet<Pubkey>) -> Result<(), InstructionError> {
+    if !signers.contains(authorized) {
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+/// The initialized vote state of the account at index 0.
+fn get_vote_state(invoke_context: &InvokeContext) -> Result<VoteStateV3, InstructionError> {
+    let vote_state = VoteStateV3::deserialize(invoke_context.get_account(0)?.data())?;
+    if vote_state.is_uninitialized() {
+        return Err(InstructionError::UninitializedAccount);
+    }
+    Ok(vote_state)
+}
+
+fn set_vote_state(
+    invoke_context: &mut InvokeContext,
+    vote_state: VoteStateV3,
+) -> Result<(), InstructionError> {
+    let account = invoke_context.get_account_mut(0)?;
+    VoteStateV3::serialize(
+        &VoteStateVersions::new_v3(vote_state),
+        account.data_as_mut_slice(),
+    )
+}
+
+/// Checks that the voter authorized for `epoch` signed, and forgets voters
+/// of earlier epochs.
+fn authorize_voter(
+    vote_state: &mut VoteStateV3,
+    epoch: Epoch,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let voter = vote_state
+        .authorized_voters
+        .get_and_cache_authorized_voter_for_epoch(epoch)
+        .ok_or(InstructionError::InvalidAccountData)?;
+    vote_state.authorized_voters.purge_authorized_voters(epoch);
+    verify_signer(&voter, signers)
+}
+
+fn process_initialize_account(
+    invoke_context: &mut InvokeContext,
+    vote_init: &VoteInit,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let account = invoke_context.get_account(0)?;
+    if account.data().len() != VoteStateV3::size_of() {
+        return Err(InstructionError::InvalidAccountData);
+    }
+    if VoteStateVersions::is_correct_size_and_initialized(account.data()) {
+        return Err(InstructionError::AccountAlreadyInitialized);
+    }
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    if account.lamports()
+        < sysvar_cache
+            .get_rent()
+            .minimum_balance(account.data().len())
+    {
+        return Err(InstructionError::InsufficientFunds);
+    }
+    verify_signer(&vote_init.node_pubkey, signers)?;
+    let vote_state = VoteStateV3::new(vote_init, sysvar_cache.get_clock());
+    set_vote_state(invoke_context, vote_state)
+}
+
+fn process_vote(
+    invoke_context: &mut InvokeContext,
+    vote: &Vote,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let mut vote_state = get_vote_state(invoke_context)?;
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    let clock = sysvar_cache.get_clock();
+    authorize_voter(&mut vote_state, clock.epoch, signers)?;
+    let slots = check_vote(&vote_state, vote, sysvar_cache.get_slot_hashes())
+        .map_err(into_instruction_error)?;
+    for slot in slots {
+        process_next_vote_slot(&mut vote_state, slot, clock.epoch, clock.slot);
+    }
+    if let Some(timestamp) = vote.timestamp {
+        let slot = vote.slots.iter().max().copied().unwrap_or_default();
+        process_timestamp(&mut vote_state, slot, timestamp).map_err(into_instruction_error)?;
+    }
+    set_vote_state(invoke_context, vote_state)
+}
+
+/// The slots of `vote` the tower does not hold yet, once every one of them
+/// is in SlotHashes and the last one has the hash the vote names.
+fn check_vote(
+    vote_state: &VoteStateV3,
+    vote: &Vote,
+    slot_hashes: &SlotHashes,
+) -> Result<Vec<Slot>, VoteError> {
+    if vote.slots.is_empty() {
+        return Err(VoteError::EmptySlots);
+    }
+    let last_voted_slot = last_voted_slot(vote_state);
+    let slots: Vec<Slot> = vote
+        .slots
+        .iter()
+        .copied()
+        .filter(|slot| last_voted_slot.is_none_or(|last| *slot > last))
+        .collect();
+    let Some(&last) = slots.last() else {
+        return Err(VoteError::VoteTooOld);
+    };
+    if !slots.windows(2).all(|pair| pair[0] < pair[1])
+        || slots.iter().any(|slot| slot_hashes.get(slot).is_none())
+    {
+        return Err(VoteError::SlotsMismatch);
+    }
+    if slot_hashes.get(&last) != Some(&vote.hash) {
+        return Err(VoteError::SlotHashMismatch);
+    }
+    Ok(slots)
+}
+
+/// The newest slot the tower holds, or its root once the tower is empty.
+fn last_voted_slot(vote_state: &VoteStateV3) -> Option<Slot> {
+    vote_state
+        .votes
+        .back()
+        .map(LandedVote::slot)
+        .or(vote_state.root_slot)
+}
+
+/// Pushes a vote on `slot` that landed in `current_slot`, popping the
+/// votes it expires and rooting the oldest one once the tower is full.
+fn process_next_vote_slot(
+    vote_state: &mut VoteStateV3,
+    slot: Slot,
+    epoch: Epoch,
+    current_slot: Slot,
+) {
+    while let Some(vote) = vote_state.votes.back() {
+        if vote.lockout.is_locked_out_at_slot(slot) {
+            break;
+        }
+        vote_state.votes.pop_back();
+    }
+    if vote_state.votes.len() == MAX_LOCKOUT_HISTORY {
+        let rooted = vote_state.votes.pop_front().expect("the tower is full");
+        vote_state.root_slot = Some(rooted.slot());
+        increment_credits(vote_state, epoch, credits_for_vote(&rooted));
+    }
+    vote_state.votes.push_back(LandedVote {
+        latency: vote_latency(slot, current_slot),
+        lockout: Lockout::new(slot),
+    });
+    // Every vote with more votes stacked on it than its confirmations
+    // doubles its lockout.
+    let depth = vote_state.votes.len();
+    for (index, vote) in vote_state.votes.iter_mut().enumerate() {
+        if depth > index + vote.confirmation_count() as usize {
+            vote.lockout.increase_confirmation_count(1);
+        }
+    }
+}
+
+fn vote_latency(slot: Slot, current_slot: Slot) -> u8 {
+    current_slot.saturating_sub(slot).min(u8::MAX.into()) as u8
+}
+
+/// Timely vote credits: the maximum within the grace period after the
+/// slot, one less for every slot later, and never less than one. Votes
+/// without a latency earn a single credit.
+fn credits_for_vote(vote: &LandedVote) -> u64 {
+    if vote.latency == 0 {
+        return 1;
+    }
+    let late_by = vote.latency.saturating_sub(VOTE_CREDITS_GRACE_SLOTS);
+    VOTE_CREDITS_MAXIMUM_PER_SLOT
+        .saturating_sub(late_by)
+        .max(1)
+        .into()
+}
+
+/// Adds `credits` to the epoch's entry, opening an entry for a new epoch
+/// unless the last one earned nothing, in which case it is reused.
+fn increment_credits(vote_state: &mut VoteStateV3, epoch: Epoch, credits: u64) {
+    match vote_state.epoch_credits.last_mut() {
+        None => vote_state.epoch_credits.push((epoch, 0, 0)),
+        Some(last) if last.0 != epoch => {
+            let (_, credits, prev_credits) = *last;
+            if credits == prev_credits {
+                last.0 = epoch;
+            } else {
+                vote_state.epoch_credits.push((epoch, credits, credits));
+                if vote_state.epoch_credits.len() > MAX_EPOCH_CREDITS_HISTORY {
+                    vote_state.epoch_credits.remove(0);
+                }
+            }
+        }
+        Some(_) => {}
+    }
+    let last = vote_state
+        .epoch_credits
+        .last_mut()
+        .expect("an entry exists");
+    last.1 = last.1.saturating_add(credits);
+}
+
+/// Records the timestamp a vote reported for `slot`, which may not go back
+/// in slots or in time.
+fn process_timestamp(
+    vote_state: &mut VoteStateV3,
+    slot: Slot,
+    timestamp: UnixTimestamp,
+) -> Result<(), VoteError> {
+    let last = &vote_state.last_timestamp;
+    if slot < last.slot
+        || timestamp < last.timestamp
+        || (slot == last.slot && timestamp != last.timestamp && last.slot != 0)
+    {
+        return Err(VoteError::TimestampTooOld);
+    }
+    vote_state.last_timestamp = BlockTimestamp { slot, timestamp };
+    Ok(())
+}
+
+fn process_tower_sync(
+    invoke_context: &mut InvokeContext,
+    tower_sync: &TowerSync,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let mut vote_state = get_vote_state(invoke_context)?;
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    let clock = sysvar_cache.get_clock();
+    authorize_voter(&mut vote_state, clock.epoch, signers)?;
+    check_tower_sync(&vote_state, tower_sync, sysvar_cache.get_slot_hashes())
+        .map_err(into_instruction_error)?;
+
+    // Votes the new root covers are rooted, and earn their credits.
+    let earned: u64 = vote_state
+        .votes
+        .iter()
+        .filter(|vote| tower_sync.root.is_some_and(|root| vote.slot() <= root))
+        .map(credits_for_vote)
+        .sum();
+    let votes = tower_sync
+        .lockouts
+        .iter()
+        .map(|lockout| {
+            let latency = vote_state
+                .votes
+                .iter()
+                .find(|vote| vote.slot() == lockout.slot())
+                .map_or_else(
+                    || vote_latency(lockout.slot(), clock.slot),
+                    |vote| vote.latency,
+                );
+            LandedVote {
+                latency,
+                lockout: *lockout,
+            }
+        })
+        .collect();
+    if vote_state.root_slot != tower_sync.root {
+        increment_credits(&mut vote_state, clock.epoch, earned);
+    }
+    vote_state.votes = votes;
+    vote_state.root_slot = tower_sync.root;
+    if let Some(timestamp) = tower_sync.timestamp {
+        let slot = tower_sync.last_voted_slot().unwrap_or_default();
+        process_timestamp(&mut vote_state, slot, timestamp).map_err(into_instruction_error)?;
+    }
+    set_vote_state(invoke_context, vote_state)
+}
+
+/// Checks that `tower_sync` is a well formed tower that moves past the
+/// current one, and that its last slot has the hash it names.
+fn check_tower_sync(
+    vote_state: &VoteStateV3,
+    tower_sync: &TowerSync,
+    slot_hashes: &SlotHashes,
+) -> Result<(), VoteError> {
+    let lockouts = &tower_sync.lockouts;
+    let (Some(first), Some(last)) = (lockouts.front(), lockouts.back()) else {
+        return Err(VoteError::EmptySlots);
+    };
+    if lockouts.len() > MAX_LOCKOUT_HISTORY {
+        return Err(VoteError::TooManyVotes);
+    }
+    if last_voted_slot(vote_state).is_some_and(|slot| last.slot() <= slot) {
+        return Err(VoteError::VoteTooOld);
+    }
+    match (tower_sync.root, vote_state.root_slot) {
+        (Some(root), Some(current_root)) if root < current_root => {
+            return Err(VoteError::RootRollBack)
+        }
+        (None, Some(_)) => return Err(VoteError::RootRollBack),
+        _ => {}
+    }
+    if tower_sync.root.is_some_and(|root| first.slot() <= root) {
+        return Err(VoteError::SlotSmallerThanRoot);
+    }
+    for lockout in lockouts {
+        if lockout.confirmation_count() == 0 {
+            return Err(VoteError::ZeroConfirmations);
+        }
+        if lockout.confirmation_count() > MAX_LOCKOUT_HISTORY as u32 {
+            return Err(VoteError::ConfirmationTooLarge);
+        }
+    }
+    for pair in lockouts.iter().collect::<Vec<_>>().windows(2) {
+        if pair[0].slot() >= pair[1].slot() {
+            return Err(VoteError::SlotsNotOrdered);
+        }
+        if pair[0].confirmation_count() <= pair[1].confirmation_count() {
+            return Err(VoteError::ConfirmationsNotOrdered);
+        }
+    }
+    match slot_hashes.get(&last.slot()) {
+        None => Err(VoteError::SlotsMismatch),
+        Some(hash) if *hash != tower_sync.hash => Err(VoteError::SlotHashMismatch),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Moves `lamports` from the vote account to the account at index 1.
+///
+/// What stays behind must keep the account rent exempt, unless nothing
+/// does: then the account is closed, which a validator that earned credits
+/// in this or the previous epoch cannot do.
+fn process_withdraw(
+    invoke_context: &mut InvokeContext,
+    lamports: u64,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let vote_state = get_vote_state(invoke_context)?;
+    verify_signer(&vote_state.authorized_withdrawer, signers)?;
+    let account = invoke_context.get_account(0)?;
+    let remaining = account
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(InstructionError::InsufficientFunds)?;
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    if remaining == 0 {
+        let epoch = sysvar_cache.get_clock().epoch;
+        let active = vote_state
+            .epoch_credits
+            .last()
+            .is_some_and(|(last_epoch, _, _)| epoch.saturating_sub(*last_epoch) < 2);
+        if active {
+            return Err(into_instruction_error(VoteError::ActiveVoteAccountClose));
+        }
+        set_vote_state(invoke_context, VoteStateV3::default())?;
+    } else if remaining
+        < sysvar_cache
+            .get_rent()
+            .minimum_balance(account.data().len())
+    {
+        return Err(InstructionError::InsufficientFunds);
+    }
+
+    invoke_context.get_account_mut(0)?.set_lamports(remaining);
+    invoke_context
+        .get_account_mut(1)?
+        .checked_add_lamports(lamports)
+        .map_err(|_| InstructionError::ArithmeticOverflow)
+}
+
+/// Sets the commission. Raising it is only allowed in the first half of an
+/// epoch, so delegators can react before the epoch's rewards are paid.
+fn process_update_commission(
+    invoke_context: &mut InvokeContext,
+    commission: u8,
+    signers: &HashSet<Pubkey>,
+) -> Result<(), InstructionError> {
+    let mut vote_state = get_vote_state(invoke_context)?;
+    verify_signer(&vote_state.authorized_withdrawer, signers)?;
+    let sysvar_cache = invoke_context.get_sysvar_cache();
+    if commission > vote_state.commission
+        && !is_commission_update_allowed(
+            sysvar_cache.get_clock().slot,
+            sysvar_cache.get_epoch_schedule(),
+        )
+    {
+        return Err(into_instruction_error(VoteError::CommissionUpdateTooLate));
+    }
+    vote_state.commission = commission;
+    set_vote_state(invoke_context, vote_state)
+}
+
+/// Whether `slot` is in the first half of its epoch. Warmup epochs allow
+/// updates throughout.
+pub fn is_commission_update_allowed(slot: Slot, epoch_schedule: &EpochSchedule) -> bool {
+    if slot < epoch_schedule.first_normal_slot {
+        return true;
+    }
+    let relative_slot = (slot - epoch_schedule.first_normal_slot) % epoch_schedule.slots_per_epoch;
+    relative_slot.saturating_mul(2) <= epoch_schedule.slots_per_epoch
+}
@@ -0,0 +1,239 @@
+//! Synthetic transaction traffic.
+//!
+//! [`Workload::generate`] turns a [`WorkloadConfig`] into transactions for
+//! scheduler and executor benchmarks. Every transaction is paid by one of a
+//! fixed set of payers, sets a compute unit price drawn from the configured
+//! [`PriorityDistribution`] and transfers lamports from its payer to a
+//! number of destinations. Each destination is one of the hot accounts with
+//! the configured probability and a fresh account otherwise, so the ratio
+//! controls how much the transactions contend for write locks.
+//!
+//! Generation is deterministic: the same config always produces the same
+//! accounts and transactions.
+
+use {
+    crate::{
+        compute_budget::ComputeBudgetInstruction, sanitize::sanitize_transaction, system_program,
+    },
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    solana_account::AccountSharedData,
+    solana_message::{Message, SimpleAddressLoader},
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_sdk_ids::system_program as system_program_id,
+    solana_transaction::{
+        sanitized::SanitizedTransaction, versioned::VersionedTransaction, Transaction,
+    },
+};
+
+/// How compute unit prices, and so priorities, are spread over the
+/// transactions of a workload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PriorityDistribution {
+    /// Prices drawn uniformly from `min_cu_price..=max_cu_price`.
+    Uniform {
+        min_cu_price: u64,
+        max_cu_price: u64,
+    },
+    /// Prices `cu_price_step * rank` for ranks `1..=levels`, where rank `k`
+    /// is drawn with a probability proportional to `1 / k^exponent`: most
+    /// transactions pay little and a few pay a lot.
+    Zipf {
+        levels: u64,
+        exponent: f64,
+        cu_price_step: u64,
+    },
+}
+
+/// Shape of a synthetic workload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorkloadConfig {
+    pub num_transactions: usize,
+    pub num_payers: usize,
+    /// Probability, between 0 and 1, that a transfer goes to a hot account.
+    pub hot_account_ratio: f64,
+    pub num_hot_accounts: usize,
+    pub priority_distribution: PriorityDistribution,
+    /// Transfer instructions per transaction, which sets how large the
+    /// transactions are and how many accounts they lock.
+    pub transfers_per_transaction: usize,
+    pub seed: u64,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        Self {
+            num_transactions: 1_000,
+            num_payers: 100,
+            hot_account_ratio: 0.0,
+            num_hot_accounts: 1,
+            priority_distribution: PriorityDistribution::Uniform {
+                min_cu_price: 0,
+                max_cu_price: 1_000,
+            },
+            transfers_per_transaction: 1,
+            seed: 0,
+        }
+    }
+}
+
+/// Generated transactions with the accounts they pay from and contend on.
+#[derive(Clone, Debug)]
+pub struct Workload {
+    pub payers: Vec<Pubkey>,
+    pub hot_accounts: Vec<Pubkey>,
+    pub transactions: Vec<SanitizedTransaction>,
+}
+
+impl Workload {
+    /// Lamports every transfer of a workload moves: the rent-exempt
+    /// minimum of an account without data, so fresh destinations are
+    /// valid accounts afterwards.
+    pub fn transfer_lamports() -> u64 {
+        Rent::default().minimum_balance(0)
+    }
+
+    /// Generates the workload `config` describes.
+    ///
+    /// Transactions are unsigned; they are meant for the stages after
+    /// signature verification.
+    ///
+    /// # Panics
+    ///
+    /// If `config` has no payers, a hot account ratio outside `0..=1`, or a
+    /// positive ratio without hot accounts.
+    pub fn generate(config: &WorkloadConfig) -> Self {
+        assert!(config.num_payers > 0, "a workload needs payers");
+        assert!(
+            (0.0..=1.0).contains(&config.hot_account_ratio),
+            "the hot account ratio must be between 0 and 1"
+        );
+        assert!(
+            config.hot_account_ratio == 0.0 || config.num_hot_accounts > 0,
+            "a positive hot account ratio needs hot accounts"
+        );
+
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let payers: Vec<Pubkey> = (0..config.num_payers)
+            .map(|_| random_pubkey(&mut rng))
+            .collect();
+        let hot_accounts: Vec<Pubkey> = (0..config.num_hot_accounts)
+            .map(|_| random_pubkey(&mut rng))
+            .collect();
+        let priorities = PrioritySampler::new(&config.priority_distribution);
+
+        let transactions = (0..config.num_transactions)
+            .map(|_| {
+                let payer = payers[rng.gen_range(0..payers.len())];
+                let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_price(
+                    priorities.sample(&mut rng),
+                )];
+                for _ in 0..config.transfers_per_transaction {
+                    let destination = if rng.gen_bool(config.hot_account_ratio) {
+                        hot_accounts[rng.gen_range(0..hot_accounts.len())]
+                    } else {
+                        random_pubkey(&mut rng)
+                    };
+                    instructions.push(system_program::transfer(
+                        &payer,
+                        &destination,
+                        Self::transfer_lamports(),
+                    ));
+                }
+                let message = Message::new(&instructions, Some(&payer));
+                let transaction = VersionedTransaction::from(Transaction::new_unsigned(message));
+                sanitize_transaction(transaction, SimpleAddressLoader::Disabled)
+                    .expect("generated transactions are well formed")
+            })
+            .collect();
+
+        Self {
+            payers,
+            hot_accounts,
+            transactions,
+        }
+    }
+
+    /// System accounts for every payer holding `lamports`, ready to be
+    /// stored before the transactions execute.
+    pub fn payer_accounts(&self, lamports: u64) -> Vec<(Pubkey, AccountSharedData)> {
+        self.payers
+            .iter()
+            .map(|payer| {
+                (
+                    *payer,
+                    AccountSharedData::new(lamports, 0, &system_program_id::id()),
+                )
+            })
+            .collect()
+    }
+}
+
+fn random_pubkey(rng: &mut StdRng) -> Pubkey {
+    Pubkey::new_from_array(rng.gen())
+}
+
+/// Draws compute unit prices from a [`PriorityDistribution`].
+enum PrioritySampler {
+    Uniform {
+        min_cu_price: u64,
+        max_cu_price: u64,
+    },
+    /// Cumulative probabilities of the ranks, ending with 1.
+    Zipf { cdf: Vec<f64>, cu_price_step: u64 },
+}
+
+impl PrioritySampler {
+    fn new(distribution: &PriorityDistribution) -> Self {
+        match *distribution {
+            PriorityDistribution::Uniform {
+                min_cu_price,
+                max_cu_price,
+            } => {
+                assert!(min_cu_price <= max_cu_price, "the price range is empty");
+                Self::Uniform {
+                    min_cu_price,
+                    max_cu_price,
+                }
+            }
+            PriorityDistribution::Zipf {
+                levels,
+                exponent,
+                cu_price_step,
+            } => {
+                assert!(levels > 0, "a zipf distribution needs levels");
+                let weights: Vec<f64> = (1..=levels)
+                    .map(|rank| 1.0 / (rank as f64).powf(exponent))
+                    .collect();
+                let total: f64 = weights.iter().sum();
+                let mut cumulative = 0.0;
+                let cdf = weights
+                    .iter()
+                    .map(|weight| {
+                        cumulative += weight / total;
+                        cumulative
+                    })
+                    .collect();
+                Self::Zipf { cdf, cu_price_step }
+            }
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> u64 {
+        match self {
+            Self::Uniform {
+                min_cu_price,
+                max_cu_price,
+            } => rng.gen_range(*min_cu_price..=*max_cu_price),
+            Self::Zipf { cdf, cu_price_step } => {
+                let point: f64 = rng.gen();
+                // Rounding can leave the last cumulative probability a hair
+                // below 1, so clamp to the last rank.
+                let rank = cdf
+                    .partition_point(|&cumulative| cumulative < point)
+                    .min(cdf.len() - 1);
+                (rank as u64 + 1) * *cu_price_step
+            }
+        }
+    }
+}
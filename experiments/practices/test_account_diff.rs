@@ -0,0 +1,139 @@
+//! Unit test: Diff the accounts a transaction changed
+//!
+//! Analogy: When the head chef wants to know what an order really did, a
+//! stock clerk photographs every shelf the order used before and after it
+//! was cooked. Comparing the photos shows exactly which jars lost flour,
+//! which changed labels, and which were left untouched.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::svm::{diff_accounts, AccountDiff, TransactionExecutor};
+    use priority_graph_practice::system_program;
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::collections::HashMap;
+
+    fn transaction(instructions: &[Instruction], payer: &Pubkey) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn system_account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &system_program_id::id())
+    }
+
+    #[test]
+    fn test_diff_accounts() {
+        let unchanged = (Pubkey::new_unique(), system_account(10));
+        let before = AccountSharedData::new(50, 4, &system_program_id::id());
+        let mut after = before.clone();
+        after.set_lamports(20);
+        after.data_as_mut_slice()[0] = 1;
+        after.set_owner(Pubkey::new_unique());
+        after.set_executable(true);
+        let changed = Pubkey::new_unique();
+
+        let diffs = diff_accounts(
+            &[unchanged.clone(), (changed, before.clone())],
+            &[unchanged, (changed, after.clone())],
+        );
+        assert_eq!(
+            diffs,
+            vec![AccountDiff {
+                pubkey: changed,
+                before: before.clone(),
+                after: after.clone(),
+            }]
+        );
+        let diff = &diffs[0];
+        assert_eq!(diff.lamports_delta(), -30);
+        assert!(diff.data_changed() && diff.owner_changed() && diff.executable_changed());
+        assert_eq!(
+            diff.data_hash_before(),
+            solana_sha256_hasher::hash(&[0, 0, 0, 0])
+        );
+        assert_ne!(diff.data_hash_before(), diff.data_hash_after());
+
+        // Only the lamports moved.
+        let mut richer = before.clone();
+        richer.set_lamports(u64::MAX);
+        let diff = AccountDiff {
+            pubkey: changed,
+            before,
+            after: richer,
+        };
+        assert_eq!(diff.lamports_delta(), i128::from(u64::MAX - 50));
+        assert!(!diff.data_changed() && !diff.owner_changed() && !diff.executable_changed());
+        assert_eq!(diff.data_hash_before(), diff.data_hash_after());
+    }
+
+    #[test]
+    fn test_executor_records_account_diffs() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let store = HashMap::from([(alice, system_account(10_000_000))]);
+        let transfer = transaction(&[system_program::transfer(&alice, &bob, 1_000_000)], &alice);
+
+        let mut executor = TransactionExecutor::new();
+        assert!(!executor.record_account_diffs());
+        let result = executor.load_and_execute_transaction(&store, &transfer);
+        assert_eq!(result.account_diffs, None);
+
+        executor.set_record_account_diffs(true);
+        let result = executor.load_and_execute_transaction(&store, &transfer);
+        // The System program is loaded but never changes.
+        let diffs = result.account_diffs.unwrap();
+        let deltas: Vec<_> = diffs
+            .iter()
+            .map(|diff| (diff.pubkey, diff.lamports_delta()))
+            .collect();
+        assert_eq!(deltas, vec![(alice, -1_000_000), (bob, 1_000_000)]);
+        assert_eq!(diffs[1].before, AccountSharedData::default());
+
+        // A failed transaction changes nothing.
+        let overdraft = transaction(
+            &[system_program::transfer(&alice, &bob, 100_000_000)],
+            &alice,
+        );
+        let result = executor.load_and_execute_transaction(&store, &overdraft);
+        assert!(result.status.is_err());
+        assert_eq!(result.account_diffs, Some(vec![]));
+    }
+
+    #[test]
+    fn test_bank_diffs_include_fees() {
+        let alice = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.store_account(alice, system_account(1_000_000_000));
+        bank.set_record_account_diffs(true);
+        assert!(bank.record_account_diffs());
+
+        let assign = transaction(&[system_program::assign(&alice, &owner)], &alice);
+        let result = bank.process_transaction_batch(&[assign]).remove(0).unwrap();
+        let diffs = result.account_diffs.unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].lamports_delta(), -5_000);
+        assert!(diffs[0].owner_changed());
+        assert_eq!(diffs[0].before.lamports(), 1_000_000_000);
+
+        // A failed transaction still shows the fee it paid.
+        let bob = Pubkey::new_unique();
+        bank.store_account(bob, system_account(1_000_000));
+        let overdraft = transaction(&[system_program::transfer(&bob, &alice, 1_000_000)], &bob);
+        let result = bank
+            .process_transaction_batch(&[overdraft])
+            .remove(0)
+            .unwrap();
+        assert!(result.status.is_err());
+        let diffs = result.account_diffs.unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!((diffs[0].pubkey, diffs[0].lamports_delta()), (bob, -5_000));
+        assert!(!diffs[0].owner_changed());
+    }
+}
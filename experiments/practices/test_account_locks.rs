@@ -0,0 +1,136 @@
+//! Unit test: Turn away transactions whose accounts are locked already
+//!
+//! Analogy: However the expediter groups the orders, every cook still
+//! takes the ingredients he needs off the shelf before starting. Anyone
+//! may look at a jar someone else is looking at, but a jar somebody is
+//! cooking with cannot be touched by anybody else until it comes back.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts::{AccountLocks, LockSet, LockSetConfig},
+        bank::Bank,
+        scheduler::{ScheduleBatch, Scheduler},
+        system_program,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    /// Puts every transaction into a single batch, conflicts or not.
+    struct SingleBatchScheduler;
+
+    impl Scheduler for SingleBatchScheduler {
+        fn schedule(&mut self, batch: &[SanitizedTransaction]) -> Vec<ScheduleBatch> {
+            vec![ScheduleBatch {
+                transaction_indexes: (0..batch.len()).collect(),
+            }]
+        }
+    }
+
+    fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let message = Message::new(&[system_program::transfer(from, to, lamports)], Some(from));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_write_locks_are_exclusive() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut locks = AccountLocks::new();
+        let reads_a = LockSet::new(vec![b], vec![a]);
+        let also_reads_a = LockSet::new(vec![c], vec![a]);
+        assert_eq!(locks.try_lock(&reads_a), Ok(()));
+        assert_eq!(locks.try_lock(&also_reads_a), Ok(()));
+        assert!(locks.is_read_locked(&a) && locks.is_write_locked(&b));
+
+        // A conflicting lock set takes none of its locks.
+        let writes_a = LockSet::new(vec![a], vec![Pubkey::new_unique()]);
+        assert_eq!(
+            locks.try_lock(&writes_a),
+            Err(TransactionError::AccountInUse)
+        );
+        let reads_b = LockSet::new(vec![], vec![b]);
+        assert_eq!(
+            locks.try_lock(&reads_b),
+            Err(TransactionError::AccountInUse)
+        );
+        assert!(!locks.is_locked(&writes_a.readonly()[0]));
+
+        locks.unlock(&reads_a);
+        assert!(locks.is_read_locked(&a));
+        locks.unlock(&also_reads_a);
+        assert!(locks.is_empty());
+        assert_eq!(locks.try_lock(&writes_a), Ok(()));
+    }
+
+    #[test]
+    fn test_lock_accounts_of_transactions() {
+        let (alice, bob, carol) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let transactions = [
+            transfer(&alice, &bob, 1),
+            transfer(&bob, &carol, 1),
+            transfer(&carol, &Pubkey::new_unique(), 1),
+            transfer(&Pubkey::new_unique(), &Pubkey::new_unique(), 1),
+        ];
+        let config = LockSetConfig::default();
+        let mut locks = AccountLocks::new();
+        let results = locks.lock_accounts(&transactions, config);
+        assert_eq!(
+            results,
+            vec![Ok(()), Err(TransactionError::AccountInUse), Ok(()), Ok(())]
+        );
+        // The System program is only read locked, by all of them.
+        assert!(locks.is_read_locked(&solana_sdk_ids::system_program::id()));
+        assert!(locks.is_write_locked(&carol));
+
+        locks.unlock_accounts(&transactions, &results, config);
+        assert!(locks.is_empty());
+    }
+
+    #[test]
+    fn test_bank_rejects_conflicting_batch_members() {
+        let (alice, bob, carol) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let system_account =
+            || AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id());
+        let mut bank = Bank::default();
+        bank.store_account(alice, system_account());
+        bank.store_account(bob, system_account());
+
+        let transactions = [
+            transfer(&alice, &carol, 1_000_000),
+            transfer(&bob, &carol, 1_000_000),
+        ];
+        let results =
+            bank.process_transaction_batch_with_scheduler(&transactions, &mut SingleBatchScheduler);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(
+            results[1].as_ref().err(),
+            Some(&TransactionError::AccountInUse)
+        );
+        // The rejected transaction was not charged.
+        assert_eq!(bank.get_account(&bob).unwrap().lamports(), LAMPORTS);
+        assert_eq!(bank.scheduler_metrics(bank.slot()), None);
+
+        // The priority graph puts them into separate batches, and its
+        // locks are released after every batch.
+        let results = bank.process_transaction_batch(&transactions[1..]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(bank.get_account(&carol).unwrap().lamports(), 2_000_000);
+    }
+}
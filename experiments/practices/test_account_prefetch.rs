@@ -0,0 +1,127 @@
+//! Unit test: Prefetch the accounts of the next batch while one executes
+//!
+//! Analogy: While the cooks work through the current round of orders, a
+//! runner already fetches the ingredients for the next round from the
+//! cellar. Whatever the current round may still use up is left for the
+//! cooks to fetch themselves, so nobody cooks with a stale crate.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts::{AccountLoadMetrics, PrefetchedAccounts},
+        bank::Bank,
+        svm::AccountLoader,
+        system_program,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::collections::HashMap;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let message = Message::new(&[system_program::transfer(from, to, lamports)], Some(from));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn system_account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &solana_sdk_ids::system_program::id())
+    }
+
+    #[test]
+    fn test_prefetched_accounts_serve_loads() {
+        let (stored, missing, other) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let store = HashMap::from([(stored, system_account(7)), (other, system_account(9))]);
+        let mut prefetched = PrefetchedAccounts::load(&store, &[stored]);
+        prefetched.extend(PrefetchedAccounts::load(&store, &[missing]));
+        assert_eq!(prefetched.len(), 2);
+        assert!(prefetched.contains(&missing));
+        assert!(!prefetched.contains(&other));
+
+        let loader = prefetched.loader(&store);
+        assert_eq!(loader.load_account(&stored).unwrap().lamports(), 7);
+        // Accounts the store did not have are prefetched as missing.
+        assert_eq!(loader.load_account(&missing), None);
+        assert_eq!(loader.load_account(&other).unwrap().lamports(), 9);
+        let metrics = prefetched.metrics();
+        assert_eq!(
+            (
+                metrics.accounts_prefetched,
+                metrics.prefetch_hits,
+                metrics.prefetch_misses
+            ),
+            (2, 2, 1)
+        );
+        assert!((metrics.hit_percentage() - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(AccountLoadMetrics::default().hit_percentage(), 0.0);
+    }
+
+    #[test]
+    fn test_bank_reloads_accounts_an_earlier_batch_writes() {
+        let (alice, bob, carol, dave) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut bank = Bank::default();
+        bank.store_account(alice, system_account(LAMPORTS));
+        bank.store_account(carol, system_account(LAMPORTS));
+        assert_eq!(bank.account_load_metrics(bank.slot()), None);
+
+        // Bob only has lamports to spend once Alice's transfer landed, and
+        // Dave is only funded by the second batch.
+        let transactions = [
+            transfer(&alice, &bob, LAMPORTS / 2),
+            transfer(&bob, &dave, LAMPORTS / 4),
+            transfer(&carol, &dave, 1_000_000),
+        ];
+        let results = bank.process_transaction_batch(&transactions);
+        for result in &results {
+            assert!(result.as_ref().unwrap().was_successful());
+        }
+        let fee = results[0].as_ref().unwrap().fee_details.total_fee();
+        assert_eq!(
+            bank.get_account(&bob).unwrap().lamports(),
+            LAMPORTS / 2 - LAMPORTS / 4 - fee
+        );
+        assert_eq!(
+            bank.get_account(&dave).unwrap().lamports(),
+            LAMPORTS / 4 + 1_000_000
+        );
+
+        let metrics = *bank.account_load_metrics(bank.slot()).unwrap();
+        assert!(metrics.accounts_prefetched > 0);
+        assert!(metrics.prefetch_hits > 0);
+        // Bob and Dave were written before their second batch ran.
+        assert!(metrics.prefetch_misses >= 2);
+        assert!(metrics.blocking_prefetch_us <= metrics.prefetch_us);
+    }
+
+    #[test]
+    fn test_load_metrics_accumulate_per_slot() {
+        let payer = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.store_account(payer, system_account(LAMPORTS));
+        bank.process_transaction_batch(&[transfer(&payer, &Pubkey::new_unique(), 1_000_000)]);
+        let first = *bank.account_load_metrics(0).unwrap();
+        bank.process_transaction_batch(&[transfer(&payer, &Pubkey::new_unique(), 1_000_000)]);
+        let second = *bank.account_load_metrics(0).unwrap();
+        assert_eq!(
+            second.prefetch_hits + second.prefetch_misses,
+            2 * (first.prefetch_hits + first.prefetch_misses)
+        );
+
+        bank.advance_slot(solana_hash::Hash::new_unique());
+        assert_eq!(bank.account_load_metrics(1), None);
+        // Nothing executed, so nothing was loaded either.
+        bank.process_transaction_batch(&[]);
+        assert_eq!(bank.account_load_metrics(1), None);
+    }
+}
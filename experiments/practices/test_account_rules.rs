@@ -0,0 +1,230 @@
+//! Unit test: Enforce who may change which account
+//!
+//! Analogy: Every cook may look at any plate on the pass, but only the
+//! station that owns a plate may take food off it or rearrange it, a
+//! plate already sent out stays as it is, and at the end of every ticket
+//! the food on the pass must add up to what was there before.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts::reserved_account_keys,
+        svm::{InvokeContext, TransactionExecutor},
+        system_program,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    const DEBIT: u8 = 0;
+    const WRITE: u8 = 1;
+    const ASSIGN: u8 = 2;
+    const MINT: u8 = 3;
+    const FLIP_EXECUTABLE: u8 = 4;
+    const TRANSFER_VIA_SYSTEM: u8 = 5;
+
+    // A mock program that does to its first account what `data[0]` says,
+    // whether the runtime allows it or not.
+    fn meddle(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let program_id = *invoke_context.program_id();
+        match invoke_context.instruction_data().first() {
+            Some(&DEBIT) => {
+                invoke_context.get_account_mut(0)?.checked_sub_lamports(1)?;
+                invoke_context.get_account_mut(1)?.checked_add_lamports(1)?;
+            }
+            Some(&WRITE) => invoke_context.get_account_mut(0)?.data_as_mut_slice()[0] = 1,
+            Some(&ASSIGN) => invoke_context
+                .get_account_mut(0)?
+                .set_owner(Pubkey::new_unique()),
+            Some(&MINT) => invoke_context.get_account_mut(0)?.checked_add_lamports(1)?,
+            Some(&FLIP_EXECUTABLE) => {
+                let account = invoke_context.get_account_mut(0)?;
+                let executable = account.executable();
+                account.set_executable(!executable);
+            }
+            Some(&TRANSFER_VIA_SYSTEM) => {
+                let from = *invoke_context.get_key(0)?;
+                let to = *invoke_context.get_key(1)?;
+                invoke_context.invoke(&system_program::transfer(&from, &to, 1))?;
+                // What the System program debited is not held against the
+                // caller, but what the caller does next is.
+                if invoke_context.get_account(1)?.owner() != &program_id {
+                    invoke_context.get_account_mut(1)?.checked_sub_lamports(1)?;
+                    invoke_context.get_account_mut(0)?.checked_add_lamports(1)?;
+                }
+            }
+            _ => return Err(InstructionError::InvalidInstructionData),
+        }
+        Ok(())
+    }
+
+    struct Setup {
+        executor: TransactionExecutor,
+        store: HashMap<Pubkey, AccountSharedData>,
+        program_id: Pubkey,
+        lamports: u64,
+    }
+
+    impl Setup {
+        fn new() -> Self {
+            let program_id = Pubkey::new_unique();
+            let mut executor = TransactionExecutor::new();
+            executor.add_program(program_id, meddle);
+            Self {
+                executor,
+                store: HashMap::new(),
+                program_id,
+                lamports: Rent::default().minimum_balance(1) * 2,
+            }
+        }
+
+        fn account(&mut self, owner: &Pubkey, executable: bool) -> Pubkey {
+            let pubkey = Pubkey::new_unique();
+            let mut account = AccountSharedData::new(self.lamports, 1, owner);
+            account.set_executable(executable);
+            self.store.insert(pubkey, account);
+            pubkey
+        }
+
+        fn execute(&self, op: u8, accounts: Vec<AccountMeta>) -> Result<(), TransactionError> {
+            let instruction = Instruction::new_with_bytes(self.program_id, &[op], accounts);
+            let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+            let tx = SanitizedTransaction::try_from_legacy_transaction(
+                Transaction::new_unsigned(message),
+                reserved_account_keys(),
+            )
+            .unwrap();
+            self.executor
+                .load_and_execute_transaction(&self.store, &tx)
+                .status
+        }
+    }
+
+    fn failed(err: InstructionError) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(0, err))
+    }
+
+    #[test]
+    fn test_only_the_owner_debits_writes_and_assigns() {
+        let mut setup = Setup::new();
+        let program_id = setup.program_id;
+        let owned = setup.account(&program_id, false);
+        let foreign = setup.account(&Pubkey::new_unique(), false);
+        let recipient = setup.account(&Pubkey::new_unique(), false);
+        let metas = |pubkey| {
+            vec![
+                AccountMeta::new(pubkey, false),
+                AccountMeta::new(recipient, false),
+            ]
+        };
+
+        for op in [DEBIT, WRITE] {
+            assert_eq!(setup.execute(op, metas(owned)), Ok(()));
+        }
+        assert_eq!(
+            setup.execute(DEBIT, metas(foreign)),
+            failed(InstructionError::ExternalAccountLamportSpend)
+        );
+        assert_eq!(
+            setup.execute(WRITE, metas(foreign)),
+            failed(InstructionError::ExternalAccountDataModified)
+        );
+        assert_eq!(
+            setup.execute(ASSIGN, metas(foreign)),
+            failed(InstructionError::ModifiedProgramId)
+        );
+        // The owner may only hand an account over with its data zeroed.
+        assert_eq!(setup.execute(ASSIGN, metas(owned)), Ok(()));
+        setup
+            .store
+            .get_mut(&owned)
+            .unwrap()
+            .set_data_from_slice(&[7]);
+        assert_eq!(
+            setup.execute(ASSIGN, metas(owned)),
+            failed(InstructionError::ModifiedProgramId)
+        );
+    }
+
+    #[test]
+    fn test_executable_and_demoted_accounts_are_read_only() {
+        let mut setup = Setup::new();
+        let program_id = setup.program_id;
+        let executable = setup.account(&program_id, true);
+        let recipient = setup.account(&program_id, false);
+        let metas = |pubkey| {
+            vec![
+                AccountMeta::new(pubkey, false),
+                AccountMeta::new(recipient, false),
+            ]
+        };
+
+        assert_eq!(
+            setup.execute(DEBIT, metas(executable)),
+            failed(InstructionError::ExecutableLamportChange)
+        );
+        assert_eq!(
+            setup.execute(WRITE, metas(executable)),
+            failed(InstructionError::ExecutableDataModified)
+        );
+        assert_eq!(
+            setup.execute(FLIP_EXECUTABLE, metas(executable)),
+            failed(InstructionError::ExecutableModified)
+        );
+        assert_eq!(setup.execute(FLIP_EXECUTABLE, metas(recipient)), Ok(()));
+
+        // Sysvars are demoted to read-only even if listed as writable.
+        let clock = solana_sdk_ids::sysvar::clock::id();
+        setup.store.insert(
+            clock,
+            AccountSharedData::new(setup.lamports, 1, &solana_sdk_ids::sysvar::id()),
+        );
+        assert_eq!(
+            setup.execute(WRITE, metas(clock)),
+            failed(InstructionError::ReadonlyDataModified)
+        );
+    }
+
+    #[test]
+    fn test_lamports_add_up_across_invocations() {
+        let mut setup = Setup::new();
+        let program_id = setup.program_id;
+        let owned = setup.account(&program_id, false);
+        assert_eq!(
+            setup.execute(MINT, vec![AccountMeta::new(owned, false)]),
+            failed(InstructionError::UnbalancedInstruction)
+        );
+
+        let system_program_id = solana_sdk_ids::system_program::id();
+        let [from, to] = [(); 2].map(|()| {
+            let pubkey = Pubkey::new_unique();
+            let account = AccountSharedData::new(setup.lamports, 0, &system_program_id);
+            setup.store.insert(pubkey, account);
+            pubkey
+        });
+        let metas = |from, to| {
+            vec![
+                AccountMeta::new(from, true),
+                AccountMeta::new(to, false),
+                AccountMeta::new_readonly(system_program_id, false),
+            ]
+        };
+        // The System program may debit `from` for the caller, but the caller
+        // may not debit `to` itself.
+        assert_eq!(
+            setup.execute(TRANSFER_VIA_SYSTEM, metas(from, to)),
+            failed(InstructionError::ExternalAccountLamportSpend)
+        );
+        let owned_to = setup.account(&program_id, false);
+        assert_eq!(
+            setup.execute(TRANSFER_VIA_SYSTEM, metas(from, owned_to)),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Unit test: Keep account state in `AccountsDb` and roll back failed writes
+//!
+//! Analogy: The host keeps a ledger of every table's bill. Before a party
+//! orders, the host bookmarks the page; if the party walks out without
+//! paying, the host tears out everything written since the bookmark.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{accounts_db::AccountsDb, svm::AccountLoader};
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_pubkey::Pubkey;
+
+    fn account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &Pubkey::default())
+    }
+
+    fn lamports(accounts_db: &AccountsDb, pubkey: &Pubkey) -> Option<u64> {
+        accounts_db
+            .get_account(pubkey)
+            .map(|account| account.lamports())
+    }
+
+    #[test]
+    fn test_store_and_get_account() {
+        let mut accounts_db = AccountsDb::new();
+        let pubkey = Pubkey::new_unique();
+        assert_eq!(accounts_db.get_account(&pubkey), None);
+
+        accounts_db.store_account(pubkey, account(10));
+        assert_eq!(lamports(&accounts_db, &pubkey), Some(10));
+        assert_eq!(accounts_db.load_account(&pubkey), Some(account(10)));
+
+        // Draining an account to zero lamports closes it.
+        accounts_db.store_account(pubkey, account(0));
+        assert_eq!(accounts_db.get_account(&pubkey), None);
+        assert_eq!(accounts_db.num_accounts(), 0);
+    }
+
+    #[test]
+    fn test_rollback_discards_writes() {
+        let mut accounts_db = AccountsDb::new();
+        let existing = Pubkey::new_unique();
+        let created = Pubkey::new_unique();
+        accounts_db.store_account(existing, account(10));
+
+        let snapshot = accounts_db.snapshot();
+        accounts_db.store_account(existing, account(5));
+        accounts_db.store_account(existing, account(1));
+        accounts_db.store_account(created, account(7));
+        assert_eq!(lamports(&accounts_db, &existing), Some(1));
+
+        accounts_db.rollback(snapshot);
+        assert_eq!(lamports(&accounts_db, &existing), Some(10));
+        assert_eq!(accounts_db.get_account(&created), None);
+        assert_eq!(accounts_db.num_snapshots(), 0);
+    }
+
+    #[test]
+    fn test_nested_snapshots() {
+        let mut accounts_db = AccountsDb::new();
+        let pubkey = Pubkey::new_unique();
+        accounts_db.store_account(pubkey, account(10));
+
+        let outer = accounts_db.snapshot();
+        accounts_db.store_account(pubkey, account(20));
+
+        // Releasing the inner snapshot keeps its write...
+        let inner = accounts_db.snapshot();
+        accounts_db.store_account(pubkey, account(30));
+        accounts_db.release(inner);
+        assert_eq!(lamports(&accounts_db, &pubkey), Some(30));
+
+        // ...but the outer snapshot can still undo it.
+        let inner = accounts_db.snapshot();
+        accounts_db.store_account(pubkey, account(40));
+        accounts_db.rollback(inner);
+        assert_eq!(lamports(&accounts_db, &pubkey), Some(30));
+
+        accounts_db.rollback(outer);
+        assert_eq!(lamports(&accounts_db, &pubkey), Some(10));
+    }
+}
@@ -0,0 +1,124 @@
+//! Unit test: Save `AccountsDb` to a snapshot file and load it back
+//!
+//! Analogy: At closing time the host copies the whole bill ledger into a
+//! binder and puts it on the shelf. The next morning nobody has to ask every
+//! regular what they owe again; the binder is opened and the ledger is back
+//! exactly as it was, as long as the binder is really one of the host's.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::accounts_db::{AccountsDb, SnapshotError};
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_pubkey::Pubkey;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "accounts-snapshot-{name}-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ))
+    }
+
+    fn sorted_accounts(accounts_db: &AccountsDb) -> Vec<(Pubkey, AccountSharedData)> {
+        let mut accounts: Vec<_> = accounts_db.iter().collect();
+        accounts.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+        accounts
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let path = temp_path("round-trip");
+        let mut accounts_db = AccountsDb::new();
+        for lamports in 1..=100 {
+            accounts_db.store_account(
+                Pubkey::new_unique(),
+                AccountSharedData::new(lamports, lamports as usize, &Pubkey::new_unique()),
+            );
+        }
+        let program = Pubkey::new_unique();
+        let mut account = AccountSharedData::new(5, 0, &Pubkey::new_unique());
+        account.set_data_from_slice(b"program bytes");
+        account.set_executable(true);
+        account.set_rent_epoch(u64::MAX);
+        accounts_db.store_account(program, account);
+
+        accounts_db.snapshot_to_file(&path).unwrap();
+        let loaded = AccountsDb::load_from_snapshot(&path).unwrap();
+        assert_eq!(loaded.num_accounts(), 101);
+        assert_eq!(loaded.num_snapshots(), 0);
+        assert_eq!(sorted_accounts(&loaded), sorted_accounts(&accounts_db));
+        let loaded_program = loaded.get_account(&program).unwrap();
+        assert!(loaded_program.executable());
+        assert_eq!(loaded_program.data(), b"program bytes");
+        assert_eq!(loaded_program.rent_epoch(), u64::MAX);
+
+        // The same state always writes the same bytes.
+        let again = temp_path("again");
+        loaded.snapshot_to_file(&again).unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            std::fs::read(&again).unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&again).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_saves_current_state_only() {
+        let path = temp_path("current");
+        let kept = Pubkey::new_unique();
+        let pending = Pubkey::new_unique();
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(kept, AccountSharedData::new(10, 0, &Pubkey::default()));
+        let snapshot = accounts_db.snapshot();
+        accounts_db.store_account(pending, AccountSharedData::new(20, 0, &Pubkey::default()));
+
+        accounts_db.snapshot_to_file(&path).unwrap();
+        accounts_db.rollback(snapshot);
+        let loaded = AccountsDb::load_from_snapshot(&path).unwrap();
+        assert_eq!(loaded.num_snapshots(), 0);
+        assert_eq!(loaded.get_account(&pending).unwrap().lamports(), 20);
+        assert_eq!(accounts_db.get_account(&pending), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_files() {
+        let path = temp_path("invalid");
+        assert!(matches!(
+            AccountsDb::load_from_snapshot(&path),
+            Err(SnapshotError::Io(_))
+        ));
+
+        std::fs::write(&path, b"SVM").unwrap();
+        assert!(matches!(
+            AccountsDb::load_from_snapshot(&path),
+            Err(SnapshotError::InvalidHeader)
+        ));
+
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(
+            Pubkey::new_unique(),
+            AccountSharedData::new(10, 64, &Pubkey::default()),
+        );
+        accounts_db.snapshot_to_file(&path).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+
+        let mut future = bytes.clone();
+        future[7] = 2;
+        std::fs::write(&path, &future).unwrap();
+        assert!(matches!(
+            AccountsDb::load_from_snapshot(&path),
+            Err(SnapshotError::UnsupportedVersion(2))
+        ));
+
+        bytes.truncate(bytes.len() - 10);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            AccountsDb::load_from_snapshot(&path),
+            Err(SnapshotError::Corrupted)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
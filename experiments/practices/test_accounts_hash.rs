@@ -0,0 +1,164 @@
+//! Unit test: Hash the committed account state, in full and slot by slot
+//!
+//! Analogy: Every evening the host adds up one fingerprint per table's
+//! bill into a running total. Rather than fingerprinting every bill again,
+//! the host subtracts the fingerprints of the bills that changed during
+//! the day and adds their new ones; two restaurants with the same bills end
+//! up with the same total, no matter in which order they were written.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::accounts_db::AccountsDb;
+    use priority_graph_practice::accounts_hash::{
+        account_lt_hash, calculate_accounts_delta_hash, calculate_accounts_delta_lt_hash,
+        calculate_accounts_lt_hash, LtHash,
+    };
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::system_program;
+    use solana_account::AccountSharedData;
+    use solana_hash::Hash;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let instruction = system_program::transfer(from, to, lamports);
+        let message = Message::new(&[instruction], Some(from));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn system_account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &system_program_id::id())
+    }
+
+    #[test]
+    fn test_lt_hash_mixes_order_independently() {
+        let accounts: Vec<_> = (1..=4)
+            .map(|lamports| (Pubkey::new_unique(), system_account(lamports)))
+            .collect();
+        let mut forward = LtHash::identity();
+        for (pubkey, account) in &accounts {
+            forward.mix_in(&account_lt_hash(pubkey, account));
+        }
+        let mut backward = LtHash::identity();
+        for (pubkey, account) in accounts.iter().rev() {
+            backward.mix_in(&account_lt_hash(pubkey, account));
+        }
+        assert_eq!(forward, backward);
+        assert_eq!(forward.checksum(), backward.checksum());
+
+        for (pubkey, account) in &accounts {
+            forward.mix_out(&account_lt_hash(pubkey, account));
+        }
+        assert!(forward.is_identity());
+
+        // Zero lamports means the account does not exist; any other field
+        // changes the hash.
+        let (pubkey, account) = &accounts[0];
+        assert!(account_lt_hash(pubkey, &system_account(0)).is_identity());
+        let mut changed = account.clone();
+        changed.set_data_from_slice(&[1]);
+        assert_ne!(
+            account_lt_hash(pubkey, account),
+            account_lt_hash(pubkey, &changed)
+        );
+        assert_ne!(
+            account_lt_hash(pubkey, account),
+            account_lt_hash(&Pubkey::new_unique(), account)
+        );
+    }
+
+    #[test]
+    fn test_incremental_lt_hash_matches_full_calculation() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(alice, system_account(LAMPORTS));
+        accounts_db.store_account(bob, system_account(LAMPORTS));
+        let mut bank = Bank::new(accounts_db);
+        let genesis_lt_hash = bank.accounts_lt_hash();
+        assert_eq!(
+            genesis_lt_hash,
+            calculate_accounts_lt_hash(bank.accounts_db())
+        );
+
+        for slot in 1..=3u64 {
+            let carol = Pubkey::new_unique();
+            let results = bank.process_transaction_batch(&[
+                transfer(&alice, &carol, 1_000_000 * slot),
+                transfer(&bob, &alice, 2_000_000 * slot),
+            ]);
+            assert!(results
+                .iter()
+                .all(|result| result.as_ref().unwrap().was_successful()));
+            assert_eq!(
+                bank.accounts_lt_hash(),
+                calculate_accounts_lt_hash(bank.accounts_db())
+            );
+            bank.advance_slot(Hash::new_unique());
+            assert_eq!(
+                bank.accounts_lt_hash(),
+                calculate_accounts_lt_hash(bank.accounts_db())
+            );
+        }
+        // Closing an account mixes it out entirely.
+        bank.store_account(bob, system_account(0));
+        assert_eq!(
+            bank.accounts_lt_hash(),
+            calculate_accounts_lt_hash(bank.accounts_db())
+        );
+        assert_ne!(bank.accounts_lt_hash(), genesis_lt_hash);
+    }
+
+    #[test]
+    fn test_delta_hashes_cover_the_slot_writes() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let untouched = Pubkey::new_unique();
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(alice, system_account(10));
+        accounts_db.store_account(untouched, system_account(20));
+        accounts_db.checkpoint();
+        let empty_delta_hash = calculate_accounts_delta_hash(&accounts_db);
+        assert!(calculate_accounts_delta_lt_hash(&accounts_db).is_identity());
+
+        // Writes that end where they started leave the delta unchanged.
+        let snapshot = accounts_db.snapshot();
+        accounts_db.store_account(alice, system_account(11));
+        accounts_db.rollback(snapshot);
+        assert_eq!(
+            calculate_accounts_delta_hash(&accounts_db),
+            empty_delta_hash
+        );
+        assert!(calculate_accounts_delta_lt_hash(&accounts_db).is_identity());
+
+        accounts_db.store_account(alice, system_account(5));
+        accounts_db.store_account(bob, system_account(5));
+        let delta_hash = calculate_accounts_delta_hash(&accounts_db);
+        assert_ne!(delta_hash, empty_delta_hash);
+        let mut expected = LtHash::identity();
+        expected.mix_out(&account_lt_hash(&alice, &system_account(10)));
+        expected.mix_in(&account_lt_hash(&alice, &system_account(5)));
+        expected.mix_in(&account_lt_hash(&bob, &system_account(5)));
+        assert_eq!(calculate_accounts_delta_lt_hash(&accounts_db), expected);
+
+        // The same writes without the untouched account give the
+        // same delta hash, in whichever order they were stored.
+        let mut other = AccountsDb::new();
+        other.store_account(alice, system_account(10));
+        other.checkpoint();
+        other.store_account(bob, system_account(5));
+        other.store_account(alice, system_account(5));
+        assert_eq!(calculate_accounts_delta_hash(&other), delta_hash);
+
+        let mut bank = Bank::new(accounts_db);
+        assert_eq!(bank.accounts_delta_hash(), empty_delta_hash);
+        bank.store_account(bob, system_account(6));
+        assert_ne!(bank.accounts_delta_hash(), empty_delta_hash);
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(bank.accounts_delta_hash(), empty_delta_hash);
+    }
+}
@@ -0,0 +1,150 @@
+//! Unit test: Keep the accounts in a pluggable storage backend
+//!
+//! Analogy: Whether the ledger is a notebook behind the counter or a
+//! filing cabinet in the cellar, the host looks up tables, crosses out
+//! orders and tears out a page of mistakes the same way; only how fast a
+//! page comes to hand differs.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts_db::AccountsDb,
+        accounts_storage::{AccountsStorage, InMemoryStorage, ShardedStorage},
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_pubkey::Pubkey;
+
+    /// Runs the store through writes, closes, rollbacks and forks, which
+    /// every storage has to handle alike.
+    fn check_store(storage: Box<dyn AccountsStorage>) {
+        let program_id = Pubkey::new_unique();
+        let [first, second] = [(); 2].map(|()| Pubkey::new_unique());
+        let mut accounts_db = AccountsDb::with_storage(storage);
+        accounts_db.store_account(first, AccountSharedData::new(1, 0, &program_id));
+        accounts_db.store_account(second, AccountSharedData::new(2, 4, &program_id));
+        accounts_db.store_account(first, AccountSharedData::new(3, 0, &program_id));
+        assert_eq!(accounts_db.num_accounts(), 2);
+        assert_eq!(accounts_db.get_account(&first).unwrap().lamports(), 3);
+        assert_eq!(accounts_db.get_program_accounts(&program_id).len(), 2);
+
+        let snapshot = accounts_db.snapshot();
+        accounts_db.store_account(second, AccountSharedData::new(0, 0, &program_id));
+        assert!(accounts_db.get_account(&second).is_none());
+        assert_eq!(accounts_db.num_accounts(), 1);
+        accounts_db.rollback(snapshot);
+        assert_eq!(accounts_db.get_account(&second).unwrap().data(), [0; 4]);
+
+        // A fork gets accounts of its own.
+        let mut fork = accounts_db.clone();
+        fork.store_account(first, AccountSharedData::new(9, 0, &program_id));
+        assert_eq!(accounts_db.get_account(&first).unwrap().lamports(), 3);
+        assert_eq!(fork.get_account(&first).unwrap().lamports(), 9);
+        let mut lamports: Vec<u64> = fork.iter().map(|(_, account)| account.lamports()).collect();
+        lamports.sort_unstable();
+        assert_eq!(lamports, [2, 9]);
+    }
+
+    #[test]
+    fn test_in_memory_storage() {
+        check_store(Box::new(InMemoryStorage::new()));
+    }
+
+    #[test]
+    fn test_sharded_storage() {
+        check_store(Box::new(ShardedStorage::default()));
+        check_store(Box::new(ShardedStorage::new(1)));
+    }
+
+    #[test]
+    fn test_sharded_storage_takes_concurrent_commits() {
+        let storage = ShardedStorage::new(8);
+        let owner = Pubkey::new_unique();
+        let pubkeys: Vec<Vec<Pubkey>> = (0..16)
+            .map(|_| (0..100).map(|_| Pubkey::new_unique()).collect())
+            .collect();
+        std::thread::scope(|scope| {
+            for pubkeys in &pubkeys {
+                let storage = &storage;
+                scope.spawn(move || {
+                    for pair in pubkeys.chunks(2) {
+                        storage.commit(
+                            pair.iter()
+                                .map(|pubkey| (*pubkey, AccountSharedData::new(1, 0, &owner))),
+                        );
+                    }
+                    // Closing is a commit like any other.
+                    storage.commit([(pubkeys[0], AccountSharedData::new(0, 0, &owner))]);
+                });
+            }
+        });
+        assert_eq!(storage.len(), 16 * 99);
+        for pubkeys in &pubkeys {
+            assert!(storage.load(&pubkeys[0]).is_none());
+            assert!(pubkeys[1..]
+                .iter()
+                .all(|pubkey| storage.load(pubkey).is_some()));
+        }
+
+        // Shards go by the pubkey's first two bytes, and the last write to
+        // an account in a commit wins.
+        let mut prefix = [0; 32];
+        prefix[1] = 9;
+        let pubkey = Pubkey::new_from_array(prefix);
+        assert_eq!(storage.shard_index(&pubkey), 1);
+        storage.commit([
+            (pubkey, AccountSharedData::new(1, 0, &owner)),
+            (pubkey, AccountSharedData::new(2, 0, &owner)),
+        ]);
+        assert_eq!(storage.load(&pubkey).unwrap().lamports(), 2);
+    }
+
+    #[test]
+    fn test_accounts_already_stored_are_indexed() {
+        let program_id = Pubkey::new_unique();
+        let pubkey = Pubkey::new_unique();
+        let mut storage = InMemoryStorage::new();
+        storage.insert(pubkey, AccountSharedData::new(1, 0, &program_id));
+        let accounts_db = AccountsDb::with_storage(Box::new(storage));
+        assert_eq!(
+            accounts_db.get_program_accounts(&program_id),
+            [(pubkey, AccountSharedData::new(1, 0, &program_id))]
+        );
+        assert_eq!(accounts_db.written_accounts().count(), 0);
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_storage() {
+        use {
+            priority_graph_practice::{
+                accounts_hash::calculate_accounts_lt_hash, accounts_storage::SledStorage,
+                bank::Bank,
+            },
+            solana_hash::Hash,
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "accounts-sled-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        check_store(Box::new(SledStorage::open(&path).unwrap()));
+        assert!(!path.exists());
+
+        // Banks on either storage end up with the same accounts.
+        let [on_disk, in_memory] = [
+            AccountsDb::with_storage(Box::new(SledStorage::open(&path).unwrap())),
+            AccountsDb::new(),
+        ]
+        .map(|accounts_db| {
+            let mut bank = Bank::new(accounts_db);
+            bank.store_account(
+                Pubkey::new_from_array([1; 32]),
+                AccountSharedData::new(5, 0, &Pubkey::default()),
+            );
+            bank.advance_slot(Hash::default());
+            calculate_accounts_lt_hash(bank.accounts_db())
+        });
+        assert_eq!(on_disk, in_memory);
+    }
+}
@@ -0,0 +1,160 @@
+//! Unit test: Resolve address lookup tables for v0 transactions
+//!
+//! Analogy: Regulars don't spell out every table they want; they say "my
+//! usual, numbers 0 and 2" and the host looks the numbers up in the
+//! regulars' book before seating them.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts::{LockSet, LockSetConfig},
+        address_lookup_table::AccountsDbAddressLoader,
+        bank::Bank,
+        svm::InvokeContext,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_address_lookup_table_interface::state::{AddressLookupTable, LookupTableMeta};
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::{
+        v0::{self, MessageAddressTableLookup},
+        AddressLoader, AddressLookupTableAccount, VersionedMessage,
+    };
+    use solana_pubkey::Pubkey;
+    use solana_signature::Signature;
+    use solana_transaction::versioned::VersionedTransaction;
+    use solana_transaction_error::{AddressLoaderError, TransactionError};
+    use std::borrow::Cow;
+
+    fn lookup_table_account(addresses: &[Pubkey]) -> AccountSharedData {
+        let lookup_table = AddressLookupTable {
+            meta: LookupTableMeta::new(Pubkey::new_unique()),
+            addresses: Cow::Borrowed(addresses),
+        };
+        let mut account = AccountSharedData::new(1, 0, &solana_sdk_ids::address_lookup_table::id());
+        account.set_data_from_slice(&lookup_table.serialize_for_tests().unwrap());
+        account
+    }
+
+    fn v0_transaction(
+        payer: &Pubkey,
+        instruction: Instruction,
+        lookup_table: AddressLookupTableAccount,
+    ) -> VersionedTransaction {
+        let message =
+            v0::Message::try_compile(payer, &[instruction], &[lookup_table], Default::default())
+                .unwrap();
+        VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::V0(message),
+        }
+    }
+
+    // A mock program that sets the first data byte of its first account.
+    fn set_flag(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        invoke_context
+            .get_account_mut(0)?
+            .data_as_mut_slice()
+            .first_mut()
+            .map(|flag| *flag = 1)
+            .ok_or(InstructionError::AccountDataTooSmall)
+    }
+
+    #[test]
+    fn test_looked_up_accounts_are_locked_and_executed() {
+        let program_id = Pubkey::new_unique();
+        let table_key = Pubkey::new_unique();
+        let flag = Pubkey::new_unique();
+        let reader = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.add_builtin(program_id, set_flag);
+        bank.store_account(table_key, lookup_table_account(&[reader, flag]));
+        bank.store_account(flag, AccountSharedData::new(1, 1, &program_id));
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        // Addresses added to a table only resolve from the next slot on.
+        bank.advance_slot(Hash::new_unique());
+
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![
+                AccountMeta::new(flag, false),
+                AccountMeta::new_readonly(reader, false),
+            ],
+        );
+        let lookup_table = AddressLookupTableAccount {
+            key: table_key,
+            addresses: vec![reader, flag],
+        };
+        let tx = bank
+            .resolve_transaction(v0_transaction(&payer, instruction, lookup_table))
+            .unwrap();
+
+        // Both accounts only appear through the table, yet they are locked...
+        let lock_set = LockSet::from_transaction(&tx, LockSetConfig::default());
+        assert!(lock_set.writable().contains(&flag));
+        assert!(lock_set.readonly().contains(&reader));
+
+        // ...and the program gets to write the looked up account.
+        let results = bank.process_transaction_batch(&[tx]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(bank.get_account(&flag).unwrap().data(), &[1]);
+    }
+
+    #[test]
+    fn test_lookup_errors() {
+        let table_key = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.store_account(table_key, lookup_table_account(&[Pubkey::new_unique()]));
+        bank.advance_slot(Hash::new_unique());
+        let loader = AccountsDbAddressLoader::new(bank.accounts_db(), bank.slot());
+
+        let lookup = |account_key, index| MessageAddressTableLookup {
+            account_key,
+            writable_indexes: vec![index],
+            readonly_indexes: vec![],
+        };
+        assert_eq!(
+            loader.load_addresses(&[lookup(Pubkey::new_unique(), 0)]),
+            Err(AddressLoaderError::LookupTableAccountNotFound)
+        );
+        assert_eq!(
+            loader.load_addresses(&[lookup(table_key, 1)]),
+            Err(AddressLoaderError::InvalidLookupIndex)
+        );
+
+        // Only accounts owned by the lookup table program are tables.
+        let impostor = Pubkey::new_unique();
+        bank.store_account(
+            impostor,
+            AccountSharedData::new(1, 0, &Pubkey::new_unique()),
+        );
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(Pubkey::new_unique(), false)],
+        );
+        let lookup_table = AddressLookupTableAccount {
+            key: impostor,
+            addresses: instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect(),
+        };
+        assert_eq!(
+            bank.resolve_transaction(v0_transaction(
+                &Pubkey::new_unique(),
+                instruction,
+                lookup_table
+            ))
+            .unwrap_err(),
+            TransactionError::InvalidAddressLookupTableOwner
+        );
+    }
+}
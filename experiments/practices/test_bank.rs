@@ -0,0 +1,163 @@
+//! Unit test: Process transaction batches with `Bank`
+//!
+//! Analogy: The bank is the restaurant for one evening. It turns away
+//! reservations made too long ago, lets the host seat the rest, and writes
+//! every served order into the ledger before the next round is seated.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::{Bank, BlockhashQueue},
+        svm::InvokeContext,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_clock::MAX_PROCESSING_AGE;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    // A mock program that moves `data[0]` lamports from its first account to
+    // its second one.
+    fn transfer(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let amount = u64::from(
+            *invoke_context
+                .instruction_data()
+                .first()
+                .ok_or(InstructionError::InvalidInstructionData)?,
+        );
+        invoke_context
+            .get_account_mut(0)?
+            .checked_sub_lamports(amount)?;
+        invoke_context
+            .get_account_mut(1)?
+            .checked_add_lamports(amount)?;
+        Ok(())
+    }
+
+    // Stores a System account that can pay the fees of many transactions.
+    fn fee_payer(bank: &mut Bank) -> Pubkey {
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        payer
+    }
+
+    fn transaction(
+        payer: &Pubkey,
+        program_id: Pubkey,
+        from: Pubkey,
+        to: Pubkey,
+        amount: u8,
+        recent_blockhash: Hash,
+    ) -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[amount],
+            vec![AccountMeta::new(from, false), AccountMeta::new(to, false)],
+        );
+        let message = Message::new_with_blockhash(&[instruction], Some(payer), &recent_blockhash);
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_process_transaction_batch_sees_earlier_writes() {
+        let program_id = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let carol = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        // Free rent lets the accounts hold only a few lamports.
+        bank.set_rent(Rent::free());
+        bank.add_builtin(program_id, transfer);
+        bank.store_account(alice, AccountSharedData::new(10, 0, &program_id));
+        // The program can only debit accounts it owns.
+        bank.store_account(bob, AccountSharedData::new(1, 0, &program_id));
+        let payer = fee_payer(&mut bank);
+
+        // Bob can only pay Carol once Alice's transfer to him has committed.
+        let blockhash = bank.last_blockhash();
+        let transactions = vec![
+            transaction(&payer, program_id, alice, bob, 7, blockhash),
+            transaction(&payer, program_id, bob, carol, 5, blockhash),
+        ];
+        let results = bank.process_transaction_batch(&transactions);
+        assert!(results
+            .iter()
+            .all(|result| result.as_ref().unwrap().was_successful()));
+
+        assert_eq!(bank.get_account(&alice).unwrap().lamports(), 3);
+        assert_eq!(bank.get_account(&bob).unwrap().lamports(), 3);
+        assert_eq!(bank.get_account(&carol).unwrap().lamports(), 5);
+    }
+
+    #[test]
+    fn test_process_transaction_batch_rejects_old_blockhash() {
+        let program_id = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.set_rent(Rent::free());
+        bank.add_builtin(program_id, transfer);
+        bank.store_account(alice, AccountSharedData::new(10, 0, &program_id));
+        let payer = fee_payer(&mut bank);
+
+        let genesis_blockhash = bank.last_blockhash();
+        for _ in 0..=MAX_PROCESSING_AGE {
+            bank.advance_slot(Hash::new_unique());
+        }
+        assert_eq!(bank.slot(), MAX_PROCESSING_AGE as u64 + 1);
+
+        let transactions = vec![
+            transaction(
+                &payer,
+                program_id,
+                alice,
+                Pubkey::new_unique(),
+                1,
+                genesis_blockhash,
+            ),
+            transaction(
+                &payer,
+                program_id,
+                alice,
+                Pubkey::new_unique(),
+                1,
+                Hash::new_unique(),
+            ),
+            transaction(
+                &payer,
+                program_id,
+                alice,
+                Pubkey::new_unique(),
+                1,
+                bank.last_blockhash(),
+            ),
+        ];
+        let results = bank.process_transaction_batch(&transactions);
+        assert_eq!(results[0], Err(TransactionError::BlockhashNotFound));
+        assert_eq!(results[1], Err(TransactionError::BlockhashNotFound));
+        assert!(results[2].as_ref().unwrap().was_successful());
+        assert_eq!(bank.get_account(&alice).unwrap().lamports(), 9);
+    }
+
+    #[test]
+    fn test_blockhash_queue_evicts_old_hashes() {
+        let mut queue = BlockhashQueue::new(2);
+        let hashes: Vec<_> = (0..4).map(|_| Hash::new_unique()).collect();
+        for hash in &hashes {
+            queue.register_hash(*hash);
+        }
+
+        assert_eq!(queue.last_hash(), Some(hashes[3]));
+        assert_eq!(queue.get_hash_age(&hashes[0]), None);
+        assert_eq!(queue.get_hash_age(&hashes[1]), Some(2));
+        assert!(queue.is_hash_valid_for_age(&hashes[2], 1));
+        assert!(!queue.is_hash_valid_for_age(&hashes[1], 1));
+    }
+}
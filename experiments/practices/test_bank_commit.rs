@@ -0,0 +1,145 @@
+//! Unit test: Commit execution results to the `Bank`
+//!
+//! Analogy: Cooked orders only reach the ledger once they are served. The
+//! host copies the final bill of every paid order into the ledger and
+//! ignores the orders that were sent back.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts_db::AccountsDb,
+        bank::Bank,
+        svm::{InvokeContext, SysvarCache, SysvarSource, TransactionExecutor},
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_epoch_schedule::EpochSchedule;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_slot_hashes::SlotHashes;
+    use solana_stake_interface::stake_history::StakeHistory;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    // A mock program that moves `data[0]` lamports from its first account to
+    // its second one and records the amount in the recipient's data.
+    fn transfer(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let amount = u64::from(
+            *invoke_context
+                .instruction_data()
+                .first()
+                .ok_or(InstructionError::InvalidInstructionData)?,
+        );
+        invoke_context
+            .get_account_mut(0)?
+            .checked_sub_lamports(amount)?;
+        let recipient = invoke_context.get_account_mut(1)?;
+        recipient.checked_add_lamports(amount)?;
+        recipient.set_data_from_slice(&[amount as u8]);
+        Ok(())
+    }
+
+    // An executor running `transfer` under free rent, so the accounts can
+    // hold only a few lamports.
+    fn executor(program_id: Pubkey) -> TransactionExecutor {
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(program_id, transfer);
+        executor.set_sysvar_cache(SysvarCache::new(SysvarSource {
+            slot: 0,
+            epoch_schedule: &EpochSchedule::default(),
+            rent: &Rent::free(),
+            slot_hashes: &SlotHashes::default(),
+            stake_history: &StakeHistory::default(),
+            recent_blockhashes: &[Hash::default()],
+            lamports_per_signature: 5_000,
+        }));
+        executor
+    }
+
+    fn transaction(
+        program_id: Pubkey,
+        from: Pubkey,
+        to: Pubkey,
+        amount: u8,
+    ) -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[amount],
+            vec![AccountMeta::new(from, false), AccountMeta::new(to, false)],
+        );
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_commit_applies_only_successful_transactions() {
+        let program_id = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let carol = Pubkey::new_unique();
+        let executor = executor(program_id);
+
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(alice, AccountSharedData::new(10, 0, &program_id));
+        // The program can only write to the data of accounts it owns.
+        accounts_db.store_account(bob, AccountSharedData::new(1, 0, &program_id));
+        accounts_db.store_account(carol, AccountSharedData::new(1, 0, &program_id));
+        let mut bank = Bank::new(accounts_db);
+
+        // Carol cannot afford her transfer, so only Alice's lands.
+        let transactions = vec![
+            transaction(program_id, alice, bob, 4),
+            transaction(program_id, carol, bob, 5),
+        ];
+        let results: Vec<_> = transactions
+            .iter()
+            .map(|tx| executor.load_and_execute_transaction(bank.accounts_db(), tx))
+            .collect();
+        assert!(!results[1].was_successful());
+
+        assert_eq!(bank.commit(&transactions, &results), 1);
+        assert_eq!(bank.get_account(&alice).unwrap().lamports(), 6);
+        let bob_account = bank.get_account(&bob).unwrap();
+        assert_eq!(bob_account.lamports(), 5);
+        assert_eq!(bob_account.data(), &[4]);
+        assert_eq!(bank.get_account(&carol).unwrap().lamports(), 1);
+    }
+
+    #[test]
+    fn test_commit_skips_readonly_accounts() {
+        let program_id = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let observed = Pubkey::new_unique();
+        let executor = executor(program_id);
+
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(alice, AccountSharedData::new(10, 0, &program_id));
+        accounts_db.store_account(bob, AccountSharedData::new(1, 0, &program_id));
+        accounts_db.store_account(observed, AccountSharedData::new(3, 0, &program_id));
+        let mut bank = Bank::new(accounts_db);
+
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[2],
+            vec![
+                AccountMeta::new(alice, false),
+                AccountMeta::new(bob, false),
+                AccountMeta::new_readonly(observed, false),
+            ],
+        );
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let result = executor.load_and_execute_transaction(bank.accounts_db(), &tx);
+
+        // The transaction only read `observed`, so committing its stale copy
+        // must not undo a write that landed in the meantime.
+        bank.store_account(observed, AccountSharedData::new(9, 0, &program_id));
+        assert_eq!(bank.commit(&[tx], &[result]), 1);
+        assert_eq!(bank.get_account(&alice).unwrap().lamports(), 8);
+        assert_eq!(bank.get_account(&observed).unwrap().lamports(), 9);
+    }
+}
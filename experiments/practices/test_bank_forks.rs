@@ -0,0 +1,154 @@
+//! Unit test: Fork child banks off a parent bank
+//!
+//! Analogy: At the end of a shift the kitchen's inventory is photocopied
+//! for each of the next shifts that might take over. Every copy starts
+//! from the same shelves and the same order history, but what one shift
+//! uses up never disappears from another shift's copy.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::system_program;
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_clock::DEFAULT_SLOTS_PER_EPOCH;
+    use solana_hash::Hash;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn transfer(
+        from: &Pubkey,
+        to: &Pubkey,
+        lamports: u64,
+        blockhash: &Hash,
+    ) -> SanitizedTransaction {
+        let instruction = system_program::transfer(from, to, lamports);
+        let message = Message::new_with_blockhash(&[instruction], Some(from), blockhash);
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn funded_bank(payer: &Pubkey) -> Bank {
+        let mut bank = Bank::default();
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &system_program_id::id()),
+        );
+        bank
+    }
+
+    fn lamports(bank: &Bank, pubkey: &Pubkey) -> u64 {
+        bank.get_account(pubkey)
+            .map_or(0, |account| account.lamports())
+    }
+
+    #[test]
+    fn test_child_carries_parent_state() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut parent = funded_bank(&alice);
+        let genesis = parent.last_blockhash();
+        let result = parent
+            .process_transaction_batch(&[transfer(&alice, &bob, 1_000_000, &genesis)])
+            .remove(0)
+            .unwrap();
+        assert!(result.was_successful());
+        parent.freeze();
+
+        let parent_blockhash = Hash::new_unique();
+        let mut child = Bank::new_from_parent(&parent, 4, parent_blockhash);
+        assert_eq!(child.slot(), 4);
+        assert_eq!(child.parent_slot(), Some(0));
+        assert_eq!(parent.parent_slot(), None);
+        assert_eq!(lamports(&child, &bob), 1_000_000);
+        assert_eq!(child.collected_fees(), 0);
+        assert_eq!(child.last_blockhash(), parent_blockhash);
+        assert_eq!(child.slot_hashes().get(&0), Some(&parent_blockhash));
+        assert_eq!(child.sysvar_cache().get_clock().slot, 4);
+
+        // Blockhashes of the parent and of the slot it ended with are both
+        // recent in the child.
+        let results = child.process_transaction_batch(&[
+            transfer(&alice, &bob, 3_000_000, &genesis),
+            transfer(&alice, &bob, 2_000_000, &parent_blockhash),
+        ]);
+        assert!(results
+            .iter()
+            .all(|result| result.as_ref().unwrap().was_successful()));
+        assert_eq!(lamports(&child, &bob), 6_000_000);
+        assert_eq!(lamports(&parent, &bob), 1_000_000);
+    }
+
+    #[test]
+    fn test_sibling_forks_are_independent() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let carol = Pubkey::new_unique();
+        let mut parent = funded_bank(&alice);
+        parent.freeze();
+        let blockhash = Hash::new_unique();
+        let mut left = Bank::new_from_parent(&parent, 1, blockhash);
+        let mut right = Bank::new_from_parent(&parent, 2, blockhash);
+
+        left.process_transaction_batch(&[transfer(&alice, &bob, 1_000_000, &blockhash)]);
+        right.process_transaction_batch(&[transfer(&alice, &carol, 2_000_000, &blockhash)]);
+        assert_eq!(
+            (lamports(&left, &bob), lamports(&left, &carol)),
+            (1_000_000, 0)
+        );
+        assert_eq!(
+            (lamports(&right, &bob), lamports(&right, &carol)),
+            (0, 2_000_000)
+        );
+        assert_eq!(lamports(&parent, &alice), LAMPORTS);
+
+        // A leader window continues one fork slot after slot.
+        left.freeze();
+        let mut next = Bank::new_from_parent(&left, 3, Hash::new_unique());
+        assert_eq!(next.parent_slot(), Some(1));
+        assert_eq!(lamports(&next, &bob), 1_000_000);
+        let result = next
+            .process_transaction_batch(&[transfer(&alice, &bob, 500_000, &blockhash)])
+            .remove(0)
+            .unwrap();
+        assert!(result.was_successful());
+        assert_eq!(lamports(&next, &bob), 1_500_000);
+        assert_eq!(next.collected_fees(), 5_000);
+        assert_eq!(
+            next.slot_hashes()
+                .iter()
+                .map(|slot_hash| slot_hash.slot)
+                .collect::<Vec<_>>(),
+            vec![1, 0]
+        );
+    }
+
+    #[test]
+    fn test_child_slot_must_follow_parent() {
+        let mut parent = Bank::default();
+        parent.freeze();
+        let mut child = Bank::new_from_parent(&parent, DEFAULT_SLOTS_PER_EPOCH, Hash::new_unique());
+        assert!(child.epoch() > parent.epoch());
+        child.freeze();
+
+        // A panicking thread takes its banks down with it.
+        let result = std::thread::spawn(move || {
+            Bank::new_from_parent(&child, DEFAULT_SLOTS_PER_EPOCH, Hash::new_unique());
+        })
+        .join();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_child_needs_frozen_parent() {
+        let alice = Pubkey::new_unique();
+        let parent = funded_bank(&alice);
+        let result = std::thread::spawn(move || {
+            Bank::new_from_parent(&parent, 1, Hash::new_unique());
+        })
+        .join();
+        assert!(result.is_err());
+    }
+}
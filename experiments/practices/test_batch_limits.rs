@@ -0,0 +1,81 @@
+//! Unit test: Cap batches with `BatchLimits`
+//!
+//! Analogy: Even with plenty of free tables, the kitchen can only cook so
+//! many dishes per round. The host stops seating parties once the round is
+//! full and seats the rest in the next round, biggest tips first.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{BatchLimits, FifoScheduler, PriorityGraphScheduler, TransactionScheduler},
+    };
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    // A transaction that conflicts with nothing and requests `cu_limit` CUs.
+    fn transaction(cu_limit: u32, cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_compute_unit_limit_splits_batches() {
+        let limits = BatchLimits {
+            max_cus: 250_000,
+            ..BatchLimits::default()
+        };
+        let mut scheduler = PriorityGraphScheduler::new();
+        let small = scheduler.push(transaction(100_000, 1));
+        let large = scheduler.push(transaction(200_000, 3));
+        let medium = scheduler.push(transaction(150_000, 2));
+
+        // `large` and `medium` pay more but do not fit together, so `large`
+        // runs alone and `medium` heads the next batch.
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![large]);
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![medium, small]);
+        assert_eq!(scheduler.num_pending(), 0);
+    }
+
+    #[test]
+    fn test_oversized_transaction_runs_alone() {
+        let limits = BatchLimits {
+            max_txs: 2,
+            max_cus: 100_000,
+            max_account_data_size: u64::MAX,
+        };
+        let mut scheduler = FifoScheduler::new();
+        let oversized = scheduler.push(transaction(1_000_000, 0));
+        let ids: Vec<_> = (0..3)
+            .map(|_| scheduler.push(transaction(10_000, 0)))
+            .collect();
+
+        // A transaction above the limits still makes progress on its own.
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![oversized]);
+        assert_eq!(scheduler.next_batch(&limits).ids, ids[..2]);
+        assert_eq!(scheduler.next_batch(&limits).ids, ids[2..]);
+    }
+
+    #[test]
+    fn test_account_data_size_limit() {
+        let limits = BatchLimits {
+            max_account_data_size: 64 * 1024 * 1024,
+            ..BatchLimits::default()
+        };
+        let mut scheduler = FifoScheduler::new();
+        let first = scheduler.push(transaction(10_000, 0));
+        let second = scheduler.push(transaction(10_000, 0));
+
+        // Without an explicit request, each transaction may load the
+        // protocol maximum of account data, so only one fits per batch.
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![first]);
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![second]);
+    }
+}
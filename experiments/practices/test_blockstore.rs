@@ -0,0 +1,173 @@
+//! Unit test: Persist executed batches to a blockstore and replay them
+//!
+//! Analogy: Every ticket the kitchen completes is pinned to the order spike
+//! in the order it went out, each one stamped with a number that depends on
+//! the ticket before it. After a power cut the next shift restocks the
+//! shelves as they were that morning, cooks the spike again from the top,
+//! and ends up with exactly the same shelves, and a ticket slipped into the
+//! middle of the spike gives itself away by its stamp.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::blockstore::{
+        replay_entries, verify_entries, Blockstore, BlockstoreError, Entry,
+    };
+    use priority_graph_practice::system_program;
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_hash::Hash;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::path::PathBuf;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let instruction = system_program::transfer(from, to, lamports);
+        let message = Message::new(&[instruction], Some(from));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn funded_bank(payers: &[Pubkey]) -> Bank {
+        let mut bank = Bank::default();
+        for payer in payers {
+            bank.store_account(
+                *payer,
+                AccountSharedData::new(LAMPORTS, 0, &system_program_id::id()),
+            );
+        }
+        bank
+    }
+
+    fn lamports(bank: &Bank, pubkey: &Pubkey) -> u64 {
+        bank.get_account(pubkey)
+            .map_or(0, |account| account.lamports())
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "blockstore-{name}-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_restart_and_replay() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let carol = Pubkey::new_unique();
+        let dave = Pubkey::new_unique();
+        let path = temp_path("replay");
+
+        let mut bank = funded_bank(&[alice, bob]);
+        let start_hash = bank.last_blockhash();
+        let first = vec![
+            transfer(&alice, &carol, 1_000_000),
+            transfer(&bob, &carol, 2_000_000),
+        ];
+        // Dave cannot pay the fee, so the bank rejects the second one.
+        let second = vec![
+            transfer(&alice, &bob, 3_000_000),
+            transfer(&dave, &alice, 1_000_000),
+        ];
+        let first_results = bank.process_transaction_batch(&first);
+        let second_results = bank.process_transaction_batch(&second);
+        assert!(second_results[1].is_err());
+        let first_entry = Entry::from_executed_batch(&start_hash, 4, &first, &first_results);
+        let second_entry =
+            Entry::from_executed_batch(&first_entry.hash, 0, &second, &second_results);
+        assert_eq!(second_entry.transactions.len(), 1);
+        {
+            let mut blockstore = Blockstore::open(&path).unwrap();
+            blockstore
+                .append_entries(0, &[first_entry.clone(), second_entry.clone()])
+                .unwrap();
+        }
+
+        let blockstore = Blockstore::open(&path).unwrap();
+        assert_eq!(blockstore.slots().unwrap(), vec![0]);
+        let entries = blockstore.slot_entries(0).unwrap();
+        assert_eq!(entries, vec![first_entry, second_entry]);
+        assert!(verify_entries(&start_hash, &entries));
+
+        let mut replayed = funded_bank(&[alice, bob]);
+        let results = replay_entries(&mut replayed, &entries);
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .iter()
+            .all(|result| result.as_ref().unwrap().was_successful()));
+        for pubkey in [alice, bob, carol] {
+            assert_eq!(lamports(&replayed, &pubkey), lamports(&bank, &pubkey));
+        }
+        assert_eq!(replayed.collected_fees(), bank.collected_fees());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_verify_entries_detects_tampering() {
+        let alice = Pubkey::new_unique();
+        let start_hash = Hash::new_unique();
+        let transaction = transfer(&alice, &Pubkey::new_unique(), 1_000_000);
+        let tick = Entry::new(&start_hash, 8, Vec::new());
+        let entry = Entry::new(&tick.hash, 2, vec![transaction.to_versioned_transaction()]);
+        assert_ne!(tick.hash, start_hash);
+        assert!(verify_entries(&start_hash, &[tick.clone(), entry.clone()]));
+
+        // Out of order, from another start, or with transactions swapped out.
+        assert!(!verify_entries(&start_hash, &[entry.clone(), tick.clone()]));
+        assert!(!verify_entries(
+            &Hash::new_unique(),
+            &[tick.clone(), entry.clone()]
+        ));
+        let mut tampered = entry;
+        tampered.transactions[0].signatures[0] = solana_signature::Signature::from([7; 64]);
+        assert!(!verify_entries(&start_hash, &[tick, tampered]));
+    }
+
+    #[test]
+    fn test_open_recovers_from_truncated_record() {
+        let path = temp_path("truncated");
+        let start_hash = Hash::new_unique();
+        let first = Entry::new(&start_hash, 1, Vec::new());
+        let second = Entry::new(&first.hash, 1, Vec::new());
+        let third = Entry::new(&second.hash, 1, Vec::new());
+        {
+            let mut blockstore = Blockstore::open(&path).unwrap();
+            blockstore
+                .append_entries(1, std::slice::from_ref(&first))
+                .unwrap();
+            blockstore
+                .append_entries(2, std::slice::from_ref(&second))
+                .unwrap();
+        }
+        // A crash halfway through writing the second record.
+        let len = std::fs::metadata(&path).unwrap().len();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 5)
+            .unwrap();
+
+        let mut blockstore = Blockstore::open(&path).unwrap();
+        assert_eq!(blockstore.read_all().unwrap(), vec![(1, first.clone())]);
+        blockstore
+            .append_entries(2, std::slice::from_ref(&third))
+            .unwrap();
+        assert_eq!(blockstore.slots().unwrap(), vec![1, 2]);
+        assert_eq!(blockstore.slot_entries(2).unwrap(), vec![third]);
+        std::fs::remove_file(&path).unwrap();
+
+        std::fs::write(&path, b"not a blockstore").unwrap();
+        assert!(matches!(
+            Blockstore::open(&path),
+            Err(BlockstoreError::InvalidHeader)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
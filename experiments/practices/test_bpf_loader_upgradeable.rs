@@ -0,0 +1,208 @@
+//! Unit test: Deploy, upgrade and close upgradeable programs
+//!
+//! Analogy: A guest chef first hands the recipe to the office, page by
+//! page, into a folder. Once it is complete the manager files it in the
+//! kitchen binder under the dish's name, and cooks start using it the next
+//! shift. A revised recipe goes through the same folder and replaces the
+//! binder page, but only the chef named on that page may swap it, and once
+//! the page is laminated nobody can.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::returning_elf;
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::bpf_loader_upgradeable::{
+        self, get_program_data_address, UpgradeableLoaderState,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    fn setup() -> (Bank, Pubkey) {
+        let mut bank = Bank::default();
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(100_000_000_000, 0, &system_program::id()),
+        );
+        (bank, payer)
+    }
+
+    fn execute(
+        bank: &mut Bank,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<(), TransactionError> {
+        let message =
+            Message::new_with_blockhash(instructions, Some(payer), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        bank.process_transaction_batch(&[tx])
+            .remove(0)
+            .unwrap()
+            .status
+    }
+
+    /// Creates a buffer holding `elf`, with `payer` as its authority.
+    fn write_buffer(bank: &mut Bank, payer: &Pubkey, elf: &[u8]) -> Pubkey {
+        let buffer = Pubkey::new_unique();
+        let lamports = bank
+            .rent()
+            .minimum_balance(UpgradeableLoaderState::size_of_buffer(elf.len()));
+        let mut instructions =
+            bpf_loader_upgradeable::create_buffer(payer, &buffer, payer, lamports, elf.len());
+        instructions.push(bpf_loader_upgradeable::write(
+            &buffer,
+            payer,
+            0,
+            elf.to_vec(),
+        ));
+        assert_eq!(execute(bank, payer, &instructions), Ok(()));
+        buffer
+    }
+
+    /// Deploys `elf` with `payer` as its upgrade authority.
+    fn deploy(bank: &mut Bank, payer: &Pubkey, elf: &[u8]) -> Pubkey {
+        let buffer = write_buffer(bank, payer, elf);
+        let program = Pubkey::new_unique();
+        let lamports = bank
+            .rent()
+            .minimum_balance(UpgradeableLoaderState::size_of_program());
+        let instructions = bpf_loader_upgradeable::deploy_with_max_program_len(
+            payer,
+            &program,
+            &buffer,
+            payer,
+            lamports,
+            elf.len() * 2,
+        );
+        assert_eq!(execute(bank, payer, &instructions), Ok(()));
+        program
+    }
+
+    fn invoke(bank: &mut Bank, payer: &Pubkey, program: Pubkey) -> Result<(), TransactionError> {
+        execute(
+            bank,
+            payer,
+            &[Instruction::new_with_bytes(program, &[], vec![])],
+        )
+    }
+
+    #[test]
+    fn test_deploy_then_invoke() {
+        let (mut bank, payer) = setup();
+        let program = deploy(&mut bank, &payer, &returning_elf(0));
+
+        let program_account = bank.get_account(&program).unwrap();
+        assert!(program_account.executable());
+        let programdata_address = get_program_data_address(&program);
+        assert_eq!(
+            UpgradeableLoaderState::deserialize(program_account.data()),
+            Ok(UpgradeableLoaderState::Program {
+                programdata_address
+            })
+        );
+        let programdata = bank.get_account(&programdata_address).unwrap();
+        assert_eq!(
+            UpgradeableLoaderState::deserialize(programdata.data()),
+            Ok(UpgradeableLoaderState::ProgramData {
+                slot: 0,
+                upgrade_authority_address: Some(payer),
+            })
+        );
+
+        // Programs become visible in the slot after their deployment.
+        assert_eq!(
+            invoke(&mut bank, &payer, program),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::UnsupportedProgramId
+            ))
+        );
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(invoke(&mut bank, &payer, program), Ok(()));
+    }
+
+    #[test]
+    fn test_upgrade_replaces_program() {
+        let (mut bank, payer) = setup();
+        let program = deploy(&mut bank, &payer, &returning_elf(0));
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(invoke(&mut bank, &payer, program), Ok(()));
+
+        let buffer = write_buffer(&mut bank, &payer, &returning_elf(1));
+        let spill = Pubkey::new_unique();
+        let upgrade = bpf_loader_upgradeable::upgrade(&program, &buffer, &payer, &spill);
+        assert_eq!(execute(&mut bank, &payer, &[upgrade]), Ok(()));
+        // The buffer's lamports went to the spill account.
+        assert!(bank.get_account(&spill).unwrap().lamports() > 0);
+
+        // A program upgraded in this slot cannot be upgraded again.
+        let buffer = write_buffer(&mut bank, &payer, &returning_elf(2));
+        let upgrade = bpf_loader_upgradeable::upgrade(&program, &buffer, &payer, &spill);
+        assert_eq!(
+            execute(&mut bank, &payer, &[upgrade]),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::InvalidArgument
+            ))
+        );
+
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(
+            invoke(&mut bank, &payer, program),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_immutable_program_and_close() {
+        let (mut bank, payer) = setup();
+        let program = deploy(&mut bank, &payer, &returning_elf(0));
+        bank.advance_slot(Hash::new_unique());
+
+        let finalize = bpf_loader_upgradeable::set_upgrade_authority(&program, &payer, None);
+        assert_eq!(execute(&mut bank, &payer, &[finalize]), Ok(()));
+        let buffer = write_buffer(&mut bank, &payer, &returning_elf(1));
+        let upgrade = bpf_loader_upgradeable::upgrade(&program, &buffer, &payer, &payer);
+        assert_eq!(
+            execute(&mut bank, &payer, &[upgrade]),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Immutable
+            ))
+        );
+
+        // Only the buffer's authority can close it.
+        let recipient = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        let close = bpf_loader_upgradeable::close(&buffer, &recipient, &stranger);
+        assert_eq!(
+            execute(&mut bank, &payer, &[close]),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::IncorrectAuthority
+            ))
+        );
+        let buffer_lamports = bank.get_account(&buffer).unwrap().lamports();
+        let close = bpf_loader_upgradeable::close(&buffer, &recipient, &payer);
+        assert_eq!(execute(&mut bank, &payer, &[close]), Ok(()));
+        assert_eq!(
+            bank.get_account(&recipient).unwrap().lamports(),
+            buffer_lamports
+        );
+        assert_eq!(invoke(&mut bank, &payer, program), Ok(()));
+    }
+}
@@ -0,0 +1,131 @@
+//! Unit test: Name the custom error codes builtins fail with
+//!
+//! Analogy: When a station sends a dish back it only shouts a number. The
+//! number means something different at every station, so the pass keeps
+//! each station's list of complaints and looks the number up on the list
+//! of the station that shouted it.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        builtin_error::BuiltinError,
+        precompiles::PrecompileError,
+        system_program::{self, SystemError},
+        token_program::{self, TokenError},
+    };
+    use serde_json::json;
+    use solana_account::AccountSharedData;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{ed25519_program, stake, vote};
+    use solana_stake_interface::error::StakeError;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use solana_vote_interface::error::VoteError;
+
+    #[test]
+    fn test_codes_round_trip() {
+        let mut code = 0;
+        while let Some(err) = SystemError::from_code(code) {
+            let decoded = BuiltinError::decode(&solana_sdk_ids::system_program::id(), code);
+            assert_eq!(decoded, Some(BuiltinError::System(err)));
+            assert_eq!(InstructionError::from(err), InstructionError::Custom(code));
+            code += 1;
+        }
+        assert_eq!(code, 8);
+        let mut code = 0;
+        while let Some(err) = TokenError::from_code(code) {
+            assert_eq!(err as u32, code);
+            code += 1;
+        }
+        assert_eq!(code, 20);
+        assert_eq!(PrecompileError::from_code(5), None);
+        assert_eq!(
+            BuiltinError::decode(&ed25519_program::id(), 2),
+            Some(BuiltinError::Precompile(PrecompileError::InvalidSignature))
+        );
+
+        for (program_id, err) in [
+            (vote::id(), BuiltinError::Vote(VoteError::SlotHashMismatch)),
+            (stake::id(), BuiltinError::Stake(StakeError::LockupInForce)),
+            (
+                token_program::ID,
+                BuiltinError::Token(TokenError::AccountFrozen),
+            ),
+        ] {
+            let code = err.code();
+            assert_eq!(BuiltinError::decode(&program_id, code), Some(err.clone()));
+            assert_eq!(InstructionError::from(err), InstructionError::Custom(code));
+        }
+        // Codes only mean something for the builtin that reported them.
+        assert_eq!(BuiltinError::decode(&Pubkey::new_unique(), 0), None);
+        assert_eq!(
+            BuiltinError::decode(&solana_sdk_ids::system_program::id(), 8),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_the_error_a_transaction_failed_with() {
+        let payer = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let recipient = Pubkey::new_unique();
+        let transfer = |lamports| system_program::transfer(&payer, &recipient, lamports);
+        let message = Message::new_with_blockhash(
+            &[transfer(1_000_000), transfer(2_000_000_000)],
+            Some(&payer),
+            &bank.last_blockhash(),
+        );
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let result = bank
+            .process_transaction_batch(std::slice::from_ref(&tx))
+            .remove(0)
+            .unwrap();
+        let err = result.status.unwrap_err();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(1, SystemError::ResultWithNegativeLamports.into())
+        );
+        assert_eq!(
+            BuiltinError::from_transaction_error(tx.message(), &err),
+            Some(BuiltinError::System(
+                SystemError::ResultWithNegativeLamports
+            ))
+        );
+        assert_eq!(
+            BuiltinError::from_transaction_error(tx.message(), &TransactionError::AccountInUse),
+            None
+        );
+    }
+
+    #[test]
+    fn test_errors_in_the_shape_rpc_reports_them() {
+        // Fixtures record `err` the way RPC renders it.
+        let recorded = json!({ "InstructionError": [0, { "Custom": 3 }] });
+        let err: TransactionError = serde_json::from_value(recorded.clone()).unwrap();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(0, SystemError::InvalidAccountDataLength.into())
+        );
+        assert_eq!(serde_json::to_value(&err).unwrap(), recorded);
+
+        let recorded = json!({ "InsufficientFundsForRent": { "account_index": 2 } });
+        let err: TransactionError = serde_json::from_value(recorded).unwrap();
+        assert_eq!(
+            err,
+            TransactionError::InsufficientFundsForRent { account_index: 2 }
+        );
+        let recorded = json!({ "InstructionError": [1, "MissingRequiredSignature"] });
+        assert_eq!(
+            serde_json::from_value::<TransactionError>(recorded).unwrap(),
+            TransactionError::InstructionError(1, InstructionError::MissingRequiredSignature)
+        );
+    }
+}
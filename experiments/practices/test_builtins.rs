@@ -0,0 +1,111 @@
+//! Unit test: Dispatch instructions through the builtin program table
+//!
+//! Analogy: The kitchen keeps a board of house recipes pinned by the pass.
+//! An order for a dish on the board is cooked straight away without
+//! fetching any cookbook, and the head chef can pin a new card or take an
+//! old one down whenever the menu changes.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::svm::{
+        BuiltinPrograms, InvokeContext, TransactionExecutor, BUILTINS, DEFAULT_LOADER_COMPUTE_UNITS,
+    };
+    use solana_account::{AccountSharedData, WritableAccount};
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{bpf_loader, compute_budget, stake, system_program, vote};
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    fn transaction(instruction: Instruction) -> SanitizedTransaction {
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[instruction], Some(&payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn instruction_error(result_status: &Result<(), TransactionError>) -> InstructionError {
+        match result_status {
+            Err(TransactionError::InstructionError(0, err)) => err.clone(),
+            other => panic!("expected an instruction error, got {other:?}"),
+        }
+    }
+
+    // A mock program that succeeds after consuming a single CU.
+    fn noop(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        invoke_context.consume_checked(1)
+    }
+
+    #[test]
+    fn test_default_table_covers_native_programs() {
+        let builtins = BuiltinPrograms::new();
+        for program_id in [
+            system_program::id(),
+            compute_budget::id(),
+            vote::id(),
+            stake::id(),
+            bpf_loader::id(),
+        ] {
+            assert!(builtins.contains(&program_id), "{program_id} is missing");
+        }
+        assert_eq!(builtins.len(), BUILTINS.len());
+        assert!(BuiltinPrograms::empty().is_empty());
+
+        // Builtins run without any program account in the store.
+        let executor = TransactionExecutor::new();
+        let store = HashMap::new();
+        let to_vote = Instruction::new_with_bytes(vote::id(), &[], vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(to_vote));
+        assert_eq!(
+            instruction_error(&result.status),
+            InstructionError::InvalidInstructionData
+        );
+
+        let to_loader = Instruction::new_with_bytes(bpf_loader::id(), &[], vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(to_loader));
+        assert_eq!(
+            instruction_error(&result.status),
+            InstructionError::UnsupportedProgramId
+        );
+        assert_eq!(result.consumed_units, DEFAULT_LOADER_COMPUTE_UNITS);
+    }
+
+    #[test]
+    fn test_mock_builtin_replaces_program_account() {
+        let program_id = Pubkey::new_unique();
+        let mut builtins = BuiltinPrograms::empty();
+        builtins.add(program_id, noop);
+        let executor = TransactionExecutor::with_builtins(builtins);
+        assert!(!executor.builtins().contains(&system_program::id()));
+
+        // The program account is not a valid SBF program, but the builtin
+        // takes precedence so it is never loaded.
+        let mut store = HashMap::new();
+        let mut program = AccountSharedData::new(1, 0, &bpf_loader::id());
+        program.set_data_from_slice(b"not an elf");
+        program.set_executable(true);
+        store.insert(program_id, program);
+
+        let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(ix));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.consumed_units, 1);
+    }
+
+    #[test]
+    fn test_removed_builtin_is_unsupported() {
+        let mut builtins = BuiltinPrograms::new();
+        assert!(builtins.remove(&stake::id()));
+        assert!(!builtins.remove(&stake::id()));
+        let executor = TransactionExecutor::with_builtins(builtins);
+
+        let ix = Instruction::new_with_bytes(stake::id(), &[], vec![]);
+        let result = executor.load_and_execute_transaction(&HashMap::new(), &transaction(ix));
+        assert_eq!(
+            instruction_error(&result.status),
+            InstructionError::UnsupportedProgramId
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Unit test: Execute transaction bundles all together or not at all
+//!
+//! Analogy: A party orders a set menu. The kitchen cooks the courses one
+//! after another at a single station, and if the dessert cannot be made
+//! the whole order is sent back unbilled, as if it had never been placed.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts::LockSetConfig,
+        bank::Bank,
+        scheduler::{
+            BatchLimits, Bundle, BundleError, FifoScheduler, TransactionScheduler, MAX_BUNDLE_LEN,
+        },
+        svm::InvokeContext,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    // A mock program that moves `data[0]` lamports from its first account to
+    // its second one.
+    fn transfer(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let amount = u64::from(
+            *invoke_context
+                .instruction_data()
+                .first()
+                .ok_or(InstructionError::InvalidInstructionData)?,
+        );
+        invoke_context
+            .get_account_mut(0)?
+            .checked_sub_lamports(amount)?;
+        invoke_context
+            .get_account_mut(1)?
+            .checked_add_lamports(amount)?;
+        Ok(())
+    }
+
+    fn transaction(
+        payer: &Pubkey,
+        program_id: Pubkey,
+        from: Pubkey,
+        to: Pubkey,
+        amount: u8,
+        recent_blockhash: Hash,
+    ) -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[amount],
+            vec![AccountMeta::new(from, false), AccountMeta::new(to, false)],
+        );
+        let message = Message::new_with_blockhash(&[instruction], Some(payer), &recent_blockhash);
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn writing(writable: Pubkey) -> SanitizedTransaction {
+        transaction(
+            &Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            writable,
+            Pubkey::new_unique(),
+            0,
+            Hash::default(),
+        )
+    }
+
+    // A bank with a transfer builtin, Alice holding 10 lamports and a payer
+    // for the fees.
+    fn setup() -> (Bank, Pubkey, Pubkey, Pubkey) {
+        let program_id = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.set_rent(Rent::free());
+        bank.add_builtin(program_id, transfer);
+        bank.store_account(alice, AccountSharedData::new(10, 0, &program_id));
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        (bank, program_id, alice, payer)
+    }
+
+    #[test]
+    fn test_bundle_validation_and_locks() {
+        assert_eq!(Bundle::new(Vec::new()).unwrap_err(), BundleError::Empty);
+        let too_many = (0..=MAX_BUNDLE_LEN)
+            .map(|_| writing(Pubkey::new_unique()))
+            .collect();
+        assert_eq!(
+            Bundle::new(too_many).unwrap_err(),
+            BundleError::TooLong(MAX_BUNDLE_LEN + 1)
+        );
+        let repeated = writing(Pubkey::new_unique());
+        assert_eq!(
+            Bundle::new(vec![repeated.clone(), repeated]).unwrap_err(),
+            BundleError::DuplicateTransaction(1)
+        );
+
+        // One transaction reads what the other writes.
+        let shared = Pubkey::new_unique();
+        let reader = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new_readonly(shared, false)],
+        );
+        let reader = SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(
+            Message::new(&[reader], Some(&Pubkey::new_unique())),
+        ));
+        let bundle = Bundle::new(vec![reader, writing(shared)]).unwrap();
+        let lock_set = bundle.lock_set(LockSetConfig::default());
+        assert!(lock_set.writable().contains(&shared));
+        assert!(!lock_set.readonly().contains(&shared));
+    }
+
+    #[test]
+    fn test_process_bundle_commits_in_order() {
+        let (mut bank, program_id, alice, payer) = setup();
+        let bob = Pubkey::new_unique();
+        // The program can only debit accounts it owns.
+        bank.store_account(bob, AccountSharedData::new(1, 0, &program_id));
+        let carol = Pubkey::new_unique();
+        let blockhash = bank.last_blockhash();
+        let bundle = Bundle::new(vec![
+            transaction(&payer, program_id, alice, bob, 7, blockhash),
+            transaction(&payer, program_id, bob, carol, 5, blockhash),
+        ])
+        .unwrap();
+
+        let results = bank.process_bundle(&bundle).unwrap();
+        assert!(results.iter().all(|result| result.was_successful()));
+        assert_eq!(bank.get_account(&alice).unwrap().lamports(), 3);
+        assert_eq!(bank.get_account(&bob).unwrap().lamports(), 3);
+        assert_eq!(bank.get_account(&carol).unwrap().lamports(), 5);
+
+        let fees: u64 = results
+            .iter()
+            .map(|result| result.fee_details.total_fee())
+            .sum();
+        assert!(fees > 0);
+        assert_eq!(bank.collected_fees(), fees);
+        assert_eq!(
+            bank.get_account(&payer).unwrap().lamports(),
+            1_000_000 - fees
+        );
+        assert_eq!(bank.cost_tracker().transaction_count(), 2);
+    }
+
+    #[test]
+    fn test_failed_bundle_leaves_no_trace() {
+        let (mut bank, program_id, alice, payer) = setup();
+        let bob = Pubkey::new_unique();
+        let blockhash = bank.last_blockhash();
+        let first = transaction(&payer, program_id, alice, bob, 7, blockhash);
+        // Bob only holds what Alice sent him.
+        let bundle = Bundle::new(vec![
+            first.clone(),
+            transaction(&payer, program_id, bob, Pubkey::new_unique(), 8, blockhash),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            bank.process_bundle(&bundle).unwrap_err(),
+            BundleError::TransactionFailed {
+                index: 1,
+                err: TransactionError::InstructionError(0, InstructionError::ArithmeticOverflow),
+            }
+        );
+        assert_eq!(bank.get_account(&alice).unwrap().lamports(), 10);
+        assert!(bank.get_account(&bob).is_none());
+        assert_eq!(bank.get_account(&payer).unwrap().lamports(), 1_000_000);
+        assert_eq!(bank.collected_fees(), 0);
+        assert_eq!(bank.cost_tracker().block_cost(), 0);
+        assert_eq!(bank.cost_tracker().transaction_count(), 0);
+
+        // Nothing was recorded, so the first transaction still goes through
+        // on its own.
+        let results = bank.process_transaction_batch(&[first]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(bank.get_account(&bob).unwrap().lamports(), 7);
+    }
+
+    #[test]
+    fn test_fifo_scheduler_hands_out_bundles_whole() {
+        let hot_account = Pubkey::new_unique();
+        let mut scheduler = FifoScheduler::new();
+        let first = scheduler.push(writing(hot_account));
+        let bundle =
+            Bundle::new(vec![writing(Pubkey::new_unique()), writing(hot_account)]).unwrap();
+        let bundle_ids = scheduler.push_bundle(bundle);
+        assert_eq!(bundle_ids, vec![first + 1, first + 2]);
+        let last = scheduler.push(writing(Pubkey::new_unique()));
+        assert_eq!(scheduler.num_pending(), 4);
+
+        // The bundle waits for `hot_account` as a whole.
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, vec![first]);
+        assert!(batch.bundles.is_empty());
+        scheduler.complete(&batch.ids);
+
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, vec![bundle_ids[0], bundle_ids[1], last]);
+        assert_eq!(batch.bundles, vec![0..2]);
+        assert_eq!(scheduler.num_pending(), 0);
+
+        // Its locks are held until every one of its transactions completed.
+        let waiting = scheduler.push(writing(hot_account));
+        scheduler.complete(&[bundle_ids[1], last]);
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        scheduler.complete(&[bundle_ids[0]]);
+        assert_eq!(
+            scheduler.next_batch(&BatchLimits::default()).ids,
+            vec![waiting]
+        );
+    }
+}
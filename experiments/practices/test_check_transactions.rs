@@ -0,0 +1,121 @@
+//! Unit test: Reject expired and already processed transactions
+//!
+//! Analogy: The kitchen only takes tickets dated within the last few
+//! sittings, and it keeps the stubs of every ticket it cooked. A ticket
+//! that is too old, or whose stub is already on the spike, goes straight
+//! back to the waiter.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::system_program;
+    use solana_account::AccountSharedData;
+    use solana_clock::MAX_PROCESSING_AGE;
+    use solana_hash::Hash;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn transfer(
+        from: &Pubkey,
+        to: &Pubkey,
+        lamports: u64,
+        blockhash: &Hash,
+    ) -> SanitizedTransaction {
+        let instruction = system_program::transfer(from, to, lamports);
+        let message = Message::new_with_blockhash(&[instruction], Some(from), blockhash);
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn funded_bank(payer: &Pubkey) -> Bank {
+        let mut bank = Bank::default();
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &system_program_id::id()),
+        );
+        bank
+    }
+
+    #[test]
+    fn test_processed_transactions_are_rejected() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut bank = funded_bank(&alice);
+        let blockhash = bank.last_blockhash();
+        let paid = transfer(&alice, &bob, 1_000_000, &blockhash);
+        // Fails for lack of funds, but still pays its fee.
+        let overdrawn = transfer(&alice, &bob, 2 * LAMPORTS, &blockhash);
+        let results = bank.process_transaction_batch(&[paid.clone(), overdrawn.clone()]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert!(!results[1].as_ref().unwrap().was_successful());
+        assert_eq!(bank.status_cache().num_blockhashes(), 1);
+
+        let checked = bank.check_transactions(&[paid, overdrawn], MAX_PROCESSING_AGE);
+        assert_eq!(checked[0], Err(TransactionError::AlreadyProcessed));
+        assert_eq!(checked[1], Err(TransactionError::AlreadyProcessed));
+
+        // A duplicate in the same batch only runs once.
+        let fresh = transfer(&alice, &bob, 3_000_000, &blockhash);
+        let results = bank.process_transaction_batch(&[fresh.clone(), fresh]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(results[1], Err(TransactionError::AlreadyProcessed));
+
+        // Under another blockhash the same transfer is a new transaction.
+        bank.advance_slot(Hash::new_unique());
+        let again = transfer(&alice, &bob, 1_000_000, &bank.last_blockhash());
+        assert_eq!(
+            bank.check_transactions(&[again], MAX_PROCESSING_AGE),
+            vec![Ok(None)]
+        );
+    }
+
+    #[test]
+    fn test_blockhash_older_than_max_age_is_rejected() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut bank = funded_bank(&alice);
+        let old = transfer(&alice, &bob, 1_000_000, &bank.last_blockhash());
+        let recent_blockhash = Hash::new_unique();
+        bank.advance_slot(recent_blockhash);
+        let recent = transfer(&alice, &bob, 1_000_000, &recent_blockhash);
+        let unknown = transfer(&alice, &bob, 1_000_000, &Hash::new_unique());
+
+        let checked = bank.check_transactions(&[old.clone(), recent.clone(), unknown], 0);
+        assert_eq!(checked[0], Err(TransactionError::BlockhashNotFound));
+        assert_eq!(checked[1], Ok(None));
+        assert_eq!(checked[2], Err(TransactionError::BlockhashNotFound));
+        assert_eq!(
+            bank.check_transactions(std::slice::from_ref(&old), 1),
+            vec![Ok(None)]
+        );
+
+        for _ in 0..MAX_PROCESSING_AGE {
+            bank.advance_slot(Hash::new_unique());
+        }
+        let results = bank.process_transaction_batch(&[old, recent]);
+        assert_eq!(results[0], Err(TransactionError::BlockhashNotFound));
+        assert!(results[1].as_ref().unwrap().was_successful());
+    }
+
+    #[test]
+    fn test_child_banks_reject_what_their_parent_processed() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut bank = funded_bank(&alice);
+        let genesis = bank.last_blockhash();
+        let paid = transfer(&alice, &bob, 1_000_000, &genesis);
+        let results = bank.process_transaction_batch(std::slice::from_ref(&paid));
+        assert!(results[0].as_ref().unwrap().was_successful());
+
+        bank.freeze();
+        let child = Bank::new_from_parent(&bank, 1, Hash::new_unique());
+        assert_eq!(
+            child.check_transactions(&[paid], MAX_PROCESSING_AGE),
+            vec![Err(TransactionError::AlreadyProcessed)]
+        );
+    }
+}
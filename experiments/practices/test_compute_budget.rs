@@ -0,0 +1,120 @@
+//! Unit test: Compute budget parsing and metering
+//!
+//! Analogy: A compute budget is a prepaid meter. The transaction tops it up
+//! with `SetComputeUnitLimit`, and every instruction that runs burns units
+//! from it. When the meter hits zero the transaction is stopped.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::{
+            process_compute_budget_instructions, ComputeBudgetInstruction, DEFAULT_COMPUTE_UNITS,
+            DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT, MAX_COMPUTE_UNIT_LIMIT,
+        },
+        svm::{InvokeContext, TransactionExecutor},
+    };
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    fn transaction(instructions: &[Instruction]) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    // A mock program that burns 600 CUs per call.
+    fn burn_600(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        invoke_context.consume_checked(600)
+    }
+
+    #[test]
+    fn test_instruction_round_trip() {
+        for instruction in [
+            ComputeBudgetInstruction::RequestHeapFrame(64 * 1024),
+            ComputeBudgetInstruction::SetComputeUnitLimit(300_000),
+            ComputeBudgetInstruction::SetComputeUnitPrice(5_000),
+            ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(1024),
+        ] {
+            assert_eq!(
+                ComputeBudgetInstruction::parse(&instruction.serialize()),
+                Ok(instruction)
+            );
+        }
+        assert_eq!(
+            ComputeBudgetInstruction::parse(&[2, 0, 0]),
+            Err(InstructionError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_process_compute_budget_instructions() {
+        let program_ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+
+        // Without a limit every other instruction gets the default allowance.
+        let tx = transaction(&[program_ix.clone(), program_ix.clone()]);
+        let limits = process_compute_budget_instructions(tx.message()).unwrap();
+        assert_eq!(
+            limits.compute_unit_limit,
+            2 * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+        );
+        assert_eq!(limits.compute_unit_price, 0);
+
+        // Explicit requests win, and are clamped to the maximum.
+        let tx = transaction(&[
+            ComputeBudgetInstruction::set_compute_unit_limit(u32::MAX),
+            ComputeBudgetInstruction::set_compute_unit_price(42),
+            program_ix.clone(),
+        ]);
+        let limits = process_compute_budget_instructions(tx.message()).unwrap();
+        assert_eq!(limits.compute_unit_limit, MAX_COMPUTE_UNIT_LIMIT);
+        assert_eq!(limits.compute_unit_price, 42);
+
+        // The same request may not appear twice.
+        let tx = transaction(&[
+            ComputeBudgetInstruction::set_compute_unit_price(1),
+            ComputeBudgetInstruction::set_compute_unit_price(2),
+        ]);
+        assert_eq!(
+            process_compute_budget_instructions(tx.message()),
+            Err(TransactionError::DuplicateInstruction(1))
+        );
+    }
+
+    #[test]
+    fn test_execution_enforces_compute_unit_limit() {
+        let program_id = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(program_id, burn_600);
+        let store = HashMap::new();
+        let program_ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+
+        // 150 (compute budget ix) + 600 fits in a 1_000 CU budget.
+        let tx = transaction(&[
+            ComputeBudgetInstruction::set_compute_unit_limit(1_000),
+            program_ix.clone(),
+        ]);
+        let result = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.consumed_units, DEFAULT_COMPUTE_UNITS + 600);
+
+        // A second 600 CU instruction exhausts the budget.
+        let tx = transaction(&[
+            ComputeBudgetInstruction::set_compute_unit_limit(1_000),
+            program_ix.clone(),
+            program_ix,
+        ]);
+        let result = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                2,
+                InstructionError::ComputationalBudgetExceeded
+            ))
+        );
+        assert_eq!(result.consumed_units, 1_000);
+    }
+}
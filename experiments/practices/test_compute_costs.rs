@@ -0,0 +1,153 @@
+//! Unit test: Load compute cost tables and charge by them
+//!
+//! Analogy: The kitchen's price list used to be painted on the wall. Now it
+//! is a card the manager can swap: raise what a soufflé costs for one
+//! evening, leave every other dish as it was, and see whether the orders
+//! still come out in time. A card that lists a dish the kitchen has never
+//! heard of is sent back rather than quietly ignored.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::{
+        svm::{ComputeCostTable, ComputeCostTableError, TransactionExecutor},
+        system_program::DEFAULT_COMPUTE_UNITS,
+    };
+    use solana_account::{AccountSharedData, WritableAccount};
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::{bpf_loader, system_program};
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::{collections::HashMap, fs};
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    fn transaction(instruction: Instruction) -> SanitizedTransaction {
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_tables_override_only_the_costs_they_list() {
+        let table = ComputeCostTable::from_toml(
+            "[syscalls]\nsyscall_base_cost = 200\n\n\
+             [builtins]\n\"11111111111111111111111111111111\" = 300\n",
+        )
+        .unwrap();
+        assert_eq!(table.syscall_base_cost, 200);
+        assert_eq!(table.builtin_cost(&system_program::id(), 0), 300);
+        let unchanged = ComputeCostTable::default();
+        assert_eq!(table.edwards_multiply_cost, unchanged.edwards_multiply_cost);
+        assert_eq!(
+            unchanged.builtin_cost(&system_program::id(), 0),
+            DEFAULT_COMPUTE_UNITS
+        );
+        // A builtin the table does not list costs what it always did.
+        assert_eq!(table.builtin_cost(&Pubkey::new_unique(), 42), 42);
+
+        let json = r#"{"syscalls": {"syscall_base_cost": 200},
+            "builtins": {"11111111111111111111111111111111": 300}}"#;
+        assert_eq!(ComputeCostTable::from_json(json).unwrap(), table);
+        // Every cost survives a round trip, so a dumped table is a template.
+        let dumped = table.to_json().to_string();
+        assert_eq!(ComputeCostTable::from_json(&dumped).unwrap(), table);
+
+        assert!(matches!(
+            ComputeCostTable::from_json(r#"{"syscalls": {"sycall_base_cost": 1}}"#),
+            Err(ComputeCostTableError::UnknownCost(name)) if name == "syscalls.sycall_base_cost"
+        ));
+        assert!(matches!(
+            ComputeCostTable::from_toml("[syscalls]\nsyscall_base_cost = -1\n"),
+            Err(ComputeCostTableError::InvalidCost(_))
+        ));
+        assert!(matches!(
+            ComputeCostTable::from_json(r#"{"builtins": {"not a pubkey": 1}}"#),
+            Err(ComputeCostTableError::UnknownCost(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_picks_the_format_by_extension() {
+        let dir = std::env::temp_dir();
+        let name = format!(
+            "compute-costs-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        );
+        let toml = dir.join(format!("{name}.toml"));
+        let json = dir.join(format!("{name}.json"));
+        let yaml = dir.join(format!("{name}.yaml"));
+        fs::write(&toml, "[syscalls]\nhash_base_cost = 1\n").unwrap();
+        fs::write(&json, r#"{"syscalls": {"hash_base_cost": 1}}"#).unwrap();
+        fs::write(&yaml, "syscalls: {}\n").unwrap();
+
+        let from_toml = ComputeCostTable::load(&toml).unwrap();
+        assert_eq!(from_toml.hash_base_cost, 1);
+        assert_eq!(ComputeCostTable::load(&json).unwrap(), from_toml);
+        assert!(matches!(
+            ComputeCostTable::load(&yaml),
+            Err(ComputeCostTableError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            ComputeCostTable::load(dir.join(format!("{name}-missing.json"))),
+            Err(ComputeCostTableError::Io(_))
+        ));
+        for path in [toml, json, yaml] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_executor_charges_by_its_table() {
+        // Calls sol_log_64_ once and returns its status.
+        let program_id = Pubkey::new_unique();
+        let text = [
+            insn(0x85, 0, 0, 0, hash_symbol_name(b"sol_log_64_") as i32), // call
+            insn(0x95, 0, 0, 0, 0),                                       // exit
+        ];
+        let store = HashMap::from([(program_id, program_account(&text))]);
+        let to_program = transaction(Instruction::new_with_bytes(program_id, &[], vec![]));
+        let to_loader = transaction(Instruction::new_with_bytes(bpf_loader::id(), &[], vec![]));
+
+        let mut executor = TransactionExecutor::new();
+        let program = executor.load_and_execute_transaction(&store, &to_program);
+        assert_eq!(program.status, Ok(()));
+        let loader = executor.load_and_execute_transaction(&store, &to_loader);
+
+        let mut table = ComputeCostTable {
+            syscall_base_cost: 1_000,
+            ..ComputeCostTable::default()
+        };
+        table.builtins.insert(bpf_loader::id(), 2_000);
+        executor.set_compute_costs(table);
+        assert_eq!(executor.compute_costs().syscall_base_cost, 1_000);
+        let repriced = executor.load_and_execute_transaction(&store, &to_program);
+        assert_eq!(repriced.status, Ok(()));
+        assert_eq!(
+            repriced.consumed_units - program.consumed_units,
+            1_000 - 100
+        );
+        let repriced = executor.load_and_execute_transaction(&store, &to_loader);
+        assert_eq!(repriced.status, loader.status);
+        assert_eq!(repriced.consumed_units, 2_000);
+    }
+}
@@ -0,0 +1,144 @@
+//! Unit test: Report the most contended accounts of a run
+//!
+//! Analogy: After closing, the manager goes through the evening's seating
+//! log and lists the tables parties queued for: how often each was taken,
+//! how much the parties waiting for it would have tipped, and how many
+//! rounds of seating they waited.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            ContentionReport, PriorityGraphScheduler, PriorityPolicy, ScheduleEvent, ScheduleLog,
+            WorkAssignment, WorkerPool,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(writable: Pubkey, readonly: &[Pubkey], cu_price: u64) -> SanitizedTransaction {
+        let accounts = std::iter::once(AccountMeta::new(writable, false))
+            .chain(
+                readonly
+                    .iter()
+                    .map(|key| AccountMeta::new_readonly(*key, false)),
+            )
+            .collect();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_hot_account_tops_the_report() {
+        let hot_account = Pubkey::new_unique();
+        let mut transactions: Vec<SanitizedTransaction> = [3_000, 2_000, 1_000]
+            .map(|cu_price| transaction(hot_account, &[], cu_price))
+            .into();
+        transactions.push(transaction(Pubkey::new_unique(), &[], 0));
+
+        let pool = WorkerPool::new(2, |_: &SanitizedTransaction| ());
+        let (_, log) = pool
+            .run_recorded(&mut PriorityGraphScheduler::new(), transactions.clone())
+            .unwrap();
+        let priority_policy = PriorityPolicy::default();
+        let report = ContentionReport::from_schedule(&log, &transactions, &priority_policy);
+        assert_eq!(report.num_batches, 3);
+
+        // Both cheaper transactions waited on the hot account, one batch
+        // and two.
+        let [hot] = report.top(1) else {
+            panic!("the run locked accounts");
+        };
+        assert_eq!(hot.account, hot_account);
+        assert_eq!(hot.write_locks, 3);
+        assert_eq!(hot.transactions_blocked, 2);
+        assert_eq!(hot.wait_batches, 3);
+        let priority = |index: usize| {
+            priority_policy
+                .priority_id(&transactions[index], index)
+                .priority
+        };
+        assert_eq!(hot.blocked_priority, priority(1) + priority(2));
+        // Every transaction also read-locks the compute budget program,
+        // which holds nobody up.
+        assert!(report.accounts[1..]
+            .iter()
+            .all(|contention| contention.transactions_blocked == 0));
+
+        let csv = report.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                "account,write_locks,read_locks,transactions_blocked,blocked_priority,wait_batches"
+            )
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("{hot_account},3,0,2,{},3", hot.blocked_priority)
+        );
+        assert_eq!(lines.count(), report.accounts.len() - 1);
+        let json = report.to_json();
+        assert_eq!(json["numBatches"], 3);
+        assert_eq!(json["accounts"][0]["account"], hot_account.to_string());
+        assert_eq!(json["accounts"][0]["waitBatches"], 3);
+    }
+
+    #[test]
+    fn test_waits_are_charged_to_the_last_conflict() {
+        let [oracle, pool_a, pool_b] = [(); 3].map(|()| Pubkey::new_unique());
+        let transactions = vec![
+            // Writes the oracle both swaps read.
+            transaction(oracle, &[], 0),
+            transaction(pool_a, &[oracle], 0),
+            // Also waits for the first swap's pool.
+            transaction(pool_a, &[], 0),
+            transaction(pool_b, &[oracle], 0),
+        ];
+        let batch = |positions: &[usize]| {
+            ScheduleEvent::Batch(vec![WorkAssignment {
+                worker: 0,
+                positions: positions.to_vec(),
+            }])
+        };
+        let log = ScheduleLog {
+            num_workers: 1,
+            events: vec![
+                batch(&[0]),
+                ScheduleEvent::Completed(vec![0]),
+                batch(&[1, 3]),
+                ScheduleEvent::Completed(vec![1, 3]),
+                batch(&[2]),
+                ScheduleEvent::Completed(vec![2]),
+            ],
+        };
+        let report = ContentionReport::from_schedule(&log, &transactions, &PriorityPolicy::Fifo);
+        let contention = |account: Pubkey| {
+            report
+                .accounts
+                .iter()
+                .find(|contention| contention.account == account)
+                .unwrap()
+                .clone()
+        };
+        let oracle = contention(oracle);
+        assert_eq!((oracle.write_locks, oracle.read_locks), (1, 2));
+        assert_eq!((oracle.transactions_blocked, oracle.wait_batches), (2, 2));
+        // The third transaction went out after the oracle was free again,
+        // so only the pool it shares with the second one held it up.
+        let pool_a = contention(pool_a);
+        assert_eq!((pool_a.transactions_blocked, pool_a.wait_batches), (1, 2));
+        assert_eq!(contention(pool_b).transactions_blocked, 0);
+        // Tied on priority and wait, the account written more often ranks
+        // first.
+        let top: Vec<Pubkey> = report.top(2).iter().map(|c| c.account).collect();
+        assert_eq!(top, [pool_a.account, oracle.account]);
+    }
+}
@@ -0,0 +1,153 @@
+//! Unit test: Price transactions with the `CostModel` and cap blocks with a
+//! `CostTracker`
+//!
+//! Analogy: Before a ticket goes in, the host estimates how long it will
+//! keep the kitchen busy: so much for checking the reservation, so much for
+//! every station it ties up, so much for the dishes themselves. Once the
+//! evening's estimates fill the kitchen, or a single station, the host
+//! turns further parties away until the next evening; when a dish turns
+//! out quicker than estimated, the spare time goes back on the board.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::compute_budget::ComputeBudgetInstruction;
+    use priority_graph_practice::cost_model::{
+        CostModel, CostTracker, CostTrackerError, TransactionCost, ED25519_VERIFY_COST,
+        SIGNATURE_COST, WRITE_LOCK_UNITS,
+    };
+    use priority_graph_practice::system_program;
+    use solana_account::AccountSharedData;
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{ed25519_program, system_program as system_program_id};
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn transfer(from: &Pubkey, to: &Pubkey, cu_limit: u32) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+            system_program::transfer(from, to, 1_000_000),
+        ];
+        let message = Message::new(&instructions, Some(from));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn cost(units: u64, writable_accounts: &[Pubkey]) -> TransactionCost {
+        TransactionCost {
+            programs_execution_cost: units,
+            writable_accounts: writable_accounts.to_vec(),
+            ..TransactionCost::default()
+        }
+    }
+
+    #[test]
+    fn test_calculate_cost() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let cost = CostModel::calculate_cost(&transfer(&alice, &bob, 10_000));
+        assert_eq!(cost.signature_cost, SIGNATURE_COST);
+        assert_eq!(cost.write_lock_cost, 2 * WRITE_LOCK_UNITS);
+        // 5 bytes of compute budget and 12 bytes of transfer data.
+        assert_eq!(cost.data_bytes_cost, 17 / 4);
+        assert_eq!(cost.programs_execution_cost, 10_000);
+        // The default 64 MiB of loaded account data, in 32 KiB pages.
+        assert_eq!(cost.loaded_accounts_data_size_cost, 2048 * 8);
+        assert_eq!(cost.writable_accounts, vec![alice, bob]);
+        assert_eq!(
+            cost.sum(),
+            SIGNATURE_COST + 2 * WRITE_LOCK_UNITS + 4 + 10_000 + 2048 * 8
+        );
+
+        // Precompile signatures are priced on top of the transaction's own.
+        let precompile = Instruction::new_with_bytes(ed25519_program::id(), &[2, 0], vec![]);
+        let message = Message::new(&[precompile], Some(&alice));
+        let transaction =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let cost = CostModel::calculate_cost(&transaction);
+        assert_eq!(
+            cost.signature_cost,
+            SIGNATURE_COST + 2 * ED25519_VERIFY_COST
+        );
+        assert_eq!(cost.writable_accounts, vec![alice]);
+    }
+
+    #[test]
+    fn test_cost_tracker_limits() {
+        let hot = Pubkey::new_unique();
+        let cold = Pubkey::new_unique();
+        let mut tracker = CostTracker::new(1_000, 400);
+        assert_eq!(tracker.try_add(&cost(300, &[hot])), Ok(300));
+        assert_eq!(
+            tracker.try_add(&cost(200, &[hot, cold])),
+            Err(CostTrackerError::WouldExceedAccountMaxLimit)
+        );
+        assert_eq!(tracker.try_add(&cost(400, &[cold])), Ok(700));
+        assert_eq!(
+            tracker.try_add(&cost(400, &[])),
+            Err(CostTrackerError::WouldExceedBlockMaxLimit)
+        );
+        assert_eq!(tracker.account_cost(&hot), 300);
+        assert_eq!(tracker.transaction_count(), 2);
+
+        // Executing with fewer units than requested frees the difference.
+        tracker.update_execution_cost(&cost(300, &[hot]), 100);
+        assert_eq!(tracker.block_cost(), 500);
+        assert_eq!(tracker.account_cost(&hot), 100);
+        assert_eq!(tracker.try_add(&cost(300, &[hot])), Ok(800));
+
+        tracker.remove_transaction_cost(&cost(400, &[cold]));
+        assert_eq!(tracker.block_cost(), 400);
+        assert_eq!(tracker.account_cost(&cold), 0);
+        tracker.reset();
+        assert_eq!(tracker.block_cost(), 0);
+        assert_eq!(tracker.transaction_count(), 0);
+        assert_eq!(tracker.block_cost_limit(), 1_000);
+    }
+
+    #[test]
+    fn test_bank_rejects_transactions_over_the_account_limit() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let hot = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        for payer in [alice, bob] {
+            bank.store_account(
+                payer,
+                AccountSharedData::new(LAMPORTS, 0, &system_program_id::id()),
+            );
+        }
+        let estimate = CostModel::calculate_cost(&transfer(&alice, &hot, 10_000)).sum();
+        // Room for one transaction writing `hot`, judging by its estimate.
+        bank.set_block_cost_limits(u64::MAX, estimate + estimate / 2);
+
+        let results = bank.process_transaction_batch(&[
+            transfer(&alice, &hot, 10_000),
+            transfer(&bob, &hot, 10_000),
+            transfer(&bob, &Pubkey::new_unique(), 10_000),
+        ]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(
+            results[1].as_ref().unwrap_err(),
+            &TransactionError::WouldExceedMaxAccountCostLimit
+        );
+        assert!(results[2].as_ref().unwrap().was_successful());
+        // The transfers consumed far fewer units than they requested.
+        let consumed = results[0].as_ref().unwrap().consumed_units;
+        assert!(consumed < 10_000);
+        assert_eq!(bank.cost_tracker().transaction_count(), 2);
+        assert_eq!(
+            bank.cost_tracker().account_cost(&hot),
+            estimate - 10_000 + consumed
+        );
+
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(bank.cost_tracker().block_cost(), 0);
+        let results = bank.process_transaction_batch(&[transfer(&bob, &hot, 10_000)]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+    }
+}
@@ -0,0 +1,205 @@
+//! Unit test: Invoke programs from other programs
+//!
+//! Analogy: A cook can hand part of a dish to another station, but only
+//! with ingredients already on their own board and only with the authority
+//! they were given. If the other station ruins its part, the board goes
+//! back the way it was, and tickets can't bounce between stations forever.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        svm::{InvokeContext, TransactionExecutor, MAX_INVOKE_DEPTH},
+        system_program,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    fn transaction(instruction: Instruction) -> SanitizedTransaction {
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[instruction], Some(&payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn post_account<'a>(
+        post_accounts: &'a [(Pubkey, AccountSharedData)],
+        pubkey: &Pubkey,
+    ) -> &'a AccountSharedData {
+        &post_accounts
+            .iter()
+            .find(|(key, _)| key == pubkey)
+            .unwrap()
+            .1
+    }
+
+    // Increments the first byte of account 0, then invokes itself until the
+    // invoke depth runs out.
+    fn recurse(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        invoke_context.get_account_mut(0)?.data_as_mut_slice()[0] += 1;
+        let program_id = *invoke_context.program_id();
+        let counter = *invoke_context.get_key(0)?;
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(program_id, false),
+            ],
+        );
+        match invoke_context.invoke(&instruction) {
+            Err(InstructionError::CallDepth) => Ok(()),
+            result => result,
+        }
+    }
+
+    #[test]
+    fn test_invoke_signed_with_program_address() {
+        let program_id = Pubkey::new_unique();
+        let (vault, bump) = Pubkey::find_program_address(&[b"vault"], &program_id);
+        let recipient = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        // Transfers 100 lamports out of the vault, signing for it with the
+        // bump seed in the instruction data, if any.
+        executor.add_program(program_id, |invoke_context: &mut InvokeContext| {
+            let vault = *invoke_context.get_key(0)?;
+            let recipient = *invoke_context.get_key(1)?;
+            let transfer = system_program::transfer(&vault, &recipient, 100);
+            match invoke_context.instruction_data().first() {
+                Some(&bump) => invoke_context.invoke_signed(&transfer, &[&[b"vault", &[bump]]]),
+                None => invoke_context.invoke(&transfer),
+            }
+        });
+
+        let mut store = HashMap::new();
+        store.insert(
+            vault,
+            AccountSharedData::new(1_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        // The recipient is already rent exempt, so 100 lamports may land.
+        let exempt = Rent::default().minimum_balance(0);
+        store.insert(
+            recipient,
+            AccountSharedData::new(exempt, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let accounts = vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(recipient, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+        ];
+
+        let signed = Instruction::new_with_bytes(program_id, &[bump], accounts.clone());
+        let result = executor.load_and_execute_transaction(&store, &transaction(signed));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(post_account(&result.post_accounts, &vault).lamports(), 900);
+        assert_eq!(
+            post_account(&result.post_accounts, &recipient).lamports(),
+            exempt + 100
+        );
+
+        let unsigned = Instruction::new_with_bytes(program_id, &[], accounts);
+        let result = executor.load_and_execute_transaction(&store, &transaction(unsigned));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::PrivilegeEscalation
+            ))
+        );
+    }
+
+    #[test]
+    fn test_max_invoke_depth() {
+        let program_id = Pubkey::new_unique();
+        let counter = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(counter, AccountSharedData::new(1, 1, &program_id));
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(program_id, false),
+            ],
+        );
+
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(program_id, recurse);
+        let result =
+            executor.load_and_execute_transaction(&store, &transaction(instruction.clone()));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(
+            post_account(&result.post_accounts, &counter).data(),
+            &[MAX_INVOKE_DEPTH as u8]
+        );
+
+        executor.set_max_invoke_depth(2);
+        let result = executor.load_and_execute_transaction(&store, &transaction(instruction));
+        assert_eq!(post_account(&result.post_accounts, &counter).data(), &[2]);
+    }
+
+    #[test]
+    fn test_failed_invoke_rolls_back_and_reentrancy() {
+        let caller = Pubkey::new_unique();
+        let callee = Pubkey::new_unique();
+        let target = Pubkey::new_unique();
+        let report = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        // Invokes the callee and reports what it returned in the report's
+        // byte, without failing itself.
+        executor.add_program(caller, move |invoke_context: &mut InvokeContext| {
+            let instruction = Instruction::new_with_bytes(
+                callee,
+                invoke_context.instruction_data(),
+                vec![
+                    AccountMeta::new(target, false),
+                    AccountMeta::new_readonly(caller, false),
+                ],
+            );
+            let status = match invoke_context.invoke(&instruction) {
+                Ok(()) => 1,
+                Err(InstructionError::ReentrancyNotAllowed) => 2,
+                Err(_) => 3,
+            };
+            invoke_context.get_account_mut(1)?.data_as_mut_slice()[0] = status;
+            Ok(())
+        });
+        // Writes the target's first byte, then either fails or calls back
+        // into the caller.
+        executor.add_program(callee, move |invoke_context: &mut InvokeContext| {
+            invoke_context.get_account_mut(0)?.data_as_mut_slice()[0] = 42;
+            if invoke_context.instruction_data().is_empty() {
+                return Err(InstructionError::Custom(0));
+            }
+            let reenter = Instruction::new_with_bytes(caller, &[], vec![]);
+            invoke_context.invoke(&reenter)
+        });
+
+        let mut store = HashMap::new();
+        store.insert(target, AccountSharedData::new(1, 1, &callee));
+        store.insert(report, AccountSharedData::new(1, 1, &caller));
+        let accounts = vec![
+            AccountMeta::new(target, false),
+            AccountMeta::new(report, false),
+            AccountMeta::new_readonly(callee, false),
+            AccountMeta::new_readonly(caller, false),
+        ];
+
+        let failing = Instruction::new_with_bytes(caller, &[], accounts.clone());
+        let result = executor.load_and_execute_transaction(&store, &transaction(failing));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(post_account(&result.post_accounts, &target).data(), &[0]);
+        assert_eq!(post_account(&result.post_accounts, &report).data(), &[3]);
+
+        let reentering = Instruction::new_with_bytes(caller, &[1], accounts);
+        let result = executor.load_and_execute_transaction(&store, &transaction(reentering));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(post_account(&result.post_accounts, &target).data(), &[0]);
+        assert_eq!(post_account(&result.post_accounts, &report).data(), &[2]);
+    }
+}
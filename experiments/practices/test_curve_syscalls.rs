@@ -0,0 +1,220 @@
+//! Unit test: Run curve25519 and alt_bn128 operations from SBF programs
+//!
+//! Analogy: Some recipes need a pastry chef's tools: a guest chef cannot
+//! bring their own, so the kitchen lends out its sugar thermometer and
+//! moulds by name. Borrowing the big mould costs more than the small one,
+//! and a tray that is not actually pastry is handed back with a note
+//! instead of a finished cake.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::svm::TransactionExecutor;
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::bpf_loader;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    /// Offset of the first account's data in the input region: the account
+    /// count, then the account's flags, key, owner, lamports and data length.
+    const FIRST_ACCOUNT_DATA: i32 = 8 + 8 + 32 + 32 + 8 + 8;
+
+    /// The compressed Edwards and Ristretto basepoints.
+    const EDWARDS_BASEPOINT: [u8; 32] = {
+        let mut point = [0x66; 32];
+        point[0] = 0x58;
+        point
+    };
+    const RISTRETTO_BASEPOINT: [u8; 32] = [
+        0xe2, 0xf2, 0xae, 0x0a, 0x6a, 0xbc, 0x4e, 0x71, 0xa8, 0x84, 0xa9, 0x61, 0xc5, 0x00, 0x51,
+        0x5f, 0x58, 0xe3, 0x0b, 0x6a, 0xa5, 0x82, 0xdd, 0x8d, 0xb6, 0xa6, 0x59, 0x45, 0xe0, 0x8d,
+        0x2d, 0x76,
+    ];
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    fn call(syscall: &[u8]) -> [u8; 8] {
+        insn(0x85, 0, 0, 0, hash_symbol_name(syscall) as i32)
+    }
+
+    /// Validates the point after the curve id in the instruction data.
+    fn validate_point_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0x71, 1, 2, 0, 0), // ldxb r1, [r2]
+            insn(0x07, 2, 0, 0, 1), // add64 r2, 1
+            call(b"sol_curve_validate_point"),
+            insn(0x95, 0, 0, 0, 0), // exit
+        ]
+    }
+
+    /// Runs the curve id and group operation in the first two instruction
+    /// data bytes on the two inputs after them, into the first account.
+    fn group_op_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0xbf, 5, 1, 0, 0),                  // mov64 r5, r1
+            insn(0x07, 5, 0, 0, FIRST_ACCOUNT_DATA), // add64 r5, data
+            insn(0x71, 1, 2, 0, 0),                  // ldxb r1, [r2]
+            insn(0xbf, 3, 2, 0, 0),                  // mov64 r3, r2
+            insn(0x07, 3, 0, 0, 2),                  // add64 r3, 2
+            insn(0xbf, 4, 2, 0, 0),                  // mov64 r4, r2
+            insn(0x07, 4, 0, 0, 34),                 // add64 r4, 34
+            insn(0x71, 2, 2, 1, 0),                  // ldxb r2, [r2 + 1]
+            call(b"sol_curve_group_op"),
+            insn(0x95, 0, 0, 0, 0), // exit
+        ]
+    }
+
+    /// Runs the alt_bn128 operation in the first instruction data byte on
+    /// the rest of the data, into the first account.
+    fn alt_bn128_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0xbf, 4, 1, 0, 0),                  // mov64 r4, r1
+            insn(0x07, 4, 0, 0, FIRST_ACCOUNT_DATA), // add64 r4, data
+            insn(0x79, 3, 2, -8, 0),                 // ldxdw r3, [r2 - 8]
+            insn(0x07, 3, 0, 0, -1),                 // add64 r3, -1
+            insn(0x71, 1, 2, 0, 0),                  // ldxb r1, [r2]
+            insn(0x07, 2, 0, 0, 1),                  // add64 r2, 1
+            call(b"sol_alt_bn128_group_op"),
+            insn(0x95, 0, 0, 0, 0), // exit
+        ]
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    /// Runs `text` on `data` with a 64 byte output account, returning the
+    /// status, the output and the compute units consumed.
+    fn run(text: &[[u8; 8]], data: &[u8]) -> (Result<(), TransactionError>, Vec<u8>, u64) {
+        let program_id = Pubkey::new_unique();
+        let output = Pubkey::new_unique();
+        let store = HashMap::from([
+            (program_id, program_account(text)),
+            (output, AccountSharedData::new(1, 64, &program_id)),
+        ]);
+        let instruction =
+            Instruction::new_with_bytes(program_id, data, vec![AccountMeta::new(output, false)]);
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[instruction], Some(&payer));
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+
+        let result = TransactionExecutor::new().load_and_execute_transaction(&store, &tx);
+        let (_, post) = result
+            .post_accounts
+            .iter()
+            .find(|(key, _)| *key == output)
+            .unwrap();
+        (result.status, post.data().to_vec(), result.consumed_units)
+    }
+
+    fn group_op(curve_id: u8, group_op: u8, left: &[u8; 32], right: &[u8; 32]) -> Vec<u8> {
+        let data = [&[curve_id, group_op][..], left, right].concat();
+        let (status, output, _) = run(&group_op_program(), &data);
+        assert_eq!(status, Ok(()));
+        output[..32].to_vec()
+    }
+
+    #[test]
+    fn test_sol_curve_validate_point() {
+        for (curve_id, point) in [(0, EDWARDS_BASEPOINT), (1, RISTRETTO_BASEPOINT)] {
+            let data = [&[curve_id][..], &point].concat();
+            let (status, _, _) = run(&validate_point_program(), &data);
+            assert_eq!(status, Ok(()));
+        }
+
+        // A non-canonical encoding is not a Ristretto point.
+        let data = [&[1][..], &[0xff; 32]].concat();
+        let (status, _, _) = run(&validate_point_program(), &data);
+        assert_eq!(
+            status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1)
+            ))
+        );
+
+        // Unknown curves abort the program.
+        let data = [&[7][..], &EDWARDS_BASEPOINT].concat();
+        let (status, _, _) = run(&validate_point_program(), &data);
+        assert_eq!(
+            status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ProgramFailedToComplete
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sol_curve_group_op() {
+        let mut two = [0; 32];
+        two[0] = 2;
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        for (curve_id, basepoint) in [(0, EDWARDS_BASEPOINT), (1, RISTRETTO_BASEPOINT)] {
+            let doubled = group_op(curve_id, 0, &basepoint, &basepoint);
+            assert_eq!(group_op(curve_id, 2, &two, &basepoint), doubled);
+            let difference = group_op(curve_id, 1, &basepoint, &basepoint);
+            // The Edwards identity is (0, 1); the Ristretto one encodes as zeroes.
+            let expected = if curve_id == 0 { identity } else { [0; 32] };
+            assert_eq!(difference, expected);
+        }
+    }
+
+    #[test]
+    fn test_sol_alt_bn128_group_op() {
+        // The G1 generator (1, 2), big-endian.
+        let mut generator = [0; 64];
+        generator[31] = 1;
+        generator[63] = 2;
+        let mut two = [0; 32];
+        two[31] = 2;
+
+        let addition = [&[0][..], &generator, &generator].concat();
+        let (status, sum, add_units) = run(&alt_bn128_program(), &addition);
+        assert_eq!(status, Ok(()));
+        let multiplication = [&[2][..], &generator, &two].concat();
+        let (status, product, mul_units) = run(&alt_bn128_program(), &multiplication);
+        assert_eq!(status, Ok(()));
+        assert_eq!(sum, product);
+        assert_eq!(mul_units - add_units, 3_840 - 334);
+
+        // A pairing check over no pairs holds.
+        let (status, result, _) = run(&alt_bn128_program(), &[3]);
+        assert_eq!(status, Ok(()));
+        assert_eq!(result[31], 1);
+
+        // Points off the curve are reported through the status.
+        let mut off_curve = generator;
+        off_curve[63] = 3;
+        let addition = [&[0][..], &off_curve, &generator].concat();
+        let (status, _, _) = run(&alt_bn128_program(), &addition);
+        assert_eq!(
+            status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1)
+            ))
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Unit test: Reproduce randomized runs from a seed
+//!
+//! Analogy: When two parties arrive at the same moment, the host flips a
+//! coin to pick who is seated first. Writing down which coin was used lets
+//! the manager replay the whole evening, coin flips, slow cooks and all,
+//! and see the same tables filled in the same order.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        determinism::{Determinism, JITTER_STREAM, TIE_BREAK_STREAM},
+        scheduler::{
+            Batch, BatchLimits, PriorityGraphScheduler, TransactionId, TransactionScheduler,
+            WorkerPool,
+        },
+        workload::{Workload, WorkloadConfig},
+    };
+    use rand::Rng;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::time::Duration;
+
+    fn transaction() -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(Pubkey::new_unique(), false)],
+        );
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    // Remembers every batch the wrapped scheduler hands out.
+    struct Recording {
+        scheduler: PriorityGraphScheduler,
+        batches: Vec<Vec<TransactionId>>,
+    }
+
+    impl TransactionScheduler for Recording {
+        fn push(&mut self, transaction: SanitizedTransaction) -> TransactionId {
+            self.scheduler.push(transaction)
+        }
+
+        fn next_batch(&mut self, limits: &BatchLimits) -> Batch {
+            let batch = self.scheduler.next_batch(limits);
+            if !batch.is_empty() {
+                self.batches.push(batch.ids.clone());
+            }
+            batch
+        }
+
+        fn complete(&mut self, ids: &[TransactionId]) {
+            self.scheduler.complete(ids);
+        }
+
+        fn num_pending(&self) -> usize {
+            self.scheduler.num_pending()
+        }
+    }
+
+    #[test]
+    fn test_seeded_streams_repeat() {
+        let seeded = Determinism::Seeded(7);
+        assert!(seeded.is_seeded());
+        assert_eq!(seeded.seed(), Some(7));
+        assert_eq!(Determinism::default().seed(), None);
+
+        let draw = |determinism: Determinism, stream| -> Vec<u64> {
+            let mut rng = determinism.rng(stream);
+            (0..4).map(|_| rng.gen()).collect()
+        };
+        assert_eq!(
+            draw(seeded, TIE_BREAK_STREAM),
+            draw(seeded, TIE_BREAK_STREAM)
+        );
+        assert_ne!(draw(seeded, TIE_BREAK_STREAM), draw(seeded, JITTER_STREAM));
+        assert_ne!(
+            draw(seeded, TIE_BREAK_STREAM),
+            draw(Determinism::Seeded(8), TIE_BREAK_STREAM)
+        );
+    }
+
+    #[test]
+    fn test_random_tie_breaks_repeat_with_the_same_seed() {
+        let transactions: Vec<_> = (0..20).map(|_| transaction()).collect();
+        let order = |scheduler: PriorityGraphScheduler| {
+            let mut scheduler = scheduler;
+            for transaction in &transactions {
+                scheduler.push(transaction.clone());
+            }
+            scheduler.next_batch(&BatchLimits::default()).ids
+        };
+
+        let arrival_order: Vec<_> = (0..transactions.len()).collect();
+        assert_eq!(order(PriorityGraphScheduler::new()), arrival_order);
+        let shuffled =
+            order(PriorityGraphScheduler::new().with_random_tie_breaks(Determinism::Seeded(3)));
+        assert_ne!(shuffled, arrival_order);
+        assert_eq!(
+            order(PriorityGraphScheduler::new().with_random_tie_breaks(Determinism::Seeded(3))),
+            shuffled
+        );
+    }
+
+    #[test]
+    fn test_seeded_worker_pool_repeats_its_schedule() {
+        let workload = Workload::generate(&WorkloadConfig {
+            num_transactions: 60,
+            num_payers: 30,
+            hot_account_ratio: 0.5,
+            num_hot_accounts: 3,
+            ..WorkloadConfig::default()
+        });
+        let run = |seed| {
+            let determinism = Determinism::Seeded(seed);
+            let pool = WorkerPool::new(4, |_: &SanitizedTransaction| ())
+                .with_batch_limits(BatchLimits {
+                    max_txs: 4,
+                    ..BatchLimits::default()
+                })
+                .with_jitter(Duration::from_micros(200))
+                .with_determinism(determinism);
+            let mut scheduler = Recording {
+                scheduler: PriorityGraphScheduler::new().with_random_tie_breaks(determinism),
+                batches: Vec::new(),
+            };
+            pool.run(&mut scheduler, workload.transactions.clone())
+                .unwrap();
+            scheduler.batches
+        };
+
+        let batches = run(11);
+        assert_eq!(
+            batches.iter().map(Vec::len).sum::<usize>(),
+            workload.transactions.len()
+        );
+        for _ in 0..3 {
+            assert_eq!(run(11), batches);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Unit test: Map large account data into SBF programs without copying it
+//!
+//! Analogy: A banquet order comes with a huge ledger of guests. Instead of
+//! photocopying it for every cook who only needs to look up a name, the
+//! kitchen passes the original around, and only makes a copy for the cook
+//! who has to write in it.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::svm::{
+        TransactionExecutor, MAX_PERMITTED_DATA_INCREASE, MIN_DIRECT_MAPPED_DATA_LEN,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::bpf_loader;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    const LEN: usize = MIN_DIRECT_MAPPED_DATA_LEN;
+    /// Offsets of the first account's data length and data in the input
+    /// region; see `test_sbf_program.rs`.
+    const FIRST_ACCOUNT_DATA_LEN: i16 = 8 + 8 + 32 + 32 + 8;
+    const FIRST_ACCOUNT_DATA: i16 = FIRST_ACCOUNT_DATA_LEN + 8;
+    /// Offset of the second account's data when the first one holds `LEN`
+    /// bytes: its spare bytes, its rent epoch and the second header follow.
+    const SECOND_ACCOUNT_DATA: usize =
+        FIRST_ACCOUNT_DATA as usize + LEN + MAX_PERMITTED_DATA_INCREASE + 8 + 88;
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    /// A rent-exempt account of `LEN` bytes counting up from 0.
+    fn large_account(owner: &Pubkey) -> AccountSharedData {
+        let lamports = Rent::default().minimum_balance(LEN + MAX_PERMITTED_DATA_INCREASE);
+        let mut account = AccountSharedData::new(lamports, 0, owner);
+        account.set_data_from_slice(&(0..LEN).map(|i| i as u8).collect::<Vec<_>>());
+        account
+    }
+
+    fn transaction(instruction: Instruction) -> SanitizedTransaction {
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[instruction], Some(&payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn post_account<'a>(
+        accounts: &'a [(Pubkey, AccountSharedData)],
+        pubkey: &Pubkey,
+    ) -> &'a AccountSharedData {
+        &accounts.iter().find(|(key, _)| key == pubkey).unwrap().1
+    }
+
+    #[test]
+    fn test_read_only_use_shares_account_data() {
+        let program_id = Pubkey::new_unique();
+        let target = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(
+            program_id,
+            program_account(&[
+                insn(0x71, 1, 1, FIRST_ACCOUNT_DATA + 42, 0), // ldxb r1, [r1 + data + 42]
+                insn(0x85, 0, 0, 0, hash_symbol_name(b"sol_log_64_") as i32), // call
+                insn(0xb7, 0, 0, 0, 0),                       // mov64 r0, 0
+                insn(0x95, 0, 0, 0, 0),                       // exit
+            ]),
+        );
+        store.insert(target, large_account(&program_id));
+
+        let executor = TransactionExecutor::new();
+        for meta in [
+            AccountMeta::new_readonly(target, false),
+            AccountMeta::new(target, false),
+        ] {
+            let ix = Instruction::new_with_bytes(program_id, &[], vec![meta]);
+            let result = executor.load_and_execute_transaction(&store, &transaction(ix));
+            assert_eq!(result.status, Ok(()));
+            assert!(result.log_messages[1].starts_with("Program log: 0x2a, "));
+            // Neither the program nor the commit copied the data.
+            let post = post_account(&result.post_accounts, &target);
+            assert_eq!(post.data().as_ptr(), store[&target].data().as_ptr());
+        }
+    }
+
+    #[test]
+    fn test_first_write_copies_account_data() {
+        let program_id = Pubkey::new_unique();
+        let large = Pubkey::new_unique();
+        let small = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(
+            program_id,
+            program_account(&[
+                insn(0x71, 3, 2, 0, 0),                                      // ldxb r3, [r2]
+                insn(0x73, 1, 3, FIRST_ACCOUNT_DATA, 0),                     // stxb [r1 + data], r3
+                insn(0xbf, 4, 1, 0, 0),                                      // mov64 r4, r1
+                insn(0x07, 4, 0, 0, FIRST_ACCOUNT_DATA as i32 + LEN as i32), // add64 r4, end
+                insn(0x73, 4, 3, 0, 0),                                      // stxb [r4], r3
+                insn(0x7a, 1, 0, FIRST_ACCOUNT_DATA_LEN, LEN as i32 + 1), // stdw [r1 + len], LEN + 1
+                insn(0xbf, 4, 1, 0, 0),                                   // mov64 r4, r1
+                insn(0x07, 4, 0, 0, SECOND_ACCOUNT_DATA as i32),          // add64 r4, second data
+                insn(0x73, 4, 3, 0, 0),                                   // stxb [r4], r3
+                insn(0xb7, 0, 0, 0, 0),                                   // mov64 r0, 0
+                insn(0x95, 0, 0, 0, 0),                                   // exit
+            ]),
+        );
+        store.insert(large, large_account(&program_id));
+        store.insert(small, AccountSharedData::new(1, 4, &program_id));
+
+        let ix = Instruction::new_with_bytes(
+            program_id,
+            &[0xff],
+            vec![
+                AccountMeta::new(large, false),
+                AccountMeta::new(small, false),
+            ],
+        );
+        let result =
+            TransactionExecutor::new().load_and_execute_transaction(&store, &transaction(ix));
+        assert_eq!(result.status, Ok(()));
+
+        // The program wrote its copy, grown into the spare bytes, while
+        // the stored account kept its data.
+        let post = post_account(&result.post_accounts, &large);
+        assert_eq!(post.data().len(), LEN + 1);
+        assert_eq!(&post.data()[..3], &[0xff, 1, 2]);
+        assert_eq!(post.data()[LEN], 0xff);
+        assert_eq!(store[&large].data().len(), LEN);
+        assert_eq!(store[&large].data()[0], 0);
+        // Accounts after mapped data are laid out as if it were copied.
+        assert_eq!(
+            post_account(&result.post_accounts, &small).data(),
+            &[0xff, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_read_only_data_stays_immutable() {
+        let program_id = Pubkey::new_unique();
+        let target = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(
+            program_id,
+            program_account(&[
+                insn(0x72, 1, 0, FIRST_ACCOUNT_DATA, 7), // stb [r1 + data], 7
+                insn(0xb7, 0, 0, 0, 0),                  // mov64 r0, 0
+                insn(0x95, 0, 0, 0, 0),                  // exit
+            ]),
+        );
+        store.insert(target, large_account(&program_id));
+
+        let executor = TransactionExecutor::new();
+        let ix = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![AccountMeta::new_readonly(target, false)],
+        );
+        let result = executor.load_and_execute_transaction(&store, &transaction(ix));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ReadonlyDataModified
+            ))
+        );
+
+        // Shrinking only changes the length, which needs no copy either.
+        let shrink_id = Pubkey::new_unique();
+        store.insert(
+            shrink_id,
+            program_account(&[
+                insn(0x7a, 1, 0, FIRST_ACCOUNT_DATA_LEN, 4), // stdw [r1 + len], 4
+                insn(0xb7, 0, 0, 0, 0),                      // mov64 r0, 0
+                insn(0x95, 0, 0, 0, 0),                      // exit
+            ]),
+        );
+        store.get_mut(&target).unwrap().set_owner(shrink_id);
+        let ix = Instruction::new_with_bytes(shrink_id, &[], vec![AccountMeta::new(target, false)]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(ix));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(
+            post_account(&result.post_accounts, &target).data(),
+            &[0, 1, 2, 3]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Unit test: Accept durable nonce transactions in the `Bank`
+//!
+//! Analogy: A regular reservation expires after a while, but a party can
+//! also hold a numbered voucher. The host accepts the voucher no matter how
+//! old it is, then punches it so the same voucher never works twice.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::{get_durable_nonce, Bank},
+        svm::InvokeContext,
+        system_program,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_clock::MAX_PROCESSING_AGE;
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_nonce::{
+        state::{DurableNonce, State},
+        versions::Versions,
+    };
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    fn nonce_account(authority: &Pubkey, durable_nonce: DurableNonce) -> AccountSharedData {
+        let state = State::new_initialized(authority, durable_nonce, 5_000);
+        let mut account = AccountSharedData::new(
+            1_000_000,
+            State::size(),
+            &solana_sdk_ids::system_program::id(),
+        );
+        account.set_data_from_slice(&bincode::serialize(&Versions::new(state)).unwrap());
+        account
+    }
+
+    fn stored_nonce(bank: &Bank, nonce: &Pubkey) -> Hash {
+        let account = bank.get_account(nonce).unwrap();
+        match bincode::deserialize::<Versions>(account.data())
+            .unwrap()
+            .state()
+        {
+            State::Initialized(data) => data.blockhash(),
+            State::Uninitialized => panic!("nonce account is not initialized"),
+        }
+    }
+
+    fn nonce_transaction(
+        nonce: &Pubkey,
+        authority: &Pubkey,
+        instruction: Instruction,
+        durable_nonce: &Hash,
+    ) -> SanitizedTransaction {
+        let instructions = [
+            system_program::advance_nonce_account(nonce, authority),
+            instruction,
+        ];
+        let message = Message::new_with_blockhash(&instructions, Some(authority), durable_nonce);
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    // Sets up a bank where the genesis blockhash is long expired and a nonce
+    // account holds a durable nonce derived from it.
+    fn setup(program_id: Pubkey) -> (Bank, Pubkey, Pubkey, Hash) {
+        let mut bank = Bank::default();
+        bank.add_builtin(program_id, |_: &mut InvokeContext| Ok(()));
+        let durable_nonce = DurableNonce::from_blockhash(&bank.last_blockhash());
+        let authority = Pubkey::new_unique();
+        let nonce = Pubkey::new_unique();
+        bank.store_account(nonce, nonce_account(&authority, durable_nonce));
+        // The authority pays the fees.
+        bank.store_account(
+            authority,
+            AccountSharedData::new(1_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        for _ in 0..=MAX_PROCESSING_AGE {
+            bank.advance_slot(Hash::new_unique());
+        }
+        (bank, nonce, authority, *durable_nonce.as_hash())
+    }
+
+    #[test]
+    fn test_nonce_transaction_advances_nonce() {
+        let program_id = Pubkey::new_unique();
+        let (mut bank, nonce, authority, durable_nonce) = setup(program_id);
+        let tx = nonce_transaction(
+            &nonce,
+            &authority,
+            Instruction::new_with_bytes(program_id, &[], vec![]),
+            &durable_nonce,
+        );
+        assert_eq!(get_durable_nonce(tx.message()), Some(&nonce));
+
+        let results = bank.process_transaction_batch(std::slice::from_ref(&tx));
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(
+            stored_nonce(&bank, &nonce),
+            *DurableNonce::from_blockhash(&bank.last_blockhash()).as_hash()
+        );
+
+        // The stored nonce moved on, so the same transaction cannot replay.
+        assert_eq!(
+            bank.process_transaction_batch(&[tx]),
+            vec![Err(TransactionError::BlockhashNotFound)]
+        );
+    }
+
+    #[test]
+    fn test_failed_nonce_transaction_still_advances_nonce() {
+        let program_id = Pubkey::new_unique();
+        let (mut bank, nonce, authority, durable_nonce) = setup(program_id);
+        let tx = nonce_transaction(
+            &nonce,
+            &authority,
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]),
+            &durable_nonce,
+        );
+
+        let results = bank.process_transaction_batch(&[tx]);
+        assert_eq!(
+            results[0].as_ref().unwrap().status,
+            Err(TransactionError::InstructionError(
+                1,
+                InstructionError::UnsupportedProgramId
+            ))
+        );
+        assert_ne!(stored_nonce(&bank, &nonce), durable_nonce);
+    }
+
+    #[test]
+    fn test_nonce_requires_leading_advance_instruction() {
+        let program_id = Pubkey::new_unique();
+        let (mut bank, nonce, authority, durable_nonce) = setup(program_id);
+
+        // The advance instruction has to come first.
+        let instructions = [
+            Instruction::new_with_bytes(program_id, &[], vec![]),
+            system_program::advance_nonce_account(&nonce, &authority),
+        ];
+        let message = Message::new_with_blockhash(&instructions, Some(&authority), &durable_nonce);
+        let misplaced =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        assert_eq!(get_durable_nonce(misplaced.message()), None);
+
+        // The nonce authority has to sign.
+        let mut advance = system_program::advance_nonce_account(&nonce, &authority);
+        advance.accounts[2].is_signer = false;
+        let message =
+            Message::new_with_blockhash(&[advance], Some(&Pubkey::new_unique()), &durable_nonce);
+        let unsigned =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+
+        assert_eq!(
+            bank.process_transaction_batch(&[misplaced, unsigned]),
+            vec![
+                Err(TransactionError::BlockhashNotFound),
+                Err(TransactionError::BlockhashNotFound)
+            ]
+        );
+    }
+}
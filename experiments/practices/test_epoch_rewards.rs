@@ -0,0 +1,259 @@
+//! Unit test: Pay inflation rewards over the first blocks of an epoch
+//!
+//! Analogy: At the start of every week the owner works out the bonus pot
+//! from how much money the restaurant holds, and shares it out by how much
+//! every diner's tab backed a station times how many dishes that station
+//! confirmed. The station keeps its cut on the spot, while the diners'
+//! shares are handed out table by table over the next few services, and
+//! nobody may touch a tab until every table got theirs.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{bank::Bank, rent_collector::SLOTS_PER_YEAR, vote_program};
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_epoch_schedule::EpochSchedule;
+    use solana_hash::Hash;
+    use solana_inflation::Inflation;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{stake, system_program};
+    use solana_stake_interface::{
+        instruction as stake_instruction,
+        stake_flags::StakeFlags,
+        state::{Delegation, Meta, Stake, StakeStateV2},
+    };
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use solana_vote_interface::state::{VoteInit, VoteStateV3, VoteStateVersions};
+
+    const LAMPORTS: u64 = 1_000_000_000_000;
+
+    /// A bank with 32-slot epochs, where `payer` is funded and validators
+    /// earn all of the bank's capitalization a year.
+    fn rewards_bank(payer: &Pubkey) -> Bank {
+        let mut bank = Bank::default();
+        bank.set_epoch_schedule(EpochSchedule::custom(32, 32, false));
+        bank.set_inflation(Inflation::new_fixed(1.0));
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &system_program::id()),
+        );
+        bank
+    }
+
+    /// Stores a vote account keeping `commission` percent, which earned
+    /// `credits` in epoch 0.
+    fn store_vote_account(bank: &mut Bank, commission: u8, credits: u64) -> Pubkey {
+        let authority = Pubkey::new_unique();
+        let vote_init = VoteInit {
+            node_pubkey: authority,
+            authorized_voter: authority,
+            authorized_withdrawer: authority,
+            commission,
+        };
+        let mut account =
+            vote_program::vote_account(&vote_init, bank.sysvar_cache().get_clock(), bank.rent());
+        let mut vote_state = VoteStateV3::deserialize(account.data()).unwrap();
+        vote_state.epoch_credits = vec![(0, credits, 0)];
+        VoteStateV3::serialize(
+            &VoteStateVersions::new_v3(vote_state),
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        let vote_pubkey = Pubkey::new_unique();
+        bank.store_account(vote_pubkey, account);
+        vote_pubkey
+    }
+
+    /// Stores a stake account delegating `stake` lamports to `vote_pubkey`
+    /// since genesis.
+    fn store_stake_account(bank: &mut Bank, vote_pubkey: &Pubkey, stake: u64) -> Pubkey {
+        let state = StakeStateV2::Stake(
+            Meta::default(),
+            Stake {
+                delegation: Delegation::new(vote_pubkey, stake, u64::MAX),
+                credits_observed: 0,
+            },
+            StakeFlags::empty(),
+        );
+        let mut account = AccountSharedData::new(stake, StakeStateV2::size_of(), &stake::id());
+        bincode::serialize_into(account.data_as_mut_slice(), &state).unwrap();
+        let stake_pubkey = Pubkey::new_unique();
+        bank.store_account(stake_pubkey, account);
+        stake_pubkey
+    }
+
+    fn stake(bank: &Bank, stake_pubkey: &Pubkey) -> Stake {
+        let account = bank.get_account(stake_pubkey).unwrap();
+        bincode::deserialize::<StakeStateV2>(account.data())
+            .unwrap()
+            .stake()
+            .unwrap()
+    }
+
+    fn lamports(bank: &Bank, pubkey: &Pubkey) -> u64 {
+        bank.get_account(pubkey).unwrap().lamports()
+    }
+
+    fn advance_to_slot(bank: &mut Bank, slot: u64) {
+        while bank.slot() < slot {
+            bank.advance_slot(Hash::new_unique());
+        }
+    }
+
+    #[test]
+    fn test_rewards_follow_points_and_commission() {
+        let payer = Pubkey::new_unique();
+        let mut bank = rewards_bank(&payer);
+        let vote_pubkey = store_vote_account(&mut bank, 10, 100);
+        let small = store_stake_account(&mut bank, &vote_pubkey, 1_000_000);
+        let large = store_stake_account(&mut bank, &vote_pubkey, 3_000_000);
+        let vote_lamports = lamports(&bank, &vote_pubkey);
+        advance_to_slot(&mut bank, 31);
+        let capitalization = bank.capitalization();
+
+        // The first block of epoch 1 calculates the rewards of epoch 0 and
+        // already pays the commission.
+        advance_to_slot(&mut bank, 32);
+        let rewards = bank.epoch_rewards().unwrap().clone();
+        let total_rewards = (capitalization as f64 * (32.0 / SLOTS_PER_YEAR as f64)) as u64;
+        assert_eq!(rewards.rewarded_epoch, 0);
+        assert_eq!(rewards.total_rewards, total_rewards);
+        assert_eq!(rewards.total_points, 4_000_000 * 100);
+        assert!(rewards.is_active());
+        let small_rewards = total_rewards / 4;
+        let large_rewards = total_rewards * 3 / 4;
+        let commission = |rewards: u64| rewards / 10;
+        assert_eq!(
+            rewards.vote_rewards,
+            commission(small_rewards) + commission(large_rewards)
+        );
+        assert_eq!(
+            lamports(&bank, &vote_pubkey),
+            vote_lamports + rewards.vote_rewards
+        );
+        assert_eq!(lamports(&bank, &small), 1_000_000);
+
+        // The next block pays the stake accounts the rest, as stake.
+        advance_to_slot(&mut bank, 33);
+        let rewards = bank.epoch_rewards().unwrap();
+        assert!(!rewards.is_active());
+        for (stake_pubkey, staked, rewards) in [
+            (small, 1_000_000, small_rewards),
+            (large, 3_000_000, large_rewards),
+        ] {
+            let staker_rewards = rewards * 9 / 10;
+            assert_eq!(lamports(&bank, &stake_pubkey), staked + staker_rewards);
+            let stake = stake(&bank, &stake_pubkey);
+            assert_eq!(stake.delegation.stake, staked + staker_rewards);
+            assert_eq!(stake.credits_observed, 100);
+        }
+        assert_eq!(
+            bank.capitalization(),
+            capitalization + rewards.vote_rewards + rewards.distributed_stake_rewards
+        );
+        assert!(rewards.vote_rewards + rewards.distributed_stake_rewards <= total_rewards);
+    }
+
+    #[test]
+    fn test_only_new_credits_earn_rewards() {
+        let payer = Pubkey::new_unique();
+        let mut bank = rewards_bank(&payer);
+        let vote_pubkey = store_vote_account(&mut bank, 0, 100);
+        let stake_pubkey = store_stake_account(&mut bank, &vote_pubkey, 1_000_000);
+        advance_to_slot(&mut bank, 33);
+        let rewarded = lamports(&bank, &stake_pubkey);
+        assert!(rewarded > 1_000_000);
+
+        // The vote account earned nothing since, and neither does its stake.
+        advance_to_slot(&mut bank, 65);
+        let rewards = bank.epoch_rewards().unwrap();
+        assert_eq!(rewards.rewarded_epoch, 1);
+        assert_eq!(rewards.total_points, 0);
+        assert_eq!(lamports(&bank, &stake_pubkey), rewarded);
+
+        // Without inflation there is nothing to share out.
+        let mut bank = rewards_bank(&payer);
+        bank.set_inflation(Inflation::new_disabled());
+        let vote_pubkey = store_vote_account(&mut bank, 0, 100);
+        let stake_pubkey = store_stake_account(&mut bank, &vote_pubkey, 1_000_000);
+        advance_to_slot(&mut bank, 33);
+        assert_eq!(bank.epoch_rewards().unwrap().total_rewards, 0);
+        assert_eq!(lamports(&bank, &stake_pubkey), 1_000_000);
+    }
+
+    #[test]
+    fn test_partitions_pay_out_block_by_block() {
+        let payer = Pubkey::new_unique();
+        let mut bank = rewards_bank(&payer);
+        bank.set_stake_rewards_per_block(4);
+        let vote_pubkey = store_vote_account(&mut bank, 0, 100);
+        let stakes: Vec<Pubkey> = (0..10)
+            .map(|_| store_stake_account(&mut bank, &vote_pubkey, 1_000_000))
+            .collect();
+        advance_to_slot(&mut bank, 32);
+
+        // Ten rewards at four a block take three blocks.
+        let rewards = bank.epoch_rewards().unwrap().clone();
+        assert_eq!(rewards.partitions.len(), 3);
+        let mut rewarded: Vec<Pubkey> = rewards
+            .partitions
+            .iter()
+            .flatten()
+            .map(|reward| reward.stake_pubkey)
+            .collect();
+        rewarded.sort();
+        let mut expected = stakes.clone();
+        expected.sort();
+        assert_eq!(rewarded, expected);
+
+        // Until the last partition is paid the Stake program is off limits.
+        let deactivate = |bank: &Bank, stake_pubkey: &Pubkey| {
+            let ix = stake_instruction::deactivate_stake(stake_pubkey, &payer);
+            let message = Message::new_with_blockhash(&[ix], Some(&payer), &bank.last_blockhash());
+            let account_index = message
+                .account_keys
+                .iter()
+                .position(|key| *key == stake::id())
+                .unwrap() as u8;
+            let tx = Transaction::new_unsigned(message);
+            (
+                SanitizedTransaction::from_transaction_for_tests(tx),
+                account_index,
+            )
+        };
+        for (block, partition) in rewards.partitions.iter().enumerate() {
+            let paid = |bank: &Bank| {
+                partition
+                    .iter()
+                    .filter(|reward| lamports(bank, &reward.stake_pubkey) > 1_000_000)
+                    .count()
+            };
+            assert_eq!(paid(&bank), 0);
+            let (tx, account_index) = deactivate(&bank, &stakes[block]);
+            assert_eq!(
+                bank.process_transaction_batch(&[tx]).remove(0).unwrap_err(),
+                TransactionError::ProgramExecutionTemporarilyRestricted { account_index }
+            );
+            advance_to_slot(&mut bank, 33 + block as u64);
+            assert_eq!(paid(&bank), partition.len());
+            assert_eq!(
+                bank.epoch_rewards().unwrap().distributed_partitions,
+                block + 1
+            );
+        }
+        assert!(!bank.epoch_rewards().unwrap().is_active());
+        // Afterwards it runs again, and turns away a deactivation the
+        // staker did not sign.
+        let (tx, _) = deactivate(&bank, &stakes[0]);
+        let result = bank.process_transaction_batch(&[tx]).remove(0).unwrap();
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::MissingRequiredSignature
+            ))
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Unit test: Broadcast typed transaction events
+//!
+//! Analogy: Instead of every manager reading the kitchen's scribbled
+//! notes, the pass calls out each order as it is seated, cooked, served or
+//! sent back. Everyone listening hears the same calls in the same order.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        events::{DropReason, Event, EventBus},
+        scheduler::{BatchLimits, Deadline, PriorityGraphScheduler, TransactionScheduler},
+        svm::InvokeContext,
+    };
+    use solana_account::AccountSharedData;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_signer::Signer;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    fn noop(_invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        Ok(())
+    }
+
+    // Signed, so every transaction has a signature of its own.
+    fn transaction(program_id: Pubkey, payer: &Keypair, blockhash: Hash) -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![AccountMeta::new(Pubkey::new_unique(), false)],
+        );
+        let message = Message::new(&[instruction], Some(&payer.pubkey()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+            &[payer],
+            message,
+            blockhash,
+        ))
+    }
+
+    #[test]
+    fn test_event_bus_broadcasts_to_every_subscriber() {
+        let event_bus = EventBus::new();
+        let first = event_bus.subscribe();
+        let second = event_bus.clone().subscribe();
+        assert_eq!(event_bus.num_subscribers(), 2);
+
+        let event = Event::BatchStarted {
+            slot: 3,
+            num_transactions: 2,
+        };
+        event_bus.publish(event.clone());
+        assert_eq!(first.try_recv(), Ok(event.clone()));
+        assert_eq!(second.try_recv(), Ok(event));
+
+        // Subscribers that hung up are dropped on the next event.
+        drop(first);
+        event_bus.publish(Event::BatchStarted {
+            slot: 3,
+            num_transactions: 1,
+        });
+        assert_eq!(event_bus.num_subscribers(), 1);
+        assert_eq!(second.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn test_bank_publishes_what_it_schedules_commits_and_drops() {
+        let program_id = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.set_rent(Rent::free());
+        bank.add_builtin(program_id, noop);
+        let payer = Keypair::new();
+        bank.store_account(
+            payer.pubkey(),
+            AccountSharedData::new(1_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let event_bus = EventBus::new();
+        let events = event_bus.subscribe();
+        bank.set_event_bus(Some(event_bus));
+
+        let blockhash = bank.last_blockhash();
+        let committed = transaction(program_id, &payer, blockhash);
+        let unfunded = transaction(program_id, &Keypair::new(), blockhash);
+        let too_old = transaction(program_id, &payer, Hash::new_unique());
+        let results =
+            bank.process_transaction_batch(&[committed.clone(), unfunded.clone(), too_old.clone()]);
+        let result = results[0].as_ref().unwrap();
+
+        let slot = bank.slot();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                Event::TransactionDropped {
+                    slot,
+                    signature: *too_old.signature(),
+                    reason: DropReason::Rejected(TransactionError::BlockhashNotFound),
+                },
+                Event::BatchStarted {
+                    slot,
+                    num_transactions: 2,
+                },
+                Event::TransactionScheduled {
+                    slot,
+                    signature: *committed.signature(),
+                },
+                Event::TransactionCommitted {
+                    slot,
+                    signature: *committed.signature(),
+                    status: Ok(()),
+                    fee: result.fee_details.total_fee(),
+                    consumed_units: result.consumed_units,
+                },
+                Event::TransactionScheduled {
+                    slot,
+                    signature: *unfunded.signature(),
+                },
+                Event::TransactionDropped {
+                    slot,
+                    signature: *unfunded.signature(),
+                    reason: DropReason::Rejected(TransactionError::AccountNotFound),
+                },
+            ]
+        );
+
+        bank.freeze();
+        let child = Bank::new_from_parent(&bank, slot + 1, Hash::new_unique());
+        assert!(child.event_bus().is_some());
+    }
+
+    #[test]
+    fn test_scheduler_publishes_handed_out_and_dropped_transactions() {
+        let event_bus = EventBus::new();
+        let events = event_bus.subscribe();
+        let mut scheduler = PriorityGraphScheduler::new().with_event_bus(event_bus);
+        let program_id = Pubkey::new_unique();
+        let expiring = transaction(program_id, &Keypair::new(), Hash::default());
+        let scheduled = transaction(program_id, &Keypair::new(), Hash::default());
+        let forwarded = transaction(program_id, &Keypair::new(), Hash::default());
+        scheduler.push_with_deadline(expiring.clone(), Deadline::Slot(0));
+        scheduler.push(scheduled.clone());
+        scheduler.set_slot(1);
+
+        scheduler.next_batch(&BatchLimits::default());
+        scheduler.push(forwarded.clone());
+        scheduler.take_for_forwarding(1);
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                Event::TransactionDropped {
+                    slot: 1,
+                    signature: *expiring.signature(),
+                    reason: DropReason::Expired,
+                },
+                Event::TransactionScheduled {
+                    slot: 1,
+                    signature: *scheduled.signature(),
+                },
+                Event::TransactionDropped {
+                    slot: 1,
+                    signature: *forwarded.signature(),
+                    reason: DropReason::Forwarded,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Unit test: Profile program invocations into an execution trace
+//!
+//! Analogy: The head chef clips a stopwatch and a tally counter to every
+//! ticket. Each station notes when it started, when it finished and how
+//! much effort it spent, including the help it called in from other
+//! stations, so afterwards the chef can see exactly where service slowed.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::svm::{ExecutionTrace, InstructionTrace};
+    use serde_json::json;
+    use solana_pubkey::Pubkey;
+    use std::time::Duration;
+
+    fn frame(
+        program_id: Pubkey,
+        instruction_index: usize,
+        stack_height: usize,
+        units: u64,
+    ) -> InstructionTrace {
+        InstructionTrace {
+            program_id,
+            instruction_index,
+            stack_height,
+            compute_units_consumed: units,
+            elapsed: Duration::from_nanos(units * 10),
+            succeeded: true,
+        }
+    }
+
+    /// `a` invokes `b`, which invokes `c`, then invokes `b` again; a second
+    /// instruction runs `c` on its own.
+    fn nested_trace(a: Pubkey, b: Pubkey, c: Pubkey) -> ExecutionTrace {
+        ExecutionTrace {
+            instructions: vec![
+                frame(a, 0, 1, 100),
+                frame(b, 0, 2, 30),
+                frame(c, 0, 3, 10),
+                frame(b, 0, 2, 20),
+                frame(c, 1, 1, 5),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let trace = nested_trace(a, b, c);
+        // Every line holds what its invocation consumed itself.
+        assert_eq!(
+            trace.folded_compute_units(),
+            format!("{a} 50\n{a};{b} 20\n{a};{b};{c} 10\n{a};{b} 20\n{c} 5\n")
+        );
+        assert_eq!(
+            trace.folded_wall_time(),
+            format!("{a} 500\n{a};{b} 200\n{a};{b};{c} 100\n{a};{b} 200\n{c} 50\n")
+        );
+        assert_eq!(ExecutionTrace::default().folded_compute_units(), "");
+    }
+
+    #[test]
+    fn test_trace_to_json() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut trace = nested_trace(a, b, c);
+        trace.instructions[4].succeeded = false;
+        let json = trace.to_json();
+        assert_eq!(json.as_array().unwrap().len(), 5);
+        assert_eq!(
+            json[4],
+            json!({
+                "program_id": c.to_string(),
+                "instruction_index": 1,
+                "stack_height": 1,
+                "compute_units_consumed": 5,
+                "elapsed_ns": 50,
+                "succeeded": false,
+            })
+        );
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_executor_traces_invocations() {
+        use priority_graph_practice::svm::{InvokeContext, TransactionExecutor};
+        use solana_instruction::{AccountMeta, Instruction};
+        use solana_instruction_error::InstructionError;
+        use solana_message::Message;
+        use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+        use std::collections::HashMap;
+
+        let caller = Pubkey::new_unique();
+        let callee = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        // Consumes 100 units, then has the callee burn 30 and then 20.
+        executor.add_program(caller, move |invoke_context: &mut InvokeContext| {
+            invoke_context.consume_checked(100)?;
+            for units in [30, 20] {
+                let instruction = Instruction::new_with_bytes(
+                    callee,
+                    &[units],
+                    vec![AccountMeta::new_readonly(callee, false)],
+                );
+                invoke_context.invoke(&instruction)?;
+            }
+            Ok(())
+        });
+        // Burns as many units as its first instruction data byte says, and
+        // fails without any.
+        executor.add_program(callee, |invoke_context: &mut InvokeContext| {
+            let units = *invoke_context
+                .instruction_data()
+                .first()
+                .ok_or(InstructionError::InvalidInstructionData)?;
+            invoke_context.consume_checked(units.into())
+        });
+
+        let payer = Pubkey::new_unique();
+        let instructions = [
+            Instruction::new_with_bytes(
+                caller,
+                &[],
+                vec![AccountMeta::new_readonly(callee, false)],
+            ),
+            Instruction::new_with_bytes(callee, &[], vec![]),
+        ];
+        let message = Message::new(&instructions, Some(&payer));
+        let transaction =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let result = executor.load_and_execute_transaction(&HashMap::new(), &transaction);
+        assert!(result.status.is_err());
+
+        let summary: Vec<_> = result
+            .trace
+            .instructions
+            .iter()
+            .map(|trace| {
+                (
+                    trace.program_id,
+                    trace.instruction_index,
+                    trace.stack_height,
+                    trace.compute_units_consumed,
+                    trace.succeeded,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (caller, 0, 1, 150, true),
+                (callee, 0, 2, 30, true),
+                (callee, 0, 2, 20, true),
+                (callee, 1, 1, 0, false),
+            ]
+        );
+        assert!(result.trace.instructions[0].elapsed >= result.trace.instructions[1].elapsed);
+        assert_eq!(
+            result.trace.folded_compute_units(),
+            format!("{caller} 100\n{caller};{callee} 30\n{caller};{callee} 20\n{callee} 0\n")
+        );
+    }
+}
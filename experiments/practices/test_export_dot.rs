@@ -0,0 +1,123 @@
+//! Unit test: Export the priority graph as Graphviz DOT
+//!
+//! Analogy: The host sketches tonight's seating plan on a whiteboard: a box
+//! for every party with how much they tipped, grouped by seating, and an
+//! arrow from each party to the ones waiting for its table, labelled with
+//! the table they fight over.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::compute_budget::ComputeBudgetInstruction;
+    use priority_graph_practice::fees::PriorityFeeCalculator;
+    use priority_graph_practice::scheduler::export_dot;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    // Build a transaction that writes `writable` and reads `readonly`,
+    // paying `cu_price` if any.
+    fn transaction(
+        writable: &[Pubkey],
+        readonly: &[Pubkey],
+        cu_price: Option<u64>,
+    ) -> SanitizedTransaction {
+        let accounts = writable
+            .iter()
+            .map(|key| AccountMeta::new(*key, false))
+            .chain(
+                readonly
+                    .iter()
+                    .map(|key| AccountMeta::new_readonly(*key, false)),
+            )
+            .collect();
+        let mut instructions = vec![Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            accounts,
+        )];
+        if let Some(cu_price) = cu_price {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(cu_price));
+        }
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_export_write_conflicts() {
+        let hot = Pubkey::new_unique();
+        let transactions = vec![
+            transaction(&[hot], &[], None),
+            transaction(&[hot], &[], None),
+            transaction(&[Pubkey::new_unique()], &[], None),
+        ];
+        assert_eq!(
+            export_dot(&transactions),
+            format!(
+                "digraph prio_graph {{
+    node [shape=box];
+    subgraph cluster_batch_0 {{
+        label=\"batch 0\";
+        tx0 [label=\"tx 0\\npriority 0\"];
+        tx2 [label=\"tx 2\\npriority 0\"];
+    }}
+    subgraph cluster_batch_1 {{
+        label=\"batch 1\";
+        tx1 [label=\"tx 1\\npriority 0\"];
+    }}
+    tx0 -> tx1 [label=\"{hot}\"];
+}}
+"
+            )
+        );
+        assert_eq!(
+            export_dot(&[]),
+            "digraph prio_graph {\n    node [shape=box];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_export_read_write_edges() {
+        let shared = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let transactions = vec![
+            transaction(&[], &[shared, other], None),
+            transaction(&[], &[shared], None),
+            transaction(&[shared, other], &[], None),
+            transaction(&[], &[shared], None),
+        ];
+        let dot = export_dot(&transactions);
+        // Readers never block each other; the writer waits for both readers
+        // and blocks the reader after it.
+        let edges: Vec<&str> = dot.lines().filter(|line| line.contains("->")).collect();
+        assert_eq!(
+            edges,
+            vec![
+                format!("    tx0 -> tx2 [label=\"{shared}\\n{other}\"];"),
+                format!("    tx1 -> tx2 [label=\"{shared}\"];"),
+                format!("    tx2 -> tx3 [label=\"{shared}\"];"),
+            ]
+        );
+        assert!(dot.contains("subgraph cluster_batch_2"));
+        assert!(!dot.contains("subgraph cluster_batch_3"));
+    }
+
+    #[test]
+    fn test_export_orders_by_priority() {
+        let hot = Pubkey::new_unique();
+        let transactions = vec![
+            transaction(&[hot], &[], Some(1)),
+            transaction(&[hot], &[], Some(1_000)),
+        ];
+        let priority = PriorityFeeCalculator::new().calculate_priority(&transactions[1]);
+        assert!(priority > 0);
+        let dot = export_dot(&transactions);
+        // The higher paying transaction goes first and blocks the other.
+        assert!(dot.contains(&format!("    tx1 -> tx0 [label=\"{hot}\"];")));
+        assert!(dot.contains(&format!("tx1 [label=\"tx 1\\npriority {priority}\"];")));
+        let batch_0 = dot.find("cluster_batch_0").unwrap();
+        let batch_1 = dot.find("cluster_batch_1").unwrap();
+        let tx1 = dot.find("tx1 [").unwrap();
+        assert!(batch_0 < tx1 && tx1 < batch_1);
+    }
+}
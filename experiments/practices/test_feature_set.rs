@@ -0,0 +1,173 @@
+//! Unit test: Gate runtime behavior on activated features
+//!
+//! Analogy: The kitchen's rulebook changes over the years: new tools are
+//! allowed, portions are trimmed, a stricter check is added at the door.
+//! A kitchen can be run by this season's rulebook, by the one in force on
+//! a given date, or by a custom mix, so a dish can be cooked both ways and
+//! the results compared.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::compute_budget::{
+        process_compute_budget_instructions_with_features,
+        MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT,
+    };
+    use priority_graph_practice::feature_set::{
+        blake3_syscall_enabled, curve25519_syscall_enabled, enable_secp256r1_precompile,
+        reserve_minimal_cus_for_builtin_instructions, FeatureSet, FEATURE_NAMES,
+    };
+    use priority_graph_practice::fees::FeeStructure;
+    use priority_graph_practice::svm::TransactionExecutor;
+    use priority_graph_practice::system_program;
+    use solana_account::{AccountSharedData, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::{bpf_loader, secp256r1_program};
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    /// Offset of the first account's data in the input region: the account
+    /// count, then the account's flags, key, owner, lamports and data length.
+    const FIRST_ACCOUNT_DATA: i32 = 8 + 8 + 32 + 32 + 8 + 8;
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    fn sanitized(instructions: &[Instruction]) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_feature_set_builders() {
+        let none = FeatureSet::default();
+        let all = FeatureSet::all_enabled();
+        for (id, _) in FEATURE_NAMES {
+            assert!(!none.is_active(id));
+            assert_eq!(all.activated_slot(id), Some(0));
+        }
+
+        // Mainnet activations happen at epoch boundaries, and never undo.
+        let genesis = FeatureSet::mainnet_beta_as_of(0);
+        assert!(genesis.active().is_empty());
+        let later = FeatureSet::mainnet_beta_as_of(u64::MAX);
+        assert!(later.is_active(&curve25519_syscall_enabled::id()));
+        assert!(!later.is_active(&blake3_syscall_enabled::id()));
+        let slot = later
+            .activated_slot(&curve25519_syscall_enabled::id())
+            .unwrap();
+        assert!(
+            !FeatureSet::mainnet_beta_as_of(slot - 1).is_active(&curve25519_syscall_enabled::id())
+        );
+        assert!(FeatureSet::mainnet_beta_as_of(slot).is_active(&curve25519_syscall_enabled::id()));
+
+        let custom = FeatureSet::all_enabled()
+            .without_feature(&enable_secp256r1_precompile::id())
+            .with_feature(&blake3_syscall_enabled::id(), 42);
+        assert!(custom
+            .inactive()
+            .contains(&enable_secp256r1_precompile::id()));
+        assert_eq!(
+            custom.activated_slot(&blake3_syscall_enabled::id()),
+            Some(42)
+        );
+        let config = custom.precompile_config();
+        assert!(config.ed25519_verify_strict);
+        assert!(!config.enable_secp256r1);
+    }
+
+    #[test]
+    fn test_syscalls_follow_features() {
+        let program_id = Pubkey::new_unique();
+        let digest = Pubkey::new_unique();
+        // Hashes no slices into the first account's data.
+        let text = [
+            insn(0xb7, 2, 0, 0, 0),                  // mov64 r2, 0
+            insn(0xbf, 3, 1, 0, 0),                  // mov64 r3, r1
+            insn(0x07, 3, 0, 0, FIRST_ACCOUNT_DATA), // add64 r3, data
+            insn(0x85, 0, 0, 0, hash_symbol_name(b"sol_blake3") as i32),
+            insn(0x95, 0, 0, 0, 0), // exit
+        ];
+        let store = HashMap::from([
+            (program_id, program_account(&text)),
+            (digest, AccountSharedData::new(1, 32, &program_id)),
+        ]);
+        let tx = sanitized(&[Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![AccountMeta::new(digest, false)],
+        )]);
+
+        let mut executor = TransactionExecutor::new();
+        let result = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(result.status, Ok(()));
+
+        // Without the feature the syscall is unknown, and calling it aborts
+        // the program.
+        executor.set_feature_set(
+            FeatureSet::all_enabled().without_feature(&blake3_syscall_enabled::id()),
+        );
+        assert!(executor.program_cache().is_empty());
+        let result = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ProgramFailedToComplete
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compute_budget_and_fees_follow_features() {
+        let payer = Pubkey::new_unique();
+        let transfer = system_program::transfer(&payer, &Pubkey::new_unique(), 1);
+        let other = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+        let tx = sanitized(&[transfer, other]);
+
+        let all = FeatureSet::all_enabled();
+        let limits = process_compute_budget_instructions_with_features(tx.message(), &all).unwrap();
+        assert_eq!(
+            limits.compute_unit_limit,
+            MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT + 200_000
+        );
+        let before = all
+            .clone()
+            .without_feature(&reserve_minimal_cus_for_builtin_instructions::id());
+        let limits =
+            process_compute_budget_instructions_with_features(tx.message(), &before).unwrap();
+        assert_eq!(limits.compute_unit_limit, 2 * 200_000);
+
+        // Two secp256r1 signatures are only charged for once the precompile
+        // is enabled.
+        let r1 = Instruction::new_with_bytes(secp256r1_program::id(), &[2, 0], vec![]);
+        let tx = sanitized(&[r1]);
+        let fee_structure = FeeStructure::default();
+        let fee = fee_structure.calculate_fee_details_with_features(&tx, &all);
+        assert_eq!(fee.transaction_fee, 3 * 5_000);
+        let disabled = all.without_feature(&enable_secp256r1_precompile::id());
+        let fee = fee_structure.calculate_fee_details_with_features(&tx, &disabled);
+        assert_eq!(fee.transaction_fee, 5_000);
+    }
+}
@@ -0,0 +1,155 @@
+//! Unit test: Validate and charge the fee payer
+//!
+//! Analogy: Every party pays the cover and any tip at the door, before the
+//! kitchen sees the order. A party that can't pay is turned away without a
+//! trace, and a dish that comes out wrong doesn't get the cover refunded.
+//! The till is counted and emptied at the end of every shift.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        compute_budget::ComputeBudgetInstruction,
+        fees::{FeeDetails, FeeStructure},
+        svm::InvokeContext,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const LAMPORTS: u64 = 1_000_000;
+
+    fn transaction(payer: &Pubkey, instructions: &[Instruction]) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn system_account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &solana_sdk_ids::system_program::id())
+    }
+
+    // Bids one lamport per compute unit for 1_000 units.
+    fn tip() -> [Instruction; 2] {
+        [
+            ComputeBudgetInstruction::set_compute_unit_limit(1_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+        ]
+    }
+
+    fn lamports(bank: &Bank, pubkey: &Pubkey) -> u64 {
+        bank.get_account(pubkey)
+            .map_or(0, |account| account.lamports())
+    }
+
+    #[test]
+    fn test_fee_structure_calculates_fee_details() {
+        let payer = Pubkey::new_unique();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_500),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(Pubkey::new_unique(), false)],
+            ),
+        ];
+        let tx = transaction(&payer, &instructions);
+
+        let fee_details = FeeStructure::default().calculate_fee_details(&tx);
+        assert_eq!(
+            fee_details,
+            FeeDetails {
+                transaction_fee: 5_000,
+                prioritization_fee: 300,
+                local_fee: 0,
+            }
+        );
+        assert_eq!(fee_details.total_fee(), 5_300);
+
+        // The payer and the instruction's account are write locked.
+        let fee_details = FeeStructure::new(1_000, 10).calculate_fee_details(&tx);
+        assert_eq!(fee_details.transaction_fee, 1_020);
+    }
+
+    #[test]
+    fn test_fee_is_charged_even_if_transaction_fails() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.add_builtin(program_id, |_: &mut InvokeContext| {
+            Err(InstructionError::Custom(0))
+        });
+        bank.store_account(payer, system_account(LAMPORTS));
+
+        let failing = transaction(
+            &payer,
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        );
+        let results = bank.process_transaction_batch(&[failing]);
+        assert!(!results[0].as_ref().unwrap().was_successful());
+        assert_eq!(lamports(&bank, &payer), LAMPORTS - 5_000);
+        assert_eq!(bank.collected_fees(), 5_000);
+
+        // Fees add up over the slot and start over in the next one.
+        let tipping = transaction(&payer, &tip());
+        let results = bank.process_transaction_batch(&[tipping]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(lamports(&bank, &payer), LAMPORTS - 11_000);
+        assert_eq!(bank.collected_fees(), 11_000);
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(bank.collected_fees(), 0);
+    }
+
+    #[test]
+    fn test_fee_payer_validation() {
+        let mut bank = Bank::default();
+        let exempt = bank.rent().minimum_balance(0);
+        let missing = Pubkey::new_unique();
+        let not_system = Pubkey::new_unique();
+        let poor = Pubkey::new_unique();
+        let near_minimum = Pubkey::new_unique();
+        let tipping = Pubkey::new_unique();
+        bank.store_account(
+            not_system,
+            AccountSharedData::new(LAMPORTS, 0, &Pubkey::new_unique()),
+        );
+        bank.store_account(poor, system_account(4_999));
+        bank.store_account(near_minimum, system_account(exempt + 4_999));
+        // Covers the base fee, but not the tip on top.
+        bank.store_account(tipping, system_account(5_500));
+
+        let transactions = vec![
+            transaction(&missing, &[]),
+            transaction(&not_system, &[]),
+            transaction(&poor, &[]),
+            transaction(&near_minimum, &[]),
+            transaction(&tipping, &tip()),
+        ];
+        assert_eq!(
+            bank.process_transaction_batch(&transactions),
+            vec![
+                Err(TransactionError::AccountNotFound),
+                Err(TransactionError::InvalidAccountForFee),
+                Err(TransactionError::InsufficientFundsForFee),
+                Err(TransactionError::InsufficientFundsForRent { account_index: 0 }),
+                Err(TransactionError::InsufficientFundsForFee),
+            ]
+        );
+
+        // Rejected transactions leave no trace.
+        assert_eq!(bank.collected_fees(), 0);
+        assert_eq!(lamports(&bank, &poor), 4_999);
+        assert_eq!(lamports(&bank, &tipping), 5_500);
+
+        // Paying out the whole balance closes the payer instead.
+        bank.store_account(poor, system_account(5_000));
+        let results = bank.process_transaction_batch(&[transaction(&poor, &[])]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(bank.get_account(&poor), None);
+    }
+}
@@ -0,0 +1,233 @@
+//! Unit test: Fetch a block and its accounts into a replay fixture
+//!
+//! Analogy: The food critic can't bring last night's pantry along, only
+//! today's. So the clerk copies the order tickets, then takes today's
+//! pantry and restocks every shelf to what last night's tickets say it
+//! held before the first order touched it. The kitchen can then cook the
+//! whole night again without calling anyone.
+
+#[cfg(all(test, feature = "fetch"))]
+mod tests {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use priority_graph_practice::{
+        fetch::{BlockFetcher, FetchError, BLOCK_FILE},
+        fixtures::load_transactions,
+        replay::{replay_block, BlockFixture, FIXTURE_FILE},
+        system_program,
+    };
+    use serde_json::{json, Value};
+    use solana_account::ReadableAccount;
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_signer::Signer;
+    use solana_transaction::{versioned::VersionedTransaction, Transaction};
+    use std::{collections::HashMap, path::PathBuf, thread};
+    use tiny_http::{Response, Server};
+
+    const SLOT: u64 = 1_000;
+
+    /// Serves JSON-RPC on a free port, answering every request with what
+    /// `handle` returns for its method and params, and returns its URL.
+    fn serve_rpc(handle: impl Fn(&str, &Value) -> Result<Value, Value> + Send + 'static) -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let call: Value = serde_json::from_str(&body).unwrap();
+                let response = match handle(call["method"].as_str().unwrap(), &call["params"]) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }),
+                    Err(error) => json!({ "jsonrpc": "2.0", "id": call["id"], "error": error }),
+                };
+                let _ = request.respond(Response::from_string(response.to_string()));
+            }
+        });
+        url
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "fetch-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ))
+    }
+
+    fn rpc_account(lamports: u64, owner: &Pubkey, executable: bool) -> Value {
+        json!({
+            "lamports": lamports,
+            "data": ["", "base64"],
+            "owner": owner.to_string(),
+            "executable": executable,
+            "rentEpoch": u64::MAX,
+            "space": 0,
+        })
+    }
+
+    /// A `getBlock` entry for a transfer of `lamports` from `from` to `to`,
+    /// with the balances of `from`, `to` and the System program.
+    fn transfer(
+        from: &Keypair,
+        to: &Pubkey,
+        lamports: u64,
+        pre: [u64; 3],
+        post: [u64; 3],
+    ) -> Value {
+        let instruction = system_program::transfer(&from.pubkey(), to, lamports);
+        let message = Message::new(&[instruction], Some(&from.pubkey()));
+        let transaction = Transaction::new(&[from], message, Hash::new_unique());
+        let bytes = bincode::serialize(&VersionedTransaction::from(transaction)).unwrap();
+        json!({
+            "transaction": [BASE64_STANDARD.encode(bytes), "base64"],
+            "meta": {
+                "err": null,
+                "fee": 5_000,
+                "preBalances": pre,
+                "postBalances": post,
+                "loadedAddresses": { "writable": [], "readonly": [] },
+            },
+            "version": "legacy",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fetch_block_into_replayable_fixture() {
+        let alice = Keypair::new();
+        let bob = Pubkey::new_unique();
+        let carol = Keypair::new();
+        let system = system_program_id::id();
+        // Alice pays Bob into existence, then Carol pays Alice and later
+        // closes her account.
+        let block = json!({
+            "blockhash": Hash::new_unique().to_string(),
+            "parentSlot": SLOT - 1,
+            "transactions": [
+                transfer(
+                    &alice,
+                    &bob,
+                    100_000_000,
+                    [1_000_000_000, 0, 1],
+                    [899_995_000, 100_000_000, 1],
+                ),
+                transfer(
+                    &carol,
+                    &alice.pubkey(),
+                    10_000_000,
+                    [50_000_000, 899_995_000, 1],
+                    [39_995_000, 909_995_000, 1],
+                ),
+            ],
+        });
+        // The accounts as they are now, long after the block.
+        let accounts_now: HashMap<String, Value> = [
+            (alice.pubkey(), rpc_account(7, &system, false)),
+            (bob, rpc_account(8, &system, false)),
+            (system, rpc_account(1, &Pubkey::default(), true)),
+        ]
+        .into_iter()
+        .map(|(pubkey, account)| (pubkey.to_string(), account))
+        .collect();
+
+        let url = serve_rpc({
+            let block = block.clone();
+            move |method, params| match method {
+                "getBlock" if params[0] == SLOT => Ok(block.clone()),
+                "getMultipleAccounts" => {
+                    let value: Vec<Value> = params[0]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|pubkey| {
+                            accounts_now
+                                .get(pubkey.as_str().unwrap())
+                                .cloned()
+                                .unwrap_or(Value::Null)
+                        })
+                        .collect();
+                    Ok(json!({ "context": { "slot": SLOT + 100 }, "value": value }))
+                }
+                _ => Err(json!({ "code": -32601, "message": "Method not found" })),
+            }
+        });
+
+        let dir = temp_dir();
+        let fixture = BlockFetcher::new(url)
+            .fetch_to_dir(SLOT, &dir)
+            .await
+            .unwrap();
+        assert_eq!(BlockFixture::load_dir(&dir).unwrap(), fixture);
+        assert!(dir.join(FIXTURE_FILE).is_file());
+        assert_eq!(load_transactions(dir.join(BLOCK_FILE)).unwrap().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(fixture.slot, SLOT);
+        assert_eq!(fixture.transactions.len(), 2);
+        let lamports: HashMap<Pubkey, u64> = fixture
+            .accounts
+            .iter()
+            .map(|(pubkey, account)| (*pubkey, account.lamports()))
+            .collect();
+        // Balances as of before the block; Bob did not exist yet, and
+        // Carol, closed since, is known by her balance alone.
+        assert_eq!(
+            lamports,
+            HashMap::from([
+                (alice.pubkey(), 1_000_000_000),
+                (carol.pubkey(), 50_000_000),
+                (system, 1),
+            ])
+        );
+
+        let report = replay_block(&fixture);
+        assert!(
+            report.is_match(),
+            "{:?}",
+            report.mismatches().collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_malformed_loaded_addresses() {
+        let mut entry = transfer(
+            &Keypair::new(),
+            &Pubkey::new_unique(),
+            1,
+            [2, 0, 1],
+            [1, 1, 1],
+        );
+        entry["meta"]["loadedAddresses"]["writable"] = json!(["not a pubkey"]);
+        let block = json!({ "transactions": [entry] });
+        let url = serve_rpc(move |_, _| Ok(block.clone()));
+        let dir = temp_dir();
+        let err = BlockFetcher::new(url)
+            .fetch_to_dir(SLOT, &dir)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FetchError::InvalidResponse(ref name) if name == "loadedAddresses"));
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_reports_rpc_errors() {
+        let url = serve_rpc(|_, params| {
+            Err(json!({
+                "code": -32007,
+                "message": format!("Slot {} was skipped", params[0]),
+            }))
+        });
+        let dir = temp_dir();
+        let err = BlockFetcher::new(url)
+            .fetch_to_dir(SLOT, &dir)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FetchError::Rpc { code: -32007, ref message } if message == "Slot 1000 was skipped"
+        ));
+        assert!(!dir.exists());
+    }
+}
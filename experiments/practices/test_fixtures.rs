@@ -0,0 +1,250 @@
+//! Unit test: Load captured transactions from fixture files
+//!
+//! Analogy: A trainee chef practises on last Saturday's order tickets.
+//! Some were kept as the printed slips, some as photos of the slips; either
+//! way the tickets go back on the rail exactly as the guests wrote them.
+
+#[cfg(test)]
+mod tests {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use priority_graph_practice::{
+        fixtures::{load_transactions, parse_transactions, FixtureError},
+        sanitize::SanitizeError,
+        system_program,
+    };
+    use serde_json::{json, Value};
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::{v0, AddressLookupTableAccount, Message, VersionedMessage};
+    use solana_pubkey::Pubkey;
+    use solana_signer::Signer;
+    use solana_transaction::{
+        sanitized::SanitizedTransaction, versioned::VersionedTransaction, Transaction,
+    };
+    use solana_transaction_error::TransactionError;
+
+    fn transfer(from: &Keypair, to: &Pubkey) -> VersionedTransaction {
+        let instruction = system_program::transfer(&from.pubkey(), to, 1_000);
+        let message = Message::new(&[instruction], Some(&from.pubkey()));
+        Transaction::new(&[from], message, Hash::new_unique()).into()
+    }
+
+    /// A transfer to the first address of `table`, looked up.
+    fn looked_up_transfer(
+        from: &Keypair,
+        table: &AddressLookupTableAccount,
+    ) -> VersionedTransaction {
+        let instruction = system_program::transfer(&from.pubkey(), &table.addresses[0], 1_000);
+        let message = v0::Message::try_compile(
+            &from.pubkey(),
+            &[instruction],
+            std::slice::from_ref(table),
+            Hash::new_unique(),
+        )
+        .unwrap();
+        let message = VersionedMessage::V0(message);
+        VersionedTransaction {
+            signatures: vec![from.sign_message(&message.serialize())],
+            message,
+        }
+    }
+
+    fn base64(transaction: &VersionedTransaction) -> String {
+        BASE64_STANDARD.encode(bincode::serialize(transaction).unwrap())
+    }
+
+    /// `transaction` the way `getTransaction` renders it with the `json`
+    /// encoding.
+    fn json_encoded(transaction: &VersionedTransaction) -> Value {
+        let message = &transaction.message;
+        let header = message.header();
+        let instructions: Vec<Value> = message
+            .instructions()
+            .iter()
+            .map(|instruction| {
+                json!({
+                    "programIdIndex": instruction.program_id_index,
+                    "accounts": instruction.accounts,
+                    "data": bs58::encode(&instruction.data).into_string(),
+                    "stackHeight": null,
+                })
+            })
+            .collect();
+        let mut encoded = json!({
+            "signatures": transaction.signatures.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "message": {
+                "header": {
+                    "numRequiredSignatures": header.num_required_signatures,
+                    "numReadonlySignedAccounts": header.num_readonly_signed_accounts,
+                    "numReadonlyUnsignedAccounts": header.num_readonly_unsigned_accounts,
+                },
+                "accountKeys": message.static_account_keys().iter().map(ToString::to_string).collect::<Vec<_>>(),
+                "recentBlockhash": message.recent_blockhash().to_string(),
+                "instructions": instructions,
+            },
+        });
+        if let Some(lookups) = message.address_table_lookups() {
+            encoded["message"]["addressTableLookups"] = lookups
+                .iter()
+                .map(|lookup| {
+                    json!({
+                        "accountKey": lookup.account_key.to_string(),
+                        "writableIndexes": lookup.writable_indexes,
+                        "readonlyIndexes": lookup.readonly_indexes,
+                    })
+                })
+                .collect();
+        }
+        encoded
+    }
+
+    fn assert_loaded(loaded: &SanitizedTransaction, expected: &VersionedTransaction) {
+        assert_eq!(loaded.signature(), &expected.signatures[0]);
+        assert_eq!(loaded.message_hash(), &expected.message.hash());
+    }
+
+    #[test]
+    fn test_load_base64_lines() {
+        let payer = Keypair::new();
+        let transactions = [
+            transfer(&payer, &Pubkey::new_unique()),
+            transfer(&payer, &Pubkey::new_unique()),
+        ];
+        let contents = format!(
+            "{}\n\n  {}  \n",
+            base64(&transactions[0]),
+            base64(&transactions[1])
+        );
+        let path = std::env::temp_dir().join(format!(
+            "fixtures-{}-{}.b64",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let loaded = load_transactions(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        for (loaded, expected) in loaded.iter().zip(&transactions) {
+            assert_loaded(loaded, expected);
+        }
+        assert!(matches!(load_transactions(&path), Err(FixtureError::Io(_))));
+    }
+
+    #[test]
+    fn test_load_every_rpc_encoding() {
+        let payer = Keypair::new();
+        let transactions: Vec<_> = (0..5)
+            .map(|_| transfer(&payer, &Pubkey::new_unique()))
+            .collect();
+        let bytes = |index: usize| bincode::serialize(&transactions[index]).unwrap();
+        let block = json!({
+            "blockhash": Hash::new_unique().to_string(),
+            "transactions": [
+                { "transaction": json_encoded(&transactions[0]), "meta": null, "version": "legacy" },
+                { "transaction": [base64(&transactions[1]), "base64"], "meta": null },
+                { "transaction": [bs58::encode(bytes(2)).into_string(), "base58"] },
+                json_encoded(&transactions[3]),
+                base64(&transactions[4]),
+            ],
+        });
+
+        for fixture in [
+            block.clone(),
+            block["transactions"].clone(),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": block }),
+        ] {
+            let loaded = parse_transactions(&fixture.to_string()).unwrap();
+            assert_eq!(loaded.len(), transactions.len());
+            for (loaded, expected) in loaded.iter().zip(&transactions) {
+                assert_loaded(loaded, expected);
+            }
+        }
+
+        let single = json!({ "transaction": json_encoded(&transactions[0]), "slot": 7 });
+        let loaded = parse_transactions(&single.to_string()).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_loaded(&loaded[0], &transactions[0]);
+    }
+
+    #[test]
+    fn test_lookups_resolve_through_loaded_addresses() {
+        let payer = Keypair::new();
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![Pubkey::new_unique()],
+        };
+        let transaction = looked_up_transfer(&payer, &table);
+        let meta = json!({
+            "err": null,
+            "loadedAddresses": { "writable": [table.addresses[0].to_string()], "readonly": [] },
+        });
+
+        for encoded in [
+            json_encoded(&transaction),
+            json!([base64(&transaction), "base64"]),
+        ] {
+            let entry = json!({ "transaction": encoded, "meta": meta, "version": 0 });
+            let loaded = parse_transactions(&json!([entry]).to_string()).unwrap();
+            assert_loaded(&loaded[0], &transaction);
+            let account_keys: Vec<Pubkey> =
+                loaded[0].message().account_keys().iter().copied().collect();
+            assert!(account_keys.contains(&table.addresses[0]));
+            assert!(loaded[0].message().is_writable(account_keys.len() - 1));
+        }
+
+        // Without `meta` the lookup has nothing to resolve through.
+        let err = parse_transactions(&base64(&transaction)).unwrap_err();
+        assert!(matches!(
+            err,
+            FixtureError::InvalidTransaction {
+                index: 0,
+                source: SanitizeError::Transaction(TransactionError::UnsupportedVersion),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_reject_malformed_fixtures() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, &Pubkey::new_unique());
+
+        let garbage = format!("{}\nnot base64!\n", base64(&transaction));
+        assert!(matches!(
+            parse_transactions(&garbage),
+            Err(FixtureError::InvalidField { index: 1, .. })
+        ));
+        assert!(matches!(
+            parse_transactions(&BASE64_STANDARD.encode([1, 2, 3])),
+            Err(FixtureError::InvalidTransaction {
+                index: 0,
+                source: SanitizeError::InvalidEncoding,
+            })
+        ));
+        assert!(matches!(
+            parse_transactions("[{"),
+            Err(FixtureError::InvalidJson)
+        ));
+
+        let mut encoded = json_encoded(&transaction);
+        encoded["message"]["accountKeys"][0] = json!("not a pubkey");
+        assert!(matches!(
+            parse_transactions(&json!([base64(&transaction), encoded]).to_string()),
+            Err(FixtureError::InvalidTransaction {
+                index: 1,
+                source: SanitizeError::InvalidEncoding,
+            })
+        ));
+
+        // The header asks for a signature the transaction does not carry.
+        let mut encoded = json_encoded(&transaction);
+        encoded["signatures"] = json!([]);
+        assert!(matches!(
+            parse_transactions(&json!([encoded]).to_string()),
+            Err(FixtureError::InvalidTransaction {
+                source: SanitizeError::SignatureCountMismatch { .. },
+                ..
+            })
+        ));
+    }
+}
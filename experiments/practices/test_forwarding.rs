@@ -0,0 +1,146 @@
+//! Unit test: Forward queued transactions to the next leader
+//!
+//! Analogy: Two restaurants share a street, but only one kitchen is open
+//! at a time. A host whose kitchen is closed can keep the guests waiting
+//! until it opens, or send them down the street to the kitchen that is
+//! cooking now, the biggest tippers first.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            forwarding::NUM_CONSECUTIVE_LEADER_SLOTS, BatchLimits, DropReason, ForwardingConfig,
+            ForwardingPolicy, ForwardingSimulation, PriorityGraphScheduler, TransactionScheduler,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(writable: Pubkey, cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(writable, false)],
+            ),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    /// Submits four transactions to node 1 while node 0 leads and
+    /// returns the simulation once all of them were included.
+    fn run(policy: ForwardingPolicy) -> ForwardingSimulation {
+        let mut simulation = ForwardingSimulation::new(ForwardingConfig {
+            policy,
+            ..ForwardingConfig::default()
+        });
+        assert_eq!(simulation.leader(0), 0);
+        assert_eq!(simulation.leader(NUM_CONSECUTIVE_LEADER_SLOTS), 1);
+        for cu_price in 0..4 {
+            simulation.submit(1, transaction(Pubkey::new_unique(), cu_price));
+        }
+        assert!(simulation.run_until_included(16));
+        simulation
+    }
+
+    #[test]
+    fn test_forwarding_shortens_inclusion_latency() {
+        // Held transactions wait for node 1's first leader slot.
+        let held = run(ForwardingPolicy::Hold);
+        assert!(held
+            .records()
+            .iter()
+            .all(|record| record.included_slot == Some(NUM_CONSECUTIVE_LEADER_SLOTS)));
+        assert_eq!(held.metrics().forwards, 0);
+        assert_eq!(held.metrics().average_inclusion_latency(), 4.0);
+
+        // Forwarded at the end of slot 0, they land in slot 1.
+        let forwarded = run(ForwardingPolicy::All);
+        let metrics = forwarded.metrics();
+        assert_eq!(metrics.transactions_submitted, 4);
+        assert_eq!(metrics.transactions_included, 4);
+        assert_eq!(metrics.forwards, 4);
+        assert_eq!(metrics.average_inclusion_latency(), 1.0);
+        assert_eq!(forwarded.scheduler(1).metrics().transactions_forwarded, 4);
+        assert_eq!(forwarded.scheduler(0).metrics().transactions_scheduled, 4);
+    }
+
+    #[test]
+    fn test_highest_priority_is_forwarded_first() {
+        let simulation = run(ForwardingPolicy::HighestPriority {
+            max_transactions: 1,
+        });
+        // One transaction per boundary, the highest compute unit price
+        // first; the last one is left for node 1 itself.
+        let included: Vec<_> = simulation
+            .records()
+            .iter()
+            .map(|record| record.included_slot.unwrap())
+            .collect();
+        assert_eq!(included, vec![4, 3, 2, 1]);
+        assert_eq!(simulation.metrics().forwards, 3);
+        assert_eq!(simulation.records()[0].forwards, 0);
+        assert_eq!(simulation.records()[0].origin, 1);
+
+        // A leader that runs out of slots forwards what it did not pack.
+        let mut simulation = ForwardingSimulation::new(ForwardingConfig {
+            policy: ForwardingPolicy::All,
+            batch_limits: BatchLimits {
+                max_txs: 1,
+                ..BatchLimits::default()
+            },
+            ..ForwardingConfig::default()
+        });
+        for _ in 0..3 {
+            simulation.run_slot();
+        }
+        for cu_price in 0..3 {
+            simulation.submit(0, transaction(Pubkey::new_unique(), cu_price));
+        }
+        assert!(simulation.run_until_included(8));
+        let included: Vec<_> = simulation
+            .records()
+            .iter()
+            .map(|record| record.included_slot.unwrap())
+            .collect();
+        assert_eq!(included, vec![5, 4, 3]);
+    }
+
+    #[test]
+    fn test_take_for_forwarding_skips_blocked_transactions() {
+        let hot_account = Pubkey::new_unique();
+        let mut scheduler = PriorityGraphScheduler::new();
+        let first = scheduler.push(transaction(hot_account, 3));
+        let blocked = scheduler.push(transaction(hot_account, 1));
+        let other = scheduler.push(transaction(Pubkey::new_unique(), 2));
+        let batch = scheduler.next_batch(&BatchLimits {
+            max_txs: 1,
+            ..BatchLimits::default()
+        });
+        assert_eq!(batch.ids, vec![first]);
+
+        // `blocked` waits on `first`, which is in flight.
+        let forwarded = scheduler.take_for_forwarding(usize::MAX);
+        assert_eq!(
+            forwarded
+                .iter()
+                .map(|dropped| dropped.id)
+                .collect::<Vec<_>>(),
+            vec![other]
+        );
+        assert_eq!(forwarded[0].reason, DropReason::Forwarded);
+        assert_eq!(scheduler.num_pending(), 1);
+        assert!(scheduler.take_for_forwarding(0).is_empty());
+
+        scheduler.complete(&batch.ids);
+        assert_eq!(scheduler.take_for_forwarding(usize::MAX)[0].id, blocked);
+        assert_eq!(scheduler.num_pending(), 0);
+        // Forwarded transactions are not reported as dropped.
+        assert!(scheduler.take_dropped().is_empty());
+    }
+}
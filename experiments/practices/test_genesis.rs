@@ -0,0 +1,198 @@
+//! Unit test: Start a bank from a genesis configuration
+//!
+//! Analogy: Before the first service the owner writes the opening sheet:
+//! which regulars already have money on their tab, which guest chefs'
+//! recipes are in the binder, what the house rules are. Every restaurant
+//! opened from the same sheet opens in exactly the same state.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::returning_elf;
+    use priority_graph_practice::{
+        bank::Bank,
+        fees::FeeStructure,
+        genesis::GenesisBuilder,
+        system_program,
+        token_program::{self, Mint, TokenAccount},
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_epoch_schedule::EpochSchedule;
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_sdk_ids::{bpf_loader, bpf_loader_upgradeable};
+    use solana_signer::Signer;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::fs;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+    const FEATURE: Pubkey = priority_graph_practice::feature_set::blake3_syscall_enabled::ID;
+
+    fn transaction(
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        blockhash: Hash,
+    ) -> SanitizedTransaction {
+        let message = Message::new_with_blockhash(instructions, Some(payer), &blockhash);
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_funded_keypairs_are_part_of_genesis() {
+        let builder = GenesisBuilder::new().with_funded_keypairs(3, LAMPORTS);
+        let keypairs = builder.keypairs();
+        assert_eq!(keypairs.len(), 3);
+        let mut bank = builder.build();
+        for keypair in keypairs {
+            assert_eq!(
+                bank.get_account(&keypair.pubkey()).unwrap().lamports(),
+                LAMPORTS
+            );
+        }
+        // Seeded accounts are in the genesis accounts hash already.
+        assert_ne!(
+            bank.accounts_lt_hash(),
+            GenesisBuilder::new().build().accounts_lt_hash()
+        );
+        assert_eq!(bank.accounts_lt_hash(), builder.build().accounts_lt_hash());
+        let reseeded = GenesisBuilder::new()
+            .with_seed(7)
+            .with_funded_keypairs(3, LAMPORTS);
+        assert_ne!(reseeded.keypairs()[0].pubkey(), keypairs[0].pubkey());
+
+        let (alice, bob) = (&keypairs[0], &keypairs[1]);
+        let message = Message::new(
+            &[system_program::transfer(
+                &alice.pubkey(),
+                &bob.pubkey(),
+                1_000_000,
+            )],
+            Some(&alice.pubkey()),
+        );
+        let transfer = SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+            &[alice],
+            message,
+            Hash::default(),
+        ));
+        let results = bank.process_transaction_batch(&[transfer]);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(
+            bank.get_account(&bob.pubkey()).unwrap().lamports(),
+            LAMPORTS + 1_000_000
+        );
+    }
+
+    #[test]
+    fn test_preloaded_programs() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let upgradeable_id = Pubkey::new_unique();
+        let path = std::env::temp_dir().join(format!("genesis-{program_id}.so"));
+        fs::write(&path, returning_elf(0)).unwrap();
+        let builder = GenesisBuilder::new()
+            .with_funded_account(payer, LAMPORTS)
+            .with_program_file(program_id, &path)
+            .unwrap()
+            .with_upgradeable_program(upgradeable_id, None, &returning_elf(0));
+        fs::remove_file(&path).unwrap();
+        assert!(GenesisBuilder::new()
+            .with_program_file(program_id, &path)
+            .is_err());
+
+        let mut bank = builder.build();
+        let program = bank.get_account(&program_id).unwrap();
+        assert!(program.executable());
+        assert_eq!(*program.owner(), bpf_loader::id());
+        assert_eq!(
+            program.lamports(),
+            Rent::default().minimum_balance(program.data().len())
+        );
+        assert_eq!(
+            *bank.get_account(&upgradeable_id).unwrap().owner(),
+            bpf_loader_upgradeable::id()
+        );
+
+        let call = |bank: &mut Bank, program_id| {
+            let instruction = Instruction::new_with_bytes(program_id, &[], vec![]);
+            let transaction = transaction(&[instruction], &payer, bank.last_blockhash());
+            bank.process_transaction_batch(&[transaction]).remove(0)
+        };
+        assert!(call(&mut bank, program_id).unwrap().was_successful());
+        // The upgradeable program was deployed in slot 0.
+        assert!(!call(&mut bank, upgradeable_id).unwrap().was_successful());
+        bank.advance_slot(Hash::new_unique());
+        assert!(call(&mut bank, upgradeable_id).unwrap().was_successful());
+    }
+
+    #[test]
+    fn test_parameters_and_builtins() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (alice_tokens, bob_tokens) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let rent = Rent {
+            lamports_per_byte: 10_000,
+            ..Rent::default()
+        };
+        let mint_state = Mint {
+            mint_authority: Some(payer),
+            supply: 100,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: None,
+        };
+        let mut bank = GenesisBuilder::new()
+            .with_rent(rent.clone())
+            .with_epoch_schedule(EpochSchedule::custom(64, 64, false))
+            .with_fee_structure(FeeStructure::new(7_000, 0))
+            .without_feature(&FEATURE)
+            .with_builtin(token_program::id(), token_program::process_instruction)
+            .with_funded_account(payer, LAMPORTS)
+            .with_accounts([
+                (mint, token_program::mint_account(&mint_state, &rent)),
+                (
+                    alice_tokens,
+                    token_program::token_account(&TokenAccount::new(mint, alice, 100), &rent),
+                ),
+                (
+                    bob_tokens,
+                    token_program::token_account(&TokenAccount::new(mint, bob, 0), &rent),
+                ),
+            ])
+            .build();
+        assert_eq!(*bank.rent(), rent);
+        assert_eq!(bank.sysvar_cache().get_rent(), &rent);
+        assert_eq!(bank.epoch_schedule().slots_per_epoch, 64);
+        assert_eq!(bank.fee_structure().lamports_per_signature, 7_000);
+        assert!(!bank.feature_set().is_active(&FEATURE));
+
+        let transfer = token_program::transfer(&alice_tokens, &bob_tokens, &alice, 40);
+        let results = bank.process_transaction_batch(&[transaction(
+            &[transfer],
+            &payer,
+            bank.last_blockhash(),
+        )]);
+        assert_eq!(
+            results[0].as_ref().unwrap().status,
+            Ok::<(), TransactionError>(())
+        );
+        let bob_account = bank.get_account(&bob_tokens).unwrap();
+        assert_eq!(TokenAccount::unpack(bob_account.data()).unwrap().amount, 40);
+        // Two signatures: the payer's and Alice's.
+        assert_eq!(
+            bank.get_account(&payer).unwrap().lamports(),
+            LAMPORTS - 14_000
+        );
+        // Stored accounts are kept as given.
+        let unfunded = GenesisBuilder::new()
+            .with_account(mint, AccountSharedData::new(1, 0, &token_program::id()))
+            .build();
+        assert_eq!(unfunded.get_account(&mint).unwrap().lamports(), 1);
+    }
+}
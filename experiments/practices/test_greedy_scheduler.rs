@@ -0,0 +1,69 @@
+//! Unit test: Pack transactions with `GreedyScheduler`
+//!
+//! Analogy: A greedy host walks down the waiting line and seats each party
+//! at the first round of tables that still has their tables free, without
+//! remembering who was waiting for whom.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::scheduler::{
+        GreedyScheduler, PriorityGraphScheduler, ScheduleBatch, Scheduler,
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(writable: &[Pubkey]) -> SanitizedTransaction {
+        let accounts = writable
+            .iter()
+            .map(|key| AccountMeta::new(*key, false))
+            .collect();
+        let instruction = Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts);
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn assert_conflict_free(transactions: &[SanitizedTransaction], batches: &[ScheduleBatch]) {
+        for batch in batches {
+            let mut written = Vec::new();
+            for &index in &batch.transaction_indexes {
+                let message = transactions[index].message();
+                for (key_index, key) in message.account_keys().iter().enumerate() {
+                    if message.is_writable(key_index) {
+                        assert!(!written.contains(key), "write conflict inside a batch");
+                        written.push(*key);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_greedy_first_fit() {
+        let (x, y) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // 0 and 1 conflict on x, 1 and 2 conflict on y, 0 and 2 are independent.
+        let transactions = vec![transaction(&[x]), transaction(&[x, y]), transaction(&[y])];
+
+        // First fit lets transaction 2 run alongside transaction 0, ahead of
+        // transaction 1 even though 1 arrived earlier.
+        let batches = GreedyScheduler::new().schedule(&transactions);
+        assert_eq!(
+            batches,
+            vec![
+                ScheduleBatch {
+                    transaction_indexes: vec![0, 2]
+                },
+                ScheduleBatch {
+                    transaction_indexes: vec![1]
+                },
+            ]
+        );
+        assert_conflict_free(&transactions, &batches);
+
+        // The priority graph keeps 2 behind 1, which costs an extra batch.
+        let batches = PriorityGraphScheduler::new().schedule(&transactions);
+        assert_eq!(batches.len(), 3);
+        assert_conflict_free(&transactions, &batches);
+    }
+}
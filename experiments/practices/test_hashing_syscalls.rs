@@ -0,0 +1,114 @@
+//! Unit test: Hash data from SBF programs through syscalls
+//!
+//! Analogy: A guest chef's recipe may say "stamp the order with today's
+//! seal". The kitchen keeps the stamps, so the chef only names which one:
+//! SHA-256, Keccak or BLAKE3. Stamping a thick order takes longer than a
+//! thin one, and the kitchen bills the time accordingly.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::svm::TransactionExecutor;
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::bpf_loader;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::collections::HashMap;
+
+    /// Offset of the first account's data in the input region: the account
+    /// count, then the account's flags, key, owner, lamports and data length.
+    const FIRST_ACCOUNT_DATA: i32 = 8 + 8 + 32 + 32 + 8 + 8;
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    /// Hashes the instruction data with `syscall` into the first account's
+    /// data, and returns the syscall's status.
+    fn hash_program(syscall: &[u8]) -> Vec<[u8; 8]> {
+        vec![
+            insn(0x79, 3, 2, -8, 0),                               // ldxdw r3, [r2 - 8]
+            insn(0x7b, 10, 2, -16, 0),                             // stxdw [r10 - 16], r2
+            insn(0x7b, 10, 3, -8, 0),                              // stxdw [r10 - 8], r3
+            insn(0xbf, 3, 1, 0, 0),                                // mov64 r3, r1
+            insn(0x07, 3, 0, 0, FIRST_ACCOUNT_DATA),               // add64 r3, data
+            insn(0xbf, 1, 10, 0, 0),                               // mov64 r1, r10
+            insn(0x07, 1, 0, 0, -16),                              // add64 r1, -16
+            insn(0xb7, 2, 0, 0, 1),                                // mov64 r2, 1
+            insn(0x85, 0, 0, 0, hash_symbol_name(syscall) as i32), // call
+            insn(0x95, 0, 0, 0, 0),                                // exit
+        ]
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    /// Runs the program hashing with `syscall` on `data`, returning the
+    /// digest and the compute units consumed.
+    fn hash(syscall: &[u8], data: &[u8]) -> ([u8; 32], u64) {
+        let program_id = Pubkey::new_unique();
+        let digest = Pubkey::new_unique();
+        let store = HashMap::from([
+            (program_id, program_account(&hash_program(syscall))),
+            (digest, AccountSharedData::new(1, 32, &program_id)),
+        ]);
+        let instruction =
+            Instruction::new_with_bytes(program_id, data, vec![AccountMeta::new(digest, false)]);
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[instruction], Some(&payer));
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+
+        let result = TransactionExecutor::new().load_and_execute_transaction(&store, &tx);
+        assert_eq!(result.status, Ok(()));
+        let (_, post) = result
+            .post_accounts
+            .iter()
+            .find(|(key, _)| *key == digest)
+            .unwrap();
+        (post.data().try_into().unwrap(), result.consumed_units)
+    }
+
+    #[test]
+    fn test_sol_sha256() {
+        let (digest, _) = hash(b"sol_sha256", b"hello");
+        assert_eq!(digest, solana_sha256_hasher::hash(b"hello").to_bytes());
+        let (digest, _) = hash(b"sol_sha256", &[]);
+        assert_eq!(digest, solana_sha256_hasher::hash(&[]).to_bytes());
+    }
+
+    #[test]
+    fn test_sol_keccak256_and_sol_blake3() {
+        let (keccak, _) = hash(b"sol_keccak256", b"hello");
+        assert_eq!(keccak, solana_keccak_hasher::hash(b"hello").to_bytes());
+        let (blake3, _) = hash(b"sol_blake3", b"hello");
+        assert_eq!(blake3, solana_blake3_hasher::hash(b"hello").to_bytes());
+        assert_ne!(keccak, blake3);
+    }
+
+    #[test]
+    fn test_hashing_cost_grows_with_input() {
+        // Short slices cost the per-slice minimum of 10 CUs, longer ones 1
+        // CU per two bytes.
+        let (_, short) = hash(b"sol_sha256", &[0; 20]);
+        let (_, long) = hash(b"sol_sha256", &[0; 1000]);
+        assert_eq!(long - short, 500 - 10);
+        let (_, blake3) = hash(b"sol_blake3", &[0; 1000]);
+        assert_eq!(blake3, long);
+    }
+}
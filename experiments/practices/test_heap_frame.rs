@@ -0,0 +1,189 @@
+//! Unit test: Request larger heaps and configure the VM's stack
+//!
+//! Analogy: Every cook gets the same small prep counter unless the ticket
+//! asks for a bigger one, and the bigger counter is billed by the extra
+//! meter. The kitchen itself decides how many cooks may hand a dish down
+//! the line at once and how large each of their shelves is; a recipe that
+//! needs more hands or deeper shelves than that cannot be made.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::{
+        compute_budget::{
+            process_compute_budget_instructions, ComputeBudgetInstruction, MAX_HEAP_FRAME_BYTES,
+            MIN_HEAP_FRAME_BYTES,
+        },
+        svm::{ComputeCostTable, TransactionExecutor, VmConfig},
+    };
+    use solana_account::{AccountSharedData, WritableAccount};
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::bpf_loader;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    /// Stores a word `offset` bytes into the heap.
+    fn heap_program(offset: i32) -> Vec<[u8; 8]> {
+        vec![
+            insn(0x18, 2, 0, 0, offset), // lddw r2, MM_HEAP_START + offset
+            insn(0x00, 0, 0, 0, 3),
+            insn(0x7a, 2, 0, 0, 1), // stdw [r2], 1
+            insn(0xb7, 0, 0, 0, 0), // mov64 r0, 0
+            insn(0x95, 0, 0, 0, 0), // exit
+        ]
+    }
+
+    /// Calls itself `depth` times, then stores a word `stack_offset` bytes
+    /// below its frame pointer.
+    fn stack_program(depth: i32, stack_offset: i16) -> Vec<[u8; 8]> {
+        vec![
+            insn(0xb7, 6, 0, 0, depth),          // mov64 r6, depth
+            insn(0x85, 0, 1, 0, 1),              // call recurse
+            insn(0x95, 0, 0, 0, 0),              // exit
+            insn(0x15, 6, 0, 2, 0),              // recurse: jeq r6, 0, +2
+            insn(0x07, 6, 0, 0, -1),             // add64 r6, -1
+            insn(0x85, 0, 1, 0, -3),             // call recurse
+            insn(0x7a, 10, 0, -stack_offset, 1), // stdw [r10 - offset], 1
+            insn(0xb7, 0, 0, 0, 0),              // mov64 r0, 0
+            insn(0x95, 0, 0, 0, 0),              // exit
+        ]
+    }
+
+    fn transaction(instructions: &[Instruction]) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_heap_frame_requests_are_validated() {
+        let tx = transaction(&[]);
+        let limits = process_compute_budget_instructions(tx.message()).unwrap();
+        assert_eq!(limits.updated_heap_bytes, MIN_HEAP_FRAME_BYTES);
+
+        let tx = transaction(&[ComputeBudgetInstruction::request_heap_frame(
+            MAX_HEAP_FRAME_BYTES,
+        )]);
+        let limits = process_compute_budget_instructions(tx.message()).unwrap();
+        assert_eq!(limits.updated_heap_bytes, MAX_HEAP_FRAME_BYTES);
+
+        // Too small, too large and not a whole number of KiB.
+        for bytes in [
+            MIN_HEAP_FRAME_BYTES - 1024,
+            MAX_HEAP_FRAME_BYTES + 1024,
+            MIN_HEAP_FRAME_BYTES + 1,
+        ] {
+            let tx = transaction(&[
+                ComputeBudgetInstruction::set_compute_unit_price(1),
+                ComputeBudgetInstruction::request_heap_frame(bytes),
+            ]);
+            assert_eq!(
+                process_compute_budget_instructions(tx.message()),
+                Err(TransactionError::InstructionError(
+                    1,
+                    InstructionError::InvalidInstructionData
+                )),
+                "{bytes} bytes"
+            );
+        }
+
+        let costs = ComputeCostTable::default();
+        assert_eq!(costs.heap_frame_cost(MIN_HEAP_FRAME_BYTES), 0);
+        assert_eq!(costs.heap_frame_cost(MIN_HEAP_FRAME_BYTES + 1024), 8);
+        assert_eq!(costs.heap_frame_cost(MAX_HEAP_FRAME_BYTES), 7 * 8);
+    }
+
+    #[test]
+    fn test_programs_get_and_pay_for_the_requested_heap() {
+        let program_id = Pubkey::new_unique();
+        let store = HashMap::from([(program_id, program_account(&heap_program(40_000)))]);
+        let program_ix = || Instruction::new_with_bytes(program_id, &[], vec![]);
+        let request = || ComputeBudgetInstruction::request_heap_frame(64 * 1024);
+        let mut executor = TransactionExecutor::new();
+
+        // Past the end of the default heap.
+        let result = executor.load_and_execute_transaction(&store, &transaction(&[program_ix()]));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ProgramFailedToComplete
+            ))
+        );
+
+        let tx = transaction(&[request(), program_ix()]);
+        let result = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(result.status, Ok(()));
+        // The second 32 KiB costs the table's heap cost on top of the four
+        // executed instructions and the compute budget instruction.
+        let costs = ComputeCostTable::default();
+        assert_eq!(result.consumed_units, 150 + 4 + costs.heap_cost);
+
+        executor.set_compute_costs(ComputeCostTable {
+            heap_cost: 1_000,
+            ..costs
+        });
+        let repriced = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(repriced.consumed_units, 150 + 4 + 1_000);
+    }
+
+    #[test]
+    fn test_vm_config_bounds_call_depth_and_stack() {
+        // Every program gets an id of its own, so none of them comes out
+        // of the program cache.
+        let run = |executor: &TransactionExecutor, program: Vec<[u8; 8]>| {
+            let program_id = Pubkey::new_unique();
+            let store = HashMap::from([(program_id, program_account(&program))]);
+            let ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+            executor
+                .load_and_execute_transaction(&store, &transaction(&[ix]))
+                .status
+        };
+        let failed = Err(TransactionError::InstructionError(
+            0,
+            InstructionError::ProgramFailedToComplete,
+        ));
+
+        let mut executor = TransactionExecutor::new();
+        assert_eq!(*executor.vm_config(), VmConfig::default());
+        assert_eq!(run(&executor, stack_program(10, 8)), Ok(()));
+        // The recursive call runs in the second frame, and reaching below
+        // the first one leaves the stack.
+        assert_eq!(run(&executor, stack_program(0, 8200)), failed);
+
+        executor.set_vm_config(VmConfig {
+            max_call_depth: 8,
+            ..VmConfig::default()
+        });
+        assert_eq!(run(&executor, stack_program(5, 8)), Ok(()));
+        assert_eq!(run(&executor, stack_program(10, 8)), failed);
+
+        executor.set_vm_config(VmConfig {
+            stack_frame_size: 16 * 1024,
+            ..VmConfig::default()
+        });
+        assert_eq!(run(&executor, stack_program(0, 8200)), Ok(()));
+    }
+}
@@ -0,0 +1,187 @@
+//! Unit test: Bound the queue in front of the scheduler with an overflow policy
+//!
+//! Analogy: The waiting area holds so many guests. When it is full the
+//! doorman either keeps the next guest on the street until a seat frees
+//! up, turns the newcomer away, or lets them in and sends the guest with
+//! the smallest tip home instead. Every guest sent away is noted in the
+//! book, so the manager can tell how much business a busy night lost.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        events::{DropReason, Event, EventBus},
+        pipeline::{
+            ExecutedBatch, IngestBuffer, OverflowPolicy, Pipeline, PipelineAccounts,
+            PipelineConfig, SigVerifyStage,
+        },
+        sigverify::SigVerifyConfig,
+        svm::TransactionExecutor,
+    };
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    fn transaction(cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn cu_prices(transactions: &[SanitizedTransaction], all: &[SanitizedTransaction]) -> Vec<u64> {
+        transactions
+            .iter()
+            .map(|transaction| all.iter().position(|other| other == transaction).unwrap() as u64)
+            .collect()
+    }
+
+    #[test]
+    fn test_full_buffer_drops_by_policy() {
+        // Each transaction's compute unit price is its index.
+        let transactions: Vec<_> = (0..4).map(transaction).collect();
+
+        let newest = IngestBuffer::new(2, OverflowPolicy::DropNewest);
+        assert_eq!(newest.push(transactions[1].clone()), None);
+        assert_eq!(newest.push(transactions[2].clone()), None);
+        assert_eq!(
+            newest.push(transactions[3].clone()),
+            Some(transactions[3].clone())
+        );
+        assert_eq!(cu_prices(&newest.pop_batch(8), &transactions), [2, 1]);
+
+        let event_bus = EventBus::new();
+        let events = event_bus.subscribe();
+        let lowest =
+            IngestBuffer::new(2, OverflowPolicy::DropLowestPriority).with_event_bus(event_bus);
+        lowest.set_slot(7);
+        lowest.push(transactions[1].clone());
+        lowest.push(transactions[2].clone());
+        // A transaction paying more displaces the cheapest one queued, and
+        // one paying less than everything queued is dropped itself.
+        assert_eq!(
+            lowest.push(transactions[3].clone()),
+            Some(transactions[1].clone())
+        );
+        assert_eq!(
+            lowest.push(transactions[0].clone()),
+            Some(transactions[0].clone())
+        );
+        assert_eq!(lowest.num_dropped(), 2);
+        assert_eq!(cu_prices(&lowest.pop_batch(1), &transactions), [3]);
+        assert_eq!(cu_prices(&lowest.pop_batch(1), &transactions), [2]);
+
+        let dropped: Vec<_> = events.try_iter().collect();
+        assert_eq!(
+            dropped,
+            [1, 0].map(|index| Event::TransactionDropped {
+                slot: 7,
+                signature: *transactions[index].signature(),
+                reason: DropReason::QueueFull,
+            })
+        );
+    }
+
+    #[test]
+    fn test_blocking_buffer_holds_the_producer_back() {
+        let buffer = Arc::new(IngestBuffer::new(1, OverflowPolicy::Block));
+        buffer.push(transaction(1));
+        let pushed = Arc::new(AtomicBool::new(false));
+        let producer = thread::spawn({
+            let (buffer, pushed) = (Arc::clone(&buffer), Arc::clone(&pushed));
+            move || {
+                assert_eq!(buffer.push(transaction(2)), None);
+                pushed.store(true, Ordering::Release);
+            }
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!pushed.load(Ordering::Acquire));
+
+        assert_eq!(buffer.pop_batch(4).len(), 1);
+        producer.join().unwrap();
+        assert!(pushed.load(Ordering::Acquire));
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer.num_dropped(), 0);
+
+        // Closing wakes a popper once the buffer is empty.
+        buffer.pop_batch(4);
+        let popper = thread::spawn({
+            let buffer = Arc::clone(&buffer);
+            move || buffer.pop_batch(4)
+        });
+        buffer.close();
+        assert!(popper.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_overloaded_pipeline_reports_dropped_transactions() {
+        let event_bus = EventBus::new();
+        let events = event_bus.subscribe();
+        let accounts = Arc::new(PipelineAccounts::default());
+        let commit_accounts = Arc::clone(&accounts);
+        // Execution is slow, so transactions pile up in front of the
+        // scheduler.
+        let slow_execute = move |transactions: Vec<SanitizedTransaction>| {
+            thread::sleep(Duration::from_millis(20));
+            ExecutedBatch {
+                results: Vec::new(),
+                transactions,
+            }
+        };
+        let config = PipelineConfig {
+            schedule_capacity: 4,
+            overflow_policy: OverflowPolicy::DropNewest,
+            schedule_batch_size: 4,
+            execute_capacity: 1,
+            ..PipelineConfig::default()
+        };
+        let skip = SigVerifyStage::new(SigVerifyConfig {
+            skip_sigverify: true,
+            ..SigVerifyConfig::default()
+        });
+        let transactions: Vec<_> = (0..200)
+            .map(|cu_price| transaction(cu_price).to_versioned_transaction())
+            .collect();
+        let (committed, metrics) = Pipeline::new(Arc::new(TransactionExecutor::new()), accounts)
+            .with_config(config)
+            .with_event_bus(event_bus)
+            .with_sigverify(skip)
+            .with_execute(slow_execute)
+            .with_commit(move |batch: ExecutedBatch| {
+                commit_accounts.commit_batch(&batch);
+                batch
+            })
+            .run(transactions.chunks(10).map(<[_]>::to_vec));
+
+        let num_committed: usize = committed.iter().map(|batch| batch.transactions.len()).sum();
+        assert!(metrics.transactions_dropped > 0);
+        assert_eq!(num_committed as u64 + metrics.transactions_dropped, 200);
+        let num_queue_full = events
+            .try_iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    Event::TransactionDropped {
+                        reason: DropReason::QueueFull,
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(num_queue_full as u64, metrics.transactions_dropped);
+        // Dropping instead of blocking keeps the producer moving: all its
+        // pushes took less than a single execution.
+        assert!(metrics.stage("sigverify").unwrap().blocked < Duration::from_millis(20));
+    }
+}
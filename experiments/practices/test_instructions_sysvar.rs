@@ -0,0 +1,216 @@
+//! Unit test: Let programs introspect their transaction's instructions
+//!
+//! Analogy: Every cook can read the whole ticket, not just their own line,
+//! along with a mark saying which line is theirs. The pastry cook only
+//! plates a dessert when the line just above theirs is the sommelier's
+//! signed note for the same table. The ticket is handed out as written;
+//! nothing a cook brings from home replaces it.
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer as _;
+    use priority_graph_practice::{
+        precompiles::{new_ed25519_instruction, CURRENT_INSTRUCTION},
+        svm::{load_transaction_accounts, InvokeContext, TransactionExecutor},
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_instructions_sysvar::load_instruction_at;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{ed25519_program, sysvar};
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    fn sysvar_account() -> AccountMeta {
+        AccountMeta::new_readonly(sysvar::instructions::id(), false)
+    }
+
+    fn transaction(instructions: &[Instruction]) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    /// The index of the executing instruction, which ends the sysvar.
+    fn current_index(data: &[u8]) -> usize {
+        u16::from_le_bytes([data[data.len() - 2], data[data.len() - 1]]).into()
+    }
+
+    fn ed25519_instruction(key: &ed25519_dalek::SigningKey, message: &[u8]) -> Instruction {
+        let signature = key.sign(message).to_bytes();
+        new_ed25519_instruction(&key.verifying_key().to_bytes(), &signature, message)
+    }
+
+    // Succeeds if the instruction before it verifies a single ed25519
+    // signature of this instruction's data by the key of its second
+    // account, with the signature, key and message all inside the verify
+    // instruction as `new_ed25519_instruction` lays them out.
+    fn require_signed(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let data = invoke_context.get_account(0)?.data();
+        let previous = current_index(data)
+            .checked_sub(1)
+            .ok_or(InstructionError::Custom(1))?;
+        let verify = load_instruction_at(previous, data)
+            .map_err(|_| InstructionError::InvalidAccountData)?;
+        if verify.program_id != ed25519_program::id() || verify.data[0] != 1 {
+            return Err(InstructionError::Custom(2));
+        }
+        let field =
+            |offset: usize| u16::from_le_bytes([verify.data[offset], verify.data[offset + 1]]);
+        // Every offset must point into the verify instruction itself.
+        if [4, 8, 14]
+            .iter()
+            .any(|&offset| field(offset) != CURRENT_INSTRUCTION)
+        {
+            return Err(InstructionError::Custom(3));
+        }
+        let public_key = usize::from(field(6));
+        let message = usize::from(field(10))..usize::from(field(10) + field(12));
+        let expected_key = invoke_context.get_key(1)?.to_bytes();
+        let signed = verify.data[public_key..public_key + 32] == expected_key
+            && verify.data[message] == *invoke_context.instruction_data();
+        signed.then_some(()).ok_or(InstructionError::Custom(4))
+    }
+
+    #[test]
+    fn test_programs_see_every_instruction_and_their_own_index() {
+        let checker = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        // Fails unless it is the instruction its data names, and the
+        // transaction holds as many instructions as its second byte says.
+        executor.add_program(checker, |invoke_context: &mut InvokeContext| {
+            let data = invoke_context.get_account(0)?.data();
+            let num_instructions = u16::from_le_bytes([data[0], data[1]]);
+            let own = match load_instruction_at(current_index(data), data) {
+                Ok(own) => own,
+                Err(_) => return Err(InstructionError::InvalidAccountData),
+            };
+            let expected = [current_index(data) as u8, num_instructions as u8];
+            (own.data == expected && *invoke_context.instruction_data() == expected)
+                .then_some(())
+                .ok_or(InstructionError::Custom(1))
+        });
+        let check =
+            |index: u8| Instruction::new_with_bytes(checker, &[index, 3], vec![sysvar_account()]);
+        let other = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[7; 4],
+            vec![AccountMeta::new(Pubkey::new_unique(), true)],
+        );
+        executor.add_program(other.program_id, |_: &mut InvokeContext| Ok(()));
+
+        let tx = transaction(&[check(0), other.clone(), check(2)]);
+        let result = executor.load_and_execute_transaction(&HashMap::new(), &tx);
+        assert_eq!(result.status, Ok(()));
+
+        // The sysvar describes instructions as the message lists them.
+        let accounts = load_transaction_accounts(&HashMap::new(), &tx);
+        let sysvar_index = tx
+            .message()
+            .account_keys()
+            .iter()
+            .position(|key| *key == sysvar::instructions::id())
+            .unwrap();
+        let result = executor.execute_transaction(&tx, accounts);
+        let data = result.post_accounts[sysvar_index].1.data();
+        assert_eq!(load_instruction_at(1, data).unwrap(), other);
+        assert_eq!(load_instruction_at(2, data).unwrap(), check(2));
+        assert!(load_instruction_at(3, data).is_err());
+
+        // Each instruction only passes at its own index.
+        let tx = transaction(&[other, check(0)]);
+        let result = executor.load_and_execute_transaction(&HashMap::new(), &tx);
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                1,
+                InstructionError::Custom(1)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_programs_check_a_preceding_ed25519_verification() {
+        let verifier = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(verifier, require_signed);
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let signer = Pubkey::from(key.verifying_key().to_bytes());
+        let require = |message: &[u8]| {
+            Instruction::new_with_bytes(
+                verifier,
+                message,
+                vec![sysvar_account(), AccountMeta::new_readonly(signer, false)],
+            )
+        };
+        let execute = |instructions: &[Instruction]| {
+            executor
+                .load_and_execute_transaction(&HashMap::new(), &transaction(instructions))
+                .status
+        };
+        let failed = |code| {
+            Err(TransactionError::InstructionError(
+                1,
+                InstructionError::Custom(code),
+            ))
+        };
+
+        assert_eq!(
+            execute(&[ed25519_instruction(&key, b"pay 5"), require(b"pay 5")]),
+            Ok(())
+        );
+        // Signed by someone else, or for another message.
+        let other_key = ed25519_dalek::SigningKey::from_bytes(&[8; 32]);
+        assert_eq!(
+            execute(&[ed25519_instruction(&other_key, b"pay 5"), require(b"pay 5")]),
+            failed(4)
+        );
+        assert_eq!(
+            execute(&[ed25519_instruction(&key, b"pay 1"), require(b"pay 5")]),
+            failed(4)
+        );
+        // Without a verification right before it.
+        assert_eq!(
+            execute(&[require(b"pay 5")]),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sysvar_is_built_rather_than_loaded() {
+        let reader = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.set_record_account_diffs(true);
+        // Fails unless the sysvar holds exactly one instruction.
+        executor.add_program(reader, |invoke_context: &mut InvokeContext| {
+            let account = invoke_context.get_account(0)?;
+            let data = account.data();
+            let is_sysvar = *account.owner() == sysvar::id() && data[..2] == 1u16.to_le_bytes();
+            is_sysvar.then_some(()).ok_or(InstructionError::Custom(1))
+        });
+        let tx = transaction(&[Instruction::new_with_bytes(
+            reader,
+            &[],
+            vec![sysvar_account()],
+        )]);
+
+        // Whatever the store holds for the sysvar is ignored.
+        let forged = AccountSharedData::new(1, 64, &reader);
+        let store = HashMap::from([(sysvar::instructions::id(), forged)]);
+        let result = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.account_diffs, Some(vec![]));
+        let (_, account) = result
+            .post_accounts
+            .iter()
+            .find(|(key, _)| *key == sysvar::instructions::id())
+            .unwrap();
+        assert_eq!(*account.owner(), sysvar::id());
+        assert_eq!(account.lamports(), 0);
+    }
+}
@@ -0,0 +1,182 @@
+//! Unit test: Run scheduling experiments from TOML files
+//!
+//! Analogy: Instead of rearranging the kitchen by hand every time the
+//! owner wants to try something, each trial is written on a card: how
+//! busy the night is, who seats the guests, how many cooks, how long a
+//! service lasts. Anyone can hand the card to the kitchen and get the same
+//! night back, with a sheet of how every service went.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        lab::{run_experiment, ExperimentConfig, ExperimentError, SchedulerKind},
+        scheduler::BatchLimits,
+        workload::PriorityDistribution,
+    };
+    use solana_pubkey::Pubkey;
+    use std::{path::PathBuf, time::Duration};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "svm-lab-{}-{}-{name}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ))
+    }
+
+    #[test]
+    fn test_experiment_overrides_defaults() {
+        let config = ExperimentConfig::from_toml(
+            r#"
+            name = "hot accounts"
+            slots = 3
+
+            [workload]
+            num_transactions = 500
+            hot_account_ratio = 1
+            num_hot_accounts = 2
+            priority_distribution = { kind = "zipf", levels = 10, exponent = 1.5, cu_price_step = 7 }
+
+            [scheduler]
+            kind = "multi_iterator"
+            num_workers = 2
+            slot_duration_ms = 50
+
+            [limits]
+            max_txs = 16
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.name, "hot accounts");
+        assert_eq!(config.num_slots, 3);
+        assert_eq!(config.workload.num_transactions, 500);
+        assert_eq!(config.workload.hot_account_ratio, 1.0);
+        assert_eq!(
+            config.workload.priority_distribution,
+            PriorityDistribution::Zipf {
+                levels: 10,
+                exponent: 1.5,
+                cu_price_step: 7,
+            }
+        );
+        assert_eq!(config.scheduler, SchedulerKind::MultiIterator);
+        assert_eq!(config.num_workers, 2);
+        assert_eq!(config.slot_duration, Duration::from_millis(50));
+        assert_eq!(
+            config.batch_limits,
+            BatchLimits {
+                max_txs: 16,
+                ..BatchLimits::default()
+            }
+        );
+
+        assert_eq!(
+            ExperimentConfig::from_toml("").unwrap(),
+            ExperimentConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_experiment_rejects_typos_and_invalid_values() {
+        let err = |toml: &str| ExperimentConfig::from_toml(toml).unwrap_err().to_string();
+        assert_eq!(
+            err("[workload]\nnum_transaction = 5"),
+            ExperimentError::UnknownKey("workload.num_transaction".to_string()).to_string()
+        );
+        assert_eq!(
+            err("[scheduler]\nkind = \"round_robin\""),
+            "`scheduler.kind` has an invalid value"
+        );
+        assert_eq!(
+            err("[scheduler]\nnum_workers = 0"),
+            "`scheduler.num_workers` has an invalid value"
+        );
+        assert_eq!(
+            err("[workload]\nhot_account_ratio = 2.0"),
+            "`workload.hot_account_ratio` has an invalid value"
+        );
+        assert_eq!(
+            err("[workload]\npriority_distribution = { kind = \"uniform\", min_cu_price = 1 }"),
+            "`workload.priority_distribution.max_cu_price` has an invalid value"
+        );
+        assert!(matches!(
+            ExperimentConfig::from_toml("slots = "),
+            Err(ExperimentError::InvalidToml(_))
+        ));
+    }
+
+    #[test]
+    fn test_experiment_runs_every_transaction_and_writes_a_report() {
+        let config = ExperimentConfig::from_toml(
+            r#"
+            name = "smoke"
+            [workload]
+            num_transactions = 200
+            num_payers = 50
+            hot_account_ratio = 0.5
+            [scheduler]
+            num_workers = 2
+            slot_duration_ms = 60000
+            [limits]
+            max_txs = 8
+            "#,
+        )
+        .unwrap();
+        let report = run_experiment(&config).unwrap();
+        assert_eq!(report.slots.len(), 1);
+        assert_eq!(report.transactions_executed(), 200);
+        assert_eq!(report.transactions_succeeded(), 200);
+        assert_eq!(report.transactions_not_executed(), 0);
+        assert_eq!(report.slots[0].transactions_pending, 0);
+        assert!(report.slots[0].consumed_units > 0);
+        assert!(report.throughput() > 0.0);
+
+        let json = report.to_json();
+        assert_eq!(json["name"], "smoke");
+        assert_eq!(json["scheduler"], "prio_graph");
+        assert_eq!(json["transactionsExecuted"], 200);
+
+        let csv_path = temp_path("report.csv");
+        report.write(&csv_path).unwrap();
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("0,200,200,"));
+        std::fs::remove_file(&csv_path).unwrap();
+        assert!(matches!(
+            report.write(temp_path("report.txt")),
+            Err(ExperimentError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_experiment_carries_what_a_slot_missed_to_the_next() {
+        // Every transfer pays the one hot account, so batches hold a
+        // single transaction and a slot this short may end with work left
+        // for the next one.
+        let config = ExperimentConfig::from_toml(
+            r#"
+            slots = 3
+            [workload]
+            num_transactions = 3000
+            hot_account_ratio = 1
+            [scheduler]
+            kind = "fifo"
+            num_workers = 2
+            slot_duration_ms = 5
+            "#,
+        )
+        .unwrap();
+        let report = run_experiment(&config).unwrap();
+        assert!(!report.slots.is_empty() && report.slots.len() <= 3);
+        assert_eq!(
+            report.transactions_executed() + report.transactions_not_executed(),
+            3000
+        );
+        for pair in report.slots.windows(2) {
+            assert_eq!(
+                pair[1].transactions_executed + pair[1].transactions_pending,
+                pair[0].transactions_pending
+            );
+        }
+    }
+}
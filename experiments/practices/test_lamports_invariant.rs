@@ -0,0 +1,106 @@
+//! Unit test: Check that committed transactions conserve lamports
+//!
+//! Analogy: After every order the manager counts the cash in the tills the
+//! order touched. What is missing has to be exactly the service charge;
+//! if money appeared or vanished on top of that, the manager stops the
+//! kitchen and names the order it happened on.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        svm::{TransactionExecutionResult, TransactionExecutor},
+        system_program,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::panic::{self, AssertUnwindSafe};
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn store_system_account(bank: &mut Bank) -> Pubkey {
+        let pubkey = Pubkey::new_unique();
+        bank.store_account(
+            pubkey,
+            AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+        );
+        pubkey
+    }
+
+    fn transfer(bank: &Bank, from: &Pubkey, to: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let message = Message::new_with_blockhash(
+            &[system_program::transfer(from, to, lamports)],
+            Some(from),
+            &bank.last_blockhash(),
+        );
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_processed_transactions_conserve_lamports() {
+        let mut bank = Bank::default();
+        assert!(bank.verify_lamports());
+        let [alice, bob] = [(); 2].map(|()| store_system_account(&mut bank));
+        let capitalization = bank.capitalization();
+
+        // A failed transaction only loses its fee, a successful one moves
+        // lamports around on top of that.
+        let transactions = [
+            transfer(&bank, &alice, &bob, 1_000),
+            transfer(&bank, &bob, &alice, 2 * LAMPORTS),
+        ];
+        let results = bank.process_transaction_batch(&transactions);
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert!(!results[1].as_ref().unwrap().was_successful());
+        assert_eq!(
+            bank.capitalization(),
+            capitalization - bank.collected_fees()
+        );
+
+        // So does executing and committing separately.
+        let tx = transfer(&bank, &bob, &alice, 500);
+        let result =
+            TransactionExecutor::new().load_and_execute_transaction(bank.accounts_db(), &tx);
+        assert_eq!(bank.commit(&[tx], &[result]), 1);
+    }
+
+    /// A bank and a transfer between two of its accounts, with the result
+    /// of a buggy executor that credits the recipient twice.
+    fn minting_transfer() -> (Bank, SanitizedTransaction, TransactionExecutionResult) {
+        let mut bank = Bank::default();
+        let [alice, bob] = [(); 2].map(|()| store_system_account(&mut bank));
+        let tx = transfer(&bank, &alice, &bob, 1_000);
+        let mut result =
+            TransactionExecutor::new().load_and_execute_transaction(bank.accounts_db(), &tx);
+        let recipient = &mut result.post_accounts[1].1;
+        recipient.set_lamports(recipient.lamports() + 1_000);
+        (bank, tx, result)
+    }
+
+    #[test]
+    fn test_minted_lamports_name_the_transaction() {
+        let (mut bank, tx, result) = minting_transfer();
+        let signature = *tx.signature();
+        let panic =
+            panic::catch_unwind(AssertUnwindSafe(|| bank.commit(&[tx], &[result]))).unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.contains(&signature.to_string()), "{message}");
+        assert!(
+            message.contains(&format!(
+                "from {} to {}",
+                2 * LAMPORTS,
+                2 * LAMPORTS + 1_000
+            )),
+            "{message}"
+        );
+
+        // Without the check the bug goes unnoticed.
+        let (mut bank, tx, result) = minting_transfer();
+        bank.set_verify_lamports(false);
+        let capitalization = bank.capitalization();
+        assert_eq!(bank.commit(&[tx], &[result]), 1);
+        assert_eq!(bank.capitalization(), capitalization + 1_000);
+    }
+}
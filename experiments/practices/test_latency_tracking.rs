@@ -0,0 +1,148 @@
+//! Unit test: Track per-transaction latency from ingest to commit
+//!
+//! Analogy: Every order gets its ticket stamped when it comes in, when the
+//! host seats it, when the kitchen starts on it and when the plate leaves
+//! the pass. At closing time the manager does not care how the average
+//! order did, but how long the slowest one in twenty, or in a hundred,
+//! waited, night by night.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts_storage::ShardedStorage,
+        metrics::{LatencyPercentiles, LatencyStage, LatencyTracker},
+        pipeline::{Pipeline, PipelineAccounts, SigVerifyStage},
+        sigverify::SigVerifyConfig,
+        svm::TransactionExecutor,
+        workload::{Workload, WorkloadConfig},
+    };
+    use solana_signature::Signature;
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_percentiles_by_nearest_rank() {
+        let percentiles = LatencyPercentiles::from_latencies((1..=100).rev().map(millis).collect());
+        assert_eq!(percentiles.samples, 100);
+        assert_eq!(percentiles.p50, millis(50));
+        assert_eq!(percentiles.p95, millis(95));
+        assert_eq!(percentiles.p99, millis(99));
+        assert_eq!(percentiles.max, millis(100));
+
+        let single = LatencyPercentiles::from_latencies(vec![millis(7)]);
+        assert_eq!(
+            (single.p50, single.p99, single.max),
+            (millis(7), millis(7), millis(7))
+        );
+        assert_eq!(
+            LatencyPercentiles::from_latencies(Vec::new()),
+            LatencyPercentiles::default()
+        );
+    }
+
+    #[test]
+    fn test_commits_are_summed_up_per_slot() {
+        let tracker = LatencyTracker::new();
+        let start = Instant::now();
+        let signatures: Vec<_> = (0..4u8).map(|byte| Signature::from([byte; 64])).collect();
+        for (offset, signature) in (0..).zip(&signatures) {
+            tracker.record(signature, LatencyStage::Ingest, start);
+            tracker.record(
+                signature,
+                LatencyStage::Schedule,
+                start + millis(offset + 1),
+            );
+            tracker.record(
+                signature,
+                LatencyStage::ExecuteStart,
+                start + millis(offset + 2),
+            );
+        }
+        assert_eq!(tracker.num_in_flight(), 4);
+        let in_flight = tracker.in_flight(&signatures[3]).unwrap();
+        assert_eq!(in_flight.queued(), Some(millis(4)));
+        assert_eq!(in_flight.total(), None);
+
+        tracker.set_slot(5);
+        tracker.record_all(&signatures[..3], LatencyStage::Commit, start + millis(10));
+        tracker.set_slot(6);
+        tracker.record(&signatures[3], LatencyStage::Commit, start + millis(20));
+        assert_eq!(tracker.num_in_flight(), 0);
+
+        let slot_5 = tracker.slot_summary(5).unwrap();
+        assert_eq!(slot_5.transactions, 3);
+        assert_eq!(slot_5.queued.p50, millis(2));
+        assert_eq!(slot_5.queued.max, millis(3));
+        assert_eq!(slot_5.dispatched.max, millis(1));
+        assert_eq!(slot_5.executed.p99, millis(8));
+        assert_eq!(slot_5.total.p99, millis(10));
+
+        let summaries = tracker.summaries();
+        assert_eq!(
+            summaries
+                .iter()
+                .map(|summary| summary.slot)
+                .collect::<Vec<_>>(),
+            [5, 6]
+        );
+        assert_eq!(summaries[1].total.max, millis(20));
+        assert!(tracker.slot_summary(7).is_none());
+    }
+
+    #[test]
+    fn test_pipeline_times_every_committed_transaction() {
+        let workload = Workload::generate(&WorkloadConfig {
+            num_transactions: 100,
+            num_payers: 10,
+            hot_account_ratio: 0.5,
+            ..WorkloadConfig::default()
+        });
+        let storage = ShardedStorage::default();
+        storage.commit(workload.payer_accounts(1_000_000_000));
+        let accounts = Arc::new(PipelineAccounts::new(storage));
+        let tracker = Arc::new(LatencyTracker::new());
+        tracker.set_slot(3);
+        // The workload is unsigned, so signatures go unchecked; the tracker
+        // only needs them to tell transactions apart.
+        let skip = SigVerifyStage::new(SigVerifyConfig {
+            skip_sigverify: true,
+            ..SigVerifyConfig::default()
+        });
+        let transactions: Vec<_> = (0u64..)
+            .zip(&workload.transactions)
+            .map(|(index, transaction)| {
+                let mut transaction = transaction.to_versioned_transaction();
+                let mut signature = [0; 64];
+                signature[..8].copy_from_slice(&index.to_le_bytes());
+                transaction.signatures[0] = Signature::from(signature);
+                transaction
+            })
+            .collect();
+        let (committed, _) = Pipeline::new(Arc::new(TransactionExecutor::new()), accounts)
+            .with_sigverify(skip)
+            .with_latency_tracker(Arc::clone(&tracker))
+            .run(transactions.chunks(25).map(<[_]>::to_vec));
+        let executed: usize = committed.iter().map(|batch| batch.results.len()).sum();
+
+        let summary = tracker.slot_summary(3).unwrap();
+        assert_eq!(summary.transactions, executed);
+        for phase in [
+            summary.queued,
+            summary.dispatched,
+            summary.executed,
+            summary.total,
+        ] {
+            assert_eq!(phase.samples, executed);
+            assert!(phase.p50 <= phase.p95 && phase.p95 <= phase.p99);
+            assert!(phase.p99 <= phase.max);
+        }
+        assert!(summary.total.max >= summary.queued.max);
+        assert_eq!(tracker.num_in_flight(), 0);
+    }
+}
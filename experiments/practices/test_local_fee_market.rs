@@ -0,0 +1,174 @@
+//! Unit test: Charge local fees for writing to contended accounts
+//!
+//! Analogy: The window table is what everyone asks for. The first few
+//! parties of the night get it at the usual price, and after that every
+//! party that wants it pays a surcharge that doubles each time, while the
+//! other tables keep their price. The count starts over every evening.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        local_fee_market::{ExponentialFeeMarket, LocalFeeMarket, WriteDemand},
+        scheduler::{Bundle, BundleError},
+        svm::InvokeContext,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::sync::Arc;
+
+    const LAMPORTS: u64 = 1_000_000;
+
+    // A mock program that fails if its instruction data is not empty.
+    fn noop(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        if invoke_context.instruction_data().is_empty() {
+            Ok(())
+        } else {
+            Err(InstructionError::Custom(0))
+        }
+    }
+
+    fn setup() -> (Bank, Pubkey) {
+        let program_id = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.set_rent(Rent::free());
+        bank.add_builtin(program_id, noop);
+        (bank, program_id)
+    }
+
+    // Writes `writable` on behalf of a new, funded fee payer.
+    fn transaction(
+        bank: &mut Bank,
+        program_id: Pubkey,
+        writable: Pubkey,
+        data: &[u8],
+    ) -> SanitizedTransaction {
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let instruction =
+            Instruction::new_with_bytes(program_id, data, vec![AccountMeta::new(writable, false)]);
+        let message =
+            Message::new_with_blockhash(&[instruction], Some(&payer), &bank.last_blockhash());
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    // Only prices writes to one account, at a flat fee per earlier write.
+    struct HotAccountMarket(Pubkey);
+
+    impl LocalFeeMarket for HotAccountMarket {
+        fn write_lock_fee(&self, account: &Pubkey, demand: u64) -> u64 {
+            if *account == self.0 {
+                demand * 10
+            } else {
+                0
+            }
+        }
+    }
+
+    #[test]
+    fn test_exponential_fee_market_and_write_demand() {
+        let market = ExponentialFeeMarket::new(10, 2);
+        let account = Pubkey::new_unique();
+        let fees: Vec<_> = (0..5)
+            .map(|demand| market.write_lock_fee(&account, demand))
+            .collect();
+        assert_eq!(fees, vec![0, 0, 10, 20, 40]);
+        assert_eq!(market.write_lock_fee(&account, 1_000), u64::MAX);
+
+        let (mut bank, program_id) = setup();
+        let transaction = transaction(&mut bank, program_id, account, &[]);
+        let mut write_demand = WriteDemand::new();
+        write_demand.record(&transaction);
+        write_demand.record(&transaction);
+        assert_eq!(write_demand.demand(&account), 2);
+        // The fee payer is write-locked too, so both accounts are priced.
+        assert_eq!(write_demand.iter().count(), 2);
+        assert_eq!(market.local_fee(&transaction, &write_demand), 20);
+        write_demand.remove(&transaction);
+        write_demand.remove(&transaction);
+        assert_eq!(write_demand, WriteDemand::new());
+    }
+
+    #[test]
+    fn test_bank_charges_local_fees_by_slot_demand() {
+        let (mut bank, program_id) = setup();
+        let hot_account = Pubkey::new_unique();
+        bank.set_local_fee_market(Some(Arc::new(ExponentialFeeMarket::new(100, 1))));
+        let transactions: Vec<_> = (0..3)
+            .map(|_| transaction(&mut bank, program_id, hot_account, &[]))
+            .collect();
+        let collected_before = bank.collected_fees();
+
+        let results = bank.process_transaction_batch(&transactions);
+        let local_fees: Vec<_> = results
+            .iter()
+            .map(|result| result.as_ref().unwrap().fee_details.local_fee)
+            .collect();
+        assert_eq!(local_fees, vec![0, 100, 200]);
+        let payer = transactions[2].message().fee_payer();
+        let fee_details = results[2].as_ref().unwrap().fee_details;
+        assert_eq!(
+            bank.get_account(payer).unwrap().lamports(),
+            LAMPORTS - fee_details.total_fee()
+        );
+        assert_eq!(
+            bank.collected_fees() - collected_before,
+            results
+                .iter()
+                .map(|result| result.as_ref().unwrap().fee_details.total_fee())
+                .sum::<u64>()
+        );
+        assert_eq!(bank.write_demand().demand(&hot_account), 3);
+        let next = transaction(&mut bank, program_id, hot_account, &[]);
+        assert_eq!(bank.local_fee(&next), 400);
+
+        // Demand starts over with the next slot; the market stays.
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(bank.write_demand().demand(&hot_account), 0);
+        assert_eq!(bank.local_fee(&next), 0);
+        assert!(bank.local_fee_market().is_some());
+        bank.set_local_fee_market(None);
+        assert!(bank.local_fee_market().is_none());
+    }
+
+    #[test]
+    fn test_failed_bundle_leaves_write_demand_untouched() {
+        let (mut bank, program_id) = setup();
+        let hot_account = Pubkey::new_unique();
+        bank.set_local_fee_market(Some(Arc::new(HotAccountMarket(hot_account))));
+        let bundle = Bundle::new(vec![
+            transaction(&mut bank, program_id, hot_account, &[]),
+            transaction(&mut bank, program_id, hot_account, &[1]),
+        ])
+        .unwrap();
+        assert_eq!(
+            bank.process_bundle(&bundle).unwrap_err(),
+            BundleError::TransactionFailed {
+                index: 1,
+                err: TransactionError::InstructionError(0, InstructionError::Custom(0)),
+            }
+        );
+        assert_eq!(bank.write_demand().demand(&hot_account), 0);
+
+        // Within a bundle, later transactions pay for the earlier ones.
+        let bundle = Bundle::new(vec![
+            transaction(&mut bank, program_id, hot_account, &[]),
+            transaction(&mut bank, program_id, hot_account, &[]),
+        ])
+        .unwrap();
+        let results = bank.process_bundle(&bundle).unwrap();
+        assert_eq!(results[0].fee_details.local_fee, 0);
+        assert_eq!(results[1].fee_details.local_fee, 10);
+        assert_eq!(bank.write_demand().demand(&hot_account), 2);
+    }
+}
@@ -0,0 +1,144 @@
+//! Unit test: Derive account locks from transactions with `LockSet`
+//!
+//! Analogy: Each reservation slip lists which tables a party will sit at
+//! (write) and which ones it only walks past (read). `LockSet` reads that
+//! slip, including the extra tables listed on a separate address book page
+//! (address lookup tables). Some tables (programs, sysvars) are never handed
+//! out for exclusive use, even if the slip asks for it.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::accounts::{LockSet, LockSetConfig};
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::{
+        v0::{self, LoadedAddresses},
+        AddressLookupTableAccount, Message, SimpleAddressLoader, VersionedMessage,
+    };
+    use solana_pubkey::Pubkey;
+    use solana_signature::Signature;
+    use solana_transaction::{
+        sanitized::{MessageHash, SanitizedTransaction},
+        versioned::VersionedTransaction,
+        Transaction,
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_lock_set_from_legacy_transaction() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let writable = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![
+                AccountMeta::new(writable, false),
+                AccountMeta::new_readonly(readonly, false),
+            ],
+        );
+        let message = Message::new(&[instruction], Some(&payer));
+        let transaction =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+
+        // The fee payer is always writable and the invoked program is read-only.
+        // Message compilation orders keys within each category, so compare
+        // sorted lists.
+        let lock_set = LockSet::from_transaction(&transaction, LockSetConfig::default());
+        let sorted = |keys: &[Pubkey]| {
+            let mut keys = keys.to_vec();
+            keys.sort();
+            keys
+        };
+        assert_eq!(lock_set.writable()[0], payer);
+        assert_eq!(sorted(lock_set.writable()), sorted(&[payer, writable]));
+        assert_eq!(sorted(lock_set.readonly()), sorted(&[readonly, program_id]));
+    }
+
+    #[test]
+    fn test_lock_set_includes_lookup_table_addresses() {
+        let payer = Pubkey::new_unique();
+        let table_writable = Pubkey::new_unique();
+        let table_readonly = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![
+                AccountMeta::new(table_writable, false),
+                AccountMeta::new_readonly(table_readonly, false),
+            ],
+        );
+        let lookup_table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![table_writable, table_readonly],
+        };
+        let message =
+            v0::Message::try_compile(&payer, &[instruction], &[lookup_table], Hash::default())
+                .unwrap();
+        let transaction = SanitizedTransaction::try_create(
+            VersionedTransaction {
+                signatures: vec![Signature::default()],
+                message: VersionedMessage::V0(message),
+            },
+            MessageHash::Compute,
+            Some(false),
+            SimpleAddressLoader::Enabled(LoadedAddresses {
+                writable: vec![table_writable],
+                readonly: vec![table_readonly],
+            }),
+            &HashSet::new(),
+        )
+        .unwrap();
+
+        let lock_set = LockSet::from_transaction(&transaction, LockSetConfig::default());
+        assert!(lock_set.writable().contains(&table_writable));
+        assert!(lock_set.readonly().contains(&table_readonly));
+    }
+
+    #[test]
+    fn test_write_lock_demotion() {
+        // The instruction asks to write both the program it invokes and the
+        // clock sysvar; neither may be write locked on mainnet.
+        let program_id = Pubkey::new_unique();
+        let clock = solana_sdk_ids::sysvar::clock::id();
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![
+                AccountMeta::new(program_id, false),
+                AccountMeta::new(clock, false),
+            ],
+        );
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        let transaction =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+
+        let demoted = LockSet::from_transaction(&transaction, LockSetConfig::default());
+        assert!(demoted.readonly().contains(&program_id));
+        assert!(demoted.readonly().contains(&clock));
+
+        let requested = LockSet::from_transaction(
+            &transaction,
+            LockSetConfig {
+                demote_program_write_locks: false,
+            },
+        );
+        assert!(requested.writable().contains(&program_id));
+        assert!(requested.writable().contains(&clock));
+    }
+
+    #[test]
+    fn test_lock_set_conflicts() {
+        let account = Pubkey::new_unique();
+        let reader = LockSet::new(vec![], vec![account]);
+        let writer = LockSet::new(vec![account], vec![]);
+
+        // Readers share, a writer excludes everyone else.
+        assert!(!reader.conflicts_with(&reader.clone()));
+        assert!(reader.conflicts_with(&writer));
+        assert!(writer.conflicts_with(&reader));
+        assert!(writer.conflicts_with(&writer.clone()));
+        assert!(!writer.conflicts_with(&LockSet::new(vec![Pubkey::new_unique()], vec![])));
+    }
+}
@@ -0,0 +1,116 @@
+//! Unit test: Collect program logs with a byte limit
+//!
+//! Analogy: Every station scribbles on the ticket as it works: who picked
+//! it up, how many hands it passed through, what they noted and whether
+//! the dish came out. The ticket only has so much room, so once it is full
+//! the next cook writes "continued elsewhere" and the rest is lost.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::svm::{InvokeContext, LogCollector, TransactionExecutor};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::collections::HashMap;
+
+    fn transaction(instruction: Instruction) -> SanitizedTransaction {
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[instruction], Some(&payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    // Logs a greeting, then invokes the program of its only account, or
+    // fails if it has none.
+    fn greet_and_invoke(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        invoke_context.log("hello");
+        let callee = *invoke_context
+            .get_key(0)
+            .map_err(|_| InstructionError::Custom(1))?;
+        invoke_context.invoke(&Instruction::new_with_bytes(callee, &[], vec![]))
+    }
+
+    #[test]
+    fn test_log_truncation() {
+        let mut log_collector = LogCollector::new_with_limit(Some(20));
+        log_collector.log("0123456789");
+        log_collector.log("0123456789");
+        log_collector.log("0123456789");
+        // Anything that still fits is kept.
+        log_collector.log("012");
+        assert_eq!(
+            log_collector.get_recorded_content(),
+            ["0123456789", "Log truncated", "012"]
+        );
+
+        let mut unlimited = LogCollector::new_with_limit(None);
+        for _ in 0..100 {
+            unlimited.log("0123456789");
+        }
+        assert_eq!(unlimited.into_messages().len(), 100);
+    }
+
+    #[test]
+    fn test_invocation_logs_are_annotated_with_depth() {
+        let (caller, callee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(caller, greet_and_invoke);
+        executor.add_program(callee, |invoke_context: &mut InvokeContext| {
+            invoke_context.log_data(&[b"ab", b"c"]);
+            Ok(())
+        });
+        let store = HashMap::new();
+
+        let instruction = Instruction::new_with_bytes(
+            caller,
+            &[],
+            vec![AccountMeta::new_readonly(callee, false)],
+        );
+        let result = executor.load_and_execute_transaction(&store, &transaction(instruction));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(
+            result.log_messages,
+            [
+                format!("Program {caller} invoke [1]"),
+                "Program log: hello".to_owned(),
+                format!("Program {callee} invoke [2]"),
+                "Program data: YWI= Yw==".to_owned(),
+                format!("Program {callee} success"),
+                format!("Program {caller} success"),
+            ]
+        );
+
+        let failing = Instruction::new_with_bytes(caller, &[], vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(failing));
+        assert_eq!(
+            result.log_messages.last().unwrap(),
+            &format!("Program {caller} failed: custom program error: 0x1")
+        );
+    }
+
+    #[test]
+    fn test_executor_log_messages_bytes_limit() {
+        let program_id = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(program_id, |invoke_context: &mut InvokeContext| {
+            for _ in 0..100 {
+                invoke_context.log("0123456789");
+            }
+            Ok(())
+        });
+        let store = HashMap::new();
+        let instruction = Instruction::new_with_bytes(program_id, &[], vec![]);
+
+        executor.set_log_messages_bytes_limit(Some(100));
+        let result =
+            executor.load_and_execute_transaction(&store, &transaction(instruction.clone()));
+        assert!(result.log_messages.len() < 10);
+        assert!(result.log_messages.contains(&"Log truncated".to_owned()));
+
+        executor.set_log_messages_bytes_limit(None);
+        let result = executor.load_and_execute_transaction(&store, &transaction(instruction));
+        // 100 logs between the invoke and success lines.
+        assert_eq!(result.log_messages.len(), 102);
+    }
+}
@@ -0,0 +1,77 @@
+//! Unit test: Limit the priority graph to a look-ahead window
+//!
+//! Analogy: The host only studies the first few parties in the queue when
+//! planning who sits where. If all of them want the window table, the
+//! quiet couple further back, who would take any table, waits too; once a
+//! party is seated the host looks one party further down the queue.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{BatchLimits, PriorityGraphScheduler, TransactionScheduler},
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(writable: Pubkey, cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(writable, false)],
+            ),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    /// Three transactions on a hot account, then one that conflicts with
+    /// nothing but pays the least.
+    fn push_workload(scheduler: &mut PriorityGraphScheduler) -> [usize; 4] {
+        let hot_account = Pubkey::new_unique();
+        let hot = [3, 2, 1].map(|cu_price| scheduler.push(transaction(hot_account, cu_price)));
+        let free = scheduler.push(transaction(Pubkey::new_unique(), 0));
+        [hot[0], hot[1], hot[2], free]
+    }
+
+    #[test]
+    fn test_window_hides_transactions_further_down() {
+        let mut unbounded = PriorityGraphScheduler::new();
+        assert_eq!(unbounded.look_ahead_window(), None);
+        let [first, ..] = push_workload(&mut unbounded);
+        let free = 3;
+        assert_eq!(
+            unbounded.next_batch(&BatchLimits::default()).ids,
+            [first, free]
+        );
+
+        let mut scheduler = PriorityGraphScheduler::new().with_look_ahead_window(2);
+        assert_eq!(scheduler.look_ahead_window(), Some(2));
+        let [first, second, third, free] = push_workload(&mut scheduler);
+        // Handing out `first` lets `third` into the window, where it is
+        // blocked like `second`; `free` stays outside.
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, [first]);
+        let checkpoint = scheduler.checkpoint();
+        assert_eq!(checkpoint.pending, [free]);
+        assert_eq!(checkpoint.blocked_behind(third), [second, first]);
+
+        // Handing out `second` makes room for `free` in the same batch.
+        scheduler.complete(&batch.ids);
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, [second, free]);
+        scheduler.complete(&batch.ids);
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, [third]);
+        assert_eq!(scheduler.num_pending(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "look-ahead window must hold a transaction")]
+    fn test_empty_window_is_rejected() {
+        let _ = PriorityGraphScheduler::new().with_look_ahead_window(0);
+    }
+}
@@ -0,0 +1,224 @@
+//! Unit test: Model the worker pool's handshakes under every interleaving
+//!
+//! Analogy: A drill for the kitchen rather than a real evening. The
+//! expediter hands out tickets, cooks take ingredients off the shelf, put
+//! them back and ring the bell, and the drill replays every order in which
+//! those steps could possibly happen. No ordering may leave two cooks
+//! holding the same jar, a ticket unanswered or a cook waiting forever
+//! after closing time.
+//!
+//! The models mirror the protocol of `WorkerPool` with loom's locks, since
+//! loom can only explore the primitives it provides, and a channel built on
+//! them that disconnects like the pool's crossbeam channels do. They only
+//! build with `--cfg loom`:
+//!
+//! ```bash
+//! RUSTFLAGS="--cfg loom" cargo test --release --test test_loom
+//! ```
+
+#[cfg(all(test, loom))]
+mod tests {
+    use loom::{
+        sync::{Arc, Condvar, Mutex},
+        thread,
+    };
+    use priority_graph_practice::accounts::{AccountLocks, LockSet};
+    use solana_pubkey::Pubkey;
+    use std::collections::VecDeque;
+
+    /// Explores every interleaving with at most `PREEMPTION_BOUND` forced
+    /// thread switches, which is where ordering bugs show up in practice
+    /// and keeps the channel models to seconds.
+    const PREEMPTION_BOUND: usize = 3;
+
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(PREEMPTION_BOUND);
+        builder.check(f);
+    }
+
+    struct Queue<T> {
+        messages: VecDeque<T>,
+        num_senders: usize,
+    }
+
+    struct Shared<T> {
+        queue: Mutex<Queue<T>>,
+        changed: Condvar,
+    }
+
+    /// An unbounded multi-consumer channel that disconnects once every
+    /// sender is gone; loom's own channel never disconnects.
+    fn channel<T>() -> (Sender<T>, Receiver<T>) {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                messages: VecDeque::new(),
+                num_senders: 1,
+            }),
+            changed: Condvar::new(),
+        });
+        (Sender(Arc::clone(&shared)), Receiver(shared))
+    }
+
+    struct Sender<T>(Arc<Shared<T>>);
+
+    impl<T> Sender<T> {
+        fn send(&self, message: T) {
+            self.0.queue.lock().unwrap().messages.push_back(message);
+            self.0.changed.notify_all();
+        }
+    }
+
+    impl<T> Clone for Sender<T> {
+        fn clone(&self) -> Self {
+            self.0.queue.lock().unwrap().num_senders += 1;
+            Self(Arc::clone(&self.0))
+        }
+    }
+
+    impl<T> Drop for Sender<T> {
+        fn drop(&mut self) {
+            self.0.queue.lock().unwrap().num_senders -= 1;
+            self.0.changed.notify_all();
+        }
+    }
+
+    #[derive(Clone)]
+    struct Receiver<T>(Arc<Shared<T>>);
+
+    impl<T> Receiver<T> {
+        /// The next message, or `None` once the channel is empty and
+        /// disconnected.
+        fn recv(&self) -> Option<T> {
+            let mut queue = self.0.queue.lock().unwrap();
+            loop {
+                if let Some(message) = queue.messages.pop_front() {
+                    return Some(message);
+                }
+                if queue.num_senders == 0 {
+                    return None;
+                }
+                queue = self.0.changed.wait(queue).unwrap();
+            }
+        }
+
+        fn try_recv(&self) -> Option<T> {
+            self.0.queue.lock().unwrap().messages.pop_front()
+        }
+    }
+
+    /// A worker of the pool: takes work off the shared channel until it
+    /// disconnects and reports each piece back once executed.
+    fn spawn_worker<T: Send + 'static>(
+        work_receiver: Receiver<T>,
+        finished_sender: Sender<T>,
+        execute: impl Fn(&T) + Send + 'static,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            while let Some(work) = work_receiver.recv() {
+                execute(&work);
+                finished_sender.send(work);
+            }
+        })
+    }
+
+    #[test]
+    fn test_every_handed_out_work_completes_once() {
+        model(|| {
+            let (work_sender, work_receiver) = channel();
+            let (finished_sender, finished_receiver) = channel();
+            let workers: Vec<_> = (0..2)
+                .map(|_| spawn_worker(work_receiver.clone(), finished_sender.clone(), |_| {}))
+                .collect();
+            drop(finished_sender);
+
+            for id in 0..2 {
+                work_sender.send(id);
+            }
+            let mut finished: Vec<_> = (0..2).map(|_| finished_receiver.recv().unwrap()).collect();
+            finished.sort_unstable();
+            assert_eq!(finished, vec![0, 1]);
+
+            // Closing the work channel ends every worker, however far along
+            // it is.
+            drop(work_sender);
+            for worker in workers {
+                worker.join().unwrap();
+            }
+            assert!(finished_receiver.try_recv().is_none());
+        });
+    }
+
+    #[test]
+    fn test_completion_releases_locks_before_conflicting_work_starts() {
+        model(|| {
+            let account = Pubkey::new_unique();
+            let lock_set = LockSet::new(vec![account], Vec::new());
+            let account_locks = Arc::new(Mutex::new(AccountLocks::new()));
+            let (work_sender, work_receiver) = channel::<usize>();
+            let (finished_sender, finished_receiver) = channel();
+            let workers: Vec<_> = (0..2)
+                .map(|_| {
+                    let account_locks = Arc::clone(&account_locks);
+                    let lock_set = lock_set.clone();
+                    // Unlocks before reporting back, as executing a batch
+                    // does, so the scheduler never hands out work whose
+                    // accounts are still held.
+                    spawn_worker(work_receiver.clone(), finished_sender.clone(), move |_| {
+                        account_locks.lock().unwrap().try_lock(&lock_set).unwrap();
+                        account_locks.lock().unwrap().unlock(&lock_set);
+                    })
+                })
+                .collect();
+            drop(finished_sender);
+
+            // Both pieces of work write `account`, so the second one is only
+            // handed out once the first one completed.
+            for id in 0..2 {
+                work_sender.send(id);
+                assert_eq!(finished_receiver.recv().unwrap(), id);
+            }
+            drop(work_sender);
+            for worker in workers {
+                worker.join().unwrap();
+            }
+            assert!(account_locks.lock().unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_lock_table_admits_one_writer_or_many_readers() {
+        model(|| {
+            let account = Pubkey::new_unique();
+            let write = LockSet::new(vec![account], Vec::new());
+            let read = LockSet::new(Vec::new(), vec![account]);
+            let account_locks = Arc::new(Mutex::new(AccountLocks::new()));
+            let handles: Vec<_> = [write.clone(), write, read]
+                .into_iter()
+                .map(|lock_set| {
+                    let account_locks = Arc::clone(&account_locks);
+                    thread::spawn(move || {
+                        let locked = account_locks.lock().unwrap().try_lock(&lock_set).is_ok();
+                        if locked {
+                            let locks = account_locks.lock().unwrap();
+                            // Nobody else writes while this lock is held.
+                            assert!(
+                                locks.is_write_locked(&account) != locks.is_read_locked(&account)
+                            );
+                            drop(locks);
+                            account_locks.lock().unwrap().unlock(&lock_set);
+                        }
+                        locked
+                    })
+                })
+                .collect();
+            let locked: Vec<_> = handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect();
+            // The first taker always gets its lock.
+            assert!(locked.contains(&true));
+            assert!(account_locks.lock().unwrap().is_empty());
+        });
+    }
+}
@@ -0,0 +1,123 @@
+//! Unit test: Stream transactions through the multi-iterator scheduler
+//!
+//! Analogy: Before the seating chart, the host walked down the queue
+//! seating every party whose table was free and skipping the rest, then
+//! carried on from where they stopped once tables freed up. A party that
+//! was skipped could watch a smaller tip take the table they were waiting
+//! for, and a big tipper arriving behind the host's back waited for the
+//! next round.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            BatchLimits, MultiIteratorScheduler, PriorityGraphScheduler, TransactionScheduler,
+        },
+        workload::{Workload, WorkloadConfig},
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::collections::HashSet;
+
+    fn transaction(writable: &[Pubkey], cu_price: u64) -> SanitizedTransaction {
+        let accounts = writable
+            .iter()
+            .map(|key| AccountMeta::new(*key, false))
+            .collect();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    const ONE: BatchLimits = BatchLimits {
+        max_txs: 1,
+        max_cus: u64::MAX,
+        max_account_data_size: u64::MAX,
+    };
+
+    /// Hands out a transaction on `x`, then queues one on `x` and `y` and a
+    /// cheaper one on `y`, returning the ids of the three.
+    fn push_overtaking_workload(scheduler: &mut impl TransactionScheduler) -> [usize; 3] {
+        let [x, y] = [(); 2].map(|()| Pubkey::new_unique());
+        let running = scheduler.push(transaction(&[x], 5));
+        assert_eq!(scheduler.next_batch(&ONE).ids, [running]);
+        let both = scheduler.push(transaction(&[x, y], 3));
+        let cheaper = scheduler.push(transaction(&[y], 1));
+        [running, both, cheaper]
+    }
+
+    #[test]
+    fn test_skipped_transactions_are_overtaken() {
+        let mut scheduler = MultiIteratorScheduler::new();
+        let [running, both, cheaper] = push_overtaking_workload(&mut scheduler);
+        // `both` waits on `x`, so the scan skips it and `cheaper` takes `y`.
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, [cheaper]);
+        scheduler.complete(&[running, cheaper]);
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, [both]);
+
+        // The priority graph queues `cheaper` behind `both` instead.
+        let mut scheduler = PriorityGraphScheduler::new();
+        let [running, both, cheaper] = push_overtaking_workload(&mut scheduler);
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        scheduler.complete(&[running]);
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, [both]);
+        scheduler.complete(&[both]);
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, [cheaper]);
+    }
+
+    #[test]
+    fn test_scan_resumes_where_it_stopped() {
+        let mut scheduler = MultiIteratorScheduler::new();
+        let first = scheduler.push(transaction(&[Pubkey::new_unique()], 3));
+        let second = scheduler.push(transaction(&[Pubkey::new_unique()], 2));
+        assert_eq!(scheduler.next_batch(&ONE).ids, [first]);
+
+        // `late` ranks above where the scan stopped, so it waits for the
+        // scan to wrap around.
+        let late = scheduler.push(transaction(&[Pubkey::new_unique()], 9));
+        assert_eq!(scheduler.next_batch(&ONE).ids, [second]);
+        assert_eq!(scheduler.next_batch(&ONE).ids, [late]);
+        assert_eq!(scheduler.num_pending(), 0);
+        assert!(scheduler.next_batch(&ONE).is_empty());
+    }
+
+    #[test]
+    fn test_every_transaction_is_handed_out_once() {
+        let workload = Workload::generate(&WorkloadConfig {
+            num_transactions: 500,
+            hot_account_ratio: 0.3,
+            ..WorkloadConfig::default()
+        });
+        let mut scheduler = MultiIteratorScheduler::new();
+        for transaction in &workload.transactions {
+            scheduler.push(transaction.clone());
+        }
+        let limits = BatchLimits {
+            max_txs: 16,
+            ..BatchLimits::default()
+        };
+        let mut handed_out = HashSet::new();
+        while scheduler.num_pending() > 0 {
+            let batch = scheduler.next_batch(&limits);
+            assert!(!batch.is_empty());
+            let mut written = HashSet::new();
+            for (id, transaction) in batch.ids.iter().zip(&batch.transactions) {
+                assert!(handed_out.insert(*id));
+                let message = transaction.message();
+                for (index, key) in message.account_keys().iter().enumerate() {
+                    if message.is_writable(index) {
+                        assert!(written.insert(*key), "batch writes {key} twice");
+                    }
+                }
+            }
+            scheduler.complete(&batch.ids);
+        }
+        assert_eq!(handed_out.len(), workload.transactions.len());
+    }
+}
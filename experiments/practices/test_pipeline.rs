@@ -0,0 +1,186 @@
+//! Unit test: Process transactions through a pipeline of stages
+//!
+//! Analogy: Orders pass from the door to the cashier, the host, the
+//! kitchen and the pass, each with a short counter in front of them. When
+//! the pass is slow the counter before it fills up, the kitchen waits to
+//! put plates down and soon the door holds guests back. The manager, timing
+//! who is busy and who is waiting, sees straight away that the pass is
+//! where the evening gets stuck, and can put someone else there without
+//! retraining the kitchen.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts_storage::ShardedStorage,
+        pipeline::{
+            ExecutedBatch, Pipeline, PipelineAccounts, PipelineConfig, ScheduleStage,
+            SigVerifyStage,
+        },
+        scheduler::GreedyScheduler,
+        sigverify::SigVerifyConfig,
+        svm::TransactionExecutor,
+        system_program,
+        workload::{Workload, WorkloadConfig},
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_signature::Signature;
+    use solana_signer::Signer;
+    use solana_transaction::{versioned::VersionedTransaction, Transaction};
+    use std::{sync::Arc, thread, time::Duration};
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn funded_accounts(pubkeys: &[Pubkey]) -> Arc<PipelineAccounts> {
+        let storage = ShardedStorage::default();
+        storage.commit(pubkeys.iter().map(|pubkey| {
+            (
+                *pubkey,
+                AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+            )
+        }));
+        Arc::new(PipelineAccounts::new(storage))
+    }
+
+    fn lamports(accounts: &PipelineAccounts, pubkey: &Pubkey) -> u64 {
+        accounts
+            .storage()
+            .load(pubkey)
+            .map_or(0, |account| account.lamports())
+    }
+
+    fn unsigned(workload: &Workload) -> Vec<VersionedTransaction> {
+        workload
+            .transactions
+            .iter()
+            .map(|transaction| transaction.to_versioned_transaction())
+            .collect()
+    }
+
+    #[test]
+    fn test_pipeline_processes_signed_transactions() {
+        let payers = [Keypair::new(), Keypair::new()];
+        let recipient = Pubkey::new_unique();
+        let accounts = funded_accounts(&payers.each_ref().map(|payer| payer.pubkey()));
+        let transfer = |payer: &Keypair, lamports| {
+            let instruction = system_program::transfer(&payer.pubkey(), &recipient, lamports);
+            let message = Message::new(&[instruction], Some(&payer.pubkey()));
+            VersionedTransaction::from(Transaction::new(&[payer], message, Hash::new_unique()))
+        };
+        let mut forged = transfer(&payers[0], 7);
+        forged.signatures[0] = Signature::from([7; 64]);
+        let mut malformed = transfer(&payers[1], 9);
+        malformed.signatures.clear();
+
+        let pipeline = Pipeline::new(Arc::new(TransactionExecutor::new()), Arc::clone(&accounts));
+        let (committed, metrics) = pipeline.run([
+            vec![transfer(&payers[0], 1_000_000), forged],
+            vec![malformed, transfer(&payers[1], 2_000_000)],
+        ]);
+
+        let results: Vec<_> = committed
+            .iter()
+            .flat_map(|batch: &ExecutedBatch| &batch.results)
+            .collect();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.was_successful()));
+        assert_eq!(lamports(&accounts, &recipient), 3_000_000);
+        assert_eq!(accounts.committed_batches(), committed.len() as u64);
+
+        let names: Vec<_> = metrics.stages.iter().map(|stage| stage.name).collect();
+        assert_eq!(
+            names,
+            ["ingest", "sigverify", "schedule", "execute", "commit"]
+        );
+        let ingest = metrics.stage("ingest").unwrap();
+        assert_eq!((ingest.inputs, ingest.transactions_in), (2, 4));
+        assert_eq!(ingest.transactions_out, 3);
+        assert_eq!(metrics.stage("sigverify").unwrap().transactions_out, 2);
+        assert_eq!(metrics.stage("commit").unwrap().transactions_out, 2);
+    }
+
+    #[test]
+    fn test_stages_can_be_swapped() {
+        let workload = Workload::generate(&WorkloadConfig {
+            num_transactions: 200,
+            num_payers: 20,
+            hot_account_ratio: 0.5,
+            ..WorkloadConfig::default()
+        });
+        let accounts = funded_accounts(&workload.payers);
+        // The workload is unsigned, so signatures go unchecked, and a
+        // different scheduler packs the batches.
+        let skip = SigVerifyConfig {
+            skip_sigverify: true,
+            ..SigVerifyConfig::default()
+        };
+        let (committed, _) =
+            Pipeline::new(Arc::new(TransactionExecutor::new()), Arc::clone(&accounts))
+                .with_sigverify(SigVerifyStage::new(skip))
+                .with_schedule(ScheduleStage::new(GreedyScheduler::new()))
+                .run(unsigned(&workload).chunks(50).map(<[_]>::to_vec));
+        let executed: usize = committed.iter().map(|batch| batch.results.len()).sum();
+        assert_eq!(executed, 200);
+        assert!(committed
+            .iter()
+            .flat_map(|batch| &batch.results)
+            .all(|result| result.was_successful()));
+    }
+
+    #[test]
+    fn test_slow_commit_is_the_bottleneck_and_pushes_back() {
+        // Every transfer pays the one hot account, so every batch reads
+        // what the batch before it wrote, committed or not.
+        let workload = Workload::generate(&WorkloadConfig {
+            num_transactions: 60,
+            num_payers: 60,
+            hot_account_ratio: 1.0,
+            ..WorkloadConfig::default()
+        });
+        let accounts = funded_accounts(&workload.payers);
+        let commit_accounts = Arc::clone(&accounts);
+        let slow_commit = move |batch: ExecutedBatch| {
+            thread::sleep(Duration::from_millis(5));
+            commit_accounts.commit_batch(&batch);
+            batch
+        };
+        let config = PipelineConfig {
+            ingest_capacity: 1,
+            sigverify_capacity: 1,
+            schedule_capacity: 1,
+            schedule_batch_size: 1,
+            execute_capacity: 1,
+            commit_capacity: 1,
+            ..PipelineConfig::default()
+        };
+        let skip = SigVerifyStage::new(SigVerifyConfig {
+            skip_sigverify: true,
+            ..SigVerifyConfig::default()
+        });
+        let (committed, metrics) =
+            Pipeline::new(Arc::new(TransactionExecutor::new()), Arc::clone(&accounts))
+                .with_config(config)
+                .with_sigverify(skip)
+                .with_commit(slow_commit)
+                .run(unsigned(&workload).chunks(1).map(<[_]>::to_vec));
+
+        assert_eq!(committed.len(), 60);
+        assert!(committed
+            .iter()
+            .all(|batch| batch.results[0].was_successful()));
+        assert_eq!(
+            lamports(&accounts, &workload.hot_accounts[0]),
+            60 * Workload::transfer_lamports()
+        );
+
+        assert_eq!(metrics.bottleneck().unwrap().name, "commit");
+        let execute = metrics.stage("execute").unwrap();
+        assert!(execute.blocked > metrics.stage("commit").unwrap().blocked);
+        assert!(execute.blocked > execute.busy);
+        assert!(metrics.producer_blocked > Duration::ZERO);
+        assert_eq!(metrics.stage("commit").unwrap().max_queue_len, 1);
+    }
+}
@@ -0,0 +1,190 @@
+//! Unit test: Follow a bank through an `AccountsNotifier`
+//!
+//! Analogy: The restaurant lets a food critic sit in the kitchen. The
+//! critic never touches a pan, but hears every plate that leaves the pass,
+//! every order that was cooked, even the ones sent back, and every time a
+//! sitting opens, closes or goes into the books for good.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::plugin::{AccountsNotifier, SlotStatus};
+    use priority_graph_practice::system_program;
+    use priority_graph_practice::transaction_status::TransactionExecutionDetails;
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_clock::Slot;
+    use solana_hash::Hash;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::sync::{Arc, Mutex};
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    #[derive(Debug, PartialEq)]
+    enum Notification {
+        Account(Slot, Pubkey, u64),
+        Transaction(Slot, bool),
+        Slot(Slot, SlotStatus),
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        notifications: Mutex<Vec<Notification>>,
+    }
+
+    impl Recorder {
+        fn take(&self) -> Vec<Notification> {
+            std::mem::take(&mut self.notifications.lock().unwrap())
+        }
+    }
+
+    impl AccountsNotifier for Recorder {
+        fn notify_account_update(
+            &self,
+            slot: Slot,
+            pubkey: &Pubkey,
+            account: &AccountSharedData,
+            _transaction: &SanitizedTransaction,
+        ) {
+            self.notifications
+                .lock()
+                .unwrap()
+                .push(Notification::Account(slot, *pubkey, account.lamports()));
+        }
+
+        fn notify_transaction(
+            &self,
+            slot: Slot,
+            _transaction: &SanitizedTransaction,
+            details: &TransactionExecutionDetails,
+        ) {
+            self.notifications
+                .lock()
+                .unwrap()
+                .push(Notification::Transaction(slot, details.status.is_ok()));
+        }
+
+        fn notify_slot_status(&self, slot: Slot, status: SlotStatus) {
+            self.notifications
+                .lock()
+                .unwrap()
+                .push(Notification::Slot(slot, status));
+        }
+    }
+
+    // Only hears about slots.
+    #[derive(Default)]
+    struct SlotCounter {
+        slots: Mutex<usize>,
+    }
+
+    impl AccountsNotifier for SlotCounter {
+        fn notify_slot_status(&self, _slot: Slot, _status: SlotStatus) {
+            *self.slots.lock().unwrap() += 1;
+        }
+    }
+
+    fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let message = Message::new(&[system_program::transfer(from, to, lamports)], Some(from));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn funded_bank(payer: &Pubkey) -> Bank {
+        let mut bank = Bank::default();
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &system_program_id::id()),
+        );
+        bank
+    }
+
+    #[test]
+    fn test_commit_notifies_accounts_and_transactions() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut bank = funded_bank(&alice);
+        let recorder = Arc::new(Recorder::default());
+        bank.add_notifier(recorder.clone());
+
+        bank.process_transaction_batch(&[transfer(&alice, &bob, 1_000_000)]);
+        assert_eq!(
+            recorder.take(),
+            vec![
+                Notification::Account(0, alice, LAMPORTS - 1_000_000 - 5_000),
+                Notification::Account(0, bob, 1_000_000),
+                Notification::Transaction(0, true),
+            ]
+        );
+
+        // A failed transfer only writes the fee its payer was charged.
+        let results = bank.process_transaction_batch(&[transfer(&alice, &bob, 2 * LAMPORTS)]);
+        assert!(!results[0].as_ref().unwrap().was_successful());
+        assert_eq!(
+            recorder.take(),
+            vec![
+                Notification::Account(0, alice, LAMPORTS - 1_000_000 - 10_000),
+                Notification::Transaction(0, false),
+            ]
+        );
+
+        // Rejected transactions never reach the notifiers.
+        bank.process_transaction_batch(&[transfer(&Pubkey::new_unique(), &bob, 1_000_000)]);
+        assert_eq!(recorder.take(), vec![]);
+    }
+
+    #[test]
+    fn test_slot_statuses_are_notified() {
+        let mut bank = Bank::default();
+        let recorder = Arc::new(Recorder::default());
+        bank.add_notifier(recorder.clone());
+
+        bank.freeze();
+        bank.freeze();
+        bank.advance_slot(Hash::new_unique());
+        bank.advance_slot(Hash::new_unique());
+        bank.set_root();
+        bank.set_root();
+        assert_eq!(
+            recorder.take(),
+            vec![
+                Notification::Slot(0, SlotStatus::Frozen),
+                Notification::Slot(1, SlotStatus::Created),
+                Notification::Slot(1, SlotStatus::Frozen),
+                Notification::Slot(2, SlotStatus::Created),
+                Notification::Slot(0, SlotStatus::Rooted),
+                Notification::Slot(1, SlotStatus::Rooted),
+                Notification::Slot(2, SlotStatus::Rooted),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_children_notify_their_parents_notifiers() {
+        let alice = Pubkey::new_unique();
+        let mut parent = funded_bank(&alice);
+        let recorder = Arc::new(Recorder::default());
+        let counter = Arc::new(SlotCounter::default());
+        parent.add_notifier(recorder.clone());
+        parent.add_notifier(counter.clone());
+
+        parent.freeze();
+        let mut child = Bank::new_from_parent(&parent, 3, Hash::new_unique());
+        let bob = Pubkey::new_unique();
+        child.process_transaction_batch(&[transfer(&alice, &bob, 1_000_000)]);
+        let notifications = recorder.take();
+        assert_eq!(
+            notifications[..2],
+            [
+                Notification::Slot(0, SlotStatus::Frozen),
+                Notification::Slot(3, SlotStatus::Created),
+            ]
+        );
+        assert_eq!(
+            notifications.last(),
+            Some(&Notification::Transaction(3, true))
+        );
+        assert_eq!(*counter.slots.lock().unwrap(), 2);
+    }
+}
@@ -0,0 +1,169 @@
+//! Unit test: Verify ed25519, secp256k1 and secp256r1 precompile instructions
+//!
+//! Analogy: Some orders arrive with letters of recommendation stapled to
+//! them. The doorman reads every letter before the order goes in, checking
+//! each signature against the name it claims, and turns away orders with a
+//! forged letter. Once inside, the letters need no cooking at all.
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer as _;
+    use priority_graph_practice::precompiles::{
+        construct_eth_address, new_ed25519_instruction, new_secp256k1_instruction,
+        new_secp256r1_instruction, verify_precompiles, PrecompileConfig, PrecompileError,
+    };
+    use priority_graph_practice::sigverify::{verify_transactions, SigVerifyConfig};
+    use priority_graph_practice::svm::TransactionExecutor;
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_signer::Signer;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    fn ed25519_instruction(message: &[u8]) -> Instruction {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let signature = key.sign(message).to_bytes();
+        new_ed25519_instruction(&key.verifying_key().to_bytes(), &signature, message)
+    }
+
+    fn secp256k1_instruction(message: &[u8], instruction_index: u8) -> Instruction {
+        let key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let eth_address = construct_eth_address(&libsecp256k1::PublicKey::from_secret_key(&key));
+        let message_hash = solana_keccak_hasher::hash(message).to_bytes();
+        let (signature, recovery_id) =
+            libsecp256k1::sign(&libsecp256k1::Message::parse(&message_hash), &key);
+        new_secp256k1_instruction(
+            &eth_address,
+            &signature.serialize(),
+            recovery_id.serialize(),
+            message,
+            instruction_index,
+        )
+    }
+
+    fn secp256r1_instruction(message: &[u8]) -> Instruction {
+        let key = p256::ecdsa::SigningKey::from_bytes(&[7; 32].into()).unwrap();
+        let signature: p256::ecdsa::Signature = key.sign(message);
+        let signature = signature.normalize_s().unwrap_or(signature);
+        let public_key = key.verifying_key().to_encoded_point(true);
+        new_secp256r1_instruction(
+            public_key.as_bytes().try_into().unwrap(),
+            &signature.to_bytes().into(),
+            message,
+        )
+    }
+
+    fn sanitized(instructions: &[Instruction]) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn verify(
+        instructions: &[Instruction],
+        config: &PrecompileConfig,
+    ) -> Result<(), TransactionError> {
+        verify_precompiles(sanitized(instructions).message(), config)
+    }
+
+    fn failure(index: u8, err: PrecompileError) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(err as u32),
+        ))
+    }
+
+    #[test]
+    fn test_verify_each_precompile() {
+        let config = PrecompileConfig::default();
+        assert_eq!(verify(&[ed25519_instruction(b"hello")], &config), Ok(()));
+        assert_eq!(verify(&[secp256r1_instruction(b"hello")], &config), Ok(()));
+        // The secp256k1 offsets name the instruction they point into.
+        let memo = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+        let instructions = [memo, secp256k1_instruction(b"hello", 1)];
+        assert_eq!(verify(&instructions, &config), Ok(()));
+
+        // Changing the signed message fails the instruction it belongs to.
+        let mut tampered = ed25519_instruction(b"hello");
+        *tampered.data.last_mut().unwrap() = b'!';
+        assert_eq!(
+            verify(&[ed25519_instruction(b"hi"), tampered], &config),
+            failure(1, PrecompileError::InvalidSignature)
+        );
+        let mut tampered = secp256k1_instruction(b"hello", 0);
+        *tampered.data.last_mut().unwrap() = b'!';
+        assert_eq!(
+            verify(&[tampered], &config),
+            failure(0, PrecompileError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_malformed_instructions_and_config() {
+        let config = PrecompileConfig::default();
+        let mut truncated = ed25519_instruction(b"hello");
+        truncated.data.truncate(10);
+        assert_eq!(
+            verify(&[truncated], &config),
+            failure(0, PrecompileError::InvalidInstructionDataSize)
+        );
+        // The message runs past the end of the instruction.
+        let mut short = secp256r1_instruction(b"hello");
+        short.data.pop();
+        assert_eq!(
+            verify(&[short.clone()], &config),
+            failure(0, PrecompileError::InvalidDataOffsets)
+        );
+
+        // Without secp256r1 its program is an ordinary one.
+        let disabled = PrecompileConfig {
+            enable_secp256r1: false,
+            ..PrecompileConfig::default()
+        };
+        assert_eq!(verify(&[short], &disabled), Ok(()));
+        // A non-strict ed25519 verification still accepts valid signatures.
+        let lenient = PrecompileConfig {
+            ed25519_verify_strict: false,
+            ..PrecompileConfig::default()
+        };
+        assert_eq!(verify(&[ed25519_instruction(b"hello")], &lenient), Ok(()));
+    }
+
+    #[test]
+    fn test_sigverify_stage_and_execution() {
+        let payer = Keypair::new();
+        let signed = |instruction: Instruction| {
+            let message = Message::new(&[instruction], Some(&payer.pubkey()));
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+                &[&payer],
+                message,
+                Hash::new_unique(),
+            ))
+        };
+        let valid = signed(ed25519_instruction(b"hello"));
+        let mut forged = ed25519_instruction(b"hello");
+        *forged.data.last_mut().unwrap() = b'!';
+        let transactions = vec![valid.clone(), signed(forged)];
+
+        let config = SigVerifyConfig::default();
+        assert_eq!(
+            verify_transactions(&transactions, &config),
+            vec![true, false]
+        );
+        let skip = SigVerifyConfig {
+            skip_sigverify: true,
+            ..SigVerifyConfig::default()
+        };
+        assert_eq!(verify_transactions(&transactions, &skip), vec![true, true]);
+
+        // Executing a verified precompile instruction does nothing.
+        let result =
+            TransactionExecutor::new().load_and_execute_transaction(&HashMap::new(), &valid);
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.consumed_units, 0);
+    }
+}
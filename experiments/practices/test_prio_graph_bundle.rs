@@ -0,0 +1,135 @@
+//! Unit test: Schedule bundles as single nodes of the priority graph
+//!
+//! Analogy: A set menu takes one table for the whole evening. Walk-ins who
+//! want that table wait until the last course is cleared, and the party's
+//! combined bill decides whether they are seated before a regular who
+//! would order alone.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            BatchLimits, Bundle, PriorityGraphScheduler, PriorityPolicy, TransactionScheduler,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(writable: Pubkey, cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(writable, false)],
+            ),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_bundle_is_serialized_against_conflicting_transactions() {
+        let x = Pubkey::new_unique();
+        let y = Pubkey::new_unique();
+        let mut scheduler = PriorityGraphScheduler::new();
+        let writes_x = scheduler.push(transaction(x, 100));
+        let writes_y = scheduler.push(transaction(y, 50));
+        let bundle =
+            scheduler.push_bundle(Bundle::new(vec![transaction(y, 1), transaction(x, 1)]).unwrap());
+        assert_eq!(scheduler.num_pending(), 4);
+
+        // The bundle conflicts with both and waits for both.
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, vec![writes_x, writes_y]);
+        assert!(batch.bundles.is_empty());
+        scheduler.complete(&[writes_x]);
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        scheduler.complete(&[writes_y]);
+
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, bundle);
+        assert_eq!(batch.bundles, vec![0..2]);
+        assert_eq!(scheduler.num_pending(), 0);
+        assert_eq!(scheduler.metrics().transactions_blocked, 2);
+
+        // Whatever queues behind the bundle waits for all of it.
+        let waiting = scheduler.push(transaction(y, 1_000));
+        scheduler.complete(&[bundle[1]]);
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        scheduler.complete(&[bundle[0]]);
+        assert_eq!(
+            scheduler.next_batch(&BatchLimits::default()).ids,
+            vec![waiting]
+        );
+    }
+
+    #[test]
+    fn test_bundle_ranks_by_what_its_transactions_pay_together() {
+        let x = Pubkey::new_unique();
+        let bundle = Bundle::new(vec![
+            transaction(x, 3),
+            transaction(Pubkey::new_unique(), 3),
+        ])
+        .unwrap();
+        let policy = PriorityPolicy::ComputeUnitPrice;
+        assert_eq!(policy.bundle_priority_id(&bundle, 0).priority, 3);
+
+        // Each member pays less than the singleton, both together more.
+        let mut scheduler = PriorityGraphScheduler::new();
+        let singleton = scheduler.push(transaction(x, 5));
+        let bundle_ids = scheduler.push_bundle(bundle.clone());
+        assert_eq!(
+            scheduler.effective_priority(bundle_ids[0]),
+            Some(
+                PriorityPolicy::PriorityFee
+                    .bundle_priority_id(&bundle, bundle_ids[0])
+                    .priority
+            )
+        );
+        assert_eq!(scheduler.effective_priority(bundle_ids[1]), None);
+
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, bundle_ids);
+        scheduler.complete(&batch.ids);
+        assert_eq!(
+            scheduler.next_batch(&BatchLimits::default()).ids,
+            vec![singleton]
+        );
+    }
+
+    #[test]
+    fn test_bundles_are_neither_retried_nor_forwarded() {
+        let mut scheduler = PriorityGraphScheduler::new();
+        let bundle = scheduler.push_bundle(
+            Bundle::new(vec![
+                transaction(Pubkey::new_unique(), 1),
+                transaction(Pubkey::new_unique(), 1),
+            ])
+            .unwrap(),
+        );
+        let singleton = scheduler.push(transaction(Pubkey::new_unique(), 1));
+
+        let forwarded = scheduler.take_for_forwarding(usize::MAX);
+        assert_eq!(
+            forwarded
+                .iter()
+                .map(|dropped| dropped.id)
+                .collect::<Vec<_>>(),
+            vec![singleton]
+        );
+        assert_eq!(scheduler.num_pending(), 2);
+
+        let mut batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, bundle);
+        let last = batch.transactions.pop().unwrap();
+        assert!(!scheduler.retry(bundle[1], last));
+        assert_eq!(scheduler.metrics().transactions_retried, 0);
+        scheduler.complete(&[bundle[0]]);
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        assert_eq!(scheduler.num_pending(), 0);
+    }
+}
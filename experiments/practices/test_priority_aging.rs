@@ -0,0 +1,128 @@
+//! Unit test: Age the priority of waiting transactions
+//!
+//! Analogy: A host who always seats the biggest tipper first can leave a
+//! modest party in the lobby all night while big spenders keep walking in.
+//! So the host lets every party's standing grow a little with each table
+//! seated ahead of them, until even the modest party is next in line.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            BatchLimits, PriorityAging, PriorityGraphScheduler, PriorityPolicy, Scheduler,
+            TransactionScheduler,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    const ONE_AT_A_TIME: BatchLimits = BatchLimits {
+        max_txs: 1,
+        max_cus: u64::MAX,
+        max_account_data_size: u64::MAX,
+    };
+
+    fn transaction(cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(Pubkey::new_unique(), false)],
+            ),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn scheduler(priority_aging: PriorityAging) -> PriorityGraphScheduler {
+        PriorityGraphScheduler::with_priority_policy(PriorityPolicy::ComputeUnitPrice)
+            .with_priority_aging(priority_aging)
+    }
+
+    #[test]
+    fn test_aging_functions() {
+        assert_eq!(PriorityAging::None.effective_priority(7, 100), 7);
+        let linear = PriorityAging::Linear { increment: 5 };
+        assert_eq!(linear.effective_priority(7, 0), 7);
+        assert_eq!(linear.effective_priority(7, 3), 22);
+        assert_eq!(linear.effective_priority(u64::MAX, 3), u64::MAX);
+
+        let exponential = PriorityAging::Exponential { half_life: 2 };
+        assert_eq!(exponential.effective_priority(3, 1), 3);
+        assert_eq!(exponential.effective_priority(3, 4), 12);
+        assert_eq!(exponential.effective_priority(0, 100), 0);
+        assert_eq!(exponential.effective_priority(3, 1000), u64::MAX);
+        // A zero half-life never doubles.
+        let never = PriorityAging::Exponential { half_life: 0 };
+        assert_eq!(never.effective_priority(3, 1000), 3);
+
+        // Aging never lowers a priority.
+        let custom = PriorityAging::Custom(|priority, age| priority.saturating_sub(age));
+        assert_eq!(custom.effective_priority(7, 3), 7);
+        let custom = PriorityAging::Custom(|priority, age| priority + age * age);
+        assert_eq!(custom.effective_priority(7, 3), 16);
+    }
+
+    #[test]
+    fn test_aging_prevents_starvation() {
+        // A new high-fee transaction arrives before every batch, and a
+        // batch only fits one transaction.
+        let run = |priority_aging| {
+            let mut scheduler = scheduler(priority_aging);
+            let low = scheduler.push(transaction(1));
+            let mut order = Vec::new();
+            for _ in 0..4 {
+                scheduler.push(transaction(100));
+                let batch = scheduler.next_batch(&ONE_AT_A_TIME);
+                scheduler.complete(&batch.ids);
+                order.extend(batch.ids);
+            }
+            (low, order, scheduler)
+        };
+
+        let (low, order, scheduler) = run(PriorityAging::None);
+        assert!(!order.contains(&low));
+        assert_eq!(scheduler.effective_priority(low), Some(1));
+        assert_eq!(scheduler.metrics().transactions_aged, 0);
+
+        // After two batches the low-fee transaction ranks at 101.
+        let (low, order, scheduler) = run(PriorityAging::Linear { increment: 50 });
+        assert_eq!(order[2], low);
+        assert_eq!(scheduler.effective_priority(low), None);
+        let metrics = scheduler.metrics();
+        // Transactions pushed at the start of a round have not waited yet;
+        // the ones outranked by `low` waited one batch.
+        assert_eq!(metrics.transactions_aged, 2);
+        assert_eq!(metrics.total_priority_boost, 100 + 50);
+        assert_eq!(metrics.average_priority_boost(), 75.0);
+    }
+
+    #[test]
+    fn test_effective_priority_of_queued_transactions() {
+        let mut scheduler = scheduler(PriorityAging::Exponential { half_life: 1 });
+        let first = scheduler.push(transaction(3));
+        let second = scheduler.push(transaction(4));
+        assert_eq!(scheduler.effective_priority(first), Some(3));
+        assert_eq!(scheduler.effective_priority(42), None);
+
+        let batch = scheduler.next_batch(&ONE_AT_A_TIME);
+        assert_eq!(batch.ids, vec![second]);
+        assert_eq!(scheduler.effective_priority(first), Some(6));
+        let third = scheduler.push(transaction(5));
+        // Doubled once, the first transaction outranks the newcomer.
+        assert_eq!(scheduler.next_batch(&ONE_AT_A_TIME).ids, vec![first]);
+        assert_eq!(scheduler.effective_priority(third), Some(10));
+        assert_eq!(scheduler.num_pending(), 1);
+
+        // Nothing waits in a one-shot schedule, so aging changes nothing.
+        let transactions: Vec<_> = (1..=3).map(transaction).collect();
+        let aged = scheduler.schedule(&transactions);
+        let plain = PriorityGraphScheduler::with_priority_policy(PriorityPolicy::ComputeUnitPrice)
+            .schedule(&transactions);
+        assert_eq!(aged, plain);
+    }
+}
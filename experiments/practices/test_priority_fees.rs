@@ -0,0 +1,75 @@
+//! Unit test: Prioritize transactions by priority fee
+//!
+//! Analogy: Parties can tip the host to be seated sooner. The tip is the
+//! price per compute unit times the number of units they reserve, and when
+//! two parties want the same table, the bigger tip is seated first.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::{ComputeBudgetInstruction, ComputeBudgetLimits},
+        fees::PriorityFeeCalculator,
+        scheduler::{PriorityGraphScheduler, ScheduleBatch, Scheduler},
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(hot_account: Pubkey, cu_limit: u32, cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(hot_account, false)],
+            ),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_priority_fee_calculation() {
+        let calculator = PriorityFeeCalculator::new();
+        let tx = transaction(Pubkey::new_unique(), 200_000, 1_500);
+
+        // 1_500 micro-lamports * 200_000 CUs = 300_000_000 micro-lamports = 300 lamports.
+        assert_eq!(calculator.calculate_priority(&tx), 300_000_000);
+        assert_eq!(calculator.calculate_prioritization_fee(&tx), 300);
+
+        // Partial lamports round up.
+        let limits = ComputeBudgetLimits {
+            compute_unit_limit: 1,
+            compute_unit_price: 1,
+            ..ComputeBudgetLimits::default()
+        };
+        assert_eq!(PriorityFeeCalculator::prioritization_fee(&limits), 1);
+    }
+
+    #[test]
+    fn test_higher_fee_is_scheduled_first() {
+        let hot_account = Pubkey::new_unique();
+        let transactions = vec![
+            transaction(hot_account, 200_000, 1),
+            transaction(hot_account, 200_000, 100),
+            transaction(Pubkey::new_unique(), 200_000, 10),
+        ];
+
+        // Transaction 1 pays the most and goes first; transaction 0 has to wait
+        // for it even though it arrived earlier.
+        let mut scheduler = PriorityGraphScheduler::new();
+        assert_eq!(
+            scheduler.schedule(&transactions),
+            vec![
+                ScheduleBatch {
+                    transaction_indexes: vec![1, 2]
+                },
+                ScheduleBatch {
+                    transaction_indexes: vec![0]
+                },
+            ]
+        );
+    }
+}
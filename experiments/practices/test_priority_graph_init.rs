@@ -1,16 +1,17 @@
-/// Unit test: Initialize Priority Graph Data Structure
-/// 
-/// Analogy: Think of a priority graph like a restaurant reservation system:
-/// - Nodes (transactions) = Customers with priority levels (VIP, regular, etc.)
-/// - Edges (accounts) = Shared tables/resources that create conflicts
-/// - Graph = Tracks which customers want the same tables
-/// 
-/// This test demonstrates how to create and initialize a priority graph,
-/// which is the foundation for scheduling transactions in Solana.
+//! Unit test: Initialize Priority Graph Data Structure
+//! 
+//! Analogy: Think of a priority graph like a restaurant reservation system:
+//! - Nodes (transactions) = Customers with priority levels (VIP, regular, etc.)
+//! - Edges (accounts) = Shared tables/resources that create conflicts
+//! - Graph = Tracks which customers want the same tables
+//! 
+//! This test demonstrates how to create and initialize a priority graph,
+//! which is the foundation for scheduling transactions in Solana.
 
 #[cfg(test)]
 mod tests {
-    use prio_graph::{AccessKind, GraphNode, PrioGraph, TopLevelId};
+    use prio_graph::{GraphNode, PrioGraph, TopLevelId};
+    use priority_graph_practice::accounts::LockSet;
     use solana_pubkey::Pubkey;
 
     // Define a simple priority ID type (like TransactionPriorityId)
@@ -78,19 +79,15 @@ mod tests {
         // Step 5: Insert transactions into the graph
         // Analogy: Adding customers to the reservation system with their desired tables
         // Transaction 1 wants account_a (read access)
-        prio_graph.insert_transaction(
-            tx1_id,
-            std::iter::once((account_a, AccessKind::Read)),
-        );
+        let tx1_locks = LockSet::new(vec![], vec![account_a]);
+        prio_graph.insert_transaction(tx1_id, tx1_locks.iter());
 
         // Verify graph is no longer empty
         assert!(!prio_graph.is_empty(), "Graph should not be empty after inserting a transaction");
 
         // Transaction 2 wants account_b (write access)
-        prio_graph.insert_transaction(
-            tx2_id,
-            std::iter::once((account_b, AccessKind::Write)),
-        );
+        let tx2_locks = LockSet::new(vec![account_b], vec![]);
+        prio_graph.insert_transaction(tx2_id, tx2_locks.iter());
 
         // Step 6: Verify we can pop transactions in priority order
         // Analogy: The host calls customers in VIP order (highest priority first)
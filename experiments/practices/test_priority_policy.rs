@@ -0,0 +1,112 @@
+//! Unit test: Rank conflicting transactions with a `PriorityPolicy`
+//!
+//! Analogy: When two parties want the same table, the host can seat the
+//! one with the bigger total bill, the one paying more per minute at the
+//! table, or simply whoever came first. Paying per minute is how the busiest
+//! restaurants decide, with the tip per minute settling a tie and the queue
+//! settling the rest.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::compute_budget::ComputeBudgetInstruction;
+    use priority_graph_practice::scheduler::{
+        BatchLimits, GreedyScheduler, PriorityGraphScheduler, PriorityPolicy, Scheduler,
+        TransactionScheduler,
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    // Writes `hot_account`, optionally with a second signer, at `cu_price`
+    // for `cu_limit` units.
+    fn transaction(
+        hot_account: Pubkey,
+        cu_price: u64,
+        cu_limit: u32,
+        second_signer: bool,
+    ) -> SanitizedTransaction {
+        let mut accounts = vec![AccountMeta::new(hot_account, false)];
+        if second_signer {
+            accounts.push(AccountMeta::new_readonly(Pubkey::new_unique(), true));
+        }
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    // Order in which `policy` runs `transactions`, which all conflict.
+    fn order(policy: PriorityPolicy, transactions: &[SanitizedTransaction]) -> Vec<usize> {
+        PriorityGraphScheduler::with_priority_policy(policy)
+            .schedule(transactions)
+            .into_iter()
+            .flat_map(|batch| batch.transaction_indexes)
+            .collect()
+    }
+
+    #[test]
+    fn test_compute_unit_price_beats_total_fee() {
+        let hot_account = Pubkey::new_unique();
+        // The first pays more in total, the second more per compute unit.
+        let transactions = vec![
+            transaction(hot_account, 10, 1_000_000, false),
+            transaction(hot_account, 20, 10_000, false),
+        ];
+        assert_eq!(order(PriorityPolicy::default(), &transactions), vec![0, 1]);
+        assert_eq!(
+            order(PriorityPolicy::PriorityFee, &transactions),
+            vec![0, 1]
+        );
+        assert_eq!(
+            order(PriorityPolicy::ComputeUnitPrice, &transactions),
+            vec![1, 0]
+        );
+        assert_eq!(order(PriorityPolicy::Fifo, &transactions), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_reward_cost_ratio_then_arrival_break_ties() {
+        let hot_account = Pubkey::new_unique();
+        // Same price; the second signature of the last one costs the block
+        // a little more but earns the leader another half base fee.
+        let transactions = vec![
+            transaction(hot_account, 0, 10_000, false),
+            transaction(hot_account, 0, 10_000, false),
+            transaction(hot_account, 0, 10_000, true),
+        ];
+        assert_eq!(
+            order(PriorityPolicy::ComputeUnitPrice, &transactions),
+            vec![2, 0, 1]
+        );
+
+        let mut greedy = GreedyScheduler::with_priority_policy(PriorityPolicy::ComputeUnitPrice);
+        let batches = greedy.schedule(&transactions);
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[0].transaction_indexes, vec![2]);
+        assert_eq!(batches[1].transaction_indexes, vec![0]);
+    }
+
+    #[test]
+    fn test_streaming_scheduler_follows_its_policy() {
+        let hot_account = Pubkey::new_unique();
+        let mut fifo = PriorityGraphScheduler::with_priority_policy(PriorityPolicy::Fifo);
+        let mut by_price =
+            PriorityGraphScheduler::with_priority_policy(PriorityPolicy::ComputeUnitPrice);
+        for scheduler in [&mut fifo, &mut by_price] {
+            scheduler.push(transaction(hot_account, 1, 10_000, false));
+            scheduler.push(transaction(hot_account, 1_000, 10_000, false));
+        }
+
+        let limits = BatchLimits::default();
+        assert_eq!(fifo.next_batch(&limits).ids, vec![0]);
+        assert_eq!(by_price.next_batch(&limits).ids, vec![1]);
+        fifo.complete(&[0]);
+        by_price.complete(&[1]);
+        assert_eq!(fifo.next_batch(&limits).ids, vec![1]);
+        assert_eq!(by_price.next_batch(&limits).ids, vec![0]);
+    }
+}
@@ -0,0 +1,129 @@
+//! Unit test: Cache verified programs between instructions
+//!
+//! Analogy: A guest chef's recipe is checked line by line the first time it
+//! arrives, then pinned above the stove. The next order for the same dish
+//! is cooked straight from the pinned copy. The wall only has room for so
+//! many recipes, so the one nobody cooked for longest comes down first, and
+//! a chef who hands in a new version gets it checked again.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::returning_elf;
+    use priority_graph_practice::{bank::Bank, svm::TransactionExecutor};
+    use solana_account::{AccountSharedData, WritableAccount};
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{bpf_loader, system_program};
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    /// A program whose only code returns `status`.
+    fn program_account(status: i32) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&returning_elf(status));
+        account.set_executable(true);
+        account
+    }
+
+    fn transaction(program_id: Pubkey, payer: &Pubkey) -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(program_id, &[], vec![]);
+        let message = Message::new(&[instruction], Some(payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_repeated_execution_hits_cache() {
+        let program_id = Pubkey::new_unique();
+        let store = HashMap::from([(program_id, program_account(0))]);
+        let executor = TransactionExecutor::new();
+        let payer = Pubkey::new_unique();
+
+        for _ in 0..3 {
+            let result =
+                executor.load_and_execute_transaction(&store, &transaction(program_id, &payer));
+            assert_eq!(result.status, Ok(()));
+        }
+        let stats = executor.program_cache().stats();
+        assert_eq!((stats.misses, stats.hits, stats.insertions), (1, 2, 1));
+        assert!(executor.program_cache().contains(&(program_id, 0)));
+
+        // Clones share the cache.
+        let clone = executor.clone();
+        clone.load_and_execute_transaction(&store, &transaction(program_id, &payer));
+        assert_eq!(executor.program_cache().stats().hits, 3);
+    }
+
+    #[test]
+    fn test_least_recently_used_program_is_evicted() {
+        let programs: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let store: HashMap<_, _> = programs
+            .iter()
+            .map(|program_id| (*program_id, program_account(0)))
+            .collect();
+        let mut executor = TransactionExecutor::new();
+        executor.set_program_cache_capacity(2);
+        let payer = Pubkey::new_unique();
+
+        for index in [0, 1, 0, 2] {
+            let result = executor
+                .load_and_execute_transaction(&store, &transaction(programs[index], &payer));
+            assert_eq!(result.status, Ok(()));
+        }
+        let cache = executor.program_cache();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&(programs[0], 0)));
+        assert!(!cache.contains(&(programs[1], 0)));
+        assert!(cache.contains(&(programs[2], 0)));
+        let stats = cache.stats();
+        assert_eq!(
+            (stats.misses, stats.hits, stats.insertions, stats.evictions),
+            (3, 1, 3, 1)
+        );
+    }
+
+    #[test]
+    fn test_storing_program_invalidates_cache() {
+        let program_id = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+        );
+        bank.store_account(program_id, program_account(0));
+
+        let execute = |bank: &mut Bank| {
+            let instruction = Instruction::new_with_bytes(program_id, &[], vec![]);
+            let message =
+                Message::new_with_blockhash(&[instruction], Some(&payer), &bank.last_blockhash());
+            let tx = SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(
+                message,
+            ));
+            bank.process_transaction_batch(&[tx])
+                .remove(0)
+                .unwrap()
+                .status
+        };
+        assert_eq!(execute(&mut bank), Ok(()));
+
+        // The new ELF is loaded, not the cached one. A new blockhash keeps
+        // the same transaction from being rejected as already processed.
+        bank.store_account(program_id, program_account(1));
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(
+            execute(&mut bank),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1)
+            ))
+        );
+        assert_eq!(bank.program_cache_stats().misses, 2);
+    }
+}
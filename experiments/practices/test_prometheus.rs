@@ -0,0 +1,162 @@
+//! Unit test: Expose pipeline metrics to Prometheus
+//!
+//! Analogy: Instead of the manager reading the tally sheets at closing
+//! time, a board by the kitchen door shows the running totals: orders
+//! taken, orders sent back, how much work each dish took, how full the
+//! pantry is. Anyone walking by can copy them down every few minutes and
+//! draw how the night went.
+
+#[cfg(all(test, feature = "metrics-prometheus"))]
+mod tests {
+    use priority_graph_practice::{
+        accounts_db::AccountsDb,
+        metrics::{Histogram, LatencyStage, LatencyTracker, PrometheusExporter, PrometheusMetrics},
+        scheduler::SchedulerMetrics,
+        svm::TransactionExecutor,
+        system_program,
+    };
+    use solana_account::AccountSharedData;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_signature::Signature;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    const LAMPORTS: u64 = 10_000_000;
+
+    fn transfer(from: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let instruction = system_program::transfer(from, &Pubkey::new_unique(), lamports);
+        let message = Message::new(&[instruction], Some(from));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn sample(exposition: &str, name: &str) -> String {
+        exposition
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("{name} is missing"))
+            .to_string()
+    }
+
+    #[test]
+    fn test_histogram_counts_by_bucket() {
+        let mut histogram = Histogram::new(&[10.0, 100.0]);
+        for value in [1.0, 10.0, 11.0, 1_000.0] {
+            histogram.observe(value);
+        }
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.sum(), 1_022.0);
+    }
+
+    #[test]
+    fn test_metrics_render_in_exposition_format() {
+        let payer = Pubkey::new_unique();
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(
+            payer,
+            AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let executor = TransactionExecutor::new();
+        // The first transfer leaves both accounts rent exempt; the second
+        // asks for more than the payer holds.
+        let results: Vec<_> = [
+            transfer(&payer, LAMPORTS / 10),
+            transfer(&payer, 10 * LAMPORTS),
+        ]
+        .iter()
+        .map(|transaction| executor.load_and_execute_transaction(&accounts_db, transaction))
+        .collect();
+
+        let metrics = PrometheusMetrics::new();
+        metrics.set_scheduler(&SchedulerMetrics {
+            transactions_queued: 5,
+            transactions_scheduled: 4,
+            batches_emitted: 2,
+            ..SchedulerMetrics::default()
+        });
+        metrics.observe_executed(&results);
+        metrics.set_accounts_db(&accounts_db);
+        let tracker = LatencyTracker::new();
+        let start = Instant::now();
+        let signature = Signature::from([1; 64]);
+        tracker.record(&signature, LatencyStage::Ingest, start);
+        tracker.record(
+            &signature,
+            LatencyStage::Commit,
+            start + Duration::from_millis(250),
+        );
+        metrics.set_latency(&tracker.slot_summary(0).unwrap());
+
+        let exposition = metrics.render();
+        assert!(exposition.contains("# TYPE svm_scheduler_transactions_queued_total counter"));
+        assert_eq!(
+            sample(&exposition, "svm_scheduler_transactions_queued_total"),
+            "5"
+        );
+        assert_eq!(
+            sample(&exposition, "svm_scheduler_batches_emitted_total"),
+            "2"
+        );
+        assert_eq!(
+            sample(&exposition, "svm_executor_transactions_executed_total"),
+            "2"
+        );
+        assert_eq!(
+            sample(&exposition, "svm_executor_transactions_failed_total"),
+            "1"
+        );
+        assert!(exposition.contains("# TYPE svm_executor_compute_units histogram"));
+        assert_eq!(
+            sample(
+                &exposition,
+                r#"svm_executor_compute_units_bucket{le="+Inf"}"#
+            ),
+            "2"
+        );
+        assert_eq!(sample(&exposition, "svm_executor_compute_units_count"), "2");
+        assert_eq!(sample(&exposition, "svm_accounts_db_accounts"), "1");
+        assert_eq!(
+            sample(
+                &exposition,
+                r#"svm_transaction_latency_seconds{phase="total",quantile="0.99"}"#
+            ),
+            "0.25"
+        );
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_exporter_serves_the_latest_metrics() {
+        let metrics = Arc::new(PrometheusMetrics::new());
+        let exporter = PrometheusExporter::new(Arc::clone(&metrics), "127.0.0.1:0").unwrap();
+        let addr = exporter.local_addr();
+
+        let response = get(addr, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("text/plain; version=0.0.4"));
+        assert!(response.contains("svm_scheduler_transactions_scheduled_total 0"));
+
+        metrics.set_scheduler(&SchedulerMetrics {
+            transactions_scheduled: 42,
+            ..SchedulerMetrics::default()
+        });
+        assert!(get(addr, "/metrics").contains("svm_scheduler_transactions_scheduled_total 42"));
+        assert!(get(addr, "/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Unit test: Enforce rent exemption and collect rent at epoch boundaries
+//!
+//! Analogy: Every table carries a cover charge. A party that has paid the
+//! full deposit sits as long as it likes; a party still running a tab pays
+//! a little at every change of shift and is shown out once it can't. No
+//! waiter may seat a new party that hasn't put down the deposit.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        fees::FeeStructure,
+        rent_collector::{CollectedInfo, RentState, RENT_EXEMPT_RENT_EPOCH, SLOTS_PER_YEAR},
+        system_program,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const MEGABYTE: usize = 1024 * 1024;
+
+    fn transfer(
+        bank: &mut Bank,
+        from: &Pubkey,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> Result<(), TransactionError> {
+        let instruction = system_program::transfer(from, to, lamports);
+        let message =
+            Message::new_with_blockhash(&[instruction], Some(from), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        bank.process_transaction_batch(&[tx])
+            .remove(0)
+            .unwrap()
+            .status
+    }
+
+    #[test]
+    fn test_rent_state_transitions() {
+        let rent = Rent::default();
+        let owner = Pubkey::new_unique();
+        let exempt = rent.minimum_balance(8);
+        let state =
+            |lamports| RentState::from_account(&AccountSharedData::new(lamports, 8, &owner), &rent);
+
+        assert_eq!(state(0), RentState::Uninitialized);
+        assert_eq!(state(exempt), RentState::RentExempt);
+        assert_eq!(
+            state(exempt - 1),
+            RentState::RentPaying {
+                lamports: exempt - 1,
+                data_size: 8
+            }
+        );
+
+        // Closing an account or funding it fully is always fine.
+        assert!(state(exempt - 1).transition_allowed(&state(0)));
+        assert!(state(0).transition_allowed(&state(exempt)));
+        // A rent-paying account may only shrink its balance.
+        assert!(state(100).transition_allowed(&state(50)));
+        assert!(!state(100).transition_allowed(&state(101)));
+        assert!(!state(0).transition_allowed(&state(100)));
+        assert!(!state(exempt).transition_allowed(&state(100)));
+        let resized = RentState::from_account(&AccountSharedData::new(50, 9, &owner), &rent);
+        assert!(!state(100).transition_allowed(&resized));
+
+        assert_eq!(SLOTS_PER_YEAR, 105_192_000);
+    }
+
+    #[test]
+    fn test_transaction_cannot_leave_account_rent_paying() {
+        let mut bank = Bank::default();
+        // Fees are waived so balances only reflect the transfers.
+        bank.set_fee_structure(FeeStructure::new(0, 0));
+        let exempt = bank.rent().minimum_balance(0);
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(10 * exempt, 0, &solana_sdk_ids::system_program::id()),
+        );
+
+        // The recipient would be created below its rent-exempt minimum.
+        assert_eq!(
+            transfer(&mut bank, &payer, &recipient, exempt - 1),
+            Err(TransactionError::InsufficientFundsForRent { account_index: 1 })
+        );
+        assert_eq!(bank.get_account(&recipient), None);
+        assert_eq!(transfer(&mut bank, &payer, &recipient, exempt), Ok(()));
+
+        // The payer may not drop below the minimum either, but it may close.
+        assert_eq!(
+            transfer(&mut bank, &payer, &recipient, 9 * exempt - 1),
+            Err(TransactionError::InsufficientFundsForRent { account_index: 0 })
+        );
+        assert_eq!(transfer(&mut bank, &payer, &recipient, 9 * exempt), Ok(()));
+        assert_eq!(bank.get_account(&payer), None);
+
+        // An account that was already rent paying may keep paying out.
+        let legacy = Pubkey::new_unique();
+        bank.store_account(
+            legacy,
+            AccountSharedData::new(1_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        assert_eq!(transfer(&mut bank, &legacy, &recipient, 100), Ok(()));
+        assert_eq!(bank.get_account(&legacy).unwrap().lamports(), 900);
+    }
+
+    #[test]
+    fn test_rent_collection_at_epoch_boundary() {
+        let mut bank = Bank::default();
+        bank.set_rent_collection_enabled(true);
+        let owner = Pubkey::new_unique();
+        let (exempt, paying, closing) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        bank.store_account(
+            exempt,
+            AccountSharedData::new(bank.rent().minimum_balance(MEGABYTE), MEGABYTE, &owner),
+        );
+        bank.store_account(paying, AccountSharedData::new(100_000, MEGABYTE, &owner));
+        bank.store_account(closing, AccountSharedData::new(1_000, MEGABYTE, &owner));
+
+        // Epoch 0 runs for 32 slots under the default warmup schedule.
+        let slots_in_epoch_0 = bank.epoch_schedule().get_slots_in_epoch(0);
+        for _ in 1..slots_in_epoch_0 {
+            bank.advance_slot(solana_hash::Hash::new_unique());
+        }
+        assert_eq!(bank.collected_rent(), CollectedInfo::default());
+        bank.advance_slot(solana_hash::Hash::new_unique());
+        assert_eq!(bank.epoch(), 1);
+
+        // The sweep charges epochs 0 and 1 in advance.
+        let slots = slots_in_epoch_0 + bank.epoch_schedule().get_slots_in_epoch(1);
+        let due = bank.rent().minimum_balance(MEGABYTE) * slots / SLOTS_PER_YEAR;
+        let account = bank.get_account(&paying).unwrap();
+        assert_eq!(account.lamports(), 100_000 - due);
+        assert_eq!(account.rent_epoch(), 2);
+        assert_eq!(bank.get_account(&closing), None);
+        assert_eq!(
+            bank.get_account(&exempt).unwrap().rent_epoch(),
+            RENT_EXEMPT_RENT_EPOCH
+        );
+        assert_eq!(
+            bank.collected_rent(),
+            CollectedInfo {
+                rent_amount: due + 1_000,
+                account_data_len_reclaimed: MEGABYTE as u64,
+            }
+        );
+
+        // Sweeping again within the same epoch finds nothing owed.
+        assert_eq!(bank.collect_rent(), CollectedInfo::default());
+    }
+}
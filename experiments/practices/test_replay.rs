@@ -0,0 +1,183 @@
+//! Unit test: Replay a recorded block and compare it with the chain
+//!
+//! Analogy: A food critic hands the kitchen last night's order tickets
+//! along with the pantry inventory from before service. The kitchen cooks
+//! every ticket again, in the same order, and compares each plate and each
+//! bill with what the critic wrote down; any plate that differs is a
+//! recipe the kitchen gets wrong.
+
+#[cfg(test)]
+mod tests {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use priority_graph_practice::replay::{
+        replay_block, BalanceMismatch, BlockFixture, ReplayError,
+    };
+    use priority_graph_practice::system_program::{self, SystemError};
+    use serde_json::{json, Value};
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_signer::Signer;
+    use solana_transaction::{versioned::VersionedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const SLOT: u64 = 1_000;
+
+    fn account(pubkey: &Pubkey, lamports: u64, owner: &Pubkey, executable: bool) -> Value {
+        json!({
+            "pubkey": pubkey.to_string(),
+            "account": {
+                "lamports": lamports,
+                "data": ["", "base64"],
+                "owner": owner.to_string(),
+                "executable": executable,
+                "rentEpoch": u64::MAX,
+            },
+        })
+    }
+
+    /// A block entry for a transfer of `lamports` from `from` to `to`, with
+    /// the balances of `from`, `to` and the System program it recorded.
+    fn transfer(
+        from: &Keypair,
+        to: &Pubkey,
+        lamports: u64,
+        pre: [u64; 3],
+        post: [u64; 3],
+    ) -> Value {
+        let instruction = system_program::transfer(&from.pubkey(), to, lamports);
+        let message = Message::new(&[instruction], Some(&from.pubkey()));
+        let transaction = Transaction::new(&[from], message, Hash::new_unique());
+        let bytes = bincode::serialize(&VersionedTransaction::from(transaction)).unwrap();
+        json!({
+            "transaction": [BASE64_STANDARD.encode(bytes), "base64"],
+            "meta": { "err": null, "fee": 5_000, "preBalances": pre, "postBalances": post },
+        })
+    }
+
+    fn fixture(transactions: Vec<Value>, accounts: Vec<Value>) -> BlockFixture {
+        let json = json!({ "slot": SLOT, "accounts": accounts, "transactions": transactions });
+        BlockFixture::from_json(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_replay_matches_recorded_block() {
+        let alice = Keypair::new();
+        let bob = Keypair::new();
+        let carol = Pubkey::new_unique();
+        let system = system_program_id::id();
+        let accounts = vec![
+            account(&alice.pubkey(), 1_000_000_000, &system, false),
+            account(&system, 1, &Pubkey::default(), true),
+        ];
+        // Bob can only pay for his transfer because of Alice's.
+        let transactions = vec![
+            transfer(
+                &alice,
+                &bob.pubkey(),
+                100_000_000,
+                [1_000_000_000, 0, 1],
+                [899_995_000, 100_000_000, 1],
+            ),
+            transfer(
+                &bob,
+                &carol,
+                10_000_000,
+                [100_000_000, 0, 1],
+                [89_995_000, 10_000_000, 1],
+            ),
+        ];
+        let fixture = fixture(transactions, accounts);
+        assert_eq!(fixture.slot, SLOT);
+        assert_eq!(fixture.accounts.len(), 2);
+
+        let report = replay_block(&fixture);
+        assert_eq!(report.transactions.len(), 2);
+        assert!(report.is_match(), "{report:?}");
+        assert_eq!(
+            report.transactions[1].signature,
+            fixture.transactions[1].transaction.signatures[0]
+        );
+    }
+
+    #[test]
+    fn test_replay_reports_differences() {
+        let alice = Keypair::new();
+        let bob = Pubkey::new_unique();
+        let system = system_program_id::id();
+        let accounts = vec![
+            account(&alice.pubkey(), 1_000_000_000, &system, false),
+            account(&system, 1, &Pubkey::default(), true),
+        ];
+        // The chain supposedly credited Bob one lamport more.
+        let mut overpaid = transfer(
+            &alice,
+            &bob,
+            100_000_000,
+            [1_000_000_000, 0, 1],
+            [899_995_000, 100_000_001, 1],
+        );
+        overpaid["meta"]["fee"] = json!(10_000);
+        // Alice cannot afford this one, so it fails where the chain said it
+        // succeeded.
+        let overdrawn = transfer(
+            &alice,
+            &bob,
+            1_000_000_000,
+            [899_995_000, 100_000_000, 1],
+            [899_990_000, 100_000_000, 1],
+        );
+        let report = replay_block(&fixture(vec![overpaid, overdrawn], accounts));
+
+        assert!(!report.is_match());
+        assert_eq!(report.mismatches().count(), 2);
+        let overpaid = &report.transactions[0];
+        assert_eq!(
+            (overpaid.expected_fee, overpaid.actual_fee),
+            (10_000, 5_000)
+        );
+        assert!(overpaid.pre_balance_mismatches.is_empty());
+        assert_eq!(
+            overpaid.post_balance_mismatches,
+            vec![BalanceMismatch {
+                index: 1,
+                pubkey: bob,
+                expected: 100_000_001,
+                actual: 100_000_000,
+            }]
+        );
+        let overdrawn = &report.transactions[1];
+        assert_eq!(overdrawn.expected_err, None);
+        assert_eq!(
+            overdrawn.actual_err,
+            Some(TransactionError::InstructionError(
+                0,
+                SystemError::ResultWithNegativeLamports.into()
+            ))
+        );
+        assert!(overdrawn.post_balance_mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_fixtures() {
+        assert_eq!(BlockFixture::from_json("{"), Err(ReplayError::InvalidJson));
+        assert_eq!(
+            BlockFixture::from_json(r#"{ "accounts": [], "transactions": [] }"#),
+            Err(ReplayError::InvalidField("slot".to_string()))
+        );
+        let garbage = json!({
+            "slot": SLOT,
+            "accounts": [],
+            "transactions": [{
+                "transaction": [BASE64_STANDARD.encode([1, 2, 3]), "base64"],
+                "meta": { "err": null, "fee": 0, "preBalances": [], "postBalances": [] },
+            }],
+        });
+        assert_eq!(
+            BlockFixture::from_json(&garbage.to_string()),
+            Err(ReplayError::InvalidTransaction(0))
+        );
+    }
+}
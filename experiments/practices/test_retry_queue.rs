@@ -0,0 +1,156 @@
+//! Unit test: Retry transactions that failed transiently
+//!
+//! Analogy: A party that finds its table still being cleared is not sent
+//! home. The host puts them back on the waiting list in their old spot,
+//! asks them to give the busy table a moment longer each time it happens,
+//! and only gives up on them after a few tries.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            is_transient_error, BatchLimits, FifoScheduler, PriorityGraphScheduler,
+            TransactionScheduler, WorkerPool, DEFAULT_MAX_RETRIES,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    fn transaction(writable: &[Pubkey], cu_price: u64) -> SanitizedTransaction {
+        let accounts = writable
+            .iter()
+            .map(|key| AccountMeta::new(*key, false))
+            .collect();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn is_retryable(output: &Result<(), TransactionError>) -> bool {
+        output.as_ref().is_err_and(is_transient_error)
+    }
+
+    #[test]
+    fn test_transient_failures_are_retried() {
+        let hot_account = Pubkey::new_unique();
+        let transactions: Vec<_> = (0..4)
+            .map(|cu_price| transaction(&[hot_account], cu_price))
+            .collect();
+
+        // Every transaction finds its accounts in use on its first attempt.
+        let attempted = Arc::new(Mutex::new(HashSet::new()));
+        let worker_attempted = Arc::clone(&attempted);
+        let pool = WorkerPool::new(2, move |tx: &SanitizedTransaction| {
+            if worker_attempted
+                .lock()
+                .unwrap()
+                .insert(*tx.message().fee_payer())
+            {
+                Err(TransactionError::AccountInUse)
+            } else {
+                Ok(())
+            }
+        })
+        .with_retryable_outputs(is_retryable);
+
+        let mut scheduler = PriorityGraphScheduler::new();
+        assert_eq!(scheduler.max_retries(), DEFAULT_MAX_RETRIES);
+        let outputs = pool.run(&mut scheduler, transactions).unwrap();
+        assert_eq!(outputs, vec![Ok(()); 4]);
+        assert_eq!(scheduler.metrics().transactions_retried, 4);
+        assert_eq!(scheduler.metrics().retries_exhausted, 0);
+        assert_eq!(scheduler.metrics().transactions_scheduled, 8);
+        assert!((0..4).all(|id| scheduler.retry_count(id) == 1));
+        assert_eq!(scheduler.take_retry_counts().len(), 4);
+        assert_eq!(scheduler.retry_count(0), 0);
+    }
+
+    #[test]
+    fn test_retries_run_out() {
+        // One retried transaction and one that fails for good right away.
+        let transactions = vec![
+            transaction(&[Pubkey::new_unique()], 1),
+            transaction(&[Pubkey::new_unique()], 1),
+        ];
+        let retried_payer = *transactions[0].message().fee_payer();
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let worker_attempts = Arc::clone(&attempts);
+        let pool = WorkerPool::new(1, move |tx: &SanitizedTransaction| {
+            if *tx.message().fee_payer() == retried_payer {
+                worker_attempts.fetch_add(1, Ordering::SeqCst);
+                Err(TransactionError::WouldExceedMaxBlockCostLimit)
+            } else {
+                Err(TransactionError::InsufficientFundsForFee)
+            }
+        })
+        .with_retryable_outputs(is_retryable);
+
+        let mut scheduler = PriorityGraphScheduler::new().with_max_retries(2);
+        let outputs = pool.run(&mut scheduler, transactions).unwrap();
+        assert_eq!(
+            outputs,
+            vec![
+                Err(TransactionError::WouldExceedMaxBlockCostLimit),
+                Err(TransactionError::InsufficientFundsForFee),
+            ]
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(scheduler.retry_count(0), 2);
+        assert_eq!(scheduler.retry_count(1), 0);
+        assert_eq!(scheduler.metrics().transactions_retried, 2);
+        assert_eq!(scheduler.metrics().retries_exhausted, 1);
+
+        // Schedulers without a retry queue complete the transaction.
+        let mut fifo = FifoScheduler::new();
+        let id = fifo.push(transaction(&[Pubkey::new_unique()], 1));
+        let batch = fifo.next_batch(&BatchLimits::default());
+        assert!(!fifo.retry(id, batch.transactions[0].clone()));
+        assert_eq!(fifo.num_pending(), 0);
+    }
+
+    #[test]
+    fn test_retry_keeps_priority_after_backoff() {
+        let limits = BatchLimits {
+            max_txs: 1,
+            ..BatchLimits::default()
+        };
+        let mut scheduler = PriorityGraphScheduler::new();
+        let high = scheduler.push(transaction(&[Pubkey::new_unique()], 10));
+        let first_low = scheduler.push(transaction(&[Pubkey::new_unique()], 2));
+        let second_low = scheduler.push(transaction(&[Pubkey::new_unique()], 1));
+
+        let mut batch = scheduler.next_batch(&limits);
+        assert_eq!(batch.ids, vec![high]);
+        assert!(scheduler.retry(high, batch.transactions.remove(0)));
+        assert_eq!(scheduler.num_pending(), 3);
+
+        // The retry sits out one batch, then outranks the rest again.
+        let batch = scheduler.next_batch(&limits);
+        assert_eq!(batch.ids, vec![first_low]);
+        let mut retried = scheduler.next_batch(&limits);
+        assert_eq!(retried.ids, vec![high]);
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![second_low]);
+
+        // Once nothing else could run, a retry skips the rest of its
+        // backoff.
+        scheduler.complete(&batch.ids);
+        scheduler.complete(&[second_low]);
+        assert!(scheduler.retry(high, retried.transactions.remove(0)));
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![high]);
+        assert_eq!(scheduler.retry_count(high), 2);
+    }
+}
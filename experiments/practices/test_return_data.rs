@@ -0,0 +1,204 @@
+//! Unit test: Hand return data back up the invocation stack
+//!
+//! Analogy: A cook who asks another station for help finds the helper's
+//! note on the pass once the plate comes back, and can act on it before
+//! leaving a note of their own. A helper who writes nothing leaves the pass
+//! as it was, a helper who drops the plate takes their note back with it,
+//! and whatever note is on the pass at the end goes out to the waiter.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::svm::{
+        InvokeContext, TransactionExecutor, TransactionReturnData, MAX_RETURN_DATA,
+    };
+    use solana_account::{AccountSharedData, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::bpf_loader;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    /// Sets the instruction data as return data, and returns success.
+    fn set_return_data_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0xbf, 1, 2, 0, 0),  // mov64 r1, r2
+            insn(0x79, 2, 1, -8, 0), // ldxdw r2, [r1 - 8]
+            insn(
+                0x85,
+                0,
+                0,
+                0,
+                hash_symbol_name(b"sol_set_return_data") as i32,
+            ), // call
+            insn(0xb7, 0, 0, 0, 0),  // mov64 r0, 0
+            insn(0x95, 0, 0, 0, 0),  // exit
+        ]
+    }
+
+    fn set_instruction_data(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let data = invoke_context.instruction_data().to_vec();
+        invoke_context.set_return_data(data)
+    }
+
+    // Invokes the program of its only account with its own instruction data.
+    fn invoke_callee(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let callee = *invoke_context.get_key(0)?;
+        let data = invoke_context.instruction_data().to_vec();
+        invoke_context.invoke(&Instruction::new_with_bytes(callee, &data, vec![]))
+    }
+
+    fn transaction(program_id: Pubkey, data: &[u8], callee: Pubkey) -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            data,
+            vec![AccountMeta::new_readonly(callee, false)],
+        );
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_callers_read_what_their_callee_returned() {
+        let (caller, builtin, sbf) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut executor = TransactionExecutor::new();
+        // Returns what its callee returned with a `!` appended, or fails if
+        // the callee returned nothing.
+        executor.add_program(caller, |invoke_context: &mut InvokeContext| {
+            invoke_callee(invoke_context)?;
+            let callee = *invoke_context.get_key(0)?;
+            let data = match invoke_context.get_return_data() {
+                (program_id, data) if *program_id == callee => [data, b"!"].concat(),
+                _ => return Err(InstructionError::Custom(1)),
+            };
+            invoke_context.set_return_data(data)
+        });
+        executor.add_program(builtin, set_instruction_data);
+        let store = HashMap::from([(sbf, program_account(&set_return_data_program()))]);
+
+        // Builtins and SBF programs hand their return data back alike.
+        for callee in [builtin, sbf] {
+            let result =
+                executor.load_and_execute_transaction(&store, &transaction(caller, b"hi", callee));
+            assert_eq!(result.status, Ok(()), "{callee}");
+            assert_eq!(
+                result.return_data,
+                Some(TransactionReturnData {
+                    program_id: caller,
+                    data: b"hi!".to_vec(),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_callees_that_return_nothing_or_fail_leave_earlier_data() {
+        let (caller, quiet, failing, sbf) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut executor = TransactionExecutor::new();
+        // Sets `[9]`, invokes its callee and ignores whether it failed, then
+        // fails unless `[9]` is still its own return data.
+        executor.add_program(caller, |invoke_context: &mut InvokeContext| {
+            invoke_context.set_return_data(vec![9])?;
+            let _ = invoke_callee(invoke_context);
+            match invoke_context.get_return_data() {
+                (program_id, [9]) if program_id == invoke_context.program_id() => Ok(()),
+                _ => Err(InstructionError::Custom(1)),
+            }
+        });
+        executor.add_program(quiet, |_: &mut InvokeContext| Ok(()));
+        executor.add_program(failing, |invoke_context: &mut InvokeContext| {
+            set_instruction_data(invoke_context)?;
+            Err(InstructionError::Custom(7))
+        });
+        let store = HashMap::from([(sbf, program_account(&set_return_data_program()))]);
+
+        let too_large = vec![0; MAX_RETURN_DATA + 1];
+        for (callee, data) in [
+            (quiet, &[1][..]),
+            (failing, &[1][..]),
+            (sbf, &too_large[..]),
+        ] {
+            let result =
+                executor.load_and_execute_transaction(&store, &transaction(caller, data, callee));
+            assert_eq!(result.status, Ok(()), "{callee}");
+            assert_eq!(
+                result.return_data,
+                Some(TransactionReturnData {
+                    program_id: caller,
+                    data: vec![9],
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_results_expose_the_last_data_within_the_limit() {
+        let (forwarder, setter) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(forwarder, invoke_callee);
+        executor.add_program(setter, set_instruction_data);
+        let store = HashMap::new();
+
+        // A caller that sets nothing itself passes its callee's data on.
+        let result =
+            executor.load_and_execute_transaction(&store, &transaction(forwarder, b"ok", setter));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(
+            result.return_data,
+            Some(TransactionReturnData {
+                program_id: setter,
+                data: b"ok".to_vec(),
+            })
+        );
+
+        // Builtins are bound by the syscall's limit.
+        let largest = vec![1; MAX_RETURN_DATA];
+        let result = executor
+            .load_and_execute_transaction(&store, &transaction(forwarder, &largest, setter));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.return_data.unwrap().data, largest);
+        let too_large = vec![1; MAX_RETURN_DATA + 1];
+        let result = executor
+            .load_and_execute_transaction(&store, &transaction(forwarder, &too_large, setter));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::InvalidArgument
+            ))
+        );
+        assert_eq!(result.return_data, None);
+    }
+}
@@ -0,0 +1,288 @@
+//! Unit test: Talk to a bank over JSON-RPC
+//!
+//! Analogy: The restaurant opens a phone line. Callers can ask what is on
+//! their tab, what today's menu code is, or place an order; the person on
+//! the phone passes the order straight to the kitchen and reads back the
+//! ticket number, or explains why the order was refused.
+
+#[cfg(all(test, feature = "rpc"))]
+mod tests {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::rpc::{
+        JsonRpcRequestProcessor, JsonRpcService, JSON_RPC_INVALID_PARAMS,
+        JSON_RPC_METHOD_NOT_FOUND, JSON_RPC_SEND_TRANSACTION_FAILURE,
+        JSON_RPC_SIGNATURE_VERIFICATION_FAILURE,
+    };
+    use priority_graph_practice::system_program;
+    use priority_graph_practice::token_program::{self, TokenAccount};
+    use serde_json::{json, Value};
+    use solana_account::AccountSharedData;
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_signature::Signature;
+    use solana_signer::Signer;
+    use solana_transaction::Transaction;
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn processor(payer: &Pubkey) -> JsonRpcRequestProcessor {
+        let mut bank = Bank::default();
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+        );
+        JsonRpcRequestProcessor::new(Arc::new(Mutex::new(bank)))
+    }
+
+    fn call(processor: &JsonRpcRequestProcessor, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        serde_json::from_str(&processor.process(&request.to_string())).unwrap()
+    }
+
+    fn transfer(from: &Keypair, to: &Pubkey, lamports: u64, blockhash: Hash) -> Transaction {
+        let message = Message::new(
+            &[system_program::transfer(&from.pubkey(), to, lamports)],
+            Some(&from.pubkey()),
+        );
+        Transaction::new(&[from], message, blockhash)
+    }
+
+    fn base58(transaction: &Transaction) -> String {
+        bs58::encode(bincode::serialize(transaction).unwrap()).into_string()
+    }
+
+    #[test]
+    fn test_read_methods() {
+        let alice = Pubkey::new_unique();
+        let processor = processor(&alice);
+
+        let balance = call(&processor, "getBalance", json!([alice.to_string()]));
+        assert_eq!(
+            balance,
+            json!({ "jsonrpc": "2.0", "id": 1, "result": { "context": { "slot": 0 }, "value": LAMPORTS } })
+        );
+        let account = call(&processor, "getAccountInfo", json!([alice.to_string()]));
+        assert_eq!(account["result"]["value"]["lamports"], LAMPORTS);
+        assert_eq!(account["result"]["value"]["data"], json!(["", "base64"]));
+        assert_eq!(
+            account["result"]["value"]["owner"],
+            "11111111111111111111111111111111"
+        );
+        let missing = call(
+            &processor,
+            "getAccountInfo",
+            json!([Pubkey::new_unique().to_string(), { "encoding": "base58" }]),
+        );
+        assert_eq!(missing["result"]["value"], Value::Null);
+        let blockhash = call(&processor, "getLatestBlockhash", json!([]));
+        assert_eq!(
+            blockhash["result"]["value"]["blockhash"],
+            Hash::default().to_string()
+        );
+
+        assert_eq!(
+            call(&processor, "getBalance", json!(["not a pubkey"]))["error"]["code"],
+            JSON_RPC_INVALID_PARAMS
+        );
+        assert_eq!(
+            call(&processor, "getSlotLeader", json!([]))["error"]["code"],
+            JSON_RPC_METHOD_NOT_FOUND
+        );
+        let batch: Value = serde_json::from_str(&processor.process(
+            r#"[{"jsonrpc":"2.0","id":1,"method":"getBalance","params":["11111111111111111111111111111111"]},
+                {"jsonrpc":"1.0","id":2,"method":"getBalance"}]"#,
+        ))
+        .unwrap();
+        assert_eq!(batch[0]["result"]["value"], 0);
+        assert_eq!(batch[1]["error"]["code"], -32600);
+        assert_eq!(
+            serde_json::from_str::<Value>(&processor.process("{")).unwrap()["error"]["code"],
+            -32700
+        );
+    }
+
+    #[test]
+    fn test_scan_methods() {
+        let [mint, other_mint, wallet] = [(); 3].map(|()| Pubkey::new_unique());
+        let [first, second] = [(); 2].map(|()| Pubkey::new_unique());
+        let mut bank = Bank::default();
+        let rent = bank.rent().clone();
+        for (pubkey, mint) in [(first, mint), (second, other_mint)] {
+            let account = TokenAccount::new(mint, wallet, 5);
+            bank.store_account(pubkey, token_program::token_account(&account, &rent));
+        }
+        let processor = JsonRpcRequestProcessor::new(Arc::new(Mutex::new(bank)));
+        let pubkeys = |accounts: &Value| -> Vec<String> {
+            let mut pubkeys: Vec<String> = accounts
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|account| account["pubkey"].as_str().unwrap().to_owned())
+                .collect();
+            pubkeys.sort();
+            pubkeys
+        };
+        let mut both = vec![first.to_string(), second.to_string()];
+        both.sort();
+
+        let program_accounts = call(
+            &processor,
+            "getProgramAccounts",
+            json!([token_program::ID.to_string()]),
+        );
+        assert_eq!(pubkeys(&program_accounts["result"]), both);
+        assert_eq!(
+            program_accounts["result"][0]["account"]["space"],
+            TokenAccount::LEN
+        );
+        let with_context = call(
+            &processor,
+            "getProgramAccounts",
+            json!([token_program::ID.to_string(), { "withContext": true }]),
+        );
+        assert_eq!(pubkeys(&with_context["result"]["value"]), both);
+
+        let by_program = call(
+            &processor,
+            "getTokenAccountsByOwner",
+            json!([wallet.to_string(), { "programId": token_program::ID.to_string() }]),
+        );
+        assert_eq!(pubkeys(&by_program["result"]["value"]), both);
+        let by_mint = call(
+            &processor,
+            "getTokenAccountsByOwner",
+            json!([wallet.to_string(), { "mint": mint.to_string() }, { "encoding": "base58" }]),
+        );
+        assert_eq!(pubkeys(&by_mint["result"]["value"]), [first.to_string()]);
+        assert_eq!(
+            by_mint["result"]["value"][0]["account"]["data"][1],
+            "base58"
+        );
+        for filter in [
+            json!({ "programId": Pubkey::new_unique().to_string() }),
+            json!({}),
+        ] {
+            let rejected = call(
+                &processor,
+                "getTokenAccountsByOwner",
+                json!([wallet.to_string(), filter]),
+            );
+            assert_eq!(rejected["error"]["code"], JSON_RPC_INVALID_PARAMS);
+        }
+    }
+
+    #[test]
+    fn test_send_and_simulate_transactions() {
+        let alice = Keypair::new();
+        let bob = Pubkey::new_unique();
+        let processor = processor(&alice.pubkey());
+
+        // Simulating with base64 commits nothing.
+        let overdraw = transfer(&alice, &bob, 2 * LAMPORTS, Hash::default());
+        let encoded = BASE64_STANDARD.encode(bincode::serialize(&overdraw).unwrap());
+        let simulated = call(
+            &processor,
+            "simulateTransaction",
+            json!([encoded, { "encoding": "base64", "sigVerify": true }]),
+        );
+        assert_eq!(
+            simulated["result"]["value"]["err"],
+            json!({ "InstructionError": [0, { "Custom": 1 }] })
+        );
+        assert_eq!(simulated["result"]["value"]["fee"], 5_000);
+        assert_eq!(
+            call(
+                &processor,
+                "getBalance",
+                json!([alice.pubkey().to_string()])
+            )["result"]["value"],
+            LAMPORTS
+        );
+
+        let paid = transfer(&alice, &bob, 1_000_000, Hash::default());
+        let sent = call(&processor, "sendTransaction", json!([base58(&paid)]));
+        assert_eq!(sent["result"], paid.signatures[0].to_string());
+        assert_eq!(
+            call(&processor, "getBalance", json!([bob.to_string()]))["result"]["value"],
+            1_000_000
+        );
+        let resent = call(&processor, "sendTransaction", json!([base58(&paid)]));
+        assert_eq!(resent["error"]["code"], JSON_RPC_SEND_TRANSACTION_FAILURE);
+
+        let mut forged = transfer(&alice, &bob, 3_000_000, Hash::default());
+        forged.signatures[0] = Signature::from([7; 64]);
+        let sent = call(&processor, "sendTransaction", json!([base58(&forged)]));
+        assert_eq!(
+            sent["error"]["code"],
+            JSON_RPC_SIGNATURE_VERIFICATION_FAILURE
+        );
+        let sent = call(&processor, "sendTransaction", json!(["not base58!"]));
+        assert_eq!(sent["error"]["code"], JSON_RPC_INVALID_PARAMS);
+    }
+
+    fn post(addr: SocketAddr, method: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_service_answers_over_http() {
+        let alice = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.store_account(
+            alice,
+            AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let bank = Arc::new(Mutex::new(bank));
+        let service = JsonRpcService::new(bank.clone(), "127.0.0.1:0").unwrap();
+        let addr = service.local_addr();
+
+        let response = post(
+            addr,
+            "POST",
+            &json!({ "jsonrpc": "2.0", "id": 7, "method": "getBalance", "params": [alice.to_string()] })
+                .to_string(),
+        );
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("application/json"));
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["id"], 7);
+        assert_eq!(body["result"]["value"], LAMPORTS);
+
+        // The server reads the bank as it is now.
+        bank.lock().unwrap().advance_slot(Hash::new_unique());
+        let response = post(
+            addr,
+            "POST",
+            r#"{"jsonrpc":"2.0","id":8,"method":"getLatestBlockhash"}"#,
+        );
+        assert!(response.contains(r#""slot":1"#));
+        assert!(post(addr, "GET", "").starts_with("HTTP/1.1 405"));
+
+        // The listener closes on the server's own thread shortly after.
+        drop(service);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while TcpStream::connect(addr).is_ok() {
+            assert!(Instant::now() < deadline, "the server kept listening");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
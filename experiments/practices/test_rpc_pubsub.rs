@@ -0,0 +1,233 @@
+//! Unit test: Subscribe to accounts, signatures and slots over WebSocket
+//!
+//! Analogy: Regulars can leave their number with the host: ring me when my
+//! tab changes, when my order is out, or when the next sitting opens. The
+//! host calls as it happens, calls once per order, and tears up the number
+//! when the regular says so or stops answering.
+
+#[cfg(all(test, feature = "rpc"))]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::rpc::pubsub::{PubSubService, RpcSubscriptions};
+    use priority_graph_practice::system_program;
+    use serde_json::{json, Value};
+    use solana_account::AccountSharedData;
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_signer::Signer;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::{
+        net::TcpStream,
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+    use tungstenite::{Message as WebSocketMessage, WebSocket};
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    struct Setup {
+        bank: Bank,
+        subscriptions: Arc<RpcSubscriptions>,
+        service: PubSubService,
+        payer: Keypair,
+    }
+
+    fn setup() -> Setup {
+        let payer = Keypair::new();
+        let mut bank = Bank::default();
+        bank.store_account(
+            payer.pubkey(),
+            AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let subscriptions = Arc::new(RpcSubscriptions::default());
+        bank.add_notifier(subscriptions.clone());
+        let service = PubSubService::new(subscriptions.clone(), "127.0.0.1:0").unwrap();
+        Setup {
+            bank,
+            subscriptions,
+            service,
+            payer,
+        }
+    }
+
+    fn connect(service: &PubSubService) -> WebSocket<TcpStream> {
+        let addr = service.local_addr();
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        tungstenite::client(format!("ws://{addr}"), stream)
+            .unwrap()
+            .0
+    }
+
+    fn read(socket: &mut WebSocket<TcpStream>) -> Value {
+        loop {
+            if let WebSocketMessage::Text(text) = socket.read().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    // Sends a request and returns the answer.
+    fn call(socket: &mut WebSocket<TcpStream>, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        socket
+            .send(WebSocketMessage::text(request.to_string()))
+            .unwrap();
+        read(socket)
+    }
+
+    fn transfer(from: &Keypair, to: &Pubkey, lamports: u64) -> SanitizedTransaction {
+        let message = Message::new(
+            &[system_program::transfer(&from.pubkey(), to, lamports)],
+            Some(&from.pubkey()),
+        );
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+            &[from],
+            message,
+            Hash::default(),
+        ))
+    }
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_commit_notifies_subscribers() {
+        let Setup {
+            mut bank,
+            subscriptions,
+            service,
+            payer,
+        } = setup();
+        let bob = Pubkey::new_unique();
+        let transaction = transfer(&payer, &bob, 1_000_000);
+        let mut socket = connect(&service);
+        let account = call(&mut socket, "accountSubscribe", json!([bob.to_string()]));
+        let signature = call(
+            &mut socket,
+            "signatureSubscribe",
+            json!([transaction.signature().to_string()]),
+        );
+        let slot = call(&mut socket, "slotSubscribe", json!([]));
+        assert_eq!(account["result"], 0);
+        assert_eq!(signature["result"], 1);
+        assert_eq!(slot["result"], 2);
+        assert_eq!(subscriptions.num_subscriptions(), 3);
+
+        bank.process_transaction_batch(&[transaction]);
+        bank.advance_slot(Hash::new_unique());
+        let notification = read(&mut socket);
+        assert_eq!(notification["method"], "accountNotification");
+        assert_eq!(notification["params"]["subscription"], 0);
+        assert_eq!(
+            notification["params"]["result"]["value"]["lamports"],
+            1_000_000
+        );
+        assert_eq!(
+            read(&mut socket),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "signatureNotification",
+                "params": {
+                    "result": { "context": { "slot": 0 }, "value": { "err": null } },
+                    "subscription": 1,
+                },
+            })
+        );
+        assert_eq!(
+            read(&mut socket)["params"],
+            json!({ "result": { "parent": 0, "root": 0, "slot": 1 }, "subscription": 2 })
+        );
+        // A signature is only notified once.
+        assert_eq!(subscriptions.num_subscriptions(), 2);
+    }
+
+    #[test]
+    fn test_unsubscribe_and_disconnect() {
+        let Setup {
+            subscriptions,
+            service,
+            ..
+        } = setup();
+        let mut socket = connect(&service);
+        let id = call(&mut socket, "slotSubscribe", json!([]))["result"].clone();
+        call(
+            &mut socket,
+            "accountSubscribe",
+            json!([Pubkey::new_unique().to_string(), { "encoding": "base58" }]),
+        );
+        assert_eq!(
+            call(&mut socket, "slotUnsubscribe", json!([id]))["result"],
+            true
+        );
+        assert_eq!(
+            call(&mut socket, "slotUnsubscribe", json!([id]))["error"]["code"],
+            -32602
+        );
+        assert_eq!(
+            call(&mut socket, "rootSubscribe", json!([]))["error"]["code"],
+            -32601
+        );
+        assert_eq!(
+            call(&mut socket, "signatureSubscribe", json!(["nope"]))["error"]["code"],
+            -32602
+        );
+        assert_eq!(subscriptions.num_subscriptions(), 1);
+
+        // A second connection cannot end the first one's subscriptions.
+        let mut other = connect(&service);
+        assert_eq!(
+            call(&mut other, "accountUnsubscribe", json!([1]))["error"]["code"],
+            -32602
+        );
+        socket.close(None).unwrap();
+        wait_for(|| subscriptions.num_subscriptions() == 0);
+    }
+
+    #[test]
+    fn test_failed_transactions_report_their_error() {
+        let Setup {
+            mut bank,
+            service,
+            payer,
+            ..
+        } = setup();
+        let mut socket = connect(&service);
+        let overdraw = transfer(&payer, &Pubkey::new_unique(), 2 * LAMPORTS);
+        call(
+            &mut socket,
+            "signatureSubscribe",
+            json!([overdraw.signature().to_string()]),
+        );
+        call(
+            &mut socket,
+            "accountSubscribe",
+            json!([payer.pubkey().to_string()]),
+        );
+
+        bank.process_transaction_batch(&[overdraw]);
+        // The fee was still charged.
+        assert_eq!(
+            read(&mut socket)["params"]["result"]["value"]["lamports"],
+            LAMPORTS - 5_000
+        );
+        assert_eq!(
+            read(&mut socket)["params"]["result"]["value"]["err"],
+            json!({ "InstructionError": [0, { "Custom": 1 }] })
+        );
+
+        // Dropping the service closes the connection.
+        drop(service);
+        assert!(matches!(socket.read(), Ok(WebSocketMessage::Close(_))));
+    }
+}
@@ -0,0 +1,155 @@
+//! Unit test: Sanitize transactions before they enter the pipeline
+//!
+//! Analogy: The host reads every order slip before it goes to the kitchen.
+//! A slip with the wrong number of signatures, a table listed twice or a
+//! dish that isn't on the menu goes back to the customer straight away,
+//! rather than confusing a cook halfway through service.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        sanitize::{
+            deserialize_transaction, sanitize_transaction, SanitizeError, MAX_INSTRUCTION_DATA_LEN,
+            MAX_TRANSACTION_LEN,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::{Message, SimpleAddressLoader, VersionedMessage};
+    use solana_pubkey::Pubkey;
+    use solana_signature::Signature;
+    use solana_transaction::{versioned::VersionedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    fn message(data: &[u8]) -> Message {
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            data,
+            vec![AccountMeta::new(Pubkey::new_unique(), false)],
+        );
+        Message::new(&[instruction], Some(&Pubkey::new_unique()))
+    }
+
+    fn transaction(message: Message) -> VersionedTransaction {
+        VersionedTransaction::from(Transaction::new_unsigned(message))
+    }
+
+    // Sanitizes `message` as is, bypassing the consistency of the builder.
+    fn sanitize(message: Message) -> Result<(), SanitizeError> {
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); message.header.num_required_signatures.into()],
+            message: VersionedMessage::Legacy(message),
+        };
+        sanitize_transaction(transaction, SimpleAddressLoader::Disabled).map(|_| ())
+    }
+
+    #[test]
+    fn test_signatures_and_header() {
+        let tx =
+            sanitize_transaction(transaction(message(&[])), SimpleAddressLoader::Disabled).unwrap();
+        assert_eq!(tx.message().account_keys().len(), 3);
+
+        let mut extra_signature = transaction(message(&[]));
+        extra_signature.signatures.push(Signature::default());
+        assert_eq!(
+            sanitize_transaction(extra_signature, SimpleAddressLoader::Disabled).map(|_| ()),
+            Err(SanitizeError::SignatureCountMismatch {
+                expected: 1,
+                actual: 2,
+            })
+        );
+
+        // The fee payer cannot be read-only.
+        let mut readonly_payer = message(&[]);
+        readonly_payer.header.num_readonly_signed_accounts = 1;
+        assert_eq!(sanitize(readonly_payer), Err(SanitizeError::InvalidHeader));
+
+        let mut too_many_readonly = message(&[]);
+        too_many_readonly.header.num_readonly_unsigned_accounts = 3;
+        assert_eq!(
+            sanitize(too_many_readonly),
+            Err(SanitizeError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn test_account_keys_and_indexes() {
+        let mut duplicate = message(&[]);
+        let key = duplicate.account_keys[1];
+        duplicate.account_keys.push(key);
+        duplicate.header.num_readonly_unsigned_accounts += 1;
+        assert_eq!(
+            sanitize(duplicate),
+            Err(SanitizeError::DuplicateAccountKey(key))
+        );
+
+        // The fee payer can't be invoked, and neither can a missing key.
+        for program_id_index in [0, 3] {
+            let mut message = message(&[]);
+            message.instructions[0].program_id_index = program_id_index;
+            assert_eq!(
+                sanitize(message),
+                Err(SanitizeError::ProgramIdIndexOutOfBounds {
+                    instruction_index: 0
+                })
+            );
+        }
+
+        let mut message = message(&[]);
+        message.instructions[0].accounts.push(3);
+        assert_eq!(
+            sanitize(message),
+            Err(SanitizeError::AccountIndexOutOfBounds {
+                instruction_index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_instruction_data_limit() {
+        assert_eq!(sanitize(message(&[0; MAX_INSTRUCTION_DATA_LEN])), Ok(()));
+        assert_eq!(
+            sanitize(message(&[0; MAX_INSTRUCTION_DATA_LEN + 1])),
+            Err(SanitizeError::InstructionDataTooLarge {
+                instruction_index: 0,
+                len: MAX_INSTRUCTION_DATA_LEN + 1,
+            })
+        );
+
+        // The bank reports sanitize errors as transaction errors.
+        let bank = Bank::default();
+        let oversized = transaction(message(&[0; MAX_INSTRUCTION_DATA_LEN + 1]));
+        assert_eq!(
+            bank.resolve_transaction(oversized).map(|_| ()),
+            Err(TransactionError::SanitizeFailure)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_wire_bytes() {
+        let transaction = transaction(message(&[1, 2, 3]));
+        let bytes = bincode::serialize(&transaction).unwrap();
+        assert_eq!(deserialize_transaction(&bytes), Ok(transaction));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            deserialize_transaction(&trailing),
+            Err(SanitizeError::InvalidEncoding)
+        );
+        assert_eq!(
+            deserialize_transaction(&bytes[..bytes.len() - 1]),
+            Err(SanitizeError::InvalidEncoding)
+        );
+        assert_eq!(
+            deserialize_transaction(&[0; MAX_TRANSACTION_LEN + 1]),
+            Err(SanitizeError::TransactionTooLarge {
+                len: MAX_TRANSACTION_LEN + 1
+            })
+        );
+        assert_eq!(
+            TransactionError::from(SanitizeError::InvalidEncoding),
+            TransactionError::SanitizeFailure
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Unit test: Execute SBF programs deployed with the BPF loader
+//!
+//! Analogy: Most dishes come from the house cooks (builtins), but a guest
+//! chef can leave a written recipe (the ELF) at the pass. The kitchen
+//! follows it step by step, hands the chef only the ingredients on the
+//! order, and checks what comes back before it leaves the kitchen.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::svm::{
+        SysvarCache, SysvarSource, TransactionExecutor, TransactionReturnData, MAX_RETURN_DATA,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_epoch_schedule::EpochSchedule;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::{bpf_loader, sysvar};
+    use solana_slot_hashes::SlotHashes;
+    use solana_stake_interface::stake_history::StakeHistory;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    /// Offset of the first account's data in the input region: the account
+    /// count, then the account's flags, key, owner, lamports and data length.
+    const FIRST_ACCOUNT_DATA: i16 = 8 + 8 + 32 + 32 + 8 + 8;
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    /// Copies the first instruction data byte into the first account's
+    /// data, logs it, and returns success.
+    fn copy_byte_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0x71, 3, 2, 0, 0),                                       // ldxb r3, [r2]
+            insn(0x73, 1, 3, FIRST_ACCOUNT_DATA, 0),                      // stxb [r1 + data], r3
+            insn(0xbf, 1, 3, 0, 0),                                       // mov64 r1, r3
+            insn(0x85, 0, 0, 0, hash_symbol_name(b"sol_log_64_") as i32), // call
+            insn(0xb7, 0, 0, 0, 0),                                       // mov64 r0, 0
+            insn(0x95, 0, 0, 0, 0),                                       // exit
+        ]
+    }
+
+    /// Reads the first eight bytes of the sysvar whose id is the
+    /// instruction data into the first account's data, and returns the
+    /// syscall's status.
+    fn get_sysvar_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0xbf, 6, 1, 0, 0),                         // mov64 r6, r1
+            insn(0xbf, 1, 2, 0, 0),                         // mov64 r1, r2
+            insn(0xbf, 2, 6, 0, 0),                         // mov64 r2, r6
+            insn(0x07, 2, 0, 0, FIRST_ACCOUNT_DATA.into()), // add64 r2, data
+            insn(0xb7, 3, 0, 0, 0),                         // mov64 r3, 0
+            insn(0xb7, 4, 0, 0, 8),                         // mov64 r4, 8
+            insn(0x85, 0, 0, 0, hash_symbol_name(b"sol_get_sysvar") as i32), // call
+            insn(0x95, 0, 0, 0, 0),                         // exit
+        ]
+    }
+
+    /// Sets the instruction data as return data, and returns success.
+    fn set_return_data_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0xbf, 1, 2, 0, 0),  // mov64 r1, r2
+            insn(0x79, 2, 1, -8, 0), // ldxdw r2, [r1 - 8]
+            insn(
+                0x85,
+                0,
+                0,
+                0,
+                hash_symbol_name(b"sol_set_return_data") as i32,
+            ), // call
+            insn(0xb7, 0, 0, 0, 0),  // mov64 r0, 0
+            insn(0x95, 0, 0, 0, 0),  // exit
+        ]
+    }
+
+    fn program_account(text: &[[u8; 8]]) -> AccountSharedData {
+        let mut account = AccountSharedData::new(1, 0, &bpf_loader::id());
+        account.set_data_from_slice(&elf(text));
+        account.set_executable(true);
+        account
+    }
+
+    fn transaction(instruction: Instruction) -> SanitizedTransaction {
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[instruction], Some(&payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_sbf_program_writes_account_data() {
+        let program_id = Pubkey::new_unique();
+        let target = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(program_id, program_account(&copy_byte_program()));
+        store.insert(target, AccountSharedData::new(1, 4, &program_id));
+
+        let ix =
+            Instruction::new_with_bytes(program_id, &[42], vec![AccountMeta::new(target, false)]);
+        let result =
+            TransactionExecutor::new().load_and_execute_transaction(&store, &transaction(ix));
+
+        assert_eq!(result.status, Ok(()));
+        let (_, post) = result
+            .post_accounts
+            .iter()
+            .find(|(key, _)| *key == target)
+            .unwrap();
+        assert_eq!(post.data(), &[42, 0, 0, 0]);
+        // One CU per SBF instruction plus the logging syscall.
+        assert_eq!(result.consumed_units, 6 + 100);
+        let logs = &result.log_messages;
+        assert_eq!(logs[0], format!("Program {program_id} invoke [1]"));
+        assert!(logs[1].starts_with("Program log: 0x2a, "));
+        assert!(logs[2].starts_with(&format!("Program {program_id} consumed 106 of ")));
+        assert_eq!(logs[3], format!("Program {program_id} success"));
+    }
+
+    #[test]
+    fn test_sbf_program_cannot_modify_readonly_account() {
+        let program_id = Pubkey::new_unique();
+        let target = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(program_id, program_account(&copy_byte_program()));
+        store.insert(target, AccountSharedData::new(1, 4, &program_id));
+
+        let ix = Instruction::new_with_bytes(
+            program_id,
+            &[42],
+            vec![AccountMeta::new_readonly(target, false)],
+        );
+        let result =
+            TransactionExecutor::new().load_and_execute_transaction(&store, &transaction(ix));
+
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ReadonlyDataModified
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sbf_program_error_status_and_invalid_elf() {
+        let failing_id = Pubkey::new_unique();
+        let broken_id = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        // mov64 r0, 7; exit
+        store.insert(
+            failing_id,
+            program_account(&[insn(0xb7, 0, 0, 0, 7), insn(0x95, 0, 0, 0, 0)]),
+        );
+        let mut broken = program_account(&[]);
+        broken.set_data_from_slice(b"not an elf");
+        store.insert(broken_id, broken);
+
+        let executor = TransactionExecutor::new();
+        let failing = Instruction::new_with_bytes(failing_id, &[], vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(failing));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(7)
+            ))
+        );
+
+        let broken = Instruction::new_with_bytes(broken_id, &[], vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(broken));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::InvalidAccountData
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sbf_program_reads_sysvar() {
+        let program_id = Pubkey::new_unique();
+        let target = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(program_id, program_account(&get_sysvar_program()));
+        store.insert(target, AccountSharedData::new(1, 8, &program_id));
+
+        let mut executor = TransactionExecutor::new();
+        executor.set_sysvar_cache(SysvarCache::new(SysvarSource {
+            slot: 42,
+            epoch_schedule: &EpochSchedule::default(),
+            rent: &Rent::default(),
+            slot_hashes: &SlotHashes::default(),
+            stake_history: &StakeHistory::default(),
+            recent_blockhashes: &[Hash::default()],
+            lamports_per_signature: 5_000,
+        }));
+
+        let read_clock = Instruction::new_with_bytes(
+            program_id,
+            sysvar::clock::id().as_ref(),
+            vec![AccountMeta::new(target, false)],
+        );
+        let result = executor.load_and_execute_transaction(&store, &transaction(read_clock));
+        assert_eq!(result.status, Ok(()));
+        let (_, post) = result
+            .post_accounts
+            .iter()
+            .find(|(key, _)| *key == target)
+            .unwrap();
+        assert_eq!(post.data(), &42u64.to_le_bytes());
+
+        // Unknown sysvars are reported through the syscall's status.
+        let read_unknown = Instruction::new_with_bytes(
+            program_id,
+            Pubkey::new_unique().as_ref(),
+            vec![AccountMeta::new(target, false)],
+        );
+        let result = executor.load_and_execute_transaction(&store, &transaction(read_unknown));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(2)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sbf_program_sets_return_data() {
+        let program_id = Pubkey::new_unique();
+        let mut store = HashMap::new();
+        store.insert(program_id, program_account(&set_return_data_program()));
+        let executor = TransactionExecutor::new();
+
+        let instruction = Instruction::new_with_bytes(program_id, b"hello", vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(instruction));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(
+            result.return_data,
+            Some(TransactionReturnData {
+                program_id,
+                data: b"hello".to_vec(),
+            })
+        );
+
+        let too_large = vec![0; MAX_RETURN_DATA + 1];
+        let instruction = Instruction::new_with_bytes(program_id, &too_large, vec![]);
+        let result = executor.load_and_execute_transaction(&store, &transaction(instruction));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ProgramFailedToComplete
+            ))
+        );
+        assert_eq!(result.return_data, None);
+    }
+}
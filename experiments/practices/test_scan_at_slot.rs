@@ -0,0 +1,133 @@
+//! Unit test: Scan the accounts as a slot left them while later slots commit
+//!
+//! Analogy: At closing time the manager photographs the ledger page of
+//! the day and counts the till from the photo, while the waiters already
+//! write tomorrow's orders on a fresh page. The count never mixes the two
+//! days, and the photo is only thrown away once nobody is counting from it.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts_db::{AccountsVersions, ScanError},
+        bank::Bank,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_hash::Hash;
+    use solana_pubkey::Pubkey;
+    use std::sync::Arc;
+
+    const ACCOUNTS: usize = 16;
+    const LAMPORTS: u64 = 1_000;
+
+    fn account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &Pubkey::new_unique())
+    }
+
+    fn total(scan: impl Iterator<Item = (Pubkey, AccountSharedData)>) -> u64 {
+        scan.map(|(_, account)| account.lamports()).sum()
+    }
+
+    #[test]
+    fn test_scans_see_whole_slots_while_later_slots_commit() {
+        let accounts_versions = Arc::new(AccountsVersions::new());
+        let mut bank = Bank::default();
+        bank.set_accounts_versions(Some(accounts_versions.clone()));
+        let pubkeys: Vec<Pubkey> = (0..ACCOUNTS).map(|_| Pubkey::new_unique()).collect();
+        for pubkey in &pubkeys {
+            bank.store_account(*pubkey, account(LAMPORTS));
+        }
+        bank.freeze();
+        let first_slot = bank.slot();
+        let expected = total(accounts_versions.scan_at_slot(first_slot).unwrap());
+
+        // Every slot moves lamports between the accounts one write at a
+        // time, so only a scan mixing slots sees a different total.
+        let scanner = {
+            let accounts_versions = accounts_versions.clone();
+            std::thread::spawn(move || {
+                let mut scans = 0;
+                while accounts_versions.last_slot() < Some(first_slot + 100) {
+                    let slot = accounts_versions.last_slot().unwrap();
+                    let scan = accounts_versions.scan_at_slot(slot).unwrap();
+                    assert_eq!(total(scan), expected, "slot {slot}");
+                    scans += 1;
+                }
+                scans
+            })
+        };
+        for round in 0..100 {
+            bank.advance_slot(Hash::new_unique());
+            let from = pubkeys[round % ACCOUNTS];
+            let to = pubkeys[(round + 1) % ACCOUNTS];
+            let from_lamports = bank.get_account(&from).unwrap().lamports();
+            let to_lamports = bank.get_account(&to).unwrap().lamports();
+            bank.store_account(from, account(from_lamports - 1));
+            bank.store_account(to, account(to_lamports + 1));
+            bank.freeze();
+        }
+        assert!(scanner.join().unwrap() > 0);
+
+        // Old slots still scan as they were.
+        let first = accounts_versions.scan_at_slot(first_slot).unwrap();
+        assert!(first
+            .filter(|(pubkey, _)| pubkeys.contains(pubkey))
+            .all(|(_, account)| account.lamports() == LAMPORTS));
+    }
+
+    #[test]
+    fn test_scans_see_closed_accounts_until_they_close() {
+        let versions = AccountsVersions::new();
+        let [kept, closed] = [(); 2].map(|()| Pubkey::new_unique());
+        assert_eq!(
+            versions.scan_at_slot(0).err(),
+            Some(ScanError::SlotNotPublished(0))
+        );
+        versions.publish(1, [(kept, Some(account(1))), (closed, Some(account(2)))]);
+        versions.publish(3, [(closed, None), (kept, Some(account(3)))]);
+
+        let pubkeys = |slot| -> Vec<Pubkey> {
+            let scan = versions.scan_at_slot(slot).unwrap();
+            assert_eq!(scan.slot(), slot);
+            scan.map(|(pubkey, _)| pubkey).collect()
+        };
+        let mut both = vec![kept, closed];
+        both.sort();
+        // Slot 2 published nothing and sees what slot 1 left.
+        assert_eq!(pubkeys(1), both);
+        assert_eq!(pubkeys(2), both);
+        assert_eq!(pubkeys(3), [kept]);
+        assert_eq!(total(versions.scan_at_slot(2).unwrap()), 3);
+        assert_eq!(total(versions.scan_at_slot(3).unwrap()), 3);
+        assert_eq!(
+            versions.scan_at_slot(4).err(),
+            Some(ScanError::SlotNotPublished(4))
+        );
+    }
+
+    #[test]
+    fn test_purging_keeps_the_slots_being_scanned() {
+        let versions = AccountsVersions::new();
+        let pubkey = Pubkey::new_unique();
+        for slot in 1..=4 {
+            versions.publish(slot, [(pubkey, Some(account(slot)))]);
+        }
+
+        let scan = versions.scan_at_slot(2).unwrap();
+        versions.purge_older_than(4);
+        assert_eq!(
+            versions.scan_at_slot(1).err(),
+            Some(ScanError::SlotPurged(1))
+        );
+        assert_eq!(total(versions.scan_at_slot(3).unwrap()), 3);
+        assert_eq!(total(scan), 2);
+
+        // Once the scan is done its slot goes on the next purge, and the
+        // newest version a kept slot sees stays.
+        versions.purge_older_than(4);
+        assert_eq!(
+            versions.scan_at_slot(3).err(),
+            Some(ScanError::SlotPurged(3))
+        );
+        assert_eq!(total(versions.scan_at_slot(4).unwrap()), 4);
+    }
+}
@@ -0,0 +1,186 @@
+//! Unit test: Record a worker pool's schedule and replay it
+//!
+//! Analogy: On the night a dish came out wrong, the manager wrote down
+//! which orders went to which cook and which plates came back before the
+//! next orders were called. The next morning the kitchen replays that
+//! exact evening, cook by cook and plate by plate, until the mistake shows
+//! up again.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        scheduler::{
+            BatchLimits, PriorityGraphScheduler, ScheduleEvent, ScheduleLog, ScheduleLogError,
+            WorkAssignment, WorkerPool, WorkerPoolError,
+        },
+        workload::{Workload, WorkloadConfig},
+    };
+    use solana_pubkey::Pubkey;
+    use solana_transaction::sanitized::SanitizedTransaction;
+    use std::{fs, path::PathBuf, thread, time::Duration};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "schedule-log-{name}-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ))
+    }
+
+    fn workload() -> Workload {
+        Workload::generate(&WorkloadConfig {
+            num_transactions: 40,
+            num_payers: 20,
+            hot_account_ratio: 0.5,
+            num_hot_accounts: 3,
+            ..WorkloadConfig::default()
+        })
+    }
+
+    // Every output names the worker thread that executed it.
+    fn pool() -> WorkerPool<String> {
+        WorkerPool::new(3, |_: &SanitizedTransaction| {
+            thread::current().name().unwrap().to_string()
+        })
+        .with_batch_limits(BatchLimits {
+            max_txs: 5,
+            ..BatchLimits::default()
+        })
+    }
+
+    // The worker thread the log assigned to each position.
+    fn recorded_workers(log: &ScheduleLog, num_transactions: usize) -> Vec<String> {
+        let mut workers = vec![String::new(); num_transactions];
+        for assignment in log.batches().flatten() {
+            for &position in &assignment.positions {
+                workers[position] = format!("svmWorker{:02}", assignment.worker);
+            }
+        }
+        workers
+    }
+
+    #[test]
+    fn test_schedule_log_round_trips_through_a_file() {
+        let log = ScheduleLog {
+            num_workers: 2,
+            events: vec![
+                ScheduleEvent::Batch(vec![
+                    WorkAssignment {
+                        worker: 1,
+                        positions: vec![0, 2],
+                    },
+                    WorkAssignment {
+                        worker: 0,
+                        positions: vec![1],
+                    },
+                ]),
+                ScheduleEvent::Completed(vec![1]),
+                ScheduleEvent::Completed(vec![0, 2]),
+            ],
+        };
+        let path = temp_path("round-trip");
+        log.save(&path).unwrap();
+        assert_eq!(ScheduleLog::load(&path).unwrap(), log);
+
+        fs::write(
+            &path,
+            "{\"version\":1,\"numWorkers\":2}\n{\"batch\":[{\"worker\":0}]}\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            ScheduleLog::load(&path),
+            Err(ScheduleLogError::Malformed(2))
+        ));
+        fs::write(&path, "{\"version\":9,\"numWorkers\":2}\n").unwrap();
+        assert!(matches!(
+            ScheduleLog::load(&path),
+            Err(ScheduleLogError::UnsupportedVersion(9))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_forces_the_recorded_workers() {
+        let workload = workload();
+        let num_transactions = workload.transactions.len();
+        let (outputs, log) = pool()
+            .run_recorded(
+                &mut PriorityGraphScheduler::new(),
+                workload.transactions.clone(),
+            )
+            .unwrap();
+        let workers = recorded_workers(&log, num_transactions);
+        assert_eq!(outputs, workers);
+        assert_eq!(log.num_workers, 3);
+        // Every part of every batch completes exactly once.
+        let num_assignments = log.batches().map(<[_]>::len).sum::<usize>();
+        let num_completed = log
+            .events
+            .iter()
+            .filter(|event| matches!(event, ScheduleEvent::Completed(_)))
+            .count();
+        assert_eq!(num_completed, num_assignments);
+
+        let path = temp_path("replay");
+        log.save(&path).unwrap();
+        let log = ScheduleLog::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        // Jitter changes the timing, but not the schedule.
+        let pool = pool().with_jitter(Duration::from_micros(300));
+        for _ in 0..3 {
+            let replayed = pool.replay(&log, workload.transactions.clone()).unwrap();
+            assert_eq!(
+                replayed,
+                workers.iter().cloned().map(Some).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_replay_rejects_logs_that_do_not_match() {
+        let workload = workload();
+        let pool = pool();
+        let log = ScheduleLog::new(4);
+        assert_eq!(
+            pool.replay(&log, workload.transactions.clone()),
+            Err(WorkerPoolError::ScheduleMismatch(
+                "the log was recorded with more workers"
+            ))
+        );
+
+        let log = ScheduleLog {
+            num_workers: 1,
+            events: vec![ScheduleEvent::Batch(vec![WorkAssignment {
+                worker: 0,
+                positions: vec![workload.transactions.len()],
+            }])],
+        };
+        assert_eq!(
+            pool.replay(&log, workload.transactions.clone()),
+            Err(WorkerPoolError::ScheduleMismatch(
+                "the log names a missing transaction"
+            ))
+        );
+
+        // Checked up front, so the batch before the mismatch never runs.
+        let log = ScheduleLog {
+            num_workers: 1,
+            events: vec![
+                ScheduleEvent::Batch(vec![WorkAssignment {
+                    worker: 0,
+                    positions: vec![0],
+                }]),
+                ScheduleEvent::Completed(vec![1]),
+            ],
+        };
+        assert_eq!(
+            pool.replay(&log, workload.transactions.clone()),
+            Err(WorkerPoolError::ScheduleMismatch(
+                "the log completes work that is not in flight"
+            ))
+        );
+        let empty = ScheduleLog::new(1);
+        let replayed = pool.replay(&empty, workload.transactions).unwrap();
+        assert!(replayed.iter().all(Option::is_none));
+    }
+}
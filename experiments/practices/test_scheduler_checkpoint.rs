@@ -0,0 +1,109 @@
+//! Unit test: Checkpoint the priority graph scheduler mid-slot
+//!
+//! Analogy: Halfway through the evening the manager photographs the
+//! host's seating chart: who is eating, who can be seated as soon as a
+//! table is free, and who is waiting on which party to leave. The photo
+//! shows that the third party waits on the second, which waits on the
+//! first, without stopping the host to ask.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{BatchLimits, PriorityGraphScheduler, TransactionScheduler},
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(
+        writable: &[Pubkey],
+        readonly: &[Pubkey],
+        cu_price: u64,
+    ) -> SanitizedTransaction {
+        let accounts = writable
+            .iter()
+            .map(|key| AccountMeta::new(*key, false))
+            .chain(
+                readonly
+                    .iter()
+                    .map(|key| AccountMeta::new_readonly(*key, false)),
+            )
+            .collect();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_checkpoint_shows_blocked_chains() {
+        let [x, y] = [(); 2].map(|()| Pubkey::new_unique());
+        let mut scheduler = PriorityGraphScheduler::new();
+        let a = scheduler.push(transaction(&[x], &[], 3));
+        let b = scheduler.push(transaction(&[x, y], &[], 2));
+        let c = scheduler.push(transaction(&[y], &[], 1));
+
+        // Nothing is in the graph before the first batch.
+        let checkpoint = scheduler.checkpoint();
+        assert_eq!(checkpoint.pending, [a, b, c]);
+        assert!(checkpoint.edges.is_empty());
+
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, [a]);
+        let checkpoint = scheduler.checkpoint();
+        assert!(checkpoint.pending.is_empty());
+        assert!(checkpoint.unblocked.is_empty());
+        assert_eq!(checkpoint.in_flight, [a]);
+        assert_eq!(checkpoint.blocked_behind(c), [b, a]);
+        assert!(checkpoint.is_blocked_behind(c, a));
+        assert!(!checkpoint.is_blocked_behind(a, c));
+        assert_eq!(
+            checkpoint.edges.into_iter().collect::<Vec<_>>(),
+            [((a, b), vec![x]), ((b, c), vec![y])]
+        );
+
+        // Completing `a` unblocks `b`, which waits for the next batch.
+        scheduler.complete(&batch.ids);
+        let checkpoint = scheduler.checkpoint();
+        assert!(checkpoint.in_flight.is_empty());
+        assert_eq!(checkpoint.unblocked, [b]);
+        assert!(!checkpoint.is_blocked(b));
+        assert_eq!(checkpoint.blocked_behind(c), [b]);
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, [b]);
+        assert_eq!(scheduler.checkpoint().in_flight, [b]);
+    }
+
+    #[test]
+    fn test_writer_waits_on_every_reader() {
+        let x = Pubkey::new_unique();
+        let mut scheduler = PriorityGraphScheduler::new();
+        let readers = [3, 2].map(|cu_price| scheduler.push(transaction(&[], &[x], cu_price)));
+        let writer = scheduler.push(transaction(&[x], &[], 1));
+        let free = scheduler.push(transaction(&[Pubkey::new_unique()], &[], 0));
+
+        let limits = BatchLimits {
+            max_txs: 1,
+            ..BatchLimits::default()
+        };
+        assert_eq!(scheduler.next_batch(&limits).ids, [readers[0]]);
+        let checkpoint = scheduler.checkpoint();
+        assert_eq!(checkpoint.in_flight, [readers[0]]);
+        // Popped but out of room, highest priority first.
+        assert_eq!(checkpoint.unblocked, [readers[1], free]);
+        assert_eq!(checkpoint.blocked[&writer], readers);
+        assert!(checkpoint.is_blocked_behind(writer, readers[1]));
+
+        let json = checkpoint.to_json();
+        assert_eq!(json["inFlight"], serde_json::json!([readers[0]]));
+        assert_eq!(
+            json["blocked"][writer.to_string()],
+            serde_json::json!(readers)
+        );
+        assert_eq!(json["edges"].as_array().unwrap().len(), 2);
+        assert_eq!(json["edges"][0]["accounts"][0], x.to_string());
+    }
+}
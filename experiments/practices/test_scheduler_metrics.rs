@@ -0,0 +1,140 @@
+//! Unit test: Measure how well transactions parallelize
+//!
+//! Analogy: At closing time the host tallies the evening: how many parties
+//! came in, how many seatings it took to serve them, how many parties sat
+//! together on average, and how many had to wait at the bar because the
+//! table they wanted was taken.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::scheduler::{
+        BatchLimits, PriorityGraphScheduler, Scheduler, SchedulerMetrics, TransactionScheduler,
+    };
+    use priority_graph_practice::system_program;
+    use solana_account::AccountSharedData;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn sanitized(instruction: Instruction, payer: &Pubkey) -> SanitizedTransaction {
+        let message = Message::new_with_blockhash(&[instruction], Some(payer), &Hash::default());
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    // Build a transaction that writes `account`.
+    fn transaction(account: Pubkey) -> SanitizedTransaction {
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(account, false)],
+        );
+        sanitized(instruction, &Pubkey::new_unique())
+    }
+
+    #[test]
+    fn test_schedule_metrics() {
+        let hot = Pubkey::new_unique();
+        let transactions = vec![
+            transaction(hot),
+            transaction(hot),
+            transaction(Pubkey::new_unique()),
+        ];
+        let mut scheduler = PriorityGraphScheduler::new();
+        assert_eq!(scheduler.metrics().average_batch_width(), 0.0);
+        assert_eq!(scheduler.schedule(&transactions).len(), 2);
+
+        let metrics = *scheduler.metrics();
+        assert_eq!(
+            metrics,
+            SchedulerMetrics {
+                transactions_queued: 3,
+                transactions_scheduled: 3,
+                batches_emitted: 2,
+                transactions_blocked: 1,
+                ..SchedulerMetrics::default()
+            }
+        );
+        assert_eq!(metrics.average_batch_width(), 1.5);
+        assert!((metrics.blocked_percentage() - 100.0 / 3.0).abs() < 1e-9);
+
+        assert_eq!(scheduler.take_metrics(), metrics);
+        assert_eq!(*scheduler.metrics(), SchedulerMetrics::default());
+        scheduler.schedule(&transactions[2..]);
+        assert_eq!(scheduler.metrics().blocked_percentage(), 0.0);
+    }
+
+    #[test]
+    fn test_streaming_metrics() {
+        let hot = Pubkey::new_unique();
+        let mut scheduler = PriorityGraphScheduler::new();
+        for account in [hot, hot, Pubkey::new_unique()] {
+            scheduler.push(transaction(account));
+        }
+        let limits = BatchLimits::default();
+        let first = scheduler.next_batch(&limits);
+        assert_eq!(first.ids, vec![0, 2]);
+        // Waiting on the first batch emits nothing.
+        assert!(scheduler.next_batch(&limits).is_empty());
+        scheduler.complete(&first.ids);
+        assert_eq!(scheduler.next_batch(&limits).ids, vec![1]);
+
+        assert_eq!(
+            *scheduler.metrics(),
+            SchedulerMetrics {
+                transactions_queued: 3,
+                transactions_scheduled: 3,
+                batches_emitted: 2,
+                transactions_blocked: 1,
+                ..SchedulerMetrics::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_bank_metrics_per_slot() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        for payer in [alice, bob] {
+            bank.store_account(
+                payer,
+                AccountSharedData::new(1_000_000_000, 0, &system_program_id::id()),
+            );
+        }
+        // The genesis blockhash stays valid across the slots below.
+        let transfer = |from: &Pubkey, lamports| {
+            sanitized(
+                system_program::transfer(from, &Pubkey::new_unique(), lamports),
+                from,
+            )
+        };
+
+        // Alice's two transfers conflict on her account.
+        let slot_0 = vec![
+            transfer(&alice, 1_000_000),
+            transfer(&alice, 2_000_000),
+            transfer(&bob, 1_000_000),
+        ];
+        bank.process_transaction_batch(&slot_0);
+        bank.process_transaction_batch(&[transfer(&bob, 3_000_000)]);
+        let metrics = *bank.scheduler_metrics(0).unwrap();
+        assert_eq!(metrics.transactions_queued, 4);
+        assert_eq!(metrics.batches_emitted, 3);
+        assert_eq!(metrics.transactions_blocked, 1);
+        assert_eq!(metrics.blocked_percentage(), 25.0);
+
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(bank.scheduler_metrics(1), None);
+        let slot_1 = vec![transfer(&alice, 1_000_000), transfer(&bob, 1_000_000)];
+        bank.process_transaction_batch(&slot_1);
+        assert_eq!(
+            bank.scheduler_metrics(1).unwrap().average_batch_width(),
+            2.0
+        );
+        assert_eq!(bank.scheduler_metrics(0), Some(&metrics));
+    }
+}
@@ -0,0 +1,237 @@
+//! Unit test: Scheduler invariants over arbitrary transactions
+//!
+//! Analogy: Rather than checking a few hand-picked evenings, the inspector
+//! makes up thousands of random ones: parties of every size asking for
+//! every mix of tables, tipping whatever they like, finishing their meals
+//! in any order. On every one of those evenings no two parties may share a
+//! table, every party must be seated eventually, and the host must seat
+//! the best-tipping party among those whose tables are free.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            BatchLimits, PriorityGraphScheduler, PriorityPolicy, TransactionId,
+            TransactionPriorityId, TransactionScheduler,
+        },
+    };
+    use proptest::{collection::vec, prelude::*};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::collections::BTreeSet;
+
+    const NUM_ACCOUNTS: usize = 6;
+
+    /// Accounts of a transaction, by index and whether it writes them, and
+    /// its compute unit price.
+    type TransactionSpec = (Vec<(usize, bool)>, u64);
+
+    fn transaction_specs() -> impl Strategy<Value = Vec<TransactionSpec>> {
+        vec(
+            (vec((0..NUM_ACCOUNTS, any::<bool>()), 1..4), 0..4u64),
+            1..40,
+        )
+    }
+
+    fn transaction(
+        accounts: &[Pubkey],
+        program_id: Pubkey,
+        spec: &TransactionSpec,
+    ) -> SanitizedTransaction {
+        let (metas, compute_unit_price) = spec;
+        let metas = metas
+            .iter()
+            .map(|&(index, is_writable)| {
+                if is_writable {
+                    AccountMeta::new(accounts[index], false)
+                } else {
+                    AccountMeta::new_readonly(accounts[index], false)
+                }
+            })
+            .collect();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(*compute_unit_price),
+            Instruction::new_with_bytes(program_id, &[], metas),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    /// Whether one transaction writes an account the other one uses.
+    fn conflicts(a: &SanitizedTransaction, b: &SanitizedTransaction) -> bool {
+        let writes = |writer: &SanitizedTransaction, other: &SanitizedTransaction| {
+            let message = writer.message();
+            message
+                .account_keys()
+                .iter()
+                .enumerate()
+                .any(|(index, key)| {
+                    message.is_writable(index)
+                        && other
+                            .message()
+                            .account_keys()
+                            .iter()
+                            .any(|other| other == key)
+                })
+        };
+        writes(a, b) || writes(b, a)
+    }
+
+    /// A batch the scheduler handed out and what was queued and in flight
+    /// right after.
+    struct Step {
+        batch: Vec<TransactionId>,
+        in_flight: Vec<TransactionId>,
+        remaining: BTreeSet<TransactionId>,
+    }
+
+    struct Run {
+        transactions: Vec<SanitizedTransaction>,
+        priority_ids: Vec<TransactionPriorityId>,
+        max_txs: usize,
+        steps: Vec<Step>,
+        num_pending: usize,
+    }
+
+    impl Run {
+        /// Whether nothing still queued before `id`, or in flight, blocks it.
+        fn is_unblocked(&self, step: &Step, id: TransactionId) -> bool {
+            let transaction = &self.transactions[id];
+            let blocks = |other: &TransactionId| {
+                *other != id && conflicts(transaction, &self.transactions[*other])
+            };
+            !step.in_flight.iter().any(blocks)
+                && !step
+                    .remaining
+                    .iter()
+                    .filter(|&&other| self.priority_ids[other] > self.priority_ids[id])
+                    .any(blocks)
+        }
+    }
+
+    /// Pushes every transaction, then hands out batches of at most `max_txs`
+    /// until nothing is left, completing one in-flight transaction per
+    /// batch, picked by `completion_picks`.
+    fn run(specs: &[TransactionSpec], max_txs: usize, completion_picks: &[usize]) -> Run {
+        let accounts: Vec<_> = (0..NUM_ACCOUNTS).map(|_| Pubkey::new_unique()).collect();
+        let program_id = Pubkey::new_unique();
+        let transactions: Vec<_> = specs
+            .iter()
+            .map(|spec| transaction(&accounts, program_id, spec))
+            .collect();
+        let priority_ids = transactions
+            .iter()
+            .enumerate()
+            .map(|(id, transaction)| PriorityPolicy::default().priority_id(transaction, id))
+            .collect();
+
+        let mut scheduler = PriorityGraphScheduler::new();
+        for transaction in &transactions {
+            scheduler.push(transaction.clone());
+        }
+        let limits = BatchLimits {
+            max_txs,
+            ..BatchLimits::default()
+        };
+        let mut remaining: BTreeSet<_> = (0..transactions.len()).collect();
+        let mut in_flight = Vec::new();
+        let mut completion_picks = completion_picks.iter().copied();
+        let mut steps = Vec::new();
+        // Every step but the last hands out or completes a transaction, so
+        // this only cuts off a scheduler that stalls.
+        for _ in 0..2 * transactions.len() + 1 {
+            let batch = scheduler.next_batch(&limits).ids;
+            for id in &batch {
+                remaining.remove(id);
+            }
+            in_flight.extend(&batch);
+            steps.push(Step {
+                batch,
+                in_flight: in_flight.clone(),
+                remaining: remaining.clone(),
+            });
+            if in_flight.is_empty() {
+                break;
+            }
+            let pick = completion_picks.next().unwrap_or(0) % in_flight.len();
+            scheduler.complete(&[in_flight.swap_remove(pick)]);
+        }
+        Run {
+            transactions,
+            priority_ids,
+            max_txs,
+            steps,
+            num_pending: scheduler.num_pending(),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_in_flight_transactions_never_conflict(
+            specs in transaction_specs(),
+            max_txs in 1..6usize,
+            completion_picks in vec(any::<usize>(), 0..80),
+        ) {
+            let run = run(&specs, max_txs, &completion_picks);
+            for step in &run.steps {
+                for (index, &a) in step.in_flight.iter().enumerate() {
+                    for &b in &step.in_flight[index + 1..] {
+                        prop_assert!(
+                            !conflicts(&run.transactions[a], &run.transactions[b]),
+                            "transactions {} and {} conflict while in flight", a, b
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn test_every_transaction_is_handed_out_once(
+            specs in transaction_specs(),
+            max_txs in 1..6usize,
+            completion_picks in vec(any::<usize>(), 0..80),
+        ) {
+            let run = run(&specs, max_txs, &completion_picks);
+            let handed_out: Vec<_> =
+                run.steps.iter().flat_map(|step| &step.batch).copied().collect();
+            let unique: BTreeSet<_> = handed_out.iter().copied().collect();
+            prop_assert_eq!(handed_out.len(), unique.len());
+            prop_assert_eq!(unique, (0..run.transactions.len()).collect::<BTreeSet<_>>());
+            prop_assert_eq!(run.num_pending, 0);
+            prop_assert!(run.steps.last().unwrap().in_flight.is_empty());
+        }
+
+        #[test]
+        fn test_batches_take_the_best_unblocked_transactions(
+            specs in transaction_specs(),
+            max_txs in 1..6usize,
+            completion_picks in vec(any::<usize>(), 0..80),
+        ) {
+            let run = run(&specs, max_txs, &completion_picks);
+            for step in &run.steps {
+                let priorities: Vec<_> =
+                    step.batch.iter().map(|&id| run.priority_ids[id]).collect();
+                prop_assert!(priorities.windows(2).all(|pair| pair[0] > pair[1]));
+                let ready: Vec<_> = step
+                    .remaining
+                    .iter()
+                    .copied()
+                    .filter(|&id| run.is_unblocked(step, id))
+                    .collect();
+                if let (Some(&lowest), false) = (priorities.last(), ready.is_empty()) {
+                    // Only a full batch leaves unblocked transactions
+                    // behind, and only ones it outranks.
+                    prop_assert_eq!(step.batch.len(), run.max_txs);
+                    for &id in &ready {
+                        prop_assert!(run.priority_ids[id] < lowest, "{} outranks the batch", id);
+                    }
+                } else {
+                    prop_assert!(ready.is_empty(), "{:?} unblocked but not handed out", ready);
+                }
+            }
+        }
+    }
+}
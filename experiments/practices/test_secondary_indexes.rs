@@ -0,0 +1,150 @@
+//! Unit test: Find accounts by owner and token accounts by mint or owner
+//!
+//! Analogy: Besides the ledger sorted by table number, the host keeps a
+//! list per waiter of the tables they serve and a list per dish of the
+//! tables that ordered it. Every change to an order updates the lists
+//! too, so "which tables ordered the soup" is answered without walking
+//! the whole room.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        accounts_db::AccountsDb,
+        token_program::{self, TokenAccount},
+    };
+    use solana_account::{AccountSharedData, WritableAccount};
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+
+    fn keys(accounts: Vec<(Pubkey, AccountSharedData)>) -> Vec<Pubkey> {
+        accounts.into_iter().map(|(pubkey, _)| pubkey).collect()
+    }
+
+    fn sorted<const N: usize>(mut pubkeys: [Pubkey; N]) -> Vec<Pubkey> {
+        pubkeys.sort();
+        pubkeys.to_vec()
+    }
+
+    fn token_account(mint: &Pubkey, owner: &Pubkey) -> AccountSharedData {
+        token_program::token_account(&TokenAccount::new(*mint, *owner, 1), &Rent::default())
+    }
+
+    #[test]
+    fn test_program_accounts_follow_writes() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+        let [first, second, third] = [(); 3].map(|()| Pubkey::new_unique());
+        let mut accounts_db = AccountsDb::new();
+        for pubkey in [first, second, third] {
+            accounts_db.store_account(pubkey, AccountSharedData::new(1, 0, &program_id));
+        }
+        assert_eq!(
+            keys(accounts_db.get_program_accounts(&program_id)),
+            sorted([first, second, third])
+        );
+
+        // Reassigning moves the account to its new owner's entry, and
+        // closing drops it.
+        let mut reassigned = accounts_db.get_account(&second).unwrap();
+        reassigned.set_owner(other_program_id);
+        accounts_db.store_account(second, reassigned);
+        accounts_db.store_account(third, AccountSharedData::new(0, 0, &program_id));
+        assert_eq!(keys(accounts_db.get_program_accounts(&program_id)), [first]);
+        assert_eq!(
+            keys(accounts_db.get_program_accounts(&other_program_id)),
+            [second]
+        );
+        assert!(accounts_db
+            .get_program_accounts(&Pubkey::new_unique())
+            .is_empty());
+
+        // Scans return the stored state.
+        let mut updated = AccountSharedData::new(7, 0, &program_id);
+        updated.set_data_from_slice(&[1, 2, 3]);
+        accounts_db.store_account(first, updated.clone());
+        assert_eq!(
+            accounts_db.get_program_accounts(&program_id),
+            [(first, updated)]
+        );
+    }
+
+    #[test]
+    fn test_token_accounts_by_mint_and_owner() {
+        let [mint, other_mint, wallet, other_wallet] = [(); 4].map(|()| Pubkey::new_unique());
+        let [first, second, third] = [(); 3].map(|()| Pubkey::new_unique());
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(first, token_account(&mint, &wallet));
+        accounts_db.store_account(second, token_account(&mint, &other_wallet));
+        accounts_db.store_account(third, token_account(&other_mint, &wallet));
+        // The mint itself is not a token account.
+        let mint_account = token_program::mint_account(
+            &token_program::Mint {
+                mint_authority: Some(wallet),
+                supply: 0,
+                decimals: 0,
+                is_initialized: true,
+                freeze_authority: None,
+            },
+            &Rent::default(),
+        );
+        accounts_db.store_account(mint, mint_account);
+
+        assert_eq!(
+            keys(accounts_db.get_token_accounts_by_mint(&mint)),
+            sorted([first, second])
+        );
+        assert_eq!(
+            keys(accounts_db.get_token_accounts_by_owner(&wallet)),
+            sorted([first, third])
+        );
+        assert_eq!(
+            keys(accounts_db.get_program_accounts(&token_program::ID)),
+            sorted([mint, first, second, third])
+        );
+
+        // Handing the tokens to another owner re-indexes the account.
+        accounts_db.store_account(first, token_account(&mint, &other_wallet));
+        assert_eq!(
+            keys(accounts_db.get_token_accounts_by_owner(&wallet)),
+            [third]
+        );
+        assert_eq!(
+            keys(accounts_db.get_token_accounts_by_owner(&other_wallet)),
+            sorted([first, second])
+        );
+        assert_eq!(
+            keys(accounts_db.get_token_accounts_by_mint(&mint)),
+            sorted([first, second])
+        );
+    }
+
+    #[test]
+    fn test_indexes_survive_rollbacks_and_snapshot_files() {
+        let program_id = Pubkey::new_unique();
+        let [kept, added] = [(); 2].map(|()| Pubkey::new_unique());
+        let mut accounts_db = AccountsDb::new();
+        accounts_db.store_account(kept, AccountSharedData::new(1, 0, &program_id));
+
+        let snapshot = accounts_db.snapshot();
+        accounts_db.store_account(added, AccountSharedData::new(1, 0, &program_id));
+        accounts_db.store_account(kept, AccountSharedData::new(0, 0, &program_id));
+        assert_eq!(keys(accounts_db.get_program_accounts(&program_id)), [added]);
+        accounts_db.rollback(snapshot);
+        assert_eq!(keys(accounts_db.get_program_accounts(&program_id)), [kept]);
+
+        let path = std::env::temp_dir().join(format!(
+            "secondary-indexes-{}-{}",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        accounts_db.store_account(added, token_account(&program_id, &program_id));
+        accounts_db.snapshot_to_file(&path).unwrap();
+        let loaded = AccountsDb::load_from_snapshot(&path).unwrap();
+        assert_eq!(keys(loaded.get_program_accounts(&program_id)), [kept]);
+        assert_eq!(
+            keys(loaded.get_token_accounts_by_mint(&program_id)),
+            [added]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
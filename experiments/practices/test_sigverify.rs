@@ -0,0 +1,182 @@
+//! Unit test: Verify transaction signatures before scheduling
+//!
+//! Analogy: The doorman checks every guest's invitation against the guest
+//! list. On a busy night he checks a whole group at a glance and only looks
+//! at each card in turn when something in the group seems off. Forged
+//! invitations stay outside; on rehearsal nights nobody checks at all. On
+//! gala nights the whole queue's cards go to the scanner at the door in one
+//! stack, and whichever scanner is used, the same guests get in.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::sigverify::{
+        filter_verified, verify_transaction, verify_transactions, verify_transactions_with,
+        CpuVerifier, SigVerifyConfig, SignatureData, SignatureVerifier, VERIFY_CHUNK_SIZE,
+    };
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_signature::Signature;
+    use solana_signer::Signer;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::sync::Mutex;
+
+    // A transaction signed by the fee payer and a second signer.
+    fn signed_transaction() -> Transaction {
+        let payer = Keypair::new();
+        let cosigner = Keypair::new();
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1, 2, 3],
+            vec![AccountMeta::new_readonly(cosigner.pubkey(), true)],
+        );
+        let message = Message::new(&[instruction], Some(&payer.pubkey()));
+        Transaction::new(&[&payer, &cosigner], message, Hash::new_unique())
+    }
+
+    fn sanitized(transaction: Transaction) -> SanitizedTransaction {
+        SanitizedTransaction::from_transaction_for_tests(transaction)
+    }
+
+    // A signed transaction whose second signature was swapped for garbage.
+    fn forged_transaction() -> Transaction {
+        let mut transaction = signed_transaction();
+        transaction.signatures[1] = Signature::from([7; 64]);
+        transaction
+    }
+
+    #[test]
+    fn test_verify_single_transaction() {
+        assert_eq!(verify_transaction(&sanitized(signed_transaction())), Ok(()));
+        assert_eq!(
+            verify_transaction(&sanitized(forged_transaction())),
+            Err(TransactionError::SignatureFailure)
+        );
+
+        // Changing the message invalidates every signature.
+        let mut tampered = signed_transaction();
+        tampered.message.instructions[0].data = vec![3, 2, 1];
+        assert_eq!(
+            verify_transaction(&sanitized(tampered)),
+            Err(TransactionError::SignatureFailure)
+        );
+    }
+
+    #[test]
+    fn test_batch_verification_finds_invalid_transactions() {
+        // Several chunks, with forgeries in some of them only.
+        let forged_indexes = [3, VERIFY_CHUNK_SIZE + 1, 2 * VERIFY_CHUNK_SIZE + 5];
+        let transactions: Vec<_> = (0..3 * VERIFY_CHUNK_SIZE)
+            .map(|index| {
+                if forged_indexes.contains(&index) {
+                    sanitized(forged_transaction())
+                } else {
+                    sanitized(signed_transaction())
+                }
+            })
+            .collect();
+
+        let verified = verify_transactions(&transactions, &SigVerifyConfig::default());
+        assert_eq!(verified.len(), transactions.len());
+        for (index, verified) in verified.iter().enumerate() {
+            assert_eq!(*verified, !forged_indexes.contains(&index), "{index}");
+        }
+        assert!(verify_transactions(&[], &SigVerifyConfig::default()).is_empty());
+    }
+
+    /// Stands in for a GPU verifier: records the size of every batch it
+    /// gets and rejects the signatures of one signer.
+    struct RecordingVerifier {
+        batch_sizes: Mutex<Vec<usize>>,
+        rejected_signer: Pubkey,
+    }
+
+    impl SignatureVerifier for RecordingVerifier {
+        fn verify_signatures(&self, signatures: &[SignatureData<'_>]) -> Vec<bool> {
+            self.batch_sizes.lock().unwrap().push(signatures.len());
+            let mut verified = CpuVerifier.verify_signatures(signatures);
+            for (verified, (_, signer, _)) in verified.iter_mut().zip(signatures) {
+                *verified &= *signer != self.rejected_signer.as_ref();
+            }
+            verified
+        }
+    }
+
+    #[test]
+    fn test_external_verifier_gets_the_whole_batch() {
+        let transactions: Vec<_> = (0..3).map(|_| signed_transaction()).collect();
+        // The cosigner signs second, so only one of a transaction's
+        // signatures is rejected.
+        let rejected_signer = transactions[1].message.account_keys[1];
+        let verifier = RecordingVerifier {
+            batch_sizes: Mutex::default(),
+            rejected_signer,
+        };
+        let transactions: Vec<_> = transactions.into_iter().map(sanitized).collect();
+        assert_eq!(
+            verify_transactions_with(&transactions, &SigVerifyConfig::default(), &verifier),
+            [true, false, true]
+        );
+        assert_eq!(*verifier.batch_sizes.lock().unwrap(), [6]);
+    }
+
+    #[cfg(feature = "sigverify-simd")]
+    #[test]
+    fn test_simd_verifier_agrees_with_cpu_verifier() {
+        use priority_graph_practice::sigverify::SimdVerifier;
+
+        let transactions: Vec<_> = (0..2 * VERIFY_CHUNK_SIZE + 3)
+            .map(|index| {
+                if index % 50 == 7 {
+                    sanitized(forged_transaction())
+                } else {
+                    sanitized(signed_transaction())
+                }
+            })
+            .collect();
+        let config = SigVerifyConfig::default();
+        let cpu = verify_transactions_with(&transactions, &config, &CpuVerifier);
+        assert_eq!(cpu.iter().filter(|verified| !**verified).count(), 3);
+        assert_eq!(
+            verify_transactions_with(&transactions, &config, &SimdVerifier),
+            cpu
+        );
+
+        // A public key that is not a point fails only its own signature.
+        let signature = Signature::from([1; 64]);
+        let valid = signed_transaction();
+        let message_data = valid.message.serialize();
+        let signatures = [
+            (&signature, [0xff; 32].as_slice(), b"".as_slice()),
+            (
+                &valid.signatures[0],
+                valid.message.account_keys[0].as_ref(),
+                message_data.as_slice(),
+            ),
+        ];
+        assert_eq!(SimdVerifier.verify_signatures(&signatures), [false, true]);
+    }
+
+    #[test]
+    fn test_filter_and_skip_sigverify() {
+        let valid = sanitized(signed_transaction());
+        let transactions = vec![
+            sanitized(forged_transaction()),
+            valid.clone(),
+            sanitized(Transaction::new_unsigned(signed_transaction().message)),
+        ];
+
+        let kept = filter_verified(transactions.clone(), &SigVerifyConfig::default());
+        assert_eq!(kept, vec![valid]);
+
+        let skip = SigVerifyConfig {
+            skip_sigverify: true,
+            ..SigVerifyConfig::default()
+        };
+        assert_eq!(verify_transactions(&transactions, &skip), vec![true; 3]);
+        assert_eq!(filter_verified(transactions.clone(), &skip), transactions);
+    }
+}
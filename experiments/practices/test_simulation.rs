@@ -0,0 +1,180 @@
+//! Unit test: Simulate transactions against a bank without committing
+//!
+//! Analogy: Before an order goes in, a guest can ask the waiter what it
+//! would cost and what would land on the table. The kitchen walks through
+//! the recipe on paper, with whatever pantry substitutions the guest
+//! suggests, but no ingredient leaves the shelf and no bill is written.
+
+#[path = "test_support/sbf_elf.rs"]
+mod sbf_elf;
+
+#[cfg(test)]
+mod tests {
+    use crate::sbf_elf::elf;
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::svm::{AccountOverrides, TransactionReturnData};
+    use priority_graph_practice::system_program;
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sbpf::ebpf::hash_symbol_name;
+    use solana_sdk_ids::{bpf_loader, system_program as system_program_id};
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = opc;
+        bytes[1] = src << 4 | dst;
+        bytes[2..4].copy_from_slice(&off.to_le_bytes());
+        bytes[4..].copy_from_slice(&imm.to_le_bytes());
+        bytes
+    }
+
+    /// Sets the instruction data as return data, and returns success.
+    fn set_return_data_program() -> Vec<[u8; 8]> {
+        vec![
+            insn(0xbf, 1, 2, 0, 0),  // mov64 r1, r2
+            insn(0x79, 2, 1, -8, 0), // ldxdw r2, [r1 - 8]
+            insn(
+                0x85,
+                0,
+                0,
+                0,
+                hash_symbol_name(b"sol_set_return_data") as i32,
+            ), // call
+            insn(0xb7, 0, 0, 0, 0),  // mov64 r0, 0
+            insn(0x95, 0, 0, 0, 0),  // exit
+        ]
+    }
+
+    fn transaction(instruction: Instruction, payer: &Pubkey) -> SanitizedTransaction {
+        let message = Message::new(&[instruction], Some(payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn system_account(lamports: u64) -> AccountSharedData {
+        AccountSharedData::new(lamports, 0, &system_program_id::id())
+    }
+
+    #[test]
+    fn test_simulation_commits_nothing() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.store_account(alice, system_account(1_000_000_000));
+        let transfer = transaction(system_program::transfer(&alice, &bob, 5_000_000), &alice);
+
+        let simulation = bank.simulate_transaction(&transfer, &AccountOverrides::new());
+        assert_eq!(simulation.status, Ok(()));
+        assert_eq!(simulation.fee_details.total_fee(), 5_000);
+        let balances: Vec<_> = simulation
+            .post_accounts
+            .iter()
+            .map(|(pubkey, account)| (*pubkey, account.lamports()))
+            .collect();
+        assert_eq!(balances, vec![(alice, 994_995_000), (bob, 5_000_000)]);
+        assert_eq!(
+            simulation.log_messages.last().unwrap(),
+            &format!("Program {} success", system_program_id::id())
+        );
+        assert!(simulation.units_consumed > 0);
+
+        // Simulating again sees the same bank and gets the same answer.
+        assert_eq!(bank.get_account(&bob), None);
+        assert_eq!(bank.collected_fees(), 0);
+        assert_eq!(
+            bank.simulate_transaction(&transfer, &AccountOverrides::new()),
+            simulation
+        );
+
+        // Processing the transaction lands what the simulation predicted.
+        let result = bank
+            .process_transaction_batch(&[transfer])
+            .remove(0)
+            .unwrap();
+        assert_eq!(result.status, Ok(()));
+        for (pubkey, account) in &simulation.post_accounts {
+            assert_eq!(bank.get_account(pubkey).as_ref(), Some(account));
+        }
+    }
+
+    #[test]
+    fn test_simulation_with_overrides_and_failures() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let bank = Bank::default();
+        let transfer = transaction(system_program::transfer(&alice, &bob, 5_000_000), &alice);
+
+        // Alice only exists in the overrides.
+        let empty = AccountOverrides::new();
+        assert_eq!(
+            bank.simulate_transaction(&transfer, &empty).status,
+            Err(TransactionError::AccountNotFound)
+        );
+        let funded: AccountOverrides = [(alice, system_account(1_000_000))].into_iter().collect();
+        let simulation = bank.simulate_transaction(&transfer, &funded);
+        assert_eq!(
+            simulation.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1)
+            ))
+        );
+        // A failed transaction still pays its fee.
+        assert_eq!(simulation.post_accounts[0].1.lamports(), 995_000);
+        assert_eq!(simulation.post_accounts[1].1.lamports(), 0);
+
+        let mut rich = funded.clone();
+        rich.set_account(alice, system_account(1_000_000_000));
+        assert_eq!(bank.simulate_transaction(&transfer, &rich).status, Ok(()));
+
+        // The age check still applies.
+        let mut message = Message::new(&[system_program::transfer(&alice, &bob, 1)], Some(&alice));
+        message.recent_blockhash = Hash::new_unique();
+        let stale =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let simulation = bank.simulate_transaction(&stale, &rich);
+        assert_eq!(simulation.status, Err(TransactionError::BlockhashNotFound));
+        assert!(simulation.post_accounts.is_empty());
+    }
+
+    #[test]
+    fn test_simulation_of_overridden_program() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.store_account(payer, system_account(1_000_000_000));
+        let mut program = AccountSharedData::new(1, 0, &bpf_loader::id());
+        program.set_data_from_slice(&elf(&set_return_data_program()));
+        program.set_executable(true);
+        let overrides: AccountOverrides = [(program_id, program)].into_iter().collect();
+
+        let instruction = Instruction::new_with_bytes(program_id, b"preflight", vec![]);
+        let simulation = bank.simulate_transaction(&transaction(instruction, &payer), &overrides);
+        assert_eq!(simulation.status, Ok(()));
+        assert_eq!(
+            simulation.return_data,
+            Some(TransactionReturnData {
+                program_id,
+                data: b"preflight".to_vec(),
+            })
+        );
+        assert_eq!(
+            simulation.log_messages,
+            vec![
+                format!("Program {program_id} invoke [1]"),
+                format!("Program return: {program_id} cHJlZmxpZ2h0"),
+                format!("Program {program_id} consumed 105 of 200000 compute units"),
+                format!("Program {program_id} success"),
+            ]
+        );
+        // Only the payer is writable.
+        assert_eq!(simulation.post_accounts.len(), 1);
+        // The overridden program never reached the bank's program cache.
+        assert_eq!(bank.program_cache_stats().insertions, 0);
+    }
+}
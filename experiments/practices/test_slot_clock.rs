@@ -0,0 +1,165 @@
+//! Unit test: Drive slots with a `SlotClock` and stop packing at slot end
+//!
+//! Analogy: Each sitting lasts exactly as long as the clock on the wall
+//! says. When the bell rings the host totals the sitting's bills and seals
+//! them, guests already served finish their plates, and nobody else is
+//! seated until the next sitting begins.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::clock::{SlotClock, SlotEvent};
+    use priority_graph_practice::scheduler::{
+        PriorityGraphScheduler, TransactionScheduler, WorkerPool,
+    };
+    use priority_graph_practice::system_program;
+    use solana_account::AccountSharedData;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const SLOT: Duration = Duration::from_millis(400);
+
+    fn transaction(payer: Pubkey, writable: &[Pubkey]) -> SanitizedTransaction {
+        let accounts = writable
+            .iter()
+            .map(|key| AccountMeta::new(*key, false))
+            .collect();
+        let instruction = Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts);
+        let message = Message::new(&[instruction], Some(&payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_tick_freezes_and_advances_the_bank() {
+        let start = Instant::now();
+        let mut bank = Bank::default();
+        let mut clock = SlotClock::new(0, SLOT, start);
+        let events = clock.subscribe();
+        assert_eq!(clock.deadline(), start + SLOT);
+        assert_eq!(clock.time_remaining(start + SLOT / 4), SLOT * 3 / 4);
+
+        assert!(clock.tick(&mut bank, start + SLOT / 2).is_empty());
+        assert_eq!(bank.slot(), 0);
+        assert!(!bank.is_frozen());
+
+        let ended = clock.tick(&mut bank, start + SLOT);
+        let bank_hash = match ended[0] {
+            SlotEvent::Ended { slot: 0, bank_hash } => bank_hash,
+            ref event => panic!("unexpected event {event:?}"),
+        };
+        assert_eq!(
+            ended[1],
+            SlotEvent::Started {
+                slot: 1,
+                deadline: start + SLOT * 2
+            }
+        );
+        assert_eq!(bank.slot(), 1);
+        assert!(!bank.is_frozen());
+        assert_eq!(bank.slot_hashes().get(&0), Some(&bank.last_blockhash()));
+        assert_eq!(
+            bank.last_blockhash(),
+            solana_sha256_hasher::hash(bank_hash.as_ref())
+        );
+
+        // Slots nobody ticked through are still produced, empty.
+        let events_after = clock.tick(&mut bank, start + SLOT * 4 + SLOT / 2);
+        assert_eq!(events_after.len(), 6);
+        assert_eq!(clock.slot(), 4);
+        assert_eq!(bank.slot(), 4);
+        let received: Vec<SlotEvent> = events.try_iter().collect();
+        assert_eq!(received, [ended, events_after].concat());
+    }
+
+    #[test]
+    fn test_freeze_computes_the_bank_hash() {
+        let alice = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        let mut other = Bank::default();
+        let genesis_hash = other.freeze();
+        bank.store_account(
+            alice,
+            AccountSharedData::new(1_000_000_000, 0, &system_program_id::id()),
+        );
+        let bank_hash = bank.freeze();
+        assert_eq!(bank.freeze(), bank_hash);
+        assert_eq!(bank.bank_hash(), Some(bank_hash));
+        // The hash commits to the accounts.
+        assert_ne!(bank_hash, genesis_hash);
+
+        let transfer = SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(
+            Message::new(
+                &[system_program::transfer(
+                    &alice,
+                    &Pubkey::new_unique(),
+                    1_000_000,
+                )],
+                Some(&alice),
+            ),
+        ));
+        let panicked = thread::spawn(move || {
+            bank.process_transaction_batch(&[transfer]);
+        })
+        .join()
+        .is_err();
+        assert!(panicked);
+
+        // Moving on unfreezes the bank, and chains the next bank hash to
+        // this one.
+        other.advance_slot(Hash::new_unique());
+        assert!(!other.is_frozen());
+        let mut sibling = Bank::default();
+        sibling.freeze();
+        sibling.advance_slot(other.last_blockhash());
+        assert_ne!(other.freeze(), genesis_hash);
+        assert_eq!(other.freeze(), sibling.freeze());
+    }
+
+    #[test]
+    fn test_run_in_slot_stops_packing_at_slot_end() {
+        let hot_account = Pubkey::new_unique();
+        let transactions: Vec<SanitizedTransaction> = (0..3)
+            .map(|_| transaction(Pubkey::new_unique(), &[hot_account]))
+            .collect();
+        let start = Instant::now();
+        let mut clock = SlotClock::new(0, SLOT, start);
+        let slot_events = clock.subscribe();
+        let mut bank = Bank::default();
+
+        // The slot ends while the first of the conflicting transactions
+        // executes, so the others are never handed out.
+        let (ticks, ticked) = crossbeam_channel::unbounded::<()>();
+        let (done, finished) = crossbeam_channel::unbounded::<()>();
+        let pool = WorkerPool::new(2, move |transaction: &SanitizedTransaction| {
+            ticks.send(()).unwrap();
+            finished.recv().unwrap();
+            *transaction.message().fee_payer()
+        });
+        let driver = thread::spawn(move || {
+            ticked.recv().unwrap();
+            clock.tick(&mut bank, start + SLOT);
+            done.send(()).unwrap();
+            bank
+        });
+
+        let mut scheduler = PriorityGraphScheduler::new();
+        let outputs = pool
+            .run_in_slot(&mut scheduler, transactions.clone(), &slot_events)
+            .unwrap();
+        assert_eq!(driver.join().unwrap().slot(), 1);
+        assert_eq!(outputs.iter().filter(|output| output.is_some()).count(), 1);
+        assert_eq!(scheduler.num_pending(), 2);
+        let executed = outputs.iter().flatten().next().unwrap();
+        assert!(transactions
+            .iter()
+            .any(|transaction| transaction.message().fee_payer() == executed));
+    }
+}
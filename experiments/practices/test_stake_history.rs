@@ -0,0 +1,163 @@
+//! Unit test: Record stake history as the bank crosses epochs
+//!
+//! Analogy: At the end of every week the kitchen notes how many cooks were
+//! fully trained, how many were still learning and how many were on their
+//! way out. New cooks only pick up as much as the trained staff can teach
+//! in a week, leavers hand over their stations just as gradually, and the
+//! weekly notes are pinned to the wall for everyone to read.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        feature_set::{reduce_stake_warmup_cooldown, FeatureSet},
+        svm::InvokeContext,
+    };
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_clock::Epoch;
+    use solana_epoch_schedule::EpochSchedule;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{stake, sysvar};
+    use solana_stake_interface::{
+        stake_flags::StakeFlags,
+        stake_history::{StakeHistory, StakeHistoryEntry},
+        state::{Delegation, Meta, Stake, StakeStateV2},
+    };
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    /// A bank with 32-slot epochs and `delegations` in stake accounts.
+    fn bank_with_stakes(delegations: &[Delegation]) -> Bank {
+        let mut bank = Bank::default();
+        bank.set_epoch_schedule(EpochSchedule::custom(32, 32, false));
+        for delegation in delegations {
+            let state = StakeStateV2::Stake(
+                Meta::default(),
+                Stake {
+                    delegation: *delegation,
+                    credits_observed: 0,
+                },
+                StakeFlags::empty(),
+            );
+            let mut account =
+                AccountSharedData::new(delegation.stake, StakeStateV2::size_of(), &stake::id());
+            bincode::serialize_into(account.data_as_mut_slice(), &state).unwrap();
+            bank.store_account(Pubkey::new_unique(), account);
+        }
+        bank
+    }
+
+    fn advance_to_epoch(bank: &mut Bank, epoch: Epoch) {
+        while bank.epoch() < epoch {
+            bank.advance_slot(Hash::new_unique());
+        }
+    }
+
+    fn entry(effective: u64, activating: u64, deactivating: u64) -> StakeHistoryEntry {
+        StakeHistoryEntry {
+            effective,
+            activating,
+            deactivating,
+        }
+    }
+
+    /// Stake that was effective since genesis.
+    fn bootstrap(stake: u64) -> Delegation {
+        Delegation::new(&Pubkey::new_unique(), stake, u64::MAX)
+    }
+
+    #[test]
+    fn test_activating_stake_warms_up_at_the_feature_rate() {
+        let delegations = [
+            bootstrap(1_000),
+            Delegation::new(&Pubkey::new_unique(), 1_000, 0),
+        ];
+        let mut bank = bank_with_stakes(&delegations);
+        assert_eq!(bank.new_warmup_cooldown_rate_epoch(), Some(0));
+        advance_to_epoch(&mut bank, 1);
+        // In the epoch it is made in, a delegation is only activating.
+        assert_eq!(bank.stake_history().get(0), Some(&entry(1_000, 1_000, 0)));
+        advance_to_epoch(&mut bank, 2);
+        // 9% of the effective stake warms up per epoch.
+        assert_eq!(bank.stake_history().get(1), Some(&entry(1_090, 910, 0)));
+
+        let mut bank = bank_with_stakes(&delegations);
+        bank.set_feature_set(
+            FeatureSet::all_enabled().without_feature(&reduce_stake_warmup_cooldown::id()),
+        );
+        assert_eq!(bank.new_warmup_cooldown_rate_epoch(), None);
+        advance_to_epoch(&mut bank, 2);
+        // 25% of it does before the feature.
+        assert_eq!(bank.stake_history().get(1), Some(&entry(1_250, 750, 0)));
+    }
+
+    #[test]
+    fn test_programs_read_deactivating_stake_from_the_sysvar() {
+        let mut leaving = bootstrap(1_000);
+        leaving.deactivation_epoch = 0;
+        let mut bank = bank_with_stakes(&[bootstrap(1_000), leaving]);
+        advance_to_epoch(&mut bank, 2);
+        let history = bank.stake_history().clone();
+        assert_eq!(history.get(0), Some(&entry(2_000, 0, 1_000)));
+        assert_eq!(history.get(1), Some(&entry(1_820, 0, 820)));
+
+        assert_eq!(bank.sysvar_cache().get_stake_history(), &history);
+        // Fails unless account 0 holds the history the sysvar cache returns.
+        let reader = Pubkey::new_unique();
+        bank.add_builtin(reader, |invoke_context: &mut InvokeContext| {
+            let history: StakeHistory = bincode::deserialize(invoke_context.get_account(0)?.data())
+                .map_err(|_| InstructionError::InvalidAccountData)?;
+            (history == *invoke_context.get_sysvar_cache().get_stake_history())
+                .then_some(())
+                .ok_or(InstructionError::Custom(1))
+        });
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let instruction = Instruction::new_with_bytes(
+            reader,
+            &[],
+            vec![AccountMeta::new_readonly(
+                sysvar::stake_history::id(),
+                false,
+            )],
+        );
+        let message =
+            Message::new_with_blockhash(&[instruction], Some(&payer), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let results = bank.process_transaction_batch(&[tx]);
+        assert_eq!(results[0].as_ref().unwrap().status, Ok(()));
+    }
+
+    #[test]
+    fn test_forks_record_only_the_epoch_they_leave() {
+        let mut bank = bank_with_stakes(&[bootstrap(1_000)]);
+        bank.freeze();
+        // Two slots into epoch 3, skipping epochs 1 and 2.
+        let child = Bank::new_from_parent(&bank, 3 * 32 + 2, Hash::new_unique());
+        let epochs: Vec<Epoch> = child
+            .stake_history()
+            .iter()
+            .map(|item| item.epoch)
+            .collect();
+        assert_eq!(epochs, vec![0]);
+        assert!(bank.stake_history().is_empty());
+
+        // Forks of the same parent keep histories of their own.
+        let mut parent = bank_with_stakes(&[bootstrap(1_000)]);
+        advance_to_epoch(&mut parent, 1);
+        parent.freeze();
+        let mut child = Bank::new_from_parent(&parent, 2 * 32, Hash::new_unique());
+        advance_to_epoch(&mut parent, 2);
+        assert_eq!(child.stake_history(), parent.stake_history());
+        advance_to_epoch(&mut child, 3);
+        assert_eq!(child.stake_history().len(), 3);
+        assert_eq!(parent.stake_history().len(), 2);
+    }
+}
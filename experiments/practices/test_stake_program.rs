@@ -0,0 +1,358 @@
+//! Unit test: Delegate, deactivate, withdraw, split and merge stake with
+//! the native Stake program
+//!
+//! Analogy: Diners put money on a tab backing one of the kitchen's
+//! stations. The money only counts for the station once the weekly
+//! books say so, a little more every week, and takes as long to stop
+//! counting once the diner pulls it. Only what does not count for anyone
+//! can be taken back, a tab can be split in two, and two tabs backing the
+//! same station can be folded into one.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{bank::Bank, stake_program, vote_program};
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_clock::Epoch;
+    use solana_epoch_schedule::EpochSchedule;
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::{stake, system_program};
+    use solana_stake_interface::{
+        error::StakeError,
+        instruction as stake_instruction,
+        stake_flags::StakeFlags,
+        stake_history::StakeHistoryEntry,
+        state::{Authorized, Delegation, Lockup, Meta, Stake, StakeStateV2},
+    };
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use solana_vote_interface::state::VoteInit;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    /// A bank with 32-slot epochs where `payer` is funded and 1_000
+    /// lamports have been staked since genesis.
+    fn stake_bank(payer: &Pubkey) -> Bank {
+        let mut bank = Bank::default();
+        bank.set_epoch_schedule(EpochSchedule::custom(32, 32, false));
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &system_program::id()),
+        );
+        let state = StakeStateV2::Stake(
+            Meta::default(),
+            Stake {
+                delegation: Delegation::new(&Pubkey::new_unique(), 1_000, u64::MAX),
+                credits_observed: 0,
+            },
+            StakeFlags::empty(),
+        );
+        let mut account = AccountSharedData::new(1_000, StakeStateV2::size_of(), &stake::id());
+        bincode::serialize_into(account.data_as_mut_slice(), &state).unwrap();
+        bank.store_account(Pubkey::new_unique(), account);
+        bank
+    }
+
+    fn store_vote_account(bank: &mut Bank) -> Pubkey {
+        let authority = Pubkey::new_unique();
+        let vote_init = VoteInit {
+            node_pubkey: authority,
+            authorized_voter: authority,
+            authorized_withdrawer: authority,
+            commission: 10,
+        };
+        let account =
+            vote_program::vote_account(&vote_init, bank.sysvar_cache().get_clock(), bank.rent());
+        let vote_pubkey = Pubkey::new_unique();
+        bank.store_account(vote_pubkey, account);
+        vote_pubkey
+    }
+
+    /// Stores a stake account holding `lamports` beyond its rent-exempt
+    /// reserve, staked and withdrawn from by `authority`.
+    fn store_stake_account(
+        bank: &mut Bank,
+        authority: &Pubkey,
+        lockup: &Lockup,
+        lamports: u64,
+    ) -> Pubkey {
+        let account = stake_program::stake_account(
+            &Authorized::auto(authority),
+            lockup,
+            bank.rent(),
+            reserve(bank) + lamports,
+        );
+        let stake_pubkey = Pubkey::new_unique();
+        bank.store_account(stake_pubkey, account);
+        stake_pubkey
+    }
+
+    fn store_recipient(bank: &mut Bank) -> Pubkey {
+        let recipient = Pubkey::new_unique();
+        bank.store_account(
+            recipient,
+            AccountSharedData::new(LAMPORTS, 0, &system_program::id()),
+        );
+        recipient
+    }
+
+    fn reserve(bank: &Bank) -> u64 {
+        bank.rent().minimum_balance(StakeStateV2::size_of())
+    }
+
+    // Processes a single transaction and returns its execution status.
+    fn process(
+        bank: &mut Bank,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<(), TransactionError> {
+        let message =
+            Message::new_with_blockhash(instructions, Some(payer), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let mut results = bank.process_transaction_batch(&[tx]);
+        results.remove(0).unwrap().status
+    }
+
+    fn instruction_error(index: u8, err: InstructionError) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(index, err))
+    }
+
+    fn stake_error(index: u8, err: StakeError) -> Result<(), TransactionError> {
+        instruction_error(index, InstructionError::Custom(err as u32))
+    }
+
+    fn stake_state(bank: &Bank, stake_pubkey: &Pubkey) -> StakeStateV2 {
+        bincode::deserialize(bank.get_account(stake_pubkey).unwrap().data()).unwrap()
+    }
+
+    fn delegation(bank: &Bank, stake_pubkey: &Pubkey) -> Delegation {
+        stake_state(bank, stake_pubkey).delegation().unwrap()
+    }
+
+    fn advance_to_epoch(bank: &mut Bank, epoch: Epoch) {
+        while bank.epoch() < epoch {
+            bank.advance_slot(Hash::new_unique());
+        }
+    }
+
+    #[test]
+    fn test_delegated_stake_activates_across_epochs() {
+        let payer = Pubkey::new_unique();
+        let mut bank = stake_bank(&payer);
+        let vote_pubkey = store_vote_account(&mut bank);
+        let stake_pubkey = Pubkey::new_unique();
+        let instructions = stake_instruction::create_account_and_delegate_stake(
+            &payer,
+            &stake_pubkey,
+            &vote_pubkey,
+            &Authorized::auto(&payer),
+            &Lockup::default(),
+            reserve(&bank) + 1_000,
+        );
+        assert_eq!(process(&mut bank, &payer, &instructions), Ok(()));
+        let delegation = delegation(&bank, &stake_pubkey);
+        assert_eq!(delegation.voter_pubkey, vote_pubkey);
+        assert_eq!(delegation.stake, 1_000);
+        assert_eq!(delegation.activation_epoch, 0);
+
+        // Only the staker may delegate, and only to a vote account.
+        let other_stake =
+            store_stake_account(&mut bank, &Pubkey::new_unique(), &Lockup::default(), 1_000);
+        let ix = stake_instruction::delegate_stake(&other_stake, &payer, &vote_pubkey);
+        assert_eq!(
+            process(&mut bank, &payer, &[ix]),
+            instruction_error(0, InstructionError::MissingRequiredSignature)
+        );
+        let ix = stake_instruction::delegate_stake(&stake_pubkey, &payer, &payer);
+        assert_eq!(
+            process(&mut bank, &payer, &[ix]),
+            instruction_error(0, InstructionError::IncorrectProgramId)
+        );
+
+        // The stake warms up at 9% of the effective stake per epoch.
+        advance_to_epoch(&mut bank, 2);
+        let history = bank.stake_history();
+        let entry = |effective, activating| StakeHistoryEntry {
+            effective,
+            activating,
+            deactivating: 0,
+        };
+        assert_eq!(history.get(0), Some(&entry(1_000, 1_000)));
+        assert_eq!(history.get(1), Some(&entry(1_090, 910)));
+        // Stake that is partly effective cannot move to another voter.
+        let other_vote = store_vote_account(&mut bank);
+        let ix = stake_instruction::delegate_stake(&stake_pubkey, &payer, &other_vote);
+        assert_eq!(
+            process(&mut bank, &payer, &[ix]),
+            stake_error(0, StakeError::TooSoonToRedelegate)
+        );
+    }
+
+    #[test]
+    fn test_withdraw_what_is_not_staked() {
+        let payer = Pubkey::new_unique();
+        let mut bank = stake_bank(&payer);
+        let vote_pubkey = store_vote_account(&mut bank);
+        let stake_pubkey = store_stake_account(&mut bank, &payer, &Lockup::default(), 1_000);
+        let delegate = stake_instruction::delegate_stake(&stake_pubkey, &payer, &vote_pubkey);
+        let deactivate = stake_instruction::deactivate_stake(&stake_pubkey, &payer);
+        assert_eq!(
+            process(&mut bank, &payer, &[delegate, deactivate.clone()]),
+            Ok(())
+        );
+        assert_eq!(delegation(&bank, &stake_pubkey).deactivation_epoch, 0);
+        // Delegating again in the epoch of the deactivation rescinds it.
+        let delegate = stake_instruction::delegate_stake(&stake_pubkey, &payer, &vote_pubkey);
+        assert_eq!(process(&mut bank, &payer, &[delegate]), Ok(()));
+        assert_eq!(
+            delegation(&bank, &stake_pubkey).deactivation_epoch,
+            u64::MAX
+        );
+
+        // 90 lamports are effective in epoch 1, and the rest can be
+        // withdrawn once the stake is deactivated.
+        advance_to_epoch(&mut bank, 1);
+        let recipient = store_recipient(&mut bank);
+        let withdraw = |lamports| {
+            stake_instruction::withdraw(&stake_pubkey, &payer, &recipient, lamports, None)
+        };
+        assert_eq!(
+            process(&mut bank, &payer, &[withdraw(1)]),
+            instruction_error(0, InstructionError::InsufficientFunds)
+        );
+        assert_eq!(process(&mut bank, &payer, &[deactivate]), Ok(()));
+        assert_eq!(
+            process(&mut bank, &payer, &[withdraw(911)]),
+            instruction_error(0, InstructionError::InsufficientFunds)
+        );
+        assert_eq!(process(&mut bank, &payer, &[withdraw(910)]), Ok(()));
+        assert_eq!(
+            bank.get_account(&recipient).unwrap().lamports(),
+            LAMPORTS + 910
+        );
+
+        // It cooled down by epoch 2, so the account can be emptied.
+        advance_to_epoch(&mut bank, 2);
+        let balance = bank.get_account(&stake_pubkey).unwrap().lamports();
+        assert_eq!(balance, reserve(&bank) + 90);
+        assert_eq!(process(&mut bank, &payer, &[withdraw(balance)]), Ok(()));
+        assert_eq!(
+            bank.get_account(&recipient).unwrap().lamports(),
+            LAMPORTS + 910 + balance
+        );
+        assert!(bank
+            .get_account(&stake_pubkey)
+            .is_none_or(|account| account.lamports() == 0));
+    }
+
+    #[test]
+    fn test_lockup_blocks_withdrawals_until_it_expires() {
+        let payer = Pubkey::new_unique();
+        let custodian = Pubkey::new_unique();
+        let mut bank = stake_bank(&payer);
+        let lockup = Lockup {
+            unix_timestamp: 0,
+            epoch: 1,
+            custodian,
+        };
+        let stake_pubkey = store_stake_account(&mut bank, &payer, &lockup, 1_000);
+        let recipient = store_recipient(&mut bank);
+        let withdraw = |lamports, custodian| {
+            stake_instruction::withdraw(&stake_pubkey, &payer, &recipient, lamports, custodian)
+        };
+        assert_eq!(
+            process(&mut bank, &payer, &[withdraw(100, None)]),
+            stake_error(0, StakeError::LockupInForce)
+        );
+        // The custodian lifts the lockup by signing.
+        assert_eq!(
+            process(&mut bank, &payer, &[withdraw(100, Some(&custodian))]),
+            Ok(())
+        );
+        // Only the withdrawer may withdraw at all.
+        let ix = stake_instruction::withdraw(&stake_pubkey, &custodian, &recipient, 100, None);
+        assert_eq!(
+            process(&mut bank, &payer, &[ix]),
+            instruction_error(0, InstructionError::MissingRequiredSignature)
+        );
+
+        advance_to_epoch(&mut bank, 1);
+        assert_eq!(process(&mut bank, &payer, &[withdraw(900, None)]), Ok(()));
+        assert_eq!(
+            bank.get_account(&recipient).unwrap().lamports(),
+            LAMPORTS + 1_000
+        );
+    }
+
+    #[test]
+    fn test_split_and_merge_activating_stake() {
+        let payer = Pubkey::new_unique();
+        let mut bank = stake_bank(&payer);
+        let vote_pubkey = store_vote_account(&mut bank);
+        let reserve = reserve(&bank);
+        let stake_pubkey =
+            store_stake_account(&mut bank, &payer, &Lockup::default(), 2 * reserve + 2_000);
+        let ix = stake_instruction::delegate_stake(&stake_pubkey, &payer, &vote_pubkey);
+        assert_eq!(process(&mut bank, &payer, &[ix]), Ok(()));
+
+        // The split account's reserve comes out of the split lamports.
+        let split_pubkey = Pubkey::new_unique();
+        let instructions =
+            stake_instruction::split(&stake_pubkey, &payer, reserve + 1_000, &split_pubkey);
+        assert_eq!(process(&mut bank, &payer, &instructions), Ok(()));
+        assert_eq!(delegation(&bank, &stake_pubkey).stake, reserve + 1_000);
+        assert_eq!(delegation(&bank, &split_pubkey).stake, 1_000);
+        assert_eq!(
+            bank.get_account(&split_pubkey).unwrap().lamports(),
+            reserve + 1_000
+        );
+
+        let merge = stake_instruction::merge(&stake_pubkey, &stake_pubkey, &payer);
+        assert_eq!(
+            process(&mut bank, &payer, &merge),
+            instruction_error(0, InstructionError::InvalidArgument)
+        );
+        // Stakes only merge if they back the same vote account.
+        let other_pubkey = store_stake_account(&mut bank, &payer, &Lockup::default(), 1_000);
+        let other_vote = store_vote_account(&mut bank);
+        let ix = stake_instruction::delegate_stake(&other_pubkey, &payer, &other_vote);
+        assert_eq!(process(&mut bank, &payer, &[ix]), Ok(()));
+        let merge = stake_instruction::merge(&stake_pubkey, &other_pubkey, &payer);
+        assert_eq!(
+            process(&mut bank, &payer, &merge),
+            stake_error(0, StakeError::MergeMismatch)
+        );
+
+        // Merging in the activation epoch stakes the source's reserve too.
+        let merge = stake_instruction::merge(&stake_pubkey, &split_pubkey, &payer);
+        assert_eq!(process(&mut bank, &payer, &merge), Ok(()));
+        assert_eq!(delegation(&bank, &stake_pubkey).stake, 2 * reserve + 2_000);
+        assert_eq!(
+            bank.get_account(&stake_pubkey).unwrap().lamports(),
+            3 * reserve + 2_000
+        );
+        assert!(bank
+            .get_account(&split_pubkey)
+            .is_none_or(|account| account.lamports() == 0));
+
+        // Once partly effective, a stake only splits into a rent-exempt
+        // account, and cannot merge.
+        advance_to_epoch(&mut bank, 1);
+        let split_pubkey = Pubkey::new_unique();
+        let instructions =
+            stake_instruction::split(&stake_pubkey, &payer, reserve + 1_000, &split_pubkey);
+        assert_eq!(
+            process(&mut bank, &payer, &instructions),
+            instruction_error(2, InstructionError::InsufficientFunds)
+        );
+        let merge = stake_instruction::merge(&stake_pubkey, &other_pubkey, &payer);
+        assert_eq!(
+            process(&mut bank, &payer, &merge),
+            stake_error(0, StakeError::MergeTransientStake)
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Unit test: Detect duplicate transactions across forks with a `StatusCache`
+//!
+//! Analogy: Every kitchen that might take over the next shift shares one
+//! spike of ticket stubs, each stub marked with the shift that cooked it.
+//! A shift refuses a ticket cooked by itself or by a shift it took over
+//! from, but not one cooked by a rival shift that was later abandoned. Once
+//! a shift is confirmed for good its stubs count for everyone, and the
+//! oldest confirmed stubs are eventually thrown out.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::status_cache::{Ancestors, StatusCache, MAX_CACHE_ENTRIES};
+    use priority_graph_practice::system_program;
+    use solana_account::AccountSharedData;
+    use solana_clock::{Slot, MAX_PROCESSING_AGE};
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program as system_program_id;
+    use solana_signer::Signer;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn transfer(from: &Keypair, lamports: u64, blockhash: Hash) -> SanitizedTransaction {
+        let instruction = system_program::transfer(&from.pubkey(), &Pubkey::new_unique(), lamports);
+        let message = Message::new(&[instruction], Some(&from.pubkey()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+            &[from],
+            message,
+            blockhash,
+        ))
+    }
+
+    fn is_already_processed(bank: &Bank, transaction: &SanitizedTransaction) -> bool {
+        bank.check_transactions(std::slice::from_ref(transaction), MAX_PROCESSING_AGE)[0]
+            == Err(TransactionError::AlreadyProcessed)
+    }
+
+    #[test]
+    fn test_statuses_are_visible_to_ancestors_and_roots() {
+        let blockhash = Hash::new_unique();
+        let transaction = transfer(&Keypair::new(), 1_000_000, blockhash);
+        let mut status_cache = StatusCache::default();
+        status_cache.insert_transaction(&transaction, 3, Ok(()));
+
+        let on_fork = Ancestors::from([1, 3]);
+        let off_fork = Ancestors::from([1, 2]);
+        let message_hash = transaction.message_hash();
+        assert_eq!(
+            status_cache.get_status(message_hash, &blockhash, &on_fork),
+            Some((3, Ok(())))
+        );
+        assert_eq!(
+            status_cache.get_signature_status(transaction.signature(), &on_fork),
+            Some((3, Ok(())))
+        );
+        assert_eq!(
+            status_cache.get_status(message_hash, &blockhash, &off_fork),
+            None
+        );
+        assert_eq!(
+            status_cache.get_status(message_hash, &Hash::new_unique(), &on_fork),
+            None
+        );
+
+        status_cache.add_root(3);
+        assert_eq!(
+            status_cache.get_status(message_hash, &blockhash, &Ancestors::new()),
+            Some((3, Ok(())))
+        );
+    }
+
+    #[test]
+    fn test_duplicates_are_rejected_on_descendants_only() {
+        let alice = Keypair::new();
+        let mut parent = Bank::default();
+        parent.store_account(
+            alice.pubkey(),
+            AccountSharedData::new(LAMPORTS, 0, &system_program_id::id()),
+        );
+        parent.freeze();
+        let blockhash = Hash::new_unique();
+        let mut left = Bank::new_from_parent(&parent, 1, blockhash);
+        let mut right = Bank::new_from_parent(&parent, 2, blockhash);
+        let transaction = transfer(&alice, 1_000_000, blockhash);
+
+        let results = left.process_transaction_batch(std::slice::from_ref(&transaction));
+        assert!(results[0].as_ref().unwrap().was_successful());
+        assert_eq!(
+            left.get_signature_status(transaction.signature()),
+            Some(Ok(()))
+        );
+        assert!(is_already_processed(&left, &transaction));
+        left.freeze();
+        let left_child = Bank::new_from_parent(&left, 3, Hash::new_unique());
+        assert!(is_already_processed(&left_child, &transaction));
+
+        // The sibling fork never saw it, so it may still land there.
+        assert_eq!(right.get_signature_status(transaction.signature()), None);
+        assert!(!is_already_processed(&right, &transaction));
+        let results = right.process_transaction_batch(std::slice::from_ref(&transaction));
+        assert!(results[0].as_ref().unwrap().was_successful());
+        right.freeze();
+        let right_child = Bank::new_from_parent(&right, 4, Hash::new_unique());
+        assert!(is_already_processed(&right_child, &transaction));
+        assert_eq!(right_child.ancestors(), &Ancestors::from([0, 2, 4]));
+    }
+
+    #[test]
+    fn test_roots_are_pruned() {
+        let alice = Keypair::new();
+        let mut bank = Bank::default();
+        bank.store_account(
+            alice.pubkey(),
+            AccountSharedData::new(LAMPORTS, 0, &system_program_id::id()),
+        );
+        let transaction = transfer(&alice, 1_000_000, bank.last_blockhash());
+        bank.process_transaction_batch(std::slice::from_ref(&transaction));
+        bank.advance_slot(Hash::new_unique());
+        bank.set_root();
+        // Rooted slots stay visible after the fork forgets them.
+        assert_eq!(bank.ancestors(), &Ancestors::from([1]));
+        assert_eq!(
+            bank.status_cache()
+                .roots()
+                .iter()
+                .copied()
+                .collect::<Vec<Slot>>(),
+            vec![0, 1]
+        );
+        assert!(is_already_processed(&bank, &transaction));
+
+        let later = transfer(&alice, 2_000_000, bank.last_blockhash());
+        bank.process_transaction_batch(std::slice::from_ref(&later));
+        assert_eq!(bank.status_cache().num_blockhashes(), 2);
+        for _ in 0..MAX_CACHE_ENTRIES - 1 {
+            bank.advance_slot(Hash::new_unique());
+            bank.set_root();
+        }
+        // Slot 0 was the oldest root to go, taking the genesis blockhash
+        // with it.
+        let status_cache = bank.status_cache();
+        assert_eq!(status_cache.roots().len(), MAX_CACHE_ENTRIES);
+        assert_eq!(status_cache.roots().first(), Some(&1));
+        assert_eq!(status_cache.num_blockhashes(), 1);
+        assert!(status_cache
+            .get_signature_status(later.signature(), &Ancestors::new())
+            .is_some());
+    }
+}
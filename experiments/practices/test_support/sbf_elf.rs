@@ -0,0 +1,45 @@
+//! SBPFv3 ELF images for the tests that run SBF programs, pulled into each
+//! of them with `#[path = "test_support/sbf_elf.rs"] mod sbf_elf;`.
+
+// Not every test that pulls this in builds both kinds of image.
+#![allow(dead_code)]
+
+/// Wraps `text` in a minimal SBPFv3 ELF: a file header and a single
+/// executable program header, with no read-only data or sections.
+pub fn elf(text: &[[u8; 8]]) -> Vec<u8> {
+    const EHDR: u16 = 64;
+    const PHDR: u16 = 56;
+    const MM_BYTECODE_START: u64 = 1 << 32;
+    let text: Vec<u8> = text.concat();
+
+    let mut elf = Vec::new();
+    elf.extend_from_slice(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0");
+    elf.extend_from_slice(&3u16.to_le_bytes()); // ET_DYN
+    elf.extend_from_slice(&247u16.to_le_bytes()); // EM_BPF
+    elf.extend_from_slice(&1u32.to_le_bytes()); // EV_CURRENT
+    elf.extend_from_slice(&MM_BYTECODE_START.to_le_bytes()); // e_entry
+    elf.extend_from_slice(&u64::from(EHDR).to_le_bytes()); // e_phoff
+    elf.extend_from_slice(&0u64.to_le_bytes()); // e_shoff
+    elf.extend_from_slice(&3u32.to_le_bytes()); // e_flags: SBPFv3
+    for half in [EHDR, PHDR, 1, 64, 0, 0] {
+        elf.extend_from_slice(&half.to_le_bytes());
+    }
+
+    // PT_LOAD, executable, mapped at the start of the bytecode region.
+    let offset = u64::from(EHDR + PHDR);
+    let size = text.len() as u64;
+    elf.extend_from_slice(&1u32.to_le_bytes());
+    elf.extend_from_slice(&1u32.to_le_bytes());
+    for word in [offset, MM_BYTECODE_START, MM_BYTECODE_START, size, size, 8] {
+        elf.extend_from_slice(&word.to_le_bytes());
+    }
+    elf.extend_from_slice(&text);
+    elf
+}
+
+/// An ELF whose only code returns `status`.
+pub fn returning_elf(status: i32) -> Vec<u8> {
+    let mut mov = [0xb7, 0, 0, 0, 0, 0, 0, 0]; // mov64 r0, status
+    mov[4..].copy_from_slice(&status.to_le_bytes());
+    elf(&[mov, [0x95, 0, 0, 0, 0, 0, 0, 0]]) // exit
+}
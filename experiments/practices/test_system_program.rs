@@ -0,0 +1,217 @@
+//! Unit test: Execute System program instructions against the bank
+//!
+//! Analogy: The front desk handles the house accounts itself. It moves
+//! money between tabs, opens a new tab only under a name nobody holds yet,
+//! and keeps the voucher book (nonce accounts) stamped with the latest
+//! service so old vouchers can't be reused.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        fees::FeeStructure,
+        system_program::{self, SystemError},
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_nonce::{
+        state::{DurableNonce, State},
+        versions::Versions,
+    };
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    fn funded_bank(payer: &Pubkey, lamports: u64) -> Bank {
+        let mut bank = Bank::default();
+        // Fees are waived so balances only reflect the instructions.
+        bank.set_fee_structure(FeeStructure::new(0, 0));
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(lamports, 0, &solana_sdk_ids::system_program::id()),
+        );
+        bank
+    }
+
+    // Processes a single transaction and returns its execution status.
+    fn process(
+        bank: &mut Bank,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<(), TransactionError> {
+        let message =
+            Message::new_with_blockhash(instructions, Some(payer), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let mut results = bank.process_transaction_batch(&[tx]);
+        results.remove(0).unwrap().status
+    }
+
+    fn lamports(bank: &Bank, pubkey: &Pubkey) -> u64 {
+        bank.get_account(pubkey)
+            .map_or(0, |account| account.lamports())
+    }
+
+    fn nonce_state(bank: &Bank, nonce: &Pubkey) -> State {
+        let account = bank.get_account(nonce).unwrap();
+        bincode::deserialize::<Versions>(account.data())
+            .unwrap()
+            .state()
+            .clone()
+    }
+
+    fn instruction_error(
+        index: u8,
+        err: impl Into<InstructionError>,
+    ) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(index, err.into()))
+    }
+
+    #[test]
+    fn test_transfer() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mut bank = funded_bank(&payer, 1_000);
+        // Free rent lets the recipient hold only a few lamports.
+        bank.set_rent(Rent::free());
+
+        let transfer = system_program::transfer(&payer, &recipient, 400);
+        assert_eq!(process(&mut bank, &payer, &[transfer]), Ok(()));
+        assert_eq!(lamports(&bank, &payer), 600);
+        assert_eq!(lamports(&bank, &recipient), 400);
+
+        // Overdrawing fails and leaves both balances untouched.
+        let overdraw = system_program::transfer(&payer, &recipient, 601);
+        assert_eq!(
+            process(&mut bank, &payer, &[overdraw]),
+            instruction_error(0, SystemError::ResultWithNegativeLamports)
+        );
+        assert_eq!(lamports(&bank, &payer), 600);
+
+        // The funding account has to sign.
+        let mut unsigned = system_program::transfer(&recipient, &payer, 100);
+        unsigned.accounts[0].is_signer = false;
+        assert_eq!(
+            process(&mut bank, &payer, &[unsigned]),
+            instruction_error(0, InstructionError::MissingRequiredSignature)
+        );
+    }
+
+    #[test]
+    fn test_create_assign_and_allocate() {
+        let payer = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let new_account = Pubkey::new_unique();
+        let mut bank = funded_bank(&payer, 10_000);
+        bank.set_rent(Rent::free());
+
+        let create = system_program::create_account(&payer, &new_account, 1_000, 16, &owner);
+        assert_eq!(
+            process(&mut bank, &payer, std::slice::from_ref(&create)),
+            Ok(())
+        );
+        let account = bank.get_account(&new_account).unwrap();
+        assert_eq!(account.lamports(), 1_000);
+        assert_eq!(account.data(), &[0; 16]);
+        assert_eq!(*account.owner(), owner);
+        // Under a new blockhash, so it is not rejected as already processed.
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(
+            process(&mut bank, &payer, &[create]),
+            instruction_error(0, SystemError::AccountAlreadyInUse)
+        );
+
+        // Allocating and assigning separately ends up in the same place.
+        let other = Pubkey::new_unique();
+        let instructions = [
+            system_program::transfer(&payer, &other, 1_000),
+            system_program::allocate(&other, 8),
+            system_program::assign(&other, &owner),
+        ];
+        assert_eq!(process(&mut bank, &payer, &instructions), Ok(()));
+        let account = bank.get_account(&other).unwrap();
+        assert_eq!((account.data().len(), *account.owner()), (8, owner));
+
+        // Seeded addresses are signed for by their base.
+        let seeded = Pubkey::create_with_seed(&payer, "vault", &owner).unwrap();
+        let create = system_program::create_account_with_seed(
+            &payer, &seeded, &payer, "vault", 500, 0, &owner,
+        );
+        assert_eq!(process(&mut bank, &payer, &[create]), Ok(()));
+        assert_eq!(lamports(&bank, &seeded), 500);
+
+        let mismatch = system_program::create_account_with_seed(
+            &payer,
+            &Pubkey::new_unique(),
+            &payer,
+            "vault",
+            500,
+            0,
+            &owner,
+        );
+        assert_eq!(
+            process(&mut bank, &payer, &[mismatch]),
+            instruction_error(0, SystemError::AddressWithSeedMismatch)
+        );
+    }
+
+    #[test]
+    fn test_nonce_account_lifecycle() {
+        let payer = Pubkey::new_unique();
+        let nonce = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let mut bank = funded_bank(&payer, 10_000_000);
+
+        // An underfunded nonce account is not rent exempt.
+        let underfunded = system_program::create_nonce_account(&payer, &nonce, &authority, 1);
+        assert_eq!(
+            process(&mut bank, &payer, &underfunded),
+            instruction_error(1, InstructionError::InsufficientFunds)
+        );
+
+        let create = system_program::create_nonce_account(&payer, &nonce, &authority, 2_000_000);
+        assert_eq!(process(&mut bank, &payer, &create), Ok(()));
+        let State::Initialized(data) = nonce_state(&bank, &nonce) else {
+            panic!("nonce account is not initialized");
+        };
+        assert_eq!(data.authority, authority);
+        assert_eq!(
+            data.durable_nonce,
+            DurableNonce::from_blockhash(&bank.last_blockhash())
+        );
+
+        let authorize = system_program::authorize_nonce_account(&nonce, &authority, &new_authority);
+        assert_eq!(process(&mut bank, &payer, &[authorize]), Ok(()));
+
+        // Only the new authority can withdraw, and only down to the
+        // rent-exempt minimum unless the account is emptied.
+        let old = system_program::withdraw_nonce_account(&nonce, &authority, &payer, 1);
+        assert_eq!(
+            process(&mut bank, &payer, &[old]),
+            instruction_error(0, InstructionError::MissingRequiredSignature)
+        );
+        let too_much =
+            system_program::withdraw_nonce_account(&nonce, &new_authority, &payer, 600_000);
+        assert_eq!(
+            process(&mut bank, &payer, &[too_much]),
+            instruction_error(0, InstructionError::InsufficientFunds)
+        );
+
+        // Emptying the account needs a blockhash newer than the stored nonce.
+        let drain =
+            system_program::withdraw_nonce_account(&nonce, &new_authority, &payer, 2_000_000);
+        assert_eq!(
+            process(&mut bank, &payer, std::slice::from_ref(&drain)),
+            instruction_error(0, SystemError::NonceBlockhashNotExpired)
+        );
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(process(&mut bank, &payer, &[drain]), Ok(()));
+        // The emptied account is closed.
+        assert_eq!(bank.get_account(&nonce), None);
+    }
+}
@@ -0,0 +1,119 @@
+//! Unit test: Materialize sysvars from bank state
+//!
+//! Analogy: The kitchen clock, the price list and the log of past services
+//! hang on the wall where every cook can glance at them. Nobody has to ask
+//! the manager for the time, and a cook who wants to take the clock down
+//! to the station gets a copy, not the real one.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{bank::Bank, svm::InvokeContext};
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_clock::Clock;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_sdk_ids::sysvar;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    // Succeeds only if account 0 holds the same clock and account 1 the same
+    // rent the sysvar cache returns.
+    fn check_sysvar_accounts(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let clock: Clock = bincode::deserialize(invoke_context.get_account(0)?.data())
+            .map_err(|_| InstructionError::InvalidAccountData)?;
+        let rent: Rent = bincode::deserialize(invoke_context.get_account(1)?.data())
+            .map_err(|_| InstructionError::InvalidAccountData)?;
+        let sysvar_cache = invoke_context.get_sysvar_cache();
+        if clock != *sysvar_cache.get_clock() || rent != *sysvar_cache.get_rent() {
+            return Err(InstructionError::Custom(1));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bank_materializes_sysvars() {
+        let mut bank = Bank::default();
+        let (first, second) = (Hash::new_unique(), Hash::new_unique());
+        bank.advance_slot(first);
+        bank.advance_slot(second);
+
+        let sysvar_cache = bank.sysvar_cache();
+        let clock = sysvar_cache.get_clock();
+        assert_eq!((clock.slot, clock.epoch), (2, 0));
+        assert_eq!(clock.unix_timestamp, 0);
+        assert_eq!(sysvar_cache.get_rent(), bank.rent());
+        assert_eq!(sysvar_cache.get_epoch_schedule(), bank.epoch_schedule());
+        assert_eq!(sysvar_cache.get_slot_hashes().get(&0), Some(&first));
+        assert_eq!(sysvar_cache.get_slot_hashes().get(&1), Some(&second));
+        let recent: Vec<Hash> = sysvar_cache
+            .get_recent_blockhashes()
+            .iter()
+            .map(|entry| entry.blockhash)
+            .collect();
+        assert_eq!(recent, vec![second, first, Hash::default()]);
+    }
+
+    #[test]
+    fn test_read_sysvar() {
+        let mut bank = Bank::default();
+        for _ in 0..3 {
+            bank.advance_slot(Hash::new_unique());
+        }
+        let sysvar_cache = bank.sysvar_cache();
+
+        // The slot is the first field of the clock.
+        assert_eq!(
+            sysvar_cache.read_sysvar(&sysvar::clock::id(), 0, 8),
+            Ok(&3u64.to_le_bytes()[..])
+        );
+        assert_eq!(
+            sysvar_cache.read_sysvar(&sysvar::clock::id(), 36, 8),
+            Err(InstructionError::InvalidArgument)
+        );
+        assert_eq!(
+            sysvar_cache.read_sysvar(&sysvar::instructions::id(), 0, 0),
+            Err(InstructionError::UnsupportedSysvar)
+        );
+    }
+
+    #[test]
+    fn test_sysvar_accounts_are_loadable() {
+        let program_id = Pubkey::new_unique();
+        let mut bank = Bank::default();
+        bank.add_builtin(program_id, check_sysvar_accounts);
+        bank.advance_slot(Hash::new_unique());
+
+        let clock = bank
+            .sysvar_cache()
+            .get_account(&sysvar::clock::id())
+            .unwrap();
+        assert_eq!(*clock.owner(), sysvar::id());
+        assert!(clock.lamports() >= bank.rent().minimum_balance(clock.data().len()));
+
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+                AccountMeta::new_readonly(sysvar::rent::id(), false),
+            ],
+        );
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000, 0, &solana_sdk_ids::system_program::id()),
+        );
+        let message =
+            Message::new_with_blockhash(&[instruction], Some(&payer), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let results = bank.process_transaction_batch(&[tx]);
+        assert_eq!(results[0].as_ref().unwrap().status, Ok(()));
+
+        // Sysvars are served from the cache, never stored.
+        assert_eq!(bank.get_account(&sysvar::clock::id()), None);
+    }
+}
@@ -0,0 +1,333 @@
+//! Unit test: Mint and move tokens with the native SPL Token program
+//!
+//! Analogy: The restaurant sells gift vouchers. The manager prints a new
+//! kind of voucher and decides how many exist, guests keep theirs in a
+//! wallet with their name on it, can hand some over, let a friend spend a
+//! few for them, and the manager can freeze a wallet nobody may spend from.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        token_program::{self, AccountState, Mint, TokenAccount, TokenError, TokenInstruction},
+    };
+    use solana_account::{AccountSharedData, ReadableAccount};
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_rent::Rent;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn token_bank(payer: &Pubkey) -> Bank {
+        let mut bank = Bank::default();
+        bank.add_builtin(token_program::id(), token_program::process_instruction);
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &solana_sdk_ids::system_program::id()),
+        );
+        bank
+    }
+
+    // Processes a single transaction and returns its execution status.
+    fn process(
+        bank: &mut Bank,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<(), TransactionError> {
+        let message =
+            Message::new_with_blockhash(instructions, Some(payer), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let mut results = bank.process_transaction_batch(&[tx]);
+        results.remove(0).unwrap().status
+    }
+
+    fn token_error(index: u8, err: TokenError) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(index, err.into()))
+    }
+
+    fn mint(bank: &Bank, pubkey: &Pubkey) -> Mint {
+        Mint::unpack(bank.get_account(pubkey).unwrap().data()).unwrap()
+    }
+
+    fn token_account(bank: &Bank, pubkey: &Pubkey) -> TokenAccount {
+        TokenAccount::unpack(bank.get_account(pubkey).unwrap().data()).unwrap()
+    }
+
+    #[test]
+    fn test_create_mint_and_transfer() {
+        let payer = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let alice_tokens = Pubkey::new_unique();
+        let bob_tokens = Pubkey::new_unique();
+        let mut bank = token_bank(&payer);
+        let rent = Rent::default();
+
+        let mut instructions = token_program::create_mint(
+            &payer,
+            &mint_pubkey,
+            &payer,
+            None,
+            6,
+            rent.minimum_balance(Mint::LEN),
+        );
+        for (account, owner) in [(alice_tokens, alice), (bob_tokens, bob)] {
+            instructions.extend(token_program::create_token_account(
+                &payer,
+                &account,
+                &mint_pubkey,
+                &owner,
+                rent.minimum_balance(TokenAccount::LEN),
+            ));
+        }
+        instructions.push(token_program::mint_to(
+            &mint_pubkey,
+            &alice_tokens,
+            &payer,
+            1_000,
+        ));
+        assert_eq!(process(&mut bank, &payer, &instructions), Ok(()));
+        let account = bank.get_account(&alice_tokens).unwrap();
+        assert_eq!(*account.owner(), token_program::id());
+        assert_eq!(account.data().len(), TokenAccount::LEN);
+        assert_eq!(
+            token_account(&bank, &alice_tokens),
+            TokenAccount::new(mint_pubkey, alice, 1_000)
+        );
+
+        let transfer = token_program::transfer_checked(
+            &alice_tokens,
+            &mint_pubkey,
+            &bob_tokens,
+            &alice,
+            400,
+            6,
+        );
+        assert_eq!(transfer.data[0], 12);
+        assert_eq!(
+            TokenInstruction::parse(&transfer.data),
+            Ok(TokenInstruction::TransferChecked {
+                amount: 400,
+                decimals: 6
+            })
+        );
+        assert_eq!(process(&mut bank, &payer, &[transfer]), Ok(()));
+        assert_eq!(token_account(&bank, &alice_tokens).amount, 600);
+        assert_eq!(token_account(&bank, &bob_tokens).amount, 400);
+        assert_eq!(mint(&bank, &mint_pubkey).supply, 1_000);
+
+        // Every account of a mint can only be initialized once.
+        assert_eq!(
+            process(
+                &mut bank,
+                &payer,
+                &[token_program::initialize_account3(
+                    &bob_tokens,
+                    &mint_pubkey,
+                    &alice
+                )]
+            ),
+            token_error(0, TokenError::AlreadyInUse)
+        );
+        assert_eq!(
+            TokenInstruction::parse(&[6, 0]),
+            Err(TokenError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_transfers_are_checked() {
+        let payer = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let other_mint = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let alice_tokens = Pubkey::new_unique();
+        let bob_tokens = Pubkey::new_unique();
+        let foreign_tokens = Pubkey::new_unique();
+        let mut bank = token_bank(&payer);
+        let rent = Rent::default();
+        let mint_state = Mint {
+            mint_authority: Some(payer),
+            decimals: 2,
+            is_initialized: true,
+            freeze_authority: Some(payer),
+            supply: 100,
+        };
+        bank.store_account(mint_pubkey, token_program::mint_account(&mint_state, &rent));
+        for (pubkey, account) in [
+            (alice_tokens, TokenAccount::new(mint_pubkey, alice, 100)),
+            (
+                bob_tokens,
+                TokenAccount::new(mint_pubkey, Pubkey::new_unique(), 0),
+            ),
+            (foreign_tokens, TokenAccount::new(other_mint, alice, 0)),
+        ] {
+            bank.store_account(pubkey, token_program::token_account(&account, &rent));
+        }
+
+        let transfer = |amount| token_program::transfer(&alice_tokens, &bob_tokens, &alice, amount);
+        assert_eq!(
+            process(&mut bank, &payer, &[transfer(101)]),
+            token_error(0, TokenError::InsufficientFunds)
+        );
+        assert_eq!(
+            process(
+                &mut bank,
+                &payer,
+                &[token_program::transfer(
+                    &alice_tokens,
+                    &bob_tokens,
+                    &payer,
+                    1
+                )]
+            ),
+            token_error(0, TokenError::OwnerMismatch)
+        );
+        assert_eq!(
+            process(
+                &mut bank,
+                &payer,
+                &[token_program::transfer(
+                    &alice_tokens,
+                    &foreign_tokens,
+                    &alice,
+                    1
+                )]
+            ),
+            token_error(0, TokenError::MintMismatch)
+        );
+        assert_eq!(
+            process(
+                &mut bank,
+                &payer,
+                &[token_program::transfer_checked(
+                    &alice_tokens,
+                    &mint_pubkey,
+                    &bob_tokens,
+                    &alice,
+                    1,
+                    6
+                )]
+            ),
+            token_error(0, TokenError::MintDecimalsMismatch)
+        );
+
+        // A frozen account cannot send or receive until it is thawed.
+        let freeze = token_program::freeze_account(&bob_tokens, &mint_pubkey, &payer);
+        assert_eq!(process(&mut bank, &payer, &[freeze]), Ok(()));
+        assert_eq!(
+            token_account(&bank, &bob_tokens).state,
+            AccountState::Frozen
+        );
+        assert_eq!(
+            process(&mut bank, &payer, &[transfer(1)]),
+            token_error(0, TokenError::AccountFrozen)
+        );
+        let thaw = token_program::thaw_account(&bob_tokens, &mint_pubkey, &payer);
+        assert_eq!(process(&mut bank, &payer, &[thaw, transfer(1)]), Ok(()));
+        assert_eq!(token_account(&bank, &bob_tokens).amount, 1);
+
+        // Accounts the token program does not own are rejected.
+        let stranger = Pubkey::new_unique();
+        bank.store_account(
+            stranger,
+            AccountSharedData::new(LAMPORTS, TokenAccount::LEN, &Pubkey::new_unique()),
+        );
+        assert_eq!(
+            process(
+                &mut bank,
+                &payer,
+                &[token_program::transfer(&stranger, &bob_tokens, &alice, 1)]
+            ),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::IncorrectProgramId
+            ))
+        );
+    }
+
+    #[test]
+    fn test_delegate_burn_and_close() {
+        let payer = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let alice_tokens = Pubkey::new_unique();
+        let bob_tokens = Pubkey::new_unique();
+        let mut bank = token_bank(&payer);
+        let rent = Rent::default();
+        let mint_state = Mint {
+            mint_authority: None,
+            supply: 50,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: None,
+        };
+        bank.store_account(mint_pubkey, token_program::mint_account(&mint_state, &rent));
+        bank.store_account(
+            alice_tokens,
+            token_program::token_account(&TokenAccount::new(mint_pubkey, alice, 50), &rent),
+        );
+        bank.store_account(
+            bob_tokens,
+            token_program::token_account(
+                &TokenAccount::new(mint_pubkey, Pubkey::new_unique(), 0),
+                &rent,
+            ),
+        );
+
+        let approve = token_program::approve(&alice_tokens, &delegate, &alice, 30);
+        let spend = token_program::transfer(&alice_tokens, &bob_tokens, &delegate, 20);
+        assert_eq!(
+            process(&mut bank, &payer, &[approve, spend.clone()]),
+            Ok(())
+        );
+        let account = token_account(&bank, &alice_tokens);
+        assert_eq!(account.delegate, Some(delegate));
+        assert_eq!(account.delegated_amount, 10);
+        assert_eq!(
+            process(&mut bank, &payer, &[spend]),
+            token_error(0, TokenError::InsufficientFunds)
+        );
+        let burn = token_program::burn(&alice_tokens, &mint_pubkey, &delegate, 10);
+        assert_eq!(process(&mut bank, &payer, &[burn]), Ok(()));
+        let account = token_account(&bank, &alice_tokens);
+        assert_eq!((account.amount, account.delegate), (20, None));
+        assert_eq!(mint(&bank, &mint_pubkey).supply, 40);
+        assert_eq!(
+            process(
+                &mut bank,
+                &payer,
+                &[token_program::mint_to(
+                    &mint_pubkey,
+                    &alice_tokens,
+                    &payer,
+                    1
+                )]
+            ),
+            token_error(0, TokenError::FixedSupply)
+        );
+
+        // Only an empty account can be closed; its rent goes to the
+        // destination.
+        let close = token_program::close_account(&alice_tokens, &alice, &alice);
+        assert_eq!(
+            process(&mut bank, &payer, std::slice::from_ref(&close)),
+            token_error(0, TokenError::NonNativeHasBalance)
+        );
+        let burn = token_program::burn(&alice_tokens, &mint_pubkey, &alice, 20);
+        assert_eq!(process(&mut bank, &payer, &[burn, close]), Ok(()));
+        assert!(bank.get_account(&alice_tokens).is_none());
+        assert_eq!(
+            bank.get_account(&alice).unwrap().lamports(),
+            rent.minimum_balance(TokenAccount::LEN)
+        );
+        assert_eq!(mint(&bank, &mint_pubkey).supply, 20);
+    }
+}
@@ -0,0 +1,126 @@
+//! Unit test: Trace slots, batches and transactions as nested spans
+//!
+//! Analogy: Rather than the cooks shouting what they are doing, every
+//! ticket is clipped under the batch it was cooked with, and every batch
+//! under the evening it belongs to. Whoever wants to know why table nine
+//! waited pulls the evening, then the batch, then the ticket with its tip
+//! written on it.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{bank::Bank, compute_budget::ComputeBudgetInstruction};
+    use solana_account::AccountSharedData;
+    use solana_keypair::Keypair;
+    use solana_message::Message;
+    use solana_rent::Rent;
+    use solana_signer::Signer;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::{
+        collections::HashMap,
+        fmt::Debug,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+    /// A span as it was opened: its name, its parent's name and its fields.
+    #[derive(Clone, Debug)]
+    struct RecordedSpan {
+        name: &'static str,
+        parent: Option<&'static str>,
+        fields: HashMap<&'static str, String>,
+    }
+
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<RecordedSpan>>>);
+
+    struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanRecorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = HashMap::new();
+            attrs.record(&mut FieldVisitor(&mut fields));
+            let parent = ctx
+                .span(id)
+                .and_then(|span| span.parent())
+                .map(|parent| parent.name());
+            self.0.lock().unwrap().push(RecordedSpan {
+                name: attrs.metadata().name(),
+                parent,
+                fields,
+            });
+        }
+    }
+
+    fn transaction(payer: &Keypair, cu_price: u64, bank: &Bank) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(10_000),
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+        ];
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+            &[payer],
+            message,
+            bank.last_blockhash(),
+        ))
+    }
+
+    #[test]
+    fn test_bank_nests_transaction_spans_in_batches_in_the_slot() {
+        let mut bank = Bank::default();
+        bank.set_rent(Rent::free());
+        let payers = [Keypair::new(), Keypair::new()];
+        for payer in &payers {
+            bank.store_account(
+                payer.pubkey(),
+                AccountSharedData::new(1_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+            );
+        }
+        let transactions = [
+            transaction(&payers[0], 3, &bank),
+            transaction(&payers[1], 5, &bank),
+        ];
+
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            bank.process_transaction_batch(&transactions);
+        });
+
+        let spans = recorder.0.lock().unwrap().clone();
+        let slots: Vec<_> = spans.iter().filter(|span| span.name == "slot").collect();
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].fields["slot"], bank.slot().to_string());
+        assert!(spans
+            .iter()
+            .filter(|span| span.name == "batch")
+            .all(|span| span.parent == Some("slot")));
+
+        let transaction_spans: Vec<_> = spans
+            .iter()
+            .filter(|span| span.name == "transaction")
+            .collect();
+        assert_eq!(transaction_spans.len(), 2);
+        for span in &transaction_spans {
+            assert_eq!(span.parent, Some("batch"));
+        }
+        // Both transactions write different payers, so they share a batch
+        // and the one paying more runs first.
+        assert_eq!(
+            transaction_spans[0].fields["signature"],
+            transactions[1].signature().to_string()
+        );
+        assert_eq!(transaction_spans[0].fields["priority"], "50000");
+        assert_eq!(transaction_spans[1].fields["priority"], "30000");
+    }
+}
@@ -0,0 +1,119 @@
+//! Unit test: Drop queued transactions once their deadline passes
+//!
+//! Analogy: A party that booked for the early seating does not want a
+//! table at midnight. When their turn finally comes after closing time,
+//! the host crosses them off the list and notes why, instead of seating
+//! them anyway.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            BatchLimits, Deadline, DropReason, PriorityGraphScheduler, TransactionScheduler,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::time::{Duration, Instant};
+
+    fn transaction(writable: Pubkey, cu_price: u64) -> SanitizedTransaction {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(writable, false)],
+            ),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_expired_transactions_are_dropped() {
+        let mut scheduler = PriorityGraphScheduler::new();
+        let expiring =
+            scheduler.push_with_deadline(transaction(Pubkey::new_unique(), 10), Deadline::Slot(5));
+        let lasting = scheduler.push(transaction(Pubkey::new_unique(), 1));
+        scheduler.set_slot(6);
+        assert_eq!(scheduler.slot(), 6);
+
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, vec![lasting]);
+        assert_eq!(scheduler.num_pending(), 0);
+        let dropped = scheduler.take_dropped();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].id, expiring);
+        assert_eq!(dropped[0].reason, DropReason::Expired);
+        assert_eq!(scheduler.metrics().transactions_expired, 1);
+        assert!(scheduler.take_dropped().is_empty());
+
+        // A transaction may still run in its last slot.
+        let id =
+            scheduler.push_with_deadline(transaction(Pubkey::new_unique(), 1), Deadline::Slot(6));
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, vec![id]);
+    }
+
+    #[test]
+    fn test_blocked_transaction_expires_when_unblocked() {
+        let hot_account = Pubkey::new_unique();
+        let mut scheduler = PriorityGraphScheduler::new();
+        let first = scheduler.push(transaction(hot_account, 3));
+        let expiring = scheduler.push_with_deadline(transaction(hot_account, 2), Deadline::Slot(0));
+        let last = scheduler.push(transaction(hot_account, 1));
+
+        let batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, vec![first]);
+        scheduler.set_slot(1);
+        // Still waiting on `first`, the expired transaction holds its place.
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        assert_eq!(scheduler.num_pending(), 2);
+
+        scheduler.complete(&batch.ids);
+        // Dropping it unblocks the transaction behind it right away.
+        assert_eq!(
+            scheduler.next_batch(&BatchLimits::default()).ids,
+            vec![last]
+        );
+        let dropped = scheduler.take_dropped();
+        assert_eq!(
+            dropped.iter().map(|dropped| dropped.id).collect::<Vec<_>>(),
+            vec![expiring]
+        );
+        assert_eq!(scheduler.num_pending(), 0);
+    }
+
+    #[test]
+    fn test_time_deadlines_and_retries() {
+        let now = Instant::now();
+        assert!(Deadline::Time(now).is_expired(0, now));
+        assert!(!Deadline::Time(now + Duration::from_secs(1)).is_expired(0, now));
+        assert!(!Deadline::Slot(3).is_expired(3, now));
+
+        let mut scheduler = PriorityGraphScheduler::new();
+        let expired =
+            scheduler.push_with_deadline(transaction(Pubkey::new_unique(), 1), Deadline::Time(now));
+        let later = scheduler.push_with_deadline(
+            transaction(Pubkey::new_unique(), 1),
+            Deadline::Time(now + Duration::from_secs(3600)),
+        );
+        let mut batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, vec![later]);
+        assert_eq!(scheduler.take_dropped()[0].id, expired);
+
+        // A retried transaction keeps its deadline.
+        let retried =
+            scheduler.push_with_deadline(transaction(Pubkey::new_unique(), 1), Deadline::Slot(0));
+        scheduler.complete(&batch.ids);
+        batch = scheduler.next_batch(&BatchLimits::default());
+        assert_eq!(batch.ids, vec![retried]);
+        assert!(scheduler.retry(retried, batch.transactions.remove(0)));
+        scheduler.set_slot(1);
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        assert_eq!(scheduler.take_dropped()[0].id, retried);
+        assert_eq!(scheduler.metrics().transactions_expired, 2);
+    }
+}
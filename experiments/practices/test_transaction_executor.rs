@@ -0,0 +1,126 @@
+//! Unit test: Execute transactions with `TransactionExecutor`
+//!
+//! Analogy: The executor is a kitchen that cooks each order on a private
+//! prep table. Only a fully successful order leaves the kitchen; a failed
+//! one is thrown away and the ingredients go back on the shelf untouched.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::svm::{InvokeContext, TransactionExecutor};
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use std::collections::HashMap;
+
+    // A mock program that increments the first data byte of its first account.
+    fn increment(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        let account = invoke_context.get_account_mut(0)?;
+        let data = account.data_as_mut_slice();
+        let counter = data
+            .first_mut()
+            .ok_or(InstructionError::AccountDataTooSmall)?;
+        *counter = counter
+            .checked_add(1)
+            .ok_or(InstructionError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    fn transaction(instructions: &[Instruction]) -> SanitizedTransaction {
+        let payer = Pubkey::new_unique();
+        let message = Message::new(instructions, Some(&payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_execute_transaction_updates_post_state() {
+        let program_id = Pubkey::new_unique();
+        let counter = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(program_id, increment);
+
+        let mut store = HashMap::new();
+        store.insert(counter, AccountSharedData::new(1, 1, &program_id));
+
+        let ix =
+            Instruction::new_with_bytes(program_id, &[], vec![AccountMeta::new(counter, false)]);
+        let tx = transaction(&[ix.clone(), ix]);
+        let result = executor.load_and_execute_transaction(&store, &tx);
+
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.instruction_results, vec![Ok(()), Ok(())]);
+        let (_, post) = result
+            .post_accounts
+            .iter()
+            .find(|(key, _)| *key == counter)
+            .unwrap();
+        assert_eq!(post.data(), &[2]);
+
+        // The store itself is untouched: execution works on a private copy.
+        assert_eq!(store[&counter].data(), &[0]);
+    }
+
+    #[test]
+    fn test_failed_instruction_discards_writes() {
+        let program_id = Pubkey::new_unique();
+        let counter = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(program_id, increment);
+
+        let mut store = HashMap::new();
+        store.insert(counter, AccountSharedData::new(1, 1, &program_id));
+
+        // The second instruction targets a program nobody registered.
+        let writable =
+            Instruction::new_with_bytes(program_id, &[], vec![AccountMeta::new(counter, false)]);
+        let unknown_program = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+        let tx = transaction(&[writable, unknown_program]);
+        let result = executor.load_and_execute_transaction(&store, &tx);
+
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                1,
+                InstructionError::UnsupportedProgramId
+            ))
+        );
+        assert_eq!(
+            result.instruction_results,
+            vec![Ok(()), Err(InstructionError::UnsupportedProgramId)]
+        );
+        let (_, post) = result
+            .post_accounts
+            .iter()
+            .find(|(key, _)| *key == counter)
+            .unwrap();
+        assert_eq!(post.data(), &[0]);
+    }
+
+    #[test]
+    fn test_readonly_account_cannot_be_modified() {
+        let program_id = Pubkey::new_unique();
+        let counter = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(program_id, increment);
+
+        let mut store = HashMap::new();
+        store.insert(counter, AccountSharedData::new(1, 1, &program_id));
+
+        let ix = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![AccountMeta::new_readonly(counter, false)],
+        );
+        let result = executor.load_and_execute_transaction(&store, &transaction(&[ix]));
+        assert_eq!(
+            result.status,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::ReadonlyDataModified
+            ))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Unit test: Stream transactions through a `TransactionScheduler`
+//!
+//! Analogy: Parties keep walking in while dinner is being served. The host
+//! seats whoever can be seated right now and frees a table once its party
+//! leaves. A strict host (FIFO) seats parties in the order they arrived; a
+//! tip-aware host (priority graph) lets the biggest tip at a table go first.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        compute_budget::ComputeBudgetInstruction,
+        scheduler::{
+            BatchLimits, FifoScheduler, PriorityGraphScheduler, TransactionScheduler, WorkerPool,
+        },
+    };
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+
+    fn transaction(writable: &[Pubkey], cu_price: u64) -> SanitizedTransaction {
+        let accounts = writable
+            .iter()
+            .map(|key| AccountMeta::new(*key, false))
+            .collect();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], accounts),
+        ];
+        let message = Message::new(&instructions, Some(&Pubkey::new_unique()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    #[test]
+    fn test_priority_graph_streaming() {
+        let hot_account = Pubkey::new_unique();
+        let mut scheduler = PriorityGraphScheduler::new();
+        let low = scheduler.push(transaction(&[hot_account], 1));
+        let high = scheduler.push(transaction(&[hot_account], 10));
+        let other = scheduler.push(transaction(&[Pubkey::new_unique()], 5));
+
+        // The higher tip wins the hot account; `max_txs` caps the batch size.
+        let limits = BatchLimits {
+            max_txs: 1,
+            ..BatchLimits::default()
+        };
+        let batch = scheduler.next_batch(&limits);
+        assert_eq!(batch.ids, vec![high]);
+        assert_eq!(
+            scheduler.next_batch(&BatchLimits::default()).ids,
+            vec![other]
+        );
+
+        // `low` waits until `high` has completed.
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+        scheduler.complete(&batch.ids);
+        assert_eq!(scheduler.next_batch(&BatchLimits::default()).ids, vec![low]);
+        assert_eq!(scheduler.num_pending(), 0);
+    }
+
+    #[test]
+    fn test_fifo_never_overtakes() {
+        let hot_account = Pubkey::new_unique();
+        let mut scheduler = FifoScheduler::new();
+        let first = scheduler.push(transaction(&[hot_account], 1));
+        let second = scheduler.push(transaction(&[hot_account], 10));
+        let third = scheduler.push(transaction(&[Pubkey::new_unique()], 5));
+
+        // `third` is free to run, but it arrived after the blocked `second`.
+        assert_eq!(
+            scheduler.next_batch(&BatchLimits::default()).ids,
+            vec![first]
+        );
+        assert!(scheduler.next_batch(&BatchLimits::default()).is_empty());
+
+        scheduler.complete(&[first]);
+        assert_eq!(
+            scheduler.next_batch(&BatchLimits::default()).ids,
+            vec![second, third]
+        );
+        assert_eq!(scheduler.num_pending(), 0);
+    }
+
+    #[test]
+    fn test_worker_pool_runs_any_strategy() {
+        let hot_account = Pubkey::new_unique();
+        let transactions: Vec<_> = (0..6)
+            .map(|cu_price| transaction(&[hot_account], cu_price))
+            .collect();
+        let pool = WorkerPool::new(2, |tx: &SanitizedTransaction| *tx.message().fee_payer());
+        let payers: Vec<Pubkey> = transactions
+            .iter()
+            .map(|tx| *tx.message().fee_payer())
+            .collect();
+
+        let outputs = pool
+            .run(&mut FifoScheduler::new(), transactions.clone())
+            .unwrap();
+        assert_eq!(outputs, payers);
+        let outputs = pool
+            .run(&mut PriorityGraphScheduler::new(), transactions)
+            .unwrap();
+        assert_eq!(outputs, payers);
+    }
+}
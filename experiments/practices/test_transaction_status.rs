@@ -0,0 +1,213 @@
+//! Unit test: Report transaction status metadata like RPC does
+//!
+//! Analogy: Besides the plate, every ticket comes back from the kitchen with
+//! a slip: what it cost, how the till looked before and after, which
+//! stations were asked for help along the way and any note the last cook
+//! left for the waiter. The slip is filled in the same way as the one the
+//! big restaurant down the road hands out, so the two can be compared.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{
+        bank::Bank,
+        svm::{InvokeContext, TransactionExecutor, TransactionReturnData},
+        transaction_status::TransactionExecutionDetails,
+    };
+    use solana_account::AccountSharedData;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use std::collections::HashMap;
+
+    fn transaction(instructions: &[Instruction], payer: &Pubkey) -> SanitizedTransaction {
+        let message = Message::new(instructions, Some(payer));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message))
+    }
+
+    fn key_index(transaction: &SanitizedTransaction, pubkey: &Pubkey) -> u8 {
+        transaction
+            .message()
+            .account_keys()
+            .iter()
+            .position(|key| key == pubkey)
+            .unwrap() as u8
+    }
+
+    // Invokes the program of its last account with its own instruction data
+    // and every other account, or fails if the data is `[0xff]`.
+    fn forward(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+        if invoke_context.instruction_data() == [0xff] {
+            return Err(InstructionError::Custom(1));
+        }
+        let last = invoke_context.get_number_of_accounts() - 1;
+        let callee = *invoke_context.get_key(last)?;
+        let accounts = (0..last)
+            .map(|index| {
+                let pubkey = *invoke_context.get_key(index)?;
+                Ok(AccountMeta::new(pubkey, false))
+            })
+            .collect::<Result<_, InstructionError>>()?;
+        let data = invoke_context.instruction_data().to_vec();
+        invoke_context.invoke(&Instruction::new_with_bytes(callee, &data, accounts))
+    }
+
+    #[test]
+    fn test_inner_instructions_record_invocations() {
+        let (caller, callee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let target = Pubkey::new_unique();
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(caller, forward);
+        executor.add_program(callee, |_: &mut InvokeContext| Ok(()));
+        let mut store = HashMap::new();
+        store.insert(target, AccountSharedData::new(1, 0, &callee));
+
+        let invoking = Instruction::new_with_bytes(
+            caller,
+            &[7],
+            vec![
+                AccountMeta::new(target, false),
+                AccountMeta::new_readonly(callee, false),
+            ],
+        );
+        let direct = Instruction::new_with_bytes(callee, &[], vec![]);
+        let tx = transaction(&[invoking, direct], &Pubkey::new_unique());
+        let result = executor.load_and_execute_transaction(&store, &tx);
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.inner_instructions.len(), 2);
+        assert!(result.inner_instructions[1].is_empty());
+
+        // Only the instruction that invoked anything is reported.
+        let details = TransactionExecutionDetails::new(&tx, &result);
+        assert_eq!(details.inner_instructions.len(), 1);
+        assert_eq!(details.inner_instructions[0].index, 0);
+        let inner = &details.inner_instructions[0].instructions[0];
+        assert_eq!(inner.stack_height, 2);
+        assert_eq!(inner.instruction.program_id_index, key_index(&tx, &callee));
+        assert_eq!(inner.instruction.accounts, vec![key_index(&tx, &target)]);
+        assert_eq!(inner.instruction.data, vec![7]);
+    }
+
+    #[test]
+    fn test_return_data() {
+        let (setter, reader) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut executor = TransactionExecutor::new();
+        executor.add_program(setter, |invoke_context: &mut InvokeContext| {
+            let data = invoke_context.instruction_data().to_vec();
+            invoke_context.set_return_data(data)
+        });
+        // Fails unless the setter left `[1, 2, 3]`.
+        executor.add_program(
+            reader,
+            move |invoke_context: &mut InvokeContext| match invoke_context.get_return_data() {
+                (program_id, [1, 2, 3]) if *program_id == setter => Ok(()),
+                _ => Err(InstructionError::Custom(1)),
+            },
+        );
+        let store = HashMap::new();
+        let payer = Pubkey::new_unique();
+
+        let set = Instruction::new_with_bytes(setter, &[1, 2, 3], vec![]);
+        let read = Instruction::new_with_bytes(reader, &[], vec![]);
+        let result =
+            executor.load_and_execute_transaction(&store, &transaction(&[set, read], &payer));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(
+            result.return_data,
+            Some(TransactionReturnData {
+                program_id: setter,
+                data: vec![1, 2, 3],
+            })
+        );
+
+        // Setting empty data clears it.
+        let set = Instruction::new_with_bytes(setter, &[1, 2, 3], vec![]);
+        let clear = Instruction::new_with_bytes(setter, &[], vec![]);
+        let result =
+            executor.load_and_execute_transaction(&store, &transaction(&[set, clear], &payer));
+        assert_eq!(result.status, Ok(()));
+        assert_eq!(result.return_data, None);
+    }
+
+    #[test]
+    fn test_rpc_json() {
+        let (caller, callee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut bank = Bank::default();
+        bank.add_builtin(caller, forward);
+        bank.add_builtin(callee, |invoke_context: &mut InvokeContext| {
+            invoke_context.set_return_data(b"done".to_vec())
+        });
+        let payer = Pubkey::new_unique();
+        bank.store_account(
+            payer,
+            AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+        );
+
+        let execute = |bank: &mut Bank, data: &[u8]| {
+            let instruction = Instruction::new_with_bytes(
+                caller,
+                data,
+                vec![AccountMeta::new_readonly(callee, false)],
+            );
+            let message =
+                Message::new_with_blockhash(&[instruction], Some(&payer), &bank.last_blockhash());
+            let tx = SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(
+                message,
+            ));
+            let result = bank
+                .process_transaction_batch(std::slice::from_ref(&tx))
+                .remove(0)
+                .unwrap();
+            TransactionExecutionDetails::new(&tx, &result).to_rpc_json()
+        };
+
+        assert_eq!(
+            execute(&mut bank, &[9]),
+            serde_json::json!({
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5_000,
+                "preBalances": [1_000_000_000u64, 0, 0],
+                "postBalances": [999_995_000u64, 0, 0],
+                "innerInstructions": [{
+                    "index": 0,
+                    "instructions": [{
+                        "programIdIndex": 2,
+                        "accounts": [],
+                        "data": bs58::encode([9]).into_string(),
+                        "stackHeight": 2,
+                    }],
+                }],
+                "logMessages": [
+                    format!("Program {caller} invoke [1]"),
+                    format!("Program {callee} invoke [2]"),
+                    format!("Program {callee} success"),
+                    format!("Program {caller} success"),
+                ],
+                "preTokenBalances": [],
+                "postTokenBalances": [],
+                "rewards": [],
+                "loadedAddresses": { "writable": [], "readonly": [] },
+                "returnData": {
+                    "programId": callee.to_string(),
+                    "data": ["ZG9uZQ==", "base64"],
+                },
+                "computeUnitsConsumed": 0,
+            })
+        );
+
+        // A failed transaction still pays its fee.
+        let failed = execute(&mut bank, &[0xff]);
+        let err = serde_json::json!({ "InstructionError": [0, { "Custom": 1 }] });
+        assert_eq!(failed["err"], err);
+        assert_eq!(failed["status"], serde_json::json!({ "Err": err }));
+        assert_eq!(failed["fee"], 5_000);
+        assert_eq!(
+            failed["postBalances"],
+            serde_json::json!([999_990_000u64, 0, 0])
+        );
+        assert_eq!(failed["returnData"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,334 @@
+//! Unit test: Record validator votes with the native Vote program
+//!
+//! Analogy: Every station keeps a tally card for the head chef. Each time
+//! a station confirms a dish it adds it to the card, and a dish confirmed
+//! often enough is final and earns the station points, more the sooner it
+//! was confirmed. Only the station's own runner may write on the card, and
+//! only its owner may change the station's cut of the tips or empty its
+//! tip jar.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::{bank::Bank, vote_program};
+    use solana_account::{AccountSharedData, ReadableAccount, WritableAccount};
+    use solana_clock::Slot;
+    use solana_epoch_schedule::EpochSchedule;
+    use solana_hash::Hash;
+    use solana_instruction::Instruction;
+    use solana_instruction_error::InstructionError;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+    use solana_sdk_ids::system_program;
+    use solana_transaction::{sanitized::SanitizedTransaction, Transaction};
+    use solana_transaction_error::TransactionError;
+    use solana_vote_interface::{
+        error::VoteError,
+        instruction::{self as vote_instruction, CreateVoteAccountConfig},
+        state::{Lockout, TowerSync, Vote, VoteInit, VoteStateV3, VoteStateVersions},
+    };
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    /// A bank with 32-slot epochs where `payer` is funded.
+    fn vote_bank(payer: &Pubkey) -> Bank {
+        let mut bank = Bank::default();
+        bank.set_epoch_schedule(EpochSchedule::custom(32, 32, false));
+        bank.store_account(
+            *payer,
+            AccountSharedData::new(LAMPORTS, 0, &system_program::id()),
+        );
+        bank
+    }
+
+    /// Stores a vote account voted and withdrawn from by `authority`.
+    fn store_vote_account(bank: &mut Bank, authority: &Pubkey) -> Pubkey {
+        let vote_init = VoteInit {
+            node_pubkey: *authority,
+            authorized_voter: *authority,
+            authorized_withdrawer: *authority,
+            commission: 10,
+        };
+        let account =
+            vote_program::vote_account(&vote_init, bank.sysvar_cache().get_clock(), bank.rent());
+        let vote_pubkey = Pubkey::new_unique();
+        bank.store_account(vote_pubkey, account);
+        vote_pubkey
+    }
+
+    // Processes a single transaction and returns its execution status.
+    fn process(
+        bank: &mut Bank,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<(), TransactionError> {
+        let message =
+            Message::new_with_blockhash(instructions, Some(payer), &bank.last_blockhash());
+        let tx =
+            SanitizedTransaction::from_transaction_for_tests(Transaction::new_unsigned(message));
+        let mut results = bank.process_transaction_batch(&[tx]);
+        results.remove(0).unwrap().status
+    }
+
+    fn vote_error(err: VoteError) -> Result<(), TransactionError> {
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(err as u32),
+        ))
+    }
+
+    fn vote_state(bank: &Bank, vote_pubkey: &Pubkey) -> VoteStateV3 {
+        VoteStateV3::deserialize(bank.get_account(vote_pubkey).unwrap().data()).unwrap()
+    }
+
+    fn slot_hash(bank: &Bank, slot: Slot) -> Hash {
+        *bank.slot_hashes().get(&slot).unwrap()
+    }
+
+    #[test]
+    fn test_create_and_initialize_vote_account() {
+        let (payer, vote_pubkey, node, withdrawer) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut bank = vote_bank(&payer);
+        let vote_init = VoteInit {
+            node_pubkey: node,
+            authorized_voter: node,
+            authorized_withdrawer: withdrawer,
+            commission: 10,
+        };
+        let lamports = bank.rent().minimum_balance(VoteStateV3::size_of());
+        let instructions = vote_instruction::create_account_with_config(
+            &payer,
+            &vote_pubkey,
+            &vote_init,
+            lamports,
+            CreateVoteAccountConfig::default(),
+        );
+        assert_eq!(process(&mut bank, &payer, &instructions), Ok(()));
+        let state = vote_state(&bank, &vote_pubkey);
+        assert_eq!(state.node_pubkey, node);
+        assert_eq!(state.authorized_withdrawer, withdrawer);
+        assert_eq!(state.authorized_voters.get_authorized_voter(0), Some(node));
+        assert_eq!(state.commission, 10);
+        // Seeded accounts hold the same state.
+        let seeded =
+            vote_program::vote_account(&vote_init, bank.sysvar_cache().get_clock(), bank.rent());
+        assert_eq!(
+            seeded.data(),
+            bank.get_account(&vote_pubkey).unwrap().data()
+        );
+
+        assert_eq!(
+            process(&mut bank, &payer, &instructions[1..]),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::AccountAlreadyInitialized
+            ))
+        );
+
+        // A new identity has to sign along with the withdrawer.
+        let new_node = Pubkey::new_unique();
+        let update =
+            vote_instruction::update_validator_identity(&vote_pubkey, &withdrawer, &new_node);
+        let mut unsigned = update.clone();
+        unsigned.accounts[1].is_signer = false;
+        assert_eq!(
+            process(&mut bank, &payer, &[unsigned]),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::MissingRequiredSignature
+            ))
+        );
+        assert_eq!(process(&mut bank, &payer, &[update]), Ok(()));
+        assert_eq!(vote_state(&bank, &vote_pubkey).node_pubkey, new_node);
+    }
+
+    #[test]
+    fn test_votes_root_slots_and_earn_timely_credits() {
+        let voter = Pubkey::new_unique();
+        let mut bank = vote_bank(&voter);
+        let vote_pubkey = store_vote_account(&mut bank, &voter);
+        let cast = |bank: &mut Bank, slot: Slot, hash: Hash| {
+            let vote = Vote::new(vec![slot], hash);
+            process(
+                bank,
+                &voter,
+                &[vote_instruction::vote(&vote_pubkey, &voter, vote)],
+            )
+        };
+
+        // A vote on every slot right after it: the 32nd roots the first.
+        for _ in 0..32 {
+            bank.advance_slot(Hash::new_unique());
+            let slot = bank.slot() - 1;
+            let hash = slot_hash(&bank, slot);
+            assert_eq!(cast(&mut bank, slot, hash), Ok(()));
+        }
+        let state = vote_state(&bank, &vote_pubkey);
+        assert_eq!(state.root_slot, Some(0));
+        assert_eq!(state.votes.len(), 31);
+        assert!(state.votes.iter().all(|vote| vote.latency == 1));
+        assert_eq!(state.credits(), 16);
+
+        let last = bank.slot() - 1;
+        let hash = slot_hash(&bank, last - 1);
+        assert_eq!(
+            cast(&mut bank, last - 1, hash),
+            vote_error(VoteError::VoteTooOld)
+        );
+        bank.advance_slot(Hash::new_unique());
+        assert_eq!(
+            cast(&mut bank, last + 1, Hash::new_unique()),
+            vote_error(VoteError::SlotHashMismatch)
+        );
+        // Only the authorized voter may vote.
+        let impostor = Pubkey::new_unique();
+        let vote = Vote::new(vec![last + 1], slot_hash(&bank, last + 1));
+        assert_eq!(
+            process(
+                &mut bank,
+                &voter,
+                &[vote_instruction::vote(&vote_pubkey, &impostor, vote)]
+            ),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::MissingRequiredSignature
+            ))
+        );
+    }
+
+    #[test]
+    fn test_tower_sync_credits_late_votes_less() {
+        let voter = Pubkey::new_unique();
+        let mut bank = vote_bank(&voter);
+        let vote_pubkey = store_vote_account(&mut bank, &voter);
+        let sync = |bank: &mut Bank, tower_sync: TowerSync| {
+            process(
+                bank,
+                &voter,
+                &[vote_instruction::tower_sync(
+                    &vote_pubkey,
+                    &voter,
+                    tower_sync,
+                )],
+            )
+        };
+        while bank.slot() < 10 {
+            bank.advance_slot(Hash::new_unique());
+        }
+        let tower = TowerSync::new_from_slots((1..=9).collect(), slot_hash(&bank, 9), None);
+        assert_eq!(sync(&mut bank, tower), Ok(()));
+        let latencies: Vec<u8> = vote_state(&bank, &vote_pubkey)
+            .votes
+            .iter()
+            .map(|vote| vote.latency)
+            .collect();
+        assert_eq!(latencies, vec![9, 8, 7, 6, 5, 4, 3, 2, 1]);
+
+        // Rooting slots 1 to 5 earns 16 credits less the slots each vote
+        // landed past the grace period: 9 + 10 + 11 + 12 + 13.
+        bank.advance_slot(Hash::new_unique());
+        let tower = TowerSync::new_from_slots((6..=10).collect(), slot_hash(&bank, 10), Some(5));
+        assert_eq!(sync(&mut bank, tower), Ok(()));
+        let state = vote_state(&bank, &vote_pubkey);
+        assert_eq!(state.root_slot, Some(5));
+        assert_eq!(state.credits(), 55);
+        assert_eq!(state.votes[0].latency, 4);
+        assert_eq!(state.votes[4].latency, 1);
+
+        let hash = slot_hash(&bank, 10);
+        bank.advance_slot(Hash::new_unique());
+        let hash_11 = slot_hash(&bank, 11);
+        assert_eq!(
+            sync(
+                &mut bank,
+                TowerSync::new_from_slots(vec![11], hash_11, None)
+            ),
+            vote_error(VoteError::RootRollBack)
+        );
+        assert_eq!(
+            sync(
+                &mut bank,
+                TowerSync::new_from_slots(vec![11], hash, Some(5))
+            ),
+            vote_error(VoteError::SlotHashMismatch)
+        );
+        let lockouts = [(10, 1), (11, 2)]
+            .map(|(slot, confirmations)| Lockout::new_with_confirmation_count(slot, confirmations));
+        let unordered = TowerSync::new(lockouts.into(), Some(5), hash_11, Hash::default());
+        assert_eq!(
+            sync(&mut bank, unordered),
+            vote_error(VoteError::ConfirmationsNotOrdered)
+        );
+    }
+
+    #[test]
+    fn test_commission_and_withdrawals() {
+        let withdrawer = Pubkey::new_unique();
+        let mut bank = vote_bank(&withdrawer);
+        let vote_pubkey = store_vote_account(&mut bank, &withdrawer);
+        let commission = |bank: &mut Bank, commission: u8| {
+            let update = vote_instruction::update_commission(&vote_pubkey, &withdrawer, commission);
+            process(bank, &withdrawer, &[update])
+        };
+
+        // Raising the commission is only allowed in the first half of an
+        // epoch, lowering it always.
+        assert_eq!(commission(&mut bank, 20), Ok(()));
+        while bank.slot() < 17 {
+            bank.advance_slot(Hash::new_unique());
+        }
+        assert_eq!(
+            commission(&mut bank, 30),
+            vote_error(VoteError::CommissionUpdateTooLate)
+        );
+        assert_eq!(commission(&mut bank, 5), Ok(()));
+        assert_eq!(vote_state(&bank, &vote_pubkey).commission, 5);
+
+        // Earned credits in epoch 0.
+        let mut account = bank.get_account(&vote_pubkey).unwrap();
+        let mut state = vote_state(&bank, &vote_pubkey);
+        state.epoch_credits.push((0, 16, 0));
+        VoteStateV3::serialize(
+            &VoteStateVersions::new_v3(state),
+            account.data_as_mut_slice(),
+        )
+        .unwrap();
+        account.checked_add_lamports(1_000).unwrap();
+        let lamports = account.lamports();
+        bank.store_account(vote_pubkey, account);
+        let withdraw = |bank: &mut Bank, amount: u64| {
+            let withdraw =
+                vote_instruction::withdraw(&vote_pubkey, &withdrawer, amount, &withdrawer);
+            process(bank, &withdrawer, &[withdraw])
+        };
+
+        // Withdrawals keep the account rent exempt...
+        assert_eq!(
+            withdraw(&mut bank, 1_001),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::InsufficientFunds
+            ))
+        );
+        assert_eq!(withdraw(&mut bank, 1_000), Ok(()));
+        // ...or close it, once it earned nothing for a full epoch.
+        let remaining = lamports - 1_000;
+        assert_eq!(
+            withdraw(&mut bank, remaining),
+            vote_error(VoteError::ActiveVoteAccountClose)
+        );
+        while bank.epoch() < 2 {
+            bank.advance_slot(Hash::new_unique());
+        }
+        let before = bank.get_account(&withdrawer).unwrap().lamports();
+        assert_eq!(withdraw(&mut bank, remaining), Ok(()));
+        assert!(bank
+            .get_account(&vote_pubkey)
+            .is_none_or(|account| account.lamports() == 0));
+        assert!(bank.get_account(&withdrawer).unwrap().lamports() > before);
+    }
+}
@@ -0,0 +1,134 @@
+//! Unit test: Generate synthetic transaction traffic
+//!
+//! Analogy: To rehearse a busy night, the manager writes a script of fake
+//! orders: so many regulars, this share of them all wanting the window
+//! table, tips spread evenly or with a few big spenders. The same script
+//! can be replayed every rehearsal, so changes to the kitchen can be
+//! compared fairly.
+
+#[cfg(test)]
+mod tests {
+    use priority_graph_practice::bank::Bank;
+    use priority_graph_practice::compute_budget::process_compute_budget_instructions;
+    use priority_graph_practice::scheduler::{PriorityGraphScheduler, Scheduler};
+    use priority_graph_practice::workload::{PriorityDistribution, Workload, WorkloadConfig};
+    use solana_account::ReadableAccount;
+
+    fn cu_prices(workload: &Workload) -> Vec<u64> {
+        workload
+            .transactions
+            .iter()
+            .map(|transaction| {
+                process_compute_budget_instructions(transaction.message())
+                    .unwrap()
+                    .compute_unit_price
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_is_deterministic() {
+        let config = WorkloadConfig {
+            num_transactions: 50,
+            num_payers: 5,
+            transfers_per_transaction: 3,
+            ..WorkloadConfig::default()
+        };
+        let workload = Workload::generate(&config);
+        assert_eq!(workload.payers.len(), 5);
+        assert_eq!(workload.transactions.len(), 50);
+        for transaction in &workload.transactions {
+            assert!(workload.payers.contains(transaction.message().fee_payer()));
+            // The compute unit price, then the transfers.
+            assert_eq!(transaction.message().instructions().len(), 4);
+        }
+
+        let again = Workload::generate(&config);
+        assert_eq!(again.payers, workload.payers);
+        assert_eq!(again.transactions, workload.transactions);
+        let reseeded = Workload::generate(&WorkloadConfig { seed: 1, ..config });
+        assert_ne!(reseeded.payers, workload.payers);
+    }
+
+    #[test]
+    fn test_hot_account_contention() {
+        let config = |hot_account_ratio| WorkloadConfig {
+            num_transactions: 40,
+            num_payers: 10_000,
+            hot_account_ratio,
+            ..WorkloadConfig::default()
+        };
+        let touches_hot = |workload: &Workload| {
+            workload
+                .transactions
+                .iter()
+                .filter(|transaction| {
+                    transaction
+                        .message()
+                        .account_keys()
+                        .iter()
+                        .any(|key| workload.hot_accounts.contains(key))
+                })
+                .count()
+        };
+
+        let cold = Workload::generate(&config(0.0));
+        assert_eq!(touches_hot(&cold), 0);
+        let mixed = Workload::generate(&config(0.5));
+        assert!((1..40).contains(&touches_hot(&mixed)));
+        let hot = Workload::generate(&config(1.0));
+        assert_eq!(touches_hot(&hot), 40);
+
+        // Everything writes the single hot account, so nothing runs in
+        // parallel; without it, almost everything does.
+        let mut scheduler = PriorityGraphScheduler::new();
+        assert_eq!(scheduler.schedule(&hot.transactions).len(), 40);
+        assert!(scheduler.schedule(&cold.transactions).len() <= 2);
+    }
+
+    #[test]
+    fn test_priority_distributions_and_execution() {
+        let uniform = Workload::generate(&WorkloadConfig {
+            num_transactions: 200,
+            priority_distribution: PriorityDistribution::Uniform {
+                min_cu_price: 10,
+                max_cu_price: 20,
+            },
+            ..WorkloadConfig::default()
+        });
+        assert!(cu_prices(&uniform)
+            .iter()
+            .all(|price| (10..=20).contains(price)));
+
+        let zipf = Workload::generate(&WorkloadConfig {
+            num_transactions: 200,
+            priority_distribution: PriorityDistribution::Zipf {
+                levels: 10,
+                exponent: 1.5,
+                cu_price_step: 100,
+            },
+            ..WorkloadConfig::default()
+        });
+        let prices = cu_prices(&zipf);
+        assert!(prices
+            .iter()
+            .all(|price| price % 100 == 0 && (100..=1_000).contains(price)));
+        let count = |price| prices.iter().filter(|&&p| p == price).count();
+        assert!(count(100) > count(200) && count(200) > count(1_000));
+
+        // Funded payers can execute the whole workload.
+        let mut bank = Bank::default();
+        for (pubkey, account) in uniform.payer_accounts(1_000_000_000) {
+            bank.store_account(pubkey, account);
+        }
+        let results = bank.process_transaction_batch(&uniform.transactions);
+        assert!(results
+            .iter()
+            .all(|result| result.as_ref().unwrap().was_successful()));
+        let recipient = uniform.transactions[0].message().account_keys()[1];
+        assert_eq!(
+            bank.get_account(&recipient).unwrap().lamports(),
+            Workload::transfer_lamports()
+        );
+    }
+}